@@ -0,0 +1,112 @@
+//! ALTO pages as fragments, one per text line, as `chonker export` and most
+//! OCR tools write them.
+
+use crate::fragment::{group, Fragment, Page};
+use quick_xml::events::{BytesStart, Event};
+use std::collections::HashMap;
+
+/// Used when a `String` has neither a style with a size nor a height.
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+fn attr(element: &BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.local_name().as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|value| value.into_owned()))
+}
+
+fn number(element: &BytesStart, name: &[u8]) -> Option<f32> {
+    attr(element, name).and_then(|value| value.parse().ok())
+}
+
+/// Reads every `Page`. The words of each `TextLine` are merged into one
+/// fragment; font sizes come from the `TextStyle` a word refers to, else its
+/// height.
+pub fn parse_alto(xml: &str) -> anyhow::Result<Vec<Page>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut styles: HashMap<String, f32> = HashMap::new();
+    let mut pages: Vec<Page> = Vec::new();
+    let mut line: Option<Vec<Fragment>> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"TextStyle" => {
+                    if let (Some(id), Some(size)) = (attr(&e, b"ID"), number(&e, b"FONTSIZE")) {
+                        styles.insert(id, size);
+                    }
+                }
+                b"Page" => pages.push(Page {
+                    width: number(&e, b"WIDTH").unwrap_or(0.0),
+                    height: number(&e, b"HEIGHT").unwrap_or(0.0),
+                    cell: None,
+                    fragments: Vec::new(),
+                }),
+                b"TextLine" => line = Some(Vec::new()),
+                b"String" => {
+                    let (Some(words), Some(text)) = (line.as_mut(), attr(&e, b"CONTENT")) else { continue };
+                    let height = number(&e, b"HEIGHT").unwrap_or(0.0);
+                    let styled = attr(&e, b"STYLEREFS")
+                        .and_then(|refs| refs.split_whitespace().find_map(|id| styles.get(id).copied()));
+                    let font_size = styled.unwrap_or(if height > 0.0 { height } else { DEFAULT_FONT_SIZE });
+                    words.push(Fragment {
+                        text,
+                        x: number(&e, b"HPOS").unwrap_or(0.0),
+                        y: number(&e, b"VPOS").unwrap_or(0.0),
+                        width: number(&e, b"WIDTH").unwrap_or(0.0),
+                        height,
+                        font_size,
+                    });
+                }
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"TextLine" => {
+                if let (Some(page), Some(words)) = (pages.last_mut(), line.take()) {
+                    page.fragments.extend(group(words));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alto_lines_become_fragments() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<alto xmlns="http://www.loc.gov/standards/alto/ns-v3#">
+<Styles><TextStyle ID="font0" FONTSIZE="9.5"/></Styles>
+<Layout>
+<Page ID="Page1" WIDTH="612.0000" HEIGHT="792.0000">
+<PrintSpace>
+<TextBlock ID="p1_b1">
+<TextLine ID="p1_t1" HPOS="160.7871" VPOS="84.8248">
+<String CONTENT="CITY" HPOS="160.7871" VPOS="84.8248" WIDTH="26.3735" HEIGHT="10.6338" STYLEREFS="font0"/>
+<SP/>
+<String CONTENT="CASH" HPOS="189.8127" VPOS="84.8248" WIDTH="29.3485" HEIGHT="10.6338" STYLEREFS="font0"/>
+</TextLine>
+<TextLine ID="p1_t2">
+<String CONTENT="A&amp;B" HPOS="160" VPOS="100" WIDTH="20" HEIGHT="11"/>
+</TextLine>
+</TextBlock>
+</PrintSpace>
+</Page>
+</Layout>
+</alto>"#;
+        let pages = parse_alto(xml).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!((pages[0].width, pages[0].height), (612.0, 792.0));
+        let fragments = &pages[0].fragments;
+        assert_eq!(fragments.len(), 2);
+        assert_eq!(fragments[0].text, "CITY CASH");
+        assert_eq!(fragments[0].font_size, 9.5);
+        assert!((fragments[0].width - (189.8127 + 29.3485 - 160.7871)).abs() < 1e-3);
+        assert_eq!((fragments[1].text.as_str(), fragments[1].font_size), ("A&B", 11.0));
+    }
+}
@@ -0,0 +1,235 @@
+//! Fragments: pieces of text placed freely on the page, independent of the
+//! character grid.
+
+use bevy::prelude::Component;
+use chonker_core::{CharBBox, CharacterMatrix, TextRegion};
+
+/// Bevy lays text out at this multiple of the font size; matrix rows are
+/// sized so grouped lines keep their row spacing.
+const LINE_HEIGHT: f32 = 1.2;
+
+/// A page's size in points and its fragments.
+pub struct Page {
+    pub width: f32,
+    pub height: f32,
+    /// The character cell, when the page came from a matrix.
+    pub cell: Option<(f32, f32)>,
+    pub fragments: Vec<Fragment>,
+}
+
+/// A piece of text and its box, in PDF points measured from the top-left
+/// corner of the page.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Fragment {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub font_size: f32,
+}
+
+impl Fragment {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    /// The first line's baseline, taking the font size as the line height.
+    pub fn baseline(&self) -> f32 {
+        self.y + self.font_size
+    }
+}
+
+/// Merges `fragments` into one box covering all of them. The text reads in
+/// order: lines top to bottom joined by newlines, and the fragments on each
+/// line left to right joined by spaces. `None` when there is nothing to merge.
+pub fn group(mut fragments: Vec<Fragment>) -> Option<Fragment> {
+    fragments.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    let first = fragments.first()?.clone();
+    let mut lines: Vec<Vec<Fragment>> = Vec::new();
+    for fragment in fragments {
+        // A fragment starting within half a line of the line's top joins it
+        match lines.last_mut() {
+            Some(line) if fragment.y < line[0].y + line[0].font_size / 2.0 => line.push(fragment),
+            _ => lines.push(vec![fragment]),
+        }
+    }
+
+    let mut text = Vec::new();
+    let (mut left, mut top, mut right, mut bottom) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for line in &mut lines {
+        line.sort_by(|a, b| a.x.total_cmp(&b.x));
+        text.push(line.iter().map(|fragment| fragment.text.as_str()).collect::<Vec<_>>().join(" "));
+        for fragment in line.iter() {
+            left = left.min(fragment.x);
+            top = top.min(fragment.y);
+            right = right.max(fragment.x + fragment.width);
+            bottom = bottom.max(fragment.y + fragment.height);
+        }
+    }
+    Some(Fragment {
+        text: text.join("\n"),
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+        font_size: first.font_size,
+    })
+}
+
+/// Fragments for a matrix: one per text region, and one per run of text
+/// outside them, runs being split where two or more spaces separate words.
+pub fn matrix_page(char_matrix: &CharacterMatrix) -> Page {
+    let (cw, ch) = (char_matrix.char_width, char_matrix.char_height);
+    let mut regions: Vec<Vec<Fragment>> = vec![Vec::new(); char_matrix.text_regions.len()];
+    let mut fragments = Vec::new();
+    for (row, cells) in char_matrix.rows().iter().enumerate() {
+        for (col, text) in runs(cells) {
+            let fragment = Fragment {
+                x: col as f32 * cw,
+                y: row as f32 * ch,
+                width: text.chars().count() as f32 * cw,
+                height: ch,
+                font_size: ch / LINE_HEIGHT,
+                text,
+            };
+            match char_matrix.text_regions.iter().position(|region| region.bbox.contains(col, row)) {
+                Some(index) => regions[index].push(fragment),
+                None => fragments.push(fragment),
+            }
+        }
+    }
+    fragments.extend(regions.into_iter().filter_map(group));
+    Page {
+        width: char_matrix.width as f32 * cw,
+        height: char_matrix.height as f32 * ch,
+        cell: Some((cw, ch)),
+        fragments,
+    }
+}
+
+/// Lays fragments back out on a grid of `cell`-sized characters, each at the
+/// cell nearest its corner and each becoming a text region. The grid covers
+/// the page, grown to fit fragments dragged past its edges; where fragments
+/// overlap, the later one in reading order wins.
+pub fn fragments_to_matrix(fragments: &[Fragment], width: f32, height: f32, cell: (f32, f32)) -> CharacterMatrix {
+    let (cw, ch) = cell;
+    let mut placed: Vec<(usize, usize, Vec<Vec<char>>, &Fragment)> = fragments
+        .iter()
+        .map(|fragment| {
+            let row = (fragment.y / ch).round().max(0.0) as usize;
+            let col = (fragment.x / cw).round().max(0.0) as usize;
+            (row, col, fragment.text.lines().map(|line| line.chars().collect()).collect(), fragment)
+        })
+        .collect();
+    placed.sort_by_key(|(row, col, ..)| (*row, *col));
+
+    let line_width = |lines: &[Vec<char>]| lines.iter().map(Vec::len).max().unwrap_or(0);
+    let cols = placed.iter().map(|(_, col, lines, _)| col + line_width(lines)).fold((width / cw).ceil() as usize, usize::max);
+    let rows = placed.iter().map(|(row, _, lines, _)| row + lines.len()).fold((height / ch).ceil() as usize, usize::max);
+    let mut char_matrix = CharacterMatrix::new(cols, rows);
+    char_matrix.char_width = cw;
+    char_matrix.char_height = ch;
+    for (region_id, (row, col, lines, fragment)) in placed.into_iter().enumerate() {
+        for (offset, line) in lines.iter().enumerate() {
+            char_matrix.rows_mut()[row + offset][col..col + line.len()].copy_from_slice(line);
+        }
+        char_matrix.text_regions.push(TextRegion {
+            bbox: CharBBox { x: col, y: row, width: line_width(&lines), height: lines.len() },
+            confidence: 1.0,
+            text_content: fragment.text.clone(),
+            region_id,
+            is_italic: false,
+            label: None,
+            tag: None,
+        });
+    }
+    char_matrix
+}
+
+/// Start column and text of each run on a row.
+fn runs(cells: &[char]) -> Vec<(usize, String)> {
+    let mut runs = Vec::new();
+    let mut col = 0;
+    while col < cells.len() {
+        if cells[col].is_whitespace() {
+            col += 1;
+            continue;
+        }
+        let start = col;
+        // Single spaces stay inside the run
+        while col < cells.len() && !(cells[col].is_whitespace() && cells.get(col + 1).is_none_or(|c| c.is_whitespace())) {
+            col += 1;
+        }
+        runs.push((start, cells[start..col].iter().collect()));
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(text: &str, x: f32, y: f32) -> Fragment {
+        Fragment { text: text.to_string(), x, y, width: 10.0 * text.len() as f32, height: 12.0, font_size: 12.0 }
+    }
+
+    #[test]
+    fn test_group_reads_lines_in_order() {
+        let grouped = group(vec![
+            fragment("world", 80.0, 101.0),
+            fragment("again", 20.0, 120.0),
+            fragment("hello", 20.0, 100.0),
+        ])
+        .unwrap();
+        assert_eq!(grouped.text, "hello world\nagain");
+        assert_eq!((grouped.x, grouped.y, grouped.width, grouped.height), (20.0, 100.0, 110.0, 32.0));
+        assert!(grouped.contains(125.0, 110.0));
+        assert!(!grouped.contains(131.0, 110.0));
+        assert_eq!(group(Vec::new()), None);
+    }
+
+    #[test]
+    fn test_matrix_page_splits_runs_and_groups_regions() {
+        use chonker_core::{CharBBox, TextRegion};
+        let mut char_matrix = CharacterMatrix::new(16, 3);
+        *char_matrix.rows_mut() = ["Title   page 2  ", "first line      ", "second          "]
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect();
+        char_matrix.text_regions.push(TextRegion {
+            bbox: CharBBox { x: 0, y: 1, width: 10, height: 2 },
+            confidence: 1.0,
+            text_content: String::new(),
+            region_id: 0,
+            is_italic: false,
+            label: None,
+            tag: None,
+        });
+        let page = matrix_page(&char_matrix);
+
+        assert_eq!((page.width, page.height), (16.0 * 7.2, 36.0));
+        let texts: Vec<&str> = page.fragments.iter().map(|fragment| fragment.text.as_str()).collect();
+        assert_eq!(texts, ["Title", "page 2", "first line\nsecond"]);
+        assert_eq!((page.fragments[1].x, page.fragments[1].y), (8.0 * 7.2, 0.0));
+        assert_eq!((page.fragments[2].y, page.fragments[2].height), (12.0, 24.0));
+
+        // Laid back out unmoved, the text is where it was
+        let cell = page.cell.unwrap();
+        let rebuilt = fragments_to_matrix(&page.fragments, page.width, page.height, cell);
+        assert_eq!(rebuilt.rows(), char_matrix.rows());
+        let bbox = &rebuilt.text_regions[2].bbox;
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (0, 1, 10, 2));
+
+        // A fragment dragged past the right edge grows the grid
+        let mut moved = page.fragments.clone();
+        moved[0].x = 14.6 * 7.2;
+        let rebuilt = fragments_to_matrix(&moved, page.width, page.height, cell);
+        assert_eq!(rebuilt.width, 20);
+        assert_eq!(rebuilt.rows()[0].iter().collect::<String>(), "        page 2 Title");
+    }
+}
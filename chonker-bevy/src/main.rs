@@ -0,0 +1,550 @@
+//! # chonker-bevy: free-form fragment editor
+//!
+//! Spawns the text of a page as `Text2d` entities at their PDF positions, to
+//! be selected, dragged, grouped and deleted freely instead of cell by cell
+//! as in the grid editors. Pages come from the engine (see `source.rs`), so
+//! regions found there arrive as single fragments, and each change is laid
+//! back out on the character grid into chonker-core's `DocumentModel`, which
+//! keeps the undo history.
+
+use anyhow::{bail, Context, Result};
+use bevy::ecs::system::SystemParam;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+use chonker_core::{render_export, CharacterMatrix, ChonkerConfig, DocumentEvent, DocumentModel, ExportFormat};
+use chonker_grid::{set_theme, theme, Color32, ThemeConfig};
+use fragment::{fragments_to_matrix, group, matrix_page, Fragment};
+use snap::Snap;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::Mutex;
+
+mod alto;
+mod fragment;
+mod snap;
+mod source;
+
+const USAGE: &str = "Usage: chonker-bevy <file.pdf|page.json|page.alto.xml> [PAGE]
+
+PDFs are extracted with chonker.toml's backend; JSON and ALTO files are
+chonker exports. Each text region, and each run of text outside them (each
+text line in ALTO), becomes a fragment at its position on the page.
+  click, Shift+click        select a fragment, add or remove one
+  drag                      move the selected fragments
+  S                         snap dragged fragments to the character grid, to
+                            their neighbours' baselines and edges, or not
+  wheel, drag the page      zoom around the pointer, pan (or drag with the
+                            middle or right button)
+  G                         group the selected fragments into one
+  Delete / Backspace        delete the selected fragments
+  Ctrl+Z, Ctrl+Y            undo, redo a move, group or delete
+  Ctrl+A / Esc              select all, clear the selection
+  Ctrl+S, Ctrl+Shift+S      save the page as a matrix (JSON) or as ALTO, laid
+                            out on the character grid, next to the file";
+
+/// Margin around the page when the window first shows it.
+const PAGE_MARGIN: f32 = 1.1;
+/// Zoom per wheel notch, and how far in and out the view goes.
+const ZOOM_STEP: f32 = 1.1;
+const ZOOM_RANGE: (f32, f32) = (0.05, 20.0);
+/// Touchpads scroll in pixels; about this many make a wheel notch.
+const PIXELS_PER_NOTCH: f32 = 100.0;
+
+#[derive(Resource)]
+struct PageSize {
+    width: f32,
+    height: f32,
+}
+
+/// The page's fragments until `setup` spawns them.
+#[derive(Resource)]
+struct PendingFragments(Vec<Fragment>);
+
+/// Marks the fragments the next move, group or delete applies to.
+#[derive(Component)]
+struct Selected;
+
+#[derive(Resource, Default)]
+struct Drag {
+    /// The fragment held, and where on it, while the selection is moved.
+    grab: Option<(Entity, Vec2)>,
+    /// Set once the held selection has moved, so releasing it records an
+    /// edit.
+    moved: bool,
+    /// The pointer's last window position while the view is panned.
+    pan: Option<Vec2>,
+}
+
+#[derive(Resource)]
+struct Snapping {
+    mode: Snap,
+    /// The matrix's character cell, for grid snapping and exports.
+    cell: Vec2,
+}
+
+/// The file and page being edited.
+#[derive(Resource)]
+struct Source {
+    path: PathBuf,
+    page_index: usize,
+}
+
+impl Source {
+    /// `<stem>_p0001.<ext>` next to the source, as `chonker export` names pages.
+    fn export_path(&self, format: ExportFormat) -> PathBuf {
+        let stem = self.path.file_stem().map_or_else(|| "document".to_string(), |s| s.to_string_lossy().to_string());
+        self.path.with_file_name(format!("{}_p{:04}.{}", stem, self.page_index + 1, format.extension()))
+    }
+}
+
+/// The page as the document model holds it, with the model's change
+/// notifications.
+#[derive(Resource)]
+struct Document {
+    model: DocumentModel,
+    events: Mutex<Receiver<DocumentEvent>>,
+}
+
+/// Shown in the window title after the file, page and snapping mode.
+#[derive(Resource)]
+struct Status {
+    title: String,
+    message: String,
+}
+
+fn color(color: Color32) -> Color {
+    Color::srgba_u8(color.r(), color.g(), color.b(), color.a())
+}
+
+/// World space has y up; page positions have y down from the top.
+fn to_world(x: f32, y: f32) -> Vec2 {
+    Vec2::new(x, -y)
+}
+
+fn spawn_fragment(commands: &mut Commands, fragment: Fragment) -> Entity {
+    commands
+        .spawn((
+            Text2d::new(fragment.text.clone()),
+            TextFont { font_size: fragment.font_size, ..default() },
+            TextColor(color(theme().fg)),
+            Anchor::TopLeft,
+            Transform::from_translation(to_world(fragment.x, fragment.y).extend(0.0)),
+            fragment,
+        ))
+        .id()
+}
+
+#[derive(SystemParam)]
+struct Pointer<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl Pointer<'_, '_> {
+    /// Position in the window, if the pointer is over it.
+    fn in_window(&self) -> Option<Vec2> {
+        self.windows.get_single().ok()?.cursor_position()
+    }
+
+    /// Position on the page, if the pointer is over the window.
+    fn on_page(&self) -> Option<Vec2> {
+        let (camera, transform) = self.cameras.get_single().ok()?;
+        let world = camera.viewport_to_world_2d(transform, self.in_window()?).ok()?;
+        Some(Vec2::new(world.x, -world.y))
+    }
+}
+
+/// The document, with what laying fragments out on its grid takes.
+#[derive(SystemParam)]
+struct Editing<'w> {
+    document: ResMut<'w, Document>,
+    source: Res<'w, Source>,
+    page: Res<'w, PageSize>,
+    snapping: Res<'w, Snapping>,
+}
+
+impl Editing<'_> {
+    /// Records the page as `fragments` lie, on the character grid, as one
+    /// undo step.
+    fn commit(&mut self, fragments: &[Fragment]) {
+        let cell = (self.snapping.cell.x, self.snapping.cell.y);
+        let laid_out = fragments_to_matrix(fragments, self.page.width, self.page.height, cell);
+        self.document.model.edit(self.source.page_index, |char_matrix| {
+            char_matrix.width = laid_out.width;
+            char_matrix.height = laid_out.height;
+            *char_matrix.rows_mut() = laid_out.rows().into_owned();
+            char_matrix.text_regions = laid_out.text_regions;
+        });
+    }
+}
+
+fn setup(mut commands: Commands, page: Res<PageSize>, mut pending: ResMut<PendingFragments>) {
+    commands.spawn((
+        Camera2d,
+        OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin { min_width: page.width * PAGE_MARGIN, min_height: page.height * PAGE_MARGIN },
+            ..OrthographicProjection::default_2d()
+        },
+        Transform::from_translation(to_world(page.width / 2.0, page.height / 2.0).extend(0.0)),
+    ));
+    for fragment in pending.0.drain(..) {
+        spawn_fragment(&mut commands, fragment);
+    }
+}
+
+/// Clicking selects the fragment under the pointer (the smallest one, so a
+/// large group doesn't hide what it overlaps) and dragging moves the whole
+/// selection; clicking empty space starts a pan instead. Releasing a moved
+/// selection records the move.
+fn select_and_drag(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    pointer: Pointer,
+    mut editing: Editing,
+    mut drag: ResMut<Drag>,
+    mut fragments: Query<(Entity, &mut Fragment, Has<Selected>)>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        drag.grab = None;
+        if std::mem::take(&mut drag.moved) {
+            let placed: Vec<Fragment> = fragments.iter().map(|(_, fragment, _)| fragment.clone()).collect();
+            editing.commit(&placed);
+        }
+    }
+    let Some(position) = pointer.on_page() else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let hit = fragments
+            .iter()
+            .filter(|(_, fragment, _)| fragment.contains(position.x, position.y))
+            .min_by(|(_, a, _), (_, b, _)| a.area().total_cmp(&b.area()))
+            .map(|(entity, fragment, selected)| (entity, Vec2::new(fragment.x, fragment.y), selected));
+        let clear = !shift && hit.is_none_or(|(_, _, selected)| !selected);
+        if clear {
+            for (entity, _, _) in fragments.iter().filter(|(_, _, selected)| *selected) {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+        drag.grab = None;
+        match hit {
+            Some((entity, _, true)) if shift => {
+                commands.entity(entity).remove::<Selected>();
+            }
+            Some((entity, corner, _)) => {
+                commands.entity(entity).insert(Selected);
+                drag.grab = Some((entity, position - corner));
+            }
+            None => drag.pan = pointer.in_window(),
+        }
+    } else if let Some((entity, offset)) = drag.grab.filter(|_| mouse.pressed(MouseButton::Left)) {
+        let Ok((_, held, _)) = fragments.get(entity) else {
+            return;
+        };
+        // The held fragment snaps and the rest of the selection keeps its
+        // place relative to it
+        let snapping = &editing.snapping;
+        let others: Vec<Fragment> = match snapping.mode {
+            Snap::Baseline => {
+                fragments.iter().filter(|(_, _, selected)| !selected).map(|(_, fragment, _)| fragment.clone()).collect()
+            }
+            _ => Vec::new(),
+        };
+        let to = snapping.mode.apply(held, position - offset, snapping.cell, &others);
+        let delta = to - Vec2::new(held.x, held.y);
+        if delta != Vec2::ZERO {
+            drag.moved = true;
+            for (_, mut fragment, selected) in &mut fragments {
+                if selected {
+                    fragment.x += delta.x;
+                    fragment.y += delta.y;
+                }
+            }
+        }
+    }
+}
+
+/// The wheel zooms around the pointer; dragging empty space, or with the
+/// middle or right button, pans.
+fn zoom_and_pan(
+    mut wheel: EventReader<MouseWheel>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&Camera, &GlobalTransform, &mut Transform, &mut OrthographicProjection)>,
+    mut drag: ResMut<Drag>,
+) {
+    let notches: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum();
+    let (Ok(window), Ok((camera, global, mut transform, mut projection))) = (windows.get_single(), cameras.get_single_mut())
+    else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    if notches != 0.0 {
+        if let Ok(anchor) = camera.viewport_to_world_2d(global, cursor) {
+            // The point under the pointer stays put
+            let scale = (projection.scale * ZOOM_STEP.powf(-notches)).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1);
+            let center = anchor + (transform.translation.truncate() - anchor) * (scale / projection.scale);
+            transform.translation = center.extend(transform.translation.z);
+            projection.scale = scale;
+        }
+    }
+
+    if mouse.any_just_pressed([MouseButton::Middle, MouseButton::Right]) {
+        drag.pan = Some(cursor);
+    }
+    if !mouse.any_pressed([MouseButton::Left, MouseButton::Middle, MouseButton::Right]) {
+        drag.pan = None;
+    }
+    if let Some(last) = drag.pan {
+        if let (Ok(from), Ok(to)) = (camera.viewport_to_world_2d(global, last), camera.viewport_to_world_2d(global, cursor)) {
+            transform.translation += (from - to).extend(0.0);
+        }
+        drag.pan = Some(cursor);
+    }
+}
+
+fn ctrl_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight])
+}
+
+fn toggle_snapping(keys: Res<ButtonInput<KeyCode>>, mut snapping: ResMut<Snapping>) {
+    if keys.just_pressed(KeyCode::KeyS) && !ctrl_pressed(&keys) {
+        snapping.mode = snapping.mode.next();
+    }
+}
+
+/// Ctrl+Z undoes the last move, group or delete; Ctrl+Y or Ctrl+Shift+Z
+/// redoes it.
+fn undo_redo(
+    keys: Res<ButtonInput<KeyCode>>,
+    source: Res<Source>,
+    mut document: ResMut<Document>,
+    mut status: ResMut<Status>,
+) {
+    if !ctrl_pressed(&keys) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let page_index = source.page_index;
+    let (action, done) = if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        ("redo", document.model.redo(page_index))
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        ("undo", document.model.undo(page_index))
+    } else {
+        return;
+    };
+    if !done {
+        status.message = format!("nothing to {}", action);
+    }
+}
+
+/// When undo or redo changes the page, its fragments are spawned again from
+/// the document.
+fn apply_document_events(
+    mut commands: Commands,
+    document: Res<Document>,
+    source: Res<Source>,
+    mut drag: ResMut<Drag>,
+    fragments: Query<Entity, With<Fragment>>,
+) {
+    let restored = DocumentEvent::Restored { page: source.page_index };
+    let changed =
+        document.events.lock().is_ok_and(|events| events.try_iter().filter(|event| *event == restored).count() > 0);
+    let Some(page) = document.model.page(source.page_index).filter(|_| changed) else {
+        return;
+    };
+    for entity in &fragments {
+        commands.entity(entity).despawn_recursive();
+    }
+    drag.grab = None;
+    drag.moved = false;
+    for fragment in matrix_page(&page.matrix).fragments {
+        spawn_fragment(&mut commands, fragment);
+    }
+}
+
+/// Ctrl+S writes the page, as the document holds it, as a matrix (JSON) and
+/// Ctrl+Shift+S as ALTO, both next to the source.
+fn export_page(keys: Res<ButtonInput<KeyCode>>, source: Res<Source>, document: Res<Document>, mut status: ResMut<Status>) {
+    if !(keys.just_pressed(KeyCode::KeyS) && ctrl_pressed(&keys)) {
+        return;
+    }
+    let Some(page) = document.model.page(source.page_index) else {
+        return;
+    };
+    let format = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) { ExportFormat::Alto } else { ExportFormat::Json };
+    let path = source.export_path(format);
+    let written = render_export(&page.matrix, source.page_index, format).and_then(|content| Ok(std::fs::write(&path, content)?));
+    status.message = match written {
+        Ok(()) => format!("saved {}", path.display()),
+        Err(e) => format!("could not save {}: {}", path.display(), e),
+    };
+}
+
+fn update_title(snapping: Res<Snapping>, status: Res<Status>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    if !snapping.is_changed() && !status.is_changed() {
+        return;
+    }
+    if let Ok(mut window) = windows.get_single_mut() {
+        window.title = format!("{} · snap: {}", status.title, snapping.mode.label());
+        if !status.message.is_empty() {
+            window.title.push_str(&format!(" · {}", status.message));
+        }
+    }
+}
+
+/// Deleting and grouping are recorded in the document as they happen.
+fn edit_selection(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editing: Editing,
+    fragments: Query<(Entity, &Fragment, Has<Selected>)>,
+) {
+    let ctrl = ctrl_pressed(&keys);
+    let selected: Vec<(Entity, &Fragment)> =
+        fragments.iter().filter(|(_, _, selected)| *selected).map(|(entity, fragment, _)| (entity, fragment)).collect();
+    let mut kept: Vec<Fragment> =
+        fragments.iter().filter(|(_, _, selected)| !selected).map(|(_, fragment, _)| fragment.clone()).collect();
+
+    if ctrl && keys.just_pressed(KeyCode::KeyA) {
+        for (entity, _, _) in &fragments {
+            commands.entity(entity).insert(Selected);
+        }
+    } else if keys.just_pressed(KeyCode::Escape) {
+        for (entity, _) in selected {
+            commands.entity(entity).remove::<Selected>();
+        }
+    } else if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) && !selected.is_empty() {
+        for (entity, _) in selected {
+            commands.entity(entity).despawn_recursive();
+        }
+        editing.commit(&kept);
+    } else if keys.just_pressed(KeyCode::KeyG) && selected.len() > 1 {
+        let Some(grouped) = group(selected.iter().map(|(_, fragment)| (*fragment).clone()).collect()) else {
+            return;
+        };
+        for (entity, _) in selected {
+            commands.entity(entity).despawn_recursive();
+        }
+        kept.push(grouped.clone());
+        editing.commit(&kept);
+        let entity = spawn_fragment(&mut commands, grouped);
+        commands.entity(entity).insert(Selected);
+    }
+}
+
+/// Moved fragments follow their page position.
+fn sync_fragments(mut fragments: Query<(&Fragment, &mut Transform), Changed<Fragment>>) {
+    for (fragment, mut transform) in &mut fragments {
+        transform.translation = to_world(fragment.x, fragment.y).extend(transform.translation.z);
+    }
+}
+
+fn draw_outlines(
+    mut gizmos: Gizmos,
+    page: Res<PageSize>,
+    snapping: Res<Snapping>,
+    fragments: Query<(&Fragment, Has<Selected>)>,
+) {
+    let t = theme();
+    if snapping.mode == Snap::Grid {
+        let cells = (Vec2::new(page.width, page.height) / snapping.cell).ceil();
+        let center = to_world(cells.x * snapping.cell.x / 2.0, cells.y * snapping.cell.y / 2.0);
+        let line = color(t.dim).with_alpha(0.15);
+        gizmos.grid_2d(Isometry2d::from_translation(center), cells.as_uvec2(), snapping.cell, line);
+    }
+    let outline = |gizmos: &mut Gizmos, x: f32, y: f32, width: f32, height: f32, line: Color| {
+        let center = to_world(x + width / 2.0, y + height / 2.0);
+        gizmos.rect_2d(Isometry2d::from_translation(center), Vec2::new(width, height), line);
+    };
+    outline(&mut gizmos, 0.0, 0.0, page.width, page.height, color(t.chrome));
+    for (fragment, selected) in &fragments {
+        let line = color(if selected { t.accent } else { t.dim });
+        outline(&mut gizmos, fragment.x, fragment.y, fragment.width, fragment.height, line);
+    }
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = match args.next() {
+        None => bail!("{}", USAGE),
+        Some(arg) if arg == "-h" || arg == "--help" => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Some(path) => path,
+    };
+    let page_number = match args.next() {
+        Some(page) => page.parse::<usize>().with_context(|| format!("Not a page number: {}", page))?,
+        None => 1,
+    };
+
+    let Some(page_index) = page_number.checked_sub(1) else {
+        bail!("Pages start at 1");
+    };
+
+    let config = ChonkerConfig::load();
+    set_theme(ThemeConfig::load_shared(&config).resolve());
+    let path = PathBuf::from(path);
+    let default_cell = match config.char_size {
+        Some(size) => (size.char_width, size.char_height),
+        None => {
+            let matrix = CharacterMatrix::new(0, 0);
+            (matrix.char_width, matrix.char_height)
+        }
+    };
+    let (page, char_matrix) = source::load_page(&path, page_index, config.backend, default_cell)?;
+    // Snapping follows the page's own cells where it has them
+    let cell = Vec2::from(page.cell.unwrap_or(default_cell));
+    let mut model = DocumentModel::new(path.clone());
+    let events = Mutex::new(model.subscribe());
+    model.insert_page(page_index, char_matrix);
+    let title = format!("Chonker · {} · page {}", path.display(), page_number);
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window { title: title.clone(), ..default() }),
+            ..default()
+        }))
+        .insert_resource(ClearColor(color(theme().bg)))
+        .insert_resource(PageSize { width: page.width, height: page.height })
+        .insert_resource(PendingFragments(page.fragments))
+        .insert_resource(Snapping { mode: Snap::Off, cell })
+        .insert_resource(Source { path, page_index })
+        .insert_resource(Document { model, events })
+        .insert_resource(Status { title, message: String::new() })
+        .init_resource::<Drag>()
+        .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                select_and_drag,
+                zoom_and_pan,
+                toggle_snapping,
+                edit_selection,
+                undo_redo,
+                apply_document_events,
+                export_page,
+                sync_fragments,
+                update_title,
+                draw_outlines,
+            )
+                .chain(),
+        )
+        .run();
+    Ok(())
+}
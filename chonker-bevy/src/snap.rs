@@ -0,0 +1,75 @@
+//! Where a dragged fragment comes to rest, so rearranged text stays aligned.
+
+use crate::fragment::Fragment;
+use bevy::math::Vec2;
+
+/// How far, in points, a neighbour's baseline or left edge pulls a fragment.
+pub const SNAP_DISTANCE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Snap {
+    #[default]
+    Off,
+    /// Corners on the character cells the matrix is laid out in.
+    Grid,
+    /// Baselines and left edges onto those of nearby fragments.
+    Baseline,
+}
+
+impl Snap {
+    pub fn next(self) -> Self {
+        match self {
+            Snap::Off => Snap::Grid,
+            Snap::Grid => Snap::Baseline,
+            Snap::Baseline => Snap::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Snap::Off => "off",
+            Snap::Grid => "grid",
+            Snap::Baseline => "baseline",
+        }
+    }
+
+    /// Where `fragment` lands when dropped with its corner at `to`. `cell`
+    /// is the grid's cell size and `others` the fragments to align with.
+    pub fn apply(self, fragment: &Fragment, to: Vec2, cell: Vec2, others: &[Fragment]) -> Vec2 {
+        match self {
+            Snap::Off => to,
+            Snap::Grid => (to / cell).round() * cell,
+            Snap::Baseline => {
+                let nearest = |offsets: &mut dyn Iterator<Item = f32>| {
+                    offsets.filter(|offset| offset.abs() <= SNAP_DISTANCE).min_by(|a, b| a.abs().total_cmp(&b.abs()))
+                };
+                let baseline = to.y + fragment.font_size;
+                let dy = nearest(&mut others.iter().map(|other| other.baseline() - baseline)).unwrap_or(0.0);
+                let dx = nearest(&mut others.iter().map(|other| other.x - to.x)).unwrap_or(0.0);
+                to + Vec2::new(dx, dy)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapping() {
+        let fragment = |x, y| Fragment { text: "word".to_string(), x, y, width: 30.0, height: 12.0, font_size: 12.0 };
+        let dragged = fragment(0.0, 0.0);
+        let cell = Vec2::new(7.2, 12.0);
+        let others = [fragment(100.0, 50.0), fragment(40.0, 300.0)];
+
+        assert_eq!(Snap::Off.apply(&dragged, Vec2::new(12.3, 40.0), cell, &others), Vec2::new(12.3, 40.0));
+        let on_grid = Snap::Grid.apply(&dragged, Vec2::new(12.3, 40.0), cell, &others);
+        assert!((on_grid - Vec2::new(14.4, 36.0)).length() < 1e-4);
+        // Pulled onto the first neighbour's baseline and the second's left edge
+        assert_eq!(Snap::Baseline.apply(&dragged, Vec2::new(42.5, 47.0), cell, &others), Vec2::new(40.0, 50.0));
+        // Too far from anything to move
+        assert_eq!(Snap::Baseline.apply(&dragged, Vec2::new(60.0, 80.0), cell, &others), Vec2::new(60.0, 80.0));
+        assert_eq!(Snap::Baseline.next(), Snap::Off);
+    }
+}
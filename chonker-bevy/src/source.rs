@@ -0,0 +1,86 @@
+//! The page to edit: extracted by the engine from a PDF, or read back from
+//! a JSON or ALTO export, so the editor sees the same matrices and regions
+//! as the other front ends.
+
+use crate::alto::parse_alto;
+use crate::fragment::{fragments_to_matrix, matrix_page, Page};
+use anyhow::{bail, Context, Result};
+use chonker_core::*;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Loads page `page_index` (0-based) of `path` as fragments, and as the
+/// matrix the document model holds. PDFs are extracted with the document's
+/// saved annotations and `backend`, as the TUI does; ALTO pages, which have
+/// no cells, are laid out on `cell`-sized ones.
+pub fn load_page(
+    path: &Path,
+    page_index: usize,
+    backend: Option<ExtractionBackend>,
+    cell: (f32, f32),
+) -> Result<(Page, CharacterMatrix)> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if extension == "pdf" {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut char_matrix = runtime.block_on(extract_annotated_page(path.to_path_buf(), page_index, backend))?;
+        char_matrix.ensure_dense();
+        return Ok((matrix_page(&char_matrix), char_matrix));
+    }
+
+    let text = std::fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    match extension.as_str() {
+        "json" => {
+            let mut char_matrix = saved_matrix(&text, page_index)?;
+            char_matrix.ensure_dense();
+            Ok((matrix_page(&char_matrix), char_matrix))
+        }
+        "xml" => {
+            let pages = parse_alto(&text).with_context(|| format!("Could not parse {}", path.display()))?;
+            let total = pages.len();
+            let page = pages
+                .into_iter()
+                .nth(page_index)
+                .ok_or(ChonkerError::PageOutOfRange { page: page_index, total })?;
+            let char_matrix = fragments_to_matrix(&page.fragments, page.width, page.height, cell);
+            Ok((page, char_matrix))
+        }
+        _ => bail!("{} is not a PDF, a JSON export or an ALTO file", path.display()),
+    }
+}
+
+/// A matrix written by `chonker export --format json`: a single page, used
+/// whatever `page_index` is, or an array of pages tagged with their number.
+fn saved_matrix(json: &str, page_index: usize) -> Result<CharacterMatrix> {
+    #[derive(Deserialize)]
+    struct DocumentPage {
+        page: usize,
+        #[serde(flatten)]
+        matrix: CharacterMatrix,
+    }
+
+    if let Ok(pages) = serde_json::from_str::<Vec<DocumentPage>>(json) {
+        return match pages.into_iter().find(|page| page.page == page_index + 1) {
+            Some(page) => Ok(page.matrix),
+            None => bail!("Page {} is not in the export", page_index + 1),
+        };
+    }
+    Ok(serde_json::from_str(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_matrices_load_by_page() {
+        let mut char_matrix = CharacterMatrix::new(5, 1);
+        *char_matrix.rows_mut() = vec!["hello".chars().collect()];
+
+        let single = render_export(&char_matrix, 0, ExportFormat::Json).unwrap();
+        assert_eq!(saved_matrix(&single, 3).unwrap().rows(), char_matrix.rows());
+
+        let document = render_document_export(&[(2, char_matrix.clone())], ExportFormat::Json).unwrap();
+        assert_eq!(saved_matrix(&document, 2).unwrap().rows(), char_matrix.rows());
+        assert!(saved_matrix(&document, 0).is_err());
+    }
+}
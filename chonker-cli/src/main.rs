@@ -0,0 +1,397 @@
+//! # chonker: headless front end for chonker-core
+//!
+//! Runs the same extraction and export pipeline as the GUI without a window,
+//! for scripts and batch jobs.
+
+use anyhow::Result;
+use chonker_core::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+mod serve;
+
+const USAGE: &str = "Usage: chonker <command> <file.pdf> [options]
+
+Commands:
+  extract   one page to stdout or a file
+  convert   selected pages into a single file
+  export    one file per page
+  serve     JSON-RPC server for editors and tools (--stdio or --ws)
+
+Run `chonker <command> --help` for its options.";
+
+/// Writes to `output`, or to stdout when it is `None`.
+fn write_output(output: Option<&Path>, content: &str) -> Result<()> {
+    match output {
+        Some(path) => {
+            std::fs::write(path, content)?;
+            eprintln!("✅ {}", path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Fails when any page failed; the first failure decides the exit code.
+fn check_failures(failures: &BTreeMap<usize, ChonkerError>, total: usize, done: &str) -> Result<()> {
+    match failures.values().next() {
+        Some(first) => Err(anyhow::Error::new(first.clone()).context(format!(
+            "{} of {} page(s) {}; {}",
+            total - failures.len(),
+            total,
+            done,
+            failed_pages_summary(failures)
+        ))),
+        None => Ok(()),
+    }
+}
+
+// ============= EXTRACT =============
+const EXTRACT_USAGE: &str = "Usage: chonker extract <file.pdf> [--page 3] [--backend auto|mutool|stext|pdfium] [--format txt|json|alto|reading] [--region x,y,w,h] [--out file|-]";
+
+struct ExtractArgs {
+    pdf_path: PathBuf,
+    page_index: usize,
+    /// `None` tries mutool first and falls back to PDFium.
+    backend: Option<ExtractionBackend>,
+    format: ExportFormat,
+    region: Option<CharBBox>,
+    /// `None` writes to stdout.
+    output: Option<PathBuf>,
+}
+
+fn parse_extract_args(args: &[String]) -> Result<ExtractArgs> {
+    let mut pdf_path = None;
+    let mut page = 1;
+    let mut backend = None;
+    let mut format = None;
+    let mut region = None;
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{} requires a value\n{}", name, EXTRACT_USAGE))
+        };
+        match arg.as_str() {
+            "--page" | "-p" => {
+                page = value("--page")?
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("--page takes a page number\n{}", EXTRACT_USAGE))?
+            }
+            "--backend" | "-b" => {
+                backend = match value("--backend")?.as_str() {
+                    "auto" => None,
+                    name => Some(name.parse()?),
+                }
+            }
+            "--format" | "-f" => format = Some(value("--format")?.parse()?),
+            "--region" | "-r" => region = Some(CharBBox::parse(&value("--region")?)?),
+            "--out" | "-o" => {
+                output = match value("--out")?.as_str() {
+                    "-" => None,
+                    path => Some(PathBuf::from(path)),
+                }
+            }
+            "--help" | "-h" => return Err(anyhow::anyhow!(EXTRACT_USAGE)),
+            flag if flag.starts_with('-') => {
+                return Err(anyhow::anyhow!("Unknown option: {}\n{}", flag, EXTRACT_USAGE))
+            }
+            path => pdf_path = Some(PathBuf::from(path)),
+        }
+    }
+    if page == 0 {
+        return Err(anyhow::anyhow!("Pages are numbered from 1\n{}", EXTRACT_USAGE));
+    }
+
+    Ok(ExtractArgs {
+        pdf_path: pdf_path.ok_or_else(|| anyhow::anyhow!(EXTRACT_USAGE))?,
+        page_index: page - 1,
+        backend,
+        // Without --format the output's extension decides
+        format: format
+            .or_else(|| output.as_deref().and_then(ExportFormat::from_path))
+            .unwrap_or(ExportFormat::Txt),
+        region,
+        output,
+    })
+}
+
+/// Headless `extract` subcommand: one page, with the document's saved overrides
+/// and labels, written to stdout or `--out`.
+fn run_extract_cli(args: &[String]) -> Result<()> {
+    let args = parse_extract_args(args)?;
+    let total_pages = pdf_page_count(&args.pdf_path)?;
+    if args.page_index >= total_pages {
+        return Err(ChonkerError::PageOutOfRange { page: args.page_index, total: total_pages }.into());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut char_matrix = runtime.block_on(extract_annotated_page(args.pdf_path.clone(), args.page_index, args.backend))?;
+    if let Some(region) = &args.region {
+        char_matrix = char_matrix.crop(region);
+    }
+    let char_matrix = char_matrix.without_low_confidence(ChonkerConfig::load().min_confidence.unwrap_or(0.0));
+    write_output(args.output.as_deref(), &render_export(&char_matrix, args.page_index, args.format)?)
+}
+
+// ============= CONVERT =============
+const CONVERT_USAGE: &str = "Usage: chonker convert <file.pdf> --out <out.txt|out.json|out.alto.xml|out.reading.txt|-> [--pages 1-20] [--format txt|json|alto|reading]";
+
+struct ConvertArgs {
+    pdf_path: PathBuf,
+    pages: String,
+    format: ExportFormat,
+    /// `None` writes to stdout (`--out -`).
+    output: Option<PathBuf>,
+}
+
+fn parse_convert_args(args: &[String]) -> Result<ConvertArgs> {
+    let mut pdf_path = None;
+    let mut pages = String::new();
+    let mut format = None;
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{} requires a value\n{}", name, CONVERT_USAGE))
+        };
+        match arg.as_str() {
+            "--pages" | "-p" => pages = value("--pages")?,
+            "--format" | "-f" => format = Some(value("--format")?.parse()?),
+            "--out" | "-o" => {
+                output = Some(match value("--out")?.as_str() {
+                    "-" => None,
+                    path => Some(PathBuf::from(path)),
+                })
+            }
+            "--help" | "-h" => return Err(anyhow::anyhow!(CONVERT_USAGE)),
+            flag if flag.starts_with('-') => {
+                return Err(anyhow::anyhow!("Unknown option: {}\n{}", flag, CONVERT_USAGE))
+            }
+            path => pdf_path = Some(PathBuf::from(path)),
+        }
+    }
+    let output = output.ok_or_else(|| anyhow::anyhow!("--out is required\n{}", CONVERT_USAGE))?;
+
+    Ok(ConvertArgs {
+        pdf_path: pdf_path.ok_or_else(|| anyhow::anyhow!(CONVERT_USAGE))?,
+        pages,
+        // Without --format the output's extension decides
+        format: format
+            .or_else(|| output.as_deref().and_then(ExportFormat::from_path))
+            .unwrap_or(ExportFormat::Txt),
+        output,
+    })
+}
+
+/// Headless `convert` subcommand: the selected pages in one file. A failing
+/// page is reported and left out; the others are still written.
+fn run_convert_cli(args: &[String]) -> Result<()> {
+    let args = parse_convert_args(args)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    let results = runtime.block_on(extract_document(args.pdf_path.clone(), &args.pages))?;
+
+    let min_confidence = ChonkerConfig::load().min_confidence.unwrap_or(0.0);
+    let total = results.len();
+    let mut pages = Vec::with_capacity(total);
+    let mut failures = BTreeMap::new();
+    for (page_index, result) in results {
+        match result {
+            Ok(char_matrix) => pages.push((page_index, char_matrix.without_low_confidence(min_confidence))),
+            Err(e) => {
+                eprintln!("❌ Page {}: {}", page_index + 1, e);
+                failures.insert(page_index, e);
+            }
+        }
+    }
+    write_output(args.output.as_deref(), &render_document_export(&pages, args.format)?)?;
+    check_failures(&failures, total, "converted")
+}
+
+// ============= EXPORT =============
+const EXPORT_USAGE: &str = "Usage: chonker export <file.pdf> [--pages 1-20] [--format txt|json|alto|reading] [--region x,y,w,h] [--out dir/|-]";
+
+struct ExportArgs {
+    pdf_path: PathBuf,
+    pages: String,
+    format: ExportFormat,
+    region: Option<CharBBox>,
+    /// `None` writes to stdout (`--out -`).
+    out_dir: Option<PathBuf>,
+}
+
+fn parse_export_args(args: &[String]) -> Result<ExportArgs> {
+    let mut pdf_path = None;
+    let mut pages = String::new();
+    let mut format = ExportFormat::Txt;
+    let mut region = None;
+    let mut out_dir = Some(PathBuf::from("."));
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{} requires a value\n{}", name, EXPORT_USAGE))
+        };
+        match arg.as_str() {
+            "--pages" | "-p" => pages = value("--pages")?,
+            "--format" | "-f" => format = value("--format")?.parse()?,
+            "--region" | "-r" => region = Some(CharBBox::parse(&value("--region")?)?),
+            "--out" | "-o" => {
+                out_dir = match value("--out")?.as_str() {
+                    "-" => None,
+                    dir => Some(PathBuf::from(dir)),
+                }
+            }
+            "--help" | "-h" => return Err(anyhow::anyhow!(EXPORT_USAGE)),
+            flag if flag.starts_with('-') => {
+                return Err(anyhow::anyhow!("Unknown option: {}\n{}", flag, EXPORT_USAGE))
+            }
+            path => pdf_path = Some(PathBuf::from(path)),
+        }
+    }
+
+    Ok(ExportArgs {
+        pdf_path: pdf_path.ok_or_else(|| anyhow::anyhow!(EXPORT_USAGE))?,
+        pages,
+        format,
+        region,
+        out_dir,
+    })
+}
+
+/// Headless `export` subcommand. Pages go through the same extraction path as
+/// the GUI (`chonker_core::extract_page`) and are written one file per page,
+/// or concatenated to stdout with `--out -`.
+fn run_export_cli(args: &[String]) -> Result<()> {
+    let args = parse_export_args(args)?;
+    let total_pages = pdf_page_count(&args.pdf_path)?;
+    let pages = parse_page_range(&args.pages, total_pages)?;
+    if let Some(out_dir) = &args.out_dir {
+        std::fs::create_dir_all(out_dir)?;
+    }
+
+    let stem = args
+        .pdf_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+    let runtime = tokio::runtime::Runtime::new()?;
+    let char_overrides = load_char_overrides(&args.pdf_path);
+    let region_labels = load_region_labels(&args.pdf_path);
+    let region_tags = load_region_tags(&args.pdf_path);
+    let reading_orders = load_reading_orders(&args.pdf_path);
+    let segmentation = load_segmentation(&args.pdf_path);
+    let config = ChonkerConfig::load();
+    let min_confidence = config.min_confidence.unwrap_or(0.0);
+
+    // A failing page is reported and skipped; the others are still exported
+    let total = pages.len();
+    let mut failures = BTreeMap::new();
+    for page_index in pages {
+        // Per-page override, then the configured cell size, as in extract_document
+        let dimensions = char_overrides.get(&page_index).copied().or(config.char_size);
+        let mut char_matrix = match runtime.block_on(extract_page(args.pdf_path.clone(), page_index, dimensions)) {
+            Ok(char_matrix) => char_matrix,
+            Err(e) => {
+                eprintln!("❌ Page {}: {}", page_index + 1, e);
+                failures.insert(page_index, e);
+                continue;
+            }
+        };
+        char_matrix.ensure_dense();
+        apply_segmentation(&mut char_matrix, segmentation);
+        apply_region_labels(&mut char_matrix, region_labels.get(&page_index));
+        apply_region_tags(&mut char_matrix, region_tags.get(&page_index));
+        char_matrix.reading_order = reading_orders.get(&page_index).cloned();
+        if let Some(region) = &args.region {
+            char_matrix = char_matrix.crop(region);
+        }
+        let content = render_export(&char_matrix.without_low_confidence(min_confidence), page_index, args.format)?;
+
+        match &args.out_dir {
+            Some(out_dir) => {
+                let output_path = out_dir.join(format!(
+                    "{}_p{:04}.{}",
+                    stem,
+                    page_index + 1,
+                    args.format.extension()
+                ));
+                std::fs::write(&output_path, content)?;
+                eprintln!("✅ Page {} → {}", page_index + 1, output_path.display());
+            }
+            None => print!("{}", content),
+        }
+    }
+
+    check_failures(&failures, total, "exported")
+}
+
+fn init_tracing() {
+    use tracing_subscriber::EnvFilter;
+
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+fn main() {
+    init_tracing();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("extract") => run_extract_cli(&args[1..]),
+        Some("convert") => run_convert_cli(&args[1..]),
+        Some("export") => run_export_cli(&args[1..]),
+        Some("serve") => serve::run_serve_cli(&args[1..]),
+        Some("--help" | "-h") | None => {
+            println!("{}", USAGE);
+            return;
+        }
+        Some(other) => Err(anyhow::anyhow!("Unknown command: {}\n{}", other, USAGE)),
+    };
+    if let Err(e) = result {
+        eprintln!("❌ {}", e);
+        let code = e.downcast_ref::<ChonkerError>().map_or(1, ChonkerError::exit_code);
+        std::process::exit(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_extract_and_convert_args() {
+        let extract = parse_extract_args(&args("in.pdf --page 3 --backend pdfium -o page.json")).unwrap();
+        assert_eq!(extract.page_index, 2);
+        assert_eq!(extract.backend, Some(ExtractionBackend::Pdfium));
+        assert_eq!(extract.format, ExportFormat::Json);
+        assert_eq!(extract.output, Some(PathBuf::from("page.json")));
+        let extract = parse_extract_args(&args("in.pdf --backend auto --format alto")).unwrap();
+        assert_eq!(
+            (extract.page_index, extract.backend, extract.format, extract.output),
+            (0, None, ExportFormat::Alto, None)
+        );
+        assert!(parse_extract_args(&args("in.pdf --page 0")).is_err());
+        assert!(parse_extract_args(&args("in.pdf --backend ocr")).is_err());
+
+        let convert = parse_convert_args(&args("in.pdf -o out.alto.xml --pages 1-3")).unwrap();
+        assert_eq!((convert.pages.as_str(), convert.format), ("1-3", ExportFormat::Alto));
+        let convert = parse_convert_args(&args("in.pdf -o - -f json")).unwrap();
+        assert_eq!((convert.format, convert.output), (ExportFormat::Json, None));
+        assert!(parse_convert_args(&args("in.pdf")).is_err());
+    }
+}
@@ -0,0 +1,520 @@
+//! `chonker serve`: JSON-RPC 2.0 so editors and tools can keep documents open
+//! and drive the engine without a window. `--stdio` speaks over stdin/stdout
+//! with the `Content-Length` framing of the Language Server Protocol;
+//! `--ws ADDR` accepts WebSocket clients one at a time, one message per text
+//! frame.
+//!
+//! Methods (pages are 0-based):
+//! - `openDocument {path}` → `{document, pageCount}`
+//! - `extractPage {document, page, backend?}` → `{page, width, height, lines, backend, warnings}`;
+//!   `backend` applies when the page is first extracted
+//! - `extractDocument {document, pages?}` → `{pages, failed}`; extracts the
+//!   pages not yet held (1-based spec as on the command line, all when
+//!   absent), sending a `progress` notification with `document` and a
+//!   `ProgressEvent` as each page is queued, started and finished
+//! - `getRegions {document, page}` → the page's text regions
+//! - `applyEdit {document, page, row, col, text}` → `{changed}`; overwrites
+//!   cells from `(row, col)` as typing `text` there would, so each line after
+//!   the first starts at column 0 of the next row; characters past the matrix
+//!   edge are dropped
+//! - `saveDocument {document}` → `{saved}`; records edited pages in the matrix
+//!   history and edit log, as saving in the GUI does
+//! - `export {document, format, pages?}` → `{content}`; edited pages are
+//!   exported as edited
+//! - `closeDocument {document}`, `shutdown`
+//!
+//! Engine failures are answered with code -32000 and the failure's CLI exit
+//! code and hint in `data`. Logs go to stderr; stdout carries only messages.
+
+use anyhow::{anyhow, Result};
+use chonker_core::*;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const ENGINE_ERROR: i64 = -32000;
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+}
+
+impl From<ChonkerError> for RpcError {
+    fn from(error: ChonkerError) -> Self {
+        Self {
+            code: ENGINE_ERROR,
+            message: error.to_string(),
+            data: Some(json!({ "exitCode": error.exit_code(), "hint": error.hint() })),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(error: anyhow::Error) -> Self {
+        ChonkerError::from_anyhow(error).into()
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))
+}
+
+#[derive(Deserialize)]
+struct OpenParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct DocumentParams {
+    document: u64,
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    document: u64,
+    page: usize,
+    #[serde(default)]
+    backend: Option<ExtractionBackend>,
+}
+
+#[derive(Deserialize)]
+struct EditParams {
+    document: u64,
+    page: usize,
+    row: usize,
+    col: usize,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct PagesParams {
+    document: u64,
+    #[serde(default)]
+    pages: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    document: u64,
+    format: String,
+    /// 1-based page spec as on the command line; all pages when absent.
+    #[serde(default)]
+    pages: Option<String>,
+}
+
+struct ServedPage {
+    char_matrix: CharacterMatrix,
+    /// The extraction before any `applyEdit`, for the edit log.
+    original: Vec<Vec<char>>,
+    edited: bool,
+}
+
+struct OpenDocument {
+    path: PathBuf,
+    page_count: usize,
+    pages: BTreeMap<usize, ServedPage>,
+}
+
+struct Server {
+    runtime: tokio::runtime::Runtime,
+    documents: HashMap<u64, OpenDocument>,
+    next_document: u64,
+    shutdown: bool,
+}
+
+impl Server {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            runtime: tokio::runtime::Runtime::new()?,
+            documents: HashMap::new(),
+            next_document: 1,
+            shutdown: false,
+        })
+    }
+
+    /// Answers one message body; notifications get no response. `notify`
+    /// sends server notifications while the request is handled.
+    fn respond(&mut self, body: &str, notify: &mut dyn FnMut(Value)) -> Option<Value> {
+        let request: Value = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)))),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id.unwrap_or(Value::Null), RpcError::new(INVALID_REQUEST, "Invalid request")));
+        };
+        let result = self.handle(method, request.get("params").cloned().unwrap_or(Value::Null), notify);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn handle(&mut self, method: &str, params: Value, notify: &mut dyn FnMut(Value)) -> Result<Value, RpcError> {
+        match method {
+            "openDocument" => {
+                let params: OpenParams = parse_params(params)?;
+                let page_count = pdf_page_count(&params.path)?;
+                let document = self.next_document;
+                self.next_document += 1;
+                tracing::info!("📂 Document {} is {}", document, params.path.display());
+                self.documents
+                    .insert(document, OpenDocument { path: params.path, page_count, pages: BTreeMap::new() });
+                Ok(json!({ "document": document, "pageCount": page_count }))
+            }
+            "extractPage" => {
+                let params: PageParams = parse_params(params)?;
+                let page = self.page(params.document, params.page, params.backend)?;
+                let char_matrix = &page.char_matrix;
+                Ok(json!({
+                    "page": params.page,
+                    "width": char_matrix.width,
+                    "height": char_matrix.height,
+                    "lines": char_matrix.rows().iter().map(|row| row.iter().collect::<String>()).collect::<Vec<_>>(),
+                    "backend": char_matrix.backend,
+                    "warnings": char_matrix.warnings,
+                }))
+            }
+            "extractDocument" => {
+                let params: PagesParams = parse_params(params)?;
+                self.extract_document(params.document, params.pages.as_deref().unwrap_or(""), notify)
+            }
+            "getRegions" => {
+                let params: PageParams = parse_params(params)?;
+                let page = self.page(params.document, params.page, params.backend)?;
+                Ok(serde_json::to_value(&page.char_matrix.text_regions).map_err(anyhow::Error::from)?)
+            }
+            "applyEdit" => {
+                let params: EditParams = parse_params(params)?;
+                let page = self.page(params.document, params.page, None)?;
+                let changed = apply_edit(page.char_matrix.rows_mut(), params.row, params.col, &params.text);
+                page.edited |= changed > 0;
+                Ok(json!({ "changed": changed }))
+            }
+            "saveDocument" => {
+                let params: DocumentParams = parse_params(params)?;
+                let saved = save_document(self.document(params.document)?)?;
+                Ok(json!({ "saved": saved }))
+            }
+            "export" => {
+                let params: ExportParams = parse_params(params)?;
+                let format: ExportFormat =
+                    params.format.parse().map_err(|e: anyhow::Error| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                let page_count = self.document(params.document)?.page_count;
+                let indices = parse_page_range(params.pages.as_deref().unwrap_or(""), page_count)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                let mut pages = Vec::with_capacity(indices.len());
+                for page_index in indices {
+                    pages.push((page_index, self.page(params.document, page_index, None)?.char_matrix.clone()));
+                }
+                Ok(json!({ "content": render_document_export(&pages, format)? }))
+            }
+            "closeDocument" => {
+                let params: DocumentParams = parse_params(params)?;
+                self.documents.remove(&params.document);
+                Ok(Value::Null)
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        }
+    }
+
+    /// Extracts the selected pages the document doesn't hold yet on a runtime
+    /// task, forwarding its progress events as they arrive.
+    fn extract_document(
+        &mut self,
+        document: u64,
+        pages_spec: &str,
+        notify: &mut dyn FnMut(Value),
+    ) -> Result<Value, RpcError> {
+        let runtime = &self.runtime;
+        let open = self
+            .documents
+            .get_mut(&document)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown document: {}", document)))?;
+        let selected = parse_page_range(pages_spec, open.page_count)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+        let missing: Vec<String> = selected
+            .iter()
+            .filter(|page| !open.pages.contains_key(page))
+            .map(|page| (page + 1).to_string())
+            .collect();
+
+        let mut failed = BTreeMap::new();
+        if !missing.is_empty() {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let path = open.path.clone();
+            let spec = missing.join(",");
+            let task =
+                runtime.spawn(async move { extract_document_with_progress(path, &spec, Some(sender)).await });
+            for event in receiver {
+                let mut params = serde_json::to_value(&event).map_err(anyhow::Error::from)?;
+                params["document"] = json!(document);
+                notify(json!({ "jsonrpc": "2.0", "method": "progress", "params": params }));
+            }
+            let results = runtime
+                .block_on(task)
+                .map_err(|e| ChonkerError::Other(format!("Extraction task failed: {}", e)))??;
+            for (page_index, result) in results {
+                match result {
+                    Ok(mut char_matrix) => {
+                        char_matrix.ensure_dense();
+                        let original = char_matrix.rows().into_owned();
+                        open.pages.insert(page_index, ServedPage { char_matrix, original, edited: false });
+                    }
+                    Err(e) => {
+                        failed.insert(page_index.to_string(), e.to_string());
+                    }
+                }
+            }
+        }
+        let pages: Vec<usize> = selected.into_iter().filter(|page| open.pages.contains_key(page)).collect();
+        Ok(json!({ "pages": pages, "failed": failed }))
+    }
+
+    fn document(&mut self, document: u64) -> Result<&mut OpenDocument, RpcError> {
+        self.documents
+            .get_mut(&document)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown document: {}", document)))
+    }
+
+    /// The page as last edited, extracting it on first use.
+    fn page(
+        &mut self,
+        document: u64,
+        page_index: usize,
+        backend: Option<ExtractionBackend>,
+    ) -> Result<&mut ServedPage, RpcError> {
+        let runtime = &self.runtime;
+        let document = self
+            .documents
+            .get_mut(&document)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown document: {}", document)))?;
+        if page_index >= document.page_count {
+            return Err(ChonkerError::PageOutOfRange { page: page_index, total: document.page_count }.into());
+        }
+        if !document.pages.contains_key(&page_index) {
+            let mut char_matrix = runtime.block_on(extract_annotated_page(document.path.clone(), page_index, backend))?;
+            char_matrix.ensure_dense();
+            let original = char_matrix.rows().into_owned();
+            document.pages.insert(page_index, ServedPage { char_matrix, original, edited: false });
+        }
+        Ok(document.pages.get_mut(&page_index).expect("page was just inserted"))
+    }
+}
+
+/// Overwrites cells from `(row, col)` with `text` and returns how many changed.
+/// Lines after the first start at column 0, like text typed with newlines.
+fn apply_edit(matrix: &mut [Vec<char>], row: usize, col: usize, text: &str) -> usize {
+    let mut changed = 0;
+    for (i, (matrix_row, line)) in matrix.iter_mut().skip(row).zip(text.lines()).enumerate() {
+        let start = if i == 0 { col } else { 0 };
+        for (cell, ch) in matrix_row.iter_mut().skip(start).zip(line.chars()) {
+            if *cell != ch {
+                *cell = ch;
+                changed += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// Records each edited page as a new matrix version, with its cell edits in
+/// the edit log.
+fn save_document(document: &mut OpenDocument) -> Result<Vec<usize>> {
+    let mut saved = Vec::new();
+    for (&page_index, page) in document.pages.iter_mut().filter(|(_, page)| page.edited) {
+        record_matrix_save(&document.path, page_index, &page.original, &page.char_matrix.rows())?;
+        page.edited = false;
+        saved.push(page_index);
+    }
+    Ok(saved)
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    let mut body = json!({ "code": error.code, "message": error.message });
+    if let Some(data) = error.data {
+        body["data"] = data;
+    }
+    json!({ "jsonrpc": "2.0", "id": id, "error": body })
+}
+
+/// Reads one `Content-Length` framed body, or `None` at end of input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("Message without Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8(body)?))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn serve_stdio(mut server: Server) -> Result<()> {
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    tracing::info!("🔌 Serving JSON-RPC on stdio");
+    while let Some(body) = read_message(&mut input)? {
+        let response = server.respond(&body, &mut |notification| {
+            if let Err(e) = write_message(&mut output, &notification) {
+                tracing::warn!("⚠️ Could not send notification: {}", e);
+            }
+        });
+        if let Some(response) = response {
+            write_message(&mut output, &response)?;
+        }
+        if server.shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn serve_websocket(mut server: Server, addr: &str) -> Result<()> {
+    use tungstenite::Message;
+
+    let listener = TcpListener::bind(addr)?;
+    tracing::info!("🔌 Serving JSON-RPC on ws://{}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let mut socket = match tungstenite::accept(stream?) {
+            Ok(socket) => socket,
+            Err(e) => {
+                tracing::warn!("⚠️ WebSocket handshake failed: {}", e);
+                continue;
+            }
+        };
+        // A client's documents stay open for the next one until closed
+        while let Ok(message) = socket.read() {
+            let body = match message {
+                Message::Text(body) => body,
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            let response = server.respond(&body, &mut |notification| {
+                if let Err(e) = socket.send(Message::Text(notification.to_string())) {
+                    tracing::warn!("⚠️ Could not send notification: {}", e);
+                }
+            });
+            if let Some(response) = response {
+                if socket.send(Message::Text(response.to_string())).is_err() {
+                    break;
+                }
+            }
+            if server.shutdown {
+                let _ = socket.close(None);
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run_serve_cli(args: &[String]) -> Result<()> {
+    const SERVE_USAGE: &str = "Usage: chonker serve (--stdio | --ws <addr>)
+
+  --stdio       Content-Length framed messages on stdin/stdout
+  --ws <addr>   WebSocket server, e.g. 127.0.0.1:9420";
+    match args {
+        [flag] if flag == "--stdio" => serve_stdio(Server::new()?),
+        [flag, addr] if flag == "--ws" => serve_websocket(Server::new()?, addr),
+        [flag] if flag == "--help" || flag == "-h" => {
+            println!("{}", SERVE_USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow!("{}", SERVE_USAGE)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn served(lines: &[&str]) -> OpenDocument {
+        let mut char_matrix = CharacterMatrix::new(lines[0].len(), lines.len());
+        *char_matrix.rows_mut() = lines.iter().map(|line| line.chars().collect()).collect();
+        let original = char_matrix.rows().into_owned();
+        OpenDocument {
+            path: PathBuf::from("served.pdf"),
+            page_count: 1,
+            pages: BTreeMap::from([(0, ServedPage { char_matrix, original, edited: false })]),
+        }
+    }
+
+    fn call(server: &mut Server, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        server.respond(&request.to_string(), &mut |_| {}).expect("requests with an id are answered")
+    }
+
+    #[test]
+    fn test_serve_framing_and_methods() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({ "method": "shutdown" })).unwrap();
+        framed.extend_from_slice(b"Content-Length: 2\r\n\r\n{}");
+        let mut input = std::io::Cursor::new(framed);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), r#"{"method":"shutdown"}"#);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "{}");
+        assert!(read_message(&mut input).unwrap().is_none());
+
+        let mut server = Server::new().unwrap();
+        server.documents.insert(1, served(&["abc  ", "     "]));
+
+        let edit = call(&mut server, 1, "applyEdit", json!({ "document": 1, "page": 0, "row": 0, "col": 3, "text": "de\nxyz" }));
+        assert_eq!(edit["result"]["changed"], 5);
+        let page = call(&mut server, 2, "extractPage", json!({ "document": 1, "page": 0 }));
+        assert_eq!(page["result"]["lines"], json!(["abcde", "xyz  "]));
+        let export = call(&mut server, 3, "export", json!({ "document": 1, "format": "txt" }));
+        assert!(export["result"]["content"].as_str().unwrap().starts_with("abcde"));
+
+        let out_of_range = call(&mut server, 4, "extractPage", json!({ "document": 1, "page": 3 }));
+        assert_eq!(out_of_range["error"]["code"], ENGINE_ERROR);
+        assert!(out_of_range["error"]["data"]["exitCode"].is_number());
+        assert_eq!(call(&mut server, 5, "getRegions", json!({ "page": 0 }))["error"]["code"], INVALID_PARAMS);
+        assert_eq!(call(&mut server, 6, "rotate", Value::Null)["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(call(&mut server, 7, "extractDocument", json!({ "document": 1, "pages": "1" }))["result"]["pages"], json!([0]));
+        assert_eq!(server.respond("{", &mut |_| {}).unwrap()["error"]["code"], PARSE_ERROR);
+
+        // Notifications are handled but never answered
+        assert!(server.respond(r#"{"jsonrpc":"2.0","method":"shutdown"}"#, &mut |_| {}).is_none());
+        assert!(server.shutdown);
+    }
+}
@@ -0,0 +1,53 @@
+/*
+ * chonker_core.h - C interface to the Chonker character-matrix engine.
+ *
+ * Link against the chonker_core cdylib (`cargo build -p chonker-core --release`).
+ *
+ * Functions returning int32_t give 0 on success or a sysexits.h-style code
+ * (64 bad page, 65 no text, 69 missing tool or PDFium, 70 render failure,
+ * 75 timeout, 1 other); chonker_last_error() then describes the failure.
+ * Handles and strings from the library are released with the matching
+ * *_free function.
+ */
+#ifndef CHONKER_CORE_H
+#define CHONKER_CORE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ChonkerMatrix ChonkerMatrix;
+typedef struct ChonkerDocument ChonkerDocument;
+
+/* Message of the last failure on this thread; valid until the next failure. */
+const char *chonker_last_error(void);
+
+/* Extracts one 0-based page. Free the result with chonker_matrix_free. */
+int32_t chonker_extract_page(const char *pdf_path, size_t page_index, ChonkerMatrix **out);
+
+/* Extracts the pages in `pages` ("1-5,8"; NULL or "" for all). Per-page
+ * failures are reported by chonker_document_page. */
+int32_t chonker_extract_document(const char *pdf_path, const char *pages, ChonkerDocument **out);
+size_t chonker_document_len(const ChonkerDocument *document);
+/* Sets the 0-based page number and, on success, a matrix owned by `document`. */
+int32_t chonker_document_page(const ChonkerDocument *document, size_t index,
+                              size_t *page_index, const ChonkerMatrix **matrix);
+void chonker_document_free(ChonkerDocument *document);
+
+size_t chonker_matrix_width(const ChonkerMatrix *matrix);
+size_t chonker_matrix_height(const ChonkerMatrix *matrix);
+/* `format` is "txt", "json", "alto" or "reading". Returns NULL on failure;
+ * free the result with chonker_string_free. */
+char *chonker_matrix_export(const ChonkerMatrix *matrix, size_t page_index, const char *format);
+void chonker_matrix_free(ChonkerMatrix *matrix);
+
+void chonker_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHONKER_CORE_H */
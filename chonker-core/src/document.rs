@@ -0,0 +1,440 @@
+//! The open document as every front end edits it: its extracted pages, the
+//! edits made to them, their region labels and tags, and undo history.
+//!
+//! Front ends hand their edits to the model and redraw from it; listeners
+//! returned by `subscribe` hear about every change, so undo in one view, or
+//! a page loaded in the background, reaches the others.
+
+use crate::{
+    load_region_labels, load_region_tags, record_matrix_save, save_region_labels, save_region_tags, CharacterMatrix,
+    ChonkerError, RegionTag, TextRegion,
+};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Undo steps kept per page.
+const HISTORY_LIMIT: usize = 100;
+
+/// A change to the model, by 0-based page index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocumentEvent {
+    /// The page was extracted or replaced.
+    Loaded { page: usize },
+    /// An edit changed the page.
+    Edited { page: usize },
+    /// Undo or redo put back an earlier state of the page.
+    Restored { page: usize },
+    Saved { page: usize },
+    Unloaded { page: usize },
+}
+
+/// What an undo step restores.
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    cells: Vec<Vec<char>>,
+    width: usize,
+    height: usize,
+    regions: Vec<TextRegion>,
+    reading_order: Option<Vec<usize>>,
+}
+
+impl Snapshot {
+    fn of(char_matrix: &CharacterMatrix) -> Self {
+        Self {
+            cells: char_matrix.matrix.clone(),
+            width: char_matrix.width,
+            height: char_matrix.height,
+            regions: char_matrix.text_regions.clone(),
+            reading_order: char_matrix.reading_order.clone(),
+        }
+    }
+
+    fn restore(self, char_matrix: &mut CharacterMatrix) {
+        char_matrix.matrix = self.cells;
+        char_matrix.width = self.width;
+        char_matrix.height = self.height;
+        char_matrix.text_regions = self.regions;
+        char_matrix.reading_order = self.reading_order;
+    }
+}
+
+pub struct DocumentPage {
+    /// The page as it stands: the extraction with every edit applied.
+    pub matrix: CharacterMatrix,
+    /// The cells as extracted, for the edit log and the page view.
+    pub extracted: Vec<Vec<char>>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// The last undo step came from `edit_cells` and `amend_cells` may still
+    /// fold into it.
+    open: bool,
+    /// Undo depth of the state last saved or loaded; `None` once that state
+    /// can no longer be reached by undo or redo.
+    saved_depth: Option<usize>,
+}
+
+impl DocumentPage {
+    /// Whether the page differs from its last saved or loaded state. Undoing
+    /// back to that state clears it again.
+    pub fn is_modified(&self) -> bool {
+        self.saved_depth != Some(self.undo.len())
+    }
+
+    fn push_undo(&mut self, snapshot: Snapshot) {
+        // The saved state was on the redo stack
+        if self.saved_depth.is_some_and(|depth| depth > self.undo.len()) {
+            self.saved_depth = None;
+        }
+        self.undo.push(snapshot);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+            self.saved_depth = self.saved_depth.and_then(|depth| depth.checked_sub(1));
+        }
+        self.redo.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct DocumentModel {
+    pdf_path: PathBuf,
+    pages: BTreeMap<usize, DocumentPage>,
+    listeners: Vec<Sender<DocumentEvent>>,
+}
+
+impl DocumentModel {
+    pub fn new(pdf_path: PathBuf) -> Self {
+        Self { pdf_path, ..Self::default() }
+    }
+
+    pub fn pdf_path(&self) -> &Path {
+        &self.pdf_path
+    }
+
+    /// A receiver for every change from now on. Dropping it unsubscribes.
+    pub fn subscribe(&mut self) -> Receiver<DocumentEvent> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.listeners.push(sender);
+        receiver
+    }
+
+    fn notify(&mut self, event: DocumentEvent) {
+        self.listeners.retain(|listener| listener.send(event).is_ok());
+    }
+
+    /// Takes in an extracted page, replacing any held one with its edits and
+    /// history.
+    pub fn insert_page(&mut self, page: usize, mut char_matrix: CharacterMatrix) {
+        char_matrix.ensure_dense();
+        let extracted = char_matrix.matrix.clone();
+        let document_page = DocumentPage {
+            matrix: char_matrix,
+            extracted,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            open: false,
+            saved_depth: Some(0),
+        };
+        self.pages.insert(page, document_page);
+        self.notify(DocumentEvent::Loaded { page });
+    }
+
+    pub fn remove_page(&mut self, page: usize) -> Option<DocumentPage> {
+        let removed = self.pages.remove(&page)?;
+        self.notify(DocumentEvent::Unloaded { page });
+        Some(removed)
+    }
+
+    pub fn page(&self, page: usize) -> Option<&DocumentPage> {
+        self.pages.get(&page)
+    }
+
+    /// Held pages in page order.
+    pub fn pages(&self) -> impl Iterator<Item = (usize, &DocumentPage)> {
+        self.pages.iter().map(|(&page, document_page)| (page, document_page))
+    }
+
+    pub fn is_modified(&self, page: usize) -> bool {
+        self.pages.get(&page).is_some_and(DocumentPage::is_modified)
+    }
+
+    pub fn has_unsaved_edits(&self) -> bool {
+        self.pages.values().any(DocumentPage::is_modified)
+    }
+
+    /// Applies `edit` to the page as one undo step. Returns `false`, leaving
+    /// the history alone, when the page isn't held or its cells, regions and
+    /// reading order came out unchanged.
+    pub fn edit(&mut self, page: usize, edit: impl FnOnce(&mut CharacterMatrix)) -> bool {
+        let Some(document_page) = self.pages.get_mut(&page) else {
+            return false;
+        };
+        let before = Snapshot::of(&document_page.matrix);
+        edit(&mut document_page.matrix);
+        if Snapshot::of(&document_page.matrix) == before {
+            return false;
+        }
+        document_page.push_undo(before);
+        document_page.open = false;
+        self.notify(DocumentEvent::Edited { page });
+        true
+    }
+
+    /// Replaces the page's cells, as the grid editors hand them over.
+    pub fn edit_cells(&mut self, page: usize, cells: Vec<Vec<char>>) -> bool {
+        let edited = self.edit(page, |char_matrix| char_matrix.matrix = cells);
+        if let Some(document_page) = self.pages.get_mut(&page).filter(|_| edited) {
+            document_page.open = true;
+        }
+        edited
+    }
+
+    /// Replaces the page's cells as part of the last `edit_cells` step, so an
+    /// operation handed over in pieces (a run of typing, a drag and its drop)
+    /// undoes at once. Starts a new step when there is none to extend.
+    pub fn amend_cells(&mut self, page: usize, cells: Vec<Vec<char>>) -> bool {
+        let Some(document_page) = self.pages.get_mut(&page) else {
+            return false;
+        };
+        if !document_page.open {
+            return self.edit_cells(page, cells);
+        }
+        if document_page.matrix.matrix == cells {
+            return false;
+        }
+        document_page.matrix.matrix = cells;
+        // Amended back to where it began, the step is gone
+        if document_page.undo.last() == Some(&Snapshot::of(&document_page.matrix)) {
+            document_page.undo.pop();
+            document_page.open = false;
+        }
+        self.notify(DocumentEvent::Edited { page });
+        true
+    }
+
+    pub fn set_region_label(&mut self, page: usize, region_id: usize, label: Option<String>) -> bool {
+        self.edit(page, |char_matrix| {
+            for region in char_matrix.text_regions.iter_mut().filter(|region| region.region_id == region_id) {
+                region.label = label.clone();
+            }
+        })
+    }
+
+    pub fn set_region_tag(&mut self, page: usize, region_id: usize, tag: Option<RegionTag>) -> bool {
+        self.edit(page, |char_matrix| {
+            for region in char_matrix.text_regions.iter_mut().filter(|region| region.region_id == region_id) {
+                region.tag = tag;
+            }
+        })
+    }
+
+    pub fn set_reading_order(&mut self, page: usize, order: Option<Vec<usize>>) -> bool {
+        self.edit(page, |char_matrix| char_matrix.reading_order = order)
+    }
+
+    pub fn undo(&mut self, page: usize) -> bool {
+        self.step_history(page, true)
+    }
+
+    pub fn redo(&mut self, page: usize) -> bool {
+        self.step_history(page, false)
+    }
+
+    fn step_history(&mut self, page: usize, back: bool) -> bool {
+        let Some(document_page) = self.pages.get_mut(&page) else {
+            return false;
+        };
+        let (from, to) = if back {
+            (&mut document_page.undo, &mut document_page.redo)
+        } else {
+            (&mut document_page.redo, &mut document_page.undo)
+        };
+        let Some(snapshot) = from.pop() else {
+            return false;
+        };
+        to.push(Snapshot::of(&document_page.matrix));
+        snapshot.restore(&mut document_page.matrix);
+        document_page.open = false;
+        self.notify(DocumentEvent::Restored { page });
+        true
+    }
+
+    /// Records the page's cells as a new matrix version, with their edits in
+    /// the edit log, and writes its region labels and tags next to the PDF.
+    /// Returns the number of cell edits logged.
+    pub fn save(&mut self, page: usize) -> Result<usize> {
+        let Some(document_page) = self.pages.get_mut(&page) else {
+            return Err(ChonkerError::Other(format!("Page {} is not loaded", page + 1)).into());
+        };
+        let edits = record_matrix_save(&self.pdf_path, page, &document_page.extracted, &document_page.matrix.matrix)?;
+
+        let regions = &document_page.matrix.text_regions;
+        let labels: HashMap<usize, String> =
+            regions.iter().filter_map(|region| Some((region.region_id, region.label.clone()?))).collect();
+        let mut saved_labels = load_region_labels(&self.pdf_path);
+        if update_page_entry(&mut saved_labels, page, labels) {
+            save_region_labels(&self.pdf_path, &saved_labels)?;
+        }
+        let tags: HashMap<usize, RegionTag> =
+            regions.iter().filter_map(|region| Some((region.region_id, region.tag?))).collect();
+        let mut saved_tags = load_region_tags(&self.pdf_path);
+        if update_page_entry(&mut saved_tags, page, tags) {
+            save_region_tags(&self.pdf_path, &saved_tags)?;
+        }
+
+        document_page.saved_depth = Some(document_page.undo.len());
+        document_page.open = false;
+        self.notify(DocumentEvent::Saved { page });
+        Ok(edits)
+    }
+}
+
+/// Sets the page's entry of a per-page map, dropping it when empty so pages
+/// without labels or tags leave no file behind. Returns whether it changed.
+pub fn update_page_entry<T: PartialEq>(
+    map: &mut HashMap<usize, HashMap<usize, T>>,
+    page: usize,
+    entry: HashMap<usize, T>,
+) -> bool {
+    if entry.is_empty() {
+        map.remove(&page).is_some()
+    } else if map.get(&page) == Some(&entry) {
+        false
+    } else {
+        map.insert(page, entry);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{list_matrix_versions, load_edit_log, text_to_matrix, CharBBox};
+
+    fn char_matrix(text: &str) -> CharacterMatrix {
+        let cells = text_to_matrix(text);
+        let mut char_matrix = CharacterMatrix::new(cells[0].len(), cells.len());
+        char_matrix.matrix = cells;
+        char_matrix.text_regions.push(TextRegion {
+            bbox: CharBBox { x: 0, y: 0, width: 5, height: 1 },
+            confidence: 1.0,
+            text_content: "hello".to_string(),
+            region_id: 0,
+            is_italic: false,
+            label: None,
+            tag: None,
+        });
+        char_matrix
+    }
+
+    #[test]
+    fn test_edits_undo_and_notify() {
+        let mut document = DocumentModel::new(PathBuf::from("/nonexistent/report.pdf"));
+        let events = document.subscribe();
+        document.insert_page(2, char_matrix("hello\nworld\n"));
+        assert!(!document.has_unsaved_edits());
+
+        assert!(document.edit_cells(2, text_to_matrix("jello\nworld\n")));
+        assert!(!document.edit_cells(2, text_to_matrix("jello\nworld\n")));
+        assert!(document.set_region_tag(2, 0, Some(RegionTag::Title)));
+        assert!(!document.edit_cells(0, Vec::new()));
+        assert!(document.is_modified(2));
+
+        assert!(document.undo(2));
+        assert_eq!(document.page(2).unwrap().matrix.text_regions[0].tag, None);
+        assert!(document.undo(2));
+        assert_eq!(document.page(2).unwrap().matrix.matrix, text_to_matrix("hello\nworld\n"));
+        assert!(!document.undo(2));
+        assert!(document.redo(2));
+        assert_eq!(document.page(2).unwrap().matrix.matrix, text_to_matrix("jello\nworld\n"));
+
+        // A new edit drops what could be redone
+        assert!(document.edit_cells(2, text_to_matrix("jelly\nworld\n")));
+        assert!(!document.redo(2));
+
+        assert!(document.remove_page(2).is_some());
+        let events: Vec<DocumentEvent> = events.try_iter().collect();
+        assert_eq!(
+            events,
+            [
+                DocumentEvent::Loaded { page: 2 },
+                DocumentEvent::Edited { page: 2 },
+                DocumentEvent::Edited { page: 2 },
+                DocumentEvent::Restored { page: 2 },
+                DocumentEvent::Restored { page: 2 },
+                DocumentEvent::Restored { page: 2 },
+                DocumentEvent::Edited { page: 2 },
+                DocumentEvent::Unloaded { page: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_amend_cells_folds_into_one_undo_step() {
+        let mut document = DocumentModel::new(PathBuf::from("/nonexistent/report.pdf"));
+        document.insert_page(0, char_matrix("hello\n"));
+
+        // Typing "jo" over "he" is one step
+        assert!(document.amend_cells(0, text_to_matrix("jello\n")));
+        assert!(document.amend_cells(0, text_to_matrix("jollo\n")));
+        assert!(document.edit_cells(0, text_to_matrix("jolly\n")));
+        assert!(document.undo(0));
+        assert_eq!(document.page(0).unwrap().matrix.matrix, text_to_matrix("jollo\n"));
+        assert!(document.undo(0));
+        assert_eq!(document.page(0).unwrap().matrix.matrix, text_to_matrix("hello\n"));
+
+        // After undo an amendment starts its own step
+        assert!(document.redo(0));
+        assert!(document.amend_cells(0, text_to_matrix("yollo\n")));
+        assert!(document.undo(0));
+        assert_eq!(document.page(0).unwrap().matrix.matrix, text_to_matrix("jollo\n"));
+
+        // Amending back to the start leaves no step behind
+        assert!(document.edit_cells(0, text_to_matrix("hollo\n")));
+        assert!(document.amend_cells(0, text_to_matrix("jollo\n")));
+        assert!(document.undo(0));
+        assert_eq!(document.page(0).unwrap().matrix.matrix, text_to_matrix("hello\n"));
+        assert!(!document.undo(0));
+        assert!(!document.is_modified(0));
+
+        // Folding an edit back into the loaded cells isn't a modification
+        assert!(document.edit_cells(0, text_to_matrix("hallo\n")));
+        assert!(document.is_modified(0));
+        assert!(document.amend_cells(0, text_to_matrix("hello\n")));
+        assert!(!document.is_modified(0));
+    }
+
+    #[test]
+    fn test_save_records_versions_and_region_tags() {
+        let dir = std::env::temp_dir().join(format!("chonker_document_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pdf_path = dir.join("report.pdf");
+        let mut document = DocumentModel::new(pdf_path.clone());
+        document.insert_page(0, char_matrix("hello\n"));
+        document.edit_cells(0, text_to_matrix("jello\n"));
+        document.set_region_label(0, 0, Some("greeting".to_string()));
+        document.set_region_tag(0, 0, Some(RegionTag::Title));
+
+        assert_eq!(document.save(0).unwrap(), 1);
+        assert!(!document.is_modified(0));
+
+        // Undo leaves the saved state and redo returns to it
+        assert!(document.undo(0));
+        assert!(document.is_modified(0));
+        assert!(document.redo(0));
+        assert!(!document.is_modified(0));
+
+        // Once an edit drops it from the redo stack, the saved state is gone
+        assert!(document.undo(0));
+        assert!(document.set_region_label(0, 0, None));
+        assert!(document.undo(0));
+        assert!(document.is_modified(0));
+        assert_eq!(list_matrix_versions(&pdf_path, 0).len(), 1);
+        assert_eq!(load_edit_log(&pdf_path).len(), 1);
+        assert_eq!(load_region_labels(&pdf_path)[&0][&0], "greeting");
+        assert_eq!(load_region_tags(&pdf_path)[&0][&0], RegionTag::Title);
+        assert!(document.save(1).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,296 @@
+//! C ABI over the engine, declared in `include/chonker_core.h`.
+//!
+//! Matrices and documents are opaque handles owned by the caller until passed
+//! to their `_free` function; strings returned by the library are released
+//! with `chonker_string_free`. Functions returning `int32_t` give 0 on success
+//! or the failure's `ChonkerError::exit_code`, with its message available from
+//! `chonker_last_error` on the same thread.
+
+use crate::{extract_document, render_export, CharacterMatrix, ChonkerError, ExportFormat};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::ptr;
+
+/// Pages of one `chonker_extract_document` call, in page order.
+pub struct ChonkerDocument {
+    pages: Vec<(usize, Result<CharacterMatrix, ChonkerError>)>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn to_c_string(text: String) -> CString {
+    CString::new(text.replace('\0', " ")).unwrap_or_default()
+}
+
+fn fail(error: &ChonkerError) -> i32 {
+    LAST_ERROR.with(|last| *last.borrow_mut() = to_c_string(error.to_string()));
+    error.exit_code()
+}
+
+/// Runs `f`, turning a panic into an error since unwinding across the C
+/// boundary would abort the host.
+fn guard<T>(what: &str, f: impl FnOnce() -> Result<T, ChonkerError>) -> Result<T, ChonkerError> {
+    std::panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(ChonkerError::Other(format!("{} panicked", what))))
+}
+
+/// Runs `future` to completion on a fresh runtime, under `guard`.
+fn run<F: Future>(future: F) -> Result<F::Output, ChonkerError> {
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| ChonkerError::Other(format!("Could not start runtime: {}", e)))?;
+    guard("Extraction", || Ok(runtime.block_on(future)))
+}
+
+unsafe fn str_arg<'a>(value: *const c_char, name: &str) -> Result<&'a str, ChonkerError> {
+    if value.is_null() {
+        return Err(ChonkerError::Other(format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| ChonkerError::Other(format!("{} is not valid UTF-8", name)))
+}
+
+/// Message of the last failure on this thread, or an empty string. Valid until
+/// the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn chonker_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// Extracts one 0-based page with the document's saved overrides and labels.
+///
+/// # Safety
+/// `pdf_path` must be a NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_extract_page(
+    pdf_path: *const c_char,
+    page_index: usize,
+    out: *mut *mut CharacterMatrix,
+) -> i32 {
+    let Some(page_number) = page_index.checked_add(1) else {
+        return fail(&ChonkerError::Other(format!("page_index {} is out of range", page_index)));
+    };
+    let mut document = ptr::null_mut();
+    let spec = to_c_string(page_number.to_string());
+    let code = chonker_extract_document(pdf_path, spec.as_ptr(), &mut document);
+    if code != 0 {
+        return code;
+    }
+    let document = Box::from_raw(document);
+    match document.pages.into_iter().next() {
+        Some((_, Ok(char_matrix))) => {
+            *out = Box::into_raw(Box::new(char_matrix));
+            0
+        }
+        Some((_, Err(e))) => fail(&e),
+        None => fail(&ChonkerError::PageOutOfRange { page: page_index, total: 0 }),
+    }
+}
+
+/// Extracts the pages in `pages` (`"1-5,8"`; NULL or empty for all). Fails only
+/// when the document cannot be read; per-page failures are reported by
+/// `chonker_document_page`.
+///
+/// # Safety
+/// `pdf_path` must be a NUL-terminated string, `pages` NULL or one, and `out`
+/// a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_extract_document(
+    pdf_path: *const c_char,
+    pages: *const c_char,
+    out: *mut *mut ChonkerDocument,
+) -> i32 {
+    let result = (|| {
+        let pdf_path = PathBuf::from(str_arg(pdf_path, "pdf_path")?);
+        let pages = if pages.is_null() { "" } else { str_arg(pages, "pages")? };
+        run(extract_document(pdf_path, pages))?
+    })();
+    match result {
+        Ok(pages) => {
+            *out = Box::into_raw(Box::new(ChonkerDocument { pages }));
+            0
+        }
+        Err(e) => fail(&e),
+    }
+}
+
+/// Number of pages in `document`.
+///
+/// # Safety
+/// `document` must come from `chonker_extract_document`.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_document_len(document: *const ChonkerDocument) -> usize {
+    let document = &*document;
+    document.pages.len()
+}
+
+/// The `index`th extracted page: its 0-based page number and, on success, its
+/// matrix, which stays owned by `document`.
+///
+/// # Safety
+/// `document` must come from `chonker_extract_document`; `page_index` and
+/// `matrix` must be valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_document_page(
+    document: *const ChonkerDocument,
+    index: usize,
+    page_index: *mut usize,
+    matrix: *mut *const CharacterMatrix,
+) -> i32 {
+    let document = &*document;
+    let Some((page, result)) = document.pages.get(index) else {
+        return fail(&ChonkerError::PageOutOfRange { page: index, total: document.pages.len() });
+    };
+    *page_index = *page;
+    match result {
+        Ok(char_matrix) => {
+            *matrix = char_matrix;
+            0
+        }
+        Err(e) => {
+            *matrix = ptr::null();
+            fail(e)
+        }
+    }
+}
+
+/// # Safety
+/// `document` must come from `chonker_extract_document` or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_document_free(document: *mut ChonkerDocument) {
+    if !document.is_null() {
+        drop(Box::from_raw(document));
+    }
+}
+
+/// Matrix width in cells.
+///
+/// # Safety
+/// `matrix` must be a matrix handle.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_matrix_width(matrix: *const CharacterMatrix) -> usize {
+    (*matrix).width
+}
+
+/// Matrix height in cells.
+///
+/// # Safety
+/// `matrix` must be a matrix handle.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_matrix_height(matrix: *const CharacterMatrix) -> usize {
+    (*matrix).height
+}
+
+/// Serializes `matrix` as `txt`, `json`, `alto` or `reading`, the formats of
+/// `chonker export`. Returns NULL on failure.
+///
+/// # Safety
+/// `matrix` must be a matrix handle and `format` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_matrix_export(
+    matrix: *const CharacterMatrix,
+    page_index: usize,
+    format: *const c_char,
+) -> *mut c_char {
+    let result = str_arg(format, "format").and_then(|format| {
+        let format: ExportFormat = format.parse().map_err(ChonkerError::from_anyhow)?;
+        guard("Export", || render_export(&*matrix, page_index, format).map_err(ChonkerError::from_anyhow))
+    });
+    match result {
+        Ok(text) => to_c_string(text).into_raw(),
+        Err(e) => {
+            fail(&e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `matrix` must come from `chonker_extract_page` or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_matrix_free(matrix: *mut CharacterMatrix) {
+    if !matrix.is_null() {
+        drop(Box::from_raw(matrix));
+    }
+}
+
+/// # Safety
+/// `text` must come from this library or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn chonker_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(chonker_last_error()) }.to_str().unwrap().to_string()
+    }
+
+    fn c_string(text: &str) -> CString {
+        CString::new(text).unwrap()
+    }
+
+    #[test]
+    fn test_extraction_errors_leave_nothing_to_free() {
+        let mut document = ptr::null_mut();
+        let code = unsafe { chonker_extract_document(ptr::null(), ptr::null(), &mut document) };
+        assert_eq!((code, last_error().as_str()), (1, "pdf_path is NULL"));
+        assert!(document.is_null());
+
+        let (path, spec) = (c_string("missing.pdf"), c_string("3-1"));
+        let code = unsafe { chonker_extract_document(path.as_ptr(), spec.as_ptr(), &mut document) };
+        assert_eq!((code, last_error().as_str()), (1, "Invalid page range: 3-1"));
+        assert!(document.is_null());
+        unsafe { chonker_document_free(document) };
+
+        let mut char_matrix = ptr::null_mut();
+        let code = unsafe { chonker_extract_page(path.as_ptr(), usize::MAX, &mut char_matrix) };
+        assert_eq!(code, 1);
+        assert!(last_error().ends_with("is out of range"));
+        assert!(char_matrix.is_null());
+        unsafe { chonker_matrix_free(char_matrix) };
+    }
+
+    #[test]
+    fn test_document_pages_report_errors_and_export() {
+        let mut char_matrix = CharacterMatrix::new(3, 1);
+        char_matrix.matrix[0] = vec!['a', 'b', 'c'];
+        let pages = vec![(0, Ok(char_matrix)), (4, Err(ChonkerError::NoText { page: 4 }))];
+        let document = Box::into_raw(Box::new(ChonkerDocument { pages }));
+        let (txt, docx) = (c_string("txt"), c_string("docx"));
+
+        unsafe {
+            assert_eq!(chonker_document_len(document), 2);
+            let (mut page, mut handle) = (usize::MAX, ptr::null());
+            assert_eq!(chonker_document_page(document, 0, &mut page, &mut handle), 0);
+            assert_eq!(page, 0);
+            assert_eq!((chonker_matrix_width(handle), chonker_matrix_height(handle)), (3, 1));
+            let text = chonker_matrix_export(handle, 0, txt.as_ptr());
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "abc\n");
+            chonker_string_free(text);
+            assert!(chonker_matrix_export(handle, 0, docx.as_ptr()).is_null());
+            assert_eq!(last_error(), "Unknown export format: docx");
+            assert!(chonker_matrix_export(handle, 0, ptr::null()).is_null());
+            assert_eq!(last_error(), "format is NULL");
+
+            // A failed page keeps its number but has no matrix
+            assert_eq!(chonker_document_page(document, 1, &mut page, &mut handle), 65);
+            assert_eq!((page, last_error().as_str()), (4, "No text found on page 5"));
+            assert!(handle.is_null());
+            assert_eq!(chonker_document_page(document, 2, &mut page, &mut handle), 64);
+
+            chonker_document_free(document);
+            chonker_string_free(ptr::null_mut());
+        }
+    }
+}
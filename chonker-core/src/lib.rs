@@ -0,0 +1,5144 @@
+//! # chonker-core: the Chonker character-matrix engine
+//!
+//! PDF pages become grids of characters that keep the page layout, with the
+//! text regions, reading order and labels found on them. The GUI
+//! (`chonker5.rs`), the TUI, the Bevy editor and the `chonker` CLI are front
+//! ends over this crate, sharing one `DocumentModel` of the open document;
+//! nothing in it depends on a windowing toolkit.
+
+use anyhow::Result;
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+// std's clock panics in the browser
+#[cfg(target_arch = "wasm32")]
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+mod document;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+
+pub use document::{update_page_entry, DocumentEvent, DocumentModel, DocumentPage};
+
+// ============= CONFIG =============
+/// Path of a per-user config file under `CHONKER5_CONFIG_DIR` (default: `.`).
+pub fn config_path(file_name: &str) -> PathBuf {
+    std::env::var_os("CHONKER5_CONFIG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(file_name)
+}
+
+/// `chonker.toml` under `CHONKER5_CONFIG_DIR`: settings shared by the GUI
+/// and the TUI. Every key is optional:
+///
+/// ```toml
+/// theme = "solarized"          # a theme preset
+/// backend = "stext"            # mutool, stext or pdfium; auto when unset
+/// cache_dir = "/tmp/chonker"   # below CHONKER5_CACHE_DIR, above XDG
+/// layout_model = "doclaynet.onnx"  # detector for the `onnx` feature
+/// min_confidence = 0.4         # hide less certain regions; 0 shows all
+///
+/// [char_size]                  # cell size for pages without an override
+/// char_width = 6.5
+/// char_height = 11.0
+///
+/// [keymap]                     # action = chord; see each front end's actions
+/// save = "Ctrl+W"
+/// toggle_regions = "F2"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChonkerConfig {
+    pub theme: Option<String>,
+    #[serde(deserialize_with = "deserialize_backend")]
+    pub backend: Option<ExtractionBackend>,
+    pub char_size: Option<CharDimensions>,
+    pub cache_dir: Option<PathBuf>,
+    /// A DocLayNet-trained YOLO detector exported to ONNX.
+    pub layout_model: Option<PathBuf>,
+    /// Regions below this confidence are left out of overlays, region lists
+    /// and exports.
+    pub min_confidence: Option<f32>,
+    pub keymap: BTreeMap<String, String>,
+}
+
+impl ChonkerConfig {
+    pub fn path() -> PathBuf {
+        config_path("chonker.toml")
+    }
+
+    /// A missing file is the defaults; a malformed one is logged and ignored.
+    pub fn load() -> Self {
+        let Ok(text) = std::fs::read_to_string(Self::path()) else {
+            return Self::default();
+        };
+        Self::parse(&text).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring {}: {}", Self::path().display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+}
+
+/// A backend name as `ExtractionBackend::from_str` reads it, or `auto`.
+fn deserialize_backend<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<ExtractionBackend>, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "auto" => Ok(None),
+        name => name.parse().map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// A key with modifiers, written like `Ctrl+Shift+S`, `Alt+,` or `PageDown`.
+/// Key names are compared lowercased, with egui's longer names folded into
+/// the short ones (`Escape` is `esc`, `ArrowUp` is `up`, `Comma` is `,`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyChord {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub key: String,
+}
+
+impl KeyChord {
+    pub fn new(ctrl: bool, alt: bool, shift: bool, key: &str) -> Self {
+        Self { ctrl, alt, shift, key: normalize_key_name(key) }
+    }
+}
+
+fn normalize_key_name(key: &str) -> String {
+    let key = key.to_lowercase();
+    match key.as_str() {
+        "escape" => "esc",
+        "return" => "enter",
+        "arrowup" => "up",
+        "arrowdown" => "down",
+        "arrowleft" => "left",
+        "arrowright" => "right",
+        "comma" => ",",
+        "period" => ".",
+        "minus" => "-",
+        "plusequals" | "equals" => "=",
+        "slash" => "/",
+        "space" => " ",
+        name if name.len() == 4 && name.starts_with("num") => &name[3..],
+        _ => return key,
+    }
+    .to_string()
+}
+
+impl std::str::FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // The key itself may be `+`, as in `Ctrl++`
+        let (modifiers, key) = match s.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        if key.trim().is_empty() {
+            return Err(anyhow::anyhow!("Key chord without a key: {}", s));
+        }
+        let mut chord = KeyChord::new(false, false, false, key.trim());
+        for modifier in modifiers.split('+').map(str::trim).filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => chord.ctrl = true,
+                "alt" | "option" => chord.alt = true,
+                "shift" => chord.shift = true,
+                other => return Err(anyhow::anyhow!("Unknown modifier {} in {}", other, s)),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+/// A front end's default shortcuts with `chonker.toml`'s `[keymap]` applied.
+/// Front ends keep matching their default chords: `remap` turns a pressed
+/// chord into the default chord of the action it is bound to.
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    /// (configured chord, the action's default chord)
+    rebound: Vec<(KeyChord, KeyChord)>,
+}
+
+impl Keymap {
+    /// `defaults` pairs action names with their chords. Bindings for unknown
+    /// actions and unreadable chords are logged and skipped.
+    pub fn new(defaults: &[(&str, &str)], bindings: &BTreeMap<String, String>) -> Self {
+        let mut rebound = Vec::new();
+        for (action, chord) in bindings {
+            let Some((_, default)) = defaults.iter().find(|(name, _)| name == action) else {
+                tracing::warn!("Ignoring key binding for unknown action {}", action);
+                continue;
+            };
+            match (chord.parse::<KeyChord>(), default.parse::<KeyChord>()) {
+                (Ok(chord), Ok(default)) => rebound.push((chord, default)),
+                (Err(e), _) | (_, Err(e)) => tracing::warn!("Ignoring key binding for {}: {}", action, e),
+            }
+        }
+        Self { rebound }
+    }
+
+    /// The chord the front end should act on: the default chord of a rebound
+    /// action, `pressed` itself when it is not bound, or `None` when it is a
+    /// default that was rebound to another key.
+    pub fn remap(&self, pressed: KeyChord) -> Option<KeyChord> {
+        if let Some((_, default)) = self.rebound.iter().find(|(chord, _)| *chord == pressed) {
+            return Some(default.clone());
+        }
+        if self.rebound.iter().any(|(_, default)| *default == pressed) {
+            return None;
+        }
+        Some(pressed)
+    }
+}
+
+// ============= ERRORS =============
+/// Failures of extraction and rendering, kept apart so the GUI can show each
+/// kind differently and the CLI can exit with a distinct code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChonkerError {
+    /// The PDFium library could not be loaded.
+    PdfiumBind(String),
+    PageOutOfRange { page: usize, total: usize },
+    NoText { page: usize },
+    RenderFailed(String),
+    Timeout { secs: u64 },
+    /// `mutool` (or another helper program) is missing or failed.
+    ExternalTool { tool: &'static str, message: String },
+    Other(String),
+}
+
+impl std::fmt::Display for ChonkerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChonkerError::PdfiumBind(e) => write!(f, "Failed to bind pdfium: {}", e),
+            ChonkerError::PageOutOfRange { page, total } => {
+                write!(f, "Page {} out of range (document has {} pages)", page + 1, total)
+            }
+            ChonkerError::NoText { page } => write!(f, "No text found on page {}", page + 1),
+            ChonkerError::RenderFailed(e) => write!(f, "Failed to render page: {}", e),
+            ChonkerError::Timeout { secs } => write!(f, "Extraction timed out after {}s", secs),
+            ChonkerError::ExternalTool { tool, message } => write!(f, "{}: {}", tool, message),
+            ChonkerError::Other(e) => f.write_str(e),
+        }
+    }
+}
+
+impl std::error::Error for ChonkerError {}
+
+impl ChonkerError {
+    /// Recovers the `ChonkerError` inside an engine error, if there is one.
+    pub fn from_anyhow(error: anyhow::Error) -> Self {
+        error
+            .downcast::<ChonkerError>()
+            .unwrap_or_else(|e| ChonkerError::Other(e.to_string()))
+    }
+
+    /// Process exit code for the CLI, following BSD `sysexits.h`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ChonkerError::PageOutOfRange { .. } => 64, // EX_USAGE
+            ChonkerError::NoText { .. } => 65,         // EX_DATAERR
+            ChonkerError::PdfiumBind(_) | ChonkerError::ExternalTool { .. } => 69, // EX_UNAVAILABLE
+            ChonkerError::RenderFailed(_) => 70,       // EX_SOFTWARE
+            ChonkerError::Timeout { .. } => 75,        // EX_TEMPFAIL
+            ChonkerError::Other(_) => 1,
+        }
+    }
+
+    /// What the user can do about it, shown under the message.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ChonkerError::PdfiumBind(_) => Some("Install libpdfium or place it in ./lib"),
+            ChonkerError::ExternalTool { tool: "ferrules", .. } => Some("Build ferrules or put it on PATH"),
+            ChonkerError::ExternalTool { tool: "layout model", .. } => {
+                Some("Set layout_model in chonker.toml to a DocLayNet ONNX detector")
+            }
+            ChonkerError::ExternalTool { .. } => Some("Install mupdf-tools so that `mutool` is on PATH"),
+            ChonkerError::NoText { .. } => Some("The page may be a scan without a text layer"),
+            ChonkerError::Timeout { .. } => Some("Raise timeout_secs in chonker5_limits.json"),
+            _ => None,
+        }
+    }
+}
+
+pub fn bind_pdfium() -> Result<Pdfium, ChonkerError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let bindings = Pdfium::bind_to_system_library()
+        .or_else(|_| Pdfium::bind_to_library("./lib/libpdfium.dylib"))
+        .or_else(|_| Pdfium::bind_to_library("/usr/local/lib/libpdfium.dylib"));
+    // In the browser the page loads pdfium.js and hands it to pdfium-render first
+    #[cfg(target_arch = "wasm32")]
+    let bindings = Pdfium::bind_to_system_library();
+    bindings.map(Pdfium::new).map_err(|e| ChonkerError::PdfiumBind(e.to_string()))
+}
+
+/// Where the engine reads a PDF from. The browser build has no file system
+/// and always passes bytes.
+#[derive(Debug, Clone, Copy)]
+pub enum PdfSource<'a> {
+    File(&'a Path),
+    Bytes(&'a [u8]),
+}
+
+impl<'a> PdfSource<'a> {
+    fn load(&self, pdfium: &'a Pdfium) -> Result<PdfDocument<'a>> {
+        match *self {
+            #[cfg(not(target_arch = "wasm32"))]
+            PdfSource::File(path) => Ok(pdfium.load_pdf_from_file(path, None)?),
+            #[cfg(target_arch = "wasm32")]
+            PdfSource::File(path) => Err(anyhow::anyhow!("Cannot open {} in the browser", path.display())),
+            PdfSource::Bytes(bytes) => Ok(pdfium.load_pdf_from_byte_slice(bytes, None)?),
+        }
+    }
+}
+
+/// "page 3 failed" / "pages 3, 7 failed" for per-page extraction errors.
+pub fn failed_pages_summary<E>(failures: &std::collections::BTreeMap<usize, E>) -> String {
+    let pages: Vec<String> = failures.keys().map(|page| (page + 1).to_string()).collect();
+    match pages.len() {
+        1 => format!("page {} failed", pages[0]),
+        _ => format!("pages {} failed", pages.join(", ")),
+    }
+}
+
+// ============= CHARACTER MATRIX ENGINE =============
+/// Character cell size in PDF points.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CharDimensions {
+    pub char_width: f32,
+    pub char_height: f32,
+}
+
+/// Per-page character dimension overrides, persisted next to the PDF as
+/// `<name>.char_overrides.json`.
+pub type CharOverrides = HashMap<usize, CharDimensions>;
+
+fn char_overrides_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("char_overrides.json")
+}
+
+pub fn load_char_overrides(pdf_path: &Path) -> CharOverrides {
+    std::fs::read_to_string(char_overrides_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_char_overrides(pdf_path: &Path, overrides: &CharOverrides) -> Result<()> {
+    let path = char_overrides_path(pdf_path);
+    if overrides.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    std::fs::write(path, serde_json::to_string_pretty(overrides)?)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterMatrix {
+    pub width: usize,
+    pub height: usize,
+    /// The cells, row by row; empty while the page is compacted, so it is
+    /// read through `rows` or `cell` and written through `rows_mut`.
+    matrix: Vec<Vec<char>>,
+    pub text_regions: Vec<TextRegion>,
+    pub original_text: Vec<String>,
+    pub char_width: f32,
+    pub char_height: f32,
+    #[serde(default)]
+    pub warnings: Vec<ExtractionWarning>,
+    /// Fixed-pitch classification of the source glyphs; `None` when the
+    /// backend has no glyph positions or too few glyphs to judge.
+    #[serde(default)]
+    pub monospace: Option<MonospaceDetection>,
+    /// User-defined reading sequence as region ids; `None` reads top to bottom.
+    #[serde(default)]
+    pub reading_order: Option<Vec<usize>>,
+    /// Which extractor produced the matrix, when known.
+    #[serde(default)]
+    pub backend: Option<ExtractionBackend>,
+    /// Text runs while the page is stored compactly (see `compact`); `matrix`
+    /// is empty until `ensure_dense` rebuilds it for editing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse: Option<SparseMatrix>,
+    /// `extraction_fingerprint` of the settings the page was extracted and
+    /// segmented with; 0 when extracted outside them (ferrules, the layout
+    /// model).
+    #[serde(default)]
+    pub settings_fingerprint: u64,
+    /// What the extractor decided while laying the page out.
+    #[serde(default)]
+    pub layout: LayoutNotes,
+}
+
+/// How an extractor laid a page out, kept to explain the result.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LayoutNotes {
+    /// Font size in points the cell size came from: the modal size for
+    /// PDFium, the median for stext. `None` when an override, a detected
+    /// pitch or mutool's own layout set the cell.
+    pub cell_font_size: Option<f32>,
+    /// Text columns, as the cell several rows of text start at.
+    pub columns: Vec<usize>,
+    /// Glyphs that landed on an occupied cell: overwritten by PDFium,
+    /// moved right by stext.
+    pub collisions: usize,
+}
+
+/// Rows that a text column must start on, at least.
+const TEXT_COLUMN_MIN_ROWS: usize = 3;
+
+/// Cells where text starts (at a line's start or after a gap of two or
+/// more blank cells) on enough rows to count as a column: three, or a fifth
+/// of the rows holding text if that's more. Starts a cell apart, as with
+/// right-aligned figures, make one column at its most common start.
+pub fn detect_text_columns(rows: &[Vec<char>]) -> Vec<usize> {
+    let mut starts: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut text_rows = 0;
+    for row in rows {
+        let mut blank_run = 2;
+        let mut any = false;
+        for (col, ch) in row.iter().enumerate() {
+            if ch.is_whitespace() {
+                blank_run += 1;
+                continue;
+            }
+            if blank_run >= 2 {
+                *starts.entry(col).or_default() += 1;
+            }
+            blank_run = 0;
+            any = true;
+        }
+        text_rows += usize::from(any);
+    }
+    let min_rows = TEXT_COLUMN_MIN_ROWS.max(text_rows / 5);
+    // (last start, most common start, its rows, rows in all)
+    let mut columns: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for (col, count) in starts {
+        match columns.last_mut() {
+            Some(column) if col - column.0 <= 1 => {
+                column.0 = col;
+                column.3 += count;
+                if count > column.2 {
+                    (column.1, column.2) = (col, count);
+                }
+            }
+            _ => columns.push((col, col, count, count)),
+        }
+    }
+    columns.into_iter().filter(|column| column.3 >= min_rows).map(|column| column.1).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtractionBackend {
+    Mutool,
+    Pdfium,
+    /// mutool's structured text, placed at the glyphs' own coordinates.
+    #[serde(rename = "stext")]
+    MutoolStext,
+}
+
+impl ExtractionBackend {
+    pub fn label(self) -> &'static str {
+        match self {
+            ExtractionBackend::Mutool => "mutool",
+            ExtractionBackend::Pdfium => "PDFium",
+            ExtractionBackend::MutoolStext => "mutool stext",
+        }
+    }
+}
+
+impl std::str::FromStr for ExtractionBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mutool" => Ok(ExtractionBackend::Mutool),
+            "pdfium" => Ok(ExtractionBackend::Pdfium),
+            "stext" | "mutool-stext" => Ok(ExtractionBackend::MutoolStext),
+            other => Err(anyhow::anyhow!("Unknown backend: {}", other)),
+        }
+    }
+}
+
+/// Non-fatal problems noticed during extraction that affect placement
+/// accuracy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExtractionWarning {
+    /// Glyph positions were spread evenly across a segment's width, but the
+    /// real glyphs drift from those positions by more than half a cell.
+    AveragedCharWidths {
+        page: usize,
+        segments: usize,
+        max_drift_pts: f32,
+        sample: String,
+        /// Ids of the regions holding misplaced glyphs; their confidence is
+        /// lowered by how far the glyphs drift.
+        #[serde(default)]
+        regions: Vec<usize>,
+    },
+    /// An `ExtractionLimits` bound was hit; the matrix holds what was
+    /// extracted before it.
+    Truncated { page: usize, reason: String },
+}
+
+impl std::fmt::Display for ExtractionWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractionWarning::AveragedCharWidths {
+                page,
+                segments,
+                max_drift_pts,
+                sample,
+                regions,
+            } => write!(
+                f,
+                "Page {}: averaged character widths misplace glyphs in {} segment(s) across {} region(s), up to {:.1}pt (e.g. \"{}\")",
+                page + 1,
+                segments,
+                regions.len(),
+                max_drift_pts,
+                sample
+            ),
+            ExtractionWarning::Truncated { page, reason } => {
+                write!(f, "Page {}: partial result - {}", page + 1, reason)
+            }
+        }
+    }
+}
+
+impl ExtractionWarning {
+    /// Ids of the regions the warning applies to; empty for page-wide ones.
+    pub fn regions(&self) -> &[usize] {
+        match self {
+            ExtractionWarning::AveragedCharWidths { regions, .. } => regions,
+            ExtractionWarning::Truncated { .. } => &[],
+        }
+    }
+}
+
+/// Collects width-averaging drift per page while segments are split into
+/// characters.
+#[derive(Default)]
+struct WidthDriftTracker {
+    pages: std::collections::BTreeMap<usize, (usize, f32, String)>,
+}
+
+impl WidthDriftTracker {
+    /// Returns the drift if it misplaces glyphs, 0 when it stays within
+    /// half a cell.
+    fn record(&mut self, page: usize, drift: f32, avg_char_width: f32, text: &str) -> f32 {
+        if drift <= avg_char_width * 0.5 {
+            return 0.0;
+        }
+        let entry = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| (0, 0.0, text.chars().take(40).collect()));
+        entry.0 += 1;
+        entry.1 = entry.1.max(drift);
+        drift
+    }
+
+    /// One warning per page, naming the regions whose confidence the drift
+    /// lowered.
+    fn into_warnings(self, regions: &[TextRegion]) -> Vec<ExtractionWarning> {
+        let drifted: Vec<usize> =
+            regions.iter().filter(|region| region.confidence < 1.0).map(|region| region.region_id).collect();
+        self.pages
+            .into_iter()
+            .map(|(page, (segments, max_drift_pts, sample))| {
+                ExtractionWarning::AveragedCharWidths {
+                    page,
+                    segments,
+                    max_drift_pts,
+                    sample,
+                    regions: drifted.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Confidence of a glyph that drifts `drift_pts` from its averaged position:
+/// each cell of drift costs a fifth, down to 0.2.
+fn drift_confidence(drift_pts: f32, char_width: f32) -> f32 {
+    if drift_pts <= 0.0 {
+        return 1.0;
+    }
+    (1.0 - 0.2 * drift_pts / char_width).clamp(0.2, 0.95)
+}
+
+impl CharacterMatrix {
+    pub fn new(width: usize, height: usize) -> Self {
+        let matrix = vec![vec![' '; width]; height];
+        Self {
+            width,
+            height,
+            matrix,
+            text_regions: Vec::new(),
+            original_text: Vec::new(),
+            char_width: 7.2,
+            char_height: 12.0,
+            warnings: Vec::new(),
+            monospace: None,
+            reading_order: None,
+            backend: None,
+            sparse: None,
+            settings_fingerprint: 0,
+            layout: LayoutNotes::default(),
+        }
+    }
+
+    /// Swaps the dense cells for text runs. Cached pages that are not being
+    /// edited are mostly blank, so this is where their memory goes.
+    pub fn compact(&mut self) {
+        if self.sparse.is_none() {
+            self.sparse = Some(SparseMatrix::from_dense(&self.matrix));
+            self.matrix = Vec::new();
+        }
+    }
+
+    /// Rebuilds the dense cells of a compacted page.
+    pub fn ensure_dense(&mut self) {
+        if let Some(sparse) = self.sparse.take() {
+            self.matrix = sparse.to_dense(self.height);
+        }
+    }
+
+    /// The cells row by row, whichever way the page is stored; a compacted
+    /// page is expanded into a copy.
+    pub fn rows(&self) -> Cow<'_, [Vec<char>]> {
+        match &self.sparse {
+            Some(sparse) => Cow::Owned(sparse.to_dense(self.height)),
+            None => Cow::Borrowed(&self.matrix),
+        }
+    }
+
+    /// The matrix with its cells dense, copied only when the page is
+    /// compacted.
+    pub fn dense(&self) -> Cow<'_, CharacterMatrix> {
+        match self.sparse {
+            Some(_) => {
+                let mut char_matrix = self.clone();
+                char_matrix.ensure_dense();
+                Cow::Owned(char_matrix)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    /// The dense cells for editing; a compacted page is expanded first.
+    pub fn rows_mut(&mut self) -> &mut Vec<Vec<char>> {
+        self.ensure_dense();
+        &mut self.matrix
+    }
+
+    /// One cell, whichever way the page is stored.
+    pub fn cell(&self, row: usize, col: usize) -> char {
+        match &self.sparse {
+            Some(sparse) => sparse.get(row, col),
+            None => self.matrix.get(row).and_then(|r| r.get(col)).copied().unwrap_or(' '),
+        }
+    }
+
+    /// The warnings that name `region_id`.
+    pub fn region_warnings(&self, region_id: usize) -> impl Iterator<Item = &ExtractionWarning> {
+        self.warnings.iter().filter(move |warning| warning.regions().contains(&region_id))
+    }
+
+    /// Swaps in regions from another segmentation, pointing region warnings
+    /// at the new regions that hold the old ones.
+    pub fn replace_regions(&mut self, regions: Vec<TextRegion>) {
+        let old = std::mem::replace(&mut self.text_regions, regions);
+        for warning in &mut self.warnings {
+            if let ExtractionWarning::AveragedCharWidths { regions: ids, .. } = warning {
+                let anchors: Vec<(usize, usize)> =
+                    old.iter().filter(|r| ids.contains(&r.region_id)).map(|r| (r.bbox.x, r.bbox.y)).collect();
+                *ids = self
+                    .text_regions
+                    .iter()
+                    .filter(|r| anchors.iter().any(|&(x, y)| r.bbox.contains(x, y)))
+                    .map(|r| r.region_id)
+                    .collect();
+            }
+        }
+    }
+
+    /// A copy without the regions below `min_confidence`, for exports that
+    /// honour the confidence filter. The cells are kept.
+    pub fn without_low_confidence(&self, min_confidence: f32) -> CharacterMatrix {
+        let mut char_matrix = self.clone();
+        char_matrix.text_regions.retain(|region| region.confidence >= min_confidence);
+        char_matrix
+    }
+
+    /// Returns the sub-matrix inside `bbox`. Regions fully inside the
+    /// rectangle are kept and re-based onto the cropped coordinates.
+    pub fn crop(&self, bbox: &CharBBox) -> CharacterMatrix {
+        let matrix = crop_matrix(&self.rows(), bbox);
+        let text_regions = self
+            .text_regions
+            .iter()
+            .filter(|r| {
+                r.bbox.x >= bbox.x
+                    && r.bbox.y >= bbox.y
+                    && r.bbox.x + r.bbox.width <= bbox.x + bbox.width
+                    && r.bbox.y + r.bbox.height <= bbox.y + bbox.height
+            })
+            .cloned()
+            .map(|mut r| {
+                r.bbox.x -= bbox.x;
+                r.bbox.y -= bbox.y;
+                r
+            })
+            .collect();
+
+        CharacterMatrix {
+            width: matrix.first().map_or(0, |row| row.len()),
+            height: matrix.len(),
+            matrix,
+            text_regions,
+            original_text: self.original_text.clone(),
+            char_width: self.char_width,
+            char_height: self.char_height,
+            warnings: self.warnings.clone(),
+            monospace: self.monospace.clone(),
+            reading_order: self.reading_order.clone(),
+            backend: self.backend,
+            sparse: None,
+            settings_fingerprint: 0,
+            layout: self.layout.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextRegion {
+    pub bbox: CharBBox,
+    pub confidence: f32,
+    pub text_content: String,
+    pub region_id: usize,
+    /// Set when the source glyphs are italic/oblique. Some documents use
+    /// italics to mark struck or superseded text, so exports must keep it.
+    #[serde(default)]
+    pub is_italic: bool,
+    /// User-assigned name, shown instead of `R<n>` when set.
+    #[serde(default)]
+    pub label: Option<String>,
+    /// User-assigned semantic role, exported with JSON and ALTO.
+    #[serde(default)]
+    pub tag: Option<RegionTag>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionTag {
+    Title,
+    Paragraph,
+    Table,
+    Figure,
+    Footer,
+}
+
+impl RegionTag {
+    pub const ALL: [RegionTag; 5] = [
+        RegionTag::Title,
+        RegionTag::Paragraph,
+        RegionTag::Table,
+        RegionTag::Figure,
+        RegionTag::Footer,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegionTag::Title => "title",
+            RegionTag::Paragraph => "paragraph",
+            RegionTag::Table => "table",
+            RegionTag::Figure => "figure",
+            RegionTag::Footer => "footer",
+        }
+    }
+}
+
+impl TextRegion {
+    pub fn display_name(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => format!("R{}", self.region_id + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharBBox {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl CharBBox {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Parses an `x,y,w,h` cell rectangle as used by the `--region` option.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let parts: Vec<usize> = spec
+            .split(',')
+            .map(|p| p.trim().parse::<usize>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| anyhow::anyhow!("Invalid region '{}': {}", spec, e))?;
+        match parts.as_slice() {
+            &[x, y, width, height] if width > 0 && height > 0 => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(anyhow::anyhow!(
+                "Invalid region '{}': expected x,y,w,h with non-zero size",
+                spec
+            )),
+        }
+    }
+}
+
+/// Copies the cells inside `bbox`, clamped to the matrix bounds.
+pub fn crop_matrix(matrix: &[Vec<char>], bbox: &CharBBox) -> Vec<Vec<char>> {
+    matrix
+        .iter()
+        .skip(bbox.y)
+        .take(bbox.height)
+        .map(|row| row.iter().skip(bbox.x).take(bbox.width).copied().collect())
+        .collect()
+}
+
+/// Text of the matrix cells under `bbox`, one line per row.
+pub fn region_text(matrix: &[Vec<char>], bbox: &CharBBox) -> String {
+    crop_matrix(matrix, bbox)
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[derive(Debug, Clone)]
+struct PreciseTextObject {
+    text: String,
+    bbox: PDFBBox,
+    font_size: f32,
+    is_italic: bool,
+    /// How far width averaging misplaces the glyph's segment, in points;
+    /// 0 within half a cell.
+    drift_pts: f32,
+}
+
+#[derive(Debug, Clone)]
+struct PDFBBox {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+}
+
+/// Places the glyphs whose origin lies inside `area` on a `cols` x `rows`
+/// grid spanning it. A glyph landing on an occupied cell moves right to the
+/// next free one instead of overwriting it.
+fn layout_area_glyphs(
+    text_objects: &[PreciseTextObject],
+    area: &PDFBBox,
+    cols: usize,
+    rows: usize,
+) -> Vec<Vec<char>> {
+    let mut block = vec![vec![' '; cols]; rows];
+    if cols == 0 || rows == 0 {
+        return block;
+    }
+    let cell_width = (area.x1 - area.x0) / cols as f32;
+    let cell_height = (area.y1 - area.y0) / rows as f32;
+
+    for obj in text_objects {
+        let inside = obj.bbox.x0 >= area.x0
+            && obj.bbox.x0 < area.x1
+            && obj.bbox.y0 >= area.y0
+            && obj.bbox.y0 < area.y1;
+        let Some(ch) = obj.text.chars().next().filter(|c| !c.is_whitespace()) else {
+            continue;
+        };
+        if !inside {
+            continue;
+        }
+
+        let row = (((obj.bbox.y0 - area.y0) / cell_height) as usize).min(rows - 1);
+        let col = ((obj.bbox.x0 - area.x0) / cell_width) as usize;
+        if let Some(free) = (col..cols).find(|&c| block[row][c] == ' ') {
+            block[row][free] = ch;
+        }
+    }
+    block
+}
+
+/// The most common font size among the glyphs, rounded to a point.
+fn modal_font_size(text_objects: &[PreciseTextObject]) -> f32 {
+    let mut font_size_counts: HashMap<i32, usize> = HashMap::new();
+    for obj in text_objects {
+        let rounded_size = obj.font_size.round() as i32;
+        *font_size_counts.entry(rounded_size).or_insert(0) += 1;
+    }
+
+    font_size_counts
+        .iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(size, _)| *size as f32)
+        .unwrap_or(12.0)
+}
+
+/// Largest gap, in points, between where even width-averaging places a glyph
+/// and where the PDF actually draws it.
+fn segment_width_drift(segment: &PdfPageTextSegment, avg_char_width: f32) -> f32 {
+    let Ok(chars) = segment.chars() else {
+        return 0.0;
+    };
+
+    let mut averaged_x = segment.bounds().left().value;
+    let mut max_drift: f32 = 0.0;
+    for ch in chars.iter() {
+        let is_space = ch.unicode_char() == Some(' ');
+        if !is_space {
+            if let Ok(bounds) = ch.loose_bounds() {
+                max_drift = max_drift.max((bounds.left().value - averaged_x).abs());
+            }
+        }
+        averaged_x += if is_space {
+            avg_char_width * 0.5
+        } else {
+            avg_char_width
+        };
+    }
+    max_drift
+}
+
+/// A segment counts as italic when most of its visible glyphs either carry the
+/// italic font flag or come from a font whose name marks it as oblique.
+fn segment_is_italic(segment: &PdfPageTextSegment) -> bool {
+    let Ok(chars) = segment.chars() else {
+        return false;
+    };
+
+    let mut visible = 0;
+    let mut slanted = 0;
+    for ch in chars.iter() {
+        if ch.unicode_char().is_none_or(|c| c.is_whitespace()) {
+            continue;
+        }
+        visible += 1;
+        let font_name = ch.font_name().to_lowercase();
+        if ch.font_is_italic() || font_name.contains("italic") || font_name.contains("oblique") {
+            slanted += 1;
+        }
+    }
+
+    visible > 0 && slanted * 2 > visible
+}
+
+/// Per-extraction measurements gathered while walking PDFium text segments.
+#[derive(Default)]
+struct ExtractionStats {
+    drift: WidthDriftTracker,
+    glyphs: Vec<GlyphSample>,
+    /// Every visible glyph at its own position rather than its segment's
+    /// averaged one, for exact columns on fixed-pitch pages.
+    exact: Vec<PreciseTextObject>,
+    /// Width and height in points of the last page walked.
+    page_size: Option<(f32, f32)>,
+    /// Why the walk stopped early, if an extraction limit was hit.
+    truncated: Option<String>,
+}
+
+impl ExtractionStats {
+    /// Samples the segment's glyphs, placing each on the segment's row at
+    /// `top` (points from the top of the page).
+    fn sample_glyphs(&mut self, segment: &PdfPageTextSegment, top: f32, font_size: f32, is_italic: bool) {
+        let Ok(chars) = segment.chars() else {
+            return;
+        };
+        for ch in chars.iter() {
+            let Some(c) = ch.unicode_char().filter(|c| !c.is_whitespace()) else {
+                continue;
+            };
+            if let Ok(bounds) = ch.loose_bounds() {
+                let (x0, x1) = (bounds.left().value, bounds.right().value);
+                self.glyphs.push(GlyphSample {
+                    x0,
+                    width: x1 - x0,
+                    fixed_pitch: ch.font_is_fixed_pitch(),
+                });
+                self.exact.push(PreciseTextObject {
+                    text: c.to_string(),
+                    bbox: PDFBBox { x0, y0: top, x1, y1: top + font_size },
+                    font_size,
+                    is_italic,
+                    drift_pts: 0.0,
+                });
+            }
+        }
+    }
+}
+
+// ============= SPARSE STORAGE =============
+/// Blank gaps at least this wide split a row into separate runs.
+const SPARSE_RUN_GAP: usize = 4;
+
+/// A mostly-blank matrix stored as `(start column, text)` runs per row;
+/// blank cells cost nothing. Rows come back padded to `width`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparseMatrix {
+    pub width: usize,
+    pub rows: Vec<Vec<(usize, String)>>,
+}
+
+impl SparseMatrix {
+    pub fn from_dense(matrix: &[Vec<char>]) -> Self {
+        let width = matrix.iter().map(Vec::len).max().unwrap_or(0);
+        let rows = matrix
+            .iter()
+            .map(|row| {
+                let mut runs = Vec::new();
+                let mut col = 0;
+                while col < row.len() {
+                    if row[col] == ' ' {
+                        col += 1;
+                        continue;
+                    }
+                    // Extend the run until a wide enough gap or the row end
+                    let start = col;
+                    let mut end = col;
+                    let mut gap = 0;
+                    while col < row.len() && gap < SPARSE_RUN_GAP {
+                        if row[col] == ' ' {
+                            gap += 1;
+                        } else {
+                            gap = 0;
+                            end = col + 1;
+                        }
+                        col += 1;
+                    }
+                    runs.push((start, row[start..end].iter().collect()));
+                }
+                runs
+            })
+            .collect();
+        Self { width, rows }
+    }
+
+    /// Dense cells, `height` rows of `width` (rows past the stored ones are blank).
+    pub fn to_dense(&self, height: usize) -> Vec<Vec<char>> {
+        (0..height.max(self.rows.len()))
+            .map(|row| {
+                let mut cells = vec![' '; self.width];
+                for (start, text) in self.rows.get(row).into_iter().flatten() {
+                    for (cell, ch) in cells.iter_mut().skip(*start).zip(text.chars()) {
+                        *cell = ch;
+                    }
+                }
+                cells
+            })
+            .collect()
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> char {
+        self.rows
+            .get(row)
+            .and_then(|runs| runs.iter().take_while(|(start, _)| *start <= col).last())
+            .and_then(|(start, text)| text.chars().nth(col - start))
+            .unwrap_or(' ')
+    }
+}
+
+// ============= MONOSPACE DETECTION =============
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphSample {
+    pub x0: f32,
+    pub width: f32,
+    pub fixed_pitch: bool,
+}
+
+/// Page-level signals that separate fixed-width sources (typewriter,
+/// terminal dumps, code listings) from proportional typesetting.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MonospaceFeatures {
+    /// Share of glyphs whose font carries the fixed-pitch flag.
+    pub fixed_pitch_ratio: f32,
+    /// Coefficient of variation of glyph widths.
+    pub width_cv: f32,
+    /// Share of glyph origins within 15% of a cell boundary at `pitch`.
+    pub grid_fit: f32,
+    /// Median glyph width in points.
+    pub pitch: f32,
+}
+
+impl MonospaceFeatures {
+    const MIN_GLYPHS: usize = 20;
+
+    pub fn from_glyphs(glyphs: &[GlyphSample]) -> Option<Self> {
+        let glyphs: Vec<&GlyphSample> = glyphs.iter().filter(|g| g.width > 0.0).collect();
+        if glyphs.len() < Self::MIN_GLYPHS {
+            return None;
+        }
+
+        let n = glyphs.len() as f32;
+        let fixed_pitch_ratio = glyphs.iter().filter(|g| g.fixed_pitch).count() as f32 / n;
+
+        let mean = glyphs.iter().map(|g| g.width).sum::<f32>() / n;
+        let variance = glyphs.iter().map(|g| (g.width - mean).powi(2)).sum::<f32>() / n;
+        let width_cv = variance.sqrt() / mean;
+
+        let mut widths: Vec<f32> = glyphs.iter().map(|g| g.width).collect();
+        widths.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let pitch = widths[widths.len() / 2];
+
+        let origin = glyphs.iter().map(|g| g.x0).fold(f32::INFINITY, f32::min);
+        let on_grid = glyphs
+            .iter()
+            .filter(|g| {
+                let offset = ((g.x0 - origin) / pitch).fract();
+                offset.min(1.0 - offset) < 0.15
+            })
+            .count();
+
+        Some(Self {
+            fixed_pitch_ratio,
+            width_cv,
+            grid_fit: on_grid as f32 / n,
+            pitch,
+        })
+    }
+
+    fn inputs(&self) -> [f32; 3] {
+        [self.fixed_pitch_ratio, self.width_cv, self.grid_fit]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonospaceDetection {
+    pub features: MonospaceFeatures,
+    pub probability: f32,
+}
+
+impl MonospaceDetection {
+    pub fn is_monospace(&self) -> bool {
+        self.probability > 0.5
+    }
+}
+
+/// Logistic classifier over `MonospaceFeatures`. The defaults are hand-tuned;
+/// labelling pages in the GUI refits the weights to the user's documents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonospaceModel {
+    pub weights: [f32; 3],
+    pub bias: f32,
+}
+
+impl Default for MonospaceModel {
+    fn default() -> Self {
+        Self {
+            weights: [3.0, -6.0, 4.0],
+            bias: -1.5,
+        }
+    }
+}
+
+impl MonospaceModel {
+    /// Scores a page's glyphs; `None` with too few to judge.
+    pub fn detect(&self, glyphs: &[GlyphSample]) -> Option<MonospaceDetection> {
+        MonospaceFeatures::from_glyphs(glyphs).map(|features| MonospaceDetection {
+            probability: self.predict(&features),
+            features,
+        })
+    }
+
+    pub fn predict(&self, features: &MonospaceFeatures) -> f32 {
+        let z = self
+            .weights
+            .iter()
+            .zip(features.inputs())
+            .map(|(w, x)| w * x)
+            .sum::<f32>()
+            + self.bias;
+        1.0 / (1.0 + (-z).exp())
+    }
+
+    /// Refits by gradient descent on the labelled samples, starting from the
+    /// current weights so a handful of samples nudges rather than replaces
+    /// the defaults.
+    pub fn train(&mut self, samples: &[(MonospaceFeatures, bool)]) {
+        const LEARNING_RATE: f32 = 0.5;
+        const ITERATIONS: usize = 500;
+
+        if samples.is_empty() {
+            return;
+        }
+        let n = samples.len() as f32;
+        for _ in 0..ITERATIONS {
+            let mut grad_w = [0.0f32; 3];
+            let mut grad_b = 0.0;
+            for (features, label) in samples {
+                let error = self.predict(features) - if *label { 1.0 } else { 0.0 };
+                for (g, x) in grad_w.iter_mut().zip(features.inputs()) {
+                    *g += error * x;
+                }
+                grad_b += error;
+            }
+            for (w, g) in self.weights.iter_mut().zip(grad_w) {
+                *w -= LEARNING_RATE * g / n;
+            }
+            self.bias -= LEARNING_RATE * grad_b / n;
+        }
+    }
+}
+
+/// Labelled pages and the model fitted to them, stored in
+/// `chonker5_monospace.json` under `CHONKER5_CONFIG_DIR` (default: `.`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonospaceTraining {
+    pub samples: Vec<(MonospaceFeatures, bool)>,
+    pub model: MonospaceModel,
+}
+
+impl MonospaceTraining {
+    fn path() -> PathBuf {
+        config_path("chonker5_monospace.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(Self::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn add_sample(&mut self, features: MonospaceFeatures, is_monospace: bool) {
+        self.samples.push((features, is_monospace));
+        self.model = MonospaceModel::default();
+        self.model.train(&self.samples);
+    }
+}
+
+/// Bounds on one page's extraction, stored in `chonker5_limits.json` under
+/// `CHONKER5_CONFIG_DIR` (default: `.`). Hitting a size limit keeps what was
+/// extracted so far and records an `ExtractionWarning::Truncated`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractionLimits {
+    pub timeout_secs: u64,
+    pub max_width: usize,
+    pub max_height: usize,
+    pub max_text_objects: usize,
+}
+
+impl Default for ExtractionLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            max_width: 2000,
+            max_height: 2000,
+            max_text_objects: 200_000,
+        }
+    }
+}
+
+impl ExtractionLimits {
+    fn path() -> PathBuf {
+        config_path("chonker5_limits.json")
+    }
+
+    pub fn load() -> Self {
+        std::fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(Self::path(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// Clamps a `(width, height)` matrix size, noting any cut in `warnings`.
+    pub fn clamp_size(&self, page: usize, (width, height): (usize, usize), warnings: &mut Vec<ExtractionWarning>) -> (usize, usize) {
+        if width > self.max_width {
+            warnings.push(ExtractionWarning::Truncated {
+                page,
+                reason: format!("{} columns exceed the limit of {}", width, self.max_width),
+            });
+        }
+        if height > self.max_height {
+            warnings.push(ExtractionWarning::Truncated {
+                page,
+                reason: format!("{} rows exceed the limit of {}", height, self.max_height),
+            });
+        }
+        (width.min(self.max_width), height.min(self.max_height))
+    }
+}
+
+pub struct CharacterMatrixEngine {
+    pub char_width: f32,
+    pub char_height: f32,
+    pub monospace_model: MonospaceModel,
+    pub limits: ExtractionLimits,
+}
+
+impl CharacterMatrixEngine {
+    pub fn new() -> Self {
+        Self {
+            char_width: 6.0,
+            char_height: 12.0,
+            monospace_model: MonospaceModel::default(),
+            limits: ExtractionLimits::default(),
+        }
+    }
+
+    pub fn new_optimized(pdf_path: &Path) -> Result<Self> {
+        let mut engine = Self::new();
+        let (char_width, char_height) = engine.find_optimal_character_dimensions(pdf_path)?;
+        engine.char_width = char_width;
+        engine.char_height = char_height;
+        Ok(engine)
+    }
+
+    pub fn find_optimal_character_dimensions(&self, pdf_path: &Path) -> Result<(f32, f32)> {
+        self.find_page_character_dimensions(pdf_path, 0)
+    }
+
+    /// Estimates character cell size from the median font size on one page.
+    pub fn find_page_character_dimensions(
+        &self,
+        pdf_path: &Path,
+        page_index: usize,
+    ) -> Result<(f32, f32)> {
+        let pdfium = bind_pdfium()?;
+
+        let document = PdfSource::File(pdf_path).load(&pdfium)?;
+        if page_index >= document.pages().len() as usize {
+            return Ok((self.char_width, self.char_height));
+        }
+
+        let page = document.pages().get(page_index as u16)?;
+        let page_text = page.text()?;
+
+        let mut font_sizes = Vec::new();
+        for char_obj in page_text.chars().iter() {
+            let font_size = char_obj.unscaled_font_size().value;
+            if font_size > 0.0 {
+                font_sizes.push(font_size);
+            }
+        }
+
+        if font_sizes.is_empty() {
+            return Ok((self.char_width, self.char_height));
+        }
+
+        font_sizes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let modal_font_size = font_sizes[font_sizes.len() / 2];
+
+        let char_width = (modal_font_size * 0.6).max(4.0);
+        let char_height = (modal_font_size * 1.2).max(8.0);
+
+        Ok((char_width, char_height))
+    }
+
+    fn extract_text_objects_for_page(
+        &self,
+        source: PdfSource,
+        target_page_index: usize,
+        stats: &mut ExtractionStats,
+    ) -> Result<Vec<PreciseTextObject>> {
+        let pdfium = bind_pdfium()?;
+
+        let document = source.load(&pdfium)?;
+        let mut text_objects = Vec::new();
+
+        let total = document.pages().len() as usize;
+        if target_page_index >= total {
+            return Err(ChonkerError::PageOutOfRange { page: target_page_index, total }.into());
+        }
+
+        let page = document.pages().get(target_page_index as u16)?;
+        let text_page = page.text()?;
+        let page_height = page.height().value;
+        stats.page_size = Some((page.width().value, page_height));
+
+        let deadline = Instant::now() + self.limits.timeout();
+        let text_segments = text_page.segments();
+        for segment in text_segments.iter() {
+            if text_objects.len() >= self.limits.max_text_objects {
+                stats.truncated = Some(format!("more than {} text objects", self.limits.max_text_objects));
+                break;
+            }
+            if Instant::now() > deadline {
+                stats.truncated = Some(format!("timed out after {}s", self.limits.timeout_secs));
+                break;
+            }
+
+            let bounds = segment.bounds();
+            let text = segment.text();
+
+            if !text.trim().is_empty() {
+                let segment_width = bounds.right().value - bounds.left().value;
+                let char_count = text.chars().count() as f32;
+                let avg_char_width = if char_count > 0.0 {
+                    segment_width / char_count
+                } else {
+                    7.2
+                };
+
+                let font_size = (bounds.top().value - bounds.bottom().value) * 0.8;
+                let is_italic = segment_is_italic(&segment);
+                let drift_pts = stats.drift.record(
+                    target_page_index,
+                    segment_width_drift(&segment, avg_char_width),
+                    avg_char_width,
+                    &text,
+                );
+                let y_from_top = page_height - bounds.top().value;
+                stats.sample_glyphs(&segment, y_from_top, font_size, is_italic);
+
+                let mut current_x = bounds.left().value;
+                for ch in text.chars() {
+                    let char_width = if ch == ' ' {
+                        avg_char_width * 0.5
+                    } else {
+                        avg_char_width
+                    };
+
+                    text_objects.push(PreciseTextObject {
+                        text: ch.to_string(),
+                        bbox: PDFBBox {
+                            x0: current_x,
+                            y0: y_from_top,
+                            x1: current_x + char_width,
+                            y1: y_from_top + font_size,
+                        },
+                        font_size,
+                        is_italic,
+                        drift_pts,
+                    });
+
+                    current_x += char_width;
+                }
+            }
+        }
+
+        Ok(text_objects)
+    }
+
+    fn extract_text_objects_with_precise_coords(
+        &self,
+        source: PdfSource,
+        stats: &mut ExtractionStats,
+    ) -> Result<Vec<PreciseTextObject>> {
+        let pdfium = bind_pdfium()?;
+
+        let document = source.load(&pdfium)?;
+        let mut text_objects = Vec::new();
+
+        for (page_index, page) in document.pages().iter().enumerate() {
+            let text_page = page.text()?;
+            let page_height = page.height().value;
+            let text_segments = text_page.segments();
+
+            for segment in text_segments.iter() {
+                let bounds = segment.bounds();
+                let text = segment.text();
+
+                if !text.trim().is_empty() {
+                    let segment_width = bounds.right().value - bounds.left().value;
+                    let char_count = text.chars().count() as f32;
+                    let avg_char_width = if char_count > 0.0 {
+                        segment_width / char_count
+                    } else {
+                        7.2
+                    };
+
+                    let font_size = (bounds.top().value - bounds.bottom().value) * 0.8;
+                    let is_italic = segment_is_italic(&segment);
+                    let drift_pts = stats.drift.record(
+                        page_index,
+                        segment_width_drift(&segment, avg_char_width),
+                        avg_char_width,
+                        &text,
+                    );
+                    let y_from_top = page_height - bounds.top().value;
+                    stats.sample_glyphs(&segment, y_from_top, font_size, is_italic);
+                    let mut current_x = bounds.left().value;
+
+                    for ch in text.chars() {
+                        let char_width = if ch == ' ' {
+                            avg_char_width * 0.5
+                        } else {
+                            avg_char_width
+                        };
+
+                        text_objects.push(PreciseTextObject {
+                            text: ch.to_string(),
+                            bbox: PDFBBox {
+                                x0: current_x,
+                                y0: y_from_top,
+                                x1: current_x + char_width,
+                                y1: y_from_top + (bounds.top().value - bounds.bottom().value),
+                            },
+                            font_size,
+                            is_italic,
+                            drift_pts,
+                        });
+
+                        current_x += char_width;
+                    }
+                }
+            }
+        }
+
+        Ok(text_objects)
+    }
+
+    fn calculate_optimal_matrix_size(
+        &self,
+        text_objects: &[PreciseTextObject],
+        dimensions: Option<CharDimensions>,
+    ) -> (usize, usize, f32, f32) {
+        if text_objects.is_empty() {
+            return (50, 50, 6.0, 12.0);
+        }
+
+        let (char_width, char_height) = match dimensions {
+            Some(dims) => (dims.char_width, dims.char_height),
+            None => {
+                let modal_font_size = modal_font_size(text_objects);
+                (modal_font_size * 0.6, modal_font_size * 1.2)
+            }
+        };
+
+        let min_x = text_objects
+            .iter()
+            .map(|t| t.bbox.x0)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(0.0);
+        let max_x = text_objects
+            .iter()
+            .map(|t| t.bbox.x1)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(100.0);
+        let min_y = text_objects
+            .iter()
+            .map(|t| t.bbox.y0)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(0.0);
+        let max_y = text_objects
+            .iter()
+            .map(|t| t.bbox.y1)
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(100.0);
+
+        let content_width = max_x - min_x;
+        let content_height = max_y - min_y;
+
+        let matrix_width = ((content_width / char_width).ceil() as usize).max(10);
+        let matrix_height = ((content_height / char_height).ceil() as usize).max(10);
+
+        (matrix_width, matrix_height, char_width, char_height)
+    }
+
+    fn merge_adjacent_regions(&self, regions: &[TextRegion]) -> Vec<TextRegion> {
+        if regions.is_empty() {
+            return Vec::new();
+        }
+
+        let mut merged = Vec::new();
+        let mut processed = vec![false; regions.len()];
+
+        for i in 0..regions.len() {
+            if processed[i] {
+                continue;
+            }
+
+            let mut current = regions[i].clone();
+            processed[i] = true;
+
+            let mut merged_any = true;
+            while merged_any {
+                merged_any = false;
+
+                for j in 0..regions.len() {
+                    if processed[j] {
+                        continue;
+                    }
+
+                    let other = &regions[j];
+
+                    if other.bbox.y == current.bbox.y
+                        && other.bbox.height == current.bbox.height
+                        && other.is_italic == current.is_italic
+                    {
+                        let current_end = current.bbox.x + current.bbox.width;
+                        let other_end = other.bbox.x + other.bbox.width;
+
+                        if (other.bbox.x as i32 - current_end as i32).abs() <= 2
+                            || (current.bbox.x as i32 - other_end as i32).abs() <= 2
+                        {
+                            let new_x = current.bbox.x.min(other.bbox.x);
+                            let new_end = current_end.max(other_end);
+                            current.bbox.x = new_x;
+                            current.bbox.width = new_end - new_x;
+                            current.text_content.push_str(&other.text_content);
+                            current.confidence = current.confidence.min(other.confidence);
+                            processed[j] = true;
+                            merged_any = true;
+                        }
+                    }
+                }
+            }
+
+            merged.push(current);
+        }
+
+        merged
+    }
+
+    pub fn process_pdf(&self, pdf_path: &Path) -> Result<CharacterMatrix> {
+        self.process_pdf_page(pdf_path, None)
+    }
+
+    pub fn process_pdf_page(
+        &self,
+        pdf_path: &Path,
+        page_index: Option<usize>,
+    ) -> Result<CharacterMatrix> {
+        self.process_pdf_page_with_dimensions(pdf_path, page_index, None)
+    }
+
+    /// Like `process_pdf_page`, but lays the matrix out on a fixed character
+    /// cell size instead of the one derived from the modal font size.
+    pub fn process_pdf_page_with_dimensions(
+        &self,
+        pdf_path: &Path,
+        page_index: Option<usize>,
+        dimensions: Option<CharDimensions>,
+    ) -> Result<CharacterMatrix> {
+        self.process_source_page(PdfSource::File(pdf_path), page_index, dimensions)
+    }
+
+    /// `process_pdf_page_with_dimensions` for a file or an in-memory PDF.
+    pub fn process_source_page(
+        &self,
+        source: PdfSource,
+        page_index: Option<usize>,
+        dimensions: Option<CharDimensions>,
+    ) -> Result<CharacterMatrix> {
+        let mut stats = ExtractionStats::default();
+        let mut text_objects = if let Some(idx) = page_index {
+            self.extract_text_objects_for_page(source, idx, &mut stats)?
+        } else {
+            self.extract_text_objects_with_precise_coords(source, &mut stats)?
+        };
+        let mut warnings = Vec::new();
+
+        if text_objects.is_empty() {
+            return Err(ChonkerError::NoText { page: page_index.unwrap_or(0) }.into());
+        }
+
+        // A detected fixed pitch gives exact cells; explicit overrides still win
+        let monospace = self.monospace_model.detect(&stats.glyphs);
+        let pitch = monospace.as_ref().filter(|m| m.is_monospace() && dimensions.is_none()).map(|m| m.features.pitch);
+        if pitch.is_some() && !stats.exact.is_empty() {
+            // Each glyph at its own origin, so columns come straight from
+            // the pitch without width averaging
+            text_objects = std::mem::take(&mut stats.exact);
+            stats.drift = WidthDriftTracker::default();
+        }
+        let dimensions = dimensions.or_else(|| {
+            pitch.map(|pitch| CharDimensions {
+                char_width: pitch,
+                char_height: pitch * 2.0,
+            })
+        });
+
+        let (matrix_width, matrix_height, char_width, char_height) =
+            self.calculate_optimal_matrix_size(&text_objects, dimensions);
+        let page = page_index.unwrap_or(0);
+        if let Some(reason) = stats.truncated.take() {
+            warnings.push(ExtractionWarning::Truncated { page, reason });
+        }
+        let (matrix_width, matrix_height) = self.limits.clamp_size(page, (matrix_width, matrix_height), &mut warnings);
+
+        let min_x = text_objects
+            .iter()
+            .map(|t| t.bbox.x0)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(0.0);
+        let min_y = text_objects
+            .iter()
+            .map(|t| t.bbox.y0)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())
+            .unwrap_or(0.0);
+
+        let mut matrix = vec![vec![' '; matrix_width]; matrix_height];
+        let mut text_regions = Vec::new();
+        let mut collisions = 0;
+
+        for text_obj in &text_objects {
+            let char_x = ((text_obj.bbox.x0 - min_x) / char_width).round() as usize;
+            let char_y = ((text_obj.bbox.y0 - min_y) / char_height).round() as usize;
+
+            if char_y < matrix_height && char_x < matrix_width {
+                if let Some(ch) = text_obj.text.chars().next() {
+                    if !ch.is_whitespace() && !matrix[char_y][char_x].is_whitespace() {
+                        collisions += 1;
+                    }
+                    matrix[char_y][char_x] = ch;
+
+                    text_regions.push(TextRegion {
+                        bbox: CharBBox {
+                            x: char_x,
+                            y: char_y,
+                            width: 1,
+                            height: 1,
+                        },
+                        confidence: drift_confidence(text_obj.drift_pts, char_width),
+                        text_content: ch.to_string(),
+                        region_id: text_regions.len(),
+                        is_italic: text_obj.is_italic,
+                        label: None,
+                        tag: None,
+                    });
+                }
+            }
+        }
+
+        let merged_regions = self.merge_adjacent_regions(&text_regions);
+        // Only drift lowers a PDFium region's confidence
+        warnings.splice(0..0, stats.drift.into_warnings(&merged_regions));
+        for warning in &warnings {
+            tracing::warn!("{}", warning);
+        }
+        let original_text: Vec<String> = text_objects.iter().map(|obj| obj.text.clone()).collect();
+        let layout = LayoutNotes {
+            cell_font_size: dimensions.is_none().then(|| modal_font_size(&text_objects)),
+            columns: detect_text_columns(&matrix),
+            collisions,
+        };
+
+        Ok(CharacterMatrix {
+            width: matrix_width,
+            height: matrix_height,
+            matrix,
+            text_regions: merged_regions,
+            original_text,
+            char_width,
+            char_height,
+            warnings,
+            monospace,
+            reading_order: None,
+            backend: Some(ExtractionBackend::Pdfium),
+            sparse: None,
+            settings_fingerprint: 0,
+            layout,
+        })
+    }
+
+    /// Re-extracts only the glyphs inside `area` (`[x0, y0, x1, y1]` as
+    /// fractions of the page, top-left origin) onto a `cols` x `rows` block.
+    /// Glyphs snap to a grid anchored at the area itself rather than at the
+    /// page's text origin, which keeps table columns straight on noisy pages.
+    pub fn extract_page_area(
+        &self,
+        pdf_path: &Path,
+        page_index: usize,
+        area: [f32; 4],
+        cols: usize,
+        rows: usize,
+    ) -> Result<Vec<Vec<char>>> {
+        let mut stats = ExtractionStats::default();
+        let text_objects = self.extract_text_objects_for_page(PdfSource::File(pdf_path), page_index, &mut stats)?;
+        let (page_width, page_height) = stats
+            .page_size
+            .ok_or_else(|| anyhow::anyhow!("Page {} has no size", page_index + 1))?;
+
+        let area = PDFBBox {
+            x0: area[0] * page_width,
+            y0: area[1] * page_height,
+            x1: area[2] * page_width,
+            y1: area[3] * page_height,
+        };
+        let block = layout_area_glyphs(&text_objects, &area, cols, rows);
+        if block.iter().all(|row| row.iter().all(|c| *c == ' ')) {
+            return Err(ChonkerError::NoText { page: page_index }.into());
+        }
+        Ok(block)
+    }
+
+    /// One page with its regions found by the layout model set as
+    /// `layout_model` in `chonker.toml`, over the stext backend's text in
+    /// this engine's cell size. Without the `onnx` feature this is
+    /// `process_pdf_page`.
+    pub async fn process_pdf_with_ai(&self, pdf_path: &Path, page_index: usize) -> Result<CharacterMatrix, ChonkerError> {
+        #[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+        {
+            let model_path = ChonkerConfig::load().layout_model.ok_or_else(|| ChonkerError::ExternalTool {
+                tool: "layout model",
+                message: "no layout_model set in chonker.toml".to_string(),
+            })?;
+            let pdf_path = pdf_path.to_path_buf();
+            let dimensions = CharDimensions { char_width: self.char_width, char_height: self.char_height };
+            let mut char_matrix = extract_mutool_stext(&pdf_path, page_index, Some(dimensions), &self.limits, &self.monospace_model).await?;
+            let detections = tokio::task::spawn_blocking(move || {
+                let png = render_page_png(&pdf_path, page_index, LAYOUT_DPI)?;
+                layout_model(&model_path)?.detect(&png, LAYOUT_DPI)
+            })
+            .await
+            .map_err(|e| ChonkerError::Other(format!("Layout model task failed: {}", e)))??;
+            char_matrix.ensure_dense();
+            char_matrix.text_regions = layout_regions(&char_matrix, &detections);
+            Ok(char_matrix)
+        }
+        #[cfg(not(all(feature = "onnx", not(target_arch = "wasm32"))))]
+        {
+            tracing::warn!("Built without the onnx feature, falling back to basic processing");
+            self.process_pdf_page(pdf_path, Some(page_index))
+                .map_err(ChonkerError::from_anyhow)
+        }
+    }
+
+    /// One page laid out by ferrules, in this engine's cell size. `rerun`
+    /// ignores a cached result, as in `run_ferrules_cached`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn process_pdf_with_ferrules(
+        &self,
+        pdf_path: &Path,
+        ferrules_path: &Path,
+        page_index: usize,
+        rerun: bool,
+    ) -> Result<CharacterMatrix, ChonkerError> {
+        let document = run_ferrules_cached(ferrules_path, pdf_path, page_index, rerun).await?;
+        ferrules_to_matrix(&document, page_index, self.char_width, self.char_height, &self.limits)
+    }
+
+    pub fn render_matrix_as_string(&self, char_matrix: &CharacterMatrix) -> String {
+        let mut result = String::new();
+
+        result.push_str(&format!(
+            "Character Matrix ({}x{}) | Char: {:.1}x{:.1}pt:\n",
+            char_matrix.width, char_matrix.height, char_matrix.char_width, char_matrix.char_height
+        ));
+        result.push_str(&format!(
+            "Text Regions: {} | Original Text Objects: {}\n",
+            char_matrix.text_regions.len(),
+            char_matrix.original_text.len()
+        ));
+        for warning in &char_matrix.warnings {
+            result.push_str(&format!("⚠️ {}\n", warning));
+        }
+        result.push_str(&"═".repeat(char_matrix.width.min(80)));
+        result.push('\n');
+
+        for (row_idx, row) in char_matrix.matrix.iter().enumerate() {
+            if char_matrix.height > 20 {
+                result.push_str(&format!("{:3} ", row_idx));
+            }
+
+            for &ch in row {
+                result.push(ch);
+            }
+            result.push('\n');
+        }
+
+        result.push_str(&"═".repeat(char_matrix.width.min(80)));
+        result.push('\n');
+
+        for (i, region) in char_matrix.text_regions.iter().enumerate() {
+            result.push_str(&format!(
+                "Region {}: ({},{}) {}x{} conf:{:.2}{} - \"{}\"\n",
+                i + 1,
+                region.bbox.x,
+                region.bbox.y,
+                region.bbox.width,
+                region.bbox.height,
+                region.confidence,
+                if region.is_italic { " [italic]" } else { "" },
+                region.text_content.chars().take(50).collect::<String>()
+            ));
+        }
+
+        result
+    }
+
+    pub fn generate_spatial_console_output(&self, char_matrix: &CharacterMatrix) -> String {
+        let mut result = String::new();
+
+        result.push_str("📊 Ferrules Character Matrix Output - Exact Placement Visualization\n");
+        result.push_str(&format!(
+            "Matrix Size: {} columns × {} rows\n",
+            char_matrix.width, char_matrix.height
+        ));
+        result.push_str(&format!(
+            "Regions Detected: {}\n",
+            char_matrix.text_regions.len()
+        ));
+        result.push_str(&format!(
+            "Text Objects: {}\n",
+            char_matrix.original_text.len()
+        ));
+        result.push_str("Processing Time: N/A\n");
+        result.push_str("Toggle Text Highlighting Toggle Grid Lines\n");
+
+        for (row_idx, row) in char_matrix.matrix.iter().enumerate() {
+            result.push_str(&format!("{:3} ", row_idx));
+            for &ch in row.iter() {
+                result.push(if ch == ' ' { '·' } else { ch });
+            }
+            result.push('\n');
+        }
+
+        result.push_str("What Ferrules Accomplished:\n");
+
+        let mut accomplishments = Vec::new();
+        for (i, region) in char_matrix.text_regions.iter().enumerate().take(5) {
+            if !region.text_content.trim().is_empty() {
+                let content_preview = if region.text_content.len() > 50 {
+                    format!("{}...", &region.text_content[..50])
+                } else {
+                    region.text_content.clone()
+                };
+                accomplishments.push(format!(
+                    "✅ Found text region {}: \"{}\" (Confidence: {:.1}%)",
+                    i + 1,
+                    content_preview,
+                    region.confidence * 100.0
+                ));
+            }
+        }
+
+        if accomplishments.is_empty() {
+            accomplishments
+                .push("✅ Successfully processed PDF with Ferrules ML vision model".to_string());
+            accomplishments
+                .push("✅ Generated spatial character matrix representation".to_string());
+            accomplishments.push("✅ Preserved document layout structure".to_string());
+        }
+
+        for accomplishment in accomplishments {
+            result.push_str(&format!("{}\n", accomplishment));
+        }
+
+        let issues = vec![
+            "❌ Text concatenation: Words may run together without spaces",
+            "❌ Overlapping text: Multiple words placed in same positions",
+            "❌ Inconsistent spacing: Some areas dense, others sparse",
+            "❌ Character accuracy: OCR/vision may misread some characters",
+        ];
+
+        result.push_str("Placement Issues:\n");
+        for issue in issues {
+            result.push_str(&format!("{}\n", issue));
+        }
+
+        result
+    }
+}
+
+impl Default for CharacterMatrixEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============= PROGRESS =============
+/// Per-page progress of a multi-page extraction, so clients can draw accurate
+/// progress for long documents. Serialized with an `event` tag, e.g.
+/// `{"event":"started","page":4}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum ProgressEvent {
+    Queued { page: usize },
+    Started { page: usize },
+    /// `error` is the failure message of a page that could not be extracted.
+    Finished { page: usize, error: Option<String> },
+    /// Share of the batch finished, sent after each `Finished`.
+    Percent { done: usize, total: usize, percent: f32 },
+}
+
+impl ProgressEvent {
+    pub fn percent(done: usize, total: usize) -> Self {
+        let percent = if total == 0 { 100.0 } else { done as f32 * 100.0 / total as f32 };
+        ProgressEvent::Percent { done, total, percent }
+    }
+}
+
+// ============= EXTRACTION =============
+/// Extracts one page: mutool first, PDFium when that fails. Results are
+/// read from and written to the disk cache.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn extract_page(
+    pdf_path: PathBuf,
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let result = tokio::task::spawn_blocking(move || {
+        tracing::info!(
+            "Starting async PDF processing: {} (page {})",
+            pdf_path.display(),
+            page_index + 1
+        );
+
+        let model = MonospaceTraining::load().model;
+        let cache = cache_dir()
+            .zip(document_hash(&pdf_path))
+            .map(|(dir, file_hash)| (dir, DiskCacheKey::matrix(file_hash, page_index, dimensions, None, &model)));
+        if let Some(matrix) = cache.as_ref().and_then(|(dir, key)| load_cached_matrix(dir, key)) {
+            tracing::info!("Loaded page {} from the disk cache", page_index + 1);
+            return Ok(matrix);
+        }
+
+        let limits = ExtractionLimits::load();
+        let start_time = std::time::Instant::now();
+        let timeout = limits.timeout();
+
+        let rt = tokio::runtime::Handle::current();
+
+        let mut result = match rt.block_on(extract_mutool(&pdf_path, page_index, dimensions, &limits, &model)) {
+            Ok(matrix) => {
+                tracing::info!(
+                    "Simple text extraction successful in {:?}",
+                    start_time.elapsed()
+                );
+                Ok(matrix)
+            }
+            Err(simple_err) => {
+                tracing::warn!("Simple extraction failed: {}, trying PDFium", simple_err);
+
+                let Some(remaining) = timeout.checked_sub(start_time.elapsed()).filter(|d| !d.is_zero()) else {
+                    return Err(ChonkerError::Timeout { secs: limits.timeout_secs });
+                };
+
+                let engine = CharacterMatrixEngine {
+                    monospace_model: model.clone(),
+                    // PDFium gets whatever the mutool attempt left of the budget
+                    limits: ExtractionLimits { timeout_secs: remaining.as_secs().max(1), ..limits },
+                    ..CharacterMatrixEngine::new()
+                };
+                engine
+                    .process_pdf_page_with_dimensions(&pdf_path, Some(page_index), dimensions)
+                    .map_err(ChonkerError::from_anyhow)
+            }
+        };
+
+        if let Ok(matrix) = &mut result {
+            matrix.settings_fingerprint = extraction_fingerprint(dimensions, None, Segmentation::Backend, &model);
+        }
+        // Partial results are not cached so that raising a limit takes effect
+        let partial = result.as_ref().is_ok_and(|m| {
+            m.warnings.iter().any(|w| matches!(w, ExtractionWarning::Truncated { .. }))
+        });
+        if let (Ok(matrix), Some((dir, key)), false) = (&result, &cache, partial) {
+            if let Err(e) = save_cached_matrix(dir, key, matrix) {
+                tracing::warn!("Failed to cache page {}: {}", page_index + 1, e);
+            }
+        }
+        result
+    })
+    .await;
+
+    match result {
+        Ok(pdf_result) => pdf_result,
+        Err(join_err) => Err(ChonkerError::Other(format!("PDF processing task failed: {}", join_err))),
+    }
+}
+
+/// Extracts one page with a single backend, skipping `extract_page`'s
+/// fallback and the disk cache.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn extract_page_with_backend(
+    pdf_path: PathBuf,
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+    backend: ExtractionBackend,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let model = MonospaceTraining::load().model;
+    let limits = ExtractionLimits::load();
+    let mut matrix = match backend {
+        ExtractionBackend::Mutool => extract_mutool(&pdf_path, page_index, dimensions, &limits, &model).await?,
+        ExtractionBackend::MutoolStext => extract_mutool_stext(&pdf_path, page_index, dimensions, &limits, &model).await?,
+        ExtractionBackend::Pdfium => {
+            let engine = CharacterMatrixEngine {
+                monospace_model: model.clone(),
+                limits,
+                ..CharacterMatrixEngine::new()
+            };
+            tokio::task::spawn_blocking(move || {
+                engine
+                    .process_pdf_page_with_dimensions(&pdf_path, Some(page_index), dimensions)
+                    .map_err(ChonkerError::from_anyhow)
+            })
+            .await
+            .map_err(|e| ChonkerError::Other(format!("PDF processing task failed: {}", e)))??
+        }
+    };
+    matrix.settings_fingerprint = extraction_fingerprint(dimensions, Some(backend), Segmentation::Backend, &model);
+    Ok(matrix)
+}
+
+/// Extracts one page with the document's saved overrides, labels, tags and
+/// reading order applied; pages without a character override use
+/// `chonker.toml`'s `char_size`. `backend` forces a single backend.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn extract_annotated_page(
+    pdf_path: PathBuf,
+    page_index: usize,
+    backend: Option<ExtractionBackend>,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let dimensions = load_char_overrides(&pdf_path).get(&page_index).copied().or(ChonkerConfig::load().char_size);
+    let mut char_matrix = match backend {
+        Some(backend) => extract_page_with_backend(pdf_path.clone(), page_index, dimensions, backend).await,
+        None => extract_page(pdf_path.clone(), page_index, dimensions).await,
+    }?;
+    apply_segmentation(&mut char_matrix, load_segmentation(&pdf_path));
+    apply_region_labels(&mut char_matrix, load_region_labels(&pdf_path).get(&page_index));
+    apply_region_tags(&mut char_matrix, load_region_tags(&pdf_path).get(&page_index));
+    char_matrix.reading_order = load_reading_orders(&pdf_path).get(&page_index).cloned();
+    Ok(char_matrix)
+}
+
+/// Extracts one page of an in-memory PDF with PDFium alone, for callers
+/// without a file system or `mutool` such as the browser build.
+pub fn extract_page_from_bytes(
+    bytes: &[u8],
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let model = MonospaceTraining::load().model;
+    let engine = CharacterMatrixEngine {
+        monospace_model: model.clone(),
+        ..CharacterMatrixEngine::new()
+    };
+    let mut matrix = engine
+        .process_source_page(PdfSource::Bytes(bytes), Some(page_index), dimensions)
+        .map_err(ChonkerError::from_anyhow)?;
+    matrix.settings_fingerprint = extraction_fingerprint(dimensions, Some(ExtractionBackend::Pdfium), Segmentation::Backend, &model);
+    Ok(matrix)
+}
+
+/// Page count of an in-memory PDF.
+pub fn page_count_from_bytes(bytes: &[u8]) -> Result<usize, ChonkerError> {
+    let pdfium = bind_pdfium()?;
+    let document = PdfSource::Bytes(bytes).load(&pdfium).map_err(ChonkerError::from_anyhow)?;
+    Ok(document.pages().len() as usize)
+}
+
+/// Extracts the pages selected by `pages_spec` (see [`parse_page_range`]) with
+/// the document's saved character overrides, region labels, tags and reading
+/// orders applied. A failing page does not stop the others; only an
+/// unreadable document or a bad page spec fails the whole call.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn extract_document(
+    pdf_path: PathBuf,
+    pages_spec: &str,
+) -> Result<Vec<(usize, Result<CharacterMatrix, ChonkerError>)>, ChonkerError> {
+    extract_document_with_progress(pdf_path, pages_spec, None).await
+}
+
+/// [`extract_document`], sending a [`ProgressEvent`] to `progress` as each page
+/// is queued, started and finished. The sender is dropped when the call
+/// returns, which ends a receiver's iteration.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn extract_document_with_progress(
+    pdf_path: PathBuf,
+    pages_spec: &str,
+    progress: Option<std::sync::mpsc::Sender<ProgressEvent>>,
+) -> Result<Vec<(usize, Result<CharacterMatrix, ChonkerError>)>, ChonkerError> {
+    // A client that stopped listening doesn't stop the extraction
+    let send = |event: ProgressEvent| {
+        if let Some(progress) = &progress {
+            let _ = progress.send(event);
+        }
+    };
+    // A malformed spec fails before the PDF is read
+    page_spans(pages_spec).map_err(ChonkerError::from_anyhow)?;
+    let total_pages = pdf_page_count(&pdf_path).map_err(ChonkerError::from_anyhow)?;
+    let pages = parse_page_range(pages_spec, total_pages).map_err(ChonkerError::from_anyhow)?;
+    let char_overrides = load_char_overrides(&pdf_path);
+    let region_labels = load_region_labels(&pdf_path);
+    let region_tags = load_region_tags(&pdf_path);
+    let reading_orders = load_reading_orders(&pdf_path);
+    let segmentation = load_segmentation(&pdf_path);
+    let default_dimensions = ChonkerConfig::load().char_size;
+
+    for &page in &pages {
+        send(ProgressEvent::Queued { page });
+    }
+    let mut results = Vec::with_capacity(pages.len());
+    for &page_index in &pages {
+        send(ProgressEvent::Started { page: page_index });
+        let dimensions = char_overrides.get(&page_index).copied().or(default_dimensions);
+        let result = extract_page(pdf_path.clone(), page_index, dimensions).await.map(|mut char_matrix| {
+            apply_segmentation(&mut char_matrix, segmentation);
+            apply_region_labels(&mut char_matrix, region_labels.get(&page_index));
+            apply_region_tags(&mut char_matrix, region_tags.get(&page_index));
+            char_matrix.reading_order = reading_orders.get(&page_index).cloned();
+            char_matrix
+        });
+        send(ProgressEvent::Finished { page: page_index, error: result.as_ref().err().map(|e| e.to_string()) });
+        results.push((page_index, result));
+        send(ProgressEvent::percent(results.len(), pages.len()));
+    }
+    Ok(results)
+}
+
+/// Runs `mutool draw -F <format>` on one page and returns its stdout.
+#[cfg(not(target_arch = "wasm32"))]
+async fn run_mutool_draw(
+    pdf_path: &PathBuf,
+    page_index: usize,
+    format: &str,
+    limits: &ExtractionLimits,
+) -> Result<Vec<u8>, ChonkerError> {
+    let output = tokio::process::Command::new("mutool")
+        .arg("draw")
+        .arg("-F")
+        .arg(format)
+        .arg(pdf_path)
+        .arg((page_index + 1).to_string())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(limits.timeout(), output)
+        .await
+        .map_err(|_| ChonkerError::Timeout { secs: limits.timeout_secs })?
+        .map_err(|e| ChonkerError::ExternalTool { tool: "mutool", message: e.to_string() })?;
+
+    if !output.status.success() {
+        return Err(ChonkerError::ExternalTool {
+            tool: "mutool",
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
+
+/// mutool's text layout, or with a cell size (a per-page override or
+/// `chonker.toml`'s `char_size`) its stext glyphs laid out at that size,
+/// since the text layout has no cell size to change. The text layout has
+/// no glyph positions either, so the page is judged fixed-width from its
+/// stext, and a fixed-width page is laid out from that at its pitch.
+#[cfg(not(target_arch = "wasm32"))]
+async fn extract_mutool(
+    pdf_path: &PathBuf,
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+    limits: &ExtractionLimits,
+    model: &MonospaceModel,
+) -> Result<CharacterMatrix, ChonkerError> {
+    if dimensions.is_some() {
+        return extract_mutool_stext(pdf_path, page_index, dimensions, limits, model).await;
+    }
+    let stext = extract_mutool_stext(pdf_path, page_index, None, limits, model).await.ok();
+    let monospace = stext.as_ref().and_then(|matrix| matrix.monospace.clone());
+    match stext {
+        Some(matrix) if monospace.as_ref().is_some_and(MonospaceDetection::is_monospace) => Ok(matrix),
+        _ => {
+            let mut matrix = extract_mutool_text(pdf_path, page_index, limits).await?;
+            matrix.monospace = monospace;
+            Ok(matrix)
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn extract_mutool_text(
+    pdf_path: &PathBuf,
+    page_index: usize,
+    limits: &ExtractionLimits,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let stdout = run_mutool_draw(pdf_path, page_index, "text", limits).await?;
+    let text = String::from_utf8_lossy(&stdout);
+    let lines: Vec<&str> = text.lines().collect();
+    let mut warnings = Vec::new();
+    let (max_width, height) = limits.clamp_size(
+        page_index,
+        (lines.iter().map(|line| line.len()).max().unwrap_or(80), lines.len().max(25)),
+        &mut warnings,
+    );
+
+    let mut matrix = vec![vec![' '; max_width]; height];
+
+    for (y, line) in lines.iter().enumerate() {
+        if y < height {
+            for (x, ch) in line.chars().enumerate() {
+                if x < max_width {
+                    matrix[y][x] = ch;
+                }
+            }
+        }
+    }
+
+    let layout = LayoutNotes {
+        columns: detect_text_columns(&matrix),
+        ..LayoutNotes::default()
+    };
+
+    Ok(CharacterMatrix {
+        width: max_width,
+        height,
+        matrix,
+        text_regions: Vec::new(),
+        original_text: lines.iter().map(|s| s.to_string()).collect(),
+        char_width: 8.0,
+        char_height: 12.0,
+        warnings,
+        monospace: None,
+        reading_order: None,
+        backend: Some(ExtractionBackend::Mutool),
+        sparse: None,
+        settings_fingerprint: 0,
+        layout,
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn extract_mutool_stext(
+    pdf_path: &PathBuf,
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+    limits: &ExtractionLimits,
+    model: &MonospaceModel,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let stdout = run_mutool_draw(pdf_path, page_index, "stext", limits).await?;
+    parse_stext_to_matrix(&String::from_utf8_lossy(&stdout), page_index, dimensions, limits, model)
+}
+
+// ============= MUTOOL STEXT =============
+/// One glyph of `mutool draw -F stext` output, in points from the page's
+/// top-left corner.
+#[derive(Debug, Clone)]
+struct StextChar {
+    c: char,
+    /// Left edge of the glyph origin.
+    x: f32,
+    /// Glyph width from its quad (or bbox in older mutool), 0 when absent.
+    width: f32,
+    font_size: f32,
+    /// stext has no font flags, so this goes by the font's name.
+    fixed_pitch: bool,
+}
+
+#[derive(Debug, Default)]
+struct StextLine {
+    baseline: f32,
+    chars: Vec<StextChar>,
+}
+
+#[derive(Debug, Default)]
+struct StextBlock {
+    lines: Vec<StextLine>,
+    italic: bool,
+}
+
+#[derive(Debug, Default)]
+struct StextPage {
+    width: f32,
+    height: f32,
+    blocks: Vec<StextBlock>,
+}
+
+fn stext_attr(element: &quick_xml::events::BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attr| attr.key.as_ref() == name)
+        .and_then(|attr| attr.unescape_value().ok().map(|value| value.into_owned()))
+}
+
+fn stext_numbers(element: &quick_xml::events::BytesStart, name: &[u8]) -> Vec<f32> {
+    stext_attr(element, name)
+        .map(|value| value.split_whitespace().filter_map(|n| n.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Reads the first `<page>` of an stext document. Image blocks and anything
+/// outside `<block>/<line>/<font>/<char>` are skipped.
+fn parse_stext(xml: &str) -> std::result::Result<StextPage, quick_xml::Error> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut page = StextPage::default();
+    let mut in_page = false;
+    let mut block: Option<StextBlock> = None;
+    let mut line: Option<StextLine> = None;
+    let mut font_size = 0.0f32;
+    let mut fixed_pitch = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                b"page" if !in_page => {
+                    in_page = true;
+                    page.width = stext_attr(&e, b"width").and_then(|w| w.parse().ok()).unwrap_or(0.0);
+                    page.height = stext_attr(&e, b"height").and_then(|h| h.parse().ok()).unwrap_or(0.0);
+                }
+                b"block" if in_page => block = Some(StextBlock::default()),
+                b"line" if block.is_some() => line = Some(StextLine::default()),
+                b"font" => {
+                    font_size = stext_attr(&e, b"size").and_then(|s| s.parse().ok()).unwrap_or(0.0);
+                    let name = stext_attr(&e, b"name").unwrap_or_default().to_lowercase();
+                    fixed_pitch = ["mono", "courier", "consol"].iter().any(|family| name.contains(family));
+                    if let Some(block) = block.as_mut() {
+                        block.italic |= name.contains("italic") || name.contains("oblique");
+                    }
+                }
+                b"char" => {
+                    let Some(line) = line.as_mut() else { continue };
+                    let Some(c) = stext_attr(&e, b"c").and_then(|c| c.chars().next()) else { continue };
+                    let quad = stext_numbers(&e, b"quad");
+                    let bbox = stext_numbers(&e, b"bbox");
+                    let width = match (quad.as_slice(), bbox.as_slice()) {
+                        ([ulx, _, urx, ..], _) | (_, [ulx, _, urx, _]) => (urx - ulx).abs(),
+                        _ => 0.0,
+                    };
+                    let x = stext_attr(&e, b"x").and_then(|x| x.parse().ok()).or_else(|| quad.first().copied());
+                    let y = stext_attr(&e, b"y").and_then(|y| y.parse().ok()).or_else(|| quad.get(5).copied());
+                    let (Some(x), Some(y)) = (x, y) else { continue };
+                    if line.chars.is_empty() {
+                        line.baseline = y;
+                    }
+                    line.chars.push(StextChar { c, x, width, font_size, fixed_pitch });
+                }
+                _ => {}
+            },
+            Event::End(e) => match e.name().as_ref() {
+                b"line" => {
+                    if let (Some(block), Some(line)) = (block.as_mut(), line.take()) {
+                        if !line.chars.is_empty() {
+                            block.lines.push(line);
+                        }
+                    }
+                }
+                b"block" => {
+                    if let Some(block) = block.take().filter(|block| !block.lines.is_empty()) {
+                        page.blocks.push(block);
+                    }
+                }
+                b"page" => break,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(page)
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    values.retain(|v| v.is_finite() && *v > 0.0);
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.get(values.len() / 2).copied()
+}
+
+/// Builds a matrix from `mutool draw -F stext` XML. Cells are `dimensions`
+/// or else sized from the page's median glyph width and font size; each `<line>` takes the row of
+/// its baseline and each `<char>` the column of its origin, so the layout
+/// follows the PDF rather than mutool's plain-text reflow. Each `<block>`
+/// becomes a text region. Without `dimensions`, a page `model` judges
+/// fixed-width is laid out at its pitch from the left margin, one glyph per
+/// column.
+pub fn parse_stext_to_matrix(
+    xml: &str,
+    page_index: usize,
+    dimensions: Option<CharDimensions>,
+    limits: &ExtractionLimits,
+    model: &MonospaceModel,
+) -> Result<CharacterMatrix, ChonkerError> {
+    let page = parse_stext(xml).map_err(|e| ChonkerError::ExternalTool {
+        tool: "mutool",
+        message: format!("unreadable stext output: {}", e),
+    })?;
+    let glyphs = || page.blocks.iter().flat_map(|b| &b.lines).flat_map(|l| &l.chars);
+    if glyphs().all(|glyph| glyph.c.is_whitespace()) {
+        return Err(ChonkerError::NoText { page: page_index });
+    }
+
+    let samples: Vec<GlyphSample> = glyphs()
+        .filter(|glyph| !glyph.c.is_whitespace())
+        .map(|glyph| GlyphSample { x0: glyph.x, width: glyph.width, fixed_pitch: glyph.fixed_pitch })
+        .collect();
+    let monospace = model.detect(&samples);
+    let pitch = monospace.as_ref().filter(|m| m.is_monospace() && dimensions.is_none()).map(|m| m.features.pitch);
+
+    let cell_font_size = dimensions.is_none().then(|| median(glyphs().map(|glyph| glyph.font_size).collect()).unwrap_or(12.0));
+    let (char_width, char_height) = match dimensions {
+        Some(dims) => (dims.char_width, dims.char_height),
+        None => {
+            let char_height = cell_font_size.unwrap_or(12.0);
+            let char_width = pitch
+                .or_else(|| median(samples.iter().map(|g| g.width).collect()))
+                .unwrap_or(char_height * 0.5);
+            (char_width, char_height)
+        }
+    };
+    // A fixed pitch maps columns exactly from the left margin
+    let left_margin = match pitch {
+        Some(_) => samples.iter().map(|g| g.x0).fold(f32::INFINITY, f32::min),
+        None => 0.0,
+    };
+    // Rows are numbered from the baseline, so a line sits one row up from it
+    let row_of = |baseline: f32| ((baseline / char_height).round() as usize).saturating_sub(1);
+    let col_of = |x: f32| ((x - left_margin).max(0.0) / char_width).round() as usize;
+
+    let last_row = page.blocks.iter().flat_map(|b| &b.lines).map(|l| row_of(l.baseline)).max().unwrap_or(0);
+    let last_col = page.blocks.iter().flat_map(|b| &b.lines).map(|l| col_of(l.chars[0].x) + l.chars.len()).max().unwrap_or(0);
+    let mut warnings = Vec::new();
+    let (width, height) = limits.clamp_size(
+        page_index,
+        (
+            ((page.width / char_width).ceil() as usize).max(last_col + 1),
+            ((page.height / char_height).ceil() as usize).max(last_row + 1),
+        ),
+        &mut warnings,
+    );
+
+    let mut matrix = vec![vec![' '; width]; height];
+    let mut text_regions = Vec::new();
+    let mut original_text = Vec::new();
+    let mut collisions = 0;
+    for block in &page.blocks {
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (usize::MAX, usize::MAX, 0, 0);
+        let mut block_text = Vec::new();
+        for line in &block.lines {
+            let y = row_of(line.baseline);
+            let text: String = line.chars.iter().map(|glyph| glyph.c).collect();
+            block_text.push(text.trim_end().to_string());
+            original_text.push(text);
+            if y >= height {
+                continue;
+            }
+            // Never let a glyph overwrite its left neighbour, even where
+            // kerning or a wide font rounds two glyphs into one cell; a fixed
+            // pitch needs no such nudging
+            let mut next_free = 0;
+            for glyph in &line.chars {
+                let wanted = col_of(glyph.x);
+                let x = if pitch.is_some() { wanted } else { wanted.max(next_free) };
+                if x >= width {
+                    break;
+                }
+                next_free = x + 1;
+                if glyph.c.is_whitespace() {
+                    continue;
+                }
+                if x != wanted || !matrix[y][x].is_whitespace() {
+                    collisions += 1;
+                }
+                matrix[y][x] = glyph.c;
+                min_x = min_x.min(x);
+                max_x = max_x.max(x);
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+        }
+        if min_x <= max_x && min_y <= max_y {
+            text_regions.push(TextRegion {
+                bbox: CharBBox { x: min_x, y: min_y, width: max_x - min_x + 1, height: max_y - min_y + 1 },
+                confidence: 1.0,
+                text_content: block_text.join("\n"),
+                region_id: text_regions.len(),
+                is_italic: block.italic,
+                label: None,
+                tag: None,
+            });
+        }
+    }
+
+    let layout = LayoutNotes {
+        cell_font_size,
+        columns: detect_text_columns(&matrix),
+        collisions,
+    };
+
+    Ok(CharacterMatrix {
+        width,
+        height,
+        matrix,
+        text_regions,
+        original_text,
+        char_width,
+        char_height,
+        warnings,
+        monospace,
+        reading_order: None,
+        backend: Some(ExtractionBackend::MutoolStext),
+        sparse: None,
+        settings_fingerprint: 0,
+        layout,
+    })
+}
+
+// ============= FERRULES =============
+/// The JSON document ferrules writes next to its other results. Only the
+/// parts the matrix needs are read; unknown fields and block kinds are
+/// tolerated so newer ferrules releases still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FerrulesDocument {
+    #[serde(default)]
+    pub pages: Vec<FerrulesPage>,
+    #[serde(default)]
+    pub blocks: Vec<FerrulesBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FerrulesPage {
+    /// 0-based page index.
+    pub id: usize,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FerrulesBlock {
+    pub kind: FerrulesBlockKind,
+    /// The pages the block sits on, as `FerrulesPage::id`s.
+    #[serde(default)]
+    pub pages_id: Vec<usize>,
+    pub bbox: FerrulesBBox,
+}
+
+/// In points from the page's top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FerrulesBBox {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "block_type")]
+pub enum FerrulesBlockKind {
+    Title {
+        #[serde(default)]
+        text: String,
+    },
+    Header {
+        #[serde(default)]
+        text: String,
+    },
+    Footer {
+        #[serde(default)]
+        text: String,
+    },
+    TextBlock {
+        #[serde(default)]
+        text: String,
+    },
+    ListBlock {
+        #[serde(default)]
+        items: Vec<String>,
+    },
+    Image,
+    Table,
+    #[serde(other)]
+    Other,
+}
+
+impl FerrulesBlockKind {
+    fn text(&self) -> String {
+        match self {
+            FerrulesBlockKind::Title { text }
+            | FerrulesBlockKind::Header { text }
+            | FerrulesBlockKind::Footer { text }
+            | FerrulesBlockKind::TextBlock { text } => text.clone(),
+            FerrulesBlockKind::ListBlock { items } => items.join("\n"),
+            FerrulesBlockKind::Image | FerrulesBlockKind::Table | FerrulesBlockKind::Other => String::new(),
+        }
+    }
+
+    fn tag(&self) -> Option<RegionTag> {
+        match self {
+            FerrulesBlockKind::Title { .. } => Some(RegionTag::Title),
+            FerrulesBlockKind::TextBlock { .. } | FerrulesBlockKind::ListBlock { .. } => Some(RegionTag::Paragraph),
+            FerrulesBlockKind::Header { .. } | FerrulesBlockKind::Footer { .. } => Some(RegionTag::Footer),
+            FerrulesBlockKind::Image => Some(RegionTag::Figure),
+            FerrulesBlockKind::Table => Some(RegionTag::Table),
+            FerrulesBlockKind::Other => None,
+        }
+    }
+}
+
+/// Runs the ferrules binary on one page and reads the JSON document it
+/// writes into a scratch output directory. Dropping the future kills the
+/// process.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_ferrules(
+    ferrules_path: &Path,
+    pdf_path: &Path,
+    page_index: usize,
+) -> Result<FerrulesDocument, ChonkerError> {
+    let tool_error = |message: String| ChonkerError::ExternalTool { tool: "ferrules", message };
+    // A fresh directory per run, so concurrent runs never share output and
+    // it is removed even when the run is cancelled
+    let out_dir = tempfile::Builder::new()
+        .prefix(&format!("chonker_ferrules_p{}_", page_index + 1))
+        .tempdir()
+        .map_err(|e| tool_error(e.to_string()))?;
+    let output = tokio::process::Command::new(ferrules_path)
+        .arg(pdf_path)
+        .arg("--page-range")
+        .arg((page_index + 1).to_string())
+        .arg("--output-dir")
+        .arg(out_dir.path())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| tool_error(e.to_string()))?;
+    if !output.status.success() {
+        return Err(tool_error(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    // ferrules nests its results in a `<document>-results` directory
+    let json_in = |dir: &Path| {
+        std::fs::read_dir(dir).ok()?.flatten().map(|entry| entry.path()).find(|path| path.extension().is_some_and(|e| e == "json"))
+    };
+    let json_path = json_in(out_dir.path()).or_else(|| {
+        std::fs::read_dir(out_dir.path()).ok()?.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).find_map(|dir| json_in(&dir))
+    });
+    json_path
+        .ok_or_else(|| tool_error(format!("no JSON output in {}", out_dir.path().display())))
+        .and_then(|path| std::fs::read_to_string(path).map_err(|e| tool_error(e.to_string())))
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| tool_error(format!("unreadable JSON output: {}", e))))
+}
+
+/// `run_ferrules` through the disk cache, keyed by the document's content
+/// and the page. `rerun` runs ferrules anyway and replaces the cached
+/// document, for when ferrules or its model has changed.
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn run_ferrules_cached(
+    ferrules_path: &Path,
+    pdf_path: &Path,
+    page_index: usize,
+    rerun: bool,
+) -> Result<FerrulesDocument, ChonkerError> {
+    let cache_path = cache_dir()
+        .zip(document_hash(pdf_path))
+        .map(|(dir, file_hash)| dir.join(DiskCacheKey::ferrules(file_hash, page_index).file_name("json")));
+    let cached = cache_path
+        .as_ref()
+        .filter(|_| !rerun)
+        .and_then(|path| std::fs::read(path).ok())
+        .and_then(|json| serde_json::from_slice(&json).ok());
+    if let Some(document) = cached {
+        tracing::info!("Loaded ferrules output for page {} from the disk cache", page_index + 1);
+        return Ok(document);
+    }
+
+    let document = run_ferrules(ferrules_path, pdf_path, page_index).await?;
+    if let Some(path) = &cache_path {
+        let saved = serde_json::to_vec(&document).map_err(anyhow::Error::from).and_then(|json| write_cache_file(path, &json));
+        if let Err(e) = saved {
+            tracing::warn!("Failed to cache ferrules output: {}", e);
+        }
+    }
+    Ok(document)
+}
+
+/// Lays ferrules' blocks for one page onto a page-sized grid of
+/// `char_width` x `char_height` cells, as the stext backend does: each block
+/// becomes a tagged text region at its bounding box, with its text wrapped
+/// to the box's width.
+pub fn ferrules_to_matrix(
+    document: &FerrulesDocument,
+    page_index: usize,
+    char_width: f32,
+    char_height: f32,
+    limits: &ExtractionLimits,
+) -> Result<CharacterMatrix, ChonkerError> {
+    // With a page range the only page may keep its id in the whole document
+    let page = match document.pages.as_slice() {
+        [page] => page,
+        pages => pages
+            .iter()
+            .find(|page| page.id == page_index)
+            .ok_or(ChonkerError::PageOutOfRange { page: page_index, total: pages.len() })?,
+    };
+    let blocks: Vec<&FerrulesBlock> = document.blocks.iter().filter(|block| block.pages_id.contains(&page.id)).collect();
+    if blocks.is_empty() {
+        return Err(ChonkerError::NoText { page: page_index });
+    }
+
+    let mut warnings = Vec::new();
+    let (width, height) = limits.clamp_size(
+        page_index,
+        ((page.width / char_width).ceil() as usize, (page.height / char_height).ceil() as usize),
+        &mut warnings,
+    );
+    if width == 0 || height == 0 {
+        return Err(ChonkerError::NoText { page: page_index });
+    }
+    let mut matrix = vec![vec![' '; width]; height];
+    let mut text_regions = Vec::new();
+    let mut original_text = Vec::new();
+    for block in blocks {
+        let x = ((block.bbox.x0.max(0.0) / char_width).round() as usize).min(width.saturating_sub(1));
+        let y = ((block.bbox.y0.max(0.0) / char_height).round() as usize).min(height.saturating_sub(1));
+        let box_width = (((block.bbox.x1 - block.bbox.x0) / char_width).round() as usize).clamp(1, width - x);
+        let box_height = (((block.bbox.y1 - block.bbox.y0) / char_height).round() as usize).max(1);
+        let text = block.kind.text();
+        // Lines past the box's bottom run on below it rather than being lost
+        let lines = wrap_text(&text, box_width);
+        for (row, line) in (y..height).zip(&lines) {
+            for (col, ch) in (x..).zip(line.chars()) {
+                matrix[row][col] = ch;
+            }
+        }
+        original_text.push(text.clone());
+        text_regions.push(TextRegion {
+            bbox: CharBBox { x, y, width: box_width, height: box_height.max(lines.len()).min(height - y) },
+            confidence: 1.0,
+            text_content: text,
+            region_id: text_regions.len(),
+            is_italic: false,
+            label: None,
+            tag: block.kind.tag(),
+        });
+    }
+
+    let layout = LayoutNotes {
+        columns: detect_text_columns(&matrix),
+        ..LayoutNotes::default()
+    };
+
+    Ok(CharacterMatrix {
+        width,
+        height,
+        matrix,
+        text_regions,
+        original_text,
+        char_width,
+        char_height,
+        warnings,
+        monospace: None,
+        reading_order: None,
+        backend: None,
+        sparse: None,
+        settings_fingerprint: 0,
+        layout,
+    })
+}
+
+/// `text` broken into lines of at most `width` characters, at spaces where
+/// possible; paragraphs stay on their own lines.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let used = line.chars().count();
+            if used > 0 && used + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            } else if used > 0 {
+                line.push(' ');
+            }
+            // Words longer than the box are split across lines
+            while word.len() > width {
+                lines.push(word.drain(..width).collect());
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// ============= LAYOUT MODEL =============
+/// DocLayNet's classes, numbered as DocLayNet-trained detectors number them.
+pub const DOCLAYNET_CLASSES: [&str; 11] = [
+    "Caption",
+    "Footnote",
+    "Formula",
+    "List-item",
+    "Page-footer",
+    "Page-header",
+    "Picture",
+    "Section-header",
+    "Table",
+    "Text",
+    "Title",
+];
+
+/// Resolution pages are rendered at for the layout model.
+pub const LAYOUT_DPI: f32 = 144.0;
+/// Detections the model is less sure of are dropped.
+pub const LAYOUT_MIN_CONFIDENCE: f32 = 0.25;
+
+fn doclaynet_tag(class: usize) -> Option<RegionTag> {
+    match *DOCLAYNET_CLASSES.get(class)? {
+        "Title" | "Section-header" => Some(RegionTag::Title),
+        "Text" | "List-item" | "Caption" | "Footnote" | "Formula" => Some(RegionTag::Paragraph),
+        "Table" => Some(RegionTag::Table),
+        "Picture" => Some(RegionTag::Figure),
+        "Page-header" | "Page-footer" => Some(RegionTag::Footer),
+        _ => None,
+    }
+}
+
+/// One box found by the layout model, in points from the page's top-left
+/// corner as `[x0, y0, x1, y1]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutDetection {
+    /// Index into `DOCLAYNET_CLASSES`.
+    pub class: usize,
+    pub confidence: f32,
+    pub bbox: [f32; 4],
+}
+
+fn overlap(a: &[f32; 4], b: &[f32; 4]) -> f32 {
+    let area = |r: &[f32; 4]| (r[2] - r[0]).max(0.0) * (r[3] - r[1]).max(0.0);
+    let shared = area(&[a[0].max(b[0]), a[1].max(b[1]), a[2].min(b[2]), a[3].min(b[3])]);
+    let union = area(a) + area(b) - shared;
+    if union > 0.0 {
+        shared / union
+    } else {
+        0.0
+    }
+}
+
+/// Reads a YOLO-style detector output of shape `[1, 4 + classes, anchors]`:
+/// each anchor's box centre and size in input pixels, then a score per
+/// class. Keeps each anchor's best class when it reaches `min_confidence`
+/// and drops boxes overlapping a likelier one of the same class by more than
+/// `max_overlap` (intersection over union). `scale` is points per input
+/// pixel.
+pub fn decode_layout_output(
+    shape: &[usize],
+    data: &[f32],
+    min_confidence: f32,
+    max_overlap: f32,
+    scale: f32,
+) -> Vec<LayoutDetection> {
+    let [1, rows, anchors] = *shape else {
+        return Vec::new();
+    };
+    if rows <= 4 || data.len() < rows * anchors {
+        return Vec::new();
+    }
+    let at = |row: usize, anchor: usize| data[row * anchors + anchor];
+
+    let mut candidates: Vec<LayoutDetection> = (0..anchors)
+        .filter_map(|anchor| {
+            let (class, confidence) = (0..rows - 4)
+                .map(|class| (class, at(4 + class, anchor)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))?;
+            if confidence < min_confidence {
+                return None;
+            }
+            let (cx, cy, w, h) = (at(0, anchor), at(1, anchor), at(2, anchor), at(3, anchor));
+            let bbox = [cx - w / 2.0, cy - h / 2.0, cx + w / 2.0, cy + h / 2.0].map(|v| v.max(0.0) * scale);
+            Some(LayoutDetection { class, confidence, bbox })
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut kept: Vec<LayoutDetection> = Vec::new();
+    for candidate in candidates {
+        let suppressed = kept
+            .iter()
+            .any(|k| k.class == candidate.class && overlap(&k.bbox, &candidate.bbox) > max_overlap);
+        if !suppressed {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+/// The detections as tagged regions of a page-aligned matrix (such as the
+/// stext backend's), in reading order, each holding the text under it.
+pub fn layout_regions(char_matrix: &CharacterMatrix, detections: &[LayoutDetection]) -> Vec<TextRegion> {
+    let (width, height) = (char_matrix.width, char_matrix.height);
+    let mut boxes: Vec<(CharBBox, &LayoutDetection)> = detections
+        .iter()
+        .filter_map(|detection| {
+            let [x0, y0, x1, y1] = detection.bbox;
+            let x = ((x0 / char_matrix.char_width).floor() as usize).min(width);
+            let y = ((y0 / char_matrix.char_height).floor() as usize).min(height);
+            let x_end = ((x1 / char_matrix.char_width).ceil() as usize).min(width);
+            let y_end = ((y1 / char_matrix.char_height).ceil() as usize).min(height);
+            (x_end > x && y_end > y).then(|| (CharBBox { x, y, width: x_end - x, height: y_end - y }, detection))
+        })
+        .collect();
+    boxes.sort_by_key(|(bbox, _)| (bbox.y, bbox.x));
+
+    boxes
+        .into_iter()
+        .enumerate()
+        .map(|(region_id, (bbox, detection))| {
+            let text_content = char_matrix
+                .matrix
+                .iter()
+                .skip(bbox.y)
+                .take(bbox.height)
+                .map(|row| row.iter().skip(bbox.x).take(bbox.width).collect::<String>().trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            TextRegion {
+                bbox,
+                confidence: detection.confidence,
+                text_content,
+                region_id,
+                is_italic: false,
+                label: None,
+                tag: doclaynet_tag(detection.class),
+            }
+        })
+        .collect()
+}
+
+/// A layout detector loaded into ONNX Runtime.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub struct LayoutModel {
+    session: ort::session::Session,
+    /// Side of the square input image.
+    input_size: u32,
+}
+
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+fn model_error(error: impl std::fmt::Display) -> ChonkerError {
+    ChonkerError::ExternalTool { tool: "layout model", message: error.to_string() }
+}
+
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+impl LayoutModel {
+    pub fn load(path: &Path) -> Result<Self, ChonkerError> {
+        let session = ort::session::Session::builder()
+            .and_then(|builder| builder.commit_from_file(path))
+            .map_err(|e| model_error(format!("{}: {}", path.display(), e)))?;
+        // Exports with a dynamic input size report it as -1
+        let input_size = match session.inputs.first().map(|input| &input.input_type) {
+            Some(ort::value::ValueType::Tensor { dimensions, .. }) => {
+                dimensions.last().copied().filter(|&side| side > 0).map(|side| side as u32)
+            }
+            _ => None,
+        };
+        Ok(Self { session, input_size: input_size.unwrap_or(640) })
+    }
+
+    /// Runs the model on a page rendered as PNG at `dpi`. The page is scaled
+    /// to fit the input with grey padding right and below, as YOLO
+    /// letterboxing does.
+    pub fn detect(&self, png: &[u8], dpi: f32) -> Result<Vec<LayoutDetection>, ChonkerError> {
+        let page = image::load_from_memory(png).map_err(|e| ChonkerError::RenderFailed(e.to_string()))?.to_rgb8();
+        let size = self.input_size;
+        let resize = (size as f32 / page.width() as f32).min(size as f32 / page.height() as f32);
+        let resized = image::imageops::resize(
+            &page,
+            ((page.width() as f32 * resize).round() as u32).clamp(1, size),
+            ((page.height() as f32 * resize).round() as u32).clamp(1, size),
+            image::imageops::FilterType::Triangle,
+        );
+
+        let plane = (size * size) as usize;
+        let mut input = vec![114.0 / 255.0; 3 * plane];
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            let i = (y * size + x) as usize;
+            for channel in 0..3 {
+                input[channel * plane + i] = pixel[channel] as f32 / 255.0;
+            }
+        }
+        let side = size as usize;
+        let tensor = ort::value::Tensor::from_array(([1, 3, side, side], input.into_boxed_slice())).map_err(model_error)?;
+        let outputs = self.session.run(ort::inputs![tensor].map_err(model_error)?).map_err(model_error)?;
+        let (shape, data) = outputs[0].try_extract_raw_tensor::<f32>().map_err(model_error)?;
+        let shape: Vec<usize> = shape.iter().map(|&d| d.max(0) as usize).collect();
+        Ok(decode_layout_output(&shape, data, LAYOUT_MIN_CONFIDENCE, 0.5, 72.0 / dpi / resize))
+    }
+}
+
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+static LAYOUT_MODEL: std::sync::Mutex<Option<(PathBuf, std::sync::Arc<LayoutModel>)>> = std::sync::Mutex::new(None);
+
+/// The model at `path`, loaded once and kept until another path is asked for.
+#[cfg(all(feature = "onnx", not(target_arch = "wasm32")))]
+pub fn layout_model(path: &Path) -> Result<std::sync::Arc<LayoutModel>, ChonkerError> {
+    let mut loaded = LAYOUT_MODEL.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, model)) = loaded.as_ref().filter(|(loaded_path, _)| loaded_path == path) {
+        return Ok(model.clone());
+    }
+    let model = std::sync::Arc::new(LayoutModel::load(path)?);
+    *loaded = Some((path.to_path_buf(), model.clone()));
+    Ok(model)
+}
+
+// ============= XY-CUT SEGMENTATION =============
+/// How a page's glyphs are grouped into text regions, chosen per document
+/// and persisted as `<name>.segmentation.json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Segmentation {
+    /// The backend's own regions: glyphs merged along a line for PDFium,
+    /// blocks for mutool stext.
+    #[default]
+    Backend,
+    /// Recursive XY-cut at whitespace valleys, which keeps columns apart.
+    XyCut,
+}
+
+impl Segmentation {
+    pub const ALL: [Segmentation; 2] = [Segmentation::Backend, Segmentation::XyCut];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Segmentation::Backend => "Backend",
+            Segmentation::XyCut => "XY-cut",
+        }
+    }
+}
+
+fn segmentation_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("segmentation.json")
+}
+
+pub fn load_segmentation(pdf_path: &Path) -> Segmentation {
+    std::fs::read_to_string(segmentation_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_segmentation(pdf_path: &Path, segmentation: Segmentation) -> Result<()> {
+    let path = segmentation_path(pdf_path);
+    if segmentation == Segmentation::default() {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&segmentation)?)?;
+    Ok(())
+}
+
+/// Narrowest valleys XY-cut splits at: more blank rows than the page's usual
+/// line spacing, and more blank columns than the spaces between words.
+const XY_CUT_MIN_ROWS: usize = 1;
+const XY_CUT_MIN_COLS: usize = 3;
+
+/// Replaces the backend's regions with XY-cut blocks when `segmentation`
+/// asks for them. Labels and tags refer to regions by id, so apply them
+/// after this.
+pub fn apply_segmentation(char_matrix: &mut CharacterMatrix, segmentation: Segmentation) {
+    if segmentation == Segmentation::XyCut {
+        let regions = xy_cut_regions(char_matrix);
+        char_matrix.replace_regions(regions);
+        if char_matrix.settings_fingerprint != 0 {
+            char_matrix.settings_fingerprint = segmented_fingerprint(char_matrix.settings_fingerprint, segmentation);
+        }
+    }
+}
+
+/// The page's glyphs split into blocks by recursive XY-cut, in the order
+/// the cuts visit them: top to bottom, and column by column where a page
+/// splits into columns. Each block keeps the mean confidence of the
+/// backend's regions starting inside it, and is italic when they all are.
+pub fn xy_cut_regions(char_matrix: &CharacterMatrix) -> Vec<TextRegion> {
+    let mut blocks = Vec::new();
+    let page = CharBBox { x: 0, y: 0, width: char_matrix.width, height: char_matrix.height };
+    let min_rows = (line_spacing(char_matrix) + 1).max(XY_CUT_MIN_ROWS);
+    xy_cut(char_matrix, page, min_rows, &mut blocks);
+
+    blocks
+        .into_iter()
+        .enumerate()
+        .map(|(region_id, bbox)| {
+            let inside: Vec<&TextRegion> =
+                char_matrix.text_regions.iter().filter(|region| bbox.contains(region.bbox.x, region.bbox.y)).collect();
+            let confidence = match inside.len() {
+                0 => 1.0,
+                n => inside.iter().map(|region| region.confidence).sum::<f32>() / n as f32,
+            };
+            let text_content = (bbox.y..bbox.y + bbox.height)
+                .map(|row| {
+                    let line: String = (bbox.x..bbox.x + bbox.width).map(|col| char_matrix.cell(row, col)).collect();
+                    line.trim_end().to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string();
+            TextRegion {
+                bbox,
+                confidence,
+                text_content,
+                region_id,
+                is_italic: !inside.is_empty() && inside.iter().all(|region| region.is_italic),
+                label: None,
+                tag: None,
+            }
+        })
+        .collect()
+}
+
+/// The most common number of blank rows between consecutive lines of the
+/// page, the smaller on a tie: 0 for single-spaced text, 1 for double.
+fn line_spacing(char_matrix: &CharacterMatrix) -> usize {
+    let lines: Vec<usize> = (0..char_matrix.height)
+        .filter(|&row| (0..char_matrix.width).any(|col| !char_matrix.cell(row, col).is_whitespace()))
+        .collect();
+    let mut spacings: BTreeMap<usize, usize> = BTreeMap::new();
+    for pair in lines.windows(2) {
+        *spacings.entry(pair[1] - pair[0] - 1).or_default() += 1;
+    }
+    spacings
+        .into_iter()
+        .max_by_key(|&(spacing, count)| (count, std::cmp::Reverse(spacing)))
+        .map_or(0, |(spacing, _)| spacing)
+}
+
+/// Trims `area` to its glyphs and splits it at its widest valley of at least
+/// `min_rows` blank rows or `XY_CUT_MIN_COLS` blank columns, until no valley
+/// is wide enough.
+fn xy_cut(char_matrix: &CharacterMatrix, area: CharBBox, min_rows: usize, blocks: &mut Vec<CharBBox>) {
+    let ink = |row: usize, col: usize| !char_matrix.cell(row, col).is_whitespace();
+    let rows: Vec<bool> =
+        (area.y..area.y + area.height).map(|row| (area.x..area.x + area.width).any(|col| ink(row, col))).collect();
+    let cols: Vec<bool> =
+        (area.x..area.x + area.width).map(|col| (area.y..area.y + area.height).any(|row| ink(row, col))).collect();
+    let (Some(top), Some(bottom), Some(left), Some(right)) = (
+        rows.iter().position(|&filled| filled),
+        rows.iter().rposition(|&filled| filled),
+        cols.iter().position(|&filled| filled),
+        cols.iter().rposition(|&filled| filled),
+    ) else {
+        return;
+    };
+    let area = CharBBox { x: area.x + left, y: area.y + top, width: right - left + 1, height: bottom - top + 1 };
+
+    let row_gap = widest_gap(&rows[top..=bottom]).filter(|&(_, len)| len >= min_rows);
+    let col_gap = widest_gap(&cols[left..=right]).filter(|&(_, len)| len >= XY_CUT_MIN_COLS);
+    // Gaps are compared in points, since a cell is taller than it is wide
+    let cut = match (row_gap, col_gap) {
+        (Some((_, rows)), Some(cols))
+            if cols.1 as f32 * char_matrix.char_width > rows as f32 * char_matrix.char_height =>
+        {
+            Some((false, cols))
+        }
+        (Some(rows), _) => Some((true, rows)),
+        (None, cols) => cols.map(|cols| (false, cols)),
+    };
+    match cut {
+        None => blocks.push(area),
+        Some((true, (start, len))) => {
+            xy_cut(char_matrix, CharBBox { height: start, ..area }, min_rows, blocks);
+            let rest = CharBBox { y: area.y + start + len, height: area.height - start - len, ..area };
+            xy_cut(char_matrix, rest, min_rows, blocks);
+        }
+        Some((false, (start, len))) => {
+            xy_cut(char_matrix, CharBBox { width: start, ..area }, min_rows, blocks);
+            let rest = CharBBox { x: area.x + start + len, width: area.width - start - len, ..area };
+            xy_cut(char_matrix, rest, min_rows, blocks);
+        }
+    }
+}
+
+/// Start and length of the longest run of unfilled lines.
+fn widest_gap(filled: &[bool]) -> Option<(usize, usize)> {
+    let mut widest: Option<(usize, usize)> = None;
+    let mut start = None;
+    for (i, &line) in filled.iter().chain([&true]).enumerate() {
+        match (line, start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                if widest.is_none_or(|(_, len)| i - s > len) {
+                    widest = Some((s, i - s));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    widest
+}
+
+// ============= MATRIX HISTORY =============
+/// A previously saved copy of a page's matrix, stored next to the PDF in
+/// `<name>.matrix_history/`.
+#[derive(Debug, Clone)]
+pub struct MatrixVersion {
+    pub path: PathBuf,
+    pub page: usize,
+    pub saved_at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowDiff {
+    pub row: usize,
+    pub old: String,
+    pub new: String,
+}
+
+fn matrix_history_dir(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("matrix_history")
+}
+
+pub fn matrix_to_text(matrix: &[Vec<char>]) -> String {
+    let mut content = String::with_capacity(matrix.len() * (matrix.first().map_or(0, |r| r.len()) + 1));
+    for row in matrix {
+        content.extend(row.iter());
+        content.push('\n');
+    }
+    content
+}
+
+pub fn text_to_matrix(text: &str) -> Vec<Vec<char>> {
+    text.lines().map(|line| line.chars().collect()).collect()
+}
+
+pub fn write_matrix_version(pdf_path: &Path, page: usize, content: &str) -> Result<PathBuf> {
+    let dir = matrix_history_dir(pdf_path);
+    std::fs::create_dir_all(&dir)?;
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let path = dir.join(format!("p{:04}-{}.matrix.txt", page + 1, millis));
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// Lists saved versions of one page, newest first.
+pub fn list_matrix_versions(pdf_path: &Path, page: usize) -> Vec<MatrixVersion> {
+    let prefix = format!("p{:04}-", page + 1);
+    let mut versions: Vec<MatrixVersion> = std::fs::read_dir(matrix_history_dir(pdf_path))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let millis: u64 = name
+                        .strip_prefix(&prefix)?
+                        .strip_suffix(".matrix.txt")?
+                        .parse()
+                        .ok()?;
+                    Some(MatrixVersion {
+                        path: entry.path(),
+                        page,
+                        saved_at: UNIX_EPOCH + Duration::from_millis(millis),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    versions.sort_by_key(|version| std::cmp::Reverse(version.saved_at));
+    versions
+}
+
+/// Row-by-row comparison. Matrix rows are positional, so rows are compared by
+/// index rather than aligned like a text diff.
+pub fn diff_matrix_rows(old: &str, new: &str) -> Vec<RowDiff> {
+    let old_rows: Vec<&str> = old.lines().collect();
+    let new_rows: Vec<&str> = new.lines().collect();
+    (0..old_rows.len().max(new_rows.len()))
+        .filter_map(|row| {
+            let old = old_rows.get(row).copied().unwrap_or("");
+            let new = new_rows.get(row).copied().unwrap_or("");
+            (old.trim_end() != new.trim_end()).then(|| RowDiff {
+                row,
+                old: old.to_string(),
+                new: new.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn format_age(saved_at: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(saved_at)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    match secs {
+        0..=59 => format!("{}s ago", secs),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+// ============= EDIT LOG =============
+/// Per-cell difference between two matrices, sized to the larger of each
+/// row; cells missing on one side count as blanks.
+pub fn matrix_diff(a: &[Vec<char>], b: &[Vec<char>]) -> Vec<Vec<bool>> {
+    let cell = |m: &[Vec<char>], row: usize, col: usize| {
+        m.get(row).and_then(|r| r.get(col)).copied().unwrap_or(' ')
+    };
+    (0..a.len().max(b.len()))
+        .map(|row| {
+            let width = a.get(row).map_or(0, Vec::len).max(b.get(row).map_or(0, Vec::len));
+            (0..width).map(|col| cell(a, row, col) != cell(b, row, col)).collect()
+        })
+        .collect()
+}
+
+/// One hand-edited cell, recorded when the page is saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellEdit {
+    pub page: usize,
+    pub row: usize,
+    pub col: usize,
+    pub before: char,
+    pub after: char,
+    /// Unix time in milliseconds of the save that recorded the edit.
+    pub timestamp: u64,
+}
+
+/// The exported changelog: every recorded edit of one document.
+#[derive(Debug, Serialize)]
+pub struct EditChangelog<'a> {
+    pub document: String,
+    pub edits: &'a [CellEdit],
+}
+
+/// Cells that differ between two states of a page; cells missing on one
+/// side count as blanks.
+pub fn cell_edits(page: usize, before: &[Vec<char>], after: &[Vec<char>], timestamp: u64) -> Vec<CellEdit> {
+    let cell = |m: &[Vec<char>], row: usize, col: usize| m.get(row).and_then(|r| r.get(col)).copied().unwrap_or(' ');
+    let mut edits = Vec::new();
+    for (row, changed) in matrix_diff(before, after).iter().enumerate() {
+        for col in changed.iter().enumerate().filter(|(_, &differs)| differs).map(|(col, _)| col) {
+            edits.push(CellEdit {
+                page,
+                row,
+                col,
+                before: cell(before, row, col),
+                after: cell(after, row, col),
+                timestamp,
+            });
+        }
+    }
+    edits
+}
+
+/// Carries the cells changed from `base` to `edited` onto `fresh`, a new
+/// extraction of the same page. Returns the merged matrix and the number of
+/// conflicts: edited cells that `fresh` also changed (the edit wins) or that
+/// fall outside it (the edit is dropped).
+pub fn merge_cell_edits(base: &[Vec<char>], edited: &[Vec<char>], fresh: &[Vec<char>]) -> (Vec<Vec<char>>, usize) {
+    let mut merged = fresh.to_vec();
+    let mut conflicts = 0;
+    for edit in cell_edits(0, base, edited, 0) {
+        match merged.get_mut(edit.row).and_then(|row| row.get_mut(edit.col)) {
+            Some(cell) => {
+                if *cell != edit.before && *cell != edit.after {
+                    conflicts += 1;
+                }
+                *cell = edit.after;
+            }
+            None => conflicts += 1,
+        }
+    }
+    (merged, conflicts)
+}
+
+fn edit_log_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("edit_log.json")
+}
+
+pub fn load_edit_log(pdf_path: &Path) -> Vec<CellEdit> {
+    std::fs::read_to_string(edit_log_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_edit_log(pdf_path: &Path, edits: &[CellEdit]) -> Result<()> {
+    std::fs::write(edit_log_path(pdf_path), serde_json::to_string_pretty(edits)?)?;
+    Ok(())
+}
+
+/// Saves `edited` as a new version of `page`, logging its cell edits against
+/// the latest saved version, or `extracted` when there is none. Returns the
+/// number of edits logged.
+pub fn record_matrix_save(pdf_path: &Path, page: usize, extracted: &[Vec<char>], edited: &[Vec<char>]) -> Result<usize> {
+    let previous = list_matrix_versions(pdf_path, page)
+        .first()
+        .and_then(|version| std::fs::read_to_string(&version.path).ok())
+        .map(|text| text_to_matrix(&text))
+        .unwrap_or_else(|| extracted.to_vec());
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    let edits = cell_edits(page, &previous, edited, timestamp);
+    if !edits.is_empty() {
+        let mut log = load_edit_log(pdf_path);
+        log.extend(edits.iter().cloned());
+        save_edit_log(pdf_path, &log)?;
+    }
+    write_matrix_version(pdf_path, page, &matrix_to_text(edited))?;
+    Ok(edits.len())
+}
+
+// ============= EXPORT =============
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Txt,
+    Json,
+    Alto,
+    /// Region texts in reading order.
+    Reading,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Txt => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Alto => "alto.xml",
+            ExportFormat::Reading => "reading.txt",
+        }
+    }
+
+    /// The format an output path's extension asks for, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        [ExportFormat::Alto, ExportFormat::Reading, ExportFormat::Json, ExportFormat::Txt]
+            .into_iter()
+            .find(|format| name.ends_with(&format!(".{}", format.extension())))
+            .or_else(|| name.ends_with(".xml").then_some(ExportFormat::Alto))
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "txt" | "text" => Ok(ExportFormat::Txt),
+            "json" => Ok(ExportFormat::Json),
+            "alto" | "xml" => Ok(ExportFormat::Alto),
+            "reading" => Ok(ExportFormat::Reading),
+            other => Err(anyhow::anyhow!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+/// Parses a 1-based page spec such as `1-5,8,10-12` into sorted, de-duplicated
+/// 0-based page indices. An empty spec selects every page.
+pub fn parse_page_range(spec: &str, total_pages: usize) -> Result<Vec<usize>> {
+    if spec.trim().is_empty() {
+        return Ok((0..total_pages).collect());
+    }
+
+    let mut pages = Vec::new();
+    for (start, end) in page_spans(spec)? {
+        if end > total_pages {
+            return Err(ChonkerError::PageOutOfRange { page: end - 1, total: total_pages }.into());
+        }
+        pages.extend(start - 1..end);
+    }
+
+    pages.sort_unstable();
+    pages.dedup();
+    Ok(pages)
+}
+
+/// The 1-based inclusive ranges of a page spec, checked without a page
+/// count.
+fn page_spans(spec: &str) -> Result<Vec<(usize, usize)>> {
+    let mut spans = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a.trim().parse::<usize>()?, b.trim().parse::<usize>()?),
+            None => {
+                let page = part.parse::<usize>()?;
+                (page, page)
+            }
+        };
+        if start == 0 || start > end {
+            return Err(anyhow::anyhow!("Invalid page range: {}", part));
+        }
+        spans.push((start, end));
+    }
+    Ok(spans)
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the matrix as a minimal ALTO v3 page. Each run of non-space cells
+/// becomes a `String`, positioned using the matrix character dimensions.
+pub fn matrix_to_alto(char_matrix: &CharacterMatrix, page_index: usize) -> String {
+    pages_to_alto(&[(page_index, char_matrix)])
+}
+
+/// One ALTO document with a `Page` per matrix, as `matrix_to_alto` lays them out.
+pub fn pages_to_alto(pages: &[(usize, &CharacterMatrix)]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<alto xmlns=\"http://www.loc.gov/standards/alto/ns-v3#\">\n");
+
+    let mut tags: Vec<RegionTag> = pages
+        .iter()
+        .flat_map(|(_, char_matrix)| char_matrix.text_regions.iter().filter_map(|r| r.tag))
+        .collect();
+    tags.sort_by_key(|t| t.as_str());
+    tags.dedup();
+    if !tags.is_empty() {
+        xml.push_str("<Tags>\n");
+        for tag in &tags {
+            xml.push_str(&format!(
+                "<StructureTag ID=\"TAG_{}\" LABEL=\"{}\"/>\n",
+                tag.as_str(),
+                tag.as_str()
+            ));
+        }
+        xml.push_str("</Tags>\n");
+    }
+    xml.push_str("<Layout>\n");
+    for (page_index, char_matrix) in pages {
+        push_alto_page(&mut xml, char_matrix, *page_index);
+    }
+    xml.push_str("</Layout>\n</alto>\n");
+    xml
+}
+
+fn push_alto_page(xml: &mut String, char_matrix: &CharacterMatrix, page_index: usize) {
+    let cw = char_matrix.char_width;
+    let ch = char_matrix.char_height;
+    xml.push_str(&format!(
+        "<Page ID=\"Page{}\" PHYSICAL_IMG_NR=\"{}\" WIDTH=\"{:.2}\" HEIGHT=\"{:.2}\">\n<PrintSpace>\n",
+        page_index + 1,
+        page_index + 1,
+        char_matrix.width as f32 * cw,
+        char_matrix.height as f32 * ch
+    ));
+
+    let mut word_id = 0;
+    for (row_idx, row) in char_matrix.rows().iter().enumerate() {
+        let mut words = Vec::new();
+        let mut col = 0;
+        while col < row.len() {
+            if row[col].is_whitespace() {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while col < row.len() && !row[col].is_whitespace() {
+                col += 1;
+            }
+            words.push((start, row[start..col].iter().collect::<String>()));
+        }
+        if words.is_empty() {
+            continue;
+        }
+
+        // Tags of the regions this line runs through
+        let line_end = words.last().map_or(0, |(start, word)| start + word.chars().count());
+        let mut line_tags: Vec<RegionTag> = char_matrix
+            .text_regions
+            .iter()
+            .filter(|r| {
+                row_idx >= r.bbox.y
+                    && row_idx < r.bbox.y + r.bbox.height
+                    && r.bbox.x < line_end
+                    && words[0].0 < r.bbox.x + r.bbox.width
+            })
+            .filter_map(|r| r.tag)
+            .collect();
+        line_tags.sort_by_key(|t| t.as_str());
+        line_tags.dedup();
+        let tagrefs = if line_tags.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " TAGREFS=\"{}\"",
+                line_tags.iter().map(|t| format!("TAG_{}", t.as_str())).collect::<Vec<_>>().join(" ")
+            )
+        };
+
+        let vpos = row_idx as f32 * ch;
+        xml.push_str(&format!(
+            "<TextBlock ID=\"p{}_b{}\"{}>\n<TextLine ID=\"p{}_t{}\" HPOS=\"{:.2}\" VPOS=\"{:.2}\">\n",
+            page_index + 1,
+            row_idx + 1,
+            tagrefs,
+            page_index + 1,
+            row_idx + 1,
+            words[0].0 as f32 * cw,
+            vpos
+        ));
+        for (start, word) in words {
+            word_id += 1;
+            xml.push_str(&format!(
+                "<String ID=\"p{}_w{}\" CONTENT=\"{}\" HPOS=\"{:.2}\" VPOS=\"{:.2}\" WIDTH=\"{:.2}\" HEIGHT=\"{:.2}\"/>\n",
+                page_index + 1,
+                word_id,
+                xml_escape(&word),
+                start as f32 * cw,
+                vpos,
+                word.chars().count() as f32 * cw,
+                ch
+            ));
+        }
+        xml.push_str("</TextLine>\n</TextBlock>\n");
+    }
+
+    xml.push_str("</PrintSpace>\n</Page>\n");
+}
+
+/// Renders several pages into one file. Text formats separate pages with a
+/// form feed as `pdftotext` does, JSON becomes an array of matrices tagged
+/// with their 1-based page, and ALTO gets one `Page` per matrix.
+pub fn render_document_export(pages: &[(usize, CharacterMatrix)], format: ExportFormat) -> Result<String> {
+    #[derive(Serialize)]
+    struct DocumentPage<'a> {
+        page: usize,
+        #[serde(flatten)]
+        matrix: Cow<'a, CharacterMatrix>,
+    }
+
+    Ok(match format {
+        ExportFormat::Json => serde_json::to_string_pretty(
+            &pages
+                .iter()
+                .map(|(page_index, matrix)| DocumentPage { page: page_index + 1, matrix: matrix.dense() })
+                .collect::<Vec<_>>(),
+        )?,
+        ExportFormat::Alto => {
+            pages_to_alto(&pages.iter().map(|(page_index, matrix)| (*page_index, matrix)).collect::<Vec<_>>())
+        }
+        ExportFormat::Txt | ExportFormat::Reading => pages
+            .iter()
+            .map(|(page_index, matrix)| render_export(matrix, *page_index, format))
+            .collect::<Result<Vec<_>>>()?
+            .join("\x0c"),
+    })
+}
+
+pub fn render_export(char_matrix: &CharacterMatrix, page_index: usize, format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        // A user-defined reading order turns the text export into a read-through
+        ExportFormat::Txt if char_matrix.reading_order.is_some() => matrix_to_reading_text(char_matrix),
+        ExportFormat::Txt => matrix_to_text(&char_matrix.rows()),
+        ExportFormat::Json => serde_json::to_string_pretty(&*char_matrix.dense())?,
+        ExportFormat::Alto => matrix_to_alto(char_matrix, page_index),
+        ExportFormat::Reading => matrix_to_reading_text(char_matrix),
+    })
+}
+
+pub fn pdf_page_count(path: &Path) -> Result<usize> {
+    if Command::new("mutool").arg("--version").output().is_err() {
+        return Err(ChonkerError::ExternalTool { tool: "mutool", message: "not found".to_string() }.into());
+    }
+
+    let output = Command::new("mutool").arg("info").arg(path).output()?;
+
+    let info = String::from_utf8_lossy(&output.stdout);
+    for line in info.lines() {
+        if line.contains("Pages:") {
+            if let Some(pages_str) = line.split(':').nth(1) {
+                return pages_str
+                    .trim()
+                    .parse()
+                    .map_err(|e| anyhow::anyhow!("Parse error: {}", e));
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!("Could not determine page count"))
+}
+
+// ============= REGION LABELS =============
+/// Region labels keyed by page index and then region id, persisted next to
+/// the PDF as `<name>.region_labels.json`.
+pub type RegionLabels = HashMap<usize, HashMap<usize, String>>;
+
+const REGIONS_CSV_HEADER: &str = "page,region_id,x,y,width,height,confidence,italic,label,text";
+
+fn region_labels_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("region_labels.json")
+}
+
+pub fn load_region_labels(pdf_path: &Path) -> RegionLabels {
+    std::fs::read_to_string(region_labels_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_region_labels(pdf_path: &Path, labels: &RegionLabels) -> Result<()> {
+    std::fs::write(region_labels_path(pdf_path), serde_json::to_string_pretty(labels)?)?;
+    Ok(())
+}
+
+pub fn apply_region_labels(char_matrix: &mut CharacterMatrix, labels: Option<&HashMap<usize, String>>) {
+    for region in &mut char_matrix.text_regions {
+        region.label = labels.and_then(|l| l.get(&region.region_id)).cloned();
+    }
+}
+
+/// Region tags keyed like `RegionLabels`, persisted as `<name>.region_tags.json`.
+pub type RegionTags = HashMap<usize, HashMap<usize, RegionTag>>;
+
+fn region_tags_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("region_tags.json")
+}
+
+pub fn load_region_tags(pdf_path: &Path) -> RegionTags {
+    std::fs::read_to_string(region_tags_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_region_tags(pdf_path: &Path, tags: &RegionTags) -> Result<()> {
+    std::fs::write(region_tags_path(pdf_path), serde_json::to_string_pretty(tags)?)?;
+    Ok(())
+}
+
+pub fn apply_region_tags(char_matrix: &mut CharacterMatrix, tags: Option<&HashMap<usize, RegionTag>>) {
+    for region in &mut char_matrix.text_regions {
+        region.tag = tags.and_then(|t| t.get(&region.region_id)).copied();
+    }
+}
+
+/// User-defined reading order per page as region ids, persisted as
+/// `<name>.reading_order.json`.
+pub type ReadingOrders = HashMap<usize, Vec<usize>>;
+
+fn reading_orders_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("reading_order.json")
+}
+
+pub fn load_reading_orders(pdf_path: &Path) -> ReadingOrders {
+    std::fs::read_to_string(reading_orders_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_reading_orders(pdf_path: &Path, orders: &ReadingOrders) -> Result<()> {
+    std::fs::write(reading_orders_path(pdf_path), serde_json::to_string_pretty(orders)?)?;
+    Ok(())
+}
+
+/// Region indices in reading order: the user-defined sequence first, then
+/// any regions it does not mention from top to bottom, left to right.
+pub fn reading_sequence(char_matrix: &CharacterMatrix) -> Vec<usize> {
+    let regions = &char_matrix.text_regions;
+    let mut sequence: Vec<usize> = char_matrix
+        .reading_order
+        .iter()
+        .flatten()
+        .filter_map(|id| regions.iter().position(|r| r.region_id == *id))
+        .collect();
+    let mut rest: Vec<usize> = (0..regions.len()).filter(|i| !sequence.contains(i)).collect();
+    rest.sort_by_key(|&i| (regions[i].bbox.y, regions[i].bbox.x));
+    sequence.extend(rest);
+    sequence
+}
+
+/// Region texts in reading order, separated by blank lines.
+pub fn matrix_to_reading_text(char_matrix: &CharacterMatrix) -> String {
+    let rows = char_matrix.rows();
+    let mut text = reading_sequence(char_matrix)
+        .into_iter()
+        .map(|i| region_text(&rows, &char_matrix.text_regions[i].bbox))
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    text.push('\n');
+    text
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits one CSV record, honouring double-quoted fields with `""` escapes.
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (ch, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// One row per region at or above `min_confidence` across the given pages,
+/// for editing labels in a spreadsheet.
+pub fn regions_to_csv(cache: &PageCache, min_confidence: f32) -> String {
+    let mut pages: Vec<&usize> = cache.keys().collect();
+    pages.sort();
+
+    let mut csv = String::from(REGIONS_CSV_HEADER);
+    csv.push('\n');
+    for page in pages {
+        for region in cache[page].text_regions.iter().filter(|region| region.confidence >= min_confidence) {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{:.2},{},{},{}\n",
+                page + 1,
+                region.region_id,
+                region.bbox.x,
+                region.bbox.y,
+                region.bbox.width,
+                region.bbox.height,
+                region.confidence,
+                region.is_italic,
+                csv_field(region.label.as_deref().unwrap_or("")),
+                csv_field(&region.text_content)
+            ));
+        }
+    }
+    csv
+}
+
+/// Reads the `page`, `region_id` and `label` columns back from an edited CSV.
+/// Columns are located by header name so they may be reordered; an empty
+/// label clears the region's label.
+pub fn labels_from_csv(csv: &str) -> Result<RegionLabels> {
+    let mut lines = csv.lines().filter(|l| !l.trim().is_empty());
+    let header = parse_csv_line(lines.next().ok_or_else(|| anyhow::anyhow!("CSV is empty"))?);
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("CSV is missing the '{}' column", name))
+    };
+    let (page_col, id_col, label_col) = (column("page")?, column("region_id")?, column("label")?);
+
+    let mut labels = RegionLabels::new();
+    for (line_no, line) in lines.enumerate() {
+        let fields = parse_csv_line(line);
+        let field = |col: usize| fields.get(col).map(|f| f.trim()).unwrap_or("");
+        let page: usize = field(page_col)
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Row {}: bad page: {}", line_no + 2, e))?;
+        let region_id: usize = field(id_col)
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Row {}: bad region_id: {}", line_no + 2, e))?;
+        let label = field(label_col);
+        if page == 0 {
+            return Err(anyhow::anyhow!("Row {}: pages are numbered from 1", line_no + 2));
+        }
+        if !label.is_empty() {
+            labels
+                .entry(page - 1)
+                .or_default()
+                .insert(region_id, label.to_string());
+        }
+    }
+    Ok(labels)
+}
+
+// ============= TABLES =============
+/// A region read as a grid of cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedTable {
+    pub region_id: usize,
+    pub bbox: CharBBox,
+    /// Cell texts row by row, every row as wide as the table; blank rows are
+    /// left out.
+    pub cells: Vec<Vec<String>>,
+}
+
+/// The page's tables: regions tagged `Table`, and untagged regions whose
+/// text splits into two or more columns over two or more rows. Columns are
+/// split at gutters of two or more cells left blank in every row, as
+/// `align_columns` reads them.
+pub fn detect_tables(char_matrix: &CharacterMatrix) -> Vec<DetectedTable> {
+    char_matrix
+        .text_regions
+        .iter()
+        .filter(|region| matches!(region.tag, None | Some(RegionTag::Table)))
+        .filter_map(|region| {
+            let cells = table_cells(char_matrix, &region.bbox);
+            let columns = cells.first().map_or(0, Vec::len);
+            let tagged = region.tag == Some(RegionTag::Table);
+            (tagged || (columns >= 2 && cells.len() >= 2)).then(|| DetectedTable {
+                region_id: region.region_id,
+                bbox: region.bbox.clone(),
+                cells,
+            })
+        })
+        .collect()
+}
+
+fn table_cells(char_matrix: &CharacterMatrix, bbox: &CharBBox) -> Vec<Vec<String>> {
+    let rows: Vec<Vec<char>> = (bbox.y..bbox.y + bbox.height)
+        .map(|row| (bbox.x..bbox.x + bbox.width).map(|col| char_matrix.cell(row, col)).collect::<Vec<char>>())
+        .filter(|row| row.iter().any(|ch| !ch.is_whitespace()))
+        .collect();
+    let blank = |col: usize| rows.iter().all(|row| row[col].is_whitespace());
+
+    let mut columns = Vec::new();
+    let mut col = 0;
+    while col < bbox.width {
+        if blank(col) {
+            col += 1;
+            continue;
+        }
+        let start = col;
+        // A single blank column stays inside the cell, as a space between words
+        while col < bbox.width && !(blank(col) && (col + 1 == bbox.width || blank(col + 1))) {
+            col += 1;
+        }
+        columns.push(start..col);
+    }
+    rows.iter()
+        .map(|row| columns.iter().map(|span| row[span.clone()].iter().collect::<String>().trim().to_string()).collect())
+        .collect()
+}
+
+/// The cells as CSV, one record per row.
+pub fn table_to_csv(cells: &[Vec<String>]) -> String {
+    cells
+        .iter()
+        .map(|row| row.iter().map(|cell| csv_field(cell)).collect::<Vec<_>>().join(",") + "\n")
+        .collect()
+}
+
+// ============= PAGE CACHE =============
+/// Extracted matrices keyed by page index.
+pub type PageCache = HashMap<usize, CharacterMatrix>;
+
+/// Hash of the settings that shape a page's extraction: the char size, the
+/// backend asked for (`None` for mutool with the PDFium fallback), the
+/// monospace classifier, which every backend consults, and how regions are
+/// segmented.
+pub fn extraction_fingerprint(
+    dimensions: Option<CharDimensions>,
+    backend: Option<ExtractionBackend>,
+    segmentation: Segmentation,
+    model: &MonospaceModel,
+) -> u64 {
+    let size = match dimensions {
+        Some(d) => format!("{}x{}", d.char_width, d.char_height),
+        None => "auto".to_string(),
+    };
+    let backend = backend.map_or("auto", ExtractionBackend::label);
+    let inputs = format!("{};{};{:?};{}", size, backend, model.weights, model.bias);
+    segmented_fingerprint(fnv1a(inputs.as_bytes()), segmentation)
+}
+
+/// `fingerprint` with `segmentation` mixed in. The backend's own regions
+/// leave it as extracted, so segmenting an extracted page can update it.
+fn segmented_fingerprint(fingerprint: u64, segmentation: Segmentation) -> u64 {
+    match segmentation {
+        Segmentation::Backend => fingerprint,
+        Segmentation::XyCut => fnv1a(format!("{};{:?}", fingerprint, segmentation).as_bytes()),
+    }
+}
+
+// ============= DISK CACHE =============
+/// Bump when cached matrices or renders would no longer match fresh output.
+const DISK_CACHE_VERSION: u32 = 3;
+/// Extractor recorded in matrix keys: mutool, falling back to PDFium.
+const MATRIX_PIPELINE: &str = "mutool+pdfium";
+
+/// `CHONKER5_CACHE_DIR`, else `cache_dir` in `chonker.toml`, else
+/// `$XDG_CACHE_HOME/chonker5`, else `~/.cache/chonker5`.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("CHONKER5_CACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = ChonkerConfig::load().cache_dir {
+        return Some(dir);
+    }
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("chonker5"))
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is stable between builds, so
+/// cache keys survive upgrades.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+static DOCUMENT_HASHES: std::sync::Mutex<Vec<(PathBuf, u64, SystemTime, u64)>> =
+    std::sync::Mutex::new(Vec::new());
+
+/// Content hash of a PDF, remembered per path until its size or
+/// modification time changes.
+pub fn document_hash(path: &Path) -> Option<u64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let (len, modified) = (metadata.len(), metadata.modified().ok()?);
+    let known = |hashes: &[(PathBuf, u64, SystemTime, u64)]| {
+        hashes
+            .iter()
+            .find(|(p, l, m, _)| p == path && *l == len && *m == modified)
+            .map(|&(.., hash)| hash)
+    };
+    if let Some(hash) = known(&DOCUMENT_HASHES.lock().unwrap_or_else(|e| e.into_inner())) {
+        return Some(hash);
+    }
+
+    let hash = fnv1a(&std::fs::read(path).ok()?);
+    let mut hashes = DOCUMENT_HASHES.lock().unwrap_or_else(|e| e.into_inner());
+    hashes.retain(|(p, ..)| p != path);
+    hashes.push((path.to_path_buf(), len, modified, hash));
+    Some(hash)
+}
+
+/// Identifies one cached artifact: a rendered page, an extracted matrix or
+/// ferrules' output for a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCacheKey {
+    pub file_hash: u64,
+    pub page: usize,
+    /// Render resolution; 0 for matrices, which do not depend on it.
+    pub dpi: u32,
+    pub backend: &'static str,
+    /// Hash of any other inputs that change the output.
+    pub settings: u64,
+}
+
+impl DiskCacheKey {
+    /// `backend` is the one asked for, `None` for mutool with the PDFium
+    /// fallback.
+    pub fn matrix(
+        file_hash: u64,
+        page: usize,
+        dimensions: Option<CharDimensions>,
+        backend: Option<ExtractionBackend>,
+        model: &MonospaceModel,
+    ) -> Self {
+        // Segmentation is applied after loading, so one matrix serves both
+        let settings = extraction_fingerprint(dimensions, backend, Segmentation::Backend, model);
+        Self { file_hash, page, dpi: 0, backend: MATRIX_PIPELINE, settings }
+    }
+
+    pub fn render(file_hash: u64, page: usize, dpi: f32) -> Self {
+        // Dark mode is applied after loading, so one render serves both
+        Self { file_hash, page, dpi: dpi.round() as u32, backend: "mutool", settings: 0 }
+    }
+
+    pub fn ferrules(file_hash: u64, page: usize) -> Self {
+        // The parsed document is cached and laid out on load, so one run
+        // serves every cell size
+        Self { file_hash, page, dpi: 0, backend: "ferrules", settings: 0 }
+    }
+
+    pub fn file_name(&self, extension: &str) -> String {
+        format!(
+            "{:016x}-p{}-{}dpi-{}-{:016x}-v{}.{}",
+            self.file_hash,
+            self.page + 1,
+            self.dpi,
+            self.backend.replace('+', "_"),
+            self.settings,
+            DISK_CACHE_VERSION,
+            extension
+        )
+    }
+}
+
+/// Writes through a temporary file so concurrent readers never see a
+/// partial entry.
+pub fn write_cache_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let temp = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Entries are stored compacted; the matrix comes back dense like a fresh
+/// extraction.
+fn load_cached_matrix(dir: &Path, key: &DiskCacheKey) -> Option<CharacterMatrix> {
+    let json = std::fs::read(dir.join(key.file_name("json"))).ok()?;
+    let mut char_matrix: CharacterMatrix = serde_json::from_slice(&json).ok()?;
+    char_matrix.ensure_dense();
+    Some(char_matrix)
+}
+
+fn save_cached_matrix(dir: &Path, key: &DiskCacheKey, matrix: &CharacterMatrix) -> Result<()> {
+    let mut matrix = matrix.clone();
+    matrix.compact();
+    write_cache_file(&dir.join(key.file_name("json")), &serde_json::to_vec(&matrix)?)
+}
+
+// ============= PAGE RENDERING =============
+/// Rasterises one page to PNG bytes with `mutool draw`, through the disk
+/// cache. Front ends apply dark mode themselves.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_page_png(pdf_path: &Path, page: usize, dpi: f32) -> Result<Vec<u8>, ChonkerError> {
+    let cache_path = cache_dir()
+        .zip(document_hash(pdf_path))
+        .map(|(dir, file_hash)| dir.join(DiskCacheKey::render(file_hash, page, dpi).file_name("png")));
+    if let Some(image_data) = cache_path.as_ref().and_then(|path| std::fs::read(path).ok()) {
+        return Ok(image_data);
+    }
+    let image_data = run_mutool_render(pdf_path, page, dpi)?;
+    if let Some(path) = &cache_path {
+        if let Err(e) = write_cache_file(path, &image_data) {
+            tracing::warn!("Failed to cache rendered page: {}", e);
+        }
+    }
+    Ok(image_data)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_mutool_render(pdf_path: &Path, page: usize, dpi: f32) -> Result<Vec<u8>, ChonkerError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let temp_png = std::env::temp_dir().join(format!("chonker_page_{}_{}_{}.png", std::process::id(), page, nanos));
+
+    let output = Command::new("mutool")
+        .arg("draw")
+        .arg("-o")
+        .arg(&temp_png)
+        .arg("-r")
+        .arg(dpi.to_string())
+        .arg("-F")
+        .arg("png")
+        .arg(pdf_path)
+        .arg(format!("{}", page + 1))
+        .output()
+        .map_err(|e| ChonkerError::ExternalTool { tool: "mutool", message: e.to_string() })?;
+
+    if !output.status.success() {
+        return Err(ChonkerError::RenderFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let image_data = std::fs::read(&temp_png);
+    let _ = std::fs::remove_file(&temp_png);
+    image_data.map_err(|e| ChonkerError::RenderFailed(format!("could not read the image: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_config_and_keymap() {
+        let config = ChonkerConfig::parse(
+            r#"
+theme = "solarized"
+backend = "stext"
+min_confidence = 0.4
+
+[char_size]
+char_width = 6.5
+char_height = 11.0
+
+[keymap]
+save = "Ctrl+W"
+toggle_regions = "F2"
+frobnicate = "Ctrl+K"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.theme.as_deref(), Some("solarized"));
+        assert_eq!(config.backend, Some(ExtractionBackend::MutoolStext));
+        assert_eq!(config.char_size, Some(CharDimensions { char_width: 6.5, char_height: 11.0 }));
+        assert_eq!(config.min_confidence, Some(0.4));
+        assert_eq!(ChonkerConfig::parse("backend = \"auto\"").unwrap(), ChonkerConfig::default());
+        assert!(ChonkerConfig::parse("backend = \"ocr\"").is_err());
+
+        assert_eq!("Ctrl+Shift+s".parse::<KeyChord>().unwrap(), KeyChord::new(true, false, true, "S"));
+        assert_eq!("Alt+,".parse::<KeyChord>().unwrap(), KeyChord::new(false, true, false, "Comma"));
+        assert_eq!("Ctrl++".parse::<KeyChord>().unwrap().key, "+");
+        assert!("Hyper+S".parse::<KeyChord>().is_err());
+
+        let keymap = Keymap::new(&[("save", "Ctrl+S"), ("toggle_regions", "Ctrl+B")], &config.keymap);
+        let chord = |s: &str| s.parse::<KeyChord>().unwrap();
+        assert_eq!(keymap.remap(chord("Ctrl+W")), Some(chord("Ctrl+S")));
+        assert_eq!(keymap.remap(chord("F2")), Some(chord("Ctrl+B")));
+        // The old chord is freed, and unbound keys pass through
+        assert_eq!(keymap.remap(chord("Ctrl+S")), None);
+        assert_eq!(keymap.remap(chord("Ctrl+K")), Some(chord("Ctrl+K")));
+    }
+
+    #[test]
+    fn test_parse_stext_places_glyphs_at_their_coordinates() {
+        let quad = |x: f32, y: f32| format!("{} {} {} {} {} {} {} {}", x, y - 8.0, x + 5.0, y - 8.0, x, y, x + 5.0, y);
+        let glyph = |c: char, x: f32, y: f32| format!(r#"<char quad="{}" x="{}" y="{}" c="{}"/>"#, quad(x, y), x, y, c);
+        let xml = format!(
+            r#"<document name="t.pdf"><page id="page1" width="60" height="40">
+<block bbox="10 10 20 28"><line bbox="10 10 20 18" wmode="0" dir="1 0"><font name="Helvetica" size="10">{}{}</font></line>
+<line bbox="10 20 17 28" wmode="0" dir="1 0"><font name="Helvetica" size="10">{}{}</font></line></block>
+<image bbox="0 0 5 5" transform="1 0 0 1 0 0" width="5" height="5"/>
+<block bbox="40 30 45 38"><line bbox="40 30 45 38" wmode="0" dir="1 0"><font name="Times-Italic" size="10">{}</font></line></block>
+</page></document>"#,
+            glyph('H', 10.0, 18.0),
+            glyph('i', 15.0, 18.0),
+            glyph('o', 10.0, 28.0),
+            glyph('k', 12.0, 28.0),
+            glyph('Z', 40.0, 38.0),
+        );
+
+        let matrix = parse_stext_to_matrix(&xml, 0, None, &ExtractionLimits::default(), &MonospaceModel::default()).unwrap();
+        assert_eq!((matrix.char_width, matrix.char_height), (5.0, 10.0));
+        assert_eq!((matrix.width, matrix.height), (12, 4));
+        assert_eq!(matrix.backend, Some(ExtractionBackend::MutoolStext));
+        let rows: Vec<String> = matrix.matrix.iter().map(|row| row.iter().collect()).collect();
+        assert_eq!(rows, ["            ", "  Hi        ", "  ok        ", "        Z   "]);
+        assert_eq!(matrix.original_text, ["Hi", "ok", "Z"]);
+        // 'k' rounds onto 'o' and moves one cell right
+        assert_eq!((matrix.layout.cell_font_size, matrix.layout.collisions), (Some(10.0), 1));
+
+        assert_eq!(matrix.text_regions.len(), 2);
+        let first = &matrix.text_regions[0];
+        assert_eq!((first.bbox.x, first.bbox.y, first.bbox.width, first.bbox.height), (2, 1, 2, 2));
+        assert_eq!(first.text_content, "Hi\nok");
+        assert!(!first.is_italic && matrix.text_regions[1].is_italic);
+
+        // A cell size override lays the glyphs out again at that size
+        let dims = CharDimensions { char_width: 2.5, char_height: 10.0 };
+        let regridded = parse_stext_to_matrix(&xml, 0, Some(dims), &ExtractionLimits::default(), &MonospaceModel::default()).unwrap();
+        assert_eq!((regridded.char_width, regridded.char_height), (2.5, 10.0));
+        assert_eq!((regridded.width, regridded.height), (24, 4));
+        assert_eq!((regridded.cell(1, 4), regridded.cell(1, 6)), ('H', 'i'));
+        assert_eq!(regridded.layout.cell_font_size, None);
+
+        assert!(matches!(
+            parse_stext_to_matrix(
+                r#"<document><page width="60" height="40"></page></document>"#,
+                3,
+                None,
+                &ExtractionLimits::default(),
+                &MonospaceModel::default()
+            ),
+            Err(ChonkerError::NoText { page: 3 })
+        ));
+        assert!("stext".parse::<ExtractionBackend>().unwrap() == ExtractionBackend::MutoolStext);
+    }
+
+    #[test]
+    fn test_parse_stext_maps_fixed_pitch_columns_exactly() {
+        let report = ["ACCT    BALANCE", "1001      12.50", "1002     310.00"];
+        let lines: String = report
+            .iter()
+            .enumerate()
+            .map(|(row, text)| {
+                let y = 20.0 + row as f32 * 10.0;
+                // Origins a little off the grid, from a left margin off the page grid
+                let chars: String = text
+                    .chars()
+                    .enumerate()
+                    .map(|(col, c)| {
+                        let x = 31.0 + col as f32 * 6.0 + if col % 2 == 0 { 0.4 } else { -0.4 };
+                        format!(r#"<char quad="{x} {} {} {} {x} {y} {} {y}" x="{x}" y="{y}" c="{c}"/>"#, y - 8.0, x + 6.0, y - 8.0, x + 6.0)
+                    })
+                    .collect();
+                format!(r#"<line wmode="0" dir="1 0"><font name="Courier" size="10">{}</font></line>"#, chars)
+            })
+            .collect();
+        let xml = format!(r#"<document><page width="150" height="50"><block>{}</block></page></document>"#, lines);
+
+        let matrix = parse_stext_to_matrix(&xml, 0, None, &ExtractionLimits::default(), &MonospaceModel::default()).unwrap();
+        assert!(matrix.monospace.as_ref().is_some_and(MonospaceDetection::is_monospace));
+        assert_eq!(matrix.char_width, 6.0);
+        let rows: Vec<String> = matrix.matrix[1..4].iter().map(|row| row.iter().collect::<String>().trim_end().to_string()).collect();
+        assert_eq!(rows, report);
+        assert_eq!(matrix.layout.collisions, 0);
+
+        // An override still wins over the detected pitch
+        let dims = CharDimensions { char_width: 3.0, char_height: 10.0 };
+        let overridden = parse_stext_to_matrix(&xml, 0, Some(dims), &ExtractionLimits::default(), &MonospaceModel::default()).unwrap();
+        assert_eq!(overridden.char_width, 3.0);
+        assert_eq!(overridden.cell(1, 10), 'A');
+    }
+
+    #[test]
+    fn test_char_bbox_contains() {
+        let bbox = CharBBox {
+            x: 10,
+            y: 5,
+            width: 20,
+            height: 15,
+        };
+
+        assert!(bbox.contains(10, 5));
+        assert!(bbox.contains(15, 10));
+        assert!(bbox.contains(29, 19));
+        assert!(!bbox.contains(9, 5));
+        assert!(!bbox.contains(10, 4));
+        assert!(!bbox.contains(30, 10));
+        assert!(!bbox.contains(15, 20));
+    }
+
+    #[test]
+    fn test_char_bbox_area() {
+        let bbox = CharBBox {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 5,
+        };
+        assert_eq!(bbox.area(), 50);
+
+        let zero_bbox = CharBBox {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 10,
+        };
+        assert_eq!(zero_bbox.area(), 0);
+    }
+
+    #[test]
+    fn test_character_matrix_engine_new() {
+        let engine = CharacterMatrixEngine::new();
+        assert_eq!(engine.char_width, 6.0);
+        assert_eq!(engine.char_height, 12.0);
+    }
+
+    #[test]
+    fn test_detect_text_columns() {
+        let rows = text_to_matrix(
+            "Name      Qty   Price\n\
+             Apples     3    1.20\n\
+             Pears     12    0.80\n\
+             Plums      7   11.00\n\
+             a footnote that runs across\n",
+        );
+        // Right-aligned figures start a cell or so apart, still one column
+        assert_eq!(detect_text_columns(&rows), vec![0, 10, 16]);
+        assert!(detect_text_columns(&rows[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_character_matrix_creation() {
+        let matrix = CharacterMatrix {
+            width: 80,
+            height: 25,
+            matrix: vec![vec![' '; 80]; 25],
+            text_regions: vec![],
+            original_text: vec!["Test text".to_string()],
+            char_width: 6.0,
+            char_height: 12.0,
+            warnings: vec![],
+            monospace: None,
+            reading_order: None,
+            backend: None,
+            sparse: None,
+            settings_fingerprint: 0,
+            layout: LayoutNotes::default(),
+        };
+
+        assert_eq!(matrix.width, 80);
+        assert_eq!(matrix.height, 25);
+        assert_eq!(matrix.matrix.len(), 25);
+        assert_eq!(matrix.matrix[0].len(), 80);
+        assert_eq!(matrix.original_text.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_keeps_italic_runs_separate() {
+        let region = |x: usize, text: &str, is_italic: bool| TextRegion {
+            bbox: CharBBox {
+                x,
+                y: 3,
+                width: 1,
+                height: 1,
+            },
+            confidence: 1.0,
+            text_content: text.to_string(),
+            region_id: x,
+            is_italic,
+            label: None,
+            tag: None,
+        };
+
+        let engine = CharacterMatrixEngine::new();
+        let merged = engine.merge_adjacent_regions(&[
+            region(0, "a", false),
+            region(1, "b", false),
+            region(2, "c", true),
+            region(3, "d", true),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].text_content, "ab");
+        assert!(!merged[0].is_italic);
+        assert_eq!(merged[1].text_content, "cd");
+        assert!(merged[1].is_italic);
+    }
+
+    #[test]
+    fn test_diff_matrix_rows_reports_changed_rows() {
+        let diffs = diff_matrix_rows("abc\ndef\nghi\n", "abc\nxyz\nghi\nnew\n");
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].row, 1);
+        assert_eq!(diffs[0].old, "def");
+        assert_eq!(diffs[0].new, "xyz");
+        assert_eq!(diffs[1].row, 3);
+        assert_eq!(diffs[1].old, "");
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range("", 3).unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_page_range("1-3,2,5", 5).unwrap(), vec![0, 1, 2, 4]);
+        assert!(parse_page_range("0-2", 5).is_err());
+        assert!(parse_page_range("4-2", 5).is_err());
+        assert!(parse_page_range("1-9", 5).is_err());
+    }
+
+    #[test]
+    fn test_matrix_to_alto_emits_words() {
+        let mut matrix = CharacterMatrix::new(12, 2);
+        for (i, ch) in "A&B  cd".chars().enumerate() {
+            matrix.matrix[1][i] = ch;
+        }
+        let alto = matrix_to_alto(&matrix, 0);
+        assert!(alto.contains("CONTENT=\"A&amp;B\""));
+        assert!(alto.contains("CONTENT=\"cd\""));
+        assert_eq!(alto.matches("<TextLine").count(), 1);
+    }
+
+    #[test]
+    fn test_region_tags_reach_alto_and_json() {
+        let mut matrix = CharacterMatrix::new(12, 2);
+        for (i, ch) in "Heading".chars().enumerate() {
+            matrix.matrix[0][i] = ch;
+        }
+        matrix.text_regions.push(TextRegion {
+            bbox: CharBBox { x: 0, y: 0, width: 7, height: 1 },
+            confidence: 0.9,
+            text_content: "Heading".to_string(),
+            region_id: 4,
+            is_italic: false,
+            label: None,
+            tag: None,
+        });
+        let tags = HashMap::from([(4, RegionTag::Title)]);
+        apply_region_tags(&mut matrix, Some(&tags));
+
+        let alto = matrix_to_alto(&matrix, 0);
+        assert!(alto.contains("<StructureTag ID=\"TAG_title\" LABEL=\"title\"/>"));
+        assert!(alto.contains("TAGREFS=\"TAG_title\""));
+
+        let json = serde_json::to_string(&matrix).unwrap();
+        assert!(json.contains("\"tag\":\"title\""));
+    }
+
+    #[test]
+    fn test_region_parse_and_crop() {
+        let bbox = CharBBox::parse("1, 0, 2, 2").unwrap();
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (1, 0, 2, 2));
+        assert!(CharBBox::parse("1,2,3").is_err());
+        assert!(CharBBox::parse("1,2,0,3").is_err());
+
+        let matrix = text_to_matrix("abcd\nefgh\nijkl");
+        assert_eq!(matrix_to_text(&crop_matrix(&matrix, &bbox)), "bc\nfg\n");
+    }
+
+    #[test]
+    fn test_region_labels_csv_round_trip() {
+        let mut page = CharacterMatrix::new(10, 2);
+        page.text_regions.push(TextRegion {
+            bbox: CharBBox {
+                x: 1,
+                y: 0,
+                width: 4,
+                height: 1,
+            },
+            confidence: 1.0,
+            text_content: "a, \"b\"".to_string(),
+            region_id: 7,
+            is_italic: false,
+            label: None,
+            tag: None,
+        });
+        let mut cache = PageCache::new();
+        cache.insert(1, page);
+
+        let csv = regions_to_csv(&cache, 0.0);
+        assert!(csv.contains("\"a, \"\"b\"\"\""));
+        assert_eq!(regions_to_csv(&cache, 1.5).lines().count(), 1);
+
+        let edited = csv.replace("false,,", "false,\"Total, net\",");
+        let labels = labels_from_csv(&edited).unwrap();
+        assert_eq!(labels[&1][&7], "Total, net");
+
+        let char_matrix = cache.get_mut(&1).unwrap();
+        apply_region_labels(char_matrix, labels.get(&1));
+        assert_eq!(char_matrix.text_regions[0].display_name(), "Total, net");
+    }
+
+    #[test]
+    fn test_width_drift_tracker_aggregates_per_page() {
+        let mut drift = WidthDriftTracker::default();
+        assert_eq!(drift.record(0, 1.0, 6.0, "within half a cell"), 0.0);
+        assert_eq!(drift.record(2, 4.0, 6.0, "Illuminating"), 4.0);
+        drift.record(2, 9.5, 6.0, "Wide");
+
+        // Drifted glyphs cost their regions confidence
+        let region = |region_id: usize, x: usize, confidence: f32| TextRegion {
+            bbox: CharBBox { x, y: 0, width: 4, height: 1 },
+            confidence,
+            text_content: String::new(),
+            region_id,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+        assert_eq!(drift_confidence(0.0, 6.0), 1.0);
+        assert!((drift_confidence(6.0, 6.0) - 0.8).abs() < 1e-6);
+        assert_eq!(drift_confidence(60.0, 6.0), 0.2);
+        let regions = [region(0, 0, 1.0), region(4, 10, drift_confidence(9.5, 6.0))];
+
+        let warnings = drift.into_warnings(&regions);
+        assert_eq!(
+            warnings,
+            vec![ExtractionWarning::AveragedCharWidths {
+                page: 2,
+                segments: 2,
+                max_drift_pts: 9.5,
+                sample: "Illuminating".to_string(),
+                regions: vec![4],
+            }]
+        );
+        assert!(warnings[0].to_string().starts_with("Page 3:"));
+
+        // Another segmentation keeps the warning on the regions holding them
+        let mut char_matrix = CharacterMatrix::new(20, 1);
+        char_matrix.text_regions = regions.to_vec();
+        char_matrix.warnings = warnings;
+        assert_eq!(char_matrix.region_warnings(4).count(), 1);
+        char_matrix.replace_regions(vec![region(0, 0, 1.0), region(1, 8, 0.7)]);
+        assert_eq!(char_matrix.warnings[0].regions(), [1]);
+        assert_eq!(char_matrix.region_warnings(0).count(), 0);
+    }
+
+    fn glyph_row(widths: &[f32], fixed_pitch: bool) -> Vec<GlyphSample> {
+        let mut x = 72.0;
+        widths
+            .iter()
+            .cycle()
+            .take(60)
+            .map(|&width| {
+                let glyph = GlyphSample { x0: x, width, fixed_pitch };
+                x += width;
+                glyph
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_monospace_features_and_default_model() {
+        let model = MonospaceModel::default();
+
+        let mono = MonospaceFeatures::from_glyphs(&glyph_row(&[7.2], false)).unwrap();
+        assert!((mono.pitch - 7.2).abs() < 1e-4);
+        assert!(mono.width_cv < 0.01);
+        assert!(mono.grid_fit > 0.95);
+        assert!(model.predict(&mono) > 0.5);
+
+        let proportional =
+            MonospaceFeatures::from_glyphs(&glyph_row(&[2.8, 6.1, 9.2, 5.0, 3.3], false)).unwrap();
+        assert!(model.predict(&proportional) < 0.5);
+
+        assert!(MonospaceFeatures::from_glyphs(&glyph_row(&[7.2], true)[..5]).is_none());
+    }
+
+    #[test]
+    fn test_monospace_model_training_moves_prediction() {
+        let features = MonospaceFeatures {
+            fixed_pitch_ratio: 0.0,
+            width_cv: 0.3,
+            grid_fit: 0.6,
+            pitch: 6.0,
+        };
+        let mut model = MonospaceModel::default();
+        let before = model.predict(&features);
+        model.train(&[(features, true)]);
+        assert!(model.predict(&features) > before);
+        assert!(model.predict(&features) > 0.5);
+    }
+
+    #[test]
+    fn test_reading_order_drives_text_export() {
+        let mut matrix = CharacterMatrix::new(10, 3);
+        for (i, ch) in "Top".chars().enumerate() {
+            matrix.matrix[0][i] = ch;
+        }
+        for (i, ch) in "Side".chars().enumerate() {
+            matrix.matrix[2][6 + i] = ch;
+        }
+        for (i, ch) in "Low".chars().enumerate() {
+            matrix.matrix[2][i] = ch;
+        }
+        let region = |id: usize, x: usize, y: usize, width: usize| TextRegion {
+            bbox: CharBBox { x, y, width, height: 1 },
+            confidence: 1.0,
+            text_content: String::new(),
+            region_id: id,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+        matrix.text_regions = vec![region(0, 0, 0, 3), region(1, 6, 2, 4), region(2, 0, 2, 3)];
+
+        assert_eq!(reading_sequence(&matrix), vec![0, 2, 1]);
+        assert_eq!(
+            render_export(&matrix, 0, ExportFormat::Reading).unwrap(),
+            "Top\n\nLow\n\nSide\n"
+        );
+
+        // Unlisted regions follow the user's sequence in position order
+        matrix.reading_order = Some(vec![1, 7]);
+        assert_eq!(reading_sequence(&matrix), vec![1, 0, 2]);
+        assert_eq!(
+            render_export(&matrix, 0, ExportFormat::Txt).unwrap(),
+            "Side\n\nTop\n\nLow\n"
+        );
+    }
+
+    #[test]
+    fn test_matrix_diff_pads_ragged_rows() {
+        let a = vec![vec!['a', 'b'], vec!['c']];
+        let b = vec![vec!['a', 'x', ' '], vec!['c', 'd'], vec![' ']];
+        assert_eq!(
+            matrix_diff(&a, &b),
+            vec![vec![false, true, false], vec![false, true], vec![false]]
+        );
+    }
+
+    #[test]
+    fn test_cell_edits_round_trip() {
+        let dir = std::env::temp_dir().join(format!("chonker5_edit_log_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pdf = dir.join("doc.pdf");
+
+        let before = text_to_matrix("abc\ndef");
+        let after = text_to_matrix("aXc\nde");
+        let edits = cell_edits(3, &before, &after, 42);
+        assert_eq!(edits.len(), 2);
+        assert_eq!((edits[0].row, edits[0].col, edits[0].before, edits[0].after), (0, 1, 'b', 'X'));
+        assert_eq!((edits[1].row, edits[1].col, edits[1].before, edits[1].after), (1, 2, 'f', ' '));
+        assert!(edits.iter().all(|e| e.page == 3 && e.timestamp == 42));
+
+        assert!(load_edit_log(&pdf).is_empty());
+        save_edit_log(&pdf, &edits).unwrap();
+        assert_eq!(load_edit_log(&pdf), edits);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_layout_area_glyphs_keeps_only_area() {
+        let glyph = |ch: char, x0: f32, y0: f32| PreciseTextObject {
+            text: ch.to_string(),
+            bbox: PDFBBox { x0, y0, x1: x0 + 5.0, y1: y0 + 10.0 },
+            font_size: 10.0,
+            is_italic: false,
+            drift_pts: 0.0,
+        };
+        let objects = vec![
+            glyph('a', 102.0, 201.0),
+            glyph('b', 108.0, 201.0),
+            // Collides with 'b' and moves to the next free cell
+            glyph('c', 109.0, 202.0),
+            glyph('d', 101.0, 213.0),
+            glyph('x', 50.0, 201.0),
+            glyph('y', 102.0, 240.0),
+        ];
+        let area = PDFBBox { x0: 100.0, y0: 200.0, x1: 130.0, y1: 224.0 };
+        let block = layout_area_glyphs(&objects, &area, 5, 2);
+        assert_eq!(block, vec!["abc  ".chars().collect::<Vec<_>>(), "d    ".chars().collect()]);
+    }
+
+    #[test]
+    fn test_sparse_matrix_round_trip() {
+        let dense = text_to_matrix("  ab c      d\n             \nx            ");
+        let sparse = SparseMatrix::from_dense(&dense);
+        assert_eq!(sparse.rows[0], vec![(2, "ab c".to_string()), (12, "d".to_string())]);
+        assert!(sparse.rows[1].is_empty());
+        assert_eq!(sparse.to_dense(3), dense);
+        assert_eq!((sparse.get(0, 5), sparse.get(0, 12), sparse.get(2, 0), sparse.get(9, 9)), ('c', 'd', 'x', ' '));
+
+        let mut page = CharacterMatrix::new(13, 3);
+        page.matrix = dense.clone();
+        page.compact();
+        assert!(page.matrix.is_empty());
+        assert_eq!(page.cell(0, 3), 'b');
+        // Exports read a compacted page as if it were dense
+        let mut dense_page = CharacterMatrix::new(13, 3);
+        dense_page.matrix = dense.clone();
+        for format in [ExportFormat::Txt, ExportFormat::Json, ExportFormat::Alto] {
+            assert_eq!(render_export(&page, 0, format).unwrap(), render_export(&dense_page, 0, format).unwrap());
+        }
+        let json = serde_json::to_string(&page).unwrap();
+        let mut restored: CharacterMatrix = serde_json::from_str(&json).unwrap();
+        restored.ensure_dense();
+        assert_eq!(restored.matrix, dense);
+
+        // Writing to a compacted page expands it first
+        assert_eq!(*page.rows(), dense);
+        page.rows_mut()[2][1] = 'y';
+        assert!(page.sparse.is_none());
+        assert_eq!((page.cell(0, 3), page.cell(2, 1)), ('b', 'y'));
+    }
+
+    #[test]
+    fn test_disk_cache_keys_and_matrix_round_trip() {
+        let model = MonospaceModel::default();
+        let plain = DiskCacheKey::matrix(0xabc, 2, None, None, &model);
+        let sized = DiskCacheKey::matrix(0xabc, 2, Some(CharDimensions { char_width: 6.0, char_height: 12.0 }), None, &model);
+        assert_ne!(plain.file_name("json"), sized.file_name("json"));
+        let forced = DiskCacheKey::matrix(0xabc, 2, None, Some(ExtractionBackend::MutoolStext), &model);
+        assert_ne!(plain.file_name("json"), forced.file_name("json"));
+        assert_ne!(
+            DiskCacheKey::render(0xabc, 2, 150.0).file_name("png"),
+            DiskCacheKey::render(0xabc, 2, 300.0).file_name("png")
+        );
+        assert_ne!(DiskCacheKey::ferrules(0xabc, 2).file_name("json"), plain.file_name("json"));
+        assert_ne!(fnv1a(b"a.pdf"), fnv1a(b"b.pdf"));
+
+        let dir = std::env::temp_dir().join(format!("chonker5_disk_cache_{}", std::process::id()));
+        assert!(load_cached_matrix(&dir, &plain).is_none());
+
+        let mut matrix = CharacterMatrix::new(4, 2);
+        matrix.matrix[1] = "ab  ".chars().collect();
+        save_cached_matrix(&dir, &plain, &matrix).unwrap();
+        let loaded = load_cached_matrix(&dir, &plain).unwrap();
+        assert!(loaded.sparse.is_none());
+        assert_eq!(loaded.matrix, matrix.matrix);
+        // A cache hit exports the same as the extraction it came from
+        assert_eq!(render_export(&loaded, 0, ExportFormat::Txt).unwrap(), "    \nab  \n");
+        for format in [ExportFormat::Txt, ExportFormat::Alto, ExportFormat::Json] {
+            assert_eq!(render_export(&loaded, 0, format).unwrap(), render_export(&matrix, 0, format).unwrap());
+        }
+        assert!(load_cached_matrix(&dir, &sized).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extraction_limits_clamp_and_warn() {
+        let limits: ExtractionLimits = serde_json::from_str(r#"{"max_width": 100}"#).unwrap();
+        assert_eq!(limits.timeout_secs, 60);
+        assert_eq!(limits.max_height, ExtractionLimits::default().max_height);
+
+        let mut warnings = Vec::new();
+        assert_eq!(limits.clamp_size(0, (80, 40), &mut warnings), (80, 40));
+        assert!(warnings.is_empty());
+
+        assert_eq!(limits.clamp_size(3, (150, 40), &mut warnings), (100, 40));
+        assert_eq!(
+            warnings,
+            vec![ExtractionWarning::Truncated { page: 3, reason: "150 columns exceed the limit of 100".to_string() }]
+        );
+        assert_eq!(warnings[0].to_string(), "Page 4: partial result - 150 columns exceed the limit of 100");
+    }
+
+    #[test]
+    fn test_failed_pages_summary() {
+        let mut failures = std::collections::BTreeMap::new();
+        failures.insert(6, "No text found in PDF".to_string());
+        assert_eq!(failed_pages_summary(&failures), "page 7 failed");
+        failures.insert(2, "mutool timed out after 60s".to_string());
+        assert_eq!(failed_pages_summary(&failures), "pages 3, 7 failed");
+    }
+
+    #[test]
+    fn test_merge_cell_edits() {
+        let m = |rows: &[&str]| rows.iter().map(|r| r.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+        let base = m(&["abc", "def"]);
+        let edited = m(&["aXc", "deY"]);
+
+        // Untouched cells follow the new extraction, edited ones keep the edit
+        let (merged, conflicts) = merge_cell_edits(&base, &edited, &m(&["abc", "dZf", "ghi"]));
+        assert_eq!(merged, m(&["aXc", "dZY", "ghi"]));
+        assert_eq!(conflicts, 0);
+
+        // The regenerated page changed an edited cell and dropped a row
+        let (merged, conflicts) = merge_cell_edits(&base, &edited, &m(&["aQc"]));
+        assert_eq!(merged, m(&["aXc"]));
+        assert_eq!(conflicts, 2);
+    }
+
+    #[test]
+    fn test_progress_event_format() {
+        let started = serde_json::to_value(ProgressEvent::Started { page: 4 }).unwrap();
+        assert_eq!(started, serde_json::json!({ "event": "started", "page": 4 }));
+        assert_eq!(ProgressEvent::percent(1, 4), ProgressEvent::Percent { done: 1, total: 4, percent: 25.0 });
+        assert_eq!(ProgressEvent::percent(0, 0), ProgressEvent::Percent { done: 0, total: 0, percent: 100.0 });
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(extract_document_with_progress(PathBuf::from("missing.pdf"), "", Some(sender)));
+        // Nothing is queued for a document that can't be read, and the
+        // channel closes with the call
+        assert!(result.is_err());
+        assert_eq!(receiver.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_document_export_and_format_from_path() {
+        assert_eq!(ExportFormat::from_path(Path::new("out/report.alto.xml")), Some(ExportFormat::Alto));
+        assert_eq!(ExportFormat::from_path(Path::new("page.XML")), Some(ExportFormat::Alto));
+        assert_eq!(ExportFormat::from_path(Path::new("notes.reading.txt")), Some(ExportFormat::Reading));
+        assert_eq!(ExportFormat::from_path(Path::new("matrix.json")), Some(ExportFormat::Json));
+        assert_eq!(ExportFormat::from_path(Path::new("report.docx")), None);
+
+        let page = |text: &str| {
+            let mut char_matrix = CharacterMatrix::new(2, 1);
+            char_matrix.matrix[0] = text.chars().collect();
+            char_matrix
+        };
+        let pages = vec![(0, page("ab")), (2, page("cd"))];
+        assert_eq!(render_document_export(&pages, ExportFormat::Txt).unwrap(), "ab\n\x0ccd\n");
+        let json: serde_json::Value =
+            serde_json::from_str(&render_document_export(&pages, ExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[1]["page"], 3);
+        assert_eq!(json[1]["width"], 2);
+        let alto = render_document_export(&pages, ExportFormat::Alto).unwrap();
+        assert_eq!(alto.matches("<alto ").count(), 1);
+        assert_eq!(alto.matches("<Page ").count(), 2);
+        assert!(alto.contains("CONTENT=\"cd\""));
+    }
+
+    #[test]
+    fn test_chonker_error_kinds_survive_anyhow() {
+        let engine_error: anyhow::Error = ChonkerError::NoText { page: 4 }.into();
+        let error = ChonkerError::from_anyhow(engine_error);
+        assert_eq!(error, ChonkerError::NoText { page: 4 });
+        assert_eq!(error.to_string(), "No text found on page 5");
+        assert_eq!(error.exit_code(), 65);
+
+        let other = ChonkerError::from_anyhow(anyhow::anyhow!("boom"));
+        assert_eq!(other, ChonkerError::Other("boom".to_string()));
+        assert_eq!(other.exit_code(), 1);
+
+        let summary = anyhow::Error::new(ChonkerError::Timeout { secs: 60 }).context("1 of 2 page(s) exported");
+        assert_eq!(summary.downcast_ref::<ChonkerError>().map(ChonkerError::exit_code), Some(75));
+
+        let range = parse_page_range("1-9", 5).unwrap_err();
+        assert_eq!(
+            range.downcast_ref::<ChonkerError>(),
+            Some(&ChonkerError::PageOutOfRange { page: 8, total: 5 })
+        );
+    }
+
+    #[test]
+    fn test_detect_tables_splits_columns_at_gutters() {
+        let mut char_matrix = CharacterMatrix::new(24, 5);
+        char_matrix.matrix = text_to_matrix(
+            "Item      Qty   Price   \nRed pen   2     1,50    \n                        \nInk       10    \"9\"     \nNot a table at all here \n",
+        );
+        let region = |region_id, y, height, tag| TextRegion {
+            bbox: CharBBox { x: 0, y, width: 24, height },
+            confidence: 1.0,
+            text_content: String::new(),
+            region_id,
+            is_italic: false,
+            label: None,
+            tag,
+        };
+        char_matrix.text_regions = vec![
+            region(0, 0, 4, None),
+            region(1, 4, 1, None),
+            region(2, 4, 1, Some(RegionTag::Table)),
+            region(3, 0, 4, Some(RegionTag::Title)),
+        ];
+
+        let tables = detect_tables(&char_matrix);
+        assert_eq!(tables.iter().map(|table| table.region_id).collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(
+            tables[0].cells,
+            [["Item", "Qty", "Price"], ["Red pen", "2", "1,50"], ["Ink", "10", "\"9\""]]
+        );
+        assert_eq!(tables[1].cells, [["Not a table at all here"]]);
+        assert_eq!(table_to_csv(&tables[0].cells), "Item,Qty,Price\nRed pen,2,\"1,50\"\nInk,10,\"\"\"9\"\"\"\n");
+    }
+
+    #[test]
+    fn test_ferrules_json_maps_blocks_to_tagged_regions() {
+        let json = r#"{
+            "doc_name": "report",
+            "pages": [{"id": 2, "width": 60.0, "height": 60.0, "need_ocr": false}],
+            "blocks": [
+                {"id": 0, "kind": {"block_type": "Title", "level": 1, "text": "Annual report"}, "pages_id": [2],
+                 "bbox": {"x0": 6.0, "y0": 0.0, "x1": 54.0, "y1": 12.0}},
+                {"id": 1, "kind": {"block_type": "TextBlock", "text": "Sales rose sharply"}, "pages_id": [2],
+                 "bbox": {"x0": 0.0, "y0": 24.0, "x1": 42.0, "y1": 36.0}},
+                {"id": 2, "kind": {"block_type": "Image", "id": 7}, "pages_id": [2],
+                 "bbox": {"x0": 42.0, "y0": 24.0, "x1": 60.0, "y1": 48.0}},
+                {"id": 3, "kind": {"block_type": "Equation"}, "pages_id": [3],
+                 "bbox": {"x0": 0.0, "y0": 0.0, "x1": 6.0, "y1": 12.0}}
+            ]
+        }"#;
+        let document: FerrulesDocument = serde_json::from_str(json).unwrap();
+        assert_eq!(document.blocks[3].kind, FerrulesBlockKind::Other);
+        // The disk cache stores the document as read
+        let cached: FerrulesDocument = serde_json::from_slice(&serde_json::to_vec(&document).unwrap()).unwrap();
+        assert_eq!(cached, document);
+
+        let char_matrix = ferrules_to_matrix(&document, 2, 6.0, 12.0, &ExtractionLimits::default()).unwrap();
+        assert_eq!(matrix_to_text(&char_matrix.matrix), " Annual   \n report   \nSales     \nrose      \nsharply   \n");
+        let regions: Vec<_> = char_matrix.text_regions.iter().map(|region| (region.bbox.clone(), region.tag)).collect();
+        assert_eq!(
+            regions,
+            [
+                (CharBBox { x: 1, y: 0, width: 8, height: 2 }, Some(RegionTag::Title)),
+                (CharBBox { x: 0, y: 2, width: 7, height: 3 }, Some(RegionTag::Paragraph)),
+                (CharBBox { x: 7, y: 2, width: 3, height: 2 }, Some(RegionTag::Figure)),
+            ]
+        );
+        let empty = FerrulesDocument { pages: document.pages.clone(), blocks: Vec::new() };
+        assert_eq!(ferrules_to_matrix(&empty, 2, 6.0, 12.0, &ExtractionLimits::default()).err(), Some(ChonkerError::NoText { page: 2 }));
+    }
+
+    #[test]
+    fn test_xy_cut_keeps_columns_apart() {
+        let mut char_matrix = CharacterMatrix::new(24, 4);
+        char_matrix.matrix = text_to_matrix("Annual Report\n\nSales rose    Costs fell\nin spring.    in autumn.\n")
+            .into_iter()
+            .map(|mut row| {
+                row.resize(24, ' ');
+                row
+            })
+            .collect();
+        char_matrix.char_width = 6.0;
+        char_matrix.char_height = 12.0;
+        // Line-by-line regions from the backend: these run across the gutter
+        let line = |y: usize, confidence: f32| TextRegion {
+            bbox: CharBBox { x: 0, y, width: 24, height: 1 },
+            confidence,
+            text_content: String::new(),
+            region_id: y,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+        char_matrix.text_regions = vec![line(0, 1.0), line(2, 0.6), line(3, 0.8)];
+
+        apply_segmentation(&mut char_matrix, Segmentation::Backend);
+        assert_eq!(char_matrix.text_regions.len(), 3);
+        apply_segmentation(&mut char_matrix, Segmentation::XyCut);
+        let regions: Vec<_> =
+            char_matrix.text_regions.iter().map(|region| (region.bbox.clone(), region.text_content.as_str())).collect();
+        assert_eq!(
+            regions,
+            [
+                (CharBBox { x: 0, y: 0, width: 13, height: 1 }, "Annual Report"),
+                (CharBBox { x: 0, y: 2, width: 10, height: 2 }, "Sales rose\nin spring."),
+                (CharBBox { x: 14, y: 2, width: 10, height: 2 }, "Costs fell\nin autumn."),
+            ]
+        );
+        assert!((char_matrix.text_regions[1].confidence - 0.7).abs() < 1e-6);
+        assert_eq!(char_matrix.text_regions[2].confidence, 1.0);
+
+        assert_eq!(serde_json::to_string(&Segmentation::XyCut).unwrap(), "\"xy-cut\"");
+    }
+
+    #[test]
+    fn test_xy_cut_keeps_double_spaced_paragraphs_whole() {
+        let mut char_matrix = CharacterMatrix::new(12, 9);
+        char_matrix.matrix = text_to_matrix("First para\n\ngoes on.\n\n\n\nSecond one\n\nends here.\n")
+            .into_iter()
+            .map(|mut row| {
+                row.resize(12, ' ');
+                row
+            })
+            .collect();
+        apply_segmentation(&mut char_matrix, Segmentation::XyCut);
+        let texts: Vec<_> = char_matrix.text_regions.iter().map(|region| region.text_content.as_str()).collect();
+        assert_eq!(texts, ["First para\n\ngoes on.", "Second one\n\nends here."]);
+    }
+
+    #[test]
+    fn test_layout_detections_become_tagged_regions() {
+        // Three anchors over [cx, cy, w, h] then scores for Text (9) and Title (10)
+        let mut data = vec![0.0; 15 * 3];
+        let mut anchor = |i: usize, values: [f32; 4], text: f32, title: f32| {
+            for (row, value) in values.into_iter().enumerate() {
+                data[row * 3 + i] = value;
+            }
+            data[13 * 3 + i] = text;
+            data[14 * 3 + i] = title;
+        };
+        anchor(0, [30.0, 6.0, 48.0, 12.0], 0.1, 0.9);
+        // Nearly the same box, less sure: suppressed
+        anchor(1, [31.0, 6.0, 48.0, 12.0], 0.0, 0.6);
+        anchor(2, [21.0, 30.0, 42.0, 12.0], 0.8, 0.0);
+        let detections = decode_layout_output(&[1, 15, 3], &data, LAYOUT_MIN_CONFIDENCE, 0.5, 1.0);
+        assert_eq!(
+            detections,
+            [
+                LayoutDetection { class: 10, confidence: 0.9, bbox: [6.0, 0.0, 54.0, 12.0] },
+                LayoutDetection { class: 9, confidence: 0.8, bbox: [0.0, 24.0, 42.0, 36.0] },
+            ]
+        );
+        assert!(decode_layout_output(&[1, 15, 3], &data, 0.95, 0.5, 1.0).is_empty());
+
+        let mut char_matrix = CharacterMatrix::new(10, 4);
+        char_matrix.matrix = text_to_matrix(" Report\n\nSales up\n");
+        char_matrix.char_width = 6.0;
+        char_matrix.char_height = 12.0;
+        let regions: Vec<_> = layout_regions(&char_matrix, &detections)
+            .into_iter()
+            .map(|region| (region.bbox, region.tag, region.text_content))
+            .collect();
+        assert_eq!(
+            regions,
+            [
+                (CharBBox { x: 1, y: 0, width: 8, height: 1 }, Some(RegionTag::Title), "Report".to_string()),
+                (CharBBox { x: 0, y: 2, width: 7, height: 1 }, Some(RegionTag::Paragraph), "Sales u".to_string()),
+            ]
+        );
+    }
+
+}
@@ -0,0 +1,3050 @@
+//! # chonker-grid: the character matrix editor widget
+//!
+//! `MatrixGrid` and the theme, selection, block and text-tool helpers behind
+//! it, shared by the desktop app (`chonker5.rs`) and the browser build
+//! (`chonker-web`). The terminal editor in `chonker-tui` reuses the theme and
+//! the selection and block helpers.
+
+use anyhow::Result;
+use chonker_core::*;
+use egui::{Align2, FontId, Rect, Response, Sense, Stroke, Vec2};
+// Theme colors are egui colors; re-exported for front ends that don't use egui
+pub use egui::Color32;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+// std's clock panics in the browser
+#[cfg(target_arch = "wasm32")]
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+// ============= THEME =============
+/// UI palette. The active theme is process-wide so that free drawing helpers
+/// and `MatrixGrid` can read it without threading it through every call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub dark: bool,
+    pub bg: Color32,
+    pub fg: Color32,
+    pub highlight: Color32,
+    pub error: Color32,
+    pub dim: Color32,
+    pub warning: Color32,
+    pub success: Color32,
+    pub accent: Color32,
+    pub chrome: Color32,
+    pub widget_bg: Color32,
+    pub widget_hover_bg: Color32,
+    pub widget_active_bg: Color32,
+    pub selection_bg: Color32,
+}
+
+impl Theme {
+    pub const TEAL: Theme = Theme {
+        dark: true,
+        bg: Color32::from_rgb(10, 15, 20),
+        fg: Color32::from_rgb(26, 188, 156),
+        highlight: Color32::from_rgb(22, 160, 133),
+        error: Color32::from_rgb(255, 80, 80),
+        dim: Color32::from_rgb(80, 100, 100),
+        warning: Color32::from_rgb(255, 200, 0),
+        success: Color32::from_rgb(46, 204, 113),
+        accent: Color32::from_rgb(52, 152, 219),
+        chrome: Color32::from_rgb(82, 86, 89),
+        widget_bg: Color32::from_rgb(20, 25, 30),
+        widget_hover_bg: Color32::from_rgb(30, 40, 45),
+        widget_active_bg: Color32::from_rgb(40, 50, 55),
+        selection_bg: Color32::from_rgb(0, 150, 140),
+    };
+
+    pub const SOLARIZED: Theme = Theme {
+        dark: true,
+        bg: Color32::from_rgb(0, 43, 54),
+        fg: Color32::from_rgb(147, 161, 161),
+        highlight: Color32::from_rgb(42, 161, 152),
+        error: Color32::from_rgb(220, 50, 47),
+        dim: Color32::from_rgb(88, 110, 117),
+        warning: Color32::from_rgb(181, 137, 0),
+        success: Color32::from_rgb(133, 153, 0),
+        accent: Color32::from_rgb(38, 139, 210),
+        chrome: Color32::from_rgb(101, 123, 131),
+        widget_bg: Color32::from_rgb(7, 54, 66),
+        widget_hover_bg: Color32::from_rgb(16, 70, 84),
+        widget_active_bg: Color32::from_rgb(24, 84, 100),
+        selection_bg: Color32::from_rgb(38, 139, 210),
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        dark: true,
+        bg: Color32::BLACK,
+        fg: Color32::WHITE,
+        highlight: Color32::from_rgb(0, 255, 255),
+        error: Color32::from_rgb(255, 64, 64),
+        dim: Color32::from_rgb(170, 170, 170),
+        warning: Color32::from_rgb(255, 255, 0),
+        success: Color32::from_rgb(0, 255, 0),
+        accent: Color32::from_rgb(0, 170, 255),
+        chrome: Color32::WHITE,
+        widget_bg: Color32::from_rgb(20, 20, 20),
+        widget_hover_bg: Color32::from_rgb(50, 50, 50),
+        widget_active_bg: Color32::from_rgb(80, 80, 80),
+        selection_bg: Color32::from_rgb(0, 120, 215),
+    };
+
+    pub const LIGHT: Theme = Theme {
+        dark: false,
+        bg: Color32::from_rgb(250, 250, 247),
+        fg: Color32::from_rgb(30, 60, 60),
+        highlight: Color32::from_rgb(0, 128, 110),
+        error: Color32::from_rgb(200, 30, 30),
+        dim: Color32::from_rgb(130, 140, 140),
+        warning: Color32::from_rgb(176, 120, 0),
+        success: Color32::from_rgb(30, 140, 70),
+        accent: Color32::from_rgb(30, 100, 180),
+        chrome: Color32::from_rgb(180, 184, 186),
+        widget_bg: Color32::from_rgb(235, 238, 238),
+        widget_hover_bg: Color32::from_rgb(220, 228, 228),
+        widget_active_bg: Color32::from_rgb(205, 218, 218),
+        selection_bg: Color32::from_rgb(150, 210, 200),
+    };
+
+    pub const PRESETS: [(&'static str, Theme); 4] = [
+        ("teal", Theme::TEAL),
+        ("solarized", Theme::SOLARIZED),
+        ("high-contrast", Theme::HIGH_CONTRAST),
+        ("light", Theme::LIGHT),
+    ];
+
+    pub fn preset(name: &str) -> Option<Theme> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, theme)| *theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color32> {
+        Some(match name {
+            "bg" => &mut self.bg,
+            "fg" => &mut self.fg,
+            "highlight" => &mut self.highlight,
+            "error" => &mut self.error,
+            "dim" => &mut self.dim,
+            "warning" => &mut self.warning,
+            "success" => &mut self.success,
+            "accent" => &mut self.accent,
+            "chrome" => &mut self.chrome,
+            "widget_bg" => &mut self.widget_bg,
+            "widget_hover_bg" => &mut self.widget_hover_bg,
+            "widget_active_bg" => &mut self.widget_active_bg,
+            "selection_bg" => &mut self.selection_bg,
+            _ => return None,
+        })
+    }
+
+    pub fn apply_to_style(&self, style: &mut egui::Style) {
+        style.visuals.dark_mode = self.dark;
+        style.visuals.override_text_color = Some(self.fg);
+        style.visuals.window_fill = self.bg;
+        style.visuals.panel_fill = self.bg;
+        style.visuals.extreme_bg_color = self.bg;
+        style.visuals.widgets.noninteractive.bg_fill = self.bg;
+        style.visuals.widgets.noninteractive.fg_stroke = Stroke::new(1.0, self.fg);
+        style.visuals.widgets.inactive.bg_fill = self.widget_bg;
+        style.visuals.widgets.inactive.weak_bg_fill = self.widget_bg;
+        style.visuals.widgets.inactive.bg_stroke = Stroke::new(1.0, self.chrome);
+        style.visuals.widgets.hovered.bg_fill = self.widget_hover_bg;
+        style.visuals.widgets.hovered.weak_bg_fill = self.widget_hover_bg;
+        style.visuals.widgets.hovered.bg_stroke = Stroke::new(1.0, self.highlight);
+        style.visuals.widgets.active.bg_fill = self.widget_active_bg;
+        style.visuals.widgets.active.weak_bg_fill = self.widget_active_bg;
+        style.visuals.widgets.active.bg_stroke = Stroke::new(1.0, self.highlight);
+        style.visuals.selection.bg_fill = self.selection_bg;
+        style.visuals.selection.stroke = Stroke::new(1.0, self.highlight);
+    }
+}
+
+static THEME: std::sync::RwLock<Theme> = std::sync::RwLock::new(Theme::TEAL);
+
+/// The active palette.
+pub fn theme() -> Theme {
+    *THEME.read().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap_or_else(|e| e.into_inner()) = theme;
+}
+
+/// Highlight color of a region tag in the page and matrix views.
+pub fn tag_color(tag: RegionTag) -> Color32 {
+    match tag {
+        RegionTag::Title => Color32::from_rgb(155, 89, 182),
+        RegionTag::Paragraph => theme().success,
+        RegionTag::Table => Color32::from_rgb(230, 126, 34),
+        RegionTag::Figure => theme().accent,
+        RegionTag::Footer => Color32::from_rgb(149, 165, 166),
+    }
+}
+
+/// Overlay color of a region: its tag's color, else keyed by confidence.
+pub fn region_color(region: &TextRegion) -> Color32 {
+    match region.tag {
+        Some(tag) => tag_color(tag),
+        None if region.confidence > 0.8 => theme().highlight,
+        None if region.confidence > 0.5 => theme().warning,
+        None => theme().dim,
+    }
+}
+
+/// `chonker5_theme.json`: a preset name plus optional per-color overrides,
+/// e.g. `{"preset": "solarized", "colors": {"accent": "#d33682"}}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
+}
+
+impl ThemeConfig {
+    pub fn load() -> Self {
+        std::fs::read_to_string(config_path("chonker5_theme.json"))
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// `load`, with `chonker.toml`'s `theme` preset taking precedence so
+    /// every front end starts in the same theme.
+    pub fn load_shared(config: &ChonkerConfig) -> Self {
+        let mut theme_config = Self::load();
+        if let Some(preset) = &config.theme {
+            theme_config.preset = Some(preset.clone());
+        }
+        theme_config
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(config_path("chonker5_theme.json"), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Builds the theme, ignoring unknown presets, color names and values.
+    pub fn resolve(&self) -> Theme {
+        let mut theme = self
+            .preset
+            .as_deref()
+            .and_then(Theme::preset)
+            .unwrap_or(Theme::TEAL);
+        for (name, value) in &self.colors {
+            if let (Some(slot), Some(color)) = (theme.color_mut(name), parse_hex_color(value)) {
+                *slot = color;
+            }
+        }
+        theme
+    }
+}
+
+/// Parses `#rrggbb` (the `#` is optional).
+fn parse_hex_color(value: &str) -> Option<Color32> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+// ============= MATRIX SELECTION =============
+/// A block selection: the rectangle being dragged (`start`/`end`) plus any
+/// rectangles added earlier with Ctrl+drag. Operations act on the union.
+#[derive(Clone, Debug)]
+pub struct MatrixSelection {
+    pub start: Option<(usize, usize)>,
+    pub end: Option<(usize, usize)>,
+    pub extra: Vec<CharBBox>,
+}
+
+impl Default for MatrixSelection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatrixSelection {
+    pub fn new() -> Self {
+        Self {
+            start: None,
+            end: None,
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.end = None;
+        self.extra.clear();
+    }
+
+    /// Keeps the current rectangle and starts a new one at `pos`.
+    pub fn add_rect(&mut self, pos: (usize, usize)) {
+        if let Some(current) = self.current_rect() {
+            self.extra.push(current);
+        }
+        self.start = Some(pos);
+        self.end = Some(pos);
+    }
+
+    pub fn is_multi(&self) -> bool {
+        !self.extra.is_empty()
+    }
+
+    fn current_rect(&self) -> Option<CharBBox> {
+        let (start, end) = (self.start?, self.end?);
+        Some(CharBBox {
+            x: start.1.min(end.1),
+            y: start.0.min(end.0),
+            width: start.1.abs_diff(end.1) + 1,
+            height: start.0.abs_diff(end.0) + 1,
+        })
+    }
+
+    /// All rectangles making up the selection.
+    pub fn rects(&self) -> Vec<CharBBox> {
+        self.extra.iter().cloned().chain(self.current_rect()).collect()
+    }
+
+    pub fn is_selected(&self, row: usize, col: usize) -> bool {
+        let inside = |r: &CharBBox| row >= r.y && row < r.y + r.height && col >= r.x && col < r.x + r.width;
+        self.extra.iter().any(inside) || self.current_rect().is_some_and(|r| inside(&r))
+    }
+
+    /// Bounding rectangle of the selection, or `None` when nothing is selected.
+    pub fn bbox(&self) -> Option<CharBBox> {
+        self.rects().into_iter().reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            CharBBox {
+                x,
+                y,
+                width: (a.x + a.width).max(b.x + b.width) - x,
+                height: (a.y + a.height).max(b.y + b.height) - y,
+            }
+        })
+    }
+
+    /// The selected cells over the bounding rectangle, clipped to the matrix;
+    /// cells outside the union read as spaces so the shape is kept.
+    pub fn selected_block(&self, matrix: &[Vec<char>]) -> Vec<Vec<char>> {
+        let Some(bbox) = self.bbox() else {
+            return Vec::new();
+        };
+        (bbox.y..bbox.y + bbox.height)
+            .filter_map(|row| matrix.get(row).map(|data| (row, data)))
+            .map(|(row, data)| {
+                (bbox.x..(bbox.x + bbox.width).min(data.len()))
+                    .map(|col| if self.is_selected(row, col) { data[col] } else { ' ' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Selected cells clipped to the matrix.
+    pub fn cells(&self, matrix: &[Vec<char>]) -> Vec<(usize, usize)> {
+        let Some(bbox) = self.bbox() else {
+            return Vec::new();
+        };
+        (bbox.y..(bbox.y + bbox.height).min(matrix.len()))
+            .flat_map(|row| {
+                (bbox.x..(bbox.x + bbox.width).min(matrix[row].len())).map(move |col| (row, col))
+            })
+            .filter(|&(row, col)| self.is_selected(row, col))
+            .collect()
+    }
+
+    /// Moves every rectangle by `(rows, cols)`; callers check the bounds.
+    pub fn translate(&mut self, rows: isize, cols: isize) {
+        let shift = |(r, c): (usize, usize)| ((r as isize + rows) as usize, (c as isize + cols) as usize);
+        self.start = self.start.map(shift);
+        self.end = self.end.map(shift);
+        for rect in &mut self.extra {
+            (rect.y, rect.x) = shift((rect.y, rect.x));
+        }
+    }
+
+    pub fn get_selected_text(&self, matrix: &[Vec<char>]) -> String {
+        let mut bytes = Vec::new();
+        // Writing into a Vec cannot fail
+        let _ = self.write_selected_text(matrix, &mut bytes);
+        String::from_utf8(bytes).unwrap_or_default()
+    }
+
+    /// Streams the selected text to `out` one row at a time, without building
+    /// the block first, so selections of any size can be copied.
+    pub fn write_selected_text<W: std::io::Write>(&self, matrix: &[Vec<char>], out: &mut W) -> std::io::Result<()> {
+        let Some(bbox) = self.bbox() else {
+            return Ok(());
+        };
+        let mut line = String::new();
+        for (row, data) in matrix.iter().enumerate().take(bbox.y + bbox.height).skip(bbox.y) {
+            line.clear();
+            if row > bbox.y {
+                line.push('\n');
+            }
+            line.extend(
+                (bbox.x..(bbox.x + bbox.width).min(data.len()))
+                    .map(|col| if self.is_selected(row, col) { data[col] } else { ' ' }),
+            );
+            out.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Cells above which Ctrl+C writes the selection to a temp file instead of
+/// the system clipboard, which some platforms truncate or choke on.
+const CLIPBOARD_TEXT_LIMIT: usize = 1_000_000;
+
+/// Writes the selected text to a file in the temp directory. The browser has
+/// no file system, so there the copy fails instead.
+fn spill_selection(selection: &MatrixSelection, matrix: &[Vec<char>]) -> Result<PathBuf> {
+    if cfg!(target_arch = "wasm32") {
+        return Err(anyhow::anyhow!("Selection is too large for the browser clipboard"));
+    }
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let path = std::env::temp_dir().join(format!("chonker5_selection_{}_{}.txt", std::process::id(), nanos));
+    let mut out = std::io::BufWriter::new(std::fs::File::create(&path)?);
+    selection.write_selected_text(matrix, &mut out)?;
+    std::io::Write::flush(&mut out)?;
+    Ok(path)
+}
+
+/// Splits pasted text into rows of cells. Tabs expand to four spaces and
+/// carriage returns are dropped so Windows line endings paste cleanly.
+pub fn text_to_block(text: &str) -> Vec<Vec<char>> {
+    text.lines()
+        .map(|line| {
+            line.chars()
+                .filter(|&ch| ch != '\r')
+                .flat_map(|ch| {
+                    let expanded: Vec<char> = if ch == '\t' { vec![' '; 4] } else { vec![ch] };
+                    expanded
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Overwrites the cells of `matrix` starting at `(row, col)` with `block`,
+/// clipping at the matrix edges.
+pub fn paste_into(matrix: &mut [Vec<char>], block: &[Vec<char>], pos: (usize, usize)) {
+    for (i, block_row) in block.iter().enumerate() {
+        let Some(target) = matrix.get_mut(pos.0 + i) else {
+            break;
+        };
+        for (j, &ch) in block_row.iter().enumerate() {
+            if let Some(cell) = target.get_mut(pos.1 + j) {
+                *cell = ch;
+            }
+        }
+    }
+}
+
+/// Like `paste_into`, but spaces in `block` are transparent and leave the
+/// cell underneath as it was.
+fn overlay_into(matrix: &mut [Vec<char>], block: &[Vec<char>], pos: (usize, usize)) {
+    for (i, block_row) in block.iter().enumerate() {
+        let Some(target) = matrix.get_mut(pos.0 + i) else {
+            break;
+        };
+        for (j, &ch) in block_row.iter().enumerate() {
+            if let Some(cell) = target.get_mut(pos.1 + j).filter(|_| ch != ' ') {
+                *cell = ch;
+            }
+        }
+    }
+}
+
+/// Drops the right-aligned `{:3} ` row number the matrix views prefix to each
+/// line. Lines without one are kept whole.
+fn strip_line_number(line: &str) -> &str {
+    let trimmed = line.trim_start_matches(' ');
+    let digits = trimmed.len() - trimmed.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && trimmed[digits..].starts_with(' ') {
+        &trimmed[digits + 1..]
+    } else {
+        line
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorMove {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Cursor position after one arrow key. With `jump`, Left/Right go to the
+/// previous/next word start and Up/Down to the edge of the current block of
+/// blank or non-blank cells in the column.
+pub fn move_cursor_pos(matrix: &[Vec<char>], (row, col): (usize, usize), step: CursorMove, jump: bool) -> (usize, usize) {
+    let last_row = matrix.len().saturating_sub(1);
+    let row_len = |r: usize| matrix.get(r).map_or(0, Vec::len);
+    let blank = |r: usize, c: usize| matrix.get(r).and_then(|line| line.get(c)).is_none_or(|ch| ch.is_whitespace());
+    let clamp_col = |r: usize, c: usize| c.min(row_len(r).saturating_sub(1));
+
+    match (step, jump) {
+        (CursorMove::Left, false) => (row, col.saturating_sub(1)),
+        (CursorMove::Right, false) => (row, clamp_col(row, col + 1)),
+        (CursorMove::Up, false) => {
+            let r = row.saturating_sub(1);
+            (r, clamp_col(r, col))
+        }
+        (CursorMove::Down, false) => {
+            let r = (row + 1).min(last_row);
+            (r, clamp_col(r, col))
+        }
+        (CursorMove::Left, true) => {
+            let mut c = col;
+            while c > 0 && blank(row, c - 1) {
+                c -= 1;
+            }
+            while c > 0 && !blank(row, c - 1) {
+                c -= 1;
+            }
+            (row, c)
+        }
+        (CursorMove::Right, true) => {
+            let end = row_len(row).saturating_sub(1);
+            let mut c = col;
+            while c < end && !blank(row, c) {
+                c += 1;
+            }
+            while c < end && blank(row, c) {
+                c += 1;
+            }
+            (row, c)
+        }
+        (CursorMove::Up, true) | (CursorMove::Down, true) => {
+            let next = |r: usize| match step {
+                CursorMove::Up => r.checked_sub(1),
+                _ => (r < last_row).then_some(r + 1),
+            };
+            let mut r = row;
+            if let Some(first) = next(r) {
+                // Run to the far edge of the block the next cell belongs to
+                let in_block = blank(first, col);
+                r = first;
+                while let Some(n) = next(r) {
+                    if blank(n, col) != in_block {
+                        if in_block {
+                            r = n;
+                        }
+                        break;
+                    }
+                    r = n;
+                }
+            }
+            (r, clamp_col(r, col))
+        }
+    }
+}
+
+/// Slides the characters in `cells` one step, blanking the cells they leave.
+/// Returns the `(rows, cols)` offset, or `None` if any cell would leave its row.
+pub fn shift_cells(matrix: &mut [Vec<char>], cells: &[(usize, usize)], step: CursorMove) -> Option<(isize, isize)> {
+    let (dr, dc): (isize, isize) = match step {
+        CursorMove::Left => (0, -1),
+        CursorMove::Right => (0, 1),
+        CursorMove::Up => (-1, 0),
+        CursorMove::Down => (1, 0),
+    };
+    let targets: Vec<(usize, usize)> = cells
+        .iter()
+        .map(|&(row, col)| {
+            let row = usize::try_from(row as isize + dr).ok()?;
+            let col = usize::try_from(col as isize + dc).ok()?;
+            (col < matrix.get(row)?.len()).then_some((row, col))
+        })
+        .collect::<Option<_>>()?;
+    if cells.is_empty() {
+        return None;
+    }
+
+    let moved: Vec<char> = cells.iter().map(|&(row, col)| matrix[row][col]).collect();
+    for &(row, col) in cells {
+        matrix[row][col] = ' ';
+    }
+    for (&(row, col), ch) in targets.iter().zip(moved) {
+        matrix[row][col] = ch;
+    }
+    Some((dr, dc))
+}
+
+/// Splits a row into tokens separated by gaps of two or more spaces.
+fn column_tokens(row: &[char]) -> Vec<String> {
+    let text: String = row.iter().collect();
+    text.trim()
+        .split("  ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Re-pads the rows so tokens separated by 2+ space gaps line up in columns,
+/// keeping the block's common indentation and each row's width. Returns
+/// `None` when an aligned row would not fit in its original width.
+pub fn align_columns(rows: &[Vec<char>]) -> Option<Vec<Vec<char>>> {
+    let indent = rows
+        .iter()
+        .filter_map(|row| row.iter().position(|ch| *ch != ' '))
+        .min()
+        .unwrap_or(0);
+    let tokens: Vec<Vec<String>> = rows.iter().map(|row| column_tokens(row)).collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &tokens {
+        for (i, token) in row.iter().enumerate() {
+            let len = token.chars().count();
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(len),
+                None => widths.push(len),
+            }
+        }
+    }
+
+    rows.iter()
+        .zip(&tokens)
+        .map(|(row, row_tokens)| {
+            let mut line = " ".repeat(indent);
+            for (i, token) in row_tokens.iter().enumerate() {
+                line.push_str(token);
+                if i + 1 < row_tokens.len() {
+                    line.push_str(&" ".repeat(widths[i] - token.chars().count() + 2));
+                }
+            }
+            let mut cells: Vec<char> = line.chars().collect();
+            if row_tokens.is_empty() {
+                cells.clear();
+            }
+            if cells.len() > row.len() {
+                return None;
+            }
+            cells.resize(row.len(), ' ');
+            Some(cells)
+        })
+        .collect()
+}
+
+/// Swaps rows and columns of a `width`×`height` block; short rows read as
+/// spaces.
+pub fn transpose_block(block: &[Vec<char>], width: usize, height: usize) -> Vec<Vec<char>> {
+    (0..width)
+        .map(|col| {
+            (0..height)
+                .map(|row| block.get(row).and_then(|r| r.get(col)).copied().unwrap_or(' '))
+                .collect()
+        })
+        .collect()
+}
+
+/// Cell-wise character transformations offered on selections.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CharTransform {
+    Upper,
+    Lower,
+    Title,
+    StripDiacritics,
+    AsciiQuotes,
+    DigitLookalikes,
+}
+
+impl CharTransform {
+    pub const ALL: [CharTransform; 6] = [
+        CharTransform::Upper,
+        CharTransform::Lower,
+        CharTransform::Title,
+        CharTransform::StripDiacritics,
+        CharTransform::AsciiQuotes,
+        CharTransform::DigitLookalikes,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CharTransform::Upper => "UPPER CASE",
+            CharTransform::Lower => "lower case",
+            CharTransform::Title => "Title Case",
+            CharTransform::StripDiacritics => "Strip diacritics",
+            CharTransform::AsciiQuotes => "Smart quotes → ASCII",
+            CharTransform::DigitLookalikes => "Digit lookalikes (O→0, l→1)",
+        }
+    }
+
+    /// `word_start` is true when the previous cell in the row is not a letter
+    /// or digit; only `Title` looks at it.
+    pub fn apply(self, ch: char, word_start: bool) -> char {
+        // Case mappings that expand to several chars (e.g. ß) keep the cell as is
+        let single = |mapped: &mut dyn Iterator<Item = char>| match (mapped.next(), mapped.next()) {
+            (Some(c), None) => c,
+            _ => ch,
+        };
+        match self {
+            CharTransform::Upper => single(&mut ch.to_uppercase()),
+            CharTransform::Lower => single(&mut ch.to_lowercase()),
+            CharTransform::Title if word_start => CharTransform::Upper.apply(ch, word_start),
+            CharTransform::Title => CharTransform::Lower.apply(ch, word_start),
+            CharTransform::StripDiacritics => strip_diacritic(ch),
+            CharTransform::AsciiQuotes => match ch {
+                '‘' | '’' | '‚' | '‛' | '′' | '`' | '´' => '\'',
+                '“' | '”' | '„' | '‟' | '″' | '«' | '»' => '"',
+                '–' | '—' | '‒' | '−' => '-',
+                '\u{a0}' => ' ',
+                _ => ch,
+            },
+            CharTransform::DigitLookalikes => match ch {
+                'O' | 'o' => '0',
+                'l' | 'I' | '|' => '1',
+                _ => ch,
+            },
+        }
+    }
+}
+
+/// Base letter for common accented Latin characters.
+fn strip_diacritic(ch: char) -> char {
+    const TABLE: &[(&str, char)] = &[
+        ("àáâãäåāăą", 'a'),
+        ("ÀÁÂÃÄÅĀĂĄ", 'A'),
+        ("çćĉċč", 'c'),
+        ("ÇĆĈĊČ", 'C'),
+        ("ďđ", 'd'),
+        ("ĎĐ", 'D'),
+        ("èéêëēĕėęě", 'e'),
+        ("ÈÉÊËĒĔĖĘĚ", 'E'),
+        ("ĝğġģ", 'g'),
+        ("ĜĞĠĢ", 'G'),
+        ("ìíîïĩīĭįı", 'i'),
+        ("ÌÍÎÏĨĪĬĮİ", 'I'),
+        ("ĺļľŀł", 'l'),
+        ("ĹĻĽĿŁ", 'L'),
+        ("ñńņňŉ", 'n'),
+        ("ÑŃŅŇ", 'N'),
+        ("òóôõöøōŏő", 'o'),
+        ("ÒÓÔÕÖØŌŎŐ", 'O'),
+        ("ŕŗř", 'r'),
+        ("ŔŖŘ", 'R'),
+        ("śŝşš", 's'),
+        ("ŚŜŞŠ", 'S'),
+        ("ţťŧ", 't'),
+        ("ŢŤŦ", 'T'),
+        ("ùúûüũūŭůűų", 'u'),
+        ("ÙÚÛÜŨŪŬŮŰŲ", 'U'),
+        ("ýÿŷ", 'y'),
+        ("ÝŸŶ", 'Y'),
+        ("źżž", 'z'),
+        ("ŹŻŽ", 'Z'),
+    ];
+    TABLE
+        .iter()
+        .find(|(accented, _)| accented.contains(ch))
+        .map_or(ch, |&(_, base)| base)
+}
+
+/// Applies `transform` to each of `cells`.
+pub fn transform_cells(matrix: &mut [Vec<char>], cells: &[(usize, usize)], transform: CharTransform) {
+    for &(row, col) in cells {
+        let word_start = col == 0 || !matrix[row][col - 1].is_alphanumeric();
+        matrix[row][col] = transform.apply(matrix[row][col], word_start);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WhitespaceOp {
+    TrimTrailing,
+    SqueezeRuns,
+    RemoveBlankRows,
+}
+
+impl WhitespaceOp {
+    pub const ALL: [WhitespaceOp; 3] = [
+        WhitespaceOp::TrimTrailing,
+        WhitespaceOp::SqueezeRuns,
+        WhitespaceOp::RemoveBlankRows,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WhitespaceOp::TrimTrailing => "Trim trailing spaces",
+            WhitespaceOp::SqueezeRuns => "Squeeze runs of spaces",
+            WhitespaceOp::RemoveBlankRows => "Remove blank rows",
+        }
+    }
+}
+
+/// Applies a whitespace cleanup to rows. Trimming and squeezing shorten
+/// rows (indentation is kept) and blank-row removal drops rows; callers pad
+/// the result back into place where the shape must be kept.
+pub fn cleanup_rows(rows: &[Vec<char>], op: WhitespaceOp) -> Vec<Vec<char>> {
+    let trimmed = |row: &Vec<char>| -> Vec<char> {
+        let keep = row.iter().rposition(|ch| *ch != ' ').map_or(0, |i| i + 1);
+        row[..keep].to_vec()
+    };
+    match op {
+        WhitespaceOp::TrimTrailing => rows.iter().map(trimmed).collect(),
+        WhitespaceOp::SqueezeRuns => rows
+            .iter()
+            .map(|row| {
+                let row = trimmed(row);
+                let indent = row.iter().take_while(|ch| **ch == ' ').count();
+                let mut squeezed = row[..indent].to_vec();
+                for &ch in &row[indent..] {
+                    if !(ch == ' ' && squeezed.last() == Some(&' ')) {
+                        squeezed.push(ch);
+                    }
+                }
+                squeezed
+            })
+            .collect(),
+        WhitespaceOp::RemoveBlankRows => rows
+            .iter()
+            .filter(|row| row.iter().any(|ch| *ch != ' '))
+            .cloned()
+            .collect(),
+    }
+}
+
+// Box-drawing arms, one bit per direction a line leaves the cell
+const BOX_UP: u8 = 1;
+const BOX_RIGHT: u8 = 2;
+const BOX_DOWN: u8 = 4;
+const BOX_LEFT: u8 = 8;
+
+/// Arms of a box-drawing character; 0 for anything else.
+fn box_arms(ch: char) -> u8 {
+    match ch {
+        '╴' => BOX_LEFT,
+        '╶' => BOX_RIGHT,
+        '╵' => BOX_UP,
+        '╷' => BOX_DOWN,
+        '─' => BOX_LEFT | BOX_RIGHT,
+        '│' => BOX_UP | BOX_DOWN,
+        '┌' => BOX_RIGHT | BOX_DOWN,
+        '┐' => BOX_LEFT | BOX_DOWN,
+        '└' => BOX_RIGHT | BOX_UP,
+        '┘' => BOX_LEFT | BOX_UP,
+        '├' => BOX_UP | BOX_DOWN | BOX_RIGHT,
+        '┤' => BOX_UP | BOX_DOWN | BOX_LEFT,
+        '┬' => BOX_LEFT | BOX_RIGHT | BOX_DOWN,
+        '┴' => BOX_LEFT | BOX_RIGHT | BOX_UP,
+        '┼' => BOX_UP | BOX_RIGHT | BOX_DOWN | BOX_LEFT,
+        _ => 0,
+    }
+}
+
+/// Character for a set of arms. A lone arm, at the end of a line, draws as a
+/// half line so a later line can still turn it into a corner.
+fn box_char(arms: u8) -> char {
+    const HORIZONTAL: u8 = BOX_LEFT | BOX_RIGHT;
+    const VERTICAL: u8 = BOX_UP | BOX_DOWN;
+    match arms {
+        0 => ' ',
+        BOX_LEFT => '╴',
+        BOX_RIGHT => '╶',
+        BOX_UP => '╵',
+        BOX_DOWN => '╷',
+        HORIZONTAL => '─',
+        VERTICAL => '│',
+        a if a == BOX_RIGHT | BOX_DOWN => '┌',
+        a if a == BOX_LEFT | BOX_DOWN => '┐',
+        a if a == BOX_RIGHT | BOX_UP => '└',
+        a if a == BOX_LEFT | BOX_UP => '┘',
+        a if a == VERTICAL | BOX_RIGHT => '├',
+        a if a == VERTICAL | BOX_LEFT => '┤',
+        a if a == HORIZONTAL | BOX_DOWN => '┬',
+        a if a == HORIZONTAL | BOX_UP => '┴',
+        _ => '┼',
+    }
+}
+
+/// Draws a line from `from` to `to` (along the row first, then down the
+/// column), merging arms with any box characters already in the way so
+/// crossings become junctions. Other characters are overwritten; short rows
+/// are padded out. Returns false if nothing changed.
+pub fn draw_box_line(matrix: &mut [Vec<char>], from: (usize, usize), to: (usize, usize)) -> bool {
+    let add_arm = |matrix: &mut [Vec<char>], (row, col): (usize, usize), arm: u8| {
+        let Some(cells) = matrix.get_mut(row) else {
+            return false;
+        };
+        if col >= cells.len() {
+            cells.resize(col + 1, ' ');
+        }
+        let ch = box_char(box_arms(cells[col]) | arm);
+        let changed = cells[col] != ch;
+        cells[col] = ch;
+        changed
+    };
+    let mut changed = false;
+    let mut at = from;
+    while at != to {
+        let (next, out_arm, in_arm) = if at.1 != to.1 {
+            if at.1 < to.1 {
+                ((at.0, at.1 + 1), BOX_RIGHT, BOX_LEFT)
+            } else {
+                ((at.0, at.1 - 1), BOX_LEFT, BOX_RIGHT)
+            }
+        } else if at.0 < to.0 {
+            ((at.0 + 1, at.1), BOX_DOWN, BOX_UP)
+        } else {
+            ((at.0 - 1, at.1), BOX_UP, BOX_DOWN)
+        };
+        changed |= add_arm(matrix, at, out_arm);
+        changed |= add_arm(matrix, next, in_arm);
+        at = next;
+    }
+    changed
+}
+
+/// Greedy word wrap of the text in `rows` to `width` columns. Blank rows
+/// separate paragraphs and are kept, a word hyphenated across a row break is
+/// joined back up, and words longer than `width` are broken.
+pub fn reflow_text(rows: &[Vec<char>], width: usize) -> Vec<Vec<char>> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let mut paragraphs: Vec<Vec<String>> = vec![Vec::new()];
+    for row in rows {
+        let line: String = row.iter().collect();
+        let mut words = line.split_whitespace().peekable();
+        let Some(first) = words.peek() else {
+            if paragraphs.last().is_some_and(|p| !p.is_empty()) {
+                paragraphs.push(Vec::new());
+            }
+            continue;
+        };
+        let paragraph = paragraphs.last_mut().expect("always one paragraph");
+        if let Some(previous) = paragraph.last_mut() {
+            let hyphenated = previous.len() > 1 && previous.ends_with('-') && !previous.ends_with("--");
+            if hyphenated && first.starts_with(char::is_lowercase) {
+                previous.pop();
+                previous.push_str(first);
+                words.next();
+            }
+        }
+        paragraph.extend(words.map(str::to_string));
+    }
+
+    let mut out: Vec<Vec<char>> = Vec::new();
+    for (i, words) in paragraphs.iter().filter(|p| !p.is_empty()).enumerate() {
+        if i > 0 {
+            out.push(Vec::new());
+        }
+        let mut line: Vec<char> = Vec::new();
+        for word in words {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > width {
+                if !line.is_empty() {
+                    out.push(std::mem::take(&mut line));
+                }
+                out.push(word.drain(..width).collect());
+            }
+            if word.is_empty() {
+                continue;
+            }
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                out.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        if !line.is_empty() {
+            out.push(line);
+        }
+    }
+    out
+}
+
+/// How `sort_rows` orders rows. `key_cols` is a column range relative to the
+/// rows (inclusive); `None` compares whole rows.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RowSort {
+    pub key_cols: Option<(usize, usize)>,
+    pub numeric: bool,
+    pub reverse: bool,
+}
+
+/// Parses a number such as `1,204.50`, `(12)` or `-3%`, ignoring padding.
+fn parse_sort_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (text, negative) = match text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) => (inner, true),
+        None => (text, false),
+    };
+    let cleaned: String = text.chars().filter(|c| !matches!(c, ',' | '$' | '%' | '€' | '£')).collect();
+    let value: f64 = cleaned.trim().parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Sorts whole rows in place (stable). Numeric sorts put rows without a
+/// number in the key columns last, in either direction.
+pub fn sort_rows(rows: &mut [Vec<char>], sort: RowSort) {
+    let key = |row: &Vec<char>| -> String {
+        match sort.key_cols {
+            Some((from, to)) => row.iter().skip(from).take(to.saturating_sub(from) + 1).collect(),
+            None => row.iter().collect(),
+        }
+    };
+    if sort.numeric {
+        rows.sort_by(|a, b| {
+            match (parse_sort_number(&key(a)), parse_sort_number(&key(b))) {
+                (Some(x), Some(y)) => {
+                    let order = x.total_cmp(&y);
+                    if sort.reverse { order.reverse() } else { order }
+                }
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+        });
+    } else {
+        rows.sort_by(|a, b| {
+            let order = key(a).trim().cmp(key(b).trim());
+            if sort.reverse { order.reverse() } else { order }
+        });
+    }
+}
+
+/// Runs of consecutive rows where `edited` differs from `original`; a row
+/// present on only one side counts as changed.
+pub fn edit_hunks(original: &[Vec<char>], edited: &[Vec<char>]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for row in 0..original.len().max(edited.len()) {
+        if original.get(row) == edited.get(row) {
+            continue;
+        }
+        match hunks.last_mut() {
+            Some(hunk) if hunk.end == row => hunk.end = row + 1,
+            _ => hunks.push(row..row + 1),
+        }
+    }
+    hunks
+}
+
+/// Puts the original rows of one hunk back. A hunk that runs to the end of
+/// the matrix also restores the original row count.
+pub fn revert_hunk(edited: &mut Vec<Vec<char>>, original: &[Vec<char>], rows: std::ops::Range<usize>) {
+    for row in rows.clone() {
+        match (original.get(row), row < edited.len()) {
+            (Some(line), true) => edited[row] = line.clone(),
+            (Some(line), false) => edited.push(line.clone()),
+            (None, _) => {}
+        }
+    }
+    if rows.end >= edited.len().max(original.len()) {
+        edited.truncate(original.len());
+    }
+}
+
+/// Read-only matrix painter for the compare view.
+pub fn paint_diff_matrix(ui: &mut egui::Ui, matrix: &[Vec<char>], mask: &[Vec<bool>], font_size: f32) -> Response {
+    let theme = theme();
+    let char_size = matrix_cell_size(font_size);
+    let cols = matrix.iter().map(Vec::len).max().unwrap_or(0);
+    let (response, painter) = ui.allocate_painter(
+        Vec2::new(cols as f32 * char_size.x, mask.len() as f32 * char_size.y),
+        Sense::hover(),
+    );
+    let font_id = FontId::monospace(font_size);
+    for (row_idx, row) in mask.iter().enumerate() {
+        for (col_idx, &differs) in row.iter().enumerate() {
+            let pos = response.rect.min + Vec2::new(col_idx as f32 * char_size.x, row_idx as f32 * char_size.y);
+            if differs {
+                painter.rect_filled(Rect::from_min_size(pos, char_size), 0.0, theme.error.gamma_multiply(0.35));
+            }
+            let ch = matrix.get(row_idx).and_then(|r| r.get(col_idx)).copied().unwrap_or(' ');
+            if ch != ' ' {
+                painter.text(
+                    pos + Vec2::new(char_size.x * 0.45, char_size.y * 0.5),
+                    Align2::CENTER_CENTER,
+                    ch.to_string(),
+                    font_id.clone(),
+                    if differs { theme.fg } else { theme.dim },
+                );
+            }
+        }
+    }
+    response
+}
+
+/// Parses a goto target: `row`, `row,col`, `row:col` or `row col`, using the
+/// zero-based row numbers printed in exported matrix files.
+pub fn parse_goto(input: &str) -> Option<(usize, usize)> {
+    let mut parts = input
+        .split(|c: char| c == ',' || c == ':' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    let row = parts.next()?.parse().ok()?;
+    let col = match parts.next() {
+        Some(col) => col.parse().ok()?,
+        None => 0,
+    };
+    parts.next().is_none().then_some((row, col))
+}
+
+/// Case-insensitive hits of `query` within each row, as `(row, col, len)`.
+/// Matches do not span rows.
+pub fn find_in_matrix(matrix: &[Vec<char>], query: &str) -> Vec<(usize, usize, usize)> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let needle: Vec<char> = query.chars().map(fold).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    for (row_idx, row) in matrix.iter().enumerate() {
+        let hay: Vec<char> = row.iter().map(|&c| fold(c)).collect();
+        let mut col = 0;
+        while col + needle.len() <= hay.len() {
+            if hay[col..col + needle.len()] == needle[..] {
+                matches.push((row_idx, col, needle.len()));
+                col += needle.len();
+            } else {
+                col += 1;
+            }
+        }
+    }
+    matches
+}
+
+fn block_to_text(block: &[Vec<char>]) -> String {
+    block
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rows and columns of a grid painted at `rect` that fall inside `clip`,
+/// so only the visible part of a large matrix is laid out each frame.
+pub fn visible_cells(rect: Rect, clip: Rect, char_size: Vec2) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+    let span = |from: f32, to: f32, origin: f32, cell: f32| {
+        let start = ((from - origin) / cell).floor().max(0.0) as usize;
+        let end = ((to - origin) / cell).ceil().max(0.0) as usize;
+        start..end.max(start)
+    };
+    (
+        span(clip.top(), clip.bottom(), rect.top(), char_size.y),
+        span(clip.left(), clip.right(), rect.left(), char_size.x),
+    )
+}
+
+/// Default matrix font size in points; cells are 6×10 at this size.
+pub const MATRIX_FONT_SIZE: f32 = 9.0;
+pub const MATRIX_FONT_RANGE: (f32, f32) = (5.0, 27.0);
+
+/// Cell size for a matrix font size, keeping the default 6×10 proportions.
+pub fn matrix_cell_size(font_size: f32) -> Vec2 {
+    Vec2::new(6.0, 10.0) * (font_size / MATRIX_FONT_SIZE)
+}
+
+/// An undo or redo asked for from the keyboard while the app keeps the
+/// history (see `MatrixGrid::shared_history`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryStep {
+    Undo,
+    Redo,
+}
+
+/// What a dropped block does to non-space cells already under it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DropPolicy {
+    #[default]
+    Overwrite,
+    Merge,
+    Reject,
+}
+
+impl DropPolicy {
+    pub const ALL: [DropPolicy; 3] = [DropPolicy::Overwrite, DropPolicy::Merge, DropPolicy::Reject];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DropPolicy::Overwrite => "Overwrite",
+            DropPolicy::Merge => "Merge (fill blanks only)",
+            DropPolicy::Reject => "Reject if text would be lost",
+        }
+    }
+}
+
+/// Per-document matrix view preferences, persisted as `<name>.matrix_view.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatrixViewPrefs {
+    #[serde(default = "default_matrix_font_size")]
+    pub font_size: f32,
+    #[serde(default)]
+    pub drop_policy: DropPolicy,
+}
+
+fn default_matrix_font_size() -> f32 {
+    MATRIX_FONT_SIZE
+}
+
+impl Default for MatrixViewPrefs {
+    fn default() -> Self {
+        Self {
+            font_size: MATRIX_FONT_SIZE,
+            drop_policy: DropPolicy::Overwrite,
+        }
+    }
+}
+
+fn matrix_view_prefs_path(pdf_path: &Path) -> PathBuf {
+    pdf_path.with_extension("matrix_view.json")
+}
+
+pub fn load_matrix_view_prefs(pdf_path: &Path) -> MatrixViewPrefs {
+    std::fs::read_to_string(matrix_view_prefs_path(pdf_path))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_matrix_view_prefs(pdf_path: &Path, prefs: &MatrixViewPrefs) -> Result<()> {
+    std::fs::write(matrix_view_prefs_path(pdf_path), serde_json::to_string_pretty(prefs)?)?;
+    Ok(())
+}
+
+/// Writes `block` with its top-left at `pos` under `policy`; cells past the
+/// matrix edge are dropped. Returns false (leaving the matrix untouched) if
+/// `Reject` finds a non-space cell the block would change.
+pub fn drop_block(matrix: &mut [Vec<char>], block: &[Vec<char>], pos: (usize, usize), policy: DropPolicy) -> bool {
+    if policy == DropPolicy::Reject {
+        let collides = block.iter().enumerate().any(|(i, block_row)| {
+            block_row.iter().enumerate().any(|(j, &ch)| {
+                matrix
+                    .get(pos.0 + i)
+                    .and_then(|row| row.get(pos.1 + j))
+                    .is_some_and(|&cell| cell != ' ' && cell != ch)
+            })
+        });
+        if collides {
+            return false;
+        }
+    }
+    for (i, block_row) in block.iter().enumerate() {
+        let Some(target) = matrix.get_mut(pos.0 + i) else {
+            break;
+        };
+        for (j, &ch) in block_row.iter().enumerate() {
+            if let Some(cell) = target.get_mut(pos.1 + j) {
+                if policy != DropPolicy::Merge || *cell == ' ' {
+                    *cell = ch;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// Where a dropped block may snap to: left edges of text regions and the
+/// rows that hold text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapGuides {
+    pub cols: Vec<usize>,
+    pub rows: Vec<usize>,
+}
+
+/// Cells a drop may move to reach a guide.
+const SNAP_RADIUS: usize = 3;
+
+impl SnapGuides {
+    pub fn from_regions(regions: &[TextRegion]) -> Self {
+        let mut cols: Vec<usize> = regions.iter().map(|r| r.bbox.x).collect();
+        let mut rows: Vec<usize> = regions.iter().flat_map(|r| r.bbox.y..r.bbox.y + r.bbox.height).collect();
+        cols.sort_unstable();
+        cols.dedup();
+        rows.sort_unstable();
+        rows.dedup();
+        Self { cols, rows }
+    }
+
+    /// Moves `(row, col)` to the nearest guide on each axis within
+    /// `SNAP_RADIUS`; an axis with no guide in reach is left alone.
+    pub fn snap(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        let nearest = |guides: &[usize], value: usize| {
+            guides
+                .iter()
+                .copied()
+                .filter(|g| g.abs_diff(value) <= SNAP_RADIUS)
+                .min_by_key(|g| g.abs_diff(value))
+                .unwrap_or(value)
+        };
+        (nearest(&self.rows, row), nearest(&self.cols, col))
+    }
+}
+
+/// Modes of the optional vim-style keybindings.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum VimMode {
+    #[default]
+    Normal,
+    Insert,
+    VisualBlock,
+}
+
+impl VimMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+            VimMode::VisualBlock => "VISUAL BLOCK",
+        }
+    }
+}
+
+/// State of the vim-style keybindings between keystrokes.
+#[derive(Debug, Clone, Default)]
+pub struct VimState {
+    pub mode: VimMode,
+    count: usize,
+    operator: Option<(char, usize)>, // `d` or `y` waiting for its repeat, with its count
+    anchor: (usize, usize),          // Visual-block corner that stays put
+}
+
+/// A block lifted with Ctrl+arrows; it is previewed at `target()` and only
+/// written back when Ctrl is released.
+struct BlockMove {
+    origin: (usize, usize),
+    offset: (isize, isize),
+    block: Vec<Vec<char>>,
+}
+
+impl BlockMove {
+    fn target(&self) -> (usize, usize) {
+        (
+            (self.origin.0 as isize + self.offset.0) as usize,
+            (self.origin.1 as isize + self.offset.1) as usize,
+        )
+    }
+}
+
+pub struct MatrixGrid {
+    pub matrix: Vec<Vec<char>>,
+    pub selection: MatrixSelection,
+    pub char_size: Vec2,
+    pub font_size: f32,
+    pub cursor_pos: Option<(usize, usize)>,
+    pub last_blink: Instant,
+    pub cursor_visible: bool,
+    pub clipboard: Vec<Vec<char>>,   // Store rectangular clipboard
+    pub modified: bool,              // Track if matrix was modified
+    pub is_dragging_selection: bool, // Track if we're dragging a selection
+    pub drag_start_pos: Option<(usize, usize)>, // Where the drag started
+    pub drag_content: Vec<Vec<char>>, // Content being dragged
+    pub scroll_to: Option<(usize, usize)>, // Cell to bring into view on next show
+    pub search_matches: Vec<(usize, usize, usize)>, // (row, col, len) of each hit
+    pub current_match: Option<usize>,
+    search_query: String,
+    undo_stack: Vec<Vec<Vec<char>>>,
+    redo_stack: Vec<Vec<Vec<char>>>,
+    edit_steps: usize,   // Undo steps started, for `take_edit`
+    handed_steps: usize, // `edit_steps` as of the last `take_edit`
+    typed_at: Option<(usize, usize)>, // Where the cursor went after the last typed character
+    pub shared_history: bool, // Undo keys go to `history_requests`; the app keeps the history
+    pub history_requests: Vec<HistoryStep>,
+    pub draw_mode: bool, // Dragging paints box-drawing lines
+    draw_last: Option<(usize, usize)>,
+    draw_before: Option<Vec<Vec<char>>>,
+    block_move: Option<BlockMove>,
+    pub snap: Option<SnapGuides>, // Snap mouse drops to text columns / rows
+    pub drop_policy: DropPolicy,
+    pub drop_rejected: bool, // Set when a drop was refused; the app reports it
+    pub copy_spill: Option<Result<PathBuf, String>>, // Where an oversized Ctrl+C went; the app reports it
+    pub vim: Option<VimState>, // Modal keybindings when set
+    pub original: Option<Vec<Vec<char>>>, // Tint cells that differ from this extraction
+    glyphs: std::cell::RefCell<GlyphCache>, // Laid-out cells at the current font size
+}
+
+/// Galleys kept before the glyph cache starts over (colors come and go with
+/// selection, theme and preview tints).
+const GLYPH_CACHE_LIMIT: usize = 4096;
+
+/// Laid-out cells, valid only for the font atlas they were laid out in:
+/// egui rebuilds the atlas when the scale changes or it fills up, and
+/// galleys from the old one point at the wrong part of the texture.
+#[derive(Default)]
+struct GlyphCache {
+    galleys: HashMap<(char, Color32), Arc<egui::Galley>>,
+    pixels_per_point: f32,
+    atlas: std::sync::Weak<egui::mutex::Mutex<egui::epaint::TextureAtlas>>,
+}
+
+impl GlyphCache {
+    /// Starts over when the fonts were rebuilt since the last frame.
+    fn begin_frame(&mut self, ctx: &egui::Context) {
+        let pixels_per_point = ctx.pixels_per_point();
+        let atlas = ctx.fonts(|fonts| fonts.texture_atlas());
+        let same_atlas = self.atlas.upgrade().is_some_and(|cached| Arc::ptr_eq(&cached, &atlas));
+        if !same_atlas || self.pixels_per_point != pixels_per_point {
+            self.galleys.clear();
+            self.pixels_per_point = pixels_per_point;
+            self.atlas = Arc::downgrade(&atlas);
+        }
+    }
+}
+
+/// Matrix snapshots kept for Ctrl+Z.
+const UNDO_LIMIT: usize = 100;
+
+impl MatrixGrid {
+    pub fn new(text: &str) -> Self {
+        let matrix: Vec<Vec<char>> = text
+            .lines()
+            .map(|line| strip_line_number(line).chars().collect())
+            .collect();
+
+        Self {
+            matrix,
+            selection: MatrixSelection::new(),
+            char_size: matrix_cell_size(MATRIX_FONT_SIZE),
+            font_size: MATRIX_FONT_SIZE,
+            cursor_pos: None,
+            last_blink: Instant::now(),
+            cursor_visible: true,
+            clipboard: Vec::new(),
+            modified: false,
+            is_dragging_selection: false,
+            drag_start_pos: None,
+            drag_content: Vec::new(),
+            scroll_to: None,
+            search_matches: Vec::new(),
+            current_match: None,
+            search_query: String::new(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            edit_steps: 0,
+            handed_steps: 0,
+            typed_at: None,
+            shared_history: false,
+            history_requests: Vec::new(),
+            draw_mode: false,
+            draw_last: None,
+            draw_before: None,
+            block_move: None,
+            snap: None,
+            drop_policy: DropPolicy::Overwrite,
+            drop_rejected: false,
+            copy_spill: None,
+            vim: None,
+            original: None,
+            glyphs: std::cell::RefCell::new(GlyphCache::default()),
+        }
+    }
+
+    /// Re-runs the search over the current contents. A changed query starts
+    /// over from the first hit; an unchanged one keeps the current hit.
+    pub fn set_search(&mut self, query: &str) {
+        let query_changed = query != self.search_query;
+        if query_changed {
+            self.search_query = query.to_string();
+        }
+        self.search_matches = find_in_matrix(&self.matrix, query);
+        if query_changed || self.current_match.is_some_and(|i| i >= self.search_matches.len()) {
+            self.current_match = None;
+        }
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search_query.clear();
+        self.search_matches.clear();
+        self.current_match = None;
+    }
+
+    /// Moves to the next (`step > 0`) or previous hit, wrapping around, and
+    /// scrolls it into view.
+    pub fn step_match(&mut self, step: i32) {
+        let count = self.search_matches.len();
+        if count == 0 {
+            return;
+        }
+        let next = match (self.current_match, step > 0) {
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.current_match = Some(next);
+        let (row, col, _) = self.search_matches[next];
+        self.scroll_to = Some((row, col));
+    }
+
+    /// Places the cursor at `(row, col)` and scrolls it into view on the next
+    /// frame, e.g. when linked from a click on the PDF.
+    pub fn set_cursor(&mut self, row: usize, col: usize) {
+        if row < self.matrix.len() && col < self.matrix[row].len() {
+            self.cursor_pos = Some((row, col));
+            self.typed_at = None;
+            self.cursor_visible = true;
+            self.last_blink = Instant::now();
+            self.selection.clear();
+            self.scroll_to = Some((row, col));
+        }
+    }
+
+    /// Selects the cells covered by `bbox` and scrolls its top-left corner
+    /// into view on the next frame.
+    pub fn select_block(&mut self, bbox: &CharBBox) {
+        if bbox.width == 0 || bbox.height == 0 {
+            return;
+        }
+        self.cursor_pos = None;
+        self.selection.clear();
+        self.selection.start = Some((bbox.y, bbox.x));
+        self.selection.end = Some((bbox.y + bbox.height - 1, bbox.x + bbox.width - 1));
+        self.scroll_to = Some((bbox.y, bbox.x));
+    }
+
+    /// Scales the font and cells together.
+    pub fn set_font_size(&mut self, font_size: f32) {
+        let font_size = font_size.clamp(MATRIX_FONT_RANGE.0, MATRIX_FONT_RANGE.1);
+        if font_size != self.font_size {
+            self.font_size = font_size;
+            self.char_size = matrix_cell_size(font_size);
+            self.glyphs.borrow_mut().galleys.clear();
+        }
+    }
+
+    /// Lines up the columns of the selected rows. Returns false when nothing
+    /// is selected or the aligned rows would not fit the selection.
+    pub fn align_selected_columns(&mut self) -> bool {
+        let Some(bbox) = self.selection.bbox() else {
+            return false;
+        };
+        let Some(aligned) = align_columns(&crop_matrix(&self.matrix, &bbox)) else {
+            return false;
+        };
+        self.checkpoint();
+        paste_into(&mut self.matrix, &aligned, (bbox.y, bbox.x));
+        self.modified = true;
+        true
+    }
+
+    /// Transposes the selected rectangle in place, anchored at its top-left
+    /// corner. Returns false if the rotated block would not fit the matrix.
+    pub fn transpose_selection(&mut self) -> bool {
+        let Some(bbox) = self.selection.bbox() else {
+            return false;
+        };
+        let fits = (bbox.y..bbox.y + bbox.width)
+            .all(|row| self.matrix.get(row).is_some_and(|r| r.len() >= bbox.x + bbox.height));
+        if !fits {
+            return false;
+        }
+
+        self.checkpoint();
+        let transposed = transpose_block(&crop_matrix(&self.matrix, &bbox), bbox.width, bbox.height);
+        let blank = vec![vec![' '; bbox.width]; bbox.height];
+        paste_into(&mut self.matrix, &blank, (bbox.y, bbox.x));
+        paste_into(&mut self.matrix, &transposed, (bbox.y, bbox.x));
+        self.select_block(&CharBBox {
+            x: bbox.x,
+            y: bbox.y,
+            width: bbox.height,
+            height: bbox.width,
+        });
+        self.modified = true;
+        true
+    }
+
+    /// Re-wraps the text under `bbox` to its width. Returns false if the
+    /// wrapped text needs more rows than the block has.
+    pub fn reflow_block(&mut self, bbox: &CharBBox) -> bool {
+        let wrapped = reflow_text(&crop_matrix(&self.matrix, bbox), bbox.width);
+        if wrapped.len() > bbox.height {
+            return false;
+        }
+        self.checkpoint();
+        paste_into(&mut self.matrix, &vec![vec![' '; bbox.width]; bbox.height], (bbox.y, bbox.x));
+        paste_into(&mut self.matrix, &wrapped, (bbox.y, bbox.x));
+        self.modified = true;
+        true
+    }
+
+    /// Reverts the rows of an edit hunk to `original` as one undo step.
+    pub fn revert_rows(&mut self, original: &[Vec<char>], rows: std::ops::Range<usize>) {
+        self.checkpoint();
+        revert_hunk(&mut self.matrix, original, rows);
+        self.after_history_step();
+    }
+
+    /// Sets every selected cell to `ch`; filling with a space clears the
+    /// selection without touching the clipboard.
+    pub fn fill_selection(&mut self, ch: char) -> bool {
+        let cells = self.selection.cells(&self.matrix);
+        if cells.is_empty() {
+            return false;
+        }
+        self.checkpoint();
+        for (row, col) in cells {
+            self.matrix[row][col] = ch;
+        }
+        self.modified = true;
+        true
+    }
+
+    pub fn transform_selection(&mut self, transform: CharTransform) -> bool {
+        let cells = self.selection.cells(&self.matrix);
+        if cells.is_empty() {
+            return false;
+        }
+        self.checkpoint();
+        transform_cells(&mut self.matrix, &cells, transform);
+        self.modified = true;
+        true
+    }
+
+    /// Sorts the rows of the selected block; the cells outside it stay put.
+    pub fn sort_selected_rows(&mut self, sort: RowSort) -> bool {
+        let Some(bbox) = self.selection.bbox() else {
+            return false;
+        };
+        self.checkpoint();
+        let mut block = crop_matrix(&self.matrix, &bbox);
+        sort_rows(&mut block, sort);
+        paste_into(&mut self.matrix, &block, (bbox.y, bbox.x));
+        self.modified = true;
+        true
+    }
+
+    /// Overwrites the cells starting at `pos` with `block`, clipping at the
+    /// matrix edges.
+    pub fn paste_block(&mut self, block: &[Vec<char>], pos: (usize, usize)) {
+        self.checkpoint();
+        paste_into(&mut self.matrix, block, pos);
+    }
+
+    /// Pastes only the non-space cells of `block`, for layering a correction
+    /// over existing text.
+    pub fn paste_block_transparent(&mut self, block: &[Vec<char>], pos: (usize, usize)) {
+        self.checkpoint();
+        overlay_into(&mut self.matrix, block, pos);
+    }
+
+    /// Records the current matrix so the next edit can be undone.
+    pub fn checkpoint(&mut self) {
+        let snapshot = self.matrix.clone();
+        self.push_undo(snapshot);
+    }
+
+    fn push_undo(&mut self, snapshot: Vec<Vec<char>>) {
+        // With a shared history only the last snapshot is kept, to roll back
+        // a refused drop
+        let limit = if self.shared_history { 1 } else { UNDO_LIMIT };
+        if self.undo_stack.len() >= limit {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(snapshot);
+        self.redo_stack.clear();
+        self.edit_steps += 1;
+        self.typed_at = None;
+    }
+
+    /// Takes the edits made since the last call, for an app keeping the
+    /// history: the cells, and whether they started a new undo step rather
+    /// than continuing the last one (more typing, a dragged block landing).
+    /// `None` when nothing changed or a box-drawing stroke is still going.
+    pub fn take_edit(&mut self) -> Option<(Vec<Vec<char>>, bool)> {
+        if !self.modified || self.draw_before.is_some() {
+            return None;
+        }
+        self.modified = false;
+        let new_step = self.handed_steps != self.edit_steps;
+        self.handed_steps = self.edit_steps;
+        Some((self.matrix.clone(), new_step))
+    }
+
+    /// Replaces the cells with ones the app restored or loaded, keeping the
+    /// cursor where it still fits.
+    pub fn set_matrix(&mut self, matrix: Vec<Vec<char>>) {
+        self.matrix = matrix;
+        self.modified = false;
+        self.typed_at = None;
+        self.draw_last = None;
+        self.draw_before = None;
+        self.block_move = None;
+        if let Some((row, col)) = self.cursor_pos {
+            if self.matrix.get(row).is_none_or(|r| col >= r.len()) {
+                self.cursor_pos = None;
+            }
+        }
+    }
+
+    fn step_history(&mut self, step: HistoryStep) {
+        if self.shared_history {
+            self.history_requests.push(step);
+        } else if step == HistoryStep::Undo {
+            self.undo();
+        } else {
+            self.redo();
+        }
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push(std::mem::replace(&mut self.matrix, previous));
+        self.after_history_step();
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(next) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push(std::mem::replace(&mut self.matrix, next));
+        self.after_history_step();
+        true
+    }
+
+    fn after_history_step(&mut self) {
+        self.modified = true;
+        self.typed_at = None;
+        if let Some((row, col)) = self.cursor_pos {
+            if self.matrix.get(row).is_none_or(|r| col >= r.len()) {
+                self.cursor_pos = None;
+            }
+        }
+    }
+
+    /// Runs a whitespace cleanup over the selection, or the whole matrix when
+    /// nothing is selected. Returns false if nothing changed.
+    pub fn cleanup_whitespace(&mut self, op: WhitespaceOp) -> bool {
+        let before = self.matrix.clone();
+        match self.selection.bbox() {
+            None => {
+                let widths: Vec<usize> = self.matrix.iter().map(Vec::len).collect();
+                self.matrix = cleanup_rows(&self.matrix, op);
+                if op == WhitespaceOp::SqueezeRuns {
+                    for (row, width) in self.matrix.iter_mut().zip(widths) {
+                        row.resize(width, ' ');
+                    }
+                }
+                self.selection.clear();
+            }
+            Some(bbox) => {
+                let cleaned = cleanup_rows(&crop_matrix(&self.matrix, &bbox), op);
+                paste_into(&mut self.matrix, &vec![vec![' '; bbox.width]; bbox.height], (bbox.y, bbox.x));
+                paste_into(&mut self.matrix, &cleaned, (bbox.y, bbox.x));
+                if op == WhitespaceOp::TrimTrailing {
+                    // Rows whose end lies inside the selection lose their trailing blanks
+                    for row in self.matrix.iter_mut().skip(bbox.y).take(bbox.height) {
+                        if bbox.x + bbox.width >= row.len() {
+                            let keep = row.iter().rposition(|ch| *ch != ' ').map_or(0, |i| i + 1);
+                            row.truncate(keep.max(bbox.x));
+                        }
+                    }
+                }
+            }
+        }
+        if self.matrix == before {
+            return false;
+        }
+        self.push_undo(before);
+        self.after_history_step();
+        true
+    }
+
+    /// Ctrl+arrow with a block selected: lifts the block on the first step,
+    /// then moves its preview one cell per step, keeping it inside the grid.
+    fn step_block_move(&mut self, step: CursorMove) {
+        if self.block_move.is_none() {
+            let Some(bbox) = self.selection.bbox().filter(|_| !self.selection.is_multi()) else {
+                return;
+            };
+            self.block_move = Some(BlockMove {
+                origin: (bbox.y, bbox.x),
+                offset: (0, 0),
+                block: self.selection.selected_block(&self.matrix),
+            });
+        }
+        let rows = self.matrix.len();
+        let width = self.matrix.first().map_or(0, Vec::len);
+        let Some(block_move) = &mut self.block_move else {
+            return;
+        };
+        let (rows_step, cols_step) = match step {
+            CursorMove::Left => (0, -1),
+            CursorMove::Right => (0, 1),
+            CursorMove::Up => (-1, 0),
+            CursorMove::Down => (1, 0),
+        };
+        let row = block_move.origin.0 as isize + block_move.offset.0 + rows_step;
+        let col = block_move.origin.1 as isize + block_move.offset.1 + cols_step;
+        let block_width = block_move.block.iter().map(Vec::len).max().unwrap_or(0);
+        if row < 0 || col < 0 || row as usize + block_move.block.len() > rows || col as usize + block_width > width {
+            return;
+        }
+        block_move.offset = (block_move.offset.0 + rows_step, block_move.offset.1 + cols_step);
+        self.scroll_to = Some((row as usize, col as usize));
+    }
+
+    /// Drops a Ctrl+arrow move where its preview is, like a mouse drop.
+    fn finish_block_move(&mut self) {
+        let Some(block_move) = self.block_move.take() else {
+            return;
+        };
+        if block_move.offset == (0, 0) {
+            return;
+        }
+        self.checkpoint();
+        for (row, col) in self.selection.cells(&self.matrix) {
+            self.matrix[row][col] = ' ';
+        }
+        if self.land_block(&block_move.block, block_move.target()) {
+            self.selection.translate(block_move.offset.0, block_move.offset.1);
+            self.modified = true;
+        }
+    }
+
+    /// Drops a lifted block under the drop policy. The caller has already
+    /// taken a checkpoint and blanked the block's origin; a rejected drop
+    /// rolls back to that checkpoint.
+    fn land_block(&mut self, block: &[Vec<char>], at: (usize, usize)) -> bool {
+        if drop_block(&mut self.matrix, block, at, self.drop_policy) {
+            return true;
+        }
+        if let Some(before) = self.undo_stack.pop() {
+            self.matrix = before;
+        }
+        self.drop_rejected = true;
+        false
+    }
+
+    fn snap_drop(&self, at: (usize, usize)) -> (usize, usize) {
+        self.snap.as_ref().map_or(at, |guides| guides.snap(at))
+    }
+
+    /// Handles a typed character in vim normal or visual-block mode: `hjkl`,
+    /// `w`/`b`, `0`/`$` and `G` move (with counts), `i`/`a` insert, `v`
+    /// starts a visual block, `x`, `dd`/`yy` and `p`/`P` work on rectangles,
+    /// and `u` undoes.
+    fn vim_key(&mut self, ch: char) {
+        let Some(mut vim) = self.vim.take() else {
+            return;
+        };
+        if self.matrix.is_empty() {
+            self.vim = Some(vim);
+            return;
+        }
+        if let Some(digit) = ch.to_digit(10).filter(|&d| d > 0 || vim.count > 0) {
+            vim.count = (vim.count * 10 + digit as usize).min(9999);
+            self.vim = Some(vim);
+            return;
+        }
+        let count = std::mem::take(&mut vim.count).max(1);
+        let last_row = self.matrix.len() - 1;
+        let cursor = self.cursor_pos.unwrap_or((0, 0));
+        let row_end = |matrix: &[Vec<char>], row: usize| matrix[row].len().saturating_sub(1);
+
+        let motion = |step: CursorMove, jump: bool| {
+            (0..count).fold(cursor, |at, _| move_cursor_pos(&self.matrix, at, step, jump))
+        };
+        let target = match ch {
+            'h' => Some(motion(CursorMove::Left, false)),
+            'j' => Some(motion(CursorMove::Down, false)),
+            'k' => Some(motion(CursorMove::Up, false)),
+            'l' => Some(motion(CursorMove::Right, false)),
+            'w' => Some(motion(CursorMove::Right, true)),
+            'b' => Some(motion(CursorMove::Left, true)),
+            '0' => Some((cursor.0, 0)),
+            '$' => Some((cursor.0, row_end(&self.matrix, cursor.0))),
+            'G' => Some((last_row, cursor.1.min(row_end(&self.matrix, last_row)))),
+            _ => None,
+        };
+        if let Some(target) = target {
+            vim.operator = None;
+            self.set_cursor(target.0, target.1);
+            if vim.mode == VimMode::VisualBlock {
+                self.selection.start = Some(vim.anchor);
+                self.selection.end = Some(target);
+            }
+            self.vim = Some(vim);
+            return;
+        }
+
+        match (vim.mode, ch) {
+            (VimMode::Normal, 'i') => {
+                self.set_cursor(cursor.0, cursor.1);
+                vim.mode = VimMode::Insert;
+            }
+            (VimMode::Normal, 'a') => {
+                let (row, col) = cursor;
+                let col = (col + 1).min(row_end(&self.matrix, row));
+                self.set_cursor(row, col);
+                vim.mode = VimMode::Insert;
+            }
+            (VimMode::Normal, 'v') => {
+                vim.mode = VimMode::VisualBlock;
+                vim.anchor = cursor;
+                self.selection.clear();
+                self.selection.start = Some(cursor);
+                self.selection.end = Some(cursor);
+            }
+            (VimMode::Normal, 'x') => {
+                let (row, col) = cursor;
+                let end = (col + count).min(self.matrix[row].len());
+                if col < end {
+                    self.clipboard = vec![self.matrix[row][col..end].to_vec()];
+                    self.checkpoint();
+                    self.matrix[row][col..end].fill(' ');
+                    self.modified = true;
+                }
+            }
+            (VimMode::Normal, 'p' | 'P') if !self.clipboard.is_empty() => {
+                let block = self.clipboard.clone();
+                if ch == 'P' {
+                    self.paste_block_transparent(&block, cursor);
+                } else {
+                    self.paste_block(&block, cursor);
+                }
+                self.modified = true;
+            }
+            (VimMode::Normal, 'u') => {
+                for _ in 0..count {
+                    self.step_history(HistoryStep::Undo);
+                }
+            }
+            (VimMode::Normal, 'd' | 'y') => match vim.operator.take() {
+                Some((pending, pending_count)) if pending == ch => {
+                    // dd / yy take whole rows, a count of rows from the cursor
+                    let rows = cursor.0..(cursor.0 + pending_count * count).min(last_row + 1);
+                    self.clipboard = self.matrix[rows.clone()].to_vec();
+                    if ch == 'd' {
+                        self.checkpoint();
+                        for row in &mut self.matrix[rows] {
+                            row.fill(' ');
+                        }
+                        self.modified = true;
+                    }
+                }
+                _ => vim.operator = Some((ch, count)),
+            },
+            (VimMode::VisualBlock, 'o') => {
+                let anchor = std::mem::replace(&mut vim.anchor, cursor);
+                self.set_cursor(anchor.0, anchor.1);
+                self.selection.start = Some(vim.anchor);
+                self.selection.end = Some(anchor);
+            }
+            (VimMode::VisualBlock, 'y' | 'd' | 'x') => {
+                self.clipboard = self.selection.selected_block(&self.matrix);
+                if ch != 'y' {
+                    self.fill_selection(' ');
+                }
+                if let Some(bbox) = self.selection.bbox() {
+                    self.set_cursor(bbox.y, bbox.x);
+                }
+                self.selection.clear();
+                vim.mode = VimMode::Normal;
+            }
+            (VimMode::VisualBlock, 'v') => {
+                self.selection.clear();
+                vim.mode = VimMode::Normal;
+            }
+            _ => vim.operator = None,
+        }
+        self.vim = Some(vim);
+    }
+
+    /// Esc in vim mode: back to normal mode, dropping any visual block or
+    /// half-typed command.
+    fn vim_escape(&mut self) {
+        let Some(vim) = &mut self.vim else {
+            return;
+        };
+        if vim.mode == VimMode::VisualBlock {
+            self.selection.clear();
+        }
+        *vim = VimState::default();
+    }
+
+    /// Paints `ch` centred on `center`, laying it out only the first time a
+    /// character/color pair is seen at this font size.
+    fn paint_glyph(&self, painter: &egui::Painter, center: egui::Pos2, ch: char, font_id: &FontId, color: Color32) {
+        let galley = {
+            let glyphs = &mut self.glyphs.borrow_mut().galleys;
+            if glyphs.len() >= GLYPH_CACHE_LIMIT {
+                glyphs.clear();
+            }
+            glyphs
+                .entry((ch, color))
+                .or_insert_with(|| painter.layout_no_wrap(ch.to_string(), font_id.clone(), color))
+                .clone()
+        };
+        painter.galley(center - galley.size() / 2.0, galley);
+    }
+
+    /// Paints `block` translucently with its top-left cell at `at`.
+    fn paint_block_preview(&self, painter: &egui::Painter, rect: Rect, font_id: &egui::FontId, block: &[Vec<char>], at: (usize, usize)) {
+        let theme = theme();
+        for (i, drag_row) in block.iter().enumerate() {
+            let target_row = at.0 + i;
+            if target_row < self.matrix.len() {
+                for (j, &ch) in drag_row.iter().enumerate() {
+                    let target_col = at.1 + j;
+                    if target_col < self.matrix.get(target_row).map_or(0, |r| r.len()) {
+                        let pos = rect.min
+                            + Vec2::new(
+                                target_col as f32 * self.char_size.x,
+                                target_row as f32 * self.char_size.y,
+                            );
+
+                        // Draw preview background
+                        let preview_rect = Rect::from_min_size(
+                            pos - Vec2::new(0.0, self.char_size.y * 0.1),
+                            Vec2::new(self.char_size.x, self.char_size.y * 1.2),
+                        );
+                        painter.rect_filled(
+                            preview_rect,
+                            2.0,
+                            theme.fg.gamma_multiply(0.25),
+                        );
+
+                        // Draw preview character
+                        self.paint_glyph(
+                            painter,
+                            pos + Vec2::new(self.char_size.x * 0.45, self.char_size.y * 0.5),
+                            ch,
+                            font_id,
+                            theme.fg.gamma_multiply(0.7),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Response {
+        self.show_viewport(ui, true)
+    }
+
+    /// Paints the grid into `ui`. When the same grid is shown in several
+    /// viewports, only the one with `active` set handles keystrokes and
+    /// pending scroll requests so edits apply once per frame.
+    pub fn show_viewport(&mut self, ui: &mut egui::Ui, active: bool) -> Response {
+        let theme = theme();
+
+        let (response, painter) = ui.allocate_painter(
+            Vec2::new(
+                self.matrix.first().map_or(0.0, |row| row.len() as f32) * self.char_size.x,
+                self.matrix.len() as f32 * self.char_size.y,
+            ),
+            Sense::click_and_drag(),
+        );
+
+        let rect = response.rect;
+        let font_id = egui::FontId::monospace(self.font_size);
+        self.glyphs.get_mut().begin_frame(ui.ctx());
+
+        // Update cursor blink
+        let now = Instant::now();
+        if now.duration_since(self.last_blink).as_millis() > 530 {
+            self.cursor_visible = !self.cursor_visible;
+            self.last_blink = now;
+            ui.ctx().request_repaint();
+        }
+
+        // Handle mouse click for cursor position
+        if response.clicked() {
+            if let Some(pos) = response.hover_pos() {
+                let local_pos = pos - rect.min;
+                let row = (local_pos.y / self.char_size.y) as usize;
+                let col = (local_pos.x / self.char_size.x) as usize;
+                if row < self.matrix.len() && col < self.matrix.get(row).map_or(0, |r| r.len()) {
+                    self.cursor_pos = Some((row, col));
+                    self.typed_at = None;
+                    self.cursor_visible = true;
+                    self.last_blink = Instant::now();
+                    // Clear selection when clicking to place cursor
+                    self.selection.clear();
+                }
+            }
+        }
+
+        // In draw mode dragging paints lines instead of selecting; the whole
+        // stroke is one undo step
+        if self.draw_mode && (response.drag_started() || response.dragged()) {
+            if let Some(pos) = response.hover_pos() {
+                let local_pos = pos - rect.min;
+                let row = ((local_pos.y.max(0.0) / self.char_size.y) as usize).min(self.matrix.len().saturating_sub(1));
+                let width = self.matrix.first().map_or(0, Vec::len);
+                let col = ((local_pos.x.max(0.0) / self.char_size.x) as usize).min(width.saturating_sub(1));
+                match self.draw_last {
+                    Some(last) if response.dragged() => {
+                        if draw_box_line(&mut self.matrix, last, (row, col)) {
+                            self.modified = true;
+                        }
+                    }
+                    _ => {
+                        self.draw_before = Some(self.matrix.clone());
+                        self.selection.clear();
+                        self.cursor_pos = None;
+                    }
+                }
+                self.draw_last = Some((row, col));
+            }
+        }
+        if response.drag_released() {
+            self.draw_last = None;
+            if let Some(before) = self.draw_before.take() {
+                if before != self.matrix {
+                    self.push_undo(before);
+                }
+            }
+        }
+
+        // Handle drag start
+        if response.drag_started() && !self.draw_mode {
+            if let Some(pos) = response.hover_pos() {
+                let local_pos = pos - rect.min;
+                let row = (local_pos.y / self.char_size.y) as usize;
+                let col = (local_pos.x / self.char_size.x) as usize;
+
+                let add_rect = ui.input(|i| i.modifiers.command || i.modifiers.ctrl);
+
+                // Check if we're starting a drag on an existing selection
+                if !add_rect
+                    && !self.selection.is_multi()
+                    && self.selection.is_selected(row, col)
+                    && self.selection.start.is_some()
+                    && self.selection.end.is_some()
+                {
+                    // Start dragging the selection
+                    self.is_dragging_selection = true;
+                    self.drag_start_pos = Some((row, col));
+
+                    // Copy the selected content
+                    if let (Some(start), Some(end)) = (self.selection.start, self.selection.end) {
+                        let min_row = start.0.min(end.0).min(self.matrix.len().saturating_sub(1));
+                        let max_row = start.0.max(end.0).min(self.matrix.len().saturating_sub(1));
+                        let min_col = start.1.min(end.1);
+                        let max_col = start.1.max(end.1);
+
+                        self.drag_content.clear();
+                        for row in min_row..=max_row {
+                            if row < self.matrix.len() {
+                                let row_data = &self.matrix[row];
+                                let mut row_chars = Vec::new();
+                                let row_max_col = max_col.min(row_data.len().saturating_sub(1));
+
+                                for col in min_col..=row_max_col {
+                                    if col < row_data.len() {
+                                        row_chars.push(row_data[col]);
+                                    }
+                                }
+                                self.drag_content.push(row_chars);
+                            }
+                        }
+
+                        // Clear the original selection
+                        self.checkpoint();
+                        for row in min_row..=max_row {
+                            if row < self.matrix.len() {
+                                let row_data = &mut self.matrix[row];
+                                let row_max_col = max_col.min(row_data.len().saturating_sub(1));
+                                for col in min_col..=row_max_col {
+                                    if col < row_data.len() {
+                                        row_data[col] = ' ';
+                                    }
+                                }
+                            }
+                        }
+                        self.modified = true;
+                    }
+                } else if add_rect {
+                    // Ctrl+drag adds another rectangle to the selection
+                    self.selection.add_rect((row, col));
+                    self.cursor_pos = None;
+                    self.is_dragging_selection = false;
+                } else {
+                    // Start a new selection
+                    self.selection.clear();
+                    self.selection.start = Some((row, col));
+                    self.selection.end = Some((row, col));
+                    self.cursor_pos = None;
+                    self.is_dragging_selection = false;
+                }
+            }
+        }
+
+        // Handle dragging
+        if response.dragged() && !self.draw_mode {
+            if let Some(pos) = response.hover_pos() {
+                let local_pos = pos - rect.min;
+                let row = (local_pos.y / self.char_size.y) as usize;
+                let col = (local_pos.x / self.char_size.x) as usize;
+
+                if self.is_dragging_selection {
+                    // Update visual feedback during drag
+                    // We'll show a preview at the current position
+                } else {
+                    // Continue selection
+                    self.selection.end = Some((row, col));
+                }
+            }
+        }
+
+        // Handle drag release
+        if response.drag_released() && !self.draw_mode && self.is_dragging_selection {
+            if let Some(pos) = response.hover_pos() {
+                let local_pos = pos - rect.min;
+                let row = (local_pos.y / self.char_size.y) as usize;
+                let col = (local_pos.x / self.char_size.x) as usize;
+                let (row, col) = self.snap_drop((row, col));
+
+                // Drop the content at the new position
+                let content = std::mem::take(&mut self.drag_content);
+                if self.land_block(&content, (row, col)) {
+                    // Clear selection after drop
+                    self.selection.clear();
+                }
+                self.modified = true;
+            }
+
+            // Reset drag state
+            self.is_dragging_selection = false;
+            self.drag_start_pos = None;
+            self.drag_content.clear();
+        }
+
+        // Draw background
+        painter.rect_filled(rect, 0.0, theme.bg);
+
+        // Only cells inside the scroll area's clip rect are painted
+        let (visible_rows, visible_cols) = visible_cells(rect, ui.clip_rect(), self.char_size);
+
+        // Tint hand-edited cells, those that differ from the original extraction
+        if let Some(original) = &self.original {
+            for (row_idx, row) in self.matrix.iter().enumerate().skip(visible_rows.start).take(visible_rows.len()) {
+                for (col_idx, &ch) in row.iter().enumerate().skip(visible_cols.start).take(visible_cols.len()) {
+                    let was = original.get(row_idx).and_then(|r| r.get(col_idx)).copied().unwrap_or(' ');
+                    if ch != was {
+                        let pos = rect.min + Vec2::new(col_idx as f32 * self.char_size.x, row_idx as f32 * self.char_size.y);
+                        painter.rect_filled(Rect::from_min_size(pos, self.char_size), 0.0, theme.success.gamma_multiply(0.3));
+                    }
+                }
+            }
+        }
+
+        // Draw search hits under the characters
+        for (i, &(row, col, len)) in self.search_matches.iter().enumerate() {
+            if !visible_rows.contains(&row) {
+                continue;
+            }
+            let hit_rect = Rect::from_min_size(
+                rect.min + Vec2::new(col as f32 * self.char_size.x, row as f32 * self.char_size.y),
+                Vec2::new(len as f32 * self.char_size.x, self.char_size.y),
+            );
+            if self.current_match == Some(i) {
+                painter.rect_filled(hit_rect, 1.0, theme.warning.gamma_multiply(0.6));
+                painter.rect_stroke(hit_rect, 1.0, Stroke::new(1.0, theme.warning));
+            } else {
+                painter.rect_filled(hit_rect, 1.0, theme.warning.gamma_multiply(0.3));
+            }
+        }
+
+        // Draw matrix with selection
+        for (row_idx, row) in self.matrix.iter().enumerate().skip(visible_rows.start).take(visible_rows.len()) {
+            for (col_idx, &ch) in row.iter().enumerate().skip(visible_cols.start).take(visible_cols.len()) {
+                let pos = rect.min
+                    + Vec2::new(
+                        col_idx as f32 * self.char_size.x,
+                        row_idx as f32 * self.char_size.y,
+                    );
+
+                // Highlight if selected
+                if self.selection.is_selected(row_idx, col_idx) {
+                    let selection_rect = Rect::from_min_size(
+                        pos - Vec2::new(0.0, self.char_size.y * 0.1),
+                        Vec2::new(self.char_size.x, self.char_size.y * 1.2),
+                    );
+                    painter.rect_filled(selection_rect, 2.0, theme.fg.gamma_multiply(0.3));
+                }
+
+                // Draw character
+                if ch == ' ' {
+                    continue;
+                }
+                let char_color = if self.selection.is_selected(row_idx, col_idx) {
+                    theme.bg
+                } else if ch == '·' {
+                    theme.dim
+                } else {
+                    theme.fg
+                };
+
+                self.paint_glyph(
+                    &painter,
+                    pos + Vec2::new(self.char_size.x * 0.45, self.char_size.y * 0.5),
+                    ch,
+                    &font_id,
+                    char_color,
+                );
+            }
+        }
+
+        if let Some((row, col)) = self.scroll_to.filter(|_| active) {
+            self.scroll_to = None;
+            let target = Rect::from_min_size(
+                rect.min + Vec2::new(col as f32 * self.char_size.x, row as f32 * self.char_size.y),
+                self.char_size,
+            );
+            ui.scroll_to_rect(target, Some(egui::Align::Center));
+        }
+
+        // Draw blinking cursor if visible
+        if let Some((cursor_row, cursor_col)) = self.cursor_pos {
+            if self.cursor_visible && cursor_row < self.matrix.len() {
+                let cursor_pos = rect.min
+                    + Vec2::new(
+                        cursor_col as f32 * self.char_size.x,
+                        cursor_row as f32 * self.char_size.y,
+                    );
+
+                painter.rect_filled(
+                    Rect::from_min_size(
+                        cursor_pos - Vec2::new(0.0, self.char_size.y * 0.1),
+                        Vec2::new(self.char_size.x * 0.8, self.char_size.y * 1.2),
+                    ),
+                    0.0,
+                    theme.fg,
+                );
+
+                if cursor_col < self.matrix[cursor_row].len() {
+                    let ch = self.matrix[cursor_row][cursor_col];
+                    self.paint_glyph(
+                        &painter,
+                        cursor_pos + Vec2::new(self.char_size.x * 0.5, self.char_size.y * 0.5),
+                        ch,
+                        &font_id,
+                        theme.bg,
+                    );
+                }
+            }
+        }
+
+        // Draw drag preview if we're dragging
+        if self.is_dragging_selection {
+            if let Some(hover_pos) = response.hover_pos() {
+                let local_pos = hover_pos - rect.min;
+                let preview_row = (local_pos.y / self.char_size.y) as usize;
+                let preview_col = (local_pos.x / self.char_size.x) as usize;
+                let at = self.snap_drop((preview_row, preview_col));
+                self.paint_block_preview(&painter, rect, &font_id, &self.drag_content, at);
+            }
+        }
+
+        // Preview of a block being moved with Ctrl+arrows
+        if let Some(block_move) = &self.block_move {
+            self.paint_block_preview(&painter, rect, &font_id, &block_move.block, block_move.target());
+        }
+
+        // Leave keystrokes to a text field (e.g. the search box) that has focus
+        if !active || ui.ctx().memory(|m| m.focus().is_some()) {
+            return response;
+        }
+
+        // Handle cut/copy/paste operations
+        ui.input(|i| {
+            if i.modifiers.command || i.modifiers.ctrl {
+                // Copy (Ctrl+C)
+                if i.key_pressed(egui::Key::C) && self.selection.bbox().is_some() {
+                    // Copy the selection's bounding block to the clipboard
+                    self.clipboard = self.selection.selected_block(&self.matrix);
+
+                    // Also copy as text to the system clipboard, or to a
+                    // file when it is too large for one
+                    let cells: usize = self.clipboard.iter().map(Vec::len).sum();
+                    if cells <= CLIPBOARD_TEXT_LIMIT {
+                        let selected_text = self.selection.get_selected_text(&self.matrix);
+                        if !selected_text.is_empty() {
+                            ui.output_mut(|o| o.copied_text = selected_text);
+                        }
+                    } else {
+                        self.copy_spill = Some(spill_selection(&self.selection, &self.matrix).map_err(|e| e.to_string()));
+                    }
+                }
+
+                // Cut (Ctrl+X)
+                if i.key_pressed(egui::Key::X) && self.selection.bbox().is_some() {
+                    // Copy to clipboard first, then clear the selected cells
+                    self.clipboard = self.selection.selected_block(&self.matrix);
+                    self.checkpoint();
+                    for (row, col) in self.selection.cells(&self.matrix) {
+                        self.matrix[row][col] = ' ';
+                    }
+                    self.modified = true;
+                }
+
+                // Undo / redo (Ctrl+Z, Ctrl+Shift+Z or Ctrl+Y)
+                if i.key_pressed(egui::Key::Z) {
+                    if i.modifiers.shift {
+                        self.step_history(HistoryStep::Redo);
+                    } else {
+                        self.step_history(HistoryStep::Undo);
+                    }
+                }
+                if i.key_pressed(egui::Key::Y) {
+                    self.step_history(HistoryStep::Redo);
+                }
+
+                // Paste (Ctrl+V); Ctrl+Shift+V treats spaces as transparent
+                if i.key_pressed(egui::Key::V) {
+                    // Determine paste position - use cursor position or the selection's top-left
+                    let paste_pos = if let Some(cursor_pos) = self.cursor_pos {
+                        cursor_pos
+                    } else if let Some(bbox) = self.selection.bbox() {
+                        (bbox.y, bbox.x)
+                    } else {
+                        (0, 0) // Default to top-left if no cursor or selection
+                    };
+
+                    // Text copied from another application arrives as a Paste event;
+                    // our own copies match the internal clipboard and keep its shape
+                    let internal_text = block_to_text(&self.clipboard);
+                    let system_block = i.events.iter().find_map(|event| match event {
+                        egui::Event::Paste(text) if !text.is_empty() && *text != internal_text => {
+                            Some(text_to_block(text))
+                        }
+                        _ => None,
+                    });
+
+                    let block = system_block.unwrap_or_else(|| self.clipboard.clone());
+                    if !block.is_empty() {
+                        if i.modifiers.shift {
+                            self.paste_block_transparent(&block, paste_pos);
+                        } else {
+                            self.paste_block(&block, paste_pos);
+                        }
+
+                        // Clear selection after paste
+                        self.selection.clear();
+                        self.modified = true;
+                    }
+                }
+            }
+
+            // A Ctrl+arrow move lands when Ctrl is let go; Esc puts it back
+            if self.block_move.is_some() {
+                if i.key_pressed(egui::Key::Escape) {
+                    self.block_move = None;
+                } else if !(i.modifiers.command || i.modifiers.ctrl) {
+                    self.finish_block_move();
+                }
+            }
+
+            // Delete / Backspace clear the selected cells
+            if self.cursor_pos.is_none()
+                && (i.key_pressed(egui::Key::Delete) || i.key_pressed(egui::Key::Backspace))
+            {
+                self.fill_selection(' ');
+            }
+
+            // Arrow keys move the cursor; Shift extends a block selection from
+            // where the cursor was, Ctrl jumps by word / block edge and Alt
+            // nudges the selected block
+            for event in &i.events {
+                let egui::Event::Key { key, pressed: true, modifiers, .. } = event else {
+                    continue;
+                };
+                let step = match key {
+                    egui::Key::ArrowLeft => CursorMove::Left,
+                    egui::Key::ArrowRight => CursorMove::Right,
+                    egui::Key::ArrowUp => CursorMove::Up,
+                    egui::Key::ArrowDown => CursorMove::Down,
+                    _ => continue,
+                };
+                if (modifiers.command || modifiers.ctrl) && self.cursor_pos.is_none() {
+                    self.step_block_move(step);
+                    continue;
+                }
+                if modifiers.alt {
+                    // Alt+arrow nudges the selected block
+                    let cells = self.selection.cells(&self.matrix);
+                    let before = self.matrix.clone();
+                    if let Some((rows, cols)) = shift_cells(&mut self.matrix, &cells, step) {
+                        self.push_undo(before);
+                        self.selection.translate(rows, cols);
+                        self.modified = true;
+                    }
+                    continue;
+                }
+                let Some(cursor) = self.cursor_pos else {
+                    continue;
+                };
+                let target = move_cursor_pos(&self.matrix, cursor, step, modifiers.command || modifiers.ctrl);
+                if modifiers.shift {
+                    if self.selection.start.is_none() || self.selection.end != Some(cursor) {
+                        self.selection.clear();
+                        self.selection.start = Some(cursor);
+                    }
+                    self.selection.end = Some(target);
+                } else {
+                    self.selection.clear();
+                }
+                self.cursor_pos = Some(target);
+                self.typed_at = None;
+                self.cursor_visible = true;
+                self.last_blink = Instant::now();
+                self.scroll_to = Some(target);
+            }
+
+            // Outside insert mode, vim bindings take the typed characters
+            if self.vim.as_ref().is_some_and(|vim| vim.mode != VimMode::Insert || i.key_pressed(egui::Key::Escape)) {
+                if i.key_pressed(egui::Key::Escape) {
+                    self.vim_escape();
+                }
+                for event in &i.events {
+                    if let egui::Event::Text(text) = event {
+                        for ch in text.chars() {
+                            self.vim_key(ch);
+                        }
+                    }
+                }
+                return;
+            }
+
+            // Handle character input for editing
+            if let Some((cursor_row, cursor_col)) = self.cursor_pos {
+                for event in &i.events {
+                    if let egui::Event::Text(text) = event {
+                        for ch in text.chars() {
+                            if cursor_row < self.matrix.len()
+                                && cursor_col < self.matrix[cursor_row].len()
+                            {
+                                // A run of typing is one undo step
+                                if self.typed_at != Some((cursor_row, cursor_col)) {
+                                    self.checkpoint();
+                                }
+                                self.matrix[cursor_row][cursor_col] = ch;
+                                self.modified = true;
+                                // Move cursor right
+                                if cursor_col + 1 < self.matrix[cursor_row].len() {
+                                    self.cursor_pos = Some((cursor_row, cursor_col + 1));
+                                }
+                                self.typed_at = self.cursor_pos;
+                                break; // Only process first character
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_system_text_as_block() {
+        let mut grid = MatrixGrid::new("");
+        grid.matrix = vec![vec!['.'; 5]; 3];
+
+        let block = text_to_block("ab\r\n\tc");
+        assert_eq!(block[1], vec![' ', ' ', ' ', ' ', 'c']);
+
+        grid.paste_block(&text_to_block("xy\nz"), (1, 3));
+        assert_eq!(matrix_to_text(&grid.matrix), ".....\n...xy\n...z.\n");
+
+        // Spaces in a transparent paste keep what is underneath
+        grid.paste_block_transparent(&text_to_block("1 2\n  3 4"), (0, 0));
+        assert_eq!(matrix_to_text(&grid.matrix), "1.2..\n..3x4\n...z.\n");
+    }
+
+    #[test]
+    fn test_matrix_search_finds_and_wraps() {
+        let mut grid = MatrixGrid::new("  0 Total total\n  1 --TOTAL--\n");
+        grid.set_search("total");
+        assert_eq!(grid.search_matches, vec![(0, 0, 5), (0, 6, 5), (1, 2, 5)]);
+
+        grid.step_match(-1);
+        assert_eq!(grid.current_match, Some(2));
+        assert_eq!(grid.scroll_to, Some((1, 2)));
+        grid.step_match(1);
+        assert_eq!(grid.current_match, Some(0));
+
+        // Editing away a hit keeps navigation in range
+        grid.matrix[1] = "---------".chars().collect();
+        grid.set_search("total");
+        assert_eq!(grid.search_matches.len(), 2);
+        assert_eq!(grid.current_match, Some(0));
+
+        grid.set_search("");
+        assert!(grid.search_matches.is_empty());
+    }
+
+    #[test]
+    fn test_theme_presets_and_overrides() {
+        assert_eq!(Theme::preset("Solarized"), Some(Theme::SOLARIZED));
+        assert_eq!(Theme::preset("nope"), None);
+        assert!(!Theme::preset("light").unwrap().dark);
+
+        assert_eq!(parse_hex_color("#ff8000"), Some(Color32::from_rgb(255, 128, 0)));
+        assert_eq!(parse_hex_color("00ff00"), Some(Color32::from_rgb(0, 255, 0)));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#gg0000"), None);
+
+        let config: ThemeConfig = serde_json::from_str(
+            r##"{"preset": "high-contrast", "colors": {"accent": "#d33682", "bogus": "#000000", "fg": "red"}}"##,
+        )
+        .unwrap();
+        let theme = config.resolve();
+        assert_eq!(theme.accent, Color32::from_rgb(211, 54, 130));
+        assert_eq!(theme.fg, Theme::HIGH_CONTRAST.fg);
+        assert_eq!(ThemeConfig::default().resolve(), Theme::TEAL);
+    }
+
+    #[test]
+    fn test_matrix_grid_font_size_scales_cells() {
+        let mut grid = MatrixGrid::new("  0 ab\n");
+        assert_eq!(grid.char_size, Vec2::new(6.0, 10.0));
+        grid.set_font_size(18.0);
+        assert_eq!(grid.char_size, Vec2::new(12.0, 20.0));
+        grid.set_font_size(100.0);
+        assert_eq!(grid.font_size, MATRIX_FONT_RANGE.1);
+
+        let prefs: MatrixViewPrefs = serde_json::from_str("{}").unwrap();
+        assert_eq!(prefs.font_size, MATRIX_FONT_SIZE);
+    }
+
+    #[test]
+    fn test_parse_goto() {
+        assert_eq!(parse_goto("12"), Some((12, 0)));
+        assert_eq!(parse_goto(" 12, 40 "), Some((12, 40)));
+        assert_eq!(parse_goto("3:7"), Some((3, 7)));
+        assert_eq!(parse_goto("3 7"), Some((3, 7)));
+        assert_eq!(parse_goto(""), None);
+        assert_eq!(parse_goto("a,1"), None);
+        assert_eq!(parse_goto("1,2,3"), None);
+    }
+
+    #[test]
+    fn test_move_cursor_pos_steps_and_jumps() {
+        let matrix: Vec<Vec<char>> = ["ab  cd ef", "x", "", "", "yz"]
+            .iter()
+            .map(|row| format!("{:<9}", row).chars().collect())
+            .collect();
+
+        assert_eq!(move_cursor_pos(&matrix, (0, 0), CursorMove::Left, false), (0, 0));
+        assert_eq!(move_cursor_pos(&matrix, (0, 8), CursorMove::Right, false), (0, 8));
+        assert_eq!(move_cursor_pos(&matrix, (4, 0), CursorMove::Down, false), (4, 0));
+
+        // Word jumps
+        assert_eq!(move_cursor_pos(&matrix, (0, 0), CursorMove::Right, true), (0, 4));
+        assert_eq!(move_cursor_pos(&matrix, (0, 4), CursorMove::Right, true), (0, 7));
+        assert_eq!(move_cursor_pos(&matrix, (0, 7), CursorMove::Left, true), (0, 4));
+        assert_eq!(move_cursor_pos(&matrix, (0, 5), CursorMove::Left, true), (0, 4));
+
+        // Block edges down column 0: x, then over the gap to y
+        assert_eq!(move_cursor_pos(&matrix, (0, 0), CursorMove::Down, true), (1, 0));
+        assert_eq!(move_cursor_pos(&matrix, (1, 0), CursorMove::Down, true), (4, 0));
+        assert_eq!(move_cursor_pos(&matrix, (4, 0), CursorMove::Up, true), (1, 0));
+    }
+
+    #[test]
+    fn test_multi_rect_selection_union() {
+        let matrix: Vec<Vec<char>> = ["abcd", "efgh", "ijkl"].iter().map(|r| r.chars().collect()).collect();
+        let mut selection = MatrixSelection::new();
+        selection.start = Some((0, 0));
+        selection.end = Some((0, 3));
+        // L-shape: the whole first row plus the first column below it
+        selection.add_rect((1, 0));
+        selection.end = Some((2, 0));
+
+        assert!(selection.is_multi());
+        assert!(selection.is_selected(2, 0));
+        assert!(!selection.is_selected(1, 1));
+        let bbox = selection.bbox().unwrap();
+        assert_eq!((bbox.x, bbox.y, bbox.width, bbox.height), (0, 0, 4, 3));
+        assert_eq!(selection.get_selected_text(&matrix), "abcd\ne   \ni   ");
+        assert_eq!(selection.cells(&matrix).len(), 6);
+
+        selection.clear();
+        assert!(selection.bbox().is_none());
+    }
+
+    #[test]
+    fn test_shift_cells_nudges_and_clears_behind() {
+        let mut matrix: Vec<Vec<char>> = ["ab  ", "cd  "].iter().map(|r| r.chars().collect()).collect();
+        let cells = vec![(0, 0), (0, 1), (1, 0), (1, 1)];
+        assert_eq!(shift_cells(&mut matrix, &cells, CursorMove::Right), Some((0, 1)));
+        assert_eq!(block_to_text(&matrix), " ab \n cd ");
+        // Blocked at the top edge; nothing changes
+        let cells = vec![(0, 1), (0, 2)];
+        assert_eq!(shift_cells(&mut matrix, &cells, CursorMove::Up), None);
+        assert_eq!(block_to_text(&matrix), " ab \n cd ");
+    }
+
+    #[test]
+    fn test_align_columns_pads_ragged_rows() {
+        let rows: Vec<Vec<char>> = ["  Item  Qty  Price", "  Large widget  2  10.00", "", "  Nut  100  0.05"]
+            .iter()
+            .map(|r| format!("{:<28}", r).chars().collect())
+            .collect();
+        let aligned: Vec<String> = align_columns(&rows)
+            .unwrap()
+            .iter()
+            .map(|r| r.iter().collect())
+            .collect();
+        assert_eq!(
+            aligned,
+            [
+                "  Item          Qty  Price",
+                "  Large widget  2    10.00",
+                "                          ",
+                "  Nut           100  0.05 ",
+            ]
+            .iter()
+            .map(|r| format!("{:<28}", r))
+            .collect::<Vec<_>>()
+        );
+
+        let narrow: Vec<Vec<char>> = ["a  b", "long  c"].iter().map(|r| r.chars().collect()).collect();
+        assert!(align_columns(&narrow).is_none());
+    }
+
+    #[test]
+    fn test_sort_rows_text_and_numeric() {
+        let block = |rows: &[&str]| -> Vec<Vec<char>> { rows.iter().map(|r| format!("{:<14}", r).chars().collect()).collect() };
+        let text = |rows: &[Vec<char>]| -> Vec<String> { rows.iter().map(|r| r.iter().collect::<String>().trim_end().to_string()).collect() };
+
+        let mut rows = block(&["pear    10", "apple   1,200", "fig     n/a", "kiwi    (3)"]);
+        sort_rows(&mut rows, RowSort::default());
+        assert_eq!(text(&rows), ["apple   1,200", "fig     n/a", "kiwi    (3)", "pear    10"]);
+        assert!(rows.iter().all(|r| r.len() == 14));
+
+        let numeric = RowSort { key_cols: Some((8, 13)), numeric: true, reverse: false };
+        sort_rows(&mut rows, numeric);
+        assert_eq!(text(&rows), ["kiwi    (3)", "pear    10", "apple   1,200", "fig     n/a"]);
+
+        sort_rows(&mut rows, RowSort { reverse: true, ..numeric });
+        assert_eq!(text(&rows), ["apple   1,200", "pear    10", "kiwi    (3)", "fig     n/a"]);
+    }
+
+    #[test]
+    fn test_transpose_selection_rotates_block() {
+        let mut grid = MatrixGrid::new("  0 abc...\n  1 de....\n  2 ......\n  3 ......\n");
+        grid.select_block(&CharBBox { x: 0, y: 0, width: 3, height: 2 });
+        assert!(grid.transpose_selection());
+        assert_eq!(block_to_text(&grid.matrix), "ad ...\nbe ...\nc.....\n......");
+        let bbox = grid.selection.bbox().unwrap();
+        assert_eq!((bbox.width, bbox.height), (2, 3));
+
+        // A 4x1 strip cannot stand up in a 4-row matrix starting at row 1
+        grid.select_block(&CharBBox { x: 0, y: 1, width: 4, height: 1 });
+        assert!(!grid.transpose_selection());
+    }
+
+    #[test]
+    fn test_char_transforms() {
+        let mut matrix: Vec<Vec<char>> = vec!["ÉTÉ “naïve” o'brien – straße".chars().collect(), "TOTAL lO5".chars().collect()];
+        let row = |m: &[Vec<char>], r: usize| m[r].iter().collect::<String>();
+        let first_row: Vec<_> = (0..matrix[0].len()).map(|c| (0, c)).collect();
+
+        transform_cells(&mut matrix, &first_row, CharTransform::Title);
+        assert_eq!(row(&matrix, 0), "Été “Naïve” O'Brien – Straße");
+        transform_cells(&mut matrix, &first_row, CharTransform::Upper);
+        assert_eq!(row(&matrix, 0), "ÉTÉ “NAÏVE” O'BRIEN – STRAßE");
+        transform_cells(&mut matrix, &first_row, CharTransform::StripDiacritics);
+        transform_cells(&mut matrix, &first_row, CharTransform::AsciiQuotes);
+        assert_eq!(row(&matrix, 0), "ETE \"NAIVE\" O'BRIEN - STRAßE");
+
+        // Only the selected cells change
+        transform_cells(&mut matrix, &[(1, 6), (1, 7)], CharTransform::DigitLookalikes);
+        assert_eq!(row(&matrix, 1), "TOTAL 105");
+    }
+
+    #[test]
+    fn test_whitespace_cleanup_with_undo() {
+        let mut grid = MatrixGrid::new("  0   a   b   \n  1       \n  2 c  d      \n");
+        assert!(grid.cleanup_whitespace(WhitespaceOp::SqueezeRuns));
+        assert_eq!(block_to_text(&grid.matrix), format!("{:<10}\n{:<6}\n{:<10}", "  a b", "", "c d"));
+
+        assert!(grid.cleanup_whitespace(WhitespaceOp::RemoveBlankRows));
+        assert!(grid.cleanup_whitespace(WhitespaceOp::TrimTrailing));
+        assert_eq!(block_to_text(&grid.matrix), "  a b\nc d");
+        assert!(!grid.cleanup_whitespace(WhitespaceOp::TrimTrailing));
+
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert_eq!(grid.matrix.len(), 3);
+        assert!(grid.redo());
+        assert_eq!(grid.matrix.len(), 2);
+    }
+
+    #[test]
+    fn test_fill_and_clear_selection() {
+        let mut grid = MatrixGrid::new("  0 abcd\n  1 efgh\n");
+        assert!(!grid.fill_selection('#'));
+        grid.selection.start = Some((0, 1));
+        grid.selection.end = Some((1, 2));
+        assert!(grid.fill_selection('#'));
+        assert_eq!(block_to_text(&grid.matrix), "a##d\ne##h");
+        assert!(grid.fill_selection(' '));
+        assert_eq!(block_to_text(&grid.matrix), "a  d\ne  h");
+        assert!(grid.undo());
+        assert!(grid.undo());
+        assert_eq!(block_to_text(&grid.matrix), "abcd\nefgh");
+    }
+
+    #[test]
+    fn test_keyboard_block_move_previews_then_drops() {
+        let mut grid = MatrixGrid::new("  0 ab..\n  1 cd..\n  2 ....\n");
+        grid.selection.start = Some((0, 0));
+        grid.selection.end = Some((1, 1));
+        grid.step_block_move(CursorMove::Right);
+        grid.step_block_move(CursorMove::Down);
+        grid.step_block_move(CursorMove::Down); // would leave the grid
+        assert_eq!(grid.block_move.as_ref().map(BlockMove::target), Some((1, 1)));
+        assert_eq!(block_to_text(&grid.matrix), "ab..\ncd..\n....");
+
+        grid.finish_block_move();
+        assert_eq!(block_to_text(&grid.matrix), "  ..\n ab.\n.cd.");
+        assert_eq!(grid.selection.bbox().map(|b| (b.y, b.x)), Some((1, 1)));
+        assert!(grid.undo());
+        assert_eq!(block_to_text(&grid.matrix), "ab..\ncd..\n....");
+    }
+
+    #[test]
+    fn test_snap_guides_pull_drops_to_regions() {
+        let region = |x: usize, y: usize, height: usize| TextRegion {
+            bbox: CharBBox { x, y, width: 8, height },
+            confidence: 1.0,
+            text_content: String::new(),
+            region_id: 0,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+        let guides = SnapGuides::from_regions(&[region(4, 2, 2), region(20, 10, 1), region(4, 12, 1)]);
+        assert_eq!(guides.cols, vec![4, 20]);
+        assert_eq!(guides.rows, vec![2, 3, 10, 12]);
+
+        assert_eq!(guides.snap((11, 6)), (10, 4));
+        assert_eq!(guides.snap((3, 18)), (3, 20));
+        // Out of reach on both axes
+        assert_eq!(guides.snap((30, 12)), (30, 12));
+    }
+
+    #[test]
+    fn test_drop_policies() {
+        let base = vec!["ab  ".chars().collect::<Vec<char>>(), "    ".chars().collect()];
+        let block = vec![vec!['x', 'y'], vec!['z', ' ']];
+
+        let mut matrix = base.clone();
+        assert!(drop_block(&mut matrix, &block, (0, 1), DropPolicy::Overwrite));
+        assert_eq!(block_to_text(&matrix), "axy \n z  ");
+
+        let mut matrix = base.clone();
+        assert!(drop_block(&mut matrix, &block, (0, 1), DropPolicy::Merge));
+        assert_eq!(block_to_text(&matrix), "aby \n z  ");
+
+        let mut matrix = base.clone();
+        assert!(!drop_block(&mut matrix, &block, (0, 1), DropPolicy::Reject));
+        assert_eq!(matrix, base);
+        assert!(drop_block(&mut matrix, &block, (0, 2), DropPolicy::Reject));
+        assert_eq!(block_to_text(&matrix), "abxy\n  z ");
+
+        // A refused keyboard move puts the block back
+        let mut grid = MatrixGrid::new("  0 ab.\n");
+        grid.drop_policy = DropPolicy::Reject;
+        grid.selection.start = Some((0, 0));
+        grid.selection.end = Some((0, 0));
+        grid.step_block_move(CursorMove::Right);
+        grid.finish_block_move();
+        assert!(grid.drop_rejected);
+        assert_eq!(block_to_text(&grid.matrix), "ab.");
+        assert!(!grid.undo());
+    }
+
+    #[test]
+    fn test_vim_keys_move_yank_and_paste() {
+        let mut grid = MatrixGrid::new("  0 abcd\n  1 efgh\n  2 ijkl\n");
+        grid.vim = Some(VimState::default());
+        let keys = |grid: &mut MatrixGrid, keys: &str| keys.chars().for_each(|ch| grid.vim_key(ch));
+
+        keys(&mut grid, "2l");
+        assert_eq!(grid.cursor_pos, Some((0, 2)));
+        keys(&mut grid, "j$");
+        assert_eq!(grid.cursor_pos, Some((1, 3)));
+
+        // Visual block from (0,1) to (1,2), yanked and pasted on the last row
+        keys(&mut grid, "0klvljy");
+        assert_eq!(grid.clipboard, vec![vec!['b', 'c'], vec!['f', 'g']]);
+        assert_eq!(grid.cursor_pos, Some((0, 1)));
+        assert_eq!(grid.vim.as_ref().map(|v| v.mode), Some(VimMode::Normal));
+        keys(&mut grid, "Gp");
+        assert_eq!(block_to_text(&grid.matrix), "abcd\nefgh\nibcl");
+
+        keys(&mut grid, "u2x");
+        assert_eq!(block_to_text(&grid.matrix), "abcd\nefgh\ni  l");
+        keys(&mut grid, "gg");
+        keys(&mut grid, "kk2dd");
+        assert_eq!(block_to_text(&grid.matrix), "    \n    \ni  l");
+        keys(&mut grid, "i");
+        assert_eq!(grid.vim.as_ref().map(|v| v.mode), Some(VimMode::Insert));
+    }
+
+    #[test]
+    fn test_shared_history_hands_undo_to_the_app() {
+        let mut grid = MatrixGrid::new("  0 abcd\n  1 efgh\n");
+        grid.shared_history = true;
+        grid.vim = Some(VimState::default());
+        grid.selection.start = Some((0, 0));
+        grid.selection.end = Some((0, 1));
+        assert!(grid.fill_selection('#'));
+        assert_eq!(grid.take_edit().map(|(_, new_step)| new_step), Some(true));
+        assert_eq!(grid.take_edit(), None);
+        assert!(grid.fill_selection('*'));
+        assert_eq!(grid.take_edit().map(|(cells, _)| block_to_text(&cells)), Some("**cd\nefgh".to_string()));
+
+        // A drop continues the step its drag started
+        grid.checkpoint();
+        grid.matrix[0][0] = ' ';
+        grid.modified = true;
+        assert_eq!(grid.take_edit().map(|(_, new_step)| new_step), Some(true));
+        assert!(grid.land_block(&[vec!['*']], (1, 0)));
+        grid.modified = true;
+        assert_eq!(grid.take_edit().map(|(_, new_step)| new_step), Some(false));
+
+        grid.vim_key('u');
+        assert_eq!(grid.history_requests, [HistoryStep::Undo]);
+        assert_eq!(block_to_text(&grid.matrix), " *cd\n*fgh");
+
+        grid.set_cursor(1, 3);
+        grid.set_matrix(text_to_matrix("abcd\n"));
+        assert!(!grid.modified);
+        assert_eq!(grid.cursor_pos, None);
+    }
+
+    #[test]
+    fn test_edit_hunks_and_revert() {
+        let original: Vec<Vec<char>> = ["aa", "bb", "cc", "dd"].iter().map(|r| r.chars().collect()).collect();
+        let mut edited = original.clone();
+        edited[1][0] = 'X';
+        edited[2][1] = 'Y';
+        edited.truncate(3);
+        assert_eq!(edit_hunks(&original, &edited), vec![1..4]);
+
+        let mut edited = original.clone();
+        edited[0][0] = 'X';
+        edited[2][0] = 'Y';
+        assert_eq!(edit_hunks(&original, &edited), vec![0..1, 2..3]);
+        revert_hunk(&mut edited, &original, 2..3);
+        assert_eq!(edit_hunks(&original, &edited), vec![0..1]);
+
+        // A trailing hunk restores removed rows
+        let mut edited = original[..2].to_vec();
+        revert_hunk(&mut edited, &original, 2..4);
+        assert_eq!(edited, original);
+    }
+
+    #[test]
+    fn test_reflow_text_wraps_and_joins_hyphens() {
+        let rows = text_to_matrix("The quick\nbrown fox jum-\nped over\n\nthe dog");
+        let wrapped: Vec<String> = reflow_text(&rows, 10).iter().map(|r| r.iter().collect()).collect();
+        assert_eq!(wrapped, vec!["The quick", "brown fox", "jumped", "over", "", "the dog"]);
+
+        let long: Vec<String> = reflow_text(&text_to_matrix("abcdefgh ij"), 3).iter().map(|r| r.iter().collect()).collect();
+        assert_eq!(long, vec!["abc", "def", "gh", "ij"]);
+
+        let mut grid = MatrixGrid::new("  0 aa bb   \n  1 cc      \n  2 dd      \n");
+        let bbox = CharBBox { x: 0, y: 0, width: 8, height: 3 };
+        assert!(grid.reflow_block(&bbox));
+        assert_eq!(block_to_text(&grid.matrix), "aa bb cc\ndd      \n        ");
+        assert!(grid.undo());
+    }
+
+    #[test]
+    fn test_glyph_cache_starts_over_when_fonts_are_rebuilt() {
+        let ctx = egui::Context::default();
+        let mut grid = MatrixGrid::new("ab\ncd\n");
+        let frame = |grid: &mut MatrixGrid| {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| {
+                egui::CentralPanel::default().show(ctx, |ui| grid.show(ui));
+            });
+        };
+
+        frame(&mut grid);
+        let first = grid.glyphs.borrow().galleys.get(&('a', theme().fg)).cloned();
+        assert!(first.is_some());
+        frame(&mut grid);
+        let again = grid.glyphs.borrow().galleys.get(&('a', theme().fg)).cloned();
+        assert!(Arc::ptr_eq(first.as_ref().unwrap(), again.as_ref().unwrap()));
+
+        // A new scale rebuilds egui's font atlas, so the galleys are laid out anew
+        ctx.set_pixels_per_point(2.0);
+        frame(&mut grid);
+        frame(&mut grid);
+        assert_eq!(grid.glyphs.borrow().pixels_per_point, 2.0);
+        let rescaled = grid.glyphs.borrow().galleys.get(&('a', theme().fg)).cloned();
+        assert!(!Arc::ptr_eq(first.as_ref().unwrap(), rescaled.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_visible_cells_follow_clip_rect() {
+        let cell = Vec2::new(6.0, 10.0);
+        let grid = Rect::from_min_size(egui::pos2(100.0, 50.0), Vec2::new(600.0, 2000.0));
+
+        // Scrolled so the clip starts mid-cell 30 rows down
+        let clip = Rect::from_min_max(egui::pos2(0.0, 355.0), egui::pos2(400.0, 455.0));
+        assert_eq!(visible_cells(grid, clip, cell), (30..41, 0..50));
+
+        // Clip entirely above the grid
+        let clip = Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(50.0, 40.0));
+        assert_eq!(visible_cells(grid, clip, cell), (0..0, 0..0));
+    }
+
+    #[test]
+    fn test_large_selection_copies_in_full() {
+        let matrix = vec![vec!['x'; 500]; 300];
+        let mut selection = MatrixSelection::new();
+        selection.start = Some((0, 0));
+        selection.end = Some((299, 499));
+
+        let text = selection.get_selected_text(&matrix);
+        assert_eq!(text.len(), 300 * 500 + 299);
+        assert_eq!(text, block_to_text(&selection.selected_block(&matrix)));
+
+        let path = spill_selection(&selection, &matrix).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_box_lines_join_at_crossings() {
+        let mut matrix = vec![vec![' '; 5]; 5];
+        assert!(draw_box_line(&mut matrix, (2, 0), (2, 4)));
+        assert!(draw_box_line(&mut matrix, (0, 2), (4, 2)));
+        assert_eq!(block_to_text(&matrix), "  ╷  \n  │  \n╶─┼─╴\n  │  \n  ╵  ");
+
+        // Right then down turns a corner, a line ending on a line end turns
+        // another, and a tee forms where a line meets the middle of one
+        let mut matrix = vec![vec![' '; 4]; 3];
+        draw_box_line(&mut matrix, (0, 0), (2, 3));
+        assert_eq!(block_to_text(&matrix), "╶──┐\n   │\n   ╵");
+        draw_box_line(&mut matrix, (2, 0), (2, 3));
+        assert_eq!(block_to_text(&matrix), "╶──┐\n   │\n╶──┘");
+        draw_box_line(&mut matrix, (0, 1), (2, 1));
+        assert_eq!(block_to_text(&matrix), "╶┬─┐\n │ │\n╶┴─┘");
+        // Closing the box at the start corner
+        draw_box_line(&mut matrix, (0, 0), (2, 0));
+        assert_eq!(block_to_text(&matrix), "┌┬─┐\n││ │\n└┴─┘");
+        assert!(!draw_box_line(&mut matrix, (1, 1), (1, 1)));
+    }
+}
@@ -0,0 +1,86 @@
+//! # chonker: Python bindings for chonker-core
+//!
+//! ```python
+//! import chonker
+//!
+//! cells, regions = chonker.extract("report.pdf", 0)
+//! cells.shape                  # (rows, cols), dtype '<U1'
+//! "".join(cells[3]).rstrip()   # one matrix row as text
+//! regions[0]["text"]
+//! ```
+//!
+//! Build into the active virtualenv with `maturin develop` from this directory.
+
+use chonker_core::{extract_document, ChonkerError, TextRegion};
+use numpy::{PyArray2, PyFixedUnicode};
+use pyo3::exceptions::{PyIndexError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::PathBuf;
+
+fn to_py_err(error: ChonkerError) -> PyErr {
+    match error {
+        ChonkerError::PageOutOfRange { .. } => PyIndexError::new_err(error.to_string()),
+        _ => PyRuntimeError::new_err(error.to_string()),
+    }
+}
+
+fn region_dict<'py>(py: Python<'py>, region: &TextRegion) -> PyResult<&'py PyDict> {
+    let dict = PyDict::new(py);
+    dict.set_item("id", region.region_id)?;
+    dict.set_item("x", region.bbox.x)?;
+    dict.set_item("y", region.bbox.y)?;
+    dict.set_item("width", region.bbox.width)?;
+    dict.set_item("height", region.bbox.height)?;
+    dict.set_item("text", &region.text_content)?;
+    dict.set_item("confidence", region.confidence)?;
+    dict.set_item("italic", region.is_italic)?;
+    dict.set_item("label", region.label.as_deref())?;
+    dict.set_item("tag", region.tag.map(|tag| tag.as_str()))?;
+    Ok(dict)
+}
+
+/// extract(path, page) -> (cells, regions)
+///
+/// Extracts the 0-based `page` of the PDF at `path`, with the document's saved
+/// character overrides and region labels applied. `cells` is a 2-D numpy array
+/// holding one character per matrix cell; `regions` is a list of dicts with the
+/// cell bounding box, text, confidence, label and tag of each text region.
+#[pyfunction]
+fn extract<'py>(
+    py: Python<'py>,
+    path: PathBuf,
+    page: usize,
+) -> PyResult<(&'py PyArray2<PyFixedUnicode<1>>, Vec<&'py PyDict>)> {
+    let spec = (page + 1).to_string();
+    // The GIL is released so other Python threads keep running during extraction
+    let result = py
+        .allow_threads(|| {
+            let runtime = tokio::runtime::Runtime::new()?;
+            Ok::<_, std::io::Error>(runtime.block_on(extract_document(path, &spec)))
+        })
+        .map_err(|e| PyRuntimeError::new_err(format!("Could not start runtime: {}", e)))?;
+    let char_matrix = match result.map_err(to_py_err)?.into_iter().next() {
+        Some((_, page_result)) => page_result.map_err(to_py_err)?,
+        None => return Err(PyIndexError::new_err(format!("page {} is out of range", page))),
+    };
+
+    let rows: Vec<Vec<PyFixedUnicode<1>>> = char_matrix
+        .rows()
+        .iter()
+        .map(|row| row.iter().map(|&ch| PyFixedUnicode([ch as u32])).collect())
+        .collect();
+    let cells = PyArray2::from_vec2(py, &rows).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let regions = char_matrix
+        .text_regions
+        .iter()
+        .map(|region| region_dict(py, region))
+        .collect::<PyResult<_>>()?;
+    Ok((cells, regions))
+}
+
+#[pymodule]
+fn chonker(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(extract, m)?)?;
+    Ok(())
+}
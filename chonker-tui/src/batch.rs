@@ -0,0 +1,171 @@
+//! `:batch` exports: a page range extracted and written one file per page on
+//! the runtime, with a gauge in the status area while it runs, so long jobs
+//! can be watched over SSH.
+
+use chonker_core::*;
+use chonker_grid::theme;
+use ratatui::{prelude::*, widgets::*};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use crate::grid::rgb;
+
+pub struct Batch {
+    progress: Receiver<ProgressEvent>,
+    pub out_dir: PathBuf,
+    pub total: usize,
+    pub done: usize,
+    /// The page being extracted or written.
+    pub current: Option<usize>,
+    pub failures: BTreeMap<usize, String>,
+    pub finished: bool,
+}
+
+impl Batch {
+    /// Exports `pages` of `pdf_path` into `out_dir` as `<stem>_p0001.<ext>`.
+    /// Pages in `edited` are written as edited; the others are extracted with
+    /// `backend` and the document's saved annotations. Regions below
+    /// `min_confidence` are left out. Dropping the batch stops it after the
+    /// page in progress.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        runtime: &tokio::runtime::Runtime,
+        pdf_path: PathBuf,
+        pages: Vec<usize>,
+        mut edited: BTreeMap<usize, CharacterMatrix>,
+        backend: Option<ExtractionBackend>,
+        format: ExportFormat,
+        min_confidence: f32,
+        out_dir: PathBuf,
+    ) -> Self {
+        let (sender, progress) = std::sync::mpsc::channel();
+        let total = pages.len();
+        let dir = out_dir.clone();
+        runtime.spawn(async move {
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                for &page in &pages {
+                    let _ = sender.send(ProgressEvent::Finished { page, error: Some(e.to_string()) });
+                }
+                return;
+            }
+            for (done, &page_index) in pages.iter().enumerate() {
+                if sender.send(ProgressEvent::Started { page: page_index }).is_err() {
+                    return;
+                }
+                let char_matrix = match edited.remove(&page_index) {
+                    Some(char_matrix) => Ok(char_matrix),
+                    None => extract_annotated_page(pdf_path.clone(), page_index, backend).await,
+                };
+                let result = char_matrix.map_err(anyhow::Error::from).and_then(|char_matrix| {
+                    let content = render_export(&char_matrix.without_low_confidence(min_confidence), page_index, format)?;
+                    Ok(std::fs::write(dir.join(export_file_name(&pdf_path, page_index, format)), content)?)
+                });
+                let _ = sender.send(ProgressEvent::Finished { page: page_index, error: result.err().map(|e| e.to_string()) });
+                let _ = sender.send(ProgressEvent::percent(done + 1, pages.len()));
+            }
+        });
+        Self { progress, out_dir, total, done: 0, current: None, failures: BTreeMap::new(), finished: false }
+    }
+
+    /// Applies the progress sent since the last call.
+    pub fn poll(&mut self) {
+        loop {
+            match self.progress.try_recv() {
+                Ok(ProgressEvent::Started { page }) => self.current = Some(page),
+                Ok(ProgressEvent::Finished { page, error }) => {
+                    self.done += 1;
+                    self.current = None;
+                    if let Some(error) = error {
+                        self.failures.insert(page, error);
+                    }
+                }
+                Ok(_) => {}
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    self.current = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The outcome, for the status line once the batch has finished.
+    pub fn summary(&self) -> String {
+        let exported = self.done - self.failures.len();
+        match self.failures.iter().next() {
+            None => format!("✅ Exported {} page(s) to {}", exported, self.out_dir.display()),
+            Some((page, error)) => format!(
+                "❌ Exported {} of {} page(s) to {}; page {}: {}",
+                exported,
+                self.total,
+                self.out_dir.display(),
+                page + 1,
+                error
+            ),
+        }
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let t = theme();
+        let mut label = format!("Exporting {}/{}", self.done, self.total);
+        if let Some(page) = self.current {
+            label.push_str(&format!(" · page {}", page + 1));
+        }
+        if !self.failures.is_empty() {
+            label.push_str(&format!(" · {} failed", self.failures.len()));
+        }
+        let ratio = if self.total == 0 { 1.0 } else { self.done as f64 / self.total as f64 };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(rgb(t.accent)).bg(rgb(t.chrome)))
+            .ratio(ratio.min(1.0))
+            .label(label);
+        frame.render_widget(gauge, area);
+    }
+}
+
+/// `<stem>_p0001.<ext>`, as `chonker export` names pages.
+pub fn export_file_name(pdf_path: &Path, page_index: usize, format: ExportFormat) -> String {
+    let stem = pdf_path.file_stem().map_or_else(|| "document".to_string(), |s| s.to_string_lossy().to_string());
+    format!("{}_p{:04}.{}", stem, page_index + 1, format.extension())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn char_matrix(text: &str) -> CharacterMatrix {
+        let mut char_matrix = CharacterMatrix::new(text.len(), 1);
+        *char_matrix.rows_mut() = vec![text.chars().collect()];
+        char_matrix
+    }
+
+    #[test]
+    fn test_batch_writes_one_file_per_page() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let out_dir = std::env::temp_dir().join(format!("chonker_tui_batch_{}", std::process::id()));
+        let edited = BTreeMap::from([(0, char_matrix("first")), (2, char_matrix("third"))]);
+        let mut batch = Batch::start(
+            &runtime,
+            PathBuf::from("/nonexistent/report.pdf"),
+            vec![0, 2],
+            edited,
+            None,
+            ExportFormat::Txt,
+            0.0,
+            out_dir.clone(),
+        );
+        while !batch.finished {
+            batch.poll();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert_eq!((batch.done, batch.total), (2, 2));
+        assert!(batch.failures.is_empty(), "{:?}", batch.failures);
+        assert!(std::fs::read_to_string(out_dir.join("report_p0001.txt")).unwrap().contains("first"));
+        assert!(std::fs::read_to_string(out_dir.join("report_p0003.txt")).unwrap().contains("third"));
+        assert!(batch.summary().starts_with("✅ Exported 2 page(s)"));
+        let _ = std::fs::remove_dir_all(out_dir);
+    }
+}
@@ -0,0 +1,84 @@
+//! The system clipboard through the terminal (OSC 52), so copies made in a
+//! session over SSH land on the local machine. Terminals without support
+//! ignore the sequence.
+
+use base64::Engine;
+use std::io::Write;
+
+/// Many terminals drop OSC 52 payloads beyond roughly this size, so larger
+/// copies stay in the TUI's own clipboard.
+pub const MAX_PAYLOAD_BYTES: usize = 100_000;
+
+/// screen cuts DCS strings at 768 bytes.
+const SCREEN_CHUNK: usize = 512;
+
+/// A terminal multiplexer between the TUI and the real terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    pub fn detect() -> Self {
+        if std::env::var_os("TMUX").is_some() {
+            Multiplexer::Tmux
+        } else if std::env::var("TERM").is_ok_and(|term| term.starts_with("screen")) {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+}
+
+/// The bytes that set the clipboard to `text`, or `None` when it is too large.
+/// Inside tmux the plain sequence (taken when `set-clipboard` is on) is
+/// followed by a passthrough copy (taken when `allow-passthrough` is on).
+pub fn osc52_sequence(text: &str, multiplexer: Multiplexer) -> Option<String> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    if encoded.len() > MAX_PAYLOAD_BYTES {
+        return None;
+    }
+    let osc = format!("\x1b]52;c;{}\x07", encoded);
+    Some(match multiplexer {
+        Multiplexer::None => osc,
+        Multiplexer::Tmux => format!("{}\x1bPtmux;{}\x1b\\", osc, osc.replace('\x1b', "\x1b\x1b")),
+        Multiplexer::Screen => osc
+            .as_bytes()
+            .chunks(SCREEN_CHUNK)
+            .map(|chunk| format!("\x1bP{}\x1b\\", String::from_utf8_lossy(chunk)))
+            .collect(),
+    })
+}
+
+/// Sends `text` to the system clipboard and returns a status message.
+pub fn copy_to_system(text: &str) -> String {
+    let Some(sequence) = osc52_sequence(text, Multiplexer::detect()) else {
+        return format!("Copied; {} kB is too large for the system clipboard", text.len() / 1000);
+    };
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(sequence.as_bytes());
+    let _ = stdout.flush();
+    format!("📋 Copied {} line(s) to the system clipboard", text.lines().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequences() {
+        assert_eq!(osc52_sequence("hi", Multiplexer::None).as_deref(), Some("\x1b]52;c;aGk=\x07"));
+        assert_eq!(
+            osc52_sequence("hi", Multiplexer::Tmux).as_deref(),
+            Some("\x1b]52;c;aGk=\x07\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\")
+        );
+
+        let screen = osc52_sequence(&"x".repeat(1000), Multiplexer::Screen).unwrap();
+        assert_eq!(screen.matches("\x1bP").count(), 3);
+        assert!(screen.split("\x1b\\").all(|part| part.len() <= SCREEN_CHUNK + 2));
+
+        assert!(osc52_sequence(&"x".repeat(MAX_PAYLOAD_BYTES), Multiplexer::None).is_none());
+    }
+}
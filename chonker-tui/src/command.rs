@@ -0,0 +1,175 @@
+//! The `:` command line, so everything the engine offers is reachable by
+//! name instead of by chord.
+
+use chonker_core::{ExportFormat, ExtractionBackend};
+use chonker_grid::Theme;
+use std::path::PathBuf;
+
+pub const COMMAND_HELP: &str = ":open FILE · :page N · :export FORMAT [FILE] · :yank · :batch PAGES FORMAT [DIR] · :cancel · :set backend=auto|mutool|stext|pdfium · :set split=20..80 · :compare [A B] · :theme [NAME] · :w · :q · :wq";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    /// Opens another PDF; `force` drops unsaved edits.
+    Open { path: PathBuf, force: bool },
+    /// 0-based page index.
+    Page(usize),
+    /// Exports the current page as edited; to a file next to the PDF when
+    /// `path` is `None`.
+    Export { format: ExportFormat, path: Option<PathBuf> },
+    /// Copies the whole page, as edited, to the system clipboard.
+    Yank,
+    /// Exports a page spec such as `1-5,8` one file per page; `all` or an
+    /// empty spec is every page. Into the PDF's folder when `dir` is `None`.
+    Batch { pages: String, format: ExportFormat, dir: Option<PathBuf> },
+    /// Stops a running batch.
+    CancelBatch,
+    /// `None` is the automatic mutool-then-PDFium fallback.
+    SetBackend(Option<ExtractionBackend>),
+    SetSplit(u16),
+    /// Diffs the current page as extracted by two backends.
+    Compare(ExtractionBackend, ExtractionBackend),
+    /// Switches to a theme preset, by its lowercase name; `None` lists them.
+    Theme(Option<&'static str>),
+    Write,
+    Quit { force: bool },
+    WriteQuit,
+    Help,
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let line = line.trim().trim_start_matches(':');
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let args: Vec<&str> = rest.split_whitespace().collect();
+
+    // `:12` jumps to a page, as in vim
+    if let Ok(page) = name.parse::<usize>() {
+        return page_command(page);
+    }
+    match name {
+        "open" | "o" | "e" | "open!" | "o!" | "e!" if !rest.is_empty() => {
+            Ok(Command::Open { path: PathBuf::from(rest), force: name.ends_with('!') })
+        }
+        "open" | "o" | "e" | "open!" | "o!" | "e!" => Err(format!(":{} needs a file", name)),
+        "page" | "p" => match args.as_slice() {
+            [page] => page.parse().map_err(|_| format!("Not a page number: {}", page)).and_then(page_command),
+            _ => Err("Usage: :page N".to_string()),
+        },
+        "export" => match args.as_slice() {
+            [format] | [format, _] => {
+                let format = format.parse::<ExportFormat>().map_err(|e| e.to_string())?;
+                Ok(Command::Export { format, path: args.get(1).map(PathBuf::from) })
+            }
+            _ => Err("Usage: :export txt|json|alto|reading [FILE]".to_string()),
+        },
+        "batch" => match args.as_slice() {
+            [pages, format] | [pages, format, _] => {
+                let format = format.parse::<ExportFormat>().map_err(|e| e.to_string())?;
+                let pages = if *pages == "all" { String::new() } else { pages.to_string() };
+                Ok(Command::Batch { pages, format, dir: args.get(2).map(PathBuf::from) })
+            }
+            _ => Err("Usage: :batch PAGES|all txt|json|alto|reading [DIR]".to_string()),
+        },
+        "cancel" => Ok(Command::CancelBatch),
+        "yank" | "y" => Ok(Command::Yank),
+        "set" => {
+            let (key, value) = rest
+                .split_once('=')
+                .or_else(|| rest.split_once(char::is_whitespace))
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Usage: :set key=value ({})", COMMAND_HELP))?;
+            match key {
+                "backend" => match value {
+                    "auto" => Ok(Command::SetBackend(None)),
+                    value => value.parse().map(|backend| Command::SetBackend(Some(backend))).map_err(|e| e.to_string()),
+                },
+                "split" => value
+                    .trim_end_matches('%')
+                    .parse()
+                    .ok()
+                    .filter(|split| crate::SPLIT_RANGE.contains(split))
+                    .map(Command::SetSplit)
+                    .ok_or_else(|| format!("split takes a percentage from 20 to 80, not {}", value)),
+                _ => Err(format!("Unknown setting: {}", key)),
+            }
+        }
+        "compare" | "diff" => match args.as_slice() {
+            [] => Ok(Command::Compare(ExtractionBackend::Pdfium, ExtractionBackend::MutoolStext)),
+            [a, b] => {
+                let backend = |name: &str| name.parse::<ExtractionBackend>().map_err(|e| e.to_string());
+                Ok(Command::Compare(backend(a)?, backend(b)?))
+            }
+            _ => Err("Usage: :compare [mutool|stext|pdfium mutool|stext|pdfium]".to_string()),
+        },
+        "theme" | "colorscheme" | "colo" => match args.as_slice() {
+            [] => Ok(Command::Theme(None)),
+            [name] => Theme::PRESETS
+                .iter()
+                .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+                .map(|(preset, _)| Command::Theme(Some(*preset)))
+                .ok_or_else(|| format!("Unknown theme: {} ({})", name, theme_names())),
+            _ => Err(format!("Usage: :theme [{}]", theme_names())),
+        },
+        "w" | "write" => Ok(Command::Write),
+        "q" | "quit" => Ok(Command::Quit { force: false }),
+        "q!" | "quit!" => Ok(Command::Quit { force: true }),
+        "wq" | "x" => Ok(Command::WriteQuit),
+        "help" | "h" => Ok(Command::Help),
+        "" => Err(COMMAND_HELP.to_string()),
+        _ => Err(format!("Unknown command: {}", name)),
+    }
+}
+
+pub fn theme_names() -> String {
+    Theme::PRESETS.iter().map(|(name, _)| *name).collect::<Vec<_>>().join("|")
+}
+
+/// Pages are 1-based on the command line.
+fn page_command(page: usize) -> Result<Command, String> {
+    page.checked_sub(1).map(Command::Page).ok_or_else(|| "Pages start at 1".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_command(":page 12"), Ok(Command::Page(11)));
+        assert_eq!(parse_command("3"), Ok(Command::Page(2)));
+        assert!(parse_command("page 0").is_err());
+        assert_eq!(
+            parse_command("open! my file.pdf"),
+            Ok(Command::Open { path: PathBuf::from("my file.pdf"), force: true })
+        );
+        assert_eq!(
+            parse_command("export json out.json"),
+            Ok(Command::Export { format: ExportFormat::Json, path: Some(PathBuf::from("out.json")) })
+        );
+        assert_eq!(parse_command("export alto"), Ok(Command::Export { format: ExportFormat::Alto, path: None }));
+        assert!(parse_command("export docx").is_err());
+        assert_eq!(
+            parse_command("batch 1-5,8 alto out/"),
+            Ok(Command::Batch { pages: "1-5,8".to_string(), format: ExportFormat::Alto, dir: Some(PathBuf::from("out/")) })
+        );
+        assert_eq!(
+            parse_command("batch all txt"),
+            Ok(Command::Batch { pages: String::new(), format: ExportFormat::Txt, dir: None })
+        );
+        assert!(parse_command("batch 1-5").is_err());
+        assert_eq!(parse_command("yank"), Ok(Command::Yank));
+        assert_eq!(parse_command("set backend=mutool"), Ok(Command::SetBackend(Some(ExtractionBackend::Mutool))));
+        assert_eq!(parse_command("set backend=stext"), Ok(Command::SetBackend(Some(ExtractionBackend::MutoolStext))));
+        assert_eq!(parse_command("set backend auto"), Ok(Command::SetBackend(None)));
+        assert_eq!(parse_command("set split=65%"), Ok(Command::SetSplit(65)));
+        assert!(parse_command("set split=95").is_err());
+        assert_eq!(parse_command("compare"), Ok(Command::Compare(ExtractionBackend::Pdfium, ExtractionBackend::MutoolStext)));
+        assert_eq!(parse_command("diff stext mutool"), Ok(Command::Compare(ExtractionBackend::MutoolStext, ExtractionBackend::Mutool)));
+        assert!(parse_command("compare pdfium").is_err());
+        assert_eq!(parse_command("theme High-Contrast"), Ok(Command::Theme(Some("high-contrast"))));
+        assert_eq!(parse_command("colo"), Ok(Command::Theme(None)));
+        assert!(parse_command("theme mauve").is_err());
+        assert_eq!(parse_command("q!"), Ok(Command::Quit { force: true }));
+        assert!(parse_command("frobnicate").is_err());
+    }
+}
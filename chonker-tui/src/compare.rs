@@ -0,0 +1,219 @@
+//! `:compare`: the current page extracted by two backends at once and shown
+//! as a unified diff of the two matrices. Rows that agree are shown once;
+//! rows that differ get a `-` line from the first backend and a `+` line
+//! from the second, with the differing cells coloured.
+
+use chonker_core::*;
+use chonker_grid::theme;
+use ratatui::{prelude::*, widgets::*};
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Instant;
+
+use crate::grid::rgb;
+
+/// One matrix row in the diff.
+#[derive(Debug, PartialEq)]
+pub enum DiffRow {
+    Same(usize),
+    /// The row with the cells that differ marked.
+    Changed(usize, Vec<bool>),
+}
+
+/// The rows of `a` and `b` in order, compared cell by cell.
+pub fn diff_rows(a: &[Vec<char>], b: &[Vec<char>]) -> Vec<DiffRow> {
+    matrix_diff(a, b)
+        .into_iter()
+        .enumerate()
+        .map(|(row, changed)| match changed.contains(&true) {
+            true => DiffRow::Changed(row, changed),
+            false => DiffRow::Same(row),
+        })
+        .collect()
+}
+
+type Extracted = Result<Box<CharacterMatrix>, ChonkerError>;
+
+/// One backend's run on the page.
+enum Side {
+    Running(Receiver<Extracted>),
+    Done(Extracted),
+}
+
+pub struct Comparison {
+    pub page: usize,
+    pub backends: [ExtractionBackend; 2],
+    sides: [Side; 2],
+    started: Instant,
+    /// First diff line in view.
+    scroll: usize,
+    /// First column in view.
+    col: usize,
+}
+
+impl Comparison {
+    /// Extracts `page` with both `backends` on the runtime, with the
+    /// document's saved annotations as a normal extraction.
+    pub fn start(runtime: &tokio::runtime::Runtime, pdf_path: PathBuf, page: usize, backends: [ExtractionBackend; 2]) -> Self {
+        let sides = backends.map(|backend| {
+            let (sender, result) = std::sync::mpsc::channel();
+            let pdf_path = pdf_path.clone();
+            runtime.spawn(async move {
+                let result = extract_annotated_page(pdf_path, page, Some(backend)).await.map(|mut char_matrix| {
+                    char_matrix.ensure_dense();
+                    Box::new(char_matrix)
+                });
+                let _ = sender.send(result);
+            });
+            Side::Running(result)
+        });
+        Self { page, backends, sides, started: Instant::now(), scroll: 0, col: 0 }
+    }
+
+    /// Takes the results that have come in.
+    pub fn poll(&mut self) {
+        for side in &mut self.sides {
+            let Side::Running(result) = side else {
+                continue;
+            };
+            match result.try_recv() {
+                Ok(result) => *side = Side::Done(result),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    *side = Side::Done(Err(ChonkerError::Other("Extraction task failed".to_string())));
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.sides.iter().any(|side| matches!(side, Side::Running(_)))
+    }
+
+    fn matrices(&self) -> Option<[&CharacterMatrix; 2]> {
+        match &self.sides {
+            [Side::Done(Ok(a)), Side::Done(Ok(b))] => Some([a.as_ref(), b.as_ref()]),
+            _ => None,
+        }
+    }
+
+    /// Diff lines, two per changed row, for scrolling.
+    fn line_count(&self) -> usize {
+        self.matrices().map_or(0, |[a, b]| {
+            diff_rows(&a.rows(), &b.rows()).iter().map(|row| if matches!(row, DiffRow::Same(_)) { 1 } else { 2 }).sum()
+        })
+    }
+
+    pub fn scroll(&mut self, rows: isize, cols: isize) {
+        self.scroll = self.scroll.saturating_add_signed(rows).min(self.line_count().saturating_sub(1));
+        self.col = self.col.saturating_add_signed(cols);
+    }
+
+    /// Scrolls to the next or previous changed row, wrapping around.
+    pub fn step_change(&mut self, step: isize) {
+        let Some([a, b]) = self.matrices() else {
+            return;
+        };
+        let mut line = 0;
+        let mut changes = Vec::new();
+        for row in diff_rows(&a.rows(), &b.rows()) {
+            if matches!(row, DiffRow::Changed(..)) {
+                changes.push(line);
+                line += 2;
+            } else {
+                line += 1;
+            }
+        }
+        let next = match step {
+            1.. => changes.iter().find(|&&line| line > self.scroll).or(changes.first()),
+            _ => changes.iter().rev().find(|&&line| line < self.scroll).or(changes.last()),
+        };
+        if let Some(&line) = next {
+            self.scroll = line;
+        }
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, block: Block) {
+        let t = theme();
+        let base = Style::default().fg(rgb(t.fg)).bg(rgb(t.bg));
+        let [a_label, b_label] = self.backends.map(ExtractionBackend::label);
+        let block = block.title(format!(" - {} · + {} ", a_label, b_label));
+        let Some([a, b]) = self.matrices() else {
+            let status = |side: &Side, label: &str| match side {
+                Side::Running(_) => format!("Extracting with {}… {}s", label, self.started.elapsed().as_secs()),
+                Side::Done(Ok(_)) => format!("{} done", label),
+                Side::Done(Err(e)) => format!("❌ {}: {}", label, e),
+            };
+            let text = format!("{}\n{}", status(&self.sides[0], a_label), status(&self.sides[1], b_label));
+            frame.render_widget(Paragraph::new(text).style(base).block(block), area);
+            return;
+        };
+
+        let rows = diff_rows(&a.rows(), &b.rows());
+        let changed: usize = rows
+            .iter()
+            .map(|row| match row {
+                DiffRow::Changed(_, cells) => cells.iter().filter(|&&differs| differs).count(),
+                DiffRow::Same(_) => 0,
+            })
+            .sum();
+        let changed_rows = rows.iter().filter(|row| matches!(row, DiffRow::Changed(..))).count();
+        let summary = format!(" {} cell(s) differ in {} row(s) ", changed, changed_rows);
+        let block = block.title(Line::from(summary).alignment(Alignment::Right));
+
+        let inner = block.inner(area);
+        let width = (inner.width as usize).saturating_sub(7);
+        let line = |sign: char, row: usize, cells: &[char], changed: Option<(&[bool], Color)>| {
+            let gutter = Style::default().fg(rgb(t.dim));
+            let mut spans = vec![Span::styled(format!("{} {:>4} ", sign, row + 1), gutter)];
+            spans.extend(cells.iter().enumerate().skip(self.col).take(width).map(|(col, &ch)| {
+                match changed.filter(|(mask, _)| mask.get(col).copied().unwrap_or(false)) {
+                    Some((_, color)) => Span::styled(ch.to_string(), Style::default().fg(rgb(t.bg)).bg(color)),
+                    None => Span::raw(ch.to_string()),
+                }
+            }));
+            Line::from(spans)
+        };
+        let cells = |matrix: &CharacterMatrix, row: usize| matrix.rows().get(row).cloned().unwrap_or_default();
+        let lines: Vec<Line> = rows
+            .iter()
+            .flat_map(|row| match row {
+                DiffRow::Same(row) => vec![line(' ', *row, &cells(a, *row), None)],
+                DiffRow::Changed(row, mask) => {
+                    // Cells past the shorter row's end still show as changed blanks
+                    let pad = |mut cells: Vec<char>| {
+                        cells.resize(cells.len().max(mask.len()), ' ');
+                        cells
+                    };
+                    vec![
+                        line('-', *row, &pad(cells(a, *row)), Some((mask, rgb(t.error)))),
+                        line('+', *row, &pad(cells(b, *row)), Some((mask, rgb(t.success)))),
+                    ]
+                }
+            })
+            .skip(self.scroll)
+            .take(inner.height as usize)
+            .collect();
+        frame.render_widget(Paragraph::new(lines).style(base).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_rows_marks_changed_cells() {
+        let a = text_to_matrix("Total  12\nsame\nab\n");
+        let b = text_to_matrix("Tota1  12\nsame\nabc\n");
+        assert_eq!(
+            diff_rows(&a, &b),
+            [
+                DiffRow::Changed(0, vec![false, false, false, false, true, false, false, false, false]),
+                DiffRow::Same(1),
+                DiffRow::Changed(2, vec![false, false, true]),
+            ]
+        );
+        assert_eq!(diff_rows(&a, &a), [DiffRow::Same(0), DiffRow::Same(1), DiffRow::Same(2)]);
+    }
+}
@@ -0,0 +1,659 @@
+//! Terminal counterpart of chonker-grid's `MatrixGrid`: cursor, block
+//! selection, cut/copy/paste, typing and block moves, built on the same
+//! selection and block helpers so both editors behave alike.
+//!
+//! Terminals report no key releases, so the GUI's hold-Ctrl block move
+//! becomes a mode here: Ctrl+G lifts the selection, arrows move it, Enter
+//! drops it and Esc puts it back.
+
+use chonker_core::{matrix_to_text, TextRegion};
+use chonker_grid::{
+    find_in_matrix, move_cursor_pos, paste_into, region_color, shift_cells, text_to_block, theme, Color32, CursorMove,
+    MatrixSelection,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::prelude::*;
+
+/// Rows and columns one wheel notch scrolls.
+const V_SCROLL_STEP: isize = 3;
+const H_SCROLL_STEP: isize = 8;
+
+/// A lifted block, previewed at `target()` until it is dropped. `grab` is the
+/// cell a mouse drag picked it up by.
+struct BlockMove {
+    origin: (usize, usize),
+    offset: (isize, isize),
+    block: Vec<Vec<char>>,
+    grab: Option<(usize, usize)>,
+}
+
+impl BlockMove {
+    fn target(&self) -> (usize, usize) {
+        (
+            (self.origin.0 as isize + self.offset.0) as usize,
+            (self.origin.1 as isize + self.offset.1) as usize,
+        )
+    }
+
+    fn covers(&self, row: usize, col: usize) -> Option<char> {
+        let (top, left) = self.target();
+        let ch = *self.block.get(row.checked_sub(top)?)?.get(col.checked_sub(left)?)?;
+        Some(ch)
+    }
+}
+
+/// Theme colors as terminal colors.
+pub fn rgb(color: Color32) -> Color {
+    Color::Rgb(color.r(), color.g(), color.b())
+}
+
+/// `color` laid over `background` at `amount` opacity; terminals have no
+/// alpha, so overlays are mixed in before drawing.
+fn tint(color: Color32, background: Color32, amount: f32) -> Color {
+    let mix = |a: u8, b: u8| (a as f32 * amount + b as f32 * (1.0 - amount)).round() as u8;
+    Color::Rgb(mix(color.r(), background.r()), mix(color.g(), background.g()), mix(color.b(), background.b()))
+}
+
+pub struct TuiGrid {
+    pub matrix: Vec<Vec<char>>,
+    pub selection: MatrixSelection,
+    pub cursor: (usize, usize),
+    pub clipboard: Vec<Vec<char>>,
+    /// Set by edits until the app hands the cells to the document, which
+    /// keeps the undo history.
+    pub modified: bool,
+    /// Top row and left column in view.
+    pub scroll: (usize, usize),
+    /// Rows and columns that fit in the view, as last drawn.
+    view: (usize, usize),
+    /// Where the view last followed the cursor to; the wheel scrolls freely
+    /// until the cursor moves again.
+    followed: Option<(usize, usize)>,
+    pub search_query: String,
+    /// Hits of `search_query` as `(row, col, len)`, in reading order.
+    pub search_matches: Vec<(usize, usize, usize)>,
+    pub current_match: Option<usize>,
+    block_move: Option<BlockMove>,
+}
+
+impl TuiGrid {
+    pub fn new(matrix: Vec<Vec<char>>) -> Self {
+        Self {
+            matrix,
+            selection: MatrixSelection::new(),
+            cursor: (0, 0),
+            clipboard: Vec::new(),
+            modified: false,
+            scroll: (0, 0),
+            view: (0, 0),
+            followed: None,
+            search_query: String::new(),
+            search_matches: Vec::new(),
+            current_match: None,
+            block_move: None,
+        }
+    }
+
+    fn width(&self) -> usize {
+        self.matrix.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Shows `matrix` in place of the cells, as after an undo, keeping the
+    /// cursor inside them. A lifted block is put back.
+    pub fn set_matrix(&mut self, matrix: Vec<Vec<char>>) {
+        self.matrix = matrix;
+        self.block_move = None;
+        self.modified = false;
+        let row = self.cursor.0.min(self.matrix.len().saturating_sub(1));
+        let col = self.cursor.1.min(self.matrix.get(row).map_or(0, Vec::len).saturating_sub(1));
+        self.cursor = (row, col);
+        if !self.search_query.is_empty() {
+            self.search_matches = find_in_matrix(&self.matrix, &self.search_query);
+            self.current_match = None;
+        }
+    }
+
+    /// Searches for `query` and puts the cursor on the first hit at or after
+    /// `from`, wrapping around, or back at `from` when there is none, so the
+    /// view follows the query as it is typed.
+    pub fn search_from(&mut self, query: &str, from: (usize, usize)) {
+        self.search_query = query.to_string();
+        self.search_matches = find_in_matrix(&self.matrix, query);
+        self.current_match = None;
+        if self.search_matches.is_empty() {
+            self.cursor = from;
+        } else {
+            let first = self.search_matches.iter().position(|&(row, col, _)| (row, col) >= from).unwrap_or(0);
+            self.go_to_match(first);
+        }
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search_query.clear();
+        self.search_matches.clear();
+        self.current_match = None;
+    }
+
+    /// Moves to the next (`step > 0`) or previous hit, wrapping around.
+    pub fn step_match(&mut self, step: i32) {
+        let count = self.search_matches.len();
+        if count == 0 {
+            return;
+        }
+        let next = match (self.current_match, step > 0) {
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.go_to_match(next);
+    }
+
+    fn go_to_match(&mut self, index: usize) {
+        let (row, col, _) = self.search_matches[index];
+        self.current_match = Some(index);
+        self.cursor = (row, col);
+        self.selection.clear();
+    }
+
+    /// Index of the hit covering `(row, col)`.
+    fn match_at(&self, row: usize, col: usize) -> Option<usize> {
+        let start = self.search_matches.partition_point(|&(r, _, _)| r < row);
+        self.search_matches[start..]
+            .iter()
+            .take_while(|&&(r, _, _)| r == row)
+            .position(|&(_, c, len)| (c..c + len).contains(&col))
+            .map(|i| start + i)
+    }
+
+    pub fn is_moving_block(&self) -> bool {
+        self.block_move.is_some()
+    }
+
+    /// The block to copy: the selection, or the cell under the cursor.
+    fn selected_block(&self) -> Vec<Vec<char>> {
+        if self.selection.bbox().is_some() {
+            self.selection.selected_block(&self.matrix)
+        } else {
+            let (row, col) = self.cursor;
+            self.matrix.get(row).and_then(|r| r.get(col)).map(|&ch| vec![vec![ch]]).unwrap_or_default()
+        }
+    }
+
+    /// Copies the selection and returns it as text for the system clipboard.
+    pub fn copy(&mut self) -> Option<String> {
+        self.clipboard = self.selected_block();
+        (!self.clipboard.is_empty()).then(|| matrix_to_text(&self.clipboard))
+    }
+
+    pub fn cut(&mut self) -> Option<String> {
+        let text = self.copy();
+        self.fill_selection(' ');
+        text
+    }
+
+    /// Pastes `text`, or the internal clipboard when `None`, at the cursor.
+    pub fn paste(&mut self, text: Option<&str>) {
+        let block = text.map(text_to_block).unwrap_or_else(|| self.clipboard.clone());
+        if block.is_empty() {
+            return;
+        }
+        paste_into(&mut self.matrix, &block, self.cursor);
+        self.selection.clear();
+        self.modified = true;
+    }
+
+    /// Overwrites the selected cells, or the cell under the cursor, with `ch`.
+    pub fn fill_selection(&mut self, ch: char) {
+        let cells = if self.selection.bbox().is_some() {
+            self.selection.cells(&self.matrix)
+        } else {
+            vec![self.cursor]
+        };
+        for (row, col) in cells {
+            if let Some(cell) = self.matrix.get_mut(row).and_then(|r| r.get_mut(col)) {
+                *cell = ch;
+            }
+        }
+        self.modified = true;
+    }
+
+    /// Types `ch` at the cursor and moves right.
+    fn type_char(&mut self, ch: char) {
+        let (row, col) = self.cursor;
+        let Some(cell) = self.matrix.get_mut(row).and_then(|r| r.get_mut(col)) else {
+            return;
+        };
+        if *cell != ch {
+            *cell = ch;
+            self.modified = true;
+        }
+        self.selection.clear();
+        self.cursor = move_cursor_pos(&self.matrix, self.cursor, CursorMove::Right, false);
+    }
+
+    fn move_cursor(&mut self, step: CursorMove, jump: bool, extend: bool) {
+        let target = move_cursor_pos(&self.matrix, self.cursor, step, jump);
+        if extend {
+            if self.selection.start.is_none() || self.selection.end != Some(self.cursor) {
+                self.selection.clear();
+                self.selection.start = Some(self.cursor);
+            }
+            self.selection.end = Some(target);
+        } else {
+            self.selection.clear();
+        }
+        self.cursor = target;
+    }
+
+    /// Lifts the selection into a block move.
+    fn lift_selection(&mut self, grab: Option<(usize, usize)>) -> bool {
+        let Some(bbox) = self.selection.bbox().filter(|_| !self.selection.is_multi()) else {
+            return false;
+        };
+        self.block_move = Some(BlockMove {
+            origin: (bbox.y, bbox.x),
+            offset: (0, 0),
+            block: self.selection.selected_block(&self.matrix),
+            grab,
+        });
+        true
+    }
+
+    /// Moves a lifted block so its corner lands at `(row, col)`, keeping it
+    /// inside the grid.
+    fn place_block(&mut self, row: isize, col: isize) {
+        let (rows, width) = (self.matrix.len(), self.width());
+        let Some(block_move) = &mut self.block_move else {
+            return;
+        };
+        let block_width = block_move.block.iter().map(Vec::len).max().unwrap_or(0);
+        let row = row.clamp(0, rows.saturating_sub(block_move.block.len()) as isize);
+        let col = col.clamp(0, width.saturating_sub(block_width) as isize);
+        block_move.offset = (row - block_move.origin.0 as isize, col - block_move.origin.1 as isize);
+    }
+
+    fn step_block_move(&mut self, step: CursorMove) {
+        let Some(block_move) = &self.block_move else {
+            return;
+        };
+        let (top, left) = block_move.target();
+        let (top, left) = (top as isize, left as isize);
+        match step {
+            CursorMove::Left => self.place_block(top, left - 1),
+            CursorMove::Right => self.place_block(top, left + 1),
+            CursorMove::Up => self.place_block(top - 1, left),
+            CursorMove::Down => self.place_block(top + 1, left),
+        }
+    }
+
+    /// Writes a lifted block where its preview is, blanking where it came from.
+    fn drop_block(&mut self) {
+        let Some(block_move) = self.block_move.take() else {
+            return;
+        };
+        if block_move.offset == (0, 0) {
+            return;
+        }
+        for (row, col) in self.selection.cells(&self.matrix) {
+            self.matrix[row][col] = ' ';
+        }
+        paste_into(&mut self.matrix, &block_move.block, block_move.target());
+        self.selection.translate(block_move.offset.0, block_move.offset.1);
+        self.cursor = block_move.target();
+        self.modified = true;
+    }
+
+    /// Handles an editing key. Returns `false` for keys the grid doesn't use,
+    /// which the app may bind instead.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let step = match key.code {
+            KeyCode::Left => Some(CursorMove::Left),
+            KeyCode::Right => Some(CursorMove::Right),
+            KeyCode::Up => Some(CursorMove::Up),
+            KeyCode::Down => Some(CursorMove::Down),
+            _ => None,
+        };
+
+        if self.block_move.is_some() {
+            match (key.code, step) {
+                (_, Some(step)) => self.step_block_move(step),
+                (KeyCode::Enter, _) => self.drop_block(),
+                (KeyCode::Esc, _) => self.block_move = None,
+                _ => {}
+            }
+            // Everything else waits until the block is down
+            return true;
+        }
+
+        if let Some(step) = step {
+            if key.modifiers.contains(KeyModifiers::ALT) {
+                // Alt+arrow nudges the selected block
+                let cells = self.selection.cells(&self.matrix);
+                if let Some((rows, cols)) = shift_cells(&mut self.matrix, &cells, step) {
+                    self.selection.translate(rows, cols);
+                    self.cursor = ((self.cursor.0 as isize + rows) as usize, (self.cursor.1 as isize + cols) as usize);
+                    self.modified = true;
+                }
+            } else {
+                self.move_cursor(step, ctrl, key.modifiers.contains(KeyModifiers::SHIFT));
+            }
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('c') if ctrl => {
+                self.copy();
+            }
+            KeyCode::Char('x') if ctrl => {
+                self.cut();
+            }
+            KeyCode::Char('v') if ctrl => self.paste(None),
+            KeyCode::Char('a') if ctrl => {
+                self.selection.clear();
+                self.selection.start = Some((0, 0));
+                self.selection.end = Some((self.matrix.len().saturating_sub(1), self.width().saturating_sub(1)));
+            }
+            KeyCode::Char('g') if ctrl => {
+                self.lift_selection(None);
+            }
+            KeyCode::Char(ch) if !ctrl => self.type_char(ch),
+            KeyCode::Delete => self.fill_selection(' '),
+            KeyCode::Backspace if self.selection.bbox().is_some() => self.fill_selection(' '),
+            KeyCode::Backspace => {
+                self.cursor = move_cursor_pos(&self.matrix, self.cursor, CursorMove::Left, false);
+                self.fill_selection(' ');
+            }
+            KeyCode::PageUp if key.modifiers.contains(KeyModifiers::SHIFT) => self.page_view(-1),
+            KeyCode::PageDown if key.modifiers.contains(KeyModifiers::SHIFT) => self.page_view(1),
+            KeyCode::Home => self.cursor.1 = 0,
+            KeyCode::End => self.cursor.1 = self.matrix.get(self.cursor.0).map_or(0, |row| row.len().saturating_sub(1)),
+            KeyCode::Esc => self.selection.clear(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles a mouse event over the grid; `cell` is the matrix cell under
+    /// the pointer. Clicking places the cursor, dragging selects a block and
+    /// dragging a selection moves it.
+    pub fn handle_mouse(&mut self, event: MouseEvent, cell: Option<(usize, usize)>) {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(cell) = cell else {
+                    return;
+                };
+                if self.selection.is_selected(cell.0, cell.1) && self.lift_selection(Some(cell)) {
+                    return;
+                }
+                if event.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.selection.add_rect(cell);
+                } else {
+                    self.selection.clear();
+                    self.selection.start = Some(cell);
+                    self.selection.end = Some(cell);
+                }
+                self.cursor = cell;
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(cell) = cell else {
+                    return;
+                };
+                match self.block_move.as_ref().and_then(|block_move| Some((block_move.origin, block_move.grab?))) {
+                    Some((origin, grab)) => {
+                        let row = origin.0 as isize + cell.0 as isize - grab.0 as isize;
+                        let col = origin.1 as isize + cell.1 as isize - grab.1 as isize;
+                        self.place_block(row, col);
+                    }
+                    None => {
+                        self.selection.end = Some(cell);
+                        self.cursor = cell;
+                    }
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if self.block_move.as_ref().is_some_and(|block_move| block_move.grab.is_some()) {
+                    self.drop_block();
+                } else if self.selection.start == self.selection.end && !self.selection.is_multi() {
+                    // A plain click leaves just the cursor
+                    self.selection.clear();
+                }
+            }
+            // Shift turns the wheel sideways, for terminals without a
+            // horizontal wheel
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp if event.modifiers.contains(KeyModifiers::SHIFT) => {
+                let step = if event.kind == MouseEventKind::ScrollDown { H_SCROLL_STEP } else { -H_SCROLL_STEP };
+                self.scroll_by(0, step);
+            }
+            MouseEventKind::ScrollDown => self.scroll_by(V_SCROLL_STEP, 0),
+            MouseEventKind::ScrollUp => self.scroll_by(-V_SCROLL_STEP, 0),
+            MouseEventKind::ScrollRight => self.scroll_by(0, H_SCROLL_STEP),
+            MouseEventKind::ScrollLeft => self.scroll_by(0, -H_SCROLL_STEP),
+            _ => {}
+        }
+    }
+
+    /// Moves the view without moving the cursor, keeping some of the matrix
+    /// in sight.
+    fn scroll_by(&mut self, rows: isize, cols: isize) {
+        let max_row = self.matrix.len().saturating_sub(1);
+        let max_col = self.width().saturating_sub(1);
+        self.scroll.0 = self.scroll.0.saturating_add_signed(rows).min(max_row);
+        self.scroll.1 = self.scroll.1.saturating_add_signed(cols).min(max_col);
+    }
+
+    /// Moves the view and the cursor a screen up (`-1`) or down.
+    fn page_view(&mut self, direction: isize) {
+        let rows = self.view.0.max(1) as isize * direction;
+        self.scroll_by(rows, 0);
+        let row = self.cursor.0.saturating_add_signed(rows).min(self.matrix.len().saturating_sub(1));
+        self.cursor.0 = row;
+        self.followed = Some(self.cursor);
+    }
+
+    /// Matrix cell under the terminal position `(column, row)` in `area`.
+    pub fn cell_at(&self, area: Rect, column: u16, row: u16) -> Option<(usize, usize)> {
+        if !area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let cell = (self.scroll.0 + (row - area.y) as usize, self.scroll.1 + (column - area.x) as usize);
+        (cell.0 < self.matrix.len() && cell.1 < self.width()).then_some(cell)
+    }
+
+    /// Scrolls so the cursor, or a lifted block, stays inside a view of
+    /// `rows` by `cols` cells.
+    fn scroll_into_view(&mut self, rows: usize, cols: usize) {
+        let (row, col) = self.block_move.as_ref().map_or(self.cursor, BlockMove::target);
+        if self.followed == Some((row, col)) {
+            return;
+        }
+        self.followed = Some((row, col));
+        if row < self.scroll.0 {
+            self.scroll.0 = row;
+        } else if rows > 0 && row >= self.scroll.0 + rows {
+            self.scroll.0 = row + 1 - rows;
+        }
+        if col < self.scroll.1 {
+            self.scroll.1 = col;
+        } else if cols > 0 && col >= self.scroll.1 + cols {
+            self.scroll.1 = col + 1 - cols;
+        }
+    }
+
+    /// Draws the matrix with `regions` shaded behind their cells, in the
+    /// GUI's overlay colors; pass none to hide them.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, focused: bool, regions: &[TextRegion]) {
+        self.view = (area.height as usize, area.width as usize);
+        self.scroll_into_view(area.height as usize, area.width as usize);
+        if !self.search_query.is_empty() {
+            // Edits may have added or removed hits
+            self.search_matches = find_in_matrix(&self.matrix, &self.search_query);
+            self.current_match = self.current_match.filter(|&i| i < self.search_matches.len());
+        }
+        let t = theme();
+        let base = Style::default().fg(rgb(t.fg)).bg(rgb(t.bg));
+        let selected = Style::default().fg(rgb(t.bg)).bg(rgb(t.selection_bg));
+        let preview = Style::default().fg(rgb(t.bg)).bg(rgb(t.warning));
+        let hit = Style::default().fg(rgb(t.bg)).bg(rgb(t.highlight));
+        let current_hit = Style::default().fg(rgb(t.bg)).bg(rgb(t.accent));
+        let cursor = if focused { base.add_modifier(Modifier::REVERSED) } else { base.add_modifier(Modifier::UNDERLINED) };
+        let region_styles: Vec<Style> = regions
+            .iter()
+            .map(|region| {
+                let style = base.bg(tint(region_color(region), t.bg, 0.3));
+                if region.is_italic {
+                    style.add_modifier(Modifier::ITALIC)
+                } else {
+                    style
+                }
+            })
+            .collect();
+
+        // Only the cells in view are styled and drawn, so huge matrices
+        // cost no more per frame than the terminal is large
+        let buffer = frame.buffer_mut();
+        buffer.set_style(area, base);
+        let (top, left) = self.scroll;
+        for (y, row) in (top..self.matrix.len()).take(area.height as usize).enumerate() {
+            let cells = &self.matrix[row];
+            for (x, col) in (left..).take(area.width as usize).enumerate() {
+                let lifted = self.block_move.as_ref().and_then(|block_move| block_move.covers(row, col));
+                let (ch, style) = match (lifted, cells.get(col)) {
+                    (Some(lifted), _) => (lifted, preview),
+                    (None, None) => continue,
+                    (None, Some(&ch)) if (row, col) == self.cursor => (ch, cursor),
+                    (None, Some(&ch)) if self.selection.is_selected(row, col) => (ch, selected),
+                    (None, Some(&ch)) => match self.match_at(row, col) {
+                        Some(index) => (ch, if Some(index) == self.current_match { current_hit } else { hit }),
+                        None => match regions.iter().position(|region| region.bbox.contains(col, row)) {
+                            Some(index) => (ch, region_styles[index]),
+                            None => (ch, base),
+                        },
+                    },
+                };
+                buffer.get_mut(area.x + x as u16, area.y + y as u16).set_char(ch).set_style(style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chonker_core::text_to_matrix;
+
+    fn press(grid: &mut TuiGrid, code: KeyCode, modifiers: KeyModifiers) {
+        grid.handle_key(KeyEvent::new(code, modifiers));
+    }
+
+    #[test]
+    fn test_keyboard_block_move_and_clipboard() {
+        let mut grid = TuiGrid::new(text_to_matrix("ab...\ncd...\n.....\n"));
+        press(&mut grid, KeyCode::Right, KeyModifiers::SHIFT);
+        press(&mut grid, KeyCode::Down, KeyModifiers::SHIFT);
+        assert_eq!(grid.copy().as_deref(), Some("ab\ncd\n"));
+
+        // Ctrl+G lifts the block, arrows move the preview and Enter drops it
+        press(&mut grid, KeyCode::Char('g'), KeyModifiers::CONTROL);
+        press(&mut grid, KeyCode::Right, KeyModifiers::NONE);
+        press(&mut grid, KeyCode::Right, KeyModifiers::NONE);
+        press(&mut grid, KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(matrix_to_text(&grid.matrix), "ab...\ncd...\n.....\n");
+        press(&mut grid, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(matrix_to_text(&grid.matrix), "  ...\n  ab.\n..cd.\n");
+        assert_eq!(grid.cursor, (1, 2));
+
+        assert!(grid.modified);
+
+        // Undo hands back an earlier matrix; the cursor stays inside it
+        grid.set_matrix(text_to_matrix("ab...\ncd...\n.....\n"));
+        assert!(!grid.modified);
+        grid.set_matrix(text_to_matrix("ab\n"));
+        assert_eq!(grid.cursor, (0, 1));
+        grid.set_matrix(text_to_matrix("ab...\ncd...\n.....\n"));
+
+        // Typing overwrites and advances; pasting lands at the cursor
+        grid.cursor = (2, 0);
+        press(&mut grid, KeyCode::Char('x'), KeyModifiers::NONE);
+        grid.paste(Some("12"));
+        assert_eq!(matrix_to_text(&grid.matrix), "ab...\ncd...\nx12..\n");
+        assert!(grid.modified);
+    }
+
+    #[test]
+    fn test_incremental_search_follows_the_query() {
+        let mut grid = TuiGrid::new(text_to_matrix("Total one\nsub total\ntotals\n"));
+        grid.search_from("to", (1, 0));
+        assert_eq!(grid.search_matches, vec![(0, 0, 2), (1, 4, 2), (2, 0, 2)]);
+        assert_eq!((grid.current_match, grid.cursor), (Some(1), (1, 4)));
+
+        // Narrowing past the last hit wraps to the first
+        grid.search_from("tals", (1, 5));
+        assert_eq!((grid.current_match, grid.cursor), (Some(0), (2, 2)));
+        grid.search_from("xyz", (1, 0));
+        assert_eq!((grid.current_match, grid.cursor), (None, (1, 0)));
+
+        grid.search_from("total", (0, 0));
+        grid.step_match(-1);
+        assert_eq!(grid.cursor, (2, 0));
+        grid.step_match(1);
+        assert_eq!(grid.cursor, (0, 0));
+        assert_eq!(grid.match_at(1, 8), Some(1));
+        assert_eq!(grid.match_at(1, 3), None);
+    }
+
+    #[test]
+    fn test_regions_shade_their_cells_by_confidence() {
+        use chonker_core::CharBBox;
+        let region = |x, confidence| TextRegion {
+            bbox: CharBBox { x, y: 0, width: 2, height: 1 },
+            confidence,
+            text_content: String::new(),
+            region_id: 0,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+        let mut grid = TuiGrid::new(text_to_matrix("abcdef
+"));
+        grid.cursor = (0, 5);
+        let mut terminal = Terminal::new(backend::TestBackend::new(6, 1)).unwrap();
+        let regions = [region(0, 0.9), region(2, 0.3)];
+        terminal.draw(|frame| grid.render(frame, frame.size(), false, &regions)).unwrap();
+
+        let t = theme();
+        let bg = |col| terminal.backend().buffer().get(col, 0).bg;
+        assert_eq!(bg(0), tint(t.highlight, t.bg, 0.3));
+        assert_eq!(bg(1), bg(0));
+        assert_eq!(bg(2), tint(t.dim, t.bg, 0.3));
+        assert_eq!(bg(4), rgb(t.bg));
+    }
+
+    #[test]
+    fn test_large_matrix_draws_only_the_view() {
+        let mut grid = TuiGrid::new(
+            (0..300).map(|row| (0..500).map(|col| char::from(b'a' + ((row + col) % 26) as u8)).collect()).collect(),
+        );
+        let mut terminal = Terminal::new(backend::TestBackend::new(10, 4)).unwrap();
+        let cell = |terminal: &Terminal<backend::TestBackend>, x, y| terminal.backend().buffer().get(x, y).symbol().to_string();
+
+        grid.cursor = (250, 400);
+        terminal.draw(|frame| grid.render(frame, frame.size(), true, &[])).unwrap();
+        assert_eq!(grid.scroll, (247, 391));
+        assert_eq!(cell(&terminal, 9, 3), char::from(b'a' + ((250 + 400) % 26) as u8).to_string());
+
+        // The wheel moves the view away from the cursor and it stays there
+        let wheel = |kind, modifiers| MouseEvent { kind, column: 0, row: 0, modifiers };
+        grid.handle_mouse(wheel(MouseEventKind::ScrollUp, KeyModifiers::NONE), None);
+        grid.handle_mouse(wheel(MouseEventKind::ScrollDown, KeyModifiers::SHIFT), None);
+        terminal.draw(|frame| grid.render(frame, frame.size(), true, &[])).unwrap();
+        assert_eq!(grid.scroll, (244, 399));
+        assert_eq!(cell(&terminal, 0, 0), char::from(b'a' + ((244 + 399) % 26) as u8).to_string());
+
+        // Shift+PgDn pages the view and the cursor together
+        grid.handle_key(KeyEvent::new(KeyCode::PageDown, KeyModifiers::SHIFT));
+        terminal.draw(|frame| grid.render(frame, frame.size(), true, &[])).unwrap();
+        assert_eq!((grid.scroll.0, grid.cursor.0), (248, 254));
+    }
+}
@@ -0,0 +1,90 @@
+//! Page jumps typed in the page pane, as in vim: `gg` and `G` go to the
+//! first and last page, and a count in front picks the page (`12G`) or how
+//! many pages PgUp and PgDn step (`5` PgDn).
+
+/// The count and `g` typed so far; any other key drops them.
+#[derive(Debug, Default, PartialEq)]
+pub struct PendingJump {
+    count: Option<usize>,
+    g: bool,
+}
+
+impl PendingJump {
+    /// Takes a typed character and returns the 0-based page once it completes
+    /// a jump. A `0` with no count before it starts none.
+    pub fn push(&mut self, ch: char, page_count: usize) -> Option<usize> {
+        let last = page_count.saturating_sub(1);
+        let target = |count: Option<usize>, default| count.map_or(default, |count| (count - 1).min(last));
+        match ch {
+            '0' if self.count.is_none() || self.g => {
+                *self = Self::default();
+                None
+            }
+            // A digit after `g` starts a new count
+            '0'..='9' => {
+                let count = if self.g { 0 } else { self.count.unwrap_or(0) };
+                let digit = ch.to_digit(10).unwrap_or(0) as usize;
+                *self = Self { count: Some(count.saturating_mul(10).saturating_add(digit)), g: false };
+                None
+            }
+            'g' if self.g => Some(target(std::mem::take(self).count, 0)),
+            'g' => {
+                self.g = true;
+                None
+            }
+            'G' => Some(target(std::mem::take(self).count, last)),
+            _ => {
+                *self = Self::default();
+                None
+            }
+        }
+    }
+
+    /// The page `step` pages from `current`, times the count, kept inside the
+    /// document.
+    pub fn step(self, current: usize, step: isize, page_count: usize) -> usize {
+        let pages = step * self.count.unwrap_or(1).min(isize::MAX as usize) as isize;
+        current.saturating_add_signed(pages).min(page_count.saturating_sub(1))
+    }
+
+    /// What has been typed, for the status bar.
+    pub fn pending(&self) -> String {
+        let count = self.count.map(|count| count.to_string()).unwrap_or_default();
+        format!("{}{}", count, if self.g { "g" } else { "" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(keys: &str, page_count: usize) -> (Option<usize>, PendingJump) {
+        let mut jump = PendingJump::default();
+        let page = keys.chars().fold(None, |_, ch| jump.push(ch, page_count));
+        (page, jump)
+    }
+
+    #[test]
+    fn test_jumps_to_first_last_and_counted_pages() {
+        assert_eq!(typed("gg", 40).0, Some(0));
+        assert_eq!(typed("G", 40).0, Some(39));
+        assert_eq!(typed("12G", 40).0, Some(11));
+        assert_eq!(typed("12gg", 40).0, Some(11));
+        assert_eq!(typed("99G", 40).0, Some(39));
+        assert_eq!(typed("0G", 40).0, Some(39));
+
+        let (page, jump) = typed("1g2", 40);
+        assert_eq!((page, jump.pending()), (None, "2".to_string()));
+        let (page, jump) = typed("3gx", 40);
+        assert_eq!((page, jump), (None, PendingJump::default()));
+        assert_eq!(typed("12g", 40).1.pending(), "12g");
+    }
+
+    #[test]
+    fn test_counted_steps_stay_in_the_document() {
+        assert_eq!(PendingJump::default().step(4, 1, 40), 5);
+        assert_eq!(typed("10", 40).1.step(4, 1, 40), 14);
+        assert_eq!(typed("10", 40).1.step(4, -1, 40), 0);
+        assert_eq!(typed("100", 40).1.step(4, 1, 40), 39);
+    }
+}
@@ -0,0 +1,211 @@
+//! The log pane: the engine's tracing output, caught by a subscriber layer
+//! instead of going to stderr under the TUI, shown below the page and matrix
+//! with a level floor and a text filter.
+
+use chonker_grid::theme;
+use ratatui::{prelude::*, widgets::*};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::field::{Field, Visit};
+use tracing::{Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::grid::rgb;
+
+/// Oldest entries are dropped past this many, as in the GUI's log panel.
+const LOG_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// Seconds since the capture started.
+    pub seconds: f32,
+    pub level: Level,
+    pub target: String,
+    /// The message with any other fields after it as `name=value`.
+    pub message: String,
+}
+
+impl LogEntry {
+    fn matches(&self, filter: &str) -> bool {
+        filter.is_empty()
+            || self.message.to_lowercase().contains(&filter.to_lowercase())
+            || self.target.to_lowercase().contains(&filter.to_lowercase())
+    }
+}
+
+/// A tracing layer that keeps the latest events for the pane.
+#[derive(Clone)]
+pub struct LogCapture {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+    started: Instant,
+}
+
+impl LogCapture {
+    pub fn new() -> Self {
+        Self { entries: Arc::default(), started: Instant::now() }
+    }
+
+    /// Makes this the global subscriber, catching debug and above.
+    pub fn install(&self) {
+        use tracing_subscriber::prelude::*;
+
+        let _ = tracing_subscriber::registry()
+            .with(tracing_subscriber::filter::LevelFilter::DEBUG)
+            .with(self.clone())
+            .try_init();
+    }
+
+    /// The kept entries at `floor` or more severe that contain `filter`, oldest
+    /// first.
+    pub fn visible(&self, floor: Level, filter: &str) -> Vec<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().filter(|entry| entry.level <= floor && entry.matches(filter)).cloned().collect()
+    }
+
+    /// Entries at warning or error level.
+    pub fn problems(&self) -> usize {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().filter(|entry| entry.level <= Level::WARN).count()
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogCapture {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+        let metadata = event.metadata();
+        let entry = LogEntry {
+            seconds: self.started.elapsed().as_secs_f32(),
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message: (message.text + &message.fields).trim_start().to_string(),
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == LOG_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// The event's message, then its other fields.
+#[derive(Default)]
+struct Message {
+    text: String,
+    fields: String,
+}
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let _ = match field.name() {
+            "message" => write!(self.text, "{:?}", value),
+            name => write!(self.fields, " {}={:?}", name, value),
+        };
+    }
+}
+
+/// What the pane shows and where it is scrolled.
+pub struct LogPane {
+    /// The least severe level shown.
+    pub floor: Level,
+    pub filter: String,
+    /// Lines scrolled back from the newest; 0 follows new entries.
+    back: usize,
+}
+
+impl Default for LogPane {
+    fn default() -> Self {
+        Self { floor: Level::INFO, filter: String::new(), back: 0 }
+    }
+}
+
+impl LogPane {
+    /// Sets the level floor from its initial: e, w, i, d or t.
+    pub fn set_floor(&mut self, initial: char) -> bool {
+        self.floor = match initial {
+            'e' => Level::ERROR,
+            'w' => Level::WARN,
+            'i' => Level::INFO,
+            'd' => Level::DEBUG,
+            't' => Level::TRACE,
+            _ => return false,
+        };
+        self.back = 0;
+        true
+    }
+
+    /// Scrolls towards older entries for a positive `step`.
+    pub fn scroll(&mut self, step: isize) {
+        self.back = self.back.saturating_add_signed(step);
+    }
+
+    /// Jumps back to the newest entries.
+    pub fn follow(&mut self) {
+        self.back = 0;
+    }
+
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, block: Block, capture: &LogCapture) {
+        let t = theme();
+        let entries = capture.visible(self.floor, &self.filter);
+        let height = block.inner(area).height as usize;
+        self.back = self.back.min(entries.len().saturating_sub(height));
+        let end = entries.len() - self.back;
+        let lines: Vec<Line> = entries[end.saturating_sub(height)..end]
+            .iter()
+            .map(|entry| {
+                let color = match entry.level {
+                    Level::ERROR => t.error,
+                    Level::WARN => t.warning,
+                    Level::INFO => t.fg,
+                    _ => t.dim,
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:>7.1}s {:<5} ", entry.seconds, entry.level), Style::default().fg(rgb(color))),
+                    Span::styled(format!("{}: ", entry.target), Style::default().fg(rgb(t.dim))),
+                    Span::raw(entry.message.clone()),
+                ])
+            })
+            .collect();
+        let filter = if self.filter.is_empty() { String::new() } else { format!(" · /{}", self.filter) };
+        let following = if self.back == 0 { "" } else { " · scrolled" };
+        let block = block.title(Line::from(format!(" {} and above{}{} · {} ", self.floor, filter, following, entries.len())).alignment(Alignment::Right));
+        frame.render_widget(Paragraph::new(lines).style(Style::default().fg(rgb(t.fg)).bg(rgb(t.bg))).block(block), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_capture_filters_by_level_and_text() {
+        let capture = LogCapture::new();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(capture.clone()), || {
+            tracing::warn!("Simple extraction failed: {}, trying PDFium", "no mutool");
+            tracing::info!(page = 3, "Loaded page from the disk cache");
+            tracing::debug!("Cache miss");
+            tracing::error!("Rendering failed");
+        });
+
+        let messages = |floor, filter| {
+            capture.visible(floor, filter).into_iter().map(|entry| entry.message).collect::<Vec<_>>()
+        };
+        assert_eq!(messages(Level::WARN, ""), ["Simple extraction failed: no mutool, trying PDFium", "Rendering failed"]);
+        assert_eq!(messages(Level::TRACE, "CACHE"), ["Loaded page from the disk cache page=3", "Cache miss"]);
+        assert_eq!(messages(Level::INFO, "log_pane"), messages(Level::INFO, ""));
+        assert_eq!(capture.problems(), 2);
+
+        let mut pane = LogPane::default();
+        assert!(pane.set_floor('e') && !pane.set_floor('x'));
+        assert_eq!(pane.floor, Level::ERROR);
+        let mut terminal = Terminal::new(backend::TestBackend::new(80, 3)).unwrap();
+        pane.scroll(5);
+        terminal.draw(|frame| pane.render(frame, frame.size(), Block::bordered(), &capture)).unwrap();
+        let row: String = (0..80).map(|x| terminal.backend().buffer().get(x, 1).symbol().to_string()).collect();
+        assert!(row.contains("ERROR") && row.contains("Rendering failed"), "{}", row);
+        assert_eq!(pane.back, 0);
+    }
+}
@@ -0,0 +1,1273 @@
+//! # chonker-tui: terminal front end for chonker-core
+//!
+//! Shows the extracted page on the left and edits its character matrix on
+//! the right, with the desktop app's cursor, block selection, clipboard and
+//! block-move workflow (see `grid.rs`). Pages and their undo history live in
+//! chonker-core's `DocumentModel`, and saving records the matrix history and
+//! edit log the same way the GUI does. With the `images` feature, terminals
+//! that speak kitty, sixel or iTerm2 graphics show the page bitmap instead of
+//! its text.
+
+use anyhow::Result;
+use chonker_core::*;
+use chonker_grid::{set_theme, theme, ThemeConfig};
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent,
+    KeyEventKind, KeyModifiers, MouseButton, MouseEventKind,
+};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use batch::{export_file_name, Batch};
+use command::{parse_command, theme_names, Command, COMMAND_HELP};
+use compare::Comparison;
+use grid::{rgb, TuiGrid};
+use jump::PendingJump;
+use log_pane::{LogCapture, LogPane};
+use table::TablePreview;
+use ratatui::{prelude::*, widgets::*};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+mod batch;
+mod clipboard;
+mod command;
+mod compare;
+mod grid;
+mod jump;
+mod log_pane;
+#[cfg(feature = "images")]
+mod page_image;
+mod table;
+
+const USAGE: &str = "Usage: chonker-tui <file.pdf>
+
+Tab switches between the page and the matrix; Alt+, and Alt+. or dragging
+the divider resize them. In the matrix:
+  arrows, Ctrl+arrows       move the cursor, by word or block with Ctrl
+  Shift+arrows, drag        select a block (Ctrl+drag adds another, Ctrl+A all)
+  Ctrl+C / Ctrl+X / Ctrl+V  copy, cut, paste; copies also reach the system
+                            clipboard over OSC 52, through tmux and SSH
+  Alt+arrows                nudge the selected block
+  Ctrl+G                    lift the selection; arrows move it, Enter drops it
+  Ctrl+Z / Ctrl+Y           undo, redo
+  Shift+PgUp/PgDn, wheel    scroll by a screen, or 3 rows (8 columns with
+                            Shift or a sideways wheel)
+  Ctrl+B                    show or hide the text regions, shaded by confidence
+  Ctrl+F, or / in the page  search as you type; Enter keeps the hits, then
+                            n/N (or F3/Shift+F3) step through them
+Ctrl+T shows the page's tables, cell by cell, in place of the page: left
+and right pick a table, up and down a row, c writes it as CSV next to the
+PDF and Esc goes back to the page.
+Ctrl+L shows the engine's log below the panes; with it focused, e, w, i, d
+and t pick the least severe level shown, / filters it as you type, up and
+down scroll and Esc hides it.
+:compare [A B] extracts the page with two backends (PDFium and mutool stext
+unless named) and shows where their matrices differ, cell by cell: n/N step
+through the changed rows, arrows scroll and Esc goes back.
+PgUp/PgDn change page, Ctrl+S saves the edited pages, Ctrl+Q quits. In the
+page, gg and G go to the first and last page, and a count picks the page
+(12G) or how many pages PgUp/PgDn step (5 then PgDn).
+chonker.toml's [keymap] rebinds quit, save, retry, search, command,
+toggle_regions, toggle_tables, toggle_log, next_page, prev_page,
+switch_focus, narrow_split, widen_split and next_match.
+Ctrl+E, or : in the page, opens the command line:
+  :open FILE, :page N, :export FORMAT [FILE], :yank, :batch PAGES FORMAT [DIR],
+  :cancel, :set backend=auto|mutool|stext|pdfium, :set split=PERCENT,
+  :compare [A B], :theme [teal|solarized|high-contrast|light], :w, :q, :q!,
+  :wq
+The theme comes from chonker.toml's theme, else chonker5_theme.json as the
+desktop app saves it; :theme switches it and saves it there.";
+
+/// Actions `chonker.toml`'s `[keymap]` can rebind, with their default chords.
+const KEY_ACTIONS: &[(&str, &str)] = &[
+    ("quit", "Ctrl+Q"),
+    ("save", "Ctrl+S"),
+    ("retry", "Ctrl+R"),
+    ("search", "Ctrl+F"),
+    ("command", "Ctrl+E"),
+    ("toggle_regions", "Ctrl+B"),
+    ("toggle_tables", "Ctrl+T"),
+    ("toggle_log", "Ctrl+L"),
+    ("next_page", "PageDown"),
+    ("prev_page", "PageUp"),
+    ("switch_focus", "Tab"),
+    ("narrow_split", "Alt+,"),
+    ("widen_split", "Alt+."),
+    ("next_match", "F3"),
+];
+
+/// Braille frames of the extraction spinner, one per `SPINNER_TICK`.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_TICK: Duration = Duration::from_millis(100);
+
+/// Page pane share of the width, in percent, as the GUI's split allows.
+const SPLIT_RANGE: std::ops::RangeInclusive<u16> = 20..=80;
+const SPLIT_STEP: u16 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Page,
+    Matrix,
+    Log,
+}
+
+/// What keys currently do, shown at the left of the status bar and driving
+/// the hint line.
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Normal,
+    Select,
+    Move,
+    Search,
+    Command,
+}
+
+impl Mode {
+    fn label(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Select => "SELECT",
+            Mode::Move => "MOVE",
+            Mode::Search => "SEARCH",
+            Mode::Command => "COMMAND",
+        }
+    }
+}
+
+/// A line being typed in the hint line.
+enum Prompt {
+    /// `/pattern`, searched as it is typed; `origin` is where the cursor was
+    /// when the search began.
+    Search { query: String, origin: (usize, usize) },
+    /// `:command`, run on Enter.
+    Command(String),
+    /// `/filter` in the log pane, applied as it is typed; `origin` is the
+    /// filter it replaces.
+    LogFilter { origin: String },
+}
+
+/// The editor of a page the document holds.
+struct Page {
+    grid: TuiGrid,
+}
+
+/// A page extraction running on the runtime, so the interface keeps
+/// responding to keys while a big page is processed.
+struct Extraction {
+    page: usize,
+    started: Instant,
+    result: Receiver<Result<CharacterMatrix, ChonkerError>>,
+}
+
+struct App {
+    pdf_path: PathBuf,
+    page_count: usize,
+    current_page: usize,
+    /// The extracted pages with their edits and undo history.
+    document: DocumentModel,
+    document_events: Receiver<DocumentEvent>,
+    pages: BTreeMap<usize, Result<Page, ChonkerError>>,
+    /// One page at a time; dropping it discards the result.
+    extraction: Option<Extraction>,
+    /// Forced by `:set backend` or `chonker.toml`; `None` falls back from
+    /// mutool to PDFium.
+    backend: Option<ExtractionBackend>,
+    keymap: Keymap,
+    /// The preset and color overrides the theme was built from.
+    theme_config: ThemeConfig,
+    runtime: tokio::runtime::Runtime,
+    focus: Focus,
+    /// Page pane offset while it has focus; otherwise it follows the matrix.
+    page_scroll: Option<(usize, usize)>,
+    /// A count or `g` typed in the page pane, until the jump it starts.
+    jump: PendingJump,
+    status: String,
+    prompt: Option<Prompt>,
+    /// Set after a search is confirmed, while n and N step through the hits.
+    stepping_matches: bool,
+    /// Page pane width in percent of the window.
+    split_ratio: u16,
+    /// Shades the text regions in the matrix, as the GUI's bounding boxes.
+    show_regions: bool,
+    /// `min_confidence` from chonker.toml: less certain regions are neither
+    /// shaded nor exported.
+    min_confidence: f32,
+    /// The table preview, shown in the page pane while open.
+    tables: Option<TablePreview>,
+    /// The engine's tracing events, caught since start-up.
+    logs: LogCapture,
+    /// The log pane, shown below the others while open.
+    log_pane: Option<LogPane>,
+    /// The `:compare` diff, shown over both panes while open.
+    comparison: Option<Comparison>,
+    /// Set while the pane divider is dragged.
+    resizing_split: bool,
+    /// Both panes, as last drawn, for dragging the divider.
+    body_area: Rect,
+    /// Where the matrix was last drawn, for mapping mouse positions to cells.
+    grid_area: Rect,
+    /// The running `:batch` export, until its summary is shown.
+    batch: Option<Batch>,
+    /// Set by a Ctrl+Q that was refused because of unsaved edits.
+    confirm_quit: bool,
+    quit: bool,
+    /// Page bitmaps, when the terminal can show them.
+    #[cfg(feature = "images")]
+    page_images: Option<page_image::PageImages>,
+}
+
+impl App {
+    fn new(pdf_path: PathBuf, config: &ChonkerConfig, logs: LogCapture) -> Result<Self> {
+        let page_count = pdf_page_count(&pdf_path)?;
+        let mut document = DocumentModel::new(pdf_path.clone());
+        let document_events = document.subscribe();
+        let theme_config = ThemeConfig::load_shared(config);
+        set_theme(theme_config.resolve());
+        Ok(Self {
+            pdf_path,
+            page_count,
+            current_page: 0,
+            document,
+            document_events,
+            pages: BTreeMap::new(),
+            extraction: None,
+            backend: config.backend,
+            keymap: Keymap::new(KEY_ACTIONS, &config.keymap),
+            theme_config,
+            runtime: tokio::runtime::Runtime::new()?,
+            focus: Focus::Matrix,
+            page_scroll: None,
+            jump: PendingJump::default(),
+            status: String::new(),
+            prompt: None,
+            stepping_matches: false,
+            split_ratio: 50,
+            show_regions: true,
+            min_confidence: config.min_confidence.unwrap_or(0.0),
+            tables: None,
+            logs,
+            log_pane: None,
+            comparison: None,
+            resizing_split: false,
+            body_area: Rect::default(),
+            grid_area: Rect::default(),
+            batch: None,
+            confirm_quit: false,
+            quit: false,
+            #[cfg(feature = "images")]
+            page_images: None,
+        })
+    }
+
+    /// Starts extracting the current page unless it is held or already on
+    /// its way.
+    fn start_extraction(&mut self) {
+        if self.extraction.is_some() || self.pages.contains_key(&self.current_page) {
+            return;
+        }
+        let (sender, result) = std::sync::mpsc::channel();
+        let (pdf_path, page, backend) = (self.pdf_path.clone(), self.current_page, self.backend);
+        self.runtime.spawn(async move {
+            let result = extract_annotated_page(pdf_path, page, backend).await.map(|mut char_matrix| {
+                char_matrix.ensure_dense();
+                char_matrix
+            });
+            let _ = sender.send(result);
+        });
+        self.extraction = Some(Extraction { page, started: Instant::now(), result });
+    }
+
+    /// Hands a finished extraction to the document.
+    fn poll_extraction(&mut self) {
+        let Some(extraction) = &self.extraction else {
+            return;
+        };
+        let result = match extraction.result.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => Err(ChonkerError::Other("Extraction task failed".to_string())),
+        };
+        let page_index = extraction.page;
+        self.extraction = None;
+        match result {
+            Ok(char_matrix) => self.document.insert_page(page_index, char_matrix),
+            Err(e) => {
+                self.status = format!("❌ Page {}: {}", page_index + 1, e);
+                self.pages.insert(page_index, Err(e));
+            }
+        }
+    }
+
+    /// Follows the document: loaded pages get an editor, and undo and redo
+    /// redraw the page they changed.
+    fn poll_document(&mut self) {
+        while let Ok(event) = self.document_events.try_recv() {
+            match event {
+                DocumentEvent::Loaded { page } | DocumentEvent::Restored { page } => {
+                    let Some(cells) = self.document.page(page).map(|page| page.matrix.rows().into_owned()) else {
+                        continue;
+                    };
+                    match self.pages.get_mut(&page) {
+                        Some(Ok(page)) => page.grid.set_matrix(cells),
+                        _ => {
+                            self.pages.insert(page, Ok(Page { grid: TuiGrid::new(cells) }));
+                        }
+                    }
+                }
+                DocumentEvent::Unloaded { page } => {
+                    self.pages.remove(&page);
+                }
+                DocumentEvent::Edited { .. } | DocumentEvent::Saved { .. } => {}
+            }
+        }
+    }
+
+    /// Hands the grid's edits to the document as one undo step.
+    fn commit_edits(&mut self) {
+        let page_index = self.current_page;
+        let Some(page) = self.page_mut() else {
+            return;
+        };
+        if std::mem::take(&mut page.grid.modified) {
+            let cells = page.grid.matrix.clone();
+            self.document.edit_cells(page_index, cells);
+        }
+    }
+
+    fn undo(&mut self, redo: bool) {
+        let page = self.current_page;
+        let done = if redo { self.document.redo(page) } else { self.document.undo(page) };
+        if !done {
+            self.status = format!("Nothing to {}", if redo { "redo" } else { "undo" });
+        }
+    }
+
+    /// A spinner frame with the seconds spent, while the current page is
+    /// being extracted.
+    fn extraction_progress(&self) -> Option<String> {
+        let extraction = self.extraction.as_ref().filter(|extraction| extraction.page == self.current_page)?;
+        let elapsed = extraction.started.elapsed();
+        let frame = SPINNER[(elapsed.as_millis() / SPINNER_TICK.as_millis()) as usize % SPINNER.len()];
+        Some(format!("{} Extracting page {}… {}s", frame, extraction.page + 1, elapsed.as_secs()))
+    }
+
+    fn page_mut(&mut self) -> Option<&mut Page> {
+        self.pages.get_mut(&self.current_page).and_then(|page| page.as_mut().ok())
+    }
+
+    fn go_to_page(&mut self, page: usize) {
+        if page < self.page_count && page != self.current_page {
+            // A long jump does not wait for the page it left to be extracted
+            if self.extraction.as_ref().is_some_and(|extraction| extraction.page != page) {
+                self.extraction = None;
+            }
+            self.current_page = page;
+            self.page_scroll = None;
+            self.focus = Focus::Matrix;
+            self.status.clear();
+            if let Some(tables) = &mut self.tables {
+                *tables = TablePreview::default();
+            }
+            if let Some(comparison) = &self.comparison {
+                self.compare(comparison.backends);
+            }
+        }
+    }
+
+    /// Starts diffing the current page as extracted by `backends`.
+    fn compare(&mut self, backends: [ExtractionBackend; 2]) {
+        self.comparison = Some(Comparison::start(&self.runtime, self.pdf_path.clone(), self.current_page, backends));
+    }
+
+    fn handle_comparison_key(&mut self, key: KeyEvent) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => comparison.scroll(-1, 0),
+            KeyCode::Down => comparison.scroll(1, 0),
+            KeyCode::Left => comparison.scroll(0, -1),
+            KeyCode::Right => comparison.scroll(0, 1),
+            KeyCode::Char('n') => comparison.step_change(1),
+            KeyCode::Char('N') => comparison.step_change(-1),
+            KeyCode::Esc => self.comparison = None,
+            _ => {}
+        }
+    }
+
+    /// Opens the table preview with the focus on it, or closes it.
+    fn toggle_tables(&mut self) {
+        if self.tables.take().is_none() {
+            self.tables = Some(TablePreview::default());
+            self.focus = Focus::Page;
+        }
+    }
+
+    /// Opens the log pane with the focus on it, or closes it.
+    fn toggle_log(&mut self) {
+        if self.log_pane.take().is_none() {
+            self.log_pane = Some(LogPane::default());
+            self.focus = Focus::Log;
+        } else if self.focus == Focus::Log {
+            self.focus = Focus::Matrix;
+        }
+    }
+
+    fn handle_log_key(&mut self, key: KeyEvent) {
+        let Some(pane) = &mut self.log_pane else {
+            return;
+        };
+        match key.code {
+            KeyCode::Up => pane.scroll(1),
+            KeyCode::Down => pane.scroll(-1),
+            KeyCode::Home => pane.scroll(isize::MAX),
+            KeyCode::End => pane.follow(),
+            KeyCode::Char('/') => self.prompt = Some(Prompt::LogFilter { origin: pane.filter.clone() }),
+            KeyCode::Char(initial) if pane.set_floor(initial) => {}
+            KeyCode::Esc => self.toggle_log(),
+            _ => {}
+        }
+    }
+
+    fn detected_tables(&self) -> Vec<DetectedTable> {
+        self.document.page(self.current_page).map(|page| detect_tables(&page.matrix)).unwrap_or_default()
+    }
+
+    fn handle_table_key(&mut self, key: KeyEvent) {
+        let tables = self.detected_tables();
+        let Some(preview) = &mut self.tables else {
+            return;
+        };
+        let rows = tables.get(preview.index).map_or(0, |table| table.cells.len().saturating_sub(1));
+        match key.code {
+            KeyCode::Left => preview.step_table(-1, tables.len()),
+            KeyCode::Right => preview.step_table(1, tables.len()),
+            KeyCode::Up => preview.step_row(-1, rows),
+            KeyCode::Down => preview.step_row(1, rows),
+            KeyCode::Char('c') => {
+                self.status = match preview.export_csv(&tables, &self.pdf_path, self.current_page) {
+                    Ok(path) => format!("✅ Exported table {} to {}", preview.index + 1, path.display()),
+                    Err(e) => format!("❌ {}", e),
+                };
+            }
+            KeyCode::Esc => {
+                self.tables = None;
+                self.focus = Focus::Matrix;
+            }
+            _ => {}
+        }
+    }
+
+    /// Records each edited page as a new matrix version, with its cell edits
+    /// in the edit log. Returns `false` when a page could not be saved.
+    fn save(&mut self) -> bool {
+        let modified: Vec<usize> =
+            self.document.pages().filter(|(_, page)| page.is_modified()).map(|(page_index, _)| page_index).collect();
+        let mut saved = Vec::new();
+        for page_index in modified {
+            if let Err(e) = self.document.save(page_index) {
+                self.status = format!("❌ Could not save page {}: {}", page_index + 1, e);
+                return false;
+            }
+            saved.push((page_index + 1).to_string());
+        }
+        self.status = if saved.is_empty() {
+            "No edits to save".to_string()
+        } else {
+            format!("✅ Saved page {}", saved.join(", "))
+        };
+        true
+    }
+
+    fn has_unsaved_edits(&self) -> bool {
+        self.document.has_unsaved_edits()
+    }
+
+    fn resize_split(&mut self, ratio: u16) {
+        self.split_ratio = ratio.clamp(*SPLIT_RANGE.start(), *SPLIT_RANGE.end());
+    }
+
+    /// The divider's column: the page pane's right border or the matrix
+    /// pane's left one.
+    fn on_divider(&self, column: u16, row: u16) -> bool {
+        let body = self.body_area;
+        let divider = self.grid_area.x.saturating_sub(1);
+        body.contains(Position::new(column, row)) && (column == divider || column + 1 == divider)
+    }
+
+    fn open_search(&mut self) {
+        let Some(page) = self.page_mut() else {
+            return;
+        };
+        let origin = page.grid.cursor;
+        page.grid.clear_search();
+        self.prompt = Some(Prompt::Search { query: String::new(), origin });
+        self.focus = Focus::Matrix;
+        self.page_scroll = None;
+    }
+
+    fn open_command(&mut self) {
+        self.prompt = Some(Prompt::Command(String::new()));
+    }
+
+    fn handle_prompt_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match &mut self.prompt {
+            Some(Prompt::Search { .. }) => self.handle_search_key(key),
+            Some(Prompt::LogFilter { origin }) => {
+                let Some(pane) = &mut self.log_pane else {
+                    self.prompt = None;
+                    return;
+                };
+                match key.code {
+                    KeyCode::Esc => {
+                        pane.filter = std::mem::take(origin);
+                        self.prompt = None;
+                    }
+                    KeyCode::Enter => self.prompt = None,
+                    KeyCode::Backspace => {
+                        pane.filter.pop();
+                    }
+                    KeyCode::Char(ch) if !ctrl => pane.filter.push(ch),
+                    _ => {}
+                }
+            }
+            Some(Prompt::Command(line)) => match key.code {
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Enter => {
+                    let line = std::mem::take(line);
+                    self.prompt = None;
+                    self.run_command(&line);
+                }
+                // Backspace past the start closes the line, as in vim
+                KeyCode::Backspace if line.is_empty() => self.prompt = None,
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Char(ch) if !ctrl => line.push(ch),
+                _ => {}
+            },
+            None => {}
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some(Prompt::Search { query, origin }) = &mut self.prompt else {
+            return;
+        };
+        let origin = *origin;
+        match key.code {
+            KeyCode::Esc => {
+                self.prompt = None;
+                if let Some(page) = self.page_mut() {
+                    page.grid.clear_search();
+                    page.grid.cursor = origin;
+                }
+                return;
+            }
+            KeyCode::Enter => {
+                let query = query.clone();
+                self.prompt = None;
+                let found = self.page_mut().is_some_and(|page| !page.grid.search_matches.is_empty());
+                self.stepping_matches = found;
+                if !found && !query.is_empty() {
+                    self.status = format!("Pattern not found: {}", query);
+                }
+                return;
+            }
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => query.push(ch),
+            _ => return,
+        }
+        let query = query.clone();
+        if let Some(page) = self.page_mut() {
+            page.grid.search_from(&query, origin);
+        }
+    }
+
+    fn run_command(&mut self, line: &str) {
+        let command = match parse_command(line) {
+            Ok(command) => command,
+            Err(message) => {
+                self.status = message;
+                return;
+            }
+        };
+        match command {
+            Command::Open { path, force } => {
+                if self.has_unsaved_edits() && !force {
+                    self.status = "Unsaved edits: :w saves them, :open! FILE drops them".to_string();
+                } else if let Err(e) = self.open(path) {
+                    self.status = format!("❌ {}", e);
+                }
+            }
+            Command::Page(page) if page < self.page_count => self.go_to_page(page),
+            Command::Page(page) => {
+                self.status = ChonkerError::PageOutOfRange { page, total: self.page_count }.to_string();
+            }
+            Command::Export { format, path } => self.export(format, path),
+            Command::Batch { pages, format, dir } => self.start_batch(&pages, format, dir),
+            Command::CancelBatch => {
+                self.status = match self.batch.take() {
+                    Some(batch) => format!("Batch cancelled after {} of {} page(s)", batch.done, batch.total),
+                    None => "No batch is running".to_string(),
+                };
+            }
+            Command::SetBackend(backend) => {
+                self.backend = backend;
+                // Pages without edits are extracted again with the new backend
+                self.extraction = None;
+                let unedited: Vec<usize> =
+                    self.document.pages().filter(|(_, page)| !page.is_modified()).map(|(page_index, _)| page_index).collect();
+                for page_index in unedited {
+                    self.document.remove_page(page_index);
+                }
+                let document = &self.document;
+                self.pages.retain(|&page_index, _| document.page(page_index).is_some());
+                self.status = format!("Backend: {}", backend.map_or("auto", ExtractionBackend::label));
+            }
+            Command::Yank => match self.page_mut() {
+                Some(page) => self.status = clipboard::copy_to_system(&matrix_to_text(&page.grid.matrix)),
+                None => self.status = format!("Page {} has no matrix to copy", self.current_page + 1),
+            },
+            Command::SetSplit(split) => self.resize_split(split),
+            Command::Compare(a, b) => self.compare([a, b]),
+            Command::Theme(Some(name)) => self.set_theme_preset(name),
+            Command::Theme(None) => {
+                let current = self.theme_config.preset.as_deref().unwrap_or("teal");
+                self.status = format!("Theme: {} ({})", current, theme_names());
+            }
+            Command::Write => {
+                self.save();
+            }
+            Command::Quit { force } => {
+                if self.has_unsaved_edits() && !force {
+                    self.status = "Unsaved edits: :w saves them, :q! quits without saving".to_string();
+                } else {
+                    self.quit = true;
+                }
+            }
+            Command::WriteQuit => self.quit = self.save(),
+            Command::Help => self.status = COMMAND_HELP.to_string(),
+        }
+    }
+
+    /// Switches the theme, keeping the color overrides, and saves it for the
+    /// other front ends.
+    fn set_theme_preset(&mut self, name: &str) {
+        self.theme_config.preset = Some(name.to_string());
+        set_theme(self.theme_config.resolve());
+        self.status = match self.theme_config.save() {
+            Ok(()) => format!("Theme: {}", name),
+            Err(e) => format!("Theme: {} (not saved: {})", name, e),
+        };
+    }
+
+    /// Replaces the open document with `pdf_path`.
+    fn open(&mut self, pdf_path: PathBuf) -> Result<()> {
+        self.page_count = pdf_page_count(&pdf_path)?;
+        self.document = DocumentModel::new(pdf_path.clone());
+        self.document_events = self.document.subscribe();
+        self.pdf_path = pdf_path;
+        self.pages.clear();
+        self.extraction = None;
+        self.current_page = 0;
+        self.page_scroll = None;
+        self.focus = Focus::Matrix;
+        #[cfg(feature = "images")]
+        if let Some(images) = &mut self.page_images {
+            images.clear();
+        }
+        self.status = format!("📂 {}", self.pdf_path.display());
+        Ok(())
+    }
+
+    /// Writes the current page, as edited, in `format`.
+    fn export(&mut self, format: ExportFormat, path: Option<PathBuf>) {
+        let page_index = self.current_page;
+        let path = path.unwrap_or_else(|| default_export_path(&self.pdf_path, page_index, format));
+        let Some(page) = self.document.page(page_index) else {
+            self.status = format!("Page {} has no matrix to export", page_index + 1);
+            return;
+        };
+        let char_matrix = page.matrix.without_low_confidence(self.min_confidence);
+        let result = render_export(&char_matrix, page_index, format).and_then(|content| Ok(std::fs::write(&path, content)?));
+        self.status = match result {
+            Ok(()) => format!("✅ Exported page {} to {}", page_index + 1, path.display()),
+            Err(e) => format!("❌ Export failed: {}", e),
+        };
+    }
+
+    /// Starts exporting `pages` in the background; held pages are written
+    /// with their edits.
+    fn start_batch(&mut self, pages: &str, format: ExportFormat, dir: Option<PathBuf>) {
+        if self.batch.as_ref().is_some_and(|batch| !batch.finished) {
+            self.status = "A batch is already running; :cancel stops it".to_string();
+            return;
+        }
+        let pages = match parse_page_range(pages, self.page_count) {
+            Ok(pages) => pages,
+            Err(e) => {
+                self.status = format!("❌ {}", e);
+                return;
+            }
+        };
+        let edited = self
+            .document
+            .pages()
+            .filter(|(page_index, page)| page.is_modified() && pages.contains(page_index))
+            .map(|(page_index, page)| (page_index, page.matrix.clone()))
+            .collect();
+        let dir = dir.unwrap_or_else(|| self.pdf_path.parent().map(Path::to_path_buf).unwrap_or_default());
+        self.status.clear();
+        self.batch = Some(Batch::start(
+            &self.runtime,
+            self.pdf_path.clone(),
+            pages,
+            edited,
+            self.backend,
+            format,
+            self.min_confidence,
+            dir,
+        ));
+    }
+
+    /// Takes in the batch's progress, and reports it once it is done.
+    fn poll_batch(&mut self) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        batch.poll();
+        if batch.finished {
+            self.status = batch.summary();
+            self.batch = None;
+        }
+    }
+
+    /// `key` as the default binding of the action it is bound to in
+    /// `chonker.toml`; `None` for a default chord that was bound elsewhere.
+    fn remap_key(&self, key: KeyEvent) -> Option<KeyEvent> {
+        let Some(chord) = key_chord(&key) else {
+            return Some(key);
+        };
+        let chord = self.keymap.remap(chord)?;
+        Some(key_event(&chord, key.kind).unwrap_or(key))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        // Typed text is never remapped
+        let key = if self.prompt.is_none() {
+            match self.remap_key(key) {
+                Some(key) => key,
+                None => return,
+            }
+        } else {
+            key
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        if !(ctrl && key.code == KeyCode::Char('q')) {
+            self.confirm_quit = false;
+        }
+        if self.prompt.is_some() {
+            self.handle_prompt_key(key);
+            return;
+        }
+        if std::mem::take(&mut self.stepping_matches) {
+            let step = match key.code {
+                KeyCode::Char('n') if !ctrl && !alt => 1,
+                KeyCode::Char('N') if !ctrl && !alt => -1,
+                _ => 0,
+            };
+            if step != 0 {
+                if let Some(page) = self.page_mut() {
+                    page.grid.step_match(step);
+                }
+                self.stepping_matches = true;
+                return;
+            }
+        }
+        let jump = std::mem::take(&mut self.jump);
+        let moving = self.page_mut().is_some_and(|page| page.grid.is_moving_block());
+        match key.code {
+            KeyCode::Char('q') if ctrl => {
+                if self.has_unsaved_edits() && !self.confirm_quit {
+                    self.status = "Unsaved edits: Ctrl+S saves, Ctrl+Q again quits".to_string();
+                    self.confirm_quit = true;
+                } else {
+                    self.quit = true;
+                }
+            }
+            KeyCode::Char('s') if ctrl => {
+                self.save();
+            }
+            KeyCode::Char('z') if ctrl && !moving => self.undo(false),
+            KeyCode::Char('y') if ctrl && !moving => self.undo(true),
+            KeyCode::Char('r') if ctrl => {
+                // Retries a page whose extraction failed
+                if matches!(self.pages.get(&self.current_page), Some(Err(_))) {
+                    self.pages.remove(&self.current_page);
+                    self.status.clear();
+                }
+            }
+            KeyCode::Char('b') if ctrl => {
+                self.show_regions = !self.show_regions;
+                self.status = format!("Regions {}", if self.show_regions { "shown" } else { "hidden" });
+            }
+            KeyCode::Char('t') if ctrl && !moving => self.toggle_tables(),
+            KeyCode::Char('l') if ctrl && !moving => self.toggle_log(),
+            KeyCode::Char(',') if alt => self.resize_split(self.split_ratio.saturating_sub(SPLIT_STEP)),
+            KeyCode::Char('.') if alt => self.resize_split(self.split_ratio + SPLIT_STEP),
+            KeyCode::Char('f') if ctrl && !moving => self.open_search(),
+            KeyCode::Char('/') if self.focus == Focus::Page => self.open_search(),
+            KeyCode::Char('e') if ctrl && !moving => self.open_command(),
+            KeyCode::Char(':') if self.focus == Focus::Page => self.open_command(),
+            KeyCode::F(3) if !moving => {
+                let step = if shift { -1 } else { 1 };
+                if let Some(page) = self.page_mut() {
+                    page.grid.step_match(step);
+                }
+            }
+            // Shift+PgUp/PgDn page through the matrix instead
+            KeyCode::PageDown if !moving && !shift => self.go_to_page(jump.step(self.current_page, 1, self.page_count)),
+            KeyCode::PageUp if !moving && !shift => self.go_to_page(jump.step(self.current_page, -1, self.page_count)),
+            KeyCode::Char(ch @ ('0'..='9' | 'g' | 'G')) if self.focus == Focus::Page && !ctrl && !alt => {
+                let mut jump = jump;
+                match jump.push(ch, self.page_count) {
+                    Some(page) => self.go_to_page(page),
+                    None => self.jump = jump,
+                }
+            }
+            KeyCode::Tab if !moving => {
+                self.focus = match self.focus {
+                    Focus::Page => Focus::Matrix,
+                    Focus::Matrix if self.log_pane.is_some() => Focus::Log,
+                    Focus::Matrix | Focus::Log => Focus::Page,
+                };
+                self.page_scroll = match self.focus {
+                    Focus::Page => self.page_mut().map(|page| page.grid.scroll),
+                    Focus::Matrix | Focus::Log => None,
+                };
+            }
+            _ if self.comparison.is_some() => self.handle_comparison_key(key),
+            _ if self.focus == Focus::Log => self.handle_log_key(key),
+            _ if self.focus == Focus::Page && self.tables.is_some() => self.handle_table_key(key),
+            _ if self.focus == Focus::Page => {
+                let Some(scroll) = &mut self.page_scroll else {
+                    return;
+                };
+                match key.code {
+                    KeyCode::Up => scroll.0 = scroll.0.saturating_sub(1),
+                    KeyCode::Down => scroll.0 += 1,
+                    KeyCode::Left => scroll.1 = scroll.1.saturating_sub(1),
+                    KeyCode::Right => scroll.1 += 1,
+                    _ => {}
+                }
+            }
+            KeyCode::Char('c' | 'x') if ctrl && !moving => {
+                let Some(page) = self.page_mut() else {
+                    return;
+                };
+                let text = if key.code == KeyCode::Char('c') { page.grid.copy() } else { page.grid.cut() };
+                if let Some(text) = text {
+                    self.status = clipboard::copy_to_system(&text);
+                }
+            }
+            _ => {
+                if let Some(page) = self.page_mut() {
+                    if key.code == KeyCode::Esc && !moving {
+                        page.grid.clear_search();
+                    }
+                    page.grid.handle_key(key);
+                }
+            }
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        match &self.prompt {
+            Some(Prompt::Search { .. } | Prompt::LogFilter { .. }) => return Mode::Search,
+            Some(Prompt::Command(_)) => return Mode::Command,
+            None => {}
+        }
+        match self.pages.get(&self.current_page) {
+            Some(Ok(page)) if page.grid.is_moving_block() => Mode::Move,
+            Some(Ok(page)) if page.grid.selection.bbox().is_some() => Mode::Select,
+            _ => Mode::Normal,
+        }
+    }
+
+    /// Key hints for the current mode and focus.
+    fn hints(&self) -> &'static str {
+        match self.mode() {
+            _ if self.stepping_matches => "n/N next/previous match · Esc clear",
+            Mode::Move => "arrows move the block · Enter drop · Esc cancel",
+            Mode::Select => {
+                "Ctrl+C copy · Ctrl+X cut · Ctrl+V paste · Alt+arrows nudge · Ctrl+G move · Ctrl+drag add · Esc clear"
+            }
+            Mode::Search => "Enter keep the hits · Esc cancel",
+            Mode::Command => COMMAND_HELP,
+            Mode::Normal if self.comparison.is_some() => "n/N next/previous change · arrows scroll · Esc back · PgUp/PgDn page",
+            Mode::Normal if self.focus == Focus::Log => {
+                "↑/↓ scroll · Home/End oldest/newest · e/w/i/d/t level · / filter · Esc hide · Tab page"
+            }
+            Mode::Normal if self.focus == Focus::Page && self.tables.is_some() => {
+                "←/→ table · ↑/↓ row · c export CSV · Esc page · Tab matrix · PgUp/PgDn page"
+            }
+            Mode::Normal if self.focus == Focus::Page => "arrows scroll · gg/G first/last page · / search · : command · Tab matrix · PgUp/PgDn page",
+            Mode::Normal => {
+                "Tab focus · Shift+arrows select · Ctrl+F search · Ctrl+E command · Ctrl+B regions · Ctrl+T tables · Ctrl+L log · Alt+,/. resize · PgUp/PgDn page · Ctrl+S save · Ctrl+Q quit"
+            }
+        }
+    }
+
+    /// File, page, cursor, unsaved edits and backend, then the last message.
+    fn render_status_bar(&self, frame: &mut Frame, area: Rect) {
+        let t = theme();
+        let bar = Style::default().fg(rgb(t.fg)).bg(rgb(t.widget_active_bg));
+        let page = self.pages.get(&self.current_page).and_then(|page| page.as_ref().ok());
+        let file = self.pdf_path.file_name().map_or_else(|| self.pdf_path.display().to_string(), |name| name.to_string_lossy().to_string());
+        let used = self.document.page(self.current_page).and_then(|page| page.matrix.backend);
+        let backend = match (self.backend, used) {
+            (Some(backend), _) => backend.label().to_string(),
+            (None, Some(used)) => format!("auto: {}", used.label()),
+            (None, None) => "auto".to_string(),
+        };
+        let mut spans = vec![
+            Span::styled(format!(" {} ", self.mode().label()), Style::default().fg(rgb(t.bg)).bg(rgb(t.accent))),
+            Span::styled(format!(" {} ", file), bar.add_modifier(Modifier::BOLD)),
+            Span::styled(format!("│ page {}/{} ", self.current_page + 1, self.page_count), bar),
+        ];
+        let pending = self.jump.pending();
+        if !pending.is_empty() {
+            spans.push(Span::styled(format!("│ {} ", pending), bar));
+        }
+        if let Some(page) = page {
+            let (row, col) = page.grid.cursor;
+            spans.push(Span::styled(format!("│ {}:{} ", row + 1, col + 1), bar));
+        }
+        if self.document.is_modified(self.current_page) {
+            spans.push(Span::styled("│ [+] ", bar.fg(rgb(t.warning))));
+        } else if self.has_unsaved_edits() {
+            spans.push(Span::styled("│ [+ other pages] ", bar.fg(rgb(t.warning))));
+        }
+        spans.push(Span::styled(format!("│ {} ", backend), bar));
+        let problems = self.logs.problems();
+        if self.log_pane.is_none() && problems > 0 {
+            spans.push(Span::styled(format!("│ log: {} warning(s) ", problems), bar.fg(rgb(t.warning))));
+        }
+
+        let left = Line::from(spans);
+        let [left_area, message_area] = Layout::horizontal([Constraint::Length(left.width() as u16), Constraint::Min(0)]).areas(area);
+        frame.render_widget(Paragraph::new(left).style(bar), left_area);
+        let message = [self.search_status(), Some(self.status.clone())]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" · ");
+        frame.render_widget(Paragraph::new(format!("{} ", message)).style(bar).alignment(Alignment::Right), message_area);
+    }
+
+    /// The search query with the hit counter, while a search is shown.
+    fn search_status(&self) -> Option<String> {
+        let page = self.pages.get(&self.current_page)?.as_ref().ok()?;
+        let query = match &self.prompt {
+            Some(Prompt::Search { query, .. }) => query,
+            _ if !page.grid.search_query.is_empty() => &page.grid.search_query,
+            _ => return None,
+        };
+        let counter = match (page.grid.current_match, page.grid.search_matches.len()) {
+            _ if query.is_empty() => String::new(),
+            (_, 0) => " [no match]".to_string(),
+            (Some(index), count) => format!(" [{}/{}]", index + 1, count),
+            (None, count) => format!(" [{}]", count),
+        };
+        Some(format!("/{}{}", query, counter))
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => self.handle_key(key),
+            // Bracketed paste brings the system clipboard in as text
+            Event::Paste(text) => {
+                if let Some(page) = self.page_mut() {
+                    page.grid.paste(Some(&text));
+                    self.focus = Focus::Matrix;
+                    self.page_scroll = None;
+                }
+            }
+            Event::Mouse(mouse)
+                if self.resizing_split
+                    || matches!(mouse.kind, MouseEventKind::Down(MouseButton::Left)) && self.on_divider(mouse.column, mouse.row) =>
+            {
+                match mouse.kind {
+                    MouseEventKind::Down(_) => self.resizing_split = true,
+                    MouseEventKind::Drag(_) => {
+                        let body = self.body_area;
+                        let ratio = mouse.column.saturating_sub(body.x) as u32 * 100 / body.width.max(1) as u32;
+                        self.resize_split(ratio as u16);
+                    }
+                    _ => self.resizing_split = false,
+                }
+            }
+            // The diff covers the matrix
+            Event::Mouse(_) if self.comparison.is_some() => {}
+            Event::Mouse(mouse) => {
+                let area = self.grid_area;
+                if area.contains(Position::new(mouse.column, mouse.row)) {
+                    self.focus = Focus::Matrix;
+                    self.page_scroll = None;
+                }
+                if let Some(page) = self.page_mut() {
+                    let cell = page.grid.cell_at(area, mouse.column, mouse.row);
+                    page.grid.handle_mouse(mouse, cell);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let t = theme();
+        let base = Style::default().fg(rgb(t.fg)).bg(rgb(t.bg));
+        let gauge_height = if self.batch.is_some() { 1 } else { 0 };
+        let [body, gauge, status_bar, status] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(gauge_height),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.size());
+        if let Some(batch) = &self.batch {
+            batch.render(frame, gauge);
+        }
+        let log_height = if self.log_pane.is_some() { body.height / 3 } else { 0 };
+        let [body, log_area] = Layout::vertical([Constraint::Min(1), Constraint::Length(log_height)]).areas(body);
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(self.split_ratio), Constraint::Percentage(100 - self.split_ratio)]).areas(body);
+        self.body_area = body;
+        let pane = |title: String, focused: bool| {
+            Block::bordered()
+                .title(title)
+                .style(base)
+                .border_style(Style::default().fg(rgb(if focused { t.accent } else { t.chrome })))
+        };
+
+        let page_number = self.current_page + 1;
+        let focus = self.focus;
+        let page_block = pane(format!(" Page {}/{} ", page_number, self.page_count), focus == Focus::Page);
+        let modified = self.document.is_modified(self.current_page);
+        let matrix_block = pane(
+            format!(" Matrix{} ", if modified { " [modified]" } else { "" }),
+            focus == Focus::Matrix,
+        );
+        self.grid_area = matrix_block.inner(right);
+
+        match (self.pages.get_mut(&self.current_page), self.document.page(self.current_page)) {
+            (Some(Ok(page)), Some(document_page)) => {
+                // Drawing the matrix first brings its cursor into view
+                frame.render_widget(matrix_block, right);
+                let regions: Vec<TextRegion> = match self.show_regions {
+                    true => document_page.matrix.without_low_confidence(self.min_confidence).text_regions,
+                    false => Vec::new(),
+                };
+                page.grid.render(frame, self.grid_area, focus == Focus::Matrix, &regions);
+                if let Some(tables) = &mut self.tables {
+                    let block = pane(format!(" Tables · page {}/{} ", page_number, self.page_count), focus == Focus::Page);
+                    tables.render(frame, left, block, &detect_tables(&document_page.matrix));
+                } else {
+                    let page_area = page_block.inner(left);
+                    frame.render_widget(page_block, left);
+                    #[cfg(feature = "images")]
+                    let drawn = self
+                        .page_images
+                        .as_mut()
+                        .map_or(false, |images| images.render(frame, page_area, &self.pdf_path, self.current_page));
+                    #[cfg(not(feature = "images"))]
+                    let drawn = false;
+                    if !drawn {
+                        // Only the rows and columns in view are turned into text
+                        let (row, col) = self.page_scroll.unwrap_or(page.grid.scroll);
+                        let text: Vec<Line> = document_page
+                            .extracted
+                            .iter()
+                            .skip(row)
+                            .take(page_area.height as usize)
+                            .map(|cells| Line::from(cells.iter().skip(col).take(page_area.width as usize).collect::<String>()))
+                            .collect();
+                        frame.render_widget(Paragraph::new(text).style(base), page_area);
+                    }
+                }
+            }
+            (Some(Err(e)), _) => {
+                let message = match e.hint() {
+                    Some(hint) => format!("{}\n\n{}\n\nCtrl+R retries the page.", e, hint),
+                    None => format!("{}\n\nCtrl+R retries the page.", e),
+                };
+                frame.render_widget(
+                    Paragraph::new(message).style(Style::default().fg(rgb(t.error))).wrap(Wrap { trim: false }).block(page_block),
+                    left,
+                );
+                frame.render_widget(matrix_block, right);
+            }
+            _ => {
+                let progress = self.extraction_progress().unwrap_or_default();
+                frame.render_widget(Paragraph::new(progress).style(base).block(page_block), left);
+                frame.render_widget(matrix_block, right);
+            }
+        }
+
+        if let Some(comparison) = &mut self.comparison {
+            frame.render_widget(Clear, body);
+            comparison.render(frame, body, pane(format!(" Compare · page {}/{} ", comparison.page + 1, self.page_count), true));
+        }
+        if let Some(log_pane) = &mut self.log_pane {
+            log_pane.render(frame, log_area, pane(" Log ".to_string(), focus == Focus::Log), &self.logs);
+        }
+
+        self.render_status_bar(frame, status_bar);
+        // A prompt takes the hint line, with the terminal cursor after what is typed
+        let prompt = match &self.prompt {
+            Some(Prompt::Search { query, .. }) => Some((format!("/{}", query), 1 + query.chars().count())),
+            Some(Prompt::Command(line)) => Some((format!(":{}", line), 1 + line.chars().count())),
+            Some(Prompt::LogFilter { .. }) => {
+                let filter = self.log_pane.as_ref().map(|log_pane| log_pane.filter.as_str()).unwrap_or_default();
+                Some((format!("/{}", filter), 1 + filter.chars().count()))
+            }
+            None => None,
+        };
+        if let Some((text, cursor)) = prompt {
+            let cursor_x = status.x + cursor as u16;
+            frame.render_widget(Paragraph::new(text).style(base), status);
+            frame.set_cursor(cursor_x.min(status.right().saturating_sub(1)), status.y);
+            return;
+        }
+        frame.render_widget(Paragraph::new(self.hints()).style(Style::default().fg(rgb(t.dim)).bg(rgb(t.bg))), status);
+    }
+}
+
+/// `<stem>_p0001.<ext>` next to the PDF.
+fn default_export_path(pdf_path: &Path, page_index: usize, format: ExportFormat) -> PathBuf {
+    pdf_path.with_file_name(export_file_name(pdf_path, page_index, format))
+}
+
+/// The chord of a key press, in `chonker.toml`'s key names.
+fn key_chord(key: &KeyEvent) -> Option<KeyChord> {
+    let mut shift = key.modifiers.contains(KeyModifiers::SHIFT);
+    let name = match key.code {
+        KeyCode::Char(ch) => {
+            shift |= ch.is_uppercase();
+            ch.to_lowercase().to_string()
+        }
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::BackTab => {
+            shift = true;
+            "tab".to_string()
+        }
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Esc => "esc".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        _ => return None,
+    };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    Some(KeyChord::new(ctrl, alt, shift, &name))
+}
+
+/// The key press crossterm reports for `chord`.
+fn key_event(chord: &KeyChord, kind: KeyEventKind) -> Option<KeyEvent> {
+    let mut chars = chord.key.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(ch), None) if chord.shift => KeyCode::Char(ch.to_uppercase().next().unwrap_or(ch)),
+        (Some(ch), None) => KeyCode::Char(ch),
+        _ => match chord.key.as_str() {
+            "tab" if chord.shift => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "esc" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    modifiers.set(KeyModifiers::CONTROL, chord.ctrl);
+    modifiers.set(KeyModifiers::ALT, chord.alt);
+    modifiers.set(KeyModifiers::SHIFT, chord.shift);
+    Some(KeyEvent::new_with_kind(code, modifiers, kind))
+}
+
+fn run(app: &mut App) -> Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    while !app.quit {
+        app.poll_extraction();
+        app.poll_document();
+        app.start_extraction();
+        app.poll_batch();
+        if let Some(comparison) = &mut app.comparison {
+            comparison.poll();
+        }
+        terminal.draw(|frame| app.render(frame))?;
+        // While work runs in the background the screen is redrawn on a
+        // tick, for the spinner and the batch gauge
+        let comparing = app.comparison.as_ref().is_some_and(Comparison::is_running);
+        if (app.extraction.is_some() || app.batch.is_some() || comparing) && !event::poll(SPINNER_TICK)? {
+            continue;
+        }
+        app.handle_event(event::read()?);
+        app.commit_edits();
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let pdf_path = match std::env::args().nth(1).as_deref() {
+        Some("--help" | "-h") | None => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        Some(path) => PathBuf::from(path),
+    };
+    // Caught from the start, so chonker.toml warnings reach the log pane
+    let logs = LogCapture::new();
+    logs.install();
+    let config = ChonkerConfig::load();
+    let mut app = App::new(pdf_path, &config, logs)?;
+    // The terminal answers graphics queries before the TUI takes over
+    #[cfg(feature = "images")]
+    {
+        app.page_images = page_image::PageImages::detect();
+    }
+
+    enable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    let result = run(&mut app);
+    disable_raw_mode()?;
+    crossterm::execute!(std::io::stdout(), DisableBracketedPaste, DisableMouseCapture, LeaveAlternateScreen)?;
+    result
+}
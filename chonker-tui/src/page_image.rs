@@ -0,0 +1,61 @@
+//! Page bitmaps in the page pane for terminals with a graphics protocol
+//! (kitty, sixel or iTerm2). Elsewhere the pane keeps the text rendering.
+
+use chonker_core::{render_page_png, ChonkerError};
+use chonker_grid::theme;
+use ratatui::prelude::*;
+use ratatui_image::picker::{Picker, ProtocolType};
+use ratatui_image::protocol::StatefulProtocol;
+use ratatui_image::{Resize, StatefulImage};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Terminal pixels are small; this is enough for a pane-sized page.
+const RENDER_DPI: f32 = 100.0;
+
+pub struct PageImages {
+    picker: Picker,
+    pages: HashMap<usize, Result<Box<dyn StatefulProtocol>, ChonkerError>>,
+}
+
+impl PageImages {
+    /// Queries the terminal; `None` when it can only draw half blocks.
+    pub fn detect() -> Option<Self> {
+        let mut picker = Picker::from_termios().ok()?;
+        if picker.guess_protocol() == ProtocolType::Halfblocks {
+            return None;
+        }
+        Some(Self { picker, pages: HashMap::new() })
+    }
+
+    /// Forgets the rendered pages, e.g. when another document is opened.
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+
+    fn load(&mut self, pdf_path: &Path, page: usize) -> Result<Box<dyn StatefulProtocol>, ChonkerError> {
+        let png = render_page_png(pdf_path, page, RENDER_DPI)?;
+        let mut image = image::load_from_memory(&png)
+            .map_err(|e| ChonkerError::RenderFailed(format!("could not decode the image: {}", e)))?;
+        if theme().dark {
+            image.invert();
+        }
+        Ok(self.picker.new_resize_protocol(image))
+    }
+
+    /// Draws `page` into `area`. Returns `false` when it could not be
+    /// rendered, so the caller can fall back to text.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, pdf_path: &Path, page: usize) -> bool {
+        if !self.pages.contains_key(&page) {
+            let result = self.load(pdf_path, page);
+            self.pages.insert(page, result);
+        }
+        match self.pages.get_mut(&page) {
+            Some(Ok(protocol)) => {
+                frame.render_stateful_widget(StatefulImage::new(None).resize(Resize::Fit(None)), area, protocol);
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! The table preview: the page's tables, as chonker-core's `detect_tables`
+//! finds them, drawn as rows and columns in place of the page text, with the
+//! focused one written to CSV on request.
+
+use chonker_core::{table_to_csv, DetectedTable};
+use chonker_grid::theme;
+use ratatui::{prelude::*, widgets::*};
+use std::path::{Path, PathBuf};
+
+use crate::grid::rgb;
+
+/// Widest a column is drawn; longer cells are cut.
+const MAX_COLUMN_WIDTH: u16 = 40;
+
+#[derive(Default)]
+pub struct TablePreview {
+    /// The focused table, in region order.
+    pub index: usize,
+    /// The highlighted row below the header.
+    state: TableState,
+}
+
+impl TablePreview {
+    /// Focuses the next or previous of `count` tables, wrapping around.
+    pub fn step_table(&mut self, step: isize, count: usize) {
+        if count > 0 {
+            self.index = (self.index as isize + step).rem_euclid(count as isize) as usize;
+            self.state = TableState::default();
+        }
+    }
+
+    /// Moves the highlight among the `rows` rows below the header.
+    pub fn step_row(&mut self, step: isize, rows: usize) {
+        let row = match self.state.selected() {
+            Some(row) => (row as isize + step).clamp(0, rows.saturating_sub(1) as isize) as usize,
+            None => 0,
+        };
+        self.state.select((rows > 0).then_some(row));
+    }
+
+    /// Writes the focused table to `<stem>_p0001_table1.csv` next to the PDF.
+    pub fn export_csv(&self, tables: &[DetectedTable], pdf_path: &Path, page_index: usize) -> Result<PathBuf, String> {
+        let table = tables.get(self.index).ok_or("No table to export on this page")?;
+        let path = csv_path(pdf_path, page_index, self.index);
+        std::fs::write(&path, table_to_csv(&table.cells)).map_err(|e| format!("Export failed: {}", e))?;
+        Ok(path)
+    }
+
+    /// Draws the focused table in `block`; its first row is the header.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, block: Block, tables: &[DetectedTable]) {
+        let t = theme();
+        let base = Style::default().fg(rgb(t.fg)).bg(rgb(t.bg));
+        let Some(table) = tables.get(self.index.min(tables.len().saturating_sub(1))) else {
+            let message = "No tables on this page. Regions tagged as tables, and regions whose text lines up in columns, show here.";
+            frame.render_widget(Paragraph::new(message).style(base).wrap(Wrap { trim: true }).block(block), area);
+            return;
+        };
+        let position = format!(" {}/{} · R{} ", self.index + 1, tables.len(), table.region_id);
+        let block = block.title(Line::from(position).alignment(Alignment::Right));
+
+        let columns = table.cells.first().map_or(0, Vec::len);
+        let widths = (0..columns).map(|col| {
+            let width = table.cells.iter().map(|row| row[col].chars().count()).max().unwrap_or(0);
+            Constraint::Length((width as u16).min(MAX_COLUMN_WIDTH))
+        });
+        let mut rows = table.cells.iter().map(|cells| Row::new(cells.iter().map(|cell| Cell::from(cell.as_str()))));
+        let header = rows.next().unwrap_or_default().style(Style::default().fg(rgb(t.accent)).add_modifier(Modifier::BOLD));
+        let widget = Table::new(rows, widths)
+            .header(header)
+            .column_spacing(2)
+            .style(base)
+            .highlight_style(Style::default().bg(rgb(t.widget_active_bg)))
+            .block(block);
+        frame.render_stateful_widget(widget, area, &mut self.state);
+    }
+}
+
+/// `<stem>_p0001_table1.csv` beside the PDF; `table_index` is 0-based.
+pub fn csv_path(pdf_path: &Path, page_index: usize, table_index: usize) -> PathBuf {
+    let stem = pdf_path.file_stem().map_or_else(|| "document".to_string(), |s| s.to_string_lossy().to_string());
+    pdf_path.with_file_name(format!("{}_p{:04}_table{}.csv", stem, page_index + 1, table_index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chonker_core::CharBBox;
+
+    #[test]
+    fn test_preview_draws_cells_and_exports_csv() {
+        let cells = |rows: &[&[&str]]| rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect();
+        let table = |region_id, rows| DetectedTable { region_id, bbox: CharBBox { x: 0, y: 0, width: 1, height: 1 }, cells: rows };
+        let tables = [table(4, cells(&[&["Item", "Qty"], &["Red pen", "2"]])), table(7, cells(&[&["a,b"]]))];
+
+        let mut preview = TablePreview::default();
+        let mut terminal = Terminal::new(backend::TestBackend::new(20, 5)).unwrap();
+        terminal.draw(|frame| preview.render(frame, frame.size(), Block::bordered(), &tables)).unwrap();
+        let line = |terminal: &Terminal<backend::TestBackend>, y| {
+            (0..20).map(|x| terminal.backend().buffer().get(x, y).symbol().to_string()).collect::<String>()
+        };
+        assert!(line(&terminal, 0).contains(" 1/2 · R4 "));
+        assert_eq!(line(&terminal, 1), "│Item     Qty      │");
+        assert_eq!(line(&terminal, 2), "│Red pen  2        │");
+
+        preview.step_row(1, 1);
+        preview.step_row(1, 1);
+        assert_eq!(preview.state.selected(), Some(0));
+        preview.step_table(-1, tables.len());
+        assert_eq!((preview.index, preview.state.selected()), (1, None));
+
+        let dir = std::env::temp_dir().join(format!("chonker_tui_table_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = preview.export_csv(&tables, &dir.join("report.pdf"), 2).unwrap();
+        assert_eq!(path, dir.join("report_p0003_table2.csv"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "\"a,b\"\n");
+        assert!(preview.export_csv(&[], &dir.join("report.pdf"), 2).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,183 @@
+//! # chonker-web: the matrix viewer in the browser
+//!
+//! Pick or drop a PDF, extract a page with PDFium compiled to WebAssembly and
+//! edit it in the same `MatrixGrid` as the desktop app, without installing
+//! anything. Build and serve with `trunk serve` from this directory; see
+//! `index.html` for the PDFium files it expects.
+
+use chonker_core::{extract_page_from_bytes, matrix_to_text, page_count_from_bytes};
+use chonker_grid::{theme, MatrixGrid};
+use eframe::egui;
+use egui::RichText;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A picked file's name and contents. The picker resolves outside the frame
+/// loop, so it leaves the file here for the next `update`.
+type PickedFile = Rc<RefCell<Option<(String, Vec<u8>)>>>;
+
+struct ChonkerWeb {
+    picked: PickedFile,
+    // The open PDF's name and bytes; there is no file system to reopen it from
+    document: Option<(String, Vec<u8>)>,
+    page_count: usize,
+    page: usize,
+    grid: Option<MatrixGrid>,
+    status: String,
+}
+
+impl ChonkerWeb {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut visuals = if theme().dark { egui::Visuals::dark() } else { egui::Visuals::light() };
+        visuals.panel_fill = theme().bg;
+        cc.egui_ctx.set_visuals(visuals);
+        Self {
+            picked: PickedFile::default(),
+            document: None,
+            page_count: 0,
+            page: 0,
+            grid: None,
+            status: "Open or drop a PDF to begin".to_string(),
+        }
+    }
+
+    fn pick_file(&self, ctx: &egui::Context) {
+        let dialog = rfd::AsyncFileDialog::new().add_filter("PDF", &["pdf"]);
+        let (picked, ctx) = (self.picked.clone(), ctx.clone());
+        let task = async move {
+            if let Some(file) = dialog.pick_file().await {
+                *picked.borrow_mut() = Some((file.file_name(), file.read().await));
+                ctx.request_repaint();
+            }
+        };
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(task);
+        #[cfg(not(target_arch = "wasm32"))]
+        pollster::block_on(task);
+    }
+
+    fn open(&mut self, name: String, bytes: Vec<u8>) {
+        match page_count_from_bytes(&bytes) {
+            Ok(page_count) => {
+                self.page_count = page_count;
+                self.document = Some((name, bytes));
+                self.load_page(0);
+            }
+            Err(e) => self.status = format!("❌ {}: {}", name, e),
+        }
+    }
+
+    fn load_page(&mut self, page: usize) {
+        let Some((name, bytes)) = &self.document else {
+            return;
+        };
+        self.page = page;
+        match extract_page_from_bytes(bytes, page, None) {
+            Ok(char_matrix) => {
+                self.grid = Some(MatrixGrid::new(&matrix_to_text(&char_matrix.rows())));
+                self.status = format!(
+                    "✅ {} page {}/{}: {}×{} characters",
+                    name,
+                    page + 1,
+                    self.page_count,
+                    char_matrix.width,
+                    char_matrix.height
+                );
+            }
+            Err(e) => {
+                self.grid = None;
+                self.status = match e.hint() {
+                    Some(hint) => format!("❌ Page {}: {} ({})", page + 1, e, hint),
+                    None => format!("❌ Page {}: {}", page + 1, e),
+                };
+            }
+        }
+    }
+}
+
+impl eframe::App for ChonkerWeb {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let picked = self.picked.borrow_mut().take();
+        let dropped = ctx.input(|i| {
+            let file = i.raw.dropped_files.first()?;
+            Some((file.name.clone(), file.bytes.as_ref()?.to_vec()))
+        });
+        if let Some((name, bytes)) = picked.or(dropped) {
+            self.open(name, bytes);
+        }
+
+        let mut goto_page = None;
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("🐹 CHONKER").color(theme().accent).monospace().strong());
+                if ui.button(RichText::new("Open PDF").monospace()).clicked() {
+                    self.pick_file(ctx);
+                }
+                if self.document.is_some() {
+                    if ui.add_enabled(self.page > 0, egui::Button::new("◀")).clicked() {
+                        goto_page = Some(self.page - 1);
+                    }
+                    ui.label(
+                        RichText::new(format!("{}/{}", self.page + 1, self.page_count))
+                            .color(theme().fg)
+                            .monospace(),
+                    );
+                    if ui.add_enabled(self.page + 1 < self.page_count, egui::Button::new("▶")).clicked() {
+                        goto_page = Some(self.page + 1);
+                    }
+                }
+                if let Some(grid) = &self.grid {
+                    if ui.button(RichText::new("Copy all").monospace()).clicked() {
+                        let text = matrix_to_text(&grid.matrix);
+                        ui.output_mut(|o| o.copied_text = text);
+                    }
+                }
+            });
+        });
+        if let Some(page) = goto_page {
+            self.load_page(page);
+        }
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.label(RichText::new(&self.status).color(theme().dim).monospace().size(10.0));
+        });
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(theme().bg))
+            .show(ctx, |ui| match &mut self.grid {
+                Some(grid) => {
+                    egui::ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+                        grid.show(ui);
+                    });
+                }
+                None => {
+                    ui.centered_and_justified(|ui| {
+                        ui.label(RichText::new("Drop a PDF here").color(theme().dim).monospace());
+                    });
+                }
+            });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(async {
+        eframe::WebRunner::new()
+            .start(
+                "chonker_canvas",
+                eframe::WebOptions::default(),
+                Box::new(|cc| Box::new(ChonkerWeb::new(cc))),
+            )
+            .await
+            .expect("failed to start the web app");
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> Result<(), eframe::Error> {
+    eframe::run_native(
+        "🐹 CHONKER web",
+        eframe::NativeOptions::default(),
+        Box::new(|cc| Box::new(ChonkerWeb::new(cc))),
+    )
+}
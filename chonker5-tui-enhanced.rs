@@ -72,37 +72,17 @@ impl ChonkerTUI {
     }
     
     fn parse_stext_to_matrix(&mut self, stext: &str) -> Result<()> {
-        // Create a large matrix
-        let mut matrix = CharacterMatrix::new(200, 100);
+        let matrix = chonker_core::parse_stext_to_matrix(
+            stext,
+            self.current_page,
+            None,
+            &chonker_core::ExtractionLimits::load(),
+            &chonker_core::MonospaceTraining::load().model,
+        )?;
         
-        // Simple parser for mutool stext output
-        // In real implementation, you'd parse the XML structure
-        let lines: Vec<&str> = stext.lines().collect();
-        let mut y = 0;
-        
-        for line in lines {
-            if line.contains("<char") {
-                // Extract character and position from stext XML
-                // Simplified parsing here
-                if let (Some(x_pos), Some(char_match)) = (
-                    line.find("x=\"").map(|i| &line[i+3..i+7]),
-                    line.find(">").and_then(|i| line.chars().nth(i+1))
-                ) {
-                    if let Ok(x) = x_pos.trim_end_matches('"').parse::<f32>() {
-                        let x_idx = (x / 7.0) as usize;
-                        if x_idx < matrix.width && y < matrix.height {
-                            matrix.matrix[y][x_idx] = char_match;
-                        }
-                    }
-                }
-            } else if line.contains("</line>") {
-                y += 1;
-            }
-        }
-        
-        self.editable_matrix = Some(matrix.matrix.clone());
+        self.editable_matrix = Some(matrix.rows().into_owned());
         self.character_matrix = Some(matrix);
-        self.status_message = "Extracted matrix using mutool".to_string();
+        self.status_message = "Extracted matrix using mutool stext".to_string();
         Ok(())
     }
 }
@@ -5,6 +5,9 @@
 //! This tool combines PDF text extraction with vision-based region detection to create faithful
 //! character representations of PDF documents.
 //!
+//! The extraction engine lives in the `chonker-core` crate and the matrix
+//! editor in `chonker-grid`; this file is the egui front end. Headless exports run through the `chonker` CLI.
+//!
 //! ## Key Features
 //! - PDF to character matrix conversion
 //! - Text region detection using character coordinate analysis
@@ -14,20 +17,27 @@
 //!
 //! ```cargo
 //! [dependencies]
+//! chonker-core = { path = "chonker-core" }
+//! chonker-grid = { path = "chonker-grid" }
 //! eframe = "0.24"
 //! egui = "0.24"
 //! rfd = "0.15"
 //! image = "0.25"
 //! pdfium-render = { version = "0.8", features = ["thread_safe"] }
-//! tokio = { version = "1.38", features = ["full", "rt-multi-thread"] }
+//! tokio = { version = "1.38", features = ["rt-multi-thread", "sync"] }
 //! anyhow = "1.0"
 //! tracing = "0.1"
 //! tracing-subscriber = { version = "0.3", features = ["env-filter"] }
+//! tracing-appender = "0.2"
 //! serde = { version = "1.0", features = ["derive"] }
 //! serde_json = "1.0"
+//! notify = "6.1"
+//! ureq = "2.9"
 //! ```
 
 use anyhow::Result;
+use chonker_core::*;
+use chonker_grid::*;
 use eframe::egui;
 use egui::{Align2, Color32, FontId, Rect, Response, RichText, Rounding, Sense, Stroke, Vec2};
 use image::{ImageBuffer, Rgb, RgbImage};
@@ -37,1176 +47,1282 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-// Teal and chrome color scheme
-const TERM_BG: Color32 = Color32::from_rgb(10, 15, 20);
-const TERM_FG: Color32 = Color32::from_rgb(26, 188, 156);
-const TERM_HIGHLIGHT: Color32 = Color32::from_rgb(22, 160, 133);
-const TERM_ERROR: Color32 = Color32::from_rgb(255, 80, 80);
-const TERM_DIM: Color32 = Color32::from_rgb(80, 100, 100);
-const TERM_YELLOW: Color32 = Color32::from_rgb(255, 200, 0);
-const TERM_GREEN: Color32 = Color32::from_rgb(46, 204, 113);
-const TERM_BLUE: Color32 = Color32::from_rgb(52, 152, 219);
-const CHROME: Color32 = Color32::from_rgb(82, 86, 89);
-
-// ============= MATRIX SELECTION =============
-#[derive(Clone, Debug)]
-pub struct MatrixSelection {
-    pub start: Option<(usize, usize)>,
-    pub end: Option<(usize, usize)>,
-}
-
-impl MatrixSelection {
-    pub fn new() -> Self {
+// ============= PAGE MAPPING =============
+/// Maps matrix cells onto the rendered page image and back. The matrix spans
+/// `width * char_width` by `height * char_height` points, stretched to fill
+/// `image_rect`.
+#[derive(Debug, Clone, Copy)]
+pub struct MatrixImageMapping {
+    pub image_rect: egui::Rect,
+    pub cell_size: Vec2,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl MatrixImageMapping {
+    pub fn new(char_matrix: &CharacterMatrix, image_rect: egui::Rect) -> Self {
+        let pdf_width_pts = char_matrix.width as f32 * char_matrix.char_width;
+        let pdf_height_pts = char_matrix.height as f32 * char_matrix.char_height;
+        let scale_x = image_rect.width() / pdf_width_pts;
+        let scale_y = image_rect.height() / pdf_height_pts;
         Self {
-            start: None,
-            end: None,
+            image_rect,
+            cell_size: Vec2::new(
+                char_matrix.char_width * scale_x,
+                char_matrix.char_height * scale_y,
+            ),
+            cols: char_matrix.width,
+            rows: char_matrix.height,
         }
     }
 
-    pub fn is_selected(&self, row: usize, col: usize) -> bool {
-        if let (Some(start), Some(end)) = (self.start, self.end) {
-            let min_row = start.0.min(end.0);
-            let max_row = start.0.max(end.0);
-            let min_col = start.1.min(end.1);
-            let max_col = start.1.max(end.1);
-            row >= min_row && row <= max_row && col >= min_col && col <= max_col
-        } else {
-            false
-        }
+    /// Screen rectangle of the cell at column `x`, row `y`.
+    pub fn cell_rect(&self, x: usize, y: usize) -> egui::Rect {
+        self.bbox_rect(&CharBBox {
+            x,
+            y,
+            width: 1,
+            height: 1,
+        })
     }
 
-    pub fn get_selected_text(&self, matrix: &[Vec<char>]) -> String {
-        if let (Some(start), Some(end)) = (self.start, self.end) {
-            let min_row = start.0.min(end.0).min(matrix.len().saturating_sub(1));
-            let max_row = start.0.max(end.0).min(matrix.len().saturating_sub(1));
-            let min_col = start.1.min(end.1);
-            let max_col = start.1.max(end.1);
+    pub fn bbox_rect(&self, bbox: &CharBBox) -> egui::Rect {
+        let min = self.image_rect.min
+            + Vec2::new(
+                bbox.x as f32 * self.cell_size.x,
+                bbox.y as f32 * self.cell_size.y,
+            );
+        egui::Rect::from_min_size(
+            min,
+            Vec2::new(
+                bbox.width as f32 * self.cell_size.x,
+                bbox.height as f32 * self.cell_size.y,
+            ),
+        )
+    }
 
-            // Limit selection size to prevent performance issues
-            if (max_row - min_row + 1) * (max_col - min_col + 1) > 100000 {
-                return String::from("[Selection too large]");
-            }
+    /// Cells spanned by the screen rectangle between two points, clamped to
+    /// the image.
+    pub fn bbox_between(&self, a: egui::Pos2, b: egui::Pos2) -> Option<CharBBox> {
+        let inner = self.image_rect.shrink(0.5);
+        let (x0, y0) = self.cell_at(inner.clamp(a.min(b)))?;
+        let (x1, y1) = self.cell_at(inner.clamp(a.max(b)))?;
+        Some(CharBBox {
+            x: x0,
+            y: y0,
+            width: x1 - x0 + 1,
+            height: y1 - y0 + 1,
+        })
+    }
 
-            let mut result =
-                String::with_capacity((max_row - min_row + 1) * (max_col - min_col + 2));
-            for row in min_row..=max_row {
-                if row < matrix.len() {
-                    let row_data = &matrix[row];
-                    let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-                    for col in min_col..=row_max_col {
-                        if col < row_data.len() {
-                            result.push(row_data[col]);
-                        }
-                    }
-                    if row < max_row {
-                        result.push('\n');
-                    }
-                }
-            }
-            result
-        } else {
-            String::new()
+    /// The `(x, y)` cell under a screen position, if it lies on the matrix.
+    pub fn cell_at(&self, pos: egui::Pos2) -> Option<(usize, usize)> {
+        let local = pos - self.image_rect.min;
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
         }
+        let x = (local.x / self.cell_size.x) as usize;
+        let y = (local.y / self.cell_size.y) as usize;
+        (x < self.cols && y < self.rows).then_some((x, y))
     }
 }
 
-pub struct MatrixGrid {
-    pub matrix: Vec<Vec<char>>,
-    pub selection: MatrixSelection,
-    pub char_size: Vec2,
-    pub cursor_pos: Option<(usize, usize)>,
-    pub last_blink: Instant,
-    pub cursor_visible: bool,
-    pub clipboard: Vec<Vec<char>>,   // Store rectangular clipboard
-    pub modified: bool,              // Track if matrix was modified
-    pub is_dragging_selection: bool, // Track if we're dragging a selection
-    pub drag_start_pos: Option<(usize, usize)>, // Where the drag started
-    pub drag_content: Vec<Vec<char>>, // Content being dragged
+/// Timeouts and empty pages are expected with some documents and are shown
+/// as warnings rather than errors.
+fn error_color(error: &ChonkerError) -> Color32 {
+    match error {
+        ChonkerError::Timeout { .. } | ChonkerError::NoText { .. } => theme().warning,
+        _ => theme().error,
+    }
 }
 
-impl MatrixGrid {
-    pub fn new(text: &str) -> Self {
-        let matrix: Vec<Vec<char>> = text
-            .lines()
-            .map(|line| {
-                if let Some(pos) = line.find(' ') {
-                    line[pos + 1..].chars().collect()
-                } else {
-                    line.chars().collect()
-                }
-            })
-            .collect();
-
-        Self {
-            matrix,
-            selection: MatrixSelection::new(),
-            char_size: Vec2::new(6.0, 10.0),
-            cursor_pos: None,
-            last_blink: Instant::now(),
-            cursor_visible: true,
-            clipboard: Vec::new(),
-            modified: false,
-            is_dragging_selection: false,
-            drag_start_pos: None,
-            drag_content: Vec::new(),
+/// Context-menu entries for tagging a region. Returns the chosen tag
+/// (`Some(None)` clears it) once an entry is clicked.
+fn region_tag_menu(ui: &mut egui::Ui, current: Option<RegionTag>) -> Option<Option<RegionTag>> {
+    let mut choice = None;
+    for tag in RegionTag::ALL {
+        let text = RichText::new(tag.as_str()).color(tag_color(tag)).monospace();
+        if ui.selectable_label(current == Some(tag), text).clicked() {
+            choice = Some(Some(tag));
         }
     }
+    ui.separator();
+    if ui.add_enabled(current.is_some(), egui::Button::new(RichText::new("Clear tag").monospace())).clicked() {
+        choice = Some(None);
+    }
+    if choice.is_some() {
+        ui.close_menu();
+    }
+    choice
+}
 
-    pub fn show(&mut self, ui: &mut egui::Ui) -> Response {
-        const TERM_TEAL: Color32 = Color32::from_rgb(26, 188, 156);
-        const TERM_TEAL_FADED: Color32 = Color32::from_rgba_premultiplied(26, 188, 156, 80);
-
-        let (response, painter) = ui.allocate_painter(
-            Vec2::new(
-                self.matrix.get(0).map_or(0.0, |row| row.len() as f32) * self.char_size.x,
-                self.matrix.len() as f32 * self.char_size.y,
-            ),
-            Sense::click_and_drag(),
-        );
-
-        let rect = response.rect;
-        let font_id = egui::FontId::monospace(9.0);
+// ============= LOGGING =============
+const LOG_PANEL_CAPACITY: usize = 500;
+const LOG_REPEAT_WINDOW: Duration = Duration::from_secs(2);
+
+/// In-app live view of log messages. Identical messages arriving in quick
+/// succession are collapsed into a single "repeated" line, kept up to date as
+/// they arrive, so that long extractions cannot flush the useful history out
+/// of the panel. Only the panel collapses them; the log file gets every one.
+pub struct LogBuffer {
+    pub entries: Vec<String>,
+    last_message: Option<String>,
+    last_time: Instant,
+    suppressed: usize,
+}
 
-        // Update cursor blink
-        let now = Instant::now();
-        if now.duration_since(self.last_blink).as_millis() > 530 {
-            self.cursor_visible = !self.cursor_visible;
-            self.last_blink = now;
-            ui.ctx().request_repaint();
-        }
-
-        // Handle mouse click for cursor position
-        if response.clicked() {
-            if let Some(pos) = response.hover_pos() {
-                let local_pos = pos - rect.min;
-                let row = (local_pos.y / self.char_size.y) as usize;
-                let col = (local_pos.x / self.char_size.x) as usize;
-                if row < self.matrix.len() && col < self.matrix.get(row).map_or(0, |r| r.len()) {
-                    self.cursor_pos = Some((row, col));
-                    self.cursor_visible = true;
-                    self.last_blink = Instant::now();
-                    // Clear selection when clicking to place cursor
-                    self.selection.start = None;
-                    self.selection.end = None;
-                }
-            }
+impl LogBuffer {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            last_message: None,
+            last_time: Instant::now(),
+            suppressed: 0,
         }
+    }
 
-        // Handle drag start
-        if response.drag_started() {
-            if let Some(pos) = response.hover_pos() {
-                let local_pos = pos - rect.min;
-                let row = (local_pos.y / self.char_size.y) as usize;
-                let col = (local_pos.x / self.char_size.x) as usize;
-
-                // Check if we're starting a drag on an existing selection
-                if self.selection.is_selected(row, col)
-                    && self.selection.start.is_some()
-                    && self.selection.end.is_some()
-                {
-                    // Start dragging the selection
-                    self.is_dragging_selection = true;
-                    self.drag_start_pos = Some((row, col));
-
-                    // Copy the selected content
-                    if let (Some(start), Some(end)) = (self.selection.start, self.selection.end) {
-                        let min_row = start.0.min(end.0).min(self.matrix.len().saturating_sub(1));
-                        let max_row = start.0.max(end.0).min(self.matrix.len().saturating_sub(1));
-                        let min_col = start.1.min(end.1);
-                        let max_col = start.1.max(end.1);
-
-                        self.drag_content.clear();
-                        for row in min_row..=max_row {
-                            if row < self.matrix.len() {
-                                let row_data = &self.matrix[row];
-                                let mut row_chars = Vec::new();
-                                let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-
-                                for col in min_col..=row_max_col {
-                                    if col < row_data.len() {
-                                        row_chars.push(row_data[col]);
-                                    }
-                                }
-                                self.drag_content.push(row_chars);
-                            }
-                        }
-
-                        // Clear the original selection
-                        for row in min_row..=max_row {
-                            if row < self.matrix.len() {
-                                let row_data = &mut self.matrix[row];
-                                let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-                                for col in min_col..=row_max_col {
-                                    if col < row_data.len() {
-                                        row_data[col] = ' ';
-                                    }
-                                }
-                            }
-                        }
-                        self.modified = true;
-                    }
-                } else {
-                    // Start a new selection
-                    self.selection.start = Some((row, col));
-                    self.selection.end = Some((row, col));
-                    self.cursor_pos = None;
-                    self.is_dragging_selection = false;
-                }
+    /// Records a message, or counts it on the "repeated" line below its
+    /// first appearance when it repeats within `LOG_REPEAT_WINDOW`.
+    pub fn push(&mut self, message: &str) {
+        let now = Instant::now();
+        let repeat = self.last_message.as_deref() == Some(message)
+            && now.duration_since(self.last_time) < LOG_REPEAT_WINDOW;
+        self.last_time = now;
+        if repeat {
+            self.suppressed += 1;
+            let line = format!("   ↳ repeated {} more times", self.suppressed);
+            match self.entries.last_mut() {
+                Some(last) if self.suppressed > 1 => *last = line,
+                _ => self.entries.push(line),
             }
+        } else {
+            self.suppressed = 0;
+            self.last_message = Some(message.to_string());
+            self.entries.push(message.to_string());
         }
 
-        // Handle dragging
-        if response.dragged() {
-            if let Some(pos) = response.hover_pos() {
-                let local_pos = pos - rect.min;
-                let row = (local_pos.y / self.char_size.y) as usize;
-                let col = (local_pos.x / self.char_size.x) as usize;
-
-                if self.is_dragging_selection {
-                    // Update visual feedback during drag
-                    // We'll show a preview at the current position
-                } else {
-                    // Continue selection
-                    self.selection.end = Some((row, col));
-                }
-            }
+        if self.entries.len() > LOG_PANEL_CAPACITY {
+            let excess = self.entries.len() - LOG_PANEL_CAPACITY;
+            self.entries.drain(..excess);
         }
+    }
 
-        // Handle drag release
-        if response.drag_released() {
-            if self.is_dragging_selection {
-                if let Some(pos) = response.hover_pos() {
-                    let local_pos = pos - rect.min;
-                    let row = (local_pos.y / self.char_size.y) as usize;
-                    let col = (local_pos.x / self.char_size.x) as usize;
-
-                    // Drop the content at the new position
-                    for (i, drag_row) in self.drag_content.iter().enumerate() {
-                        let target_row = row + i;
-                        if target_row < self.matrix.len() {
-                            for (j, &ch) in drag_row.iter().enumerate() {
-                                let target_col = col + j;
-                                if target_col < self.matrix[target_row].len() {
-                                    self.matrix[target_row][target_col] = ch;
-                                }
-                            }
-                        }
-                    }
-                    self.modified = true;
+    pub fn filtered<'a>(&'a self, filter: &str) -> impl Iterator<Item = &'a String> + 'a {
+        let filter = filter.to_lowercase();
+        self.entries
+            .iter()
+            .filter(move |entry| filter.is_empty() || entry.to_lowercase().contains(&filter))
+    }
+}
 
-                    // Clear selection after drop
-                    self.selection.start = None;
-                    self.selection.end = None;
-                }
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-                // Reset drag state
-                self.is_dragging_selection = false;
-                self.drag_start_pos = None;
-                self.drag_content.clear();
-            }
+/// Sets up tracing to stderr plus a daily-rotating file under `./logs`
+/// (override with `CHONKER5_LOG_DIR`). The returned guard must be kept alive
+/// for buffered file output to be flushed.
+fn init_tracing() -> Option<tracing_appender::non_blocking::WorkerGuard> {
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::{fmt, EnvFilter};
+
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let log_dir = std::env::var_os("CHONKER5_LOG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("./logs"));
+
+    match std::fs::create_dir_all(&log_dir) {
+        Ok(()) => {
+            let appender = tracing_appender::rolling::daily(&log_dir, "chonker5.log");
+            let (file_writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = tracing_subscriber::registry()
+                .with(env_filter)
+                .with(fmt::layer().with_writer(std::io::stderr))
+                .with(fmt::layer().with_ansi(false).with_writer(file_writer))
+                .try_init();
+            Some(guard)
+        }
+        Err(e) => {
+            let _ = tracing_subscriber::registry()
+                .with(env_filter)
+                .with(fmt::layer().with_writer(std::io::stderr))
+                .try_init();
+            tracing::warn!("Could not create log directory {}: {}", log_dir.display(), e);
+            None
         }
+    }
+}
 
-        // Draw background
-        painter.rect_filled(rect, 0.0, TERM_BG);
+// ============= PROJECT FILES =============
+pub const PROJECT_EXTENSION: &str = "chonker.json";
 
-        // Draw matrix with selection
-        for (row_idx, row) in self.matrix.iter().enumerate() {
-            for (col_idx, &ch) in row.iter().enumerate() {
-                let pos = rect.min
-                    + Vec2::new(
-                        col_idx as f32 * self.char_size.x,
-                        row_idx as f32 * self.char_size.y,
-                    );
+/// Saved session state. With `portable` set, document paths are stored
+/// relative to the project file so a project and its PDFs can be moved
+/// together.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFile {
+    #[serde(default)]
+    pub portable: bool,
+    pub pdf_path: PathBuf,
+    #[serde(default)]
+    pub current_page: usize,
+    #[serde(default = "default_zoom_level")]
+    pub zoom_level: f32,
+    #[serde(default)]
+    pub page_range: String,
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+}
 
-                // Highlight if selected
-                if self.selection.is_selected(row_idx, col_idx) {
-                    let selection_rect = Rect::from_min_size(
-                        pos - Vec2::new(0.0, self.char_size.y * 0.1),
-                        Vec2::new(self.char_size.x, self.char_size.y * 1.2),
-                    );
-                    painter.rect_filled(selection_rect, 2.0, TERM_TEAL_FADED);
-                }
+/// A named matrix position.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub page: usize,
+    pub row: usize,
+    pub col: usize,
+}
 
-                // Draw character
-                let char_color = if self.selection.is_selected(row_idx, col_idx) {
-                    Color32::BLACK
-                } else if ch == '·' {
-                    Color32::from_gray(80)
-                } else {
-                    TERM_FG
-                };
+fn default_zoom_level() -> f32 {
+    1.0
+}
 
-                painter.text(
-                    pos + Vec2::new(self.char_size.x * 0.45, self.char_size.y * 0.5),
-                    egui::Align2::CENTER_CENTER,
-                    ch.to_string(),
-                    font_id.clone(),
-                    char_color,
-                );
-            }
+impl ProjectFile {
+    pub fn save(&self, project_path: &Path) -> Result<()> {
+        let mut stored = self.clone();
+        if self.portable {
+            let base = project_base_dir(project_path);
+            stored.pdf_path = relative_path(&self.pdf_path, &base);
         }
+        std::fs::write(project_path, serde_json::to_string_pretty(&stored)?)?;
+        Ok(())
+    }
 
-        // Draw blinking cursor if visible
-        if let Some((cursor_row, cursor_col)) = self.cursor_pos {
-            if self.cursor_visible && cursor_row < self.matrix.len() {
-                let cursor_pos = rect.min
-                    + Vec2::new(
-                        cursor_col as f32 * self.char_size.x,
-                        cursor_row as f32 * self.char_size.y,
-                    );
-
-                painter.rect_filled(
-                    Rect::from_min_size(
-                        cursor_pos - Vec2::new(0.0, self.char_size.y * 0.1),
-                        Vec2::new(self.char_size.x * 0.8, self.char_size.y * 1.2),
-                    ),
-                    0.0,
-                    TERM_TEAL,
-                );
-
-                if cursor_col < self.matrix[cursor_row].len() {
-                    let ch = self.matrix[cursor_row][cursor_col];
-                    painter.text(
-                        cursor_pos + Vec2::new(self.char_size.x * 0.5, self.char_size.y * 0.5),
-                        egui::Align2::CENTER_CENTER,
-                        ch.to_string(),
-                        font_id.clone(),
-                        TERM_BG,
-                    );
-                }
-            }
+    /// Loads a project, resolving relative document paths against the
+    /// project file's directory.
+    pub fn load(project_path: &Path) -> Result<Self> {
+        let mut project: ProjectFile =
+            serde_json::from_str(&std::fs::read_to_string(project_path)?)?;
+        if project.pdf_path.is_relative() {
+            project.pdf_path = project_base_dir(project_path).join(&project.pdf_path);
         }
+        Ok(project)
+    }
+}
 
-        // Draw drag preview if we're dragging
-        if self.is_dragging_selection {
-            if let Some(hover_pos) = response.hover_pos() {
-                let local_pos = hover_pos - rect.min;
-                let preview_row = (local_pos.y / self.char_size.y) as usize;
-                let preview_col = (local_pos.x / self.char_size.x) as usize;
-
-                // Draw semi-transparent preview of dragged content
-                for (i, drag_row) in self.drag_content.iter().enumerate() {
-                    let target_row = preview_row + i;
-                    if target_row < self.matrix.len() {
-                        for (j, &ch) in drag_row.iter().enumerate() {
-                            let target_col = preview_col + j;
-                            if target_col < self.matrix.get(target_row).map_or(0, |r| r.len()) {
-                                let pos = rect.min
-                                    + Vec2::new(
-                                        target_col as f32 * self.char_size.x,
-                                        target_row as f32 * self.char_size.y,
-                                    );
-
-                                // Draw preview background
-                                let preview_rect = Rect::from_min_size(
-                                    pos - Vec2::new(0.0, self.char_size.y * 0.1),
-                                    Vec2::new(self.char_size.x, self.char_size.y * 1.2),
-                                );
-                                painter.rect_filled(
-                                    preview_rect,
-                                    2.0,
-                                    Color32::from_rgba_premultiplied(26, 188, 156, 60),
-                                );
-
-                                // Draw preview character
-                                painter.text(
-                                    pos + Vec2::new(
-                                        self.char_size.x * 0.45,
-                                        self.char_size.y * 0.5,
-                                    ),
-                                    egui::Align2::CENTER_CENTER,
-                                    ch.to_string(),
-                                    font_id.clone(),
-                                    Color32::from_rgba_premultiplied(255, 255, 255, 180),
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-        }
+fn project_base_dir(project_path: &Path) -> PathBuf {
+    let dir = project_path.parent().unwrap_or_else(|| Path::new("."));
+    dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())
+}
 
-        // Handle cut/copy/paste operations
-        ui.input(|i| {
-            if i.modifiers.command || i.modifiers.ctrl {
-                // Copy (Ctrl+C)
-                if i.key_pressed(egui::Key::C) {
-                    if let (Some(start), Some(end)) = (self.selection.start, self.selection.end) {
-                        let min_row = start.0.min(end.0).min(self.matrix.len().saturating_sub(1));
-                        let max_row = start.0.max(end.0).min(self.matrix.len().saturating_sub(1));
-                        let min_col = start.1.min(end.1);
-                        let max_col = start.1.max(end.1);
-
-                        // Limit clipboard size to prevent memory issues
-                        let selection_size = (max_row - min_row + 1) * (max_col - min_col + 1);
-                        if selection_size <= 100000 {
-                            // Copy the rectangular selection to clipboard
-                            self.clipboard.clear();
-                            self.clipboard.reserve(max_row - min_row + 1);
-
-                            for row in min_row..=max_row {
-                                if row < self.matrix.len() {
-                                    let row_data = &self.matrix[row];
-                                    let mut row_chars = Vec::with_capacity(max_col - min_col + 1);
-                                    let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-
-                                    for col in min_col..=row_max_col {
-                                        if col < row_data.len() {
-                                            row_chars.push(row_data[col]);
-                                        }
-                                    }
-                                    self.clipboard.push(row_chars);
-                                }
-                            }
+/// Expresses `path` relative to `base`, walking up with `..` where needed.
+/// Paths on different roots (e.g. other drives) are returned unchanged.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    use std::path::Component;
 
-                            // For small selections, also copy as text to system clipboard
-                            if selection_size < 10000 {
-                                let selected_text = self.selection.get_selected_text(&self.matrix);
-                                if !selected_text.is_empty()
-                                    && selected_text != "[Selection too large]"
-                                {
-                                    ui.output_mut(|o| o.copied_text = selected_text);
-                                }
-                            }
-                        }
-                    }
-                }
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
 
-                // Cut (Ctrl+X)
-                if i.key_pressed(egui::Key::X) {
-                    if let (Some(start), Some(end)) = (self.selection.start, self.selection.end) {
-                        let min_row = start.0.min(end.0).min(self.matrix.len().saturating_sub(1));
-                        let max_row = start.0.max(end.0).min(self.matrix.len().saturating_sub(1));
-                        let min_col = start.1.min(end.1);
-                        let max_col = start.1.max(end.1);
-
-                        // Limit clipboard size to prevent memory issues
-                        let selection_size = (max_row - min_row + 1) * (max_col - min_col + 1);
-                        if selection_size <= 100000 {
-                            // Copy to clipboard first
-                            self.clipboard.clear();
-                            self.clipboard.reserve(max_row - min_row + 1);
-
-                            for row in min_row..=max_row {
-                                if row < self.matrix.len() {
-                                    let row_data = &self.matrix[row];
-                                    let mut row_chars = Vec::with_capacity(max_col - min_col + 1);
-                                    let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-
-                                    for col in min_col..=row_max_col {
-                                        if col < row_data.len() {
-                                            row_chars.push(row_data[col]);
-                                        }
-                                    }
-                                    self.clipboard.push(row_chars);
-                                }
-                            }
+    if path_components.first() != base_components.first() {
+        return path;
+    }
 
-                            // Clear the selected area
-                            for row in min_row..=max_row {
-                                if row < self.matrix.len() {
-                                    let row_data = &mut self.matrix[row];
-                                    let row_max_col = max_col.min(row_data.len().saturating_sub(1));
-                                    for col in min_col..=row_max_col {
-                                        if col < row_data.len() {
-                                            row_data[col] = ' ';
-                                        }
-                                    }
-                                }
-                            }
-                            self.modified = true;
+    let common = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
 
-                            // For small selections, also copy as text to system clipboard
-                            if selection_size < 10000 {
-                                // Note: We can't get selected text after clearing, so we'd need to build it from clipboard
-                                // For now, let's skip system clipboard for cut operation on large selections
-                            }
-                        }
-                    }
-                }
+    let mut relative = PathBuf::new();
+    for _ in common..base_components.len() {
+        relative.push("..");
+    }
+    for component in &path_components[common..] {
+        relative.push(component.as_os_str());
+    }
+    relative
+}
 
-                // Paste (Ctrl+V)
-                if i.key_pressed(egui::Key::V) {
-                    // Determine paste position - use cursor position or selection start
-                    let paste_pos = if let Some(cursor_pos) = self.cursor_pos {
-                        cursor_pos
-                    } else if let Some(start) = self.selection.start {
-                        start
-                    } else {
-                        (0, 0) // Default to top-left if no cursor or selection
-                    };
+// ============= REVIEW NAVIGATION =============
+/// Cached pages whose recorded fingerprint differs from what
+/// `fingerprint(page)` gives for the current settings, in page order. Pages
+/// without one (ferrules, the layout model) never go stale.
+pub fn stale_pages(cache: &PageCache, fingerprint: impl Fn(usize) -> u64) -> Vec<usize> {
+    let mut pages: Vec<usize> = cache
+        .iter()
+        .filter(|(&page, matrix)| matrix.settings_fingerprint != 0 && matrix.settings_fingerprint != fingerprint(page))
+        .map(|(&page, _)| page)
+        .collect();
+    pages.sort_unstable();
+    pages
+}
 
-                    if !self.clipboard.is_empty() {
-                        // Paste the rectangular clipboard
-                        for (i, clipboard_row) in self.clipboard.iter().enumerate() {
-                            let target_row = paste_pos.0 + i;
-                            if target_row < self.matrix.len() {
-                                for (j, &ch) in clipboard_row.iter().enumerate() {
-                                    let target_col = paste_pos.1 + j;
-                                    if target_col < self.matrix[target_row].len() {
-                                        self.matrix[target_row][target_col] = ch;
-                                    }
-                                }
-                            }
-                        }
+/// `(page, region index)` of every region below `threshold` in the cached
+/// pages, ordered by page and then top-to-bottom, left-to-right.
+pub fn low_confidence_targets(cache: &PageCache, threshold: f32) -> Vec<(usize, usize)> {
+    let mut targets: Vec<(usize, usize, usize, usize)> = cache
+        .iter()
+        .flat_map(|(&page, matrix)| {
+            matrix
+                .text_regions
+                .iter()
+                .enumerate()
+                .filter(|(_, region)| region.confidence < threshold)
+                .map(move |(idx, region)| (page, region.bbox.y, region.bbox.x, idx))
+        })
+        .collect();
+    targets.sort_unstable();
+    targets
+        .into_iter()
+        .map(|(page, _, _, idx)| (page, idx))
+        .collect()
+}
 
-                        // Clear selection after paste
-                        self.selection.start = None;
-                        self.selection.end = None;
-                        self.modified = true;
-                    }
-                }
-            }
+/// Moves one step forward (`step > 0`) or backward through `targets`,
+/// wrapping at either end.
+pub fn step_target(
+    targets: &[(usize, usize)],
+    current: Option<(usize, usize)>,
+    step: i32,
+) -> Option<(usize, usize)> {
+    if targets.is_empty() {
+        return None;
+    }
+    let position = current.and_then(|c| targets.iter().position(|&t| t == c));
+    let next = match (position, step > 0) {
+        (Some(pos), true) => (pos + 1) % targets.len(),
+        (Some(pos), false) => (pos + targets.len() - 1) % targets.len(),
+        (None, true) => 0,
+        (None, false) => targets.len() - 1,
+    };
+    Some(targets[next])
+}
 
-            // Handle character input for editing
-            if let Some((cursor_row, cursor_col)) = self.cursor_pos {
-                for event in &i.events {
-                    if let egui::Event::Text(text) = event {
-                        for ch in text.chars() {
-                            if cursor_row < self.matrix.len()
-                                && cursor_col < self.matrix[cursor_row].len()
-                            {
-                                self.matrix[cursor_row][cursor_col] = ch;
-                                self.modified = true;
-                                // Move cursor right
-                                if cursor_col + 1 < self.matrix[cursor_row].len() {
-                                    self.cursor_pos = Some((cursor_row, cursor_col + 1));
-                                }
-                                break; // Only process first character
-                            }
-                        }
-                    }
-                }
-            }
-        });
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionEdit {
+    Merge,
+    Split(RegionSplit),
+}
 
-        response
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionSort {
+    Id,
+    Confidence,
+    Position,
 }
 
-// ============= CHARACTER MATRIX ENGINE =============
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CharacterMatrix {
-    pub width: usize,
-    pub height: usize,
-    pub matrix: Vec<Vec<char>>,
-    pub text_regions: Vec<TextRegion>,
-    pub original_text: Vec<String>,
-    pub char_width: f32,
-    pub char_height: f32,
-}
-
-impl CharacterMatrix {
-    pub fn new(width: usize, height: usize) -> Self {
-        let matrix = vec![vec![' '; width]; height];
-        Self {
-            width,
-            height,
-            matrix,
-            text_regions: Vec::new(),
-            original_text: Vec::new(),
-            char_width: 7.2,
-            char_height: 12.0,
+impl RegionSort {
+    pub const ALL: [RegionSort; 3] = [RegionSort::Id, RegionSort::Confidence, RegionSort::Position];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RegionSort::Id => "Id",
+            RegionSort::Confidence => "Confidence",
+            RegionSort::Position => "Reading order",
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TextRegion {
-    pub bbox: CharBBox,
-    pub confidence: f32,
-    pub text_content: String,
-    pub region_id: usize,
+/// Indices of the regions at or above `min_confidence`, ordered for the
+/// region list. Confidence sorts lowest first so review starts with the
+/// doubtful ones.
+pub fn region_list_order(regions: &[TextRegion], sort: RegionSort, min_confidence: f32) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..regions.len())
+        .filter(|&i| regions[i].confidence >= min_confidence)
+        .collect();
+    match sort {
+        RegionSort::Id => indices.sort_by_key(|&i| regions[i].region_id),
+        RegionSort::Confidence => indices.sort_by(|&a, &b| {
+            regions[a]
+                .confidence
+                .partial_cmp(&regions[b].confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        }),
+        RegionSort::Position => indices.sort_by_key(|&i| (regions[i].bbox.y, regions[i].bbox.x)),
+    }
+    indices
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CharBBox {
-    pub x: usize,
-    pub y: usize,
-    pub width: usize,
-    pub height: usize,
+// ============= REGION EDITING =============
+/// Where to cut a region, as an offset from its top row or left column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionSplit {
+    Row(usize),
+    Column(usize),
 }
 
-impl CharBBox {
-    pub fn contains(&self, x: usize, y: usize) -> bool {
-        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+/// Merges the regions at `indices` into the first of them, which keeps its
+/// id, label and tag. Returns the merged region's new index.
+pub fn merge_regions(char_matrix: &mut CharacterMatrix, indices: &[usize]) -> Result<usize> {
+    let mut indices: Vec<usize> = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+    if indices.len() < 2 {
+        return Err(anyhow::anyhow!("Select at least two regions to merge"));
+    }
+    if let Some(&bad) = indices.iter().find(|&&i| i >= char_matrix.text_regions.len()) {
+        return Err(anyhow::anyhow!("No region at index {}", bad));
     }
 
-    pub fn area(&self) -> usize {
-        self.width * self.height
+    let regions = &char_matrix.text_regions;
+    let x0 = indices.iter().map(|&i| regions[i].bbox.x).min().unwrap_or(0);
+    let y0 = indices.iter().map(|&i| regions[i].bbox.y).min().unwrap_or(0);
+    let x1 = indices.iter().map(|&i| regions[i].bbox.x + regions[i].bbox.width).max().unwrap_or(0);
+    let y1 = indices.iter().map(|&i| regions[i].bbox.y + regions[i].bbox.height).max().unwrap_or(0);
+    let bbox = CharBBox {
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    };
+    let confidence = indices
+        .iter()
+        .map(|&i| regions[i].confidence)
+        .fold(f32::INFINITY, f32::min);
+    let is_italic = indices.iter().all(|&i| regions[i].is_italic);
+
+    let keep = indices[0];
+    let text_content = region_text(&char_matrix.rows(), &bbox);
+    let merged = &mut char_matrix.text_regions[keep];
+    merged.bbox = bbox;
+    merged.confidence = confidence;
+    merged.is_italic = is_italic;
+    merged.text_content = text_content;
+    for &i in indices[1..].iter().rev() {
+        char_matrix.text_regions.remove(i);
     }
+    Ok(keep)
 }
 
-#[derive(Debug, Clone)]
-struct PreciseTextObject {
-    text: String,
-    bbox: PDFBBox,
-    font_size: f32,
-}
+/// Cuts a region in two. The first part keeps the id and label; the second
+/// gets the next free id and inherits the tag. Returns the second part's index.
+pub fn split_region(char_matrix: &mut CharacterMatrix, index: usize, split: RegionSplit) -> Result<usize> {
+    let next_id = char_matrix
+        .text_regions
+        .iter()
+        .map(|r| r.region_id + 1)
+        .max()
+        .unwrap_or(0);
+    let region = char_matrix
+        .text_regions
+        .get(index)
+        .ok_or_else(|| anyhow::anyhow!("No region at index {}", index))?;
+
+    let (first_bbox, second_bbox) = match split {
+        RegionSplit::Row(offset) if offset > 0 && offset < region.bbox.height => (
+            CharBBox { height: offset, ..region.bbox.clone() },
+            CharBBox {
+                y: region.bbox.y + offset,
+                height: region.bbox.height - offset,
+                ..region.bbox.clone()
+            },
+        ),
+        RegionSplit::Column(offset) if offset > 0 && offset < region.bbox.width => (
+            CharBBox { width: offset, ..region.bbox.clone() },
+            CharBBox {
+                x: region.bbox.x + offset,
+                width: region.bbox.width - offset,
+                ..region.bbox.clone()
+            },
+        ),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Split point must fall inside {} ({}x{})",
+                region.display_name(),
+                region.bbox.width,
+                region.bbox.height
+            ))
+        }
+    };
 
-#[derive(Debug, Clone)]
-struct PDFBBox {
-    x0: f32,
-    y0: f32,
-    x1: f32,
-    y1: f32,
-}
+    let mut second = region.clone();
+    second.region_id = next_id;
+    second.label = None;
+    second.text_content = region_text(&char_matrix.rows(), &second_bbox);
+    second.bbox = second_bbox;
 
-pub struct CharacterMatrixEngine {
-    pub char_width: f32,
-    pub char_height: f32,
-}
+    let first_text = region_text(&char_matrix.rows(), &first_bbox);
+    let first = &mut char_matrix.text_regions[index];
+    first.text_content = first_text;
+    first.bbox = first_bbox;
 
-impl CharacterMatrixEngine {
-    pub fn new() -> Self {
-        Self {
-            char_width: 6.0,
-            char_height: 12.0,
-        }
-    }
+    char_matrix.text_regions.insert(index + 1, second);
+    Ok(index + 1)
+}
 
-    pub fn new_optimized(pdf_path: &Path) -> Result<Self> {
-        let mut engine = Self::new();
-        let (char_width, char_height) = engine.find_optimal_character_dimensions(pdf_path)?;
-        engine.char_width = char_width;
-        engine.char_height = char_height;
-        Ok(engine)
+/// Registers Chonker 5 as a handler for `application/pdf` via a freedesktop
+/// `.desktop` entry. Other platforms need an application bundle instead.
+#[cfg(target_os = "linux")]
+fn register_pdf_handler() -> Result<String> {
+    let exe = std::env::current_exe()?;
+    let home = std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME is not set"))?;
+    let applications = PathBuf::from(home).join(".local/share/applications");
+    std::fs::create_dir_all(&applications)?;
+
+    let desktop_path = applications.join("chonker5.desktop");
+    std::fs::write(
+        &desktop_path,
+        format!(
+            "[Desktop Entry]\nType=Application\nName=Chonker 5\nComment=Character Matrix PDF Engine\nExec=\"{}\" %f\nMimeType=application/pdf;\nCategories=Office;Viewer;\nTerminal=false\n",
+            exe.display()
+        ),
+    )?;
+
+    let status = Command::new("xdg-mime")
+        .args(["default", "chonker5.desktop", "application/pdf"])
+        .status();
+    match status {
+        Ok(status) if status.success() => Ok(format!(
+            "Registered {} as the default PDF handler",
+            desktop_path.display()
+        )),
+        _ => Ok(format!(
+            "Wrote {} (run `xdg-mime default chonker5.desktop application/pdf` to make it the default)",
+            desktop_path.display()
+        )),
     }
+}
 
-    pub fn find_optimal_character_dimensions(&self, pdf_path: &Path) -> Result<(f32, f32)> {
-        let pdfium = Pdfium::new(
-            Pdfium::bind_to_system_library()
-                .or_else(|_| Pdfium::bind_to_library("./lib/libpdfium.dylib"))
-                .or_else(|_| Pdfium::bind_to_library("/usr/local/lib/libpdfium.dylib"))
-                .map_err(|e| anyhow::anyhow!("Failed to bind pdfium: {}", e))?,
-        );
+#[cfg(not(target_os = "linux"))]
+fn register_pdf_handler() -> Result<String> {
+    Err(anyhow::anyhow!(
+        "Handler registration is only automated on Linux; on macOS and Windows use \"Open With\" on a PDF and choose the chonker5 binary"
+    ))
+}
 
-        let document = pdfium.load_pdf_from_file(pdf_path, None)?;
-        if document.pages().is_empty() {
-            return Ok((self.char_width, self.char_height));
-        }
+// ============= HELP =============
+/// Offline documentation of the extraction heuristics, bundled into the
+/// binary from `docs/heuristics/`.
+pub struct HelpTopic {
+    pub title: &'static str,
+    pub body: &'static str,
+}
 
-        let page = document.pages().first()?;
-        let page_text = page.text()?;
+pub const HELP_TOPICS: &[HelpTopic] = &[
+    HelpTopic {
+        title: "Extraction backends",
+        body: include_str!("docs/heuristics/extraction-backends.md"),
+    },
+    HelpTopic {
+        title: "Character cell size",
+        body: include_str!("docs/heuristics/character-size.md"),
+    },
+    HelpTopic {
+        title: "Width averaging",
+        body: include_str!("docs/heuristics/width-averaging.md"),
+    },
+    HelpTopic {
+        title: "Region merging",
+        body: include_str!("docs/heuristics/region-merging.md"),
+    },
+    HelpTopic {
+        title: "Italic detection",
+        body: include_str!("docs/heuristics/italic-detection.md"),
+    },
+    HelpTopic {
+        title: "Region confidence",
+        body: include_str!("docs/heuristics/confidence.md"),
+    },
+    HelpTopic {
+        title: "Fixed-width detection",
+        body: include_str!("docs/heuristics/monospace-detection.md"),
+    },
+    HelpTopic {
+        title: "Extraction limits",
+        body: include_str!("docs/heuristics/extraction-limits.md"),
+    },
+    HelpTopic {
+        title: "Columns and collisions",
+        body: include_str!("docs/heuristics/columns-and-collisions.md"),
+    },
+];
+
+fn help_topic_index(title: &str) -> usize {
+    HELP_TOPICS.iter().position(|topic| topic.title == title).unwrap_or(0)
+}
 
-        let mut font_sizes = Vec::new();
-        for char_obj in page_text.chars().iter() {
-            let font_size = char_obj.unscaled_font_size().value;
-            if font_size > 0.0 {
-                font_sizes.push(font_size);
-            }
-        }
+/// Where a page diagnostic's setting lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpSetting {
+    /// `backend` in `chonker.toml`.
+    Backend,
+    /// The per-page Char w/h override under the raw-text grid.
+    CharSize,
+    /// The Monospace / Proportional labels under the raw-text grid.
+    MonospaceLabel,
+    /// The Limits row under the raw-text grid.
+    ExtractionLimits,
+    /// The region list panel and its confidence filter.
+    RegionPanel,
+    /// The region list's Segment: choice.
+    Segmentation,
+}
 
-        if font_sizes.is_empty() {
-            return Ok((self.char_width, self.char_height));
+impl HelpSetting {
+    pub fn label(self) -> &'static str {
+        match self {
+            HelpSetting::Backend => "backend (chonker.toml)",
+            HelpSetting::CharSize => "Char size override",
+            HelpSetting::MonospaceLabel => "Monospace label",
+            HelpSetting::ExtractionLimits => "Extraction limits",
+            HelpSetting::RegionPanel => "Region list",
+            HelpSetting::Segmentation => "Segmentation",
         }
+    }
+}
 
-        font_sizes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-        let modal_font_size = font_sizes[font_sizes.len() / 2];
+/// One heuristic that fired on a page, with the topic explaining it and the
+/// setting that tunes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageDiagnostic {
+    pub finding: String,
+    pub topic: usize,
+    pub setting: HelpSetting,
+}
 
-        let char_width = (modal_font_size * 0.6).max(4.0);
-        let char_height = (modal_font_size * 1.2).max(8.0);
+/// Explains how `matrix` came out the way it did: the backend, where the
+/// cell size came from, the fixed-width verdict, the columns found and
+/// glyphs that collided, where the regions came from and which are hidden,
+/// and every extraction warning.
+pub fn page_diagnostics(
+    matrix: &CharacterMatrix,
+    has_override: bool,
+    segmentation: Segmentation,
+    min_confidence: f32,
+) -> Vec<PageDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |finding: String, title: &str, setting: HelpSetting| {
+        diagnostics.push(PageDiagnostic {
+            finding,
+            topic: help_topic_index(title),
+            setting,
+        });
+    };
 
-        Ok((char_width, char_height))
+    let backend = matrix.backend.map_or("an unknown backend", ExtractionBackend::label);
+    push(format!("Extracted with {}", backend), "Extraction backends", HelpSetting::Backend);
+
+    let fixed_pitch = matrix.monospace.as_ref().filter(|m| m.is_monospace());
+    let cell_source = match (matrix.backend, matrix.layout.cell_font_size) {
+        (Some(ExtractionBackend::Mutool), _) => "mutool's own text layout".to_string(),
+        _ if has_override => "this page's override".to_string(),
+        _ if fixed_pitch.is_some() => "the detected fixed pitch".to_string(),
+        (Some(ExtractionBackend::MutoolStext), Some(size)) => format!("the median font size, {:.1}pt, and glyph width", size),
+        (_, Some(size)) => format!("the modal font size, {:.0}pt", size),
+        _ => "the configured char_size".to_string(),
+    };
+    push(
+        format!("Cell {:.1}x{:.1}pt from {}", matrix.char_width, matrix.char_height, cell_source),
+        "Character cell size",
+        HelpSetting::CharSize,
+    );
+
+    if let Some(detection) = &matrix.monospace {
+        push(
+            format!(
+                "Fixed-width {:.0}% (pitch {:.1}pt): treated as {}",
+                detection.probability * 100.0,
+                detection.features.pitch,
+                if detection.is_monospace() { "monospace" } else { "proportional" }
+            ),
+            "Fixed-width detection",
+            HelpSetting::MonospaceLabel,
+        );
     }
 
-    fn extract_text_objects_for_page(
-        &self,
-        pdf_path: &PathBuf,
-        target_page_index: usize,
-    ) -> Result<Vec<PreciseTextObject>> {
-        let pdfium = Pdfium::new(
-            Pdfium::bind_to_system_library()
-                .or_else(|_| Pdfium::bind_to_library("./lib/libpdfium.dylib"))
-                .or_else(|_| Pdfium::bind_to_library("/usr/local/lib/libpdfium.dylib"))
-                .map_err(|e| anyhow::anyhow!("Failed to bind pdfium: {}", e))?,
+    let columns = &matrix.layout.columns;
+    push(
+        match columns.len() {
+            0 => "No text columns: no cell starts text on three or more rows".to_string(),
+            n => format!(
+                "{} text column(s) starting at cell {}",
+                n,
+                columns.iter().map(|col| col.to_string()).collect::<Vec<_>>().join(", ")
+            ),
+        },
+        "Columns and collisions",
+        HelpSetting::CharSize,
+    );
+    if matrix.layout.collisions > 0 {
+        let resolution = match matrix.backend {
+            Some(ExtractionBackend::MutoolStext) if fixed_pitch.is_none() || has_override => "moved a cell right",
+            _ => "overwritten",
+        };
+        push(
+            format!("{} glyph(s) landed on an occupied cell and were {}", matrix.layout.collisions, resolution),
+            "Columns and collisions",
+            HelpSetting::CharSize,
         );
+    }
 
-        let document = pdfium.load_pdf_from_file(pdf_path, None)?;
-        let mut text_objects = Vec::new();
+    let regions = matrix.text_regions.len();
+    let region_source = match (segmentation, matrix.backend) {
+        (Segmentation::XyCut, _) => format!("{} region(s) from XY-cut blocks", regions),
+        (_, Some(ExtractionBackend::Pdfium)) => format!("{} region(s) merged from glyphs at most two cells apart", regions),
+        (_, Some(ExtractionBackend::MutoolStext)) => format!("{} region(s) from mutool's text blocks", regions),
+        (_, Some(ExtractionBackend::Mutool)) => "No regions: mutool text has none".to_string(),
+        (_, None) => format!("{} region(s) from a layout model", regions),
+    };
+    push(region_source, "Region merging", HelpSetting::Segmentation);
 
-        if target_page_index >= document.pages().len() as usize {
-            return Err(anyhow::anyhow!(
-                "Page index {} out of bounds",
-                target_page_index
-            ));
+    if !matrix.text_regions.is_empty() {
+        let italic = matrix.text_regions.iter().filter(|r| r.is_italic).count();
+        if italic > 0 {
+            push(format!("{} italic region(s) kept apart from upright text", italic), "Italic detection", HelpSetting::RegionPanel);
         }
+        let hidden = matrix.text_regions.iter().filter(|r| r.confidence < min_confidence).count();
+        if hidden > 0 {
+            push(
+                format!("{} region(s) hidden below {:.0}% confidence", hidden, min_confidence * 100.0),
+                "Region confidence",
+                HelpSetting::RegionPanel,
+            );
+        }
+    }
 
-        let page = document.pages().get(target_page_index as u16)?;
-        let text_page = page.text()?;
-        let page_height = page.height().value;
-
-        let text_segments = text_page.segments();
-        for segment in text_segments.iter() {
-            let bounds = segment.bounds();
-            let text = segment.text();
-
-            if !text.trim().is_empty() {
-                let segment_width = bounds.right().value - bounds.left().value;
-                let char_count = text.chars().count() as f32;
-                let avg_char_width = if char_count > 0.0 {
-                    segment_width / char_count
-                } else {
-                    7.2
-                };
+    for warning in &matrix.warnings {
+        let (title, setting) = match warning {
+            ExtractionWarning::AveragedCharWidths { .. } => ("Width averaging", HelpSetting::CharSize),
+            ExtractionWarning::Truncated { .. } => ("Extraction limits", HelpSetting::ExtractionLimits),
+        };
+        push(warning.to_string(), title, setting);
+    }
+    diagnostics
+}
 
-                let font_size = (bounds.top().value - bounds.bottom().value) * 0.8;
+/// Indices of topics whose title or body mention every word of `query`.
+pub fn search_help(query: &str) -> Vec<usize> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    HELP_TOPICS
+        .iter()
+        .enumerate()
+        .filter(|(_, topic)| {
+            let haystack = format!("{}\n{}", topic.title, topic.body).to_lowercase();
+            words.iter().all(|w| haystack.contains(w.as_str()))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
 
-                let mut current_x = bounds.left().value;
-                for ch in text.chars() {
-                    let y_from_top = page_height - bounds.top().value;
-                    let char_width = if ch == ' ' {
-                        avg_char_width * 0.5
-                    } else {
-                        avg_char_width
-                    };
+// ============= PDF SEARCH =============
+/// One occurrence of the query in the PDF's own text layer.
+#[derive(Debug, Clone)]
+pub struct PdfSearchHit {
+    pub page: usize,
+    /// `[x0, y0, x1, y1]` as fractions of the page, top-left origin.
+    pub rects: Vec<[f32; 4]>,
+    /// The text line the hit sits on.
+    pub context: String,
+}
 
-                    text_objects.push(PreciseTextObject {
-                        text: ch.to_string(),
-                        bbox: PDFBBox {
-                            x0: current_x,
-                            y0: y_from_top,
-                            x1: current_x + char_width,
-                            y1: y_from_top + font_size,
-                        },
-                        font_size,
-                    });
+/// Converts a PDF-space rectangle (points, bottom-left origin) to page
+/// fractions with a top-left origin, as used for drawing over the image.
+fn page_fraction_rect(left: f32, bottom: f32, right: f32, top: f32, page_width: f32, page_height: f32) -> [f32; 4] {
+    [
+        left / page_width,
+        (page_height - top) / page_height,
+        right / page_width,
+        (page_height - bottom) / page_height,
+    ]
+}
 
-                    current_x += char_width;
-                }
-            }
+/// Searches the text layer of `pages` with pdfium. This looks at the PDF
+/// itself, not the (possibly edited) matrix.
+fn search_pdf_text(pdf_path: &Path, pages: &[usize], query: &str) -> Result<Vec<PdfSearchHit>> {
+    let pdfium = bind_pdfium()?;
+    let document = pdfium.load_pdf_from_file(pdf_path, None)?;
+    let options = PdfSearchOptions::new();
+    let mut hits = Vec::new();
+
+    for &page_index in pages {
+        let page = document.pages().get(page_index as u16)?;
+        let page_width = page.width().value;
+        let page_height = page.height().value;
+        let text_page = page.text()?;
+        let search = text_page.search(query, &options)?;
+
+        for segments in search.iter(PdfSearchDirection::SearchForward) {
+            let rects: Vec<[f32; 4]> = segments
+                .iter()
+                .map(|segment| {
+                    let b = segment.bounds();
+                    page_fraction_rect(
+                        b.left().value,
+                        b.bottom().value,
+                        b.right().value,
+                        b.top().value,
+                        page_width,
+                        page_height,
+                    )
+                })
+                .collect();
+            let Some(first) = segments.iter().next() else {
+                continue;
+            };
+            let line = PdfRect::new(
+                first.bounds().bottom(),
+                PdfPoints::new(0.0),
+                first.bounds().top(),
+                PdfPoints::new(page_width),
+            );
+            let context = text_page
+                .inside_rect(line)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            hits.push(PdfSearchHit {
+                page: page_index,
+                rects,
+                context,
+            });
         }
-
-        Ok(text_objects)
     }
+    Ok(hits)
+}
 
-    fn extract_text_objects_with_precise_coords(
-        &self,
-        pdf_path: &PathBuf,
-    ) -> Result<Vec<PreciseTextObject>> {
-        let pdfium = Pdfium::new(
-            Pdfium::bind_to_system_library()
-                .or_else(|_| Pdfium::bind_to_library("./lib/libpdfium.dylib"))
-                .or_else(|_| Pdfium::bind_to_library("/usr/local/lib/libpdfium.dylib"))
-                .map_err(|e| anyhow::anyhow!("Failed to bind pdfium: {}", e))?,
-        );
+// ============= PAGE RENDERING =============
+struct RenderRequest {
+    pdf_path: PathBuf,
+    page: usize,
+    dark_mode: bool,
+    dpi: f32,
+}
 
-        let document = pdfium.load_pdf_from_file(pdf_path, None)?;
-        let mut text_objects = Vec::new();
+/// Rasterises one page, inverting it for dark mode.
+fn render_page_image(request: &RenderRequest) -> Result<egui::ColorImage, ChonkerError> {
+    let image_data = render_page_png(&request.pdf_path, request.page, request.dpi)?;
+    let mut image = image::load_from_memory(&image_data)
+        .map_err(|e| ChonkerError::RenderFailed(format!("could not decode the image: {}", e)))?;
 
-        for (page_index, page) in document.pages().iter().enumerate() {
-            let text_page = page.text()?;
-            let page_height = page.height().value;
-            let text_segments = text_page.segments();
+    if request.dark_mode {
+        let mut rgba_image = image.to_rgba8();
+        image::imageops::colorops::invert(&mut rgba_image);
+        image = image::DynamicImage::ImageRgba8(rgba_image);
+    }
 
-            for segment in text_segments.iter() {
-                let bounds = segment.bounds();
-                let text = segment.text();
+    let size = [image.width() as _, image.height() as _];
+    let image_buffer = image.to_rgba8();
+    let pixels = image_buffer.as_flat_samples();
+    Ok(egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice()))
+}
 
-                if !text.trim().is_empty() {
-                    let segment_width = bounds.right().value - bounds.left().value;
-                    let char_count = text.chars().count() as f32;
-                    let avg_char_width = if char_count > 0.0 {
-                        segment_width / char_count
-                    } else {
-                        7.2
-                    };
+// ============= SHORTCUTS =============
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShortcutScope {
+    Global,
+    PdfView,
+    MatrixView,
+}
 
-                    let font_size = (bounds.top().value - bounds.bottom().value) * 0.8;
-                    let mut current_x = bounds.left().value;
+struct Shortcut {
+    keys: &'static str,
+    action: &'static str,
+    scope: ShortcutScope,
+}
 
-                    for ch in text.chars() {
-                        let y_from_top = page_height - bounds.top().value;
-                        let char_width = if ch == ' ' {
-                            avg_char_width * 0.5
-                        } else {
-                            avg_char_width
-                        };
-
-                        text_objects.push(PreciseTextObject {
-                            text: ch.to_string(),
-                            bbox: PDFBBox {
-                                x0: current_x,
-                                y0: y_from_top,
-                                x1: current_x + char_width,
-                                y1: y_from_top + (bounds.top().value - bounds.bottom().value),
-                            },
-                            font_size,
-                        });
-
-                        current_x += char_width;
-                    }
-                }
-            }
-        }
+/// Ctrl shortcuts `chonker.toml`'s `[keymap]` can rebind, with their default
+/// chords. Action names shared with the TUI mean the same there.
+const KEY_ACTIONS: &[(&str, &str)] = &[
+    ("open", "Ctrl+O"),
+    ("save", "Ctrl+S"),
+    ("close_tab", "Ctrl+W"),
+    ("next_tab", "Ctrl+PageDown"),
+    ("prev_tab", "Ctrl+PageUp"),
+    ("toggle_log", "Ctrl+L"),
+    ("toggle_region_list", "Ctrl+R"),
+    ("history", "Ctrl+H"),
+    ("bookmarks", "Ctrl+J"),
+    ("search", "Ctrl+F"),
+    ("dark_page", "Ctrl+D"),
+    ("toggle_regions", "Ctrl+B"),
+    ("goto", "Ctrl+G"),
+];
+
+/// Keys a `[keymap]` chord can name.
+const REMAPPABLE_KEYS: &[egui::Key] = {
+    use egui::Key::*;
+    &[
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Num0, Num1, Num2, Num3, Num4,
+        Num5, Num6, Num7, Num8, Num9, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, PageUp, PageDown, Home, End,
+        Insert, Delete, Backspace, Tab, Escape, Enter, Space, ArrowUp, ArrowDown, ArrowLeft, ArrowRight, Minus,
+        PlusEquals,
+    ]
+};
+
+/// The key and modifiers of the default chord `key` is bound to in
+/// `chonker.toml`; `None` for a default chord that was bound elsewhere.
+fn remap_key(keymap: &Keymap, key: egui::Key, modifiers: egui::Modifiers) -> Option<(egui::Key, egui::Modifiers)> {
+    let ctrl = modifiers.command || modifiers.ctrl;
+    let chord = keymap.remap(KeyChord::new(ctrl, modifiers.alt, modifiers.shift, key.name()))?;
+    let remapped = REMAPPABLE_KEYS
+        .iter()
+        .copied()
+        .find(|candidate| KeyChord::new(false, false, false, candidate.name()).key == chord.key)
+        .unwrap_or(key);
+    let modifiers = egui::Modifiers { alt: chord.alt, ctrl: chord.ctrl, shift: chord.shift, mac_cmd: false, command: chord.ctrl };
+    Some((remapped, modifiers))
+}
 
-        Ok(text_objects)
-    }
+/// Every keyboard and mouse binding, listed by the `?` overlay. Keep in sync
+/// with the input handlers in `update()` and `MatrixGrid::show`.
+const SHORTCUTS: &[Shortcut] = &[
+    Shortcut { keys: "Ctrl+O", action: "Open PDF", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+S", action: "Save edited matrix", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+W", action: "Close tab", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+PgDn / PgUp", action: "Next / previous tab", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+L", action: "Toggle log panel", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+R", action: "Toggle region list", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+H", action: "Matrix history", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+J", action: "Bookmarks", scope: ShortcutScope::Global },
+    Shortcut { keys: "Tab", action: "Switch pane", scope: ShortcutScope::Global },
+    Shortcut { keys: "F1", action: "Extraction help", scope: ShortcutScope::Global },
+    Shortcut { keys: "F8 / Shift+F8", action: "Next / previous low-confidence region", scope: ShortcutScope::Global },
+    Shortcut { keys: "?", action: "Toggle this overlay", scope: ShortcutScope::Global },
+    Shortcut { keys: "Ctrl+F", action: "Find in PDF text", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Ctrl+D", action: "Toggle dark page rendering", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Ctrl+B", action: "Toggle region overlays", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Wheel", action: "Previous / next page", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Pinch / Ctrl+Wheel", action: "Zoom", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Click", action: "Move matrix cursor to this spot", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Right-click", action: "Tag region", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Drag ([A] on)", action: "Re-extract an area", scope: ShortcutScope::PdfView },
+    Shortcut { keys: "Ctrl+F", action: "Find in matrix", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Enter / Shift+Enter", action: "Next / previous match", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+G", action: "Go to row,col", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+C / X / V", action: "Copy / cut / paste block", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Shift+V", action: "Paste, skipping spaces", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Shift+S", action: "Save selection as…", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Scroll / Ctrl+± / Ctrl+0", action: "Zoom matrix / reset", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Click / Drag", action: "Place cursor / select block", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Arrows / Ctrl+Arrows", action: "Move cursor / by word or block edge", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Shift+Arrows", action: "Extend block selection", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Drag", action: "Add rectangle to selection", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Arrows (block selected)", action: "Move block, drops on Ctrl release", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Shift+F / Delete", action: "Fill / clear selection", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "[Vim] hjkl w b 0 $ G", action: "Move (with counts, e.g. 5j)", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "[Vim] i a / Esc", action: "Insert mode / back to normal", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "[Vim] v then y d o", action: "Visual block: yank / delete / other corner", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "[Vim] x dd yy p P u", action: "Delete / yank rows, paste (P: transparent), undo", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Alt+Arrows", action: "Nudge selected block one cell", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Ctrl+Z / Ctrl+Y", action: "Undo / redo edit", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Drag selection", action: "Move block", scope: ShortcutScope::MatrixView },
+    Shortcut { keys: "Type", action: "Overwrite at cursor", scope: ShortcutScope::MatrixView },
+];
+
+/// Shortcuts that apply while `pane` has focus, global ones first.
+fn shortcuts_for(pane: FocusedPane) -> Vec<&'static Shortcut> {
+    let scope = match pane {
+        FocusedPane::PdfView => ShortcutScope::PdfView,
+        FocusedPane::MatrixView => ShortcutScope::MatrixView,
+    };
+    SHORTCUTS
+        .iter()
+        .filter(|s| s.scope == ShortcutScope::Global)
+        .chain(SHORTCUTS.iter().filter(|s| s.scope == scope))
+        .collect()
+}
 
-    fn calculate_optimal_matrix_size(
-        &self,
-        text_objects: &[PreciseTextObject],
-    ) -> (usize, usize, f32, f32) {
-        if text_objects.is_empty() {
-            return (50, 50, 6.0, 12.0);
-        }
+// ============= EXTRACTION JOBS =============
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
 
-        let mut font_size_counts: HashMap<i32, usize> = HashMap::new();
-        for obj in text_objects {
-            let rounded_size = obj.font_size.round() as i32;
-            *font_size_counts.entry(rounded_size).or_insert(0) += 1;
+impl JobStatus {
+    pub fn icon(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "·",
+            JobStatus::Running => "⏳",
+            JobStatus::Done => "✓",
+            JobStatus::Failed => "✗",
         }
-
-        let modal_font_size = font_size_counts
-            .iter()
-            .max_by_key(|(_, count)| *count)
-            .map(|(size, _)| *size as f32)
-            .unwrap_or(12.0);
-
-        let char_width = modal_font_size * 0.6;
-        let char_height = modal_font_size * 1.2;
-
-        let min_x = text_objects
-            .iter()
-            .map(|t| t.bbox.x0)
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-        let max_x = text_objects
-            .iter()
-            .map(|t| t.bbox.x1)
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(100.0);
-        let min_y = text_objects
-            .iter()
-            .map(|t| t.bbox.y0)
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-        let max_y = text_objects
-            .iter()
-            .map(|t| t.bbox.y1)
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(100.0);
-
-        let content_width = max_x - min_x;
-        let content_height = max_y - min_y;
-
-        let matrix_width = ((content_width / char_width).ceil() as usize).max(10);
-        let matrix_height = ((content_height / char_height).ceil() as usize).max(10);
-
-        (matrix_width, matrix_height, char_width, char_height)
     }
 
-    fn merge_adjacent_regions(&self, regions: &[TextRegion]) -> Vec<TextRegion> {
-        if regions.is_empty() {
-            return Vec::new();
-        }
-
-        let mut merged = Vec::new();
-        let mut processed = vec![false; regions.len()];
-
-        for i in 0..regions.len() {
-            if processed[i] {
-                continue;
-            }
+    fn is_pending(self) -> bool {
+        matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
 
-            let mut current = regions[i].clone();
-            processed[i] = true;
+enum JobEvent {
+    Progress(ProgressEvent),
+    Finished(usize, Box<Result<CharacterMatrix, ChonkerError>>),
+}
 
-            let mut merged_any = true;
-            while merged_any {
-                merged_any = false;
+/// Page extractions allowed to run at once.
+const MAX_CONCURRENT_JOBS: usize = 2;
 
-                for j in 0..regions.len() {
-                    if processed[j] {
-                        continue;
-                    }
+/// Page extraction requests of one document. Each page goes queued →
+/// running → done or failed, and finished matrices are collected by `poll`.
+pub struct JobQueue {
+    status: std::collections::BTreeMap<usize, JobStatus>,
+    sender: mpsc::UnboundedSender<JobEvent>,
+    receiver: mpsc::UnboundedReceiver<JobEvent>,
+    permits: Arc<tokio::sync::Semaphore>,
+}
 
-                    let other = &regions[j];
+impl JobQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            status: std::collections::BTreeMap::new(),
+            sender,
+            receiver,
+            permits: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_JOBS)),
+        }
+    }
 
-                    if other.bbox.y == current.bbox.y && other.bbox.height == current.bbox.height {
-                        let current_end = current.bbox.x + current.bbox.width;
-                        let other_end = other.bbox.x + other.bbox.width;
+    pub fn status(&self, page: usize) -> Option<JobStatus> {
+        self.status.get(&page).copied()
+    }
 
-                        if (other.bbox.x as i32 - current_end as i32).abs() <= 2
-                            || (current.bbox.x as i32 - other_end as i32).abs() <= 2
-                        {
-                            let new_x = current.bbox.x.min(other.bbox.x);
-                            let new_end = current_end.max(other_end);
-                            current.bbox.x = new_x;
-                            current.bbox.width = new_end - new_x;
-                            current.text_content.push_str(&other.text_content);
-                            processed[j] = true;
-                            merged_any = true;
-                        }
-                    }
-                }
-            }
+    pub fn is_pending(&self, page: usize) -> bool {
+        self.status(page).is_some_and(JobStatus::is_pending)
+    }
 
-            merged.push(current);
-        }
+    pub fn pending(&self) -> usize {
+        self.status.values().filter(|s| s.is_pending()).count()
+    }
 
-        merged
+    /// Every page that has been submitted, with its status.
+    pub fn pages(&self) -> impl Iterator<Item = (usize, JobStatus)> + '_ {
+        self.status.iter().map(|(&page, &status)| (page, status))
     }
 
-    pub fn process_pdf(&self, pdf_path: &PathBuf) -> Result<CharacterMatrix> {
-        self.process_pdf_page(pdf_path, None)
+    /// Finished and total jobs since the queue last went idle.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.status.len() - self.pending(), self.status.len())
     }
 
-    pub fn process_pdf_page(
-        &self,
-        pdf_path: &PathBuf,
-        page_index: Option<usize>,
-    ) -> Result<CharacterMatrix> {
-        let text_objects = if let Some(idx) = page_index {
-            self.extract_text_objects_for_page(pdf_path, idx)?
-        } else {
-            self.extract_text_objects_with_precise_coords(pdf_path)?
-        };
+    /// `progress` as a percentage, as in the servers' `Percent` events.
+    pub fn percent(&self) -> f32 {
+        let (finished, total) = self.progress();
+        match ProgressEvent::percent(finished, total) {
+            ProgressEvent::Percent { percent, .. } => percent,
+            _ => 0.0,
+        }
+    }
 
-        if text_objects.is_empty() {
-            return Err(anyhow::anyhow!("No text found in PDF"));
+    /// Queues `job` for `page` unless that page is already queued or running.
+    pub fn submit<F>(&mut self, runtime: &tokio::runtime::Runtime, ctx: &egui::Context, page: usize, job: F)
+    where
+        F: std::future::Future<Output = Result<CharacterMatrix, ChonkerError>> + Send + 'static,
+    {
+        if self.is_pending(page) {
+            return;
         }
+        if self.pending() == 0 {
+            // A new batch starts the progress count over
+            self.status.clear();
+        }
+        self.status.insert(page, JobStatus::Queued);
 
-        let (matrix_width, matrix_height, char_width, char_height) =
-            self.calculate_optimal_matrix_size(&text_objects);
+        let sender = self.sender.clone();
+        let permits = self.permits.clone();
+        let ctx = ctx.clone();
+        runtime.spawn(async move {
+            let Ok(_permit) = permits.acquire_owned().await else {
+                return;
+            };
+            let _ = sender.send(JobEvent::Progress(ProgressEvent::Started { page }));
+            ctx.request_repaint();
+            let result = job.await;
+            let _ = sender.send(JobEvent::Finished(page, Box::new(result)));
+            ctx.request_repaint();
+        });
+    }
 
-        let min_x = text_objects
-            .iter()
-            .map(|t| t.bbox.x0)
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-        let min_y = text_objects
-            .iter()
-            .map(|t| t.bbox.y0)
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap_or(0.0);
-
-        let mut matrix = vec![vec![' '; matrix_width]; matrix_height];
-        let mut text_regions = Vec::new();
-
-        for text_obj in &text_objects {
-            let char_x = ((text_obj.bbox.x0 - min_x) / char_width).round() as usize;
-            let char_y = ((text_obj.bbox.y0 - min_y) / char_height).round() as usize;
-
-            if char_y < matrix_height && char_x < matrix_width {
-                if let Some(ch) = text_obj.text.chars().next() {
-                    matrix[char_y][char_x] = ch;
-
-                    text_regions.push(TextRegion {
-                        bbox: CharBBox {
-                            x: char_x,
-                            y: char_y,
-                            width: 1,
-                            height: 1,
-                        },
-                        confidence: 1.0,
-                        text_content: ch.to_string(),
-                        region_id: text_regions.len(),
-                    });
+    /// Applies status updates and returns the jobs that finished since the
+    /// last call.
+    pub fn poll(&mut self) -> Vec<(usize, Result<CharacterMatrix, ChonkerError>)> {
+        let mut finished = Vec::new();
+        while let Ok(event) = self.receiver.try_recv() {
+            match event {
+                JobEvent::Progress(ProgressEvent::Started { page }) => {
+                    self.status.insert(page, JobStatus::Running);
+                }
+                JobEvent::Progress(_) => {}
+                JobEvent::Finished(page, result) => {
+                    let status = if result.is_ok() { JobStatus::Done } else { JobStatus::Failed };
+                    self.status.insert(page, status);
+                    finished.push((page, *result));
                 }
             }
         }
-
-        let merged_regions = self.merge_adjacent_regions(&text_regions);
-        let original_text: Vec<String> = text_objects.iter().map(|obj| obj.text.clone()).collect();
-
-        Ok(CharacterMatrix {
-            width: matrix_width,
-            height: matrix_height,
-            matrix,
-            text_regions: merged_regions,
-            original_text,
-            char_width,
-            char_height,
-        })
-    }
-
-    pub async fn process_pdf_with_ai(&self, pdf_path: &PathBuf) -> Result<CharacterMatrix> {
-        tracing::warn!("AI sensors not available, falling back to basic processing");
-        self.process_pdf(pdf_path)
+        finished
     }
+}
 
-    pub fn process_pdf_with_ferrules(
-        &self,
-        pdf_path: &PathBuf,
-        _ferrules_path: &PathBuf,
-    ) -> Result<CharacterMatrix> {
-        self.process_pdf(pdf_path)
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn render_matrix_as_string(&self, char_matrix: &CharacterMatrix) -> String {
-        let mut result = String::new();
-
-        result.push_str(&format!(
-            "Character Matrix ({}x{}) | Char: {:.1}x{:.1}pt:\n",
-            char_matrix.width, char_matrix.height, char_matrix.char_width, char_matrix.char_height
-        ));
-        result.push_str(&format!(
-            "Text Regions: {} | Original Text Objects: {}\n",
-            char_matrix.text_regions.len(),
-            char_matrix.original_text.len()
-        ));
-        result.push_str(&"═".repeat(char_matrix.width.min(80)));
-        result.push('\n');
+// ============= PDF WATCH =============
+/// How long a watched PDF must stay unchanged before it is reloaded, so a
+/// tool still writing it isn't read half-way.
+const PDF_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Unsaved edits of a page whose PDF changed on disk, held until the page is
+/// re-extracted and the user picks how to combine them.
+struct PendingMerge {
+    pdf_path: PathBuf,
+    page: usize,
+    /// The extraction the edits were made against.
+    original: Vec<Vec<char>>,
+    edited: Vec<Vec<char>>,
+    /// The new extraction, once it arrives.
+    fresh: Option<Vec<Vec<char>>>,
+}
 
-        for (row_idx, row) in char_matrix.matrix.iter().enumerate() {
-            if char_matrix.height > 20 {
-                result.push_str(&format!("{:3} ", row_idx));
-            }
+#[derive(Clone, Copy)]
+enum MergeChoice {
+    Merge,
+    KeepEdits,
+    UseNew,
+}
 
-            for &ch in row {
-                result.push(ch);
-            }
-            result.push('\n');
+/// Watches the directory of `pdf_path` and signals `sender` when the file is
+/// written or replaced. Tools often regenerate a PDF by renaming a new file
+/// over it, which a watch on the file itself would not follow.
+fn watch_pdf_file(
+    pdf_path: &Path,
+    sender: std::sync::mpsc::Sender<()>,
+    ctx: egui::Context,
+) -> notify::Result<notify::RecommendedWatcher> {
+    use notify::Watcher;
+
+    let file_name = pdf_path.file_name().map(|name| name.to_os_string());
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let touches_pdf = event.paths.iter().any(|path| path.file_name() == file_name.as_deref());
+        if touches_pdf && (event.kind.is_create() || event.kind.is_modify()) {
+            let _ = sender.send(());
+            ctx.request_repaint();
         }
+    })?;
+    let dir = match pdf_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
 
-        result.push_str(&"═".repeat(char_matrix.width.min(80)));
-        result.push('\n');
-
-        for (i, region) in char_matrix.text_regions.iter().enumerate() {
-            result.push_str(&format!(
-                "Region {}: ({},{}) {}x{} conf:{:.2} - \"{}\"\n",
-                i + 1,
-                region.bbox.x,
-                region.bbox.y,
-                region.bbox.width,
-                region.bbox.height,
-                region.confidence,
-                region.text_content.chars().take(50).collect::<String>()
-            ));
-        }
+// ============= URL DOWNLOADS =============
+/// Downloads larger than this are refused, checked against `Content-Length`
+/// up front and against the bytes received.
+const MAX_DOWNLOAD_BYTES: u64 = 200 * 1024 * 1024;
 
-        result
-    }
+enum DownloadEvent {
+    Progress { received: u64, total: Option<u64> },
+    Done(Result<PathBuf, String>),
+}
 
-    pub fn run_ferrules_integration_test(&self, pdf_path: &PathBuf) -> Result<String> {
-        use std::process::Command;
+pub fn is_pdf_url(arg: &str) -> bool {
+    arg.starts_with("https://") || arg.starts_with("http://")
+}
 
-        let output = Command::new("./target/release/test_ferrules_integration")
-            .arg(pdf_path.to_str().unwrap_or(""))
-            .env("RUST_LOG", "debug")
-            .env("DYLD_LIBRARY_PATH", "./lib")
-            .output()
-            .map_err(|e| anyhow::anyhow!("Failed to run terminal command: {}", e))?;
+/// Temp file a download of `url` is saved to, named after the last path
+/// segment so the tab title and sidecar files stay recognisable.
+pub fn download_target(url: &str) -> PathBuf {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let name: String = path
+        .rsplit('/')
+        .find(|segment| !segment.is_empty() && !segment.contains(':'))
+        .unwrap_or("download")
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    let name = if name.to_lowercase().ends_with(".pdf") { name } else { format!("{}.pdf", name) };
+    std::env::temp_dir().join("chonker5-downloads").join(name)
+}
 
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            let filtered: String = stdout
-                .lines()
-                .skip_while(|line| !line.trim_start().starts_with(|c: char| c.is_ascii_digit()))
-                .filter(|line| {
-                    line.trim_start()
-                        .chars()
-                        .next()
-                        .map_or(false, |c| c.is_ascii_digit())
-                })
-                .collect::<Vec<_>>()
-                .join("\n");
+/// Downloads `url` to its `download_target`, calling `progress` with the
+/// bytes received so far and the expected total.
+fn download_pdf(url: &str, limit: u64, mut progress: impl FnMut(u64, Option<u64>)) -> Result<PathBuf> {
+    use std::io::{Read, Write};
 
-            Ok(filtered)
-        } else {
-            Err(anyhow::anyhow!(
-                "Terminal command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        }
+    let too_large = |bytes: u64| anyhow::anyhow!("{} MB is over the {} MB download limit", bytes / (1024 * 1024), limit / (1024 * 1024));
+    let response = ureq::get(url).timeout(Duration::from_secs(120)).call()?;
+    let total = response.header("Content-Length").and_then(|length| length.parse::<u64>().ok());
+    if let Some(total) = total.filter(|&total| total > limit) {
+        return Err(too_large(total));
     }
 
-    pub fn generate_spatial_console_output(&self, char_matrix: &CharacterMatrix) -> String {
-        let mut result = String::new();
-
-        result.push_str("📊 Ferrules Character Matrix Output - Exact Placement Visualization\n");
-        result.push_str(&format!(
-            "Matrix Size: {} columns × {} rows\n",
-            char_matrix.width, char_matrix.height
-        ));
-        result.push_str(&format!(
-            "Regions Detected: {}\n",
-            char_matrix.text_regions.len()
-        ));
-        result.push_str(&format!(
-            "Text Objects: {}\n",
-            char_matrix.original_text.len()
-        ));
-        result.push_str("Processing Time: N/A\n");
-        result.push_str("Toggle Text Highlighting Toggle Grid Lines\n");
-
-        for (row_idx, row) in char_matrix.matrix.iter().enumerate() {
-            result.push_str(&format!("{:3} ", row_idx));
-            for &ch in row.iter() {
-                result.push(if ch == ' ' { '·' } else { ch });
-            }
-            result.push('\n');
-        }
-
-        result.push_str("What Ferrules Accomplished:\n");
-
-        let mut accomplishments = Vec::new();
-        for (i, region) in char_matrix.text_regions.iter().enumerate().take(5) {
-            if !region.text_content.trim().is_empty() {
-                let content_preview = if region.text_content.len() > 50 {
-                    format!("{}...", &region.text_content[..50])
-                } else {
-                    region.text_content.clone()
-                };
-                accomplishments.push(format!(
-                    "✅ Found text region {}: \"{}\" (Confidence: {:.1}%)",
-                    i + 1,
-                    content_preview,
-                    region.confidence * 100.0
-                ));
-            }
-        }
-
-        if accomplishments.is_empty() {
-            accomplishments
-                .push("✅ Successfully processed PDF with Ferrules ML vision model".to_string());
-            accomplishments
-                .push("✅ Generated spatial character matrix representation".to_string());
-            accomplishments.push("✅ Preserved document layout structure".to_string());
+    let target = download_target(url);
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut reader = response.into_reader();
+    let mut file = std::fs::File::create(&target)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut received = 0u64;
+    let mut head = Vec::new();
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
         }
-
-        for accomplishment in accomplishments {
-            result.push_str(&format!("{}\n", accomplishment));
+        received += read as u64;
+        if received > limit {
+            drop(file);
+            let _ = std::fs::remove_file(&target);
+            return Err(too_large(received));
         }
-
-        let issues = vec![
-            "❌ Text concatenation: Words may run together without spaces",
-            "❌ Overlapping text: Multiple words placed in same positions",
-            "❌ Inconsistent spacing: Some areas dense, others sparse",
-            "❌ Character accuracy: OCR/vision may misread some characters",
-        ];
-
-        result.push_str("Placement Issues:\n");
-        for issue in issues {
-            result.push_str(&format!("{}\n", issue));
+        if head.len() < 5 {
+            head.extend_from_slice(&buffer[..read.min(5 - head.len())]);
         }
-
-        result
+        file.write_all(&buffer[..read])?;
+        progress(received, total);
     }
-}
 
-impl Default for CharacterMatrixEngine {
-    fn default() -> Self {
-        Self::new()
+    // Servers answer with HTML for login walls and missing files
+    if !head.starts_with(b"%PDF") {
+        let _ = std::fs::remove_file(&target);
+        return Err(anyhow::anyhow!("{} did not return a PDF", url));
     }
+    Ok(target)
 }
 
 // ============= APPLICATION =============
+/// Progress of the current page's extraction; the page itself, with its
+/// edits, lives in the `DocumentModel`.
 #[derive(Default)]
 struct ExtractionResult {
-    character_matrix: Option<CharacterMatrix>,
-    editable_matrix: Option<Vec<Vec<char>>>,
     is_loading: bool,
-    error: Option<String>,
-    matrix_dirty: bool,
-    original_matrix: Option<Vec<Vec<char>>>,
+    error: Option<ChonkerError>,
 }
 
+/// An area extraction's cells for `(pdf, page, area)`.
+type AreaResult = (PathBuf, usize, CharBBox, Result<Vec<Vec<char>>, ChonkerError>);
+
+/// A ferrules run's matrix for `(pdf, page)`.
+type FerrulesResult = (PathBuf, usize, Result<CharacterMatrix, ChonkerError>);
+
+/// A PDF text search's hits for `pdf`.
+type PdfSearchResult = (PathBuf, Result<Vec<PdfSearchHit>, String>);
+
+/// A region list row: region index, label, confidence, tag and warnings.
+type RegionRow = (usize, String, f32, Option<RegionTag>, Vec<String>);
+
 struct Chonker5App {
+    // Open documents; the active slot is `None` while its state is live
+    document_tabs: Vec<Option<DocumentTab>>,
+    active_document: usize,
+    pending_tab_close: Option<usize>,
+
     // PDF state
     pdf_path: Option<PathBuf>,
     current_page: usize,
@@ -1214,6 +1330,12 @@ struct Chonker5App {
     zoom_level: f32,
     pdf_texture: Option<egui::TextureHandle>,
     needs_render: bool,
+    render_receiver: Option<std::sync::mpsc::Receiver<(RenderRequest, Result<egui::ColorImage, ChonkerError>)>>,
+
+    // Draw-region tool: re-extract a dragged rectangle of the page
+    area_tool: bool,
+    area_drag: Option<(egui::Pos2, egui::Pos2)>,
+    area_receiver: Option<std::sync::mpsc::Receiver<AreaResult>>,
 
     // UI assets
     hamster_texture: Option<egui::TextureHandle>,
@@ -1225,25 +1347,171 @@ struct Chonker5App {
 
     // Character matrix engine
     matrix_engine: CharacterMatrixEngine,
+    char_overrides: CharOverrides,
+    char_override_edit: CharDimensions,
+    monospace_training: MonospaceTraining,
+    extraction_limits: ExtractionLimits,
 
     // Ferrules
     ferrules_binary: Option<PathBuf>,
     ferrules_output_cache: Option<String>,
     ferrules_matrix_grid: Option<MatrixGrid>,
+    // Aborting the task kills the ferrules process
+    ferrules_task: Option<tokio::task::JoinHandle<()>>,
+    ferrules_receiver: Option<std::sync::mpsc::Receiver<FerrulesResult>>,
 
     // Raw text matrix grid
     raw_text_matrix_grid: Option<MatrixGrid>,
 
     // Async runtime
     runtime: Arc<tokio::runtime::Runtime>,
-    vision_receiver: Option<mpsc::Receiver<Result<CharacterMatrix, String>>>,
+    jobs: JobQueue,
 
     // File dialog
     file_dialog_receiver: Option<std::sync::mpsc::Receiver<Option<PathBuf>>>,
     file_dialog_pending: bool,
+    initial_file: Option<PathBuf>,
+
+    // Opening PDFs from a URL
+    open_url_dialog: bool,
+    open_url_input: String,
+    download_receiver: Option<std::sync::mpsc::Receiver<DownloadEvent>>,
+    download_progress: Option<(String, u64, Option<u64>)>,
+
+    // Selection export
+    selection_save_receiver: Option<std::sync::mpsc::Receiver<Option<PathBuf>>>,
+    pending_selection_text: Option<String>,
+
+    // Project files
+    project_path: Option<PathBuf>,
+    project_portable: bool,
+    project_dialog_receiver: Option<std::sync::mpsc::Receiver<ProjectDialogResult>>,
 
     // Log messages
-    log_messages: Vec<String>,
+    log_messages: LogBuffer,
+    log_filter: String,
+    show_log_panel: bool,
+    _log_guard: Option<tracing_appender::non_blocking::WorkerGuard>,
+
+    // Page cache and low-confidence review
+    page_cache: PageCache,
+    review_threshold: f32,
+    review_cursor: Option<(usize, usize)>,
+    review_scanning: bool,
+    /// Pages whose last extraction failed, with the error.
+    failed_pages: std::collections::BTreeMap<usize, ChonkerError>,
+    /// Edited pages whose settings changed, awaiting confirmation before
+    /// they are re-extracted.
+    pending_reextract: Vec<usize>,
+
+    // Watch mode: reload the active PDF when another tool rewrites it
+    watch_pdf: bool,
+    pdf_watcher: Option<notify::RecommendedWatcher>,
+    watched_pdf: Option<PathBuf>,
+    pdf_change_receiver: Option<std::sync::mpsc::Receiver<()>>,
+    pdf_changed_at: Option<Instant>,
+    pending_merge: Option<PendingMerge>,
+
+    // Region list panel
+    show_region_panel: bool,
+    region_sort: RegionSort,
+    // Also hides regions from the overlay and the regions CSV
+    region_min_confidence: f32,
+    region_flash: Option<(usize, Instant)>,
+    pdf_scroll_to_flash: bool,
+    pending_grid_selection: Option<CharBBox>,
+    context_region: Option<usize>,
+    region_picks: Vec<usize>,
+    region_split_offset: usize,
+
+    // PDF text search (Ctrl+F on the PDF pane)
+    pdf_search_open: bool,
+    pdf_search_query: String,
+    pdf_search_all_pages: bool,
+    pdf_search_hits: Vec<PdfSearchHit>,
+    pdf_search_selected: Option<usize>,
+    pdf_search_scroll: bool,
+    pdf_search_receiver: Option<std::sync::mpsc::Receiver<PdfSearchResult>>,
+
+    show_shortcuts: bool,
+    theme_config: ThemeConfig,
+    /// `chonker.toml`: the backend and cell size for extraction, and the
+    /// key bindings applied by `keymap`.
+    config: ChonkerConfig,
+    keymap: Keymap,
+    matrix_view: MatrixViewPrefs,
+
+    // Matrix search (Ctrl+F)
+    matrix_search_open: bool,
+    matrix_search_query: String,
+    matrix_search_focus: bool,
+
+    // Selection menu
+    row_sort: RowSort,
+
+    // Box drawing mode for the matrix
+    box_draw: bool,
+    // Snap dragged blocks to text region columns / rows
+    snap_drops: bool,
+    // Vim-style modal keybindings for the matrix
+    vim_mode: bool,
+    // Tint cells edited since extraction
+    highlight_edits: bool,
+
+    // Split matrix view
+    matrix_split: MatrixSplit,
+    active_viewport: usize,
+
+    // Fill selection prompt (Ctrl+Shift+F)
+    fill_open: bool,
+    fill_input: String,
+    fill_focus: bool,
+
+    // Goto row/column (Ctrl+G)
+    goto_open: bool,
+    goto_input: String,
+    goto_focus: bool,
+
+    // Bookmarks (Ctrl+J)
+    bookmarks: Vec<Bookmark>,
+    show_bookmarks: bool,
+    // Original-vs-edited diff window
+    show_edits: bool,
+    bookmark_name: String,
+    bookmark_focus: bool,
+    pending_grid_cursor: Option<(usize, usize)>,
+
+    // Reading-order editor
+    reading_order_mode: bool,
+    reading_drag: Option<usize>,
+
+    // Region labels
+    region_labels: RegionLabels,
+    region_tags: RegionTags,
+    reading_orders: ReadingOrders,
+    // How extracted pages are split into regions, chosen per document
+    segmentation: Segmentation,
+    // Extracted pages with their edits, shared with the TUI and Bevy editors.
+    // The current page and edited ones are held; the rest wait compacted in
+    // `page_cache`
+    document: DocumentModel,
+    document_events: Option<std::sync::mpsc::Receiver<DocumentEvent>>,
+    regions_csv_receiver: Option<std::sync::mpsc::Receiver<RegionsCsvDialogResult>>,
+    edit_log_receiver: Option<std::sync::mpsc::Receiver<Option<PathBuf>>>,
+
+    // Help
+    show_help: bool,
+    help_query: String,
+    help_topic: usize,
+    /// Setting row to scroll into view after a help diagnostic links to it.
+    help_scroll_to: Option<HelpSetting>,
+
+    // Matrix history
+    show_history_panel: bool,
+    history_versions: Vec<MatrixVersion>,
+    history_page: usize,
+    history_selected: Option<usize>,
+    history_preview: Option<String>,
 
     // UI state
     show_bounding_boxes: bool,
@@ -1258,10 +1526,104 @@ struct Chonker5App {
     first_frame: bool,
 }
 
+/// Per-document state of an open but inactive tab. The active document lives
+/// directly in the `Chonker5App` fields and is swapped in and out of here.
+#[derive(Default)]
+struct DocumentTab {
+    pdf_path: Option<PathBuf>,
+    current_page: usize,
+    total_pages: usize,
+    zoom_level: f32,
+    pdf_texture: Option<egui::TextureHandle>,
+    page_range: String,
+    matrix_result: ExtractionResult,
+    ferrules_output_cache: Option<String>,
+    ferrules_matrix_grid: Option<MatrixGrid>,
+    raw_text_matrix_grid: Option<MatrixGrid>,
+    jobs: JobQueue,
+    char_overrides: CharOverrides,
+    page_cache: PageCache,
+    region_labels: RegionLabels,
+    region_tags: RegionTags,
+    reading_orders: ReadingOrders,
+    segmentation: Segmentation,
+    document: DocumentModel,
+    document_events: Option<std::sync::mpsc::Receiver<DocumentEvent>>,
+    matrix_view: MatrixViewPrefs,
+    bookmarks: Vec<Bookmark>,
+    review_cursor: Option<(usize, usize)>,
+    review_scanning: bool,
+    failed_pages: std::collections::BTreeMap<usize, ChonkerError>,
+    pending_reextract: Vec<usize>,
+    selected_cell: Option<(usize, usize)>,
+}
+
+impl DocumentTab {
+    fn title(&self) -> String {
+        document_title(self.pdf_path.as_deref())
+    }
+}
+
+fn document_title(pdf_path: Option<&Path>) -> String {
+    pdf_path
+        .and_then(|p| p.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string())
+}
+
+enum RegionsCsvDialogResult {
+    Export(Option<PathBuf>),
+    Import(Option<PathBuf>),
+}
+
+enum ProjectDialogResult {
+    Open(Option<PathBuf>),
+    Save(Option<PathBuf>),
+}
+
 #[derive(PartialEq, Clone, Debug)]
 enum ExtractionTab {
     RawText,
     SmartLayout,
+    Compare,
+}
+
+/// Edits applied to the raw-text grid's selection from the Selection menu.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum SelectionOp {
+    AlignColumns,
+    SortRows(RowSort),
+    Transpose,
+    Transform(CharTransform),
+    Whitespace(WhitespaceOp),
+    Fill(char),
+    Reflow,
+}
+
+/// Second viewport onto the raw-text grid.
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum MatrixSplit {
+    Off,
+    Rows,
+    Columns,
+}
+
+impl MatrixSplit {
+    fn next(self) -> Self {
+        match self {
+            MatrixSplit::Off => MatrixSplit::Rows,
+            MatrixSplit::Rows => MatrixSplit::Columns,
+            MatrixSplit::Columns => MatrixSplit::Off,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MatrixSplit::Off => "Split: off",
+            MatrixSplit::Rows => "Split: ⬍",
+            MatrixSplit::Columns => "Split: ⬌",
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -1280,10 +1642,10 @@ enum DragAction {
 }
 
 impl Chonker5App {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>, initial_file: Option<PathBuf>, watch_pdf: bool) -> Self {
         let runtime =
             Arc::new(tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime"));
-        tracing_subscriber::fmt::init();
+        let log_guard = init_tracing();
 
         let hamster_texture = if let Ok(image_data) = std::fs::read("./assets/emojis/chonker.png") {
             if let Ok(image) = image::load_from_memory(&image_data) {
@@ -1302,13 +1664,24 @@ impl Chonker5App {
             None
         };
 
+        let config = ChonkerConfig::load();
+        let theme_config = ThemeConfig::load_shared(&config);
+        set_theme(theme_config.resolve());
+
         let mut app = Self {
+            document_tabs: Vec::new(),
+            active_document: 0,
+            pending_tab_close: None,
             pdf_path: None,
             current_page: 0,
             total_pages: 0,
             zoom_level: 1.0,
             pdf_texture: None,
             needs_render: false,
+            render_receiver: None,
+            area_tool: false,
+            area_drag: None,
+            area_receiver: None,
             hamster_texture,
             page_range: "1-10".to_string(),
             matrix_result: Default::default(),
@@ -1316,19 +1689,107 @@ impl Chonker5App {
             ferrules_binary: None,
             ferrules_output_cache: None,
             ferrules_matrix_grid: None,
+            ferrules_task: None,
+            ferrules_receiver: None,
             raw_text_matrix_grid: None,
             runtime,
-            vision_receiver: None,
+            jobs: JobQueue::new(),
             file_dialog_receiver: None,
             file_dialog_pending: false,
-            log_messages: vec![
-                "🐹 CHONKER 5 Ready!".to_string(),
-                "📌 Character Matrix Engine: PDF → Char Matrix → Vision Boxes → Text Mapping"
-                    .to_string(),
-            ],
+            initial_file,
+            selection_save_receiver: None,
+            pending_selection_text: None,
+            project_path: None,
+            project_portable: true,
+            project_dialog_receiver: None,
+            log_messages: LogBuffer::new(),
+            log_filter: String::new(),
+            show_log_panel: false,
+            _log_guard: log_guard,
+            page_cache: PageCache::new(),
+            review_threshold: 0.5,
+            review_cursor: None,
+            review_scanning: false,
+            failed_pages: std::collections::BTreeMap::new(),
+            pending_reextract: Vec::new(),
+            watch_pdf,
+            pdf_watcher: None,
+            watched_pdf: None,
+            pdf_change_receiver: None,
+            pdf_changed_at: None,
+            pending_merge: None,
+            show_region_panel: false,
+            region_sort: RegionSort::Id,
+            region_min_confidence: config.min_confidence.unwrap_or(0.0),
+            region_flash: None,
+            pdf_scroll_to_flash: false,
+            pending_grid_selection: None,
+            context_region: None,
+            region_picks: Vec::new(),
+            region_split_offset: 1,
+            region_labels: RegionLabels::new(),
+            region_tags: RegionTags::new(),
+            reading_orders: ReadingOrders::new(),
+            segmentation: Segmentation::default(),
+            document: DocumentModel::default(),
+            document_events: None,
+            reading_order_mode: false,
+            pdf_search_open: false,
+            pdf_search_query: String::new(),
+            pdf_search_all_pages: false,
+            pdf_search_hits: Vec::new(),
+            pdf_search_selected: None,
+            pdf_search_scroll: false,
+            pdf_search_receiver: None,
+            show_shortcuts: false,
+            theme_config,
+            keymap: Keymap::new(KEY_ACTIONS, &config.keymap),
+            config,
+            matrix_view: MatrixViewPrefs::default(),
+            matrix_search_open: false,
+            matrix_search_query: String::new(),
+            matrix_search_focus: false,
+            row_sort: RowSort::default(),
+            box_draw: false,
+            snap_drops: false,
+            vim_mode: false,
+            highlight_edits: false,
+            matrix_split: MatrixSplit::Off,
+            active_viewport: 0,
+            fill_open: false,
+            fill_input: String::new(),
+            fill_focus: false,
+            goto_open: false,
+            goto_input: String::new(),
+            goto_focus: false,
+            bookmarks: Vec::new(),
+            show_bookmarks: false,
+            show_edits: false,
+            bookmark_name: String::new(),
+            bookmark_focus: false,
+            pending_grid_cursor: None,
+            reading_drag: None,
+            regions_csv_receiver: None,
+            edit_log_receiver: None,
+            show_help: false,
+            help_query: String::new(),
+            help_topic: 0,
+            help_scroll_to: None,
+            show_history_panel: false,
+            history_versions: Vec::new(),
+            history_page: 0,
+            history_selected: None,
+            history_preview: None,
             show_bounding_boxes: true,
             split_ratio: 0.5,
             matrix_engine: CharacterMatrixEngine::new(),
+            char_overrides: CharOverrides::new(),
+            monospace_training: MonospaceTraining::load(),
+            extraction_limits: ExtractionLimits::load(),
+            char_override_edit: CharDimensions {
+                char_width: 6.0,
+                char_height: 12.0,
+            },
             selected_cell: None,
             pdf_dark_mode: true,
             focused_pane: FocusedPane::PdfView,
@@ -1337,8 +1798,14 @@ impl Chonker5App {
             is_dragging: false,
             clipboard: String::new(),
             first_frame: true,
+            open_url_dialog: false,
+            open_url_input: String::new(),
+            download_receiver: None,
+            download_progress: None,
         };
 
+        app.log("🐹 CHONKER 5 Ready!");
+        app.log("📌 Character Matrix Engine: PDF → Char Matrix → Vision Boxes → Text Mapping");
         app.init_ferrules_binary();
         app
     }
@@ -1374,10 +1841,8 @@ impl Chonker5App {
     }
 
     fn log(&mut self, message: &str) {
-        self.log_messages.push(message.to_string());
-        if self.log_messages.len() > 100 {
-            self.log_messages.remove(0);
-        }
+        tracing::info!(target: "chonker5::ui", "{}", message);
+        self.log_messages.push(message);
     }
 
     fn open_file(&mut self, ctx: &egui::Context) {
@@ -1403,6 +1868,104 @@ impl Chonker5App {
         });
     }
 
+    /// Downloads `url` on a background thread; `process_download_result`
+    /// opens it when done.
+    fn open_url(&mut self, url: String, ctx: &egui::Context) {
+        if self.download_receiver.is_some() {
+            self.log("🌐 A download is already in progress...");
+            return;
+        }
+        if !is_pdf_url(&url) {
+            self.log(&format!("❌ Not an http(s) URL: {}", url));
+            return;
+        }
+
+        self.log(&format!("🌐 Downloading {}", url));
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.download_receiver = Some(rx);
+        self.download_progress = Some((url.clone(), 0, None));
+
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = download_pdf(&url, MAX_DOWNLOAD_BYTES, |received, total| {
+                let _ = tx.send(DownloadEvent::Progress { received, total });
+                ctx.request_repaint();
+            });
+            let _ = tx.send(DownloadEvent::Done(result.map_err(|e| e.to_string())));
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_download_result(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.download_receiver else {
+            return;
+        };
+        let mut done = None;
+        for event in receiver.try_iter() {
+            match event {
+                DownloadEvent::Progress { received, total } => {
+                    if let Some(progress) = &mut self.download_progress {
+                        progress.1 = received;
+                        progress.2 = total;
+                    }
+                }
+                DownloadEvent::Done(result) => done = Some(result),
+            }
+        }
+
+        let Some(result) = done else {
+            return;
+        };
+        self.download_receiver = None;
+        let url = self.download_progress.take().map(|(url, ..)| url).unwrap_or_default();
+        match result {
+            Ok(path) => {
+                self.log(&format!("✅ Downloaded {} to {}", url, path.display()));
+                self.load_pdf(path, 0, ctx);
+            }
+            Err(e) => self.log(&format!("❌ Download of {} failed: {}", url, e)),
+        }
+    }
+
+    fn show_open_url_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut submit = false;
+
+        egui::Window::new("OPEN URL")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.open_url_input)
+                        .hint_text("https://…/report.pdf")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(360.0),
+                );
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    submit = true;
+                }
+                ui.label(
+                    RichText::new(format!("Saved to a temp folder; up to {} MB", MAX_DOWNLOAD_BYTES / (1024 * 1024)))
+                        .color(theme().dim)
+                        .monospace()
+                        .size(10.0),
+                );
+                if ui.button(RichText::new("Download").monospace().size(10.0)).clicked() {
+                    submit = true;
+                }
+            });
+
+        if submit && !self.open_url_input.trim().is_empty() {
+            let url = self.open_url_input.trim().to_string();
+            self.open_url_dialog = false;
+            self.open_url(url, ctx);
+        } else if !open {
+            self.open_url_dialog = false;
+        }
+    }
+
     fn process_file_dialog_result(&mut self, ctx: &egui::Context) {
         if let Some(receiver) = &self.file_dialog_receiver {
             if let Ok(file_result) = receiver.try_recv() {
@@ -1412,64 +1975,7 @@ impl Chonker5App {
                 match file_result {
                     Some(path) => {
                         self.log(&format!("📂 Selected file: {}", path.display()));
-
-                        if !path.exists() {
-                            self.log("❌ File does not exist");
-                            return;
-                        }
-
-                        if !path.is_file() {
-                            self.log("❌ Selection is not a file");
-                            return;
-                        }
-
-                        if path.extension().and_then(|ext| ext.to_str()) != Some("pdf") {
-                            self.log("❌ File is not a PDF");
-                            return;
-                        }
-
-                        self.pdf_path = Some(path.clone());
-                        self.current_page = 0;
-                        self.pdf_texture = None;
-                        self.matrix_result.character_matrix = None;
-                        self.ferrules_output_cache = None;
-                        self.ferrules_matrix_grid = None;
-                        self.raw_text_matrix_grid = None;
-
-                        match self.get_pdf_info(&path) {
-                            Ok(pages) => {
-                                self.total_pages = pages;
-                                self.log(&format!(
-                                    "✅ Loaded PDF: {} ({} pages)",
-                                    path.display(),
-                                    pages
-                                ));
-
-                                if pages > 20 {
-                                    self.page_range = "1-10".to_string();
-                                    self.log(
-                                        "📄 Large PDF detected - Default page range set to 1-10",
-                                    );
-                                } else {
-                                    self.page_range.clear();
-                                }
-
-                                if let Err(e) = self.safe_render_current_page(ctx) {
-                                    self.log(&format!("⚠️ Could not render page: {}", e));
-                                }
-
-                                self.log("🚀 Starting character matrix extraction...");
-                                if let Err(e) = self.safe_extract_character_matrix(ctx) {
-                                    self.log(&format!("❌ Matrix extraction failed: {}", e));
-                                } else {
-                                    self.active_tab = ExtractionTab::RawText;
-                                }
-                            }
-                            Err(e) => {
-                                self.log(&format!("❌ Failed to load PDF: {}", e));
-                                self.pdf_path = None;
-                            }
-                        }
+                        self.load_pdf(path, 0, ctx);
                     }
                     None => {
                         self.log("📂 File selection cancelled");
@@ -1479,295 +1985,2850 @@ impl Chonker5App {
         }
     }
 
-    fn safe_render_current_page(&mut self, ctx: &egui::Context) -> Result<()> {
-        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.render_current_page(ctx);
-        })) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(anyhow::anyhow!("Page rendering panicked")),
+    fn open_project_dialog(&mut self, ctx: &egui::Context, save: bool) {
+        if self.project_dialog_receiver.is_some() {
+            self.log("📂 File dialog already in progress...");
+            return;
         }
-    }
 
-    fn safe_extract_character_matrix(&mut self, ctx: &egui::Context) -> Result<()> {
-        if self.pdf_path.is_none() {
-            return Err(anyhow::anyhow!("No PDF loaded"));
-        }
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.project_dialog_receiver = Some(rx);
+        let suggested_name = self
+            .pdf_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}.{}", s.to_string_lossy(), PROJECT_EXTENSION))
+            .unwrap_or_else(|| format!("project.{}", PROJECT_EXTENSION));
 
-        if self.vision_receiver.is_some() {
-            return Err(anyhow::anyhow!("Extraction already in progress"));
-        }
+        std::thread::spawn(move || {
+            let dialog = rfd::FileDialog::new().add_filter("Chonker project", &["json"]);
+            let result = if save {
+                ProjectDialogResult::Save(dialog.set_file_name(&suggested_name).save_file())
+            } else {
+                ProjectDialogResult::Open(dialog.pick_file())
+            };
 
-        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-            self.extract_character_matrix(ctx);
-        })) {
+            let _ = tx.send(result);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn process_project_dialog_result(&mut self, ctx: &egui::Context) {
+        let Some(result) = self
+            .project_dialog_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.project_dialog_receiver = None;
+
+        match result {
+            ProjectDialogResult::Save(Some(path)) => self.save_project(path),
+            ProjectDialogResult::Open(Some(path)) => self.open_project(path, ctx),
+            _ => self.log("📂 Project dialog cancelled"),
+        }
+    }
+
+    fn save_project(&mut self, project_path: PathBuf) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            self.log("⚠️ No PDF loaded. Open a file first.");
+            return;
+        };
+
+        let project = ProjectFile {
+            portable: self.project_portable,
+            pdf_path,
+            current_page: self.current_page,
+            zoom_level: self.zoom_level,
+            page_range: self.page_range.clone(),
+            bookmarks: self.bookmarks.clone(),
+        };
+
+        match project.save(&project_path) {
+            Ok(()) => {
+                self.log(&format!(
+                    "💾 Saved {}project: {}",
+                    if project.portable { "portable " } else { "" },
+                    project_path.display()
+                ));
+                self.project_path = Some(project_path);
+            }
+            Err(e) => self.log(&format!("❌ Failed to save project: {}", e)),
+        }
+    }
+
+    fn open_project(&mut self, project_path: PathBuf, ctx: &egui::Context) {
+        match ProjectFile::load(&project_path) {
+            Ok(project) => {
+                self.log(&format!("📂 Opened project: {}", project_path.display()));
+                self.project_portable = project.portable;
+                self.project_path = Some(project_path);
+                self.load_pdf(project.pdf_path, project.current_page, ctx);
+                self.zoom_level = project.zoom_level;
+                self.bookmarks = project.bookmarks;
+                self.needs_render = true;
+                if !project.page_range.is_empty() {
+                    self.page_range = project.page_range;
+                }
+            }
+            Err(e) => self.log(&format!("❌ Failed to open project: {}", e)),
+        }
+    }
+
+    fn set_char_override(&mut self, dimensions: Option<CharDimensions>, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+
+        match dimensions {
+            Some(dims) => {
+                self.char_overrides.insert(self.current_page, dims);
+                self.log(&format!(
+                    "📐 Page {} char size set to {:.1}x{:.1}pt",
+                    self.current_page + 1,
+                    dims.char_width,
+                    dims.char_height
+                ));
+            }
+            None => {
+                self.char_overrides.remove(&self.current_page);
+                self.log(&format!("📐 Page {} char size override cleared", self.current_page + 1));
+            }
+        }
+
+        if let Err(e) = save_char_overrides(&pdf_path, &self.char_overrides) {
+            self.log(&format!("⚠️ Could not persist char size overrides: {}", e));
+        }
+
+        if !self.page_cache.contains_key(&self.current_page) {
+            self.extract_character_matrix(ctx);
+        }
+        self.invalidate_stale_pages(ctx);
+    }
+
+    /// Re-extracts the cached pages that the current settings would extract
+    /// differently. Pages with edits are held back in `pending_reextract`
+    /// until the user confirms.
+    fn invalidate_stale_pages(&mut self, ctx: &egui::Context) {
+        let model = self.monospace_training.model.clone();
+        let overrides = &self.char_overrides;
+        let default_dimensions = self.config.char_size;
+        let stale = stale_pages(&self.page_cache, |page| {
+            let dimensions = overrides.get(&page).copied().or(default_dimensions);
+            extraction_fingerprint(dimensions, self.config.backend, self.segmentation, &model)
+        });
+        self.reextract_or_confirm(stale, ctx);
+    }
+
+    /// Switches how this document's pages are split into regions and
+    /// re-extracts the pages already seen.
+    fn set_segmentation(&mut self, segmentation: Segmentation, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        self.segmentation = segmentation;
+        self.log(&format!("✂️ Segmentation set to {}", segmentation.label()));
+        if let Err(e) = save_segmentation(&pdf_path, segmentation) {
+            self.log(&format!("⚠️ Could not persist segmentation: {}", e));
+        }
+
+        let mut pages: Vec<usize> = self.page_cache.keys().copied().collect();
+        pages.sort_unstable();
+        self.reextract_or_confirm(pages, ctx);
+    }
+
+    /// Re-extracts `pages` with the current settings, except edited ones,
+    /// which wait for the user to confirm.
+    fn reextract_or_confirm(&mut self, pages: Vec<usize>, ctx: &egui::Context) {
+        if pages.is_empty() {
+            return;
+        }
+
+        let pdf_path = self.pdf_path.clone();
+        let (edited, clean): (Vec<usize>, Vec<usize>) = pages.into_iter().partition(|&page| {
+            self.document.is_modified(page)
+                || pdf_path.as_deref().is_some_and(|path| !list_matrix_versions(path, page).is_empty())
+        });
+
+        if !clean.is_empty() {
+            self.log(&format!("♻️ Re-extracting {} page(s) affected by the new settings", clean.len()));
+            self.reextract_pages(&clean, ctx);
+        }
+        if !edited.is_empty() {
+            self.log(&format!(
+                "⚠️ {} edited page(s) were extracted with older settings - confirm to re-extract them",
+                edited.len()
+            ));
+            self.pending_reextract = edited;
+        }
+    }
+
+    fn reextract_pages(&mut self, pages: &[usize], ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        for &page in pages {
+            self.page_cache.remove(&page);
+            self.document.remove_page(page);
+            if page == self.current_page {
+                self.extract_character_matrix(ctx);
+            } else {
+                let job = self.extraction_job(pdf_path.clone(), page);
+                self.jobs.submit(&self.runtime, ctx, page, job);
+            }
+        }
+    }
+
+    fn show_reextract_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut confirm = false;
+        let mut keep = false;
+        let pages: Vec<String> = self.pending_reextract.iter().map(|page| (page + 1).to_string()).collect();
+
+        egui::Window::new("RE-EXTRACT EDITED PAGES")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(format!(
+                    "Extraction settings changed for edited page(s) {}.\nRe-extracting replaces the matrix shown for them;\nsaved versions stay in the history.",
+                    pages.join(", ")
+                ))
+                .color(theme().fg)
+                .monospace()
+                .size(10.0));
+                ui.horizontal(|ui| {
+                    if ui.button(RichText::new("Re-extract").monospace().size(10.0)).clicked() {
+                        confirm = true;
+                    }
+                    if ui.button(RichText::new("Keep edits").monospace().size(10.0)).clicked() {
+                        keep = true;
+                    }
+                });
+            });
+
+        if confirm {
+            let pages = std::mem::take(&mut self.pending_reextract);
+            self.log(&format!("♻️ Re-extracting {} edited page(s)", pages.len()));
+            self.reextract_pages(&pages, ctx);
+        } else if keep || !open {
+            self.pending_reextract.clear();
+        }
+    }
+
+    /// Follows the active document with the file watcher and reloads it once
+    /// a change has settled.
+    fn process_pdf_changes(&mut self, ctx: &egui::Context) {
+        let target = self.pdf_path.clone().filter(|_| self.watch_pdf);
+        if target != self.watched_pdf {
+            self.pdf_watcher = None;
+            self.pdf_change_receiver = None;
+            self.pdf_changed_at = None;
+            self.watched_pdf = target.clone();
+            if let Some(path) = target {
+                let (tx, rx) = std::sync::mpsc::channel();
+                match watch_pdf_file(&path, tx, ctx.clone()) {
+                    Ok(watcher) => {
+                        self.pdf_watcher = Some(watcher);
+                        self.pdf_change_receiver = Some(rx);
+                        self.log(&format!("👁 Watching {} for changes", path.display()));
+                    }
+                    Err(e) => self.log(&format!("⚠️ Could not watch {}: {}", path.display(), e)),
+                }
+            }
+        }
+
+        if let Some(receiver) = &self.pdf_change_receiver {
+            if receiver.try_iter().count() > 0 {
+                self.pdf_changed_at = Some(Instant::now());
+            }
+        }
+        if let Some(changed_at) = self.pdf_changed_at {
+            let remaining = PDF_SETTLE_TIME.saturating_sub(changed_at.elapsed());
+            if remaining.is_zero() {
+                self.pdf_changed_at = None;
+                self.reload_changed_pdf(ctx);
+            } else {
+                ctx.request_repaint_after(remaining);
+            }
+        }
+    }
+
+    /// Re-renders and re-extracts the active document after it changed on
+    /// disk. Unsaved edits of the current page are held in `pending_merge`
+    /// until the new extraction arrives.
+    fn reload_changed_pdf(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.pdf_path.clone() else {
+            return;
+        };
+        let pages = match self.get_pdf_info(&path) {
+            Ok(pages) => pages,
+            Err(e) => {
+                // Possibly still being written; the next change event retries
+                self.log(&format!("⚠️ {} changed but could not be read: {}", path.display(), e));
+                return;
+            }
+        };
+        self.log(&format!("👁 {} changed on disk - reloading ({} pages)", path.display(), pages));
+
+        // Results of jobs started on the old file are dropped with the queue
+        self.jobs = JobQueue::new();
+        self.page_cache.clear();
+        self.failed_pages.clear();
+        self.pending_reextract.clear();
+        self.cancel_ferrules();
+        self.ferrules_output_cache = None;
+        self.ferrules_matrix_grid = None;
+        self.total_pages = pages;
+        self.current_page = self.current_page.min(pages.saturating_sub(1));
+        self.needs_render = true;
+
+        self.pending_merge = self.document.page(self.current_page).filter(|page| page.is_modified()).map(|page| PendingMerge {
+            pdf_path: path,
+            page: self.current_page,
+            original: page.extracted.clone(),
+            edited: page.matrix.rows().into_owned(),
+            fresh: None,
+        });
+        // The current page stays on screen until its new extraction replaces it
+        let stale: Vec<usize> =
+            self.document.pages().map(|(page, _)| page).filter(|&page| page != self.current_page).collect();
+        for page in stale {
+            self.document.remove_page(page);
+        }
+        self.extract_character_matrix(ctx);
+    }
+
+    fn show_merge_window(&mut self, ctx: &egui::Context) {
+        let Some(merge) = &self.pending_merge else {
+            return;
+        };
+        if merge.fresh.is_none() {
+            return;
+        }
+        let page = merge.page;
+        let edits = cell_edits(page, &merge.original, &merge.edited, 0).len();
+        let mut choice = None;
+
+        egui::Window::new("PDF CHANGED ON DISK")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(format!(
+                    "The PDF was regenerated while page {} had {} unsaved cell edit(s).\nMerge carries them onto the new extraction; edits of cells\nthe new version also changed win over it.",
+                    page + 1,
+                    edits
+                ))
+                .color(theme().fg)
+                .monospace()
+                .size(10.0));
+                ui.horizontal(|ui| {
+                    if ui.button(RichText::new("Merge edits").monospace().size(10.0)).clicked() {
+                        choice = Some(MergeChoice::Merge);
+                    }
+                    if ui.button(RichText::new("Keep my matrix").monospace().size(10.0)).clicked() {
+                        choice = Some(MergeChoice::KeepEdits);
+                    }
+                    if ui.button(RichText::new("Use new extraction").monospace().size(10.0)).clicked() {
+                        choice = Some(MergeChoice::UseNew);
+                    }
+                });
+            });
+
+        let Some(choice) = choice else {
+            return;
+        };
+        let Some(PendingMerge { original, edited, fresh: Some(fresh), .. }) = self.pending_merge.take() else {
+            return;
+        };
+        // The document shows the edits until now; the choice folds into that step
+        match choice {
+            MergeChoice::Merge => {
+                let (merged, conflicts) = merge_cell_edits(&original, &edited, &fresh);
+                if conflicts > 0 {
+                    self.log(&format!("⚠️ {} edited cell(s) conflicted with the new extraction", conflicts));
+                }
+                self.log(&format!("🔀 Merged {} edit(s) onto the new extraction of page {}", edits, page + 1));
+                self.document.amend_cells(page, merged);
+            }
+            MergeChoice::KeepEdits => {}
+            MergeChoice::UseNew => {
+                self.log(&format!("♻️ Discarded the edits of page {}", page + 1));
+                if let Some(cached) = self.page_cache.get(&page).cloned() {
+                    self.document.insert_page(page, cached);
+                }
+            }
+        }
+    }
+
+    /// The current page as the document holds it, edits included.
+    fn current_matrix(&self) -> Option<&CharacterMatrix> {
+        self.document.page(self.current_page).map(|page| &page.matrix)
+    }
+
+    /// Hands this frame's raw-text grid edits to the document. An edit that
+    /// started a grid undo step starts a document one; the rest of an
+    /// operation (more typing, a dragged block landing) folds into it, so
+    /// undo takes back whole operations. Undo keys pressed in the grid step
+    /// the document's history.
+    fn commit_grid_edits(&mut self) {
+        let page = self.current_page;
+        let Some(grid) = &mut self.raw_text_matrix_grid else {
+            return;
+        };
+        match grid.take_edit() {
+            Some((cells, true)) => {
+                self.document.edit_cells(page, cells);
+            }
+            Some((cells, false)) => {
+                self.document.amend_cells(page, cells);
+            }
+            None => {}
+        }
+        for step in std::mem::take(&mut grid.history_requests) {
+            match step {
+                HistoryStep::Undo => self.document.undo(page),
+                HistoryStep::Redo => self.document.redo(page),
+            };
+        }
+    }
+
+    /// Brings the views up to date with the document: the raw-text grid
+    /// follows its page, and region annotations follow every page.
+    fn process_document_events(&mut self) {
+        let Some(events) = &self.document_events else {
+            return;
+        };
+        let events: Vec<DocumentEvent> = events.try_iter().collect();
+        for event in events {
+            match event {
+                DocumentEvent::Loaded { page } | DocumentEvent::Edited { page } | DocumentEvent::Restored { page } => {
+                    // Loaded pages got theirs from the maps
+                    if !matches!(event, DocumentEvent::Loaded { .. }) {
+                        self.sync_region_annotations(page);
+                    }
+                    if page != self.current_page {
+                        continue;
+                    }
+                    let (Some(grid), Some(document_page)) = (&mut self.raw_text_matrix_grid, self.document.page(page)) else {
+                        continue;
+                    };
+                    if matches!(event, DocumentEvent::Loaded { .. }) {
+                        grid.original = None;
+                    }
+                    if grid.matrix != *document_page.matrix.rows() {
+                        grid.set_matrix(document_page.matrix.rows().into_owned());
+                    }
+                }
+                DocumentEvent::Unloaded { page } if page == self.current_page => self.raw_text_matrix_grid = None,
+                DocumentEvent::Saved { .. } | DocumentEvent::Unloaded { .. } => {}
+            }
+        }
+    }
+
+    /// Mirrors a held page's region labels, tags and reading order into the
+    /// page cache and the files next to the PDF, so undoing a tag or a merge
+    /// undoes it there too.
+    fn sync_region_annotations(&mut self, page: usize) {
+        let Some(char_matrix) = self.document.page(page).map(|page| &page.matrix) else {
+            return;
+        };
+        if let Some(cached) = self.page_cache.get_mut(&page) {
+            cached.text_regions.clone_from(&char_matrix.text_regions);
+            cached.reading_order.clone_from(&char_matrix.reading_order);
+        }
+        let regions = &char_matrix.text_regions;
+        let labels = regions.iter().filter_map(|region| Some((region.region_id, region.label.clone()?))).collect();
+        let tags = regions.iter().filter_map(|region| Some((region.region_id, region.tag?))).collect();
+        let order = char_matrix.reading_order.clone();
+
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        if update_page_entry(&mut self.region_labels, page, labels) {
+            if let Err(e) = save_region_labels(&pdf_path, &self.region_labels) {
+                self.log(&format!("⚠️ Could not persist region labels: {}", e));
+            }
+        }
+        if update_page_entry(&mut self.region_tags, page, tags) {
+            if let Err(e) = save_region_tags(&pdf_path, &self.region_tags) {
+                self.log(&format!("⚠️ Could not persist region tags: {}", e));
+            }
+        }
+        if self.reading_orders.get(&page) != order.as_ref() {
+            match order {
+                Some(order) => self.reading_orders.insert(page, order),
+                None => self.reading_orders.remove(&page),
+            };
+            if let Err(e) = save_reading_orders(&pdf_path, &self.reading_orders) {
+                self.log(&format!("⚠️ Could not persist reading order: {}", e));
+            }
+        }
+    }
+
+    fn apply_extracted_matrix(&mut self, page: usize, mut character_matrix: CharacterMatrix) {
+        character_matrix.ensure_dense();
+        apply_region_labels(&mut character_matrix, self.region_labels.get(&page));
+        apply_region_tags(&mut character_matrix, self.region_tags.get(&page));
+        character_matrix.reading_order = self.reading_orders.get(&page).cloned();
+        let mut cached = character_matrix.clone();
+        cached.compact();
+        self.page_cache.insert(page, cached);
+        self.document.insert_page(page, character_matrix);
+        self.matrix_result.is_loading = false;
+        self.matrix_result.error = None;
+    }
+
+    /// Switches to `page`, re-using a cached extraction when there is one.
+    fn go_to_page(&mut self, page: usize, ctx: &egui::Context) {
+        if self.pdf_path.is_none() || page >= self.total_pages {
+            return;
+        }
+
+        self.commit_grid_edits();
+        // Only edited pages stay held densely; the page cache has the others
+        if page != self.current_page && !self.document.is_modified(self.current_page) {
+            self.document.remove_page(self.current_page);
+        }
+        self.current_page = page;
+        self.pending_merge = None;
+        self.raw_text_matrix_grid = None;
+        self.cancel_ferrules();
+        self.ferrules_output_cache = None;
+        self.ferrules_matrix_grid = None;
+        self.needs_render = true;
+        ctx.request_repaint();
+
+        if self.document.page(page).is_some() {
+            // Edited earlier; the edits and their history are still held
+            self.matrix_result = ExtractionResult::default();
+        } else if let Some(cached) = self.page_cache.get(&page).cloned() {
+            self.apply_extracted_matrix(page, cached);
+        } else {
+            self.extract_character_matrix(ctx);
+        }
+    }
+
+    /// Extracts every page not yet in the cache in the background so that
+    /// low-confidence navigation covers the whole document.
+    fn scan_document_for_review(&mut self, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        if self.review_scanning {
+            self.log("🔎 Document scan already in progress...");
+            return;
+        }
+
+        let pages: Vec<usize> = (0..self.total_pages)
+            .filter(|page| !self.page_cache.contains_key(page) && !self.jobs.is_pending(*page))
+            .collect();
+        if pages.is_empty() {
+            self.log("🔎 All pages already extracted");
+            return;
+        }
+
+        self.log(&format!("🔎 Scanning {} page(s) for low-confidence regions...", pages.len()));
+        self.review_scanning = true;
+        for page in pages {
+            let job = self.extraction_job(pdf_path.clone(), page);
+            self.jobs.submit(&self.runtime, ctx, page, job);
+        }
+    }
+
+    /// Applies finished extraction jobs: the current page goes into the
+    /// matrix view, background pages into the page cache.
+    fn process_extraction_jobs(&mut self) {
+        for (page, result) in self.jobs.poll() {
+            match &result {
+                Ok(_) => self.failed_pages.remove(&page),
+                Err(e) => self.failed_pages.insert(page, e.clone()),
+            };
+            match result {
+                Ok(character_matrix) if page == self.current_page => {
+                    let warnings: Vec<String> =
+                        character_matrix.warnings.iter().map(|w| format!("⚠️ {}", w)).collect();
+                    self.apply_extracted_matrix(page, character_matrix);
+                    self.log("✅ Character matrix extraction completed");
+                    // Keep showing the unsaved edits until the merge prompt is answered
+                    let pdf_path = self.pdf_path.as_ref();
+                    if let Some(merge) = self
+                        .pending_merge
+                        .as_mut()
+                        .filter(|merge| merge.page == page && Some(&merge.pdf_path) == pdf_path && merge.fresh.is_none())
+                    {
+                        merge.fresh = self.document.page(page).map(|page| page.matrix.rows().into_owned());
+                        self.document.edit_cells(page, merge.edited.clone());
+                    }
+                    for warning in warnings {
+                        self.log(&warning);
+                    }
+                }
+                Ok(mut character_matrix) => {
+                    apply_region_labels(&mut character_matrix, self.region_labels.get(&page));
+                    apply_region_tags(&mut character_matrix, self.region_tags.get(&page));
+                    character_matrix.reading_order = self.reading_orders.get(&page).cloned();
+                    character_matrix.compact();
+                    self.page_cache.insert(page, character_matrix);
+                }
+                Err(e) if page == self.current_page => {
+                    self.matrix_result.error = Some(e);
+                    self.matrix_result.is_loading = false;
+                    // The regenerated page can't be read; the document still holds the edits
+                    if self.pending_merge.take().is_some_and(|merge| merge.page == page) {
+                        self.log("⚠️ Kept the unsaved edits; re-extract once the PDF is readable");
+                    }
+                }
+                Err(e) => {
+                    self.log(&format!("⚠️ Extraction of page {} failed: {}", page + 1, e));
+                }
+            }
+        }
+
+        if self.review_scanning && self.jobs.pending() == 0 {
+            self.review_scanning = false;
+            let count = low_confidence_targets(&self.page_cache, self.review_threshold).len();
+            if self.failed_pages.is_empty() {
+                self.log(&format!("✅ Document scan complete: {} low-confidence region(s)", count));
+            } else {
+                self.log(&format!(
+                    "⚠️ Document scan complete: {} low-confidence region(s); {} - retry them from the ✗ menu",
+                    count,
+                    failed_pages_summary(&self.failed_pages)
+                ));
+            }
+        }
+    }
+
+    fn retry_failed_pages(&mut self, ctx: &egui::Context) {
+        let pages: Vec<usize> = self.failed_pages.keys().copied().collect();
+        if pages.is_empty() {
+            return;
+        }
+        self.log(&format!("🔁 Retrying {} failed page(s)", pages.len()));
+        self.reextract_pages(&pages, ctx);
+    }
+
+    fn jump_low_confidence(&mut self, step: i32, ctx: &egui::Context) {
+        let targets = low_confidence_targets(&self.page_cache, self.review_threshold);
+        let Some((page, region_idx)) = step_target(&targets, self.review_cursor, step) else {
+            self.log(&format!(
+                "🔎 No regions below {:.0}% confidence in {} extracted page(s)",
+                self.review_threshold * 100.0,
+                self.page_cache.len()
+            ));
+            return;
+        };
+
+        self.review_cursor = Some((page, region_idx));
+        if page != self.current_page {
+            self.go_to_page(page, ctx);
+        }
+
+        if let Some(region) = self
+            .page_cache
+            .get(&page)
+            .and_then(|m| m.text_regions.get(region_idx))
+        {
+            let position = targets.iter().position(|&t| t == (page, region_idx)).unwrap_or(0);
+            self.log(&format!(
+                "🔎 [{}/{}] Page {} region R{} ({:.0}%)",
+                position + 1,
+                targets.len(),
+                page + 1,
+                region.region_id + 1,
+                region.confidence * 100.0
+            ));
+            self.focus_region(region_idx);
+            self.focused_pane = FocusedPane::MatrixView;
+        }
+    }
+
+    /// Selects a region of the current page in the matrix, scrolls both panes
+    /// to it and flashes its overlay on the PDF.
+    fn focus_region(&mut self, region_idx: usize) {
+        let Some(bbox) = self
+            .current_matrix()
+            .and_then(|m| m.text_regions.get(region_idx))
+            .map(|r| r.bbox.clone())
+        else {
+            return;
+        };
+
+        self.selected_cell = Some((bbox.x, bbox.y));
+        self.active_tab = ExtractionTab::RawText;
+        self.region_flash = Some((region_idx, Instant::now()));
+        self.pdf_scroll_to_flash = true;
+        match &mut self.raw_text_matrix_grid {
+            Some(grid) => grid.select_block(&bbox),
+            // The grid is rebuilt on the next frame after a page change
+            None => self.pending_grid_selection = Some(bbox),
+        }
+    }
+
+    fn auto_char_override(&mut self, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        match self
+            .matrix_engine
+            .find_page_character_dimensions(&pdf_path, self.current_page)
+        {
+            Ok((char_width, char_height)) => {
+                self.char_override_edit = CharDimensions {
+                    char_width,
+                    char_height,
+                };
+                self.set_char_override(Some(self.char_override_edit), ctx);
+            }
+            Err(e) => self.log(&format!("❌ Could not compute char size: {}", e)),
+        }
+    }
+
+    fn open_regions_csv_dialog(&mut self, ctx: &egui::Context, export: bool) {
+        if self.regions_csv_receiver.is_some() {
+            self.log("📂 File dialog already in progress...");
+            return;
+        }
+
+        let suggested_name = self
+            .pdf_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}.regions.csv", s.to_string_lossy()))
+            .unwrap_or_else(|| "regions.csv".to_string());
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.regions_csv_receiver = Some(rx);
+
+        std::thread::spawn(move || {
+            let dialog = rfd::FileDialog::new().add_filter("CSV", &["csv"]);
+            let result = if export {
+                RegionsCsvDialogResult::Export(dialog.set_file_name(&suggested_name).save_file())
+            } else {
+                RegionsCsvDialogResult::Import(dialog.pick_file())
+            };
+
+            let _ = tx.send(result);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn open_edit_log_dialog(&mut self, ctx: &egui::Context) {
+        if self.edit_log_receiver.is_some() {
+            self.log("📂 File dialog already in progress...");
+            return;
+        }
+
+        let suggested_name = self
+            .pdf_path
+            .as_ref()
+            .and_then(|p| p.file_stem())
+            .map(|s| format!("{}.edits.json", s.to_string_lossy()))
+            .unwrap_or_else(|| "edits.json".to_string());
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.edit_log_receiver = Some(rx);
+
+        std::thread::spawn(move || {
+            let result = rfd::FileDialog::new()
+                .add_filter("JSON", &["json"])
+                .set_file_name(&suggested_name)
+                .save_file();
+
+            let _ = tx.send(result);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn process_edit_log_result(&mut self) {
+        let Some(result) = self
+            .edit_log_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.edit_log_receiver = None;
+
+        let (Some(path), Some(pdf_path)) = (result, self.pdf_path.clone()) else {
+            self.log("📂 Edit changelog export cancelled");
+            return;
+        };
+        let edits = load_edit_log(&pdf_path);
+        let changelog = EditChangelog {
+            document: pdf_path.display().to_string(),
+            edits: &edits,
+        };
+        match serde_json::to_string_pretty(&changelog)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&path, json)?))
+        {
+            Ok(_) => self.log(&format!("✅ Exported {} edit(s) to: {}", edits.len(), path.display())),
+            Err(e) => self.log(&format!("❌ Failed to export edit changelog: {}", e)),
+        }
+    }
+
+    fn process_regions_csv_result(&mut self) {
+        let Some(result) = self
+            .regions_csv_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.regions_csv_receiver = None;
+
+        match result {
+            RegionsCsvDialogResult::Export(Some(path)) => {
+                match std::fs::write(&path, regions_to_csv(&self.page_cache, self.region_min_confidence)) {
+                    Ok(_) => self.log(&format!(
+                        "✅ Exported regions of {} page(s) to: {}",
+                        self.page_cache.len(),
+                        path.display()
+                    )),
+                    Err(e) => self.log(&format!("❌ Failed to export regions: {}", e)),
+                }
+            }
+            RegionsCsvDialogResult::Import(Some(path)) => {
+                match std::fs::read_to_string(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|csv| labels_from_csv(&csv))
+                {
+                    Ok(labels) => self.set_region_labels(labels),
+                    Err(e) => self.log(&format!("❌ Failed to import labels: {}", e)),
+                }
+            }
+            _ => self.log("📂 Regions CSV dialog cancelled"),
+        }
+    }
+
+    fn set_region_tag(&mut self, region_idx: usize, tag: Option<RegionTag>) {
+        let page = self.current_page;
+        let Some(region) = self.current_matrix().and_then(|m| m.text_regions.get(region_idx)) else {
+            return;
+        };
+        let region_id = region.region_id;
+        let name = region.display_name();
+        // Reaches the page cache and the tags file with the document event
+        self.document.set_region_tag(page, region_id, tag);
+        self.log(&format!(
+            "🏷️ {} on page {} tagged {}",
+            name,
+            page + 1,
+            tag.map_or("(none)", |t| t.as_str())
+        ));
+    }
+
+    /// Index of the region of the current page under a pointer position on
+    /// the page image. Smallest region wins where they overlap.
+    fn region_at(&self, image_rect: egui::Rect, pos: egui::Pos2) -> Option<usize> {
+        let char_matrix = self.current_matrix()?;
+        let (x, y) = MatrixImageMapping::new(char_matrix, image_rect).cell_at(pos)?;
+        char_matrix
+            .text_regions
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.bbox.contains(x, y))
+            .min_by_key(|(_, r)| r.bbox.area())
+            .map(|(idx, _)| idx)
+    }
+
+    fn set_region_labels(&mut self, labels: RegionLabels) {
+        let count: usize = labels.values().map(|l| l.len()).sum();
+        self.region_labels = labels;
+
+        for (page, char_matrix) in self.page_cache.iter_mut() {
+            apply_region_labels(char_matrix, self.region_labels.get(page));
+        }
+        // Held pages without edits are reloaded rather than marked edited
+        let held: Vec<usize> = self.document.pages().map(|(page, _)| page).collect();
+        for page in held {
+            if self.document.is_modified(page) {
+                let labels = self.region_labels.get(&page);
+                self.document.edit(page, |char_matrix| apply_region_labels(char_matrix, labels));
+            } else if let Some(cached) = self.page_cache.get(&page).cloned() {
+                self.document.insert_page(page, cached);
+            }
+        }
+
+        if let Some(pdf_path) = &self.pdf_path {
+            if let Err(e) = save_region_labels(pdf_path, &self.region_labels) {
+                self.log(&format!("⚠️ Could not persist region labels: {}", e));
+            }
+        }
+        self.log(&format!("🏷️ Applied {} region label(s)", count));
+    }
+
+    fn label_monospace(&mut self, is_monospace: bool, ctx: &egui::Context) {
+        let Some(detection) = self.current_matrix().and_then(|m| m.monospace.clone())
+        else {
+            return;
+        };
+
+        self.monospace_training.add_sample(detection.features, is_monospace);
+        if let Err(e) = self.monospace_training.save() {
+            self.log(&format!("⚠️ Could not save monospace training data: {}", e));
+        }
+        let updated = self.monospace_training.model.predict(&detection.features);
+        self.log(&format!(
+            "🧠 Labelled page {} as {} ({} sample(s)); model now says {:.0}% monospace",
+            self.current_page + 1,
+            if is_monospace { "monospace" } else { "proportional" },
+            self.monospace_training.samples.len(),
+            updated * 100.0
+        ));
+        self.invalidate_stale_pages(ctx);
+    }
+
+    fn save_selection_as(&mut self, ctx: &egui::Context) {
+        if self.selection_save_receiver.is_some() {
+            self.log("📂 File dialog already in progress...");
+            return;
+        }
+
+        let Some(grid) = &self.raw_text_matrix_grid else {
+            return;
+        };
+        let Some(bbox) = grid.selection.bbox() else {
+            self.log("⚠️ Nothing selected");
+            return;
+        };
+        self.pending_selection_text = Some(matrix_to_text(&grid.selection.selected_block(&grid.matrix)));
+
+        let suggested_name = format!("selection_p{}_{}x{}.txt", self.current_page + 1, bbox.x, bbox.y);
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.selection_save_receiver = Some(rx);
+
+        std::thread::spawn(move || {
+            let result = rfd::FileDialog::new()
+                .add_filter("Text files", &["txt"])
+                .set_file_name(&suggested_name)
+                .save_file();
+
+            let _ = tx.send(result);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn process_selection_save_result(&mut self) {
+        let Some(result) = self
+            .selection_save_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.selection_save_receiver = None;
+        let text = self.pending_selection_text.take().unwrap_or_default();
+
+        match result {
+            Some(path) => match std::fs::write(&path, text) {
+                Ok(_) => self.log(&format!("✅ Saved selection to: {}", path.display())),
+                Err(e) => self.log(&format!("❌ Failed to save selection: {}", e)),
+            },
+            None => self.log("📂 Save selection cancelled"),
+        }
+    }
+
+    fn take_document(&mut self) -> DocumentTab {
+        // A ferrules run for the outgoing document starts again when it is shown
+        self.cancel_ferrules();
+        self.commit_grid_edits();
+        DocumentTab {
+            pdf_path: self.pdf_path.take(),
+            current_page: std::mem::take(&mut self.current_page),
+            total_pages: std::mem::take(&mut self.total_pages),
+            zoom_level: std::mem::replace(&mut self.zoom_level, 1.0),
+            pdf_texture: self.pdf_texture.take(),
+            page_range: std::mem::take(&mut self.page_range),
+            matrix_result: std::mem::take(&mut self.matrix_result),
+            ferrules_output_cache: self.ferrules_output_cache.take(),
+            ferrules_matrix_grid: self.ferrules_matrix_grid.take(),
+            raw_text_matrix_grid: self.raw_text_matrix_grid.take(),
+            jobs: std::mem::take(&mut self.jobs),
+            char_overrides: std::mem::take(&mut self.char_overrides),
+            page_cache: std::mem::take(&mut self.page_cache),
+            region_labels: std::mem::take(&mut self.region_labels),
+            region_tags: std::mem::take(&mut self.region_tags),
+            reading_orders: std::mem::take(&mut self.reading_orders),
+            segmentation: std::mem::take(&mut self.segmentation),
+            document: std::mem::take(&mut self.document),
+            document_events: self.document_events.take(),
+            matrix_view: std::mem::take(&mut self.matrix_view),
+            bookmarks: std::mem::take(&mut self.bookmarks),
+            review_cursor: self.review_cursor.take(),
+            review_scanning: std::mem::take(&mut self.review_scanning),
+            failed_pages: std::mem::take(&mut self.failed_pages),
+            pending_reextract: std::mem::take(&mut self.pending_reextract),
+            selected_cell: self.selected_cell.take(),
+        }
+    }
+
+    fn restore_document(&mut self, document: DocumentTab) {
+        self.pdf_path = document.pdf_path;
+        self.current_page = document.current_page;
+        self.total_pages = document.total_pages;
+        self.zoom_level = document.zoom_level;
+        self.pdf_texture = document.pdf_texture;
+        self.page_range = document.page_range;
+        self.matrix_result = document.matrix_result;
+        self.ferrules_output_cache = document.ferrules_output_cache;
+        self.ferrules_matrix_grid = document.ferrules_matrix_grid;
+        self.raw_text_matrix_grid = document.raw_text_matrix_grid;
+        self.jobs = document.jobs;
+        self.char_overrides = document.char_overrides;
+        self.page_cache = document.page_cache;
+        self.region_labels = document.region_labels;
+        self.region_tags = document.region_tags;
+        self.reading_orders = document.reading_orders;
+        self.segmentation = document.segmentation;
+        self.document = document.document;
+        self.document_events = document.document_events;
+        self.matrix_view = document.matrix_view;
+        self.bookmarks = document.bookmarks;
+        self.review_cursor = document.review_cursor;
+        self.review_scanning = document.review_scanning;
+        self.failed_pages = document.failed_pages;
+        self.pending_reextract = document.pending_reextract;
+        self.selected_cell = document.selected_cell;
+        // Dark mode may have been toggled while the tab was in the background
+        self.needs_render = true;
+        if self.show_history_panel {
+            self.refresh_history();
+        }
+    }
+
+    fn find_open_document(&self, path: &Path) -> Option<usize> {
+        self.document_tabs.iter().enumerate().position(|(i, tab)| {
+            let tab_path = match tab {
+                Some(tab) => tab.pdf_path.as_deref(),
+                None if i == self.active_document => self.pdf_path.as_deref(),
+                None => None,
+            };
+            tab_path == Some(path)
+        })
+    }
+
+    fn switch_document(&mut self, index: usize) {
+        if index == self.active_document || index >= self.document_tabs.len() {
+            return;
+        }
+        let Some(target) = self.document_tabs[index].take() else {
+            return;
+        };
+
+        let current = self.take_document();
+        self.document_tabs[self.active_document] = Some(current);
+        self.active_document = index;
+        self.restore_document(target);
+        self.pending_tab_close = None;
+    }
+
+    fn close_document(&mut self, index: usize) {
+        if index >= self.document_tabs.len() {
+            return;
+        }
+
+        let (title, dirty) = match &self.document_tabs[index] {
+            Some(tab) => (tab.title(), tab.document.has_unsaved_edits()),
+            None => (
+                document_title(self.pdf_path.as_deref()),
+                self.document.has_unsaved_edits(),
+            ),
+        };
+        if dirty && self.pending_tab_close != Some(index) {
+            self.pending_tab_close = Some(index);
+            self.log(&format!(
+                "⚠️ {} has unsaved edits - save, or close again to discard them",
+                title
+            ));
+            return;
+        }
+        self.pending_tab_close = None;
+
+        if index == self.active_document {
+            if self.document_tabs.len() > 1 {
+                let neighbour = if index + 1 < self.document_tabs.len() { index + 1 } else { index - 1 };
+                self.switch_document(neighbour);
+            } else {
+                drop(self.take_document());
+            }
+        }
+
+        self.document_tabs.remove(index);
+        if self.active_document > index {
+            self.active_document -= 1;
+        }
+        self.log(&format!("📑 Closed {}", title));
+    }
+
+    fn cycle_document(&mut self, step: isize) {
+        let count = self.document_tabs.len() as isize;
+        if count > 1 {
+            let next = (self.active_document as isize + step).rem_euclid(count) as usize;
+            self.switch_document(next);
+        }
+    }
+
+    fn load_pdf(&mut self, path: PathBuf, start_page: usize, ctx: &egui::Context) {
+        if !path.exists() {
+            self.log("❌ File does not exist");
+            return;
+        }
+
+        if !path.is_file() {
+            self.log("❌ Selection is not a file");
+            return;
+        }
+
+        if path.extension().and_then(|ext| ext.to_str()) != Some("pdf") {
+            self.log("❌ File is not a PDF");
+            return;
+        }
+
+        if let Some(index) = self.find_open_document(&path) {
+            self.log(&format!("📑 {} is already open", path.display()));
+            self.switch_document(index);
+            return;
+        }
+
+        match self.get_pdf_info(&path) {
+            Ok(pages) => {
+                // Keep the current document in its tab and open this one in a new tab
+                if self.pdf_path.is_some() {
+                    let current = self.take_document();
+                    self.document_tabs[self.active_document] = Some(current);
+                }
+                self.document_tabs.push(None);
+                self.active_document = self.document_tabs.len() - 1;
+
+                self.pdf_path = Some(path.clone());
+                self.char_overrides = load_char_overrides(&path);
+                self.region_labels = load_region_labels(&path);
+                self.region_tags = load_region_tags(&path);
+                self.reading_orders = load_reading_orders(&path);
+                self.segmentation = load_segmentation(&path);
+                self.document = DocumentModel::new(path.clone());
+                self.document_events = Some(self.document.subscribe());
+                self.matrix_view = load_matrix_view_prefs(&path);
+                self.total_pages = pages;
+                self.current_page = start_page.min(pages.saturating_sub(1));
+                self.log(&format!(
+                    "✅ Loaded PDF: {} ({} pages)",
+                    path.display(),
+                    pages
+                ));
+
+                if pages > 20 {
+                    self.page_range = "1-10".to_string();
+                    self.log("📄 Large PDF detected - Default page range set to 1-10");
+                } else {
+                    self.page_range.clear();
+                }
+
+                // Extraction and rendering both run in the background
+                self.log("🚀 Starting character matrix extraction...");
+                if let Err(e) = self.safe_extract_character_matrix(ctx) {
+                    self.log(&format!("❌ Matrix extraction failed: {}", e));
+                } else {
+                    self.active_tab = ExtractionTab::RawText;
+                }
+
+                if let Err(e) = self.safe_render_current_page(ctx) {
+                    self.log(&format!("⚠️ Could not render page: {}", e));
+                }
+            }
+            Err(e) => {
+                self.log(&format!("❌ Failed to load PDF: {}", e));
+            }
+        }
+    }
+
+    fn safe_render_current_page(&mut self, ctx: &egui::Context) -> Result<()> {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.render_current_page(ctx);
+        })) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(anyhow::anyhow!("Page rendering panicked")),
+        }
+    }
+
+    fn safe_extract_character_matrix(&mut self, ctx: &egui::Context) -> Result<()> {
+        if self.pdf_path.is_none() {
+            return Err(anyhow::anyhow!("No PDF loaded"));
+        }
+
+        if self.jobs.is_pending(self.current_page) {
+            return Err(anyhow::anyhow!("Extraction already in progress"));
+        }
+
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.extract_character_matrix(ctx);
+        })) {
             Ok(_) => Ok(()),
             Err(_) => Err(anyhow::anyhow!("Matrix extraction panicked")),
         }
     }
 
-    fn get_pdf_info(&self, path: &PathBuf) -> Result<usize> {
-        if Command::new("mutool").arg("--version").output().is_err() {
-            return Err(anyhow::anyhow!("mutool not found - install mupdf-tools"));
+    fn get_pdf_info(&self, path: &PathBuf) -> Result<usize> {
+        pdf_page_count(path)
+    }
+
+    /// Renders the current page on a background thread so that text
+    /// extraction, which is started alongside, runs in parallel. The result is
+    /// picked up by `process_render_result`.
+    fn render_current_page(&mut self, ctx: &egui::Context) {
+        if let Some(pdf_path) = self.pdf_path.clone() {
+            let request = RenderRequest {
+                pdf_path,
+                page: self.current_page,
+                dark_mode: self.pdf_dark_mode,
+                dpi: 150.0 * self.zoom_level,
+            };
+            let ctx = ctx.clone();
+            let (tx, rx) = std::sync::mpsc::channel();
+            self.render_receiver = Some(rx);
+
+            std::thread::spawn(move || {
+                let result = render_page_image(&request);
+                let _ = tx.send((request, result));
+                ctx.request_repaint();
+            });
+        }
+    }
+
+    fn process_render_result(&mut self, ctx: &egui::Context) {
+        let Some((request, result)) = self
+            .render_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.render_receiver = None;
+
+        // Ignore renders for a page or document we have since moved away from
+        if self.pdf_path.as_ref() != Some(&request.pdf_path) || self.current_page != request.page {
+            return;
+        }
+
+        match result {
+            Ok(color_image) => {
+                self.pdf_texture = Some(ctx.load_texture(
+                    format!("pdf_page_{}", request.page),
+                    color_image,
+                    Default::default(),
+                ));
+
+                self.log(&format!(
+                    "📄 Rendered page {} {}",
+                    request.page + 1,
+                    if request.dark_mode { "🌙" } else { "" }
+                ));
+            }
+            Err(e) => self.log(&format!("❌ {}", e)),
+        }
+    }
+
+    /// Tracks a rectangle dragged on the page image and, on release,
+    /// re-extracts the cells it covers.
+    fn handle_area_drag(&mut self, ui: &mut egui::Ui, response: &egui::Response) {
+        if response.drag_started() {
+            self.area_drag = response.interact_pointer_pos().map(|p| (p, p));
+        }
+        if response.dragged() {
+            if let (Some((start, _)), Some(pos)) = (self.area_drag, response.interact_pointer_pos()) {
+                self.area_drag = Some((start, pos));
+            }
+        }
+
+        if let Some((a, b)) = self.area_drag {
+            let rect = egui::Rect::from_two_pos(a, b).intersect(response.rect);
+            ui.painter().rect_filled(rect, 0.0, theme().accent.gamma_multiply(0.15));
+            ui.painter().rect_stroke(rect, 0.0, egui::Stroke::new(1.5, theme().accent));
+        }
+
+        if response.drag_released() {
+            if let Some((a, b)) = self.area_drag.take() {
+                self.extract_area(a, b, response.rect, ui.ctx());
+            }
+        }
+    }
+
+    fn extract_area(&mut self, a: egui::Pos2, b: egui::Pos2, image_rect: egui::Rect, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        if self.area_receiver.is_some() {
+            self.log("⚠️ Area extraction already in progress...");
+            return;
+        }
+        let Some(char_matrix) = self.current_matrix() else {
+            self.log("⚠️ Extract the page before drawing a region");
+            return;
+        };
+
+        let mapping = MatrixImageMapping::new(char_matrix, image_rect);
+        let Some(bbox) = mapping.bbox_between(a, b) else {
+            return;
+        };
+        // Snap the area to the cells it covers so the result lines up exactly
+        let cells = mapping.bbox_rect(&bbox);
+        let area = [
+            (cells.left() - image_rect.left()) / image_rect.width(),
+            (cells.top() - image_rect.top()) / image_rect.height(),
+            (cells.right() - image_rect.left()) / image_rect.width(),
+            (cells.bottom() - image_rect.top()) / image_rect.height(),
+        ];
+
+        self.log(&format!(
+            "▭ Re-extracting {}x{} cells at {},{} on page {}...",
+            bbox.width,
+            bbox.height,
+            bbox.x,
+            bbox.y,
+            self.current_page + 1
+        ));
+        let page = self.current_page;
+        let ctx = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.area_receiver = Some(rx);
+
+        std::thread::spawn(move || {
+            let result = CharacterMatrixEngine::new()
+                .extract_page_area(&pdf_path, page, area, bbox.width, bbox.height)
+                .map_err(ChonkerError::from_anyhow);
+            let _ = tx.send((pdf_path, page, bbox, result));
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_area_result(&mut self) {
+        let Some((pdf_path, page, bbox, result)) = self
+            .area_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.area_receiver = None;
+
+        if self.pdf_path.as_ref() != Some(&pdf_path) || self.current_page != page {
+            self.log("⚠️ Discarded area extraction for a page no longer shown");
+            return;
+        }
+
+        match result {
+            Ok(block) => {
+                // The grid picks the cells up from the document event
+                self.document.edit(page, |char_matrix| paste_into(char_matrix.rows_mut(), &block, (bbox.y, bbox.x)));
+                self.log(&format!(
+                    "✅ Replaced {}x{} cells at {},{} from the selected area",
+                    bbox.width, bbox.height, bbox.x, bbox.y
+                ));
+            }
+            Err(e) => self.log(&format!("❌ Area extraction failed: {}", e)),
+        }
+    }
+
+    fn extract_character_matrix(&mut self, ctx: &egui::Context) {
+        if self.pdf_path.is_none() {
+            self.log("⚠️ No PDF loaded. Open a file first.");
+            return;
+        }
+
+        let pdf_path = match &self.pdf_path {
+            Some(path) => path.clone(),
+            None => {
+                self.log("❌ No PDF file selected");
+                return;
+            }
+        };
+
+        self.matrix_result.is_loading = true;
+        self.matrix_result.error = None;
+
+        self.log(&format!(
+            "🔄 Processing PDF page {}...",
+            self.current_page + 1
+        ));
+
+        let current_page = self.current_page;
+        let job = self.extraction_job(pdf_path, current_page);
+        self.jobs.submit(&self.runtime, ctx, current_page, job);
+    }
+
+    /// Extracts `page` with its character override, else `chonker.toml`'s
+    /// cell size, and with the configured backend when one is set.
+    fn extraction_job(
+        &self,
+        pdf_path: PathBuf,
+        page: usize,
+    ) -> impl std::future::Future<Output = Result<CharacterMatrix, ChonkerError>> + Send + 'static {
+        let dimensions = self.char_overrides.get(&page).copied().or(self.config.char_size);
+        let backend = self.config.backend;
+        let segmentation = self.segmentation;
+        async move {
+            let mut char_matrix = match backend {
+                Some(backend) => extract_page_with_backend(pdf_path, page, dimensions, backend).await,
+                None => extract_page(pdf_path, page, dimensions).await,
+            }?;
+            apply_segmentation(&mut char_matrix, segmentation);
+            Ok(char_matrix)
+        }
+    }
+
+    fn save_edited_matrix(&mut self) {
+        let (Some(pdf_path), Some(document_page)) = (self.pdf_path.clone(), self.document.page(self.current_page)) else {
+            return;
+        };
+        let output_path = pdf_path.with_extension("matrix.txt");
+        let content = matrix_to_text(&document_page.matrix.rows());
+
+        if let Err(e) = self.document.save(self.current_page) {
+            self.log(&format!("⚠️ Could not record matrix version: {}", e));
+        }
+
+        match std::fs::write(&output_path, content) {
+            Ok(_) => {
+                self.log(&format!(
+                    "✅ Saved edited matrix to: {}",
+                    output_path.display()
+                ));
+                if self.show_history_panel {
+                    self.refresh_history();
+                }
+            }
+            Err(e) => {
+                self.log(&format!("❌ Failed to save matrix: {}", e));
+            }
+        }
+    }
+
+    fn refresh_history(&mut self) {
+        self.history_versions = match &self.pdf_path {
+            Some(pdf_path) => list_matrix_versions(pdf_path, self.current_page),
+            None => Vec::new(),
+        };
+        self.history_page = self.current_page;
+        self.history_selected = None;
+        self.history_preview = None;
+    }
+
+    fn toggle_history_panel(&mut self) {
+        self.show_history_panel = !self.show_history_panel;
+        if self.show_history_panel {
+            self.refresh_history();
+        }
+    }
+
+    fn restore_matrix_version(&mut self, index: usize) {
+        let Some(version) = self.history_versions.get(index).cloned() else {
+            return;
+        };
+        match std::fs::read_to_string(&version.path) {
+            Ok(content) => {
+                self.document.edit_cells(self.current_page, text_to_matrix(&content));
+                self.log(&format!(
+                    "⏪ Restored matrix version from {}",
+                    format_age(version.saved_at)
+                ));
+            }
+            Err(e) => self.log(&format!("❌ Failed to read matrix version: {}", e)),
+        }
+    }
+
+    fn show_help_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_help;
+        let diagnostics = self.current_matrix().map(|matrix| {
+            page_diagnostics(
+                matrix,
+                self.char_overrides.contains_key(&self.current_page),
+                self.segmentation,
+                self.region_min_confidence,
+            )
+        });
+        let mut follow = None;
+        egui::Window::new("EXTRACTION HELP")
+            .open(&mut open)
+            .default_width(640.0)
+            .default_height(420.0)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(format!("WHY PAGE {} LOOKS LIKE THIS", self.current_page + 1)).color(theme().highlight).monospace());
+                match &diagnostics {
+                    Some(diagnostics) => {
+                        egui::Grid::new("help_diagnostics").striped(true).show(ui, |ui| {
+                            for diagnostic in diagnostics {
+                                ui.label(RichText::new(&diagnostic.finding).color(theme().fg).monospace().size(10.0));
+                                if ui.small_button(RichText::new(HELP_TOPICS[diagnostic.topic].title).monospace().size(10.0))
+                                    .on_hover_text("Read how this heuristic works")
+                                    .clicked() {
+                                    self.help_query.clear();
+                                    self.help_topic = diagnostic.topic;
+                                }
+                                if ui.small_button(RichText::new(format!("⚙ {}", diagnostic.setting.label())).monospace().size(10.0))
+                                    .on_hover_text("Go to the setting that tunes this")
+                                    .clicked() {
+                                    follow = Some(diagnostic.setting);
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    }
+                    None => {
+                        ui.label(RichText::new("No character matrix yet - press [M] to extract this page").color(theme().dim).monospace());
+                    }
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Search:").color(theme().dim).monospace());
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.help_query)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(240.0),
+                    );
+                });
+                ui.separator();
+
+                let matches = search_help(&self.help_query);
+                if !matches.is_empty() && !matches.contains(&self.help_topic) {
+                    self.help_topic = matches[0];
+                }
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(170.0);
+                        for &i in &matches {
+                            let selected = self.help_topic == i;
+                            if ui
+                                .selectable_label(selected, RichText::new(HELP_TOPICS[i].title).monospace())
+                                .clicked()
+                            {
+                                self.help_topic = i;
+                            }
+                        }
+                        if matches.is_empty() {
+                            ui.label(RichText::new("No matching topics").color(theme().dim).monospace());
+                        }
+                    });
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .id_source("help_body")
+                        .show(ui, |ui| {
+                            if let Some(topic) = HELP_TOPICS.get(self.help_topic).filter(|_| !matches.is_empty()) {
+                                ui.label(RichText::new(topic.body).color(theme().fg).monospace().size(11.0));
+                            }
+                        });
+                });
+            });
+        self.show_help = open;
+        if let Some(setting) = follow {
+            self.follow_help_setting(setting);
+        }
+    }
+
+    fn scroll_to_help_setting(&mut self, ui: &egui::Ui, setting: HelpSetting, rect: Rect) {
+        if self.help_scroll_to == Some(setting) {
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+            self.help_scroll_to = None;
+        }
+    }
+
+    /// Brings a diagnostic's setting into view: the rows under the raw-text
+    /// grid scroll to themselves on the next frame.
+    fn follow_help_setting(&mut self, setting: HelpSetting) {
+        match setting {
+            HelpSetting::Backend => self.log(&format!(
+                "⚙️ The backend is set by `backend = \"auto\" | \"mutool\" | \"pdfium\" | \"stext\"` in {}",
+                ChonkerConfig::path().display()
+            )),
+            HelpSetting::RegionPanel | HelpSetting::Segmentation => self.show_region_panel = true,
+            HelpSetting::CharSize | HelpSetting::MonospaceLabel | HelpSetting::ExtractionLimits => {
+                self.active_tab = ExtractionTab::RawText;
+                self.help_scroll_to = Some(setting);
+            }
+        }
+    }
+
+    fn run_pdf_search(&mut self, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        let query = self.pdf_search_query.trim().to_string();
+        if query.is_empty() {
+            self.pdf_search_hits.clear();
+            return;
+        }
+        let pages: Vec<usize> = if self.pdf_search_all_pages {
+            (0..self.total_pages).collect()
+        } else {
+            vec![self.current_page]
+        };
+
+        let ctx = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.pdf_search_receiver = Some(rx);
+        std::thread::spawn(move || {
+            let result = search_pdf_text(&pdf_path, &pages, &query).map_err(|e| e.to_string());
+            let _ = tx.send((pdf_path, result));
+            ctx.request_repaint();
+        });
+    }
+
+    fn process_pdf_search_result(&mut self) {
+        let Some((pdf_path, result)) = self
+            .pdf_search_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.pdf_search_receiver = None;
+        if self.pdf_path.as_ref() != Some(&pdf_path) {
+            return;
+        }
+
+        match result {
+            Ok(hits) => {
+                self.log(&format!("🔎 \"{}\": {} hit(s) in the PDF text", self.pdf_search_query.trim(), hits.len()));
+                self.pdf_search_hits = hits;
+                self.pdf_search_selected = None;
+            }
+            Err(e) => self.log(&format!("❌ PDF search failed: {}", e)),
+        }
+    }
+
+    fn show_pdf_search_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.pdf_search_open;
+        let mut search = false;
+        let mut jump = None;
+
+        egui::Window::new("FIND IN PDF")
+            .open(&mut open)
+            .default_width(380.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.pdf_search_query)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(220.0),
+                    );
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        search = true;
+                    }
+                    if ui.button(RichText::new("Search").monospace()).clicked() {
+                        search = true;
+                    }
+                    if self.pdf_search_receiver.is_some() {
+                        ui.spinner();
+                    }
+                });
+                ui.checkbox(&mut self.pdf_search_all_pages, RichText::new("All pages").monospace());
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .id_source("pdf_search_hits")
+                    .show(ui, |ui| {
+                        for (i, hit) in self.pdf_search_hits.iter().enumerate() {
+                            let label = RichText::new(format!("p{:<4} {}", hit.page + 1, hit.context))
+                                .color(if hit.page == self.current_page { theme().fg } else { theme().dim })
+                                .monospace()
+                                .size(10.0);
+                            if ui.selectable_label(self.pdf_search_selected == Some(i), label).clicked() {
+                                jump = Some(i);
+                            }
+                        }
+                    });
+            });
+
+        if search {
+            self.run_pdf_search(ctx);
+        }
+        if let Some(i) = jump {
+            let page = self.pdf_search_hits[i].page;
+            self.pdf_search_selected = Some(i);
+            self.pdf_search_scroll = true;
+            if page != self.current_page {
+                self.go_to_page(page, ctx);
+            }
+        }
+        self.pdf_search_open = open;
+    }
+
+    /// Outlines PDF search hits on the current page; the selected hit is
+    /// filled and scrolled into view.
+    fn draw_pdf_search_hits(&mut self, ui: &mut egui::Ui, image_response: &egui::Response) {
+        let image_rect = image_response.rect;
+        let to_screen = |r: &[f32; 4]| {
+            egui::Rect::from_min_max(
+                image_rect.min + Vec2::new(r[0] * image_rect.width(), r[1] * image_rect.height()),
+                image_rect.min + Vec2::new(r[2] * image_rect.width(), r[3] * image_rect.height()),
+            )
+        };
+
+        let painter = ui.painter();
+        let mut scroll_target = None;
+        for (i, hit) in self.pdf_search_hits.iter().enumerate() {
+            if hit.page != self.current_page {
+                continue;
+            }
+            let selected = self.pdf_search_selected == Some(i);
+            for rect in hit.rects.iter().map(to_screen) {
+                let fill = if selected { 0.45 } else { 0.2 };
+                painter.rect_filled(rect, 1.0, theme().warning.gamma_multiply(fill));
+                if selected {
+                    painter.rect_stroke(rect, 1.0, Stroke::new(1.5, theme().warning));
+                    scroll_target.get_or_insert(rect);
+                }
+            }
+        }
+
+        if std::mem::take(&mut self.pdf_search_scroll) {
+            if let Some(rect) = scroll_target {
+                ui.scroll_to_rect(rect, Some(egui::Align::Center));
+            }
+        }
+    }
+
+    /// Scales the matrix font by `factor` (or resets it when `None`) and
+    /// remembers the size for this document.
+    fn zoom_matrix(&mut self, factor: Option<f32>) {
+        let font_size = match factor {
+            Some(factor) => (self.matrix_view.font_size * factor).clamp(MATRIX_FONT_RANGE.0, MATRIX_FONT_RANGE.1),
+            None => MATRIX_FONT_SIZE,
+        };
+        if (font_size - self.matrix_view.font_size).abs() < f32::EPSILON {
+            return;
+        }
+        self.matrix_view.font_size = font_size;
+        if let Some(pdf_path) = &self.pdf_path {
+            if let Err(e) = save_matrix_view_prefs(pdf_path, &self.matrix_view) {
+                self.log(&format!("⚠️ Could not save matrix view settings: {}", e));
+            }
+        }
+    }
+
+    fn set_drop_policy(&mut self, policy: DropPolicy) {
+        self.matrix_view.drop_policy = policy;
+        if let Some(pdf_path) = &self.pdf_path {
+            if let Err(e) = save_matrix_view_prefs(pdf_path, &self.matrix_view) {
+                self.log(&format!("⚠️ Could not save matrix view settings: {}", e));
+            }
         }
+    }
+
+    /// Starts ferrules on the runtime for the current page unless its output
+    /// is cached or a run is already going.
+    fn ensure_ferrules_output(&mut self, ctx: &egui::Context) {
+        if self.ferrules_output_cache.is_some() || self.ferrules_receiver.is_some() {
+            return;
+        }
+        self.start_ferrules(false, ctx);
+    }
+
+    /// Runs ferrules again for the current page, replacing its result in the
+    /// disk cache.
+    fn rerun_ferrules(&mut self, ctx: &egui::Context) {
+        self.cancel_ferrules();
+        self.ferrules_output_cache = None;
+        self.ferrules_matrix_grid = None;
+        self.start_ferrules(true, ctx);
+    }
+
+    fn start_ferrules(&mut self, rerun: bool, ctx: &egui::Context) {
+        let Some(pdf_path) = self.pdf_path.clone() else {
+            return;
+        };
+        let Some(ferrules_path) = self.ferrules_binary.clone() else {
+            self.ferrules_output_cache = Some("❌ Ferrules binary not found".to_string());
+            return;
+        };
+        self.log(&format!("🔄 Running Ferrules for page {}...", self.current_page + 1));
+        let page = self.current_page;
+        let engine = CharacterMatrixEngine {
+            char_width: self.matrix_engine.char_width,
+            char_height: self.matrix_engine.char_height,
+            limits: self.matrix_engine.limits,
+            ..CharacterMatrixEngine::new()
+        };
+        let ctx = ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+        self.ferrules_receiver = Some(rx);
+        self.ferrules_task = Some(self.runtime.spawn(async move {
+            let result = engine.process_pdf_with_ferrules(&pdf_path, &ferrules_path, page, rerun).await;
+            let _ = tx.send((pdf_path, page, result));
+            ctx.request_repaint();
+        }));
+    }
+
+    fn process_ferrules_result(&mut self) {
+        let Some((pdf_path, page, result)) = self
+            .ferrules_receiver
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.ferrules_receiver = None;
+        self.ferrules_task = None;
+
+        if self.pdf_path.as_ref() != Some(&pdf_path) || self.current_page != page {
+            self.log("⚠️ Discarded Ferrules output for a page no longer shown");
+            return;
+        }
+
+        match result {
+            Ok(char_matrix) => {
+                self.ferrules_output_cache = Some(format!(
+                    "📄 Page {}/{}\n{}",
+                    page + 1,
+                    self.total_pages,
+                    self.matrix_engine.render_matrix_as_string(&char_matrix)
+                ));
+                self.ferrules_matrix_grid = Some(MatrixGrid::new(&matrix_to_text(&char_matrix.rows())));
+                self.log(&format!("✅ Ferrules found {} blocks", char_matrix.text_regions.len()));
+            }
+            Err(e) => {
+                self.ferrules_output_cache = Some(format!("❌ {}", e));
+                self.log(&format!("❌ Ferrules failed: {}", e));
+            }
+        }
+    }
+
+    /// Stops a ferrules run that is still going, killing the process.
+    fn cancel_ferrules(&mut self) {
+        self.ferrules_receiver = None;
+        if let Some(task) = self.ferrules_task.take() {
+            if !task.is_finished() {
+                task.abort();
+                self.log("⏹ Cancelled Ferrules run");
+            }
+        }
+    }
+
+    /// RawText and SmartLayout matrices side by side on one scroll area so
+    /// rows stay aligned, with differing cells highlighted.
+    fn show_compare_view(&mut self, ui: &mut egui::Ui) {
+        if self.pdf_path.is_none() {
+            return;
+        }
+        self.ensure_ferrules_output(ui.ctx());
+
+        let (Some(raw), Some(smart)) = (
+            self.current_matrix().map(|m| m.rows()),
+            self.ferrules_matrix_grid.as_ref().map(|g| &g.matrix),
+        ) else {
+            let running = self.ferrules_receiver.is_some();
+            ui.centered_and_justified(|ui| {
+                if running {
+                    ui.spinner();
+                }
+                ui.label(
+                    RichText::new("Both a raw text matrix ([M]) and a Smart Layout result are needed to compare")
+                        .color(theme().dim)
+                        .monospace(),
+                );
+            });
+            return;
+        };
+
+        let mask = matrix_diff(&raw, smart);
+        let differing = mask.iter().flatten().filter(|&&d| d).count();
+        let total: usize = mask.iter().map(|row| row.len()).sum();
+        ui.label(
+            RichText::new(format!(
+                "RAW TEXT ({}) vs SMART LAYOUT (ferrules) · {} of {} cells differ",
+                self.current_matrix().and_then(|cm| cm.backend).map_or("?", ExtractionBackend::label),
+                differing,
+                total
+            ))
+            .color(if differing == 0 { theme().success } else { theme().warning })
+            .monospace()
+            .size(10.0),
+        );
+
+        let font_size = self.matrix_view.font_size;
+        egui::Frame::none().fill(theme().bg).show(ui, |ui| {
+            egui::ScrollArea::both()
+                .id_source("compare_scroll_area")
+                .auto_shrink([false; 2])
+                .show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        paint_diff_matrix(ui, &raw, &mask, font_size);
+                        ui.separator();
+                        paint_diff_matrix(ui, smart, &mask, font_size);
+                    });
+                });
+        });
+    }
+
+    fn show_status_bar(&self, ctx: &egui::Context) {
+        let grid = match self.active_tab {
+            ExtractionTab::RawText | ExtractionTab::Compare => self.raw_text_matrix_grid.as_ref(),
+            ExtractionTab::SmartLayout => self.ferrules_matrix_grid.as_ref(),
+        };
+        let character_matrix = self.current_matrix();
+
+        egui::TopBottomPanel::bottom("status_bar")
+            .exact_height(20.0)
+            .frame(egui::Frame::none().fill(theme().bg).inner_margin(egui::Margin::symmetric(8.0, 2.0)))
+            .show(ctx, |ui| {
+                ui.horizontal_centered(|ui| {
+                    let sep = |ui: &mut egui::Ui| {
+                        ui.label(RichText::new("│").color(theme().chrome).monospace().size(10.0));
+                    };
+                    let field = |ui: &mut egui::Ui, text: String, color: Color32| {
+                        ui.label(RichText::new(text).color(color).monospace().size(10.0));
+                    };
+
+                    if let Some(vim) = grid.and_then(|g| g.vim.as_ref()) {
+                        field(ui, format!("-- {} --", vim.mode.label()), theme().accent);
+                        sep(ui);
+                    }
+                    let cursor = grid.and_then(|g| g.cursor_pos);
+                    field(
+                        ui,
+                        match cursor {
+                            Some((row, col)) => format!("Row {}, Col {}", row, col),
+                            None => "Row –, Col –".to_string(),
+                        },
+                        theme().fg,
+                    );
+                    if let Some(selection) = grid.map(|g| &g.selection) {
+                        if let Some(bbox) = selection.bbox() {
+                            sep(ui);
+                            let parts = selection.rects().len();
+                            let shape = if parts > 1 { format!(" ({} rects)", parts) } else { String::new() };
+                            field(ui, format!("Sel {}×{}{}", bbox.height, bbox.width, shape), theme().fg);
+                        }
+                    }
+                    sep(ui);
+                    field(
+                        ui,
+                        if self.total_pages > 0 {
+                            format!("Page {}/{}", self.current_page + 1, self.total_pages)
+                        } else {
+                            "No document".to_string()
+                        },
+                        theme().dim,
+                    );
+                    if self.jobs.pending() > 0 {
+                        sep(ui);
+                        let (done, total) = self.jobs.progress();
+                        let percent = self.jobs.percent();
+                        let pages: Vec<String> = self
+                            .jobs
+                            .pages()
+                            .map(|(page, status)| format!("{} p{}", status.icon(), page + 1))
+                            .collect();
+                        ui.add(
+                            egui::ProgressBar::new(percent / 100.0)
+                                .desired_width(110.0)
+                                .text(RichText::new(format!("{}/{} · {:.0}%", done, total, percent)).monospace().size(9.0)),
+                        )
+                        .on_hover_text(pages.join("\n"));
+                    }
+                    if let Some((_, received, total)) = &self.download_progress {
+                        sep(ui);
+                        let mb = |bytes: u64| bytes as f32 / (1024.0 * 1024.0);
+                        match total {
+                            Some(total) => {
+                                ui.add(
+                                    egui::ProgressBar::new(*received as f32 / (*total).max(1) as f32)
+                                        .desired_width(110.0)
+                                        .text(RichText::new(format!("🌐 {:.1}/{:.1} MB", mb(*received), mb(*total))).monospace().size(9.0)),
+                                );
+                            }
+                            None => field(ui, format!("🌐 {:.1} MB", mb(*received)), theme().dim),
+                        }
+                    }
+                    if let Some(cm) = character_matrix {
+                        sep(ui);
+                        field(
+                            ui,
+                            format!("{}×{} · {} regions · {} objects", cm.width, cm.height, cm.text_regions.len(), cm.original_text.len()),
+                            theme().dim,
+                        );
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let backend = match self.active_tab {
+                            ExtractionTab::SmartLayout => Some("ferrules"),
+                            ExtractionTab::Compare => Some("compare"),
+                            ExtractionTab::RawText => character_matrix.and_then(|cm| cm.backend).map(ExtractionBackend::label),
+                        };
+                        if let Some(backend) = backend {
+                            field(ui, backend.to_string(), theme().accent);
+                            sep(ui);
+                        }
+                        if self.document.is_modified(self.current_page) {
+                            field(ui, "● modified".to_string(), theme().warning);
+                        } else {
+                            field(ui, "saved".to_string(), theme().dim);
+                        }
+                    });
+                });
+            });
+    }
+
+    fn set_theme_preset(&mut self, name: &str) {
+        self.theme_config.preset = Some(name.to_string());
+        set_theme(self.theme_config.resolve());
+        if let Err(e) = self.theme_config.save() {
+            self.log(&format!("⚠️ Could not save theme: {}", e));
+        } else {
+            self.log(&format!("🎨 Theme: {}", name));
+        }
+    }
+
+    fn show_shortcuts_overlay(&mut self, ctx: &egui::Context) {
+        let pane_name = match self.focused_pane {
+            FocusedPane::PdfView => "PDF pane",
+            FocusedPane::MatrixView => "Matrix pane",
+        };
+
+        egui::Area::new("shortcuts_overlay")
+            .order(egui::Order::Foreground)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(theme().bg.gamma_multiply(0.95))
+                    .stroke(Stroke::new(1.0, theme().highlight))
+                    .inner_margin(12.0)
+                    .show(ui, |ui| {
+                        ui.label(
+                            RichText::new(format!("SHORTCUTS · {}", pane_name))
+                                .color(theme().highlight)
+                                .monospace()
+                                .strong(),
+                        );
+                        ui.separator();
+                        egui::Grid::new("shortcuts_grid").num_columns(2).spacing([16.0, 2.0]).show(ui, |ui| {
+                            for shortcut in shortcuts_for(self.focused_pane) {
+                                let color = if shortcut.scope == ShortcutScope::Global { theme().dim } else { theme().fg };
+                                ui.label(RichText::new(shortcut.keys).color(theme().warning).monospace().size(11.0));
+                                ui.label(RichText::new(shortcut.action).color(color).monospace().size(11.0));
+                                ui.end_row();
+                            }
+                        });
+                        ui.separator();
+                        ui.label(RichText::new("Tab switches pane · ? or Esc closes").color(theme().dim).monospace().size(10.0));
+                    });
+            });
+    }
+
+    fn open_matrix_search(&mut self) {
+        self.matrix_search_open = true;
+        self.matrix_search_focus = true;
+        self.active_tab = ExtractionTab::RawText;
+    }
+
+    fn close_matrix_search(&mut self) {
+        self.matrix_search_open = false;
+        if let Some(grid) = &mut self.raw_text_matrix_grid {
+            grid.clear_search();
+        }
+    }
 
-        let output = Command::new("mutool").arg("info").arg(path).output()?;
+    /// Search bar above the raw-text grid: live query, hit count and
+    /// next/previous navigation (Enter / Shift+Enter, Esc closes).
+    fn show_matrix_search_bar(&mut self, ui: &mut egui::Ui) {
+        let mut step = None;
+        let mut close = false;
 
-        let info = String::from_utf8_lossy(&output.stdout);
-        for line in info.lines() {
-            if line.contains("Pages:") {
-                if let Some(pages_str) = line.split(':').nth(1) {
-                    return pages_str
-                        .trim()
-                        .parse()
-                        .map_err(|e| anyhow::anyhow!("Parse error: {}", e));
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Find:").color(theme().dim).monospace());
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.matrix_search_query)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(200.0),
+            );
+            if std::mem::take(&mut self.matrix_search_focus) {
+                response.request_focus();
+            }
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                } else if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    step = Some(if ui.input(|i| i.modifiers.shift) { -1 } else { 1 });
+                    response.request_focus();
                 }
             }
+
+            let (count, current) = self
+                .raw_text_matrix_grid
+                .as_ref()
+                .map_or((0, None), |g| (g.search_matches.len(), g.current_match));
+            let status = match (count, current) {
+                (0, _) if self.matrix_search_query.is_empty() => String::new(),
+                (0, _) => "no matches".to_string(),
+                (n, Some(i)) => format!("{}/{}", i + 1, n),
+                (n, None) => format!("{} match{}", n, if n == 1 { "" } else { "es" }),
+            };
+            ui.label(RichText::new(status).color(if count == 0 { theme().dim } else { theme().fg }).monospace().size(10.0));
+
+            if ui.add_enabled(count > 0, egui::Button::new("◀")).on_hover_text("Previous match (Shift+Enter)").clicked() {
+                step = Some(-1);
+            }
+            if ui.add_enabled(count > 0, egui::Button::new("▶")).on_hover_text("Next match (Enter)").clicked() {
+                step = Some(1);
+            }
+            if ui.button("✕").on_hover_text("Close search (Esc)").clicked() {
+                close = true;
+            }
+        });
+
+        if close {
+            self.close_matrix_search();
+            return;
+        }
+        if let Some(grid) = &mut self.raw_text_matrix_grid {
+            grid.set_search(&self.matrix_search_query);
+            if let Some(step) = step {
+                grid.step_match(step);
+            }
+        }
+    }
+
+    fn open_bookmarks(&mut self) {
+        self.show_bookmarks = true;
+        self.bookmark_focus = true;
+    }
+
+    /// Bookmarks the matrix cursor (or the top-left cell) on the current page.
+    fn add_bookmark(&mut self) {
+        let (row, col) = self
+            .raw_text_matrix_grid
+            .as_ref()
+            .and_then(|g| g.cursor_pos)
+            .unwrap_or((0, 0));
+        let name = match self.bookmark_name.trim() {
+            "" => format!("p{} {},{}", self.current_page + 1, row, col),
+            name => name.to_string(),
+        };
+        self.log(&format!("🔖 Bookmarked '{}' (page {}, {},{})", name, self.current_page + 1, row, col));
+        self.bookmarks.push(Bookmark {
+            name,
+            page: self.current_page,
+            row,
+            col,
+        });
+        self.bookmark_name.clear();
+    }
+
+    fn jump_to_bookmark(&mut self, index: usize, ctx: &egui::Context) {
+        let Some(bookmark) = self.bookmarks.get(index).cloned() else {
+            return;
+        };
+        self.active_tab = ExtractionTab::RawText;
+        self.focused_pane = FocusedPane::MatrixView;
+        self.selected_cell = Some((bookmark.col, bookmark.row));
+        if bookmark.page != self.current_page {
+            self.go_to_page(bookmark.page, ctx);
+        }
+        match &mut self.raw_text_matrix_grid {
+            Some(grid) if bookmark.page == self.current_page && self.document.page(bookmark.page).is_some() => {
+                grid.set_cursor(bookmark.row, bookmark.col)
+            }
+            // The grid is rebuilt once the page's matrix is available
+            _ => self.pending_grid_cursor = Some((bookmark.row, bookmark.col)),
         }
-
-        Err(anyhow::anyhow!("Could not determine page count"))
     }
 
-    fn render_current_page(&mut self, ctx: &egui::Context) {
-        if let Some(pdf_path) = &self.pdf_path {
-            let temp_png =
-                std::env::temp_dir().join(format!("chonker5_page_{}.png", self.current_page));
-            let dpi = 150.0 * self.zoom_level;
-
-            let result = Command::new("mutool")
-                .arg("draw")
-                .arg("-o")
-                .arg(&temp_png)
-                .arg("-r")
-                .arg(dpi.to_string())
-                .arg("-F")
-                .arg("png")
-                .arg(pdf_path)
-                .arg(format!("{}", self.current_page + 1))
-                .output();
-
-            match result {
-                Ok(output) => {
-                    if output.status.success() {
-                        if let Ok(image_data) = std::fs::read(&temp_png) {
-                            if let Ok(mut image) = image::load_from_memory(&image_data) {
-                                if self.pdf_dark_mode {
-                                    let mut rgba_image = image.to_rgba8();
-                                    image::imageops::colorops::invert(&mut rgba_image);
-                                    image = image::DynamicImage::ImageRgba8(rgba_image);
-                                }
-
-                                let size = [image.width() as _, image.height() as _];
-                                let image_buffer = image.to_rgba8();
-                                let pixels = image_buffer.as_flat_samples();
-
-                                let color_image = egui::ColorImage::from_rgba_unmultiplied(
-                                    size,
-                                    pixels.as_slice(),
-                                );
-                                self.pdf_texture = Some(ctx.load_texture(
-                                    format!("pdf_page_{}", self.current_page),
-                                    color_image,
-                                    Default::default(),
-                                ));
-
-                                self.log(&format!(
-                                    "📄 Rendered page {} {}",
-                                    self.current_page + 1,
-                                    if self.pdf_dark_mode { "🌙" } else { "" }
-                                ));
+    fn show_edits_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_edits;
+        let mut revert = None;
+        let mut revert_all = false;
+        let original = self.document.page(self.current_page).map_or(&[][..], |page| &page.extracted[..]);
+        let edited = self.raw_text_matrix_grid.as_ref().map_or(&[][..], |g| &g.matrix[..]);
+        let hunks = edit_hunks(original, edited);
+
+        egui::Window::new("EDITS")
+            .open(&mut open)
+            .default_width(520.0)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("{} hunk(s) on page {}", hunks.len(), self.current_page + 1))
+                        .color(theme().fg)
+                        .monospace()
+                        .size(10.0));
+                    if ui.add_enabled(!hunks.is_empty(), egui::Button::new(RichText::new("Revert all").monospace().size(10.0))).clicked() {
+                        revert_all = true;
+                    }
+                });
+                ui.separator();
+                if hunks.is_empty() {
+                    ui.label(RichText::new("No edits on this page").color(theme().dim).monospace().size(10.0));
+                }
+                egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+                    for (i, hunk) in hunks.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("@@ rows {}–{}", hunk.start, hunk.end - 1)).color(theme().accent).monospace().size(10.0));
+                            if ui.small_button("Revert").clicked() {
+                                revert = Some(i);
+                            }
+                        });
+                        let line = |rows: &[Vec<char>], row: usize| rows.get(row).map(|r| r.iter().collect::<String>());
+                        for row in hunk.clone() {
+                            if let Some(text) = line(original, row) {
+                                ui.label(RichText::new(format!("-{:4} {}", row, text.trim_end())).color(theme().error).monospace().size(10.0));
+                            }
+                        }
+                        for row in hunk.clone() {
+                            if let Some(text) = line(edited, row) {
+                                ui.label(RichText::new(format!("+{:4} {}", row, text.trim_end())).color(theme().success).monospace().size(10.0));
                             }
                         }
+                        ui.add_space(4.0);
+                    }
+                });
+            });
 
-                        let _ = std::fs::remove_file(&temp_png);
-                    } else {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        self.log(&format!("❌ Failed to render page: {}", stderr));
+        self.show_edits = open;
+        let (Some(original), Some(grid)) =
+            (self.document.page(self.current_page).map(|page| &page.extracted), self.raw_text_matrix_grid.as_mut())
+        else {
+            return;
+        };
+        if revert_all {
+            grid.revert_rows(original, 0..original.len().max(grid.matrix.len()));
+        } else if let Some(hunk) = revert.and_then(|i| hunks.get(i)) {
+            grid.revert_rows(original, hunk.clone());
+        }
+    }
+
+    fn show_bookmarks_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_bookmarks;
+        let mut add = false;
+        let mut jump = None;
+        let mut remove = None;
+
+        egui::Window::new("BOOKMARKS")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.bookmark_name)
+                            .font(egui::TextStyle::Monospace)
+                            .hint_text("name")
+                            .desired_width(180.0),
+                    );
+                    if std::mem::take(&mut self.bookmark_focus) {
+                        response.request_focus();
+                    }
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        add = true;
+                    }
+                    if ui
+                        .add_enabled(self.pdf_path.is_some(), egui::Button::new(RichText::new("Add at cursor").monospace().size(10.0)))
+                        .clicked()
+                    {
+                        add = true;
                     }
+                });
+                ui.separator();
+
+                if self.bookmarks.is_empty() {
+                    ui.label(RichText::new("No bookmarks").color(theme().dim).monospace().size(10.0));
                 }
-                Err(e) => {
-                    self.log(&format!("❌ Failed to run mutool: {}", e));
+                for (i, bookmark) in self.bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let text = format!("{:<20} p{} {},{}", bookmark.name, bookmark.page + 1, bookmark.row, bookmark.col);
+                        if ui
+                            .add(egui::Label::new(RichText::new(text).color(theme().fg).monospace().size(10.0)).sense(Sense::click()))
+                            .on_hover_text("Jump to bookmark")
+                            .clicked()
+                        {
+                            jump = Some(i);
+                        }
+                        if ui.small_button("✕").on_hover_text("Remove bookmark").clicked() {
+                            remove = Some(i);
+                        }
+                    });
                 }
-            }
+
+                ui.separator();
+                ui.label(
+                    RichText::new("Bookmarks are stored when the project is saved.")
+                        .color(theme().dim)
+                        .size(10.0),
+                );
+            });
+
+        self.show_bookmarks = open;
+        if add {
+            self.add_bookmark();
+        }
+        if let Some(i) = jump {
+            self.jump_to_bookmark(i, ctx);
+        }
+        if let Some(i) = remove {
+            self.bookmarks.remove(i);
         }
     }
 
-    fn extract_character_matrix(&mut self, ctx: &egui::Context) {
-        if self.pdf_path.is_none() {
-            self.log("⚠️ No PDF loaded. Open a file first.");
+    fn apply_selection_op(&mut self, op: SelectionOp) {
+        let Some(grid) = &mut self.raw_text_matrix_grid else {
             return;
+        };
+        let applied = match op {
+            SelectionOp::AlignColumns => grid.align_selected_columns(),
+            SelectionOp::SortRows(sort) => grid.sort_selected_rows(sort),
+            SelectionOp::Transpose => grid.transpose_selection(),
+            SelectionOp::Transform(transform) => grid.transform_selection(transform),
+            SelectionOp::Whitespace(cleanup) => grid.cleanup_whitespace(cleanup),
+            SelectionOp::Fill(ch) => grid.fill_selection(ch),
+            SelectionOp::Reflow => match grid.selection.bbox() {
+                Some(bbox) => grid.reflow_block(&bbox),
+                None => false,
+            },
+        };
+        match op {
+            SelectionOp::AlignColumns if !applied => {
+                self.log("⚠️ Aligned columns would not fit in the selection; widen it and retry")
+            }
+            SelectionOp::Transpose if !applied => {
+                self.log("⚠️ The transposed block would run past the edge of the matrix")
+            }
+            SelectionOp::Reflow if !applied => {
+                self.log("⚠️ The reflowed text needs more rows than the selection has")
+            }
+            _ => {}
         }
+    }
 
-        let pdf_path = match &self.pdf_path {
-            Some(path) => path.clone(),
-            None => {
-                self.log("❌ No PDF file selected");
-                return;
-            }
-        };
+    fn open_fill(&mut self) {
+        self.fill_open = true;
+        self.fill_focus = true;
+        self.active_tab = ExtractionTab::RawText;
+    }
 
-        let runtime = self.runtime.clone();
-        let ctx = ctx.clone();
+    fn show_fill_bar(&mut self, ui: &mut egui::Ui) {
+        let mut fill = false;
+        let mut close = false;
 
-        self.matrix_result.is_loading = true;
-        self.matrix_result.error = None;
-        self.vision_receiver = None;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Fill selection with:").color(theme().dim).monospace());
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.fill_input)
+                    .font(egui::TextStyle::Monospace)
+                    .char_limit(1)
+                    .hint_text("█")
+                    .desired_width(24.0),
+            );
+            if std::mem::take(&mut self.fill_focus) {
+                response.request_focus();
+            }
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                } else if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    fill = true;
+                }
+            }
+            if ui.button("Fill").clicked() {
+                fill = true;
+            }
+            if ui.button("✕").on_hover_text("Close (Esc)").clicked() {
+                close = true;
+            }
+        });
 
-        self.log(&format!(
-            "🔄 Processing PDF page {}...",
-            self.current_page + 1
-        ));
+        if close {
+            self.fill_open = false;
+        } else if fill {
+            let Some(ch) = self.fill_input.chars().next() else {
+                self.log("⚠️ Type a character to fill the selection with");
+                return;
+            };
+            self.apply_selection_op(SelectionOp::Fill(ch));
+            self.fill_open = false;
+        }
+    }
 
-        let (tx, rx) = mpsc::channel(1);
-        self.vision_receiver = Some(rx);
+    fn open_goto(&mut self) {
+        self.goto_open = true;
+        self.goto_focus = true;
+        self.active_tab = ExtractionTab::RawText;
+    }
 
-        let current_page = self.current_page;
-        runtime.spawn(async move {
-            let result = Self::process_pdf_async(pdf_path, current_page).await;
+    /// Moves the matrix cursor to `row,col`, clamped to the matrix.
+    fn goto_cell(&mut self) {
+        let Some((row, col)) = parse_goto(&self.goto_input) else {
+            self.log(&format!("⚠️ Goto expects row or row,col, got '{}'", self.goto_input.trim()));
+            return;
+        };
+        let Some(grid) = &mut self.raw_text_matrix_grid else {
+            return;
+        };
+        let Some(last_row) = grid.matrix.len().checked_sub(1) else {
+            return;
+        };
+        let row = row.min(last_row);
+        let col = col.min(grid.matrix[row].len().saturating_sub(1));
+        grid.set_cursor(row, col);
+        self.selected_cell = Some((col, row));
+        self.goto_open = false;
+    }
 
-            if let Err(e) = tx.send(result).await {
-                tracing::error!("Failed to send matrix result: {}", e);
-            }
+    fn show_goto_bar(&mut self, ui: &mut egui::Ui) {
+        let mut go = false;
+        let mut close = false;
 
-            ctx.request_repaint();
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Go to row,col:").color(theme().dim).monospace());
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.goto_input)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text("12,40")
+                    .desired_width(80.0),
+            );
+            if std::mem::take(&mut self.goto_focus) {
+                response.request_focus();
+            }
+            if response.lost_focus() {
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                } else if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    go = true;
+                }
+            }
+            if ui.button("Go").clicked() {
+                go = true;
+            }
+            if ui.button("✕").on_hover_text("Close (Esc)").clicked() {
+                close = true;
+            }
         });
+
+        if close {
+            self.goto_open = false;
+        } else if go {
+            self.goto_cell();
+        }
     }
 
-    async fn process_pdf_async(
-        pdf_path: PathBuf,
-        page_index: usize,
-    ) -> Result<CharacterMatrix, String> {
-        let result = tokio::task::spawn_blocking(move || {
-            tracing::info!(
-                "Starting async PDF processing: {} (page {})",
-                pdf_path.display(),
-                page_index + 1
-            );
+    fn set_reading_order(&mut self, order: Option<Vec<usize>>) {
+        let page = self.current_page;
+        let reset = order.is_none();
+        // Reaches the page cache and the reading order file with the document event
+        self.document.set_reading_order(page, order);
+        if reset {
+            self.log(&format!("📑 Page {} reading order reset to top-to-bottom", page + 1));
+        }
+    }
 
-            let start_time = std::time::Instant::now();
-            let timeout = std::time::Duration::from_secs(60);
+    fn show_reading_order_window(&mut self, ctx: &egui::Context) {
+        let Some(char_matrix) = self.current_matrix() else {
+            return;
+        };
+        let sequence = reading_sequence(char_matrix);
+        let rows: Vec<(usize, String)> = sequence
+            .iter()
+            .map(|&i| {
+                let region = &char_matrix.text_regions[i];
+                let preview: String = region.text_content.chars().take(32).collect();
+                (region.region_id, format!("{:<5} {}", region.display_name(), preview.replace('\n', " ")))
+            })
+            .collect();
+        let user_defined = char_matrix.reading_order.is_some();
 
-            let rt = tokio::runtime::Handle::current();
+        let mut open = self.reading_order_mode;
+        let mut moved: Option<(usize, usize)> = None;
+        let mut reset = false;
 
-            match rt.block_on(Self::extract_simple_text_matrix(&pdf_path, page_index)) {
-                Ok(matrix) => {
-                    tracing::info!(
-                        "Simple text extraction successful in {:?}",
-                        start_time.elapsed()
+        egui::Window::new(format!("READING ORDER - Page {}", self.current_page + 1))
+            .open(&mut open)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(if user_defined { "Custom order" } else { "Top to bottom" })
+                            .color(theme().dim)
+                            .monospace()
+                            .size(10.0),
                     );
-                    Ok(matrix)
-                }
-                Err(simple_err) => {
-                    tracing::warn!("Simple extraction failed: {}, trying PDFium", simple_err);
-
-                    if start_time.elapsed() > timeout {
-                        return Err("PDF processing timeout - file too complex".to_string());
+                    if ui.add_enabled(user_defined, egui::Button::new(RichText::new("Reset").monospace().size(10.0))).clicked() {
+                        reset = true;
                     }
+                });
+                ui.label(RichText::new("Drag rows to reorder").color(theme().dim).size(10.0));
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(360.0)
+                    .id_source("reading_order_list")
+                    .show(ui, |ui| {
+                        let mut row_rects = Vec::with_capacity(rows.len());
+                        for (pos, (_, text)) in rows.iter().enumerate() {
+                            let dragging = self.reading_drag == Some(pos);
+                            ui.horizontal(|ui| {
+                                if ui.small_button("▲").clicked() && pos > 0 {
+                                    moved = Some((pos, pos - 1));
+                                }
+                                if ui.small_button("▼").clicked() && pos + 1 < rows.len() {
+                                    moved = Some((pos, pos + 1));
+                                }
+                                let label = RichText::new(format!("{:>3}. {}", pos + 1, text))
+                                    .color(if dragging { theme().highlight } else { theme().fg })
+                                    .monospace()
+                                    .size(10.0);
+                                let response = ui.add(egui::Label::new(label).sense(Sense::drag()));
+                                if response.drag_started() {
+                                    self.reading_drag = Some(pos);
+                                }
+                                row_rects.push(response.rect);
+                            });
+                        }
 
-                    let engine = CharacterMatrixEngine::new();
-                    engine
-                        .process_pdf_page(&pdf_path, Some(page_index))
-                        .map_err(|e| format!("Ferrules processing failed: {}", e))
-                }
-            }
-        })
-        .await;
+                        // Drop onto whichever row the pointer is over
+                        if let Some(from) = self.reading_drag {
+                            let pointer = ui.input(|i| i.pointer.interact_pos());
+                            let target = pointer.and_then(|p| {
+                                row_rects.iter().position(|r| p.y >= r.top() && p.y < r.bottom())
+                            });
+                            if let Some(to) = target {
+                                let line_y = row_rects[to].top();
+                                ui.painter().hline(ui.max_rect().x_range(), line_y, Stroke::new(1.5, theme().highlight));
+                            }
+                            if ui.input(|i| i.pointer.any_released()) {
+                                self.reading_drag = None;
+                                if let Some(to) = target.filter(|&to| to != from) {
+                                    moved = Some((from, to));
+                                }
+                            }
+                        }
+                    });
+            });
 
-        match result {
-            Ok(pdf_result) => pdf_result,
-            Err(join_err) => Err(format!("PDF processing task failed: {}", join_err)),
+        if let Some((from, to)) = moved {
+            let mut ids: Vec<usize> = rows.iter().map(|(id, _)| *id).collect();
+            let id = ids.remove(from);
+            ids.insert(to, id);
+            self.set_reading_order(Some(ids));
         }
+        if reset {
+            self.set_reading_order(None);
+        }
+        self.reading_order_mode = open;
     }
 
-    async fn extract_simple_text_matrix(
-        pdf_path: &PathBuf,
-        page_index: usize,
-    ) -> Result<CharacterMatrix, String> {
-        let output = tokio::process::Command::new("mutool")
-            .arg("draw")
-            .arg("-F")
-            .arg("text")
-            .arg(pdf_path)
-            .arg((page_index + 1).to_string())
-            .output()
-            .await
-            .map_err(|e| format!("Failed to run mutool: {}", e))?;
+    /// Numbers each region on the page image in reading order and joins
+    /// consecutive regions with a line.
+    fn draw_reading_order(&self, ui: &mut egui::Ui, image_response: &egui::Response) {
+        let Some(char_matrix) = self.current_matrix() else {
+            return;
+        };
+        let mapping = MatrixImageMapping::new(char_matrix, image_response.rect);
+        let painter = ui.painter();
+
+        let rects: Vec<egui::Rect> = reading_sequence(char_matrix)
+            .into_iter()
+            .map(|i| &char_matrix.text_regions[i])
+            .filter(|region| region.confidence >= self.region_min_confidence)
+            .map(|region| mapping.bbox_rect(&region.bbox))
+            .collect();
+        for pair in rects.windows(2) {
+            painter.line_segment(
+                [pair[0].center(), pair[1].center()],
+                Stroke::new(1.0, theme().accent.gamma_multiply(0.6)),
+            );
+        }
+        for (n, rect) in rects.iter().enumerate() {
+            let badge = rect.left_top() + Vec2::new(8.0, 8.0);
+            painter.circle_filled(badge, 8.0, theme().accent);
+            painter.text(
+                badge,
+                Align2::CENTER_CENTER,
+                (n + 1).to_string(),
+                FontId::monospace(9.0),
+                theme().bg,
+            );
+        }
+    }
 
-        if !output.status.success() {
-            return Err("Mutool extraction failed".to_string());
+    fn show_history_window(&mut self, ctx: &egui::Context) {
+        if self.history_page != self.current_page {
+            self.refresh_history();
         }
 
-        let text = String::from_utf8_lossy(&output.stdout);
-        let lines: Vec<&str> = text.lines().collect();
-        let max_width = lines.iter().map(|line| line.len()).max().unwrap_or(80);
-        let height = lines.len().max(25);
+        let mut open = self.show_history_panel;
+        let mut select = None;
+        let mut restore = None;
 
-        let mut matrix = vec![vec![' '; max_width]; height];
+        egui::Window::new(format!("MATRIX HISTORY - Page {}", self.current_page + 1))
+            .open(&mut open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                if self.history_versions.is_empty() {
+                    ui.label(RichText::new("No saved versions for this page").color(theme().dim).monospace());
+                    return;
+                }
 
-        for (y, line) in lines.iter().enumerate() {
-            if y < height {
-                for (x, ch) in line.chars().enumerate() {
-                    if x < max_width {
-                        matrix[y][x] = ch;
-                    }
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .id_source("history_list")
+                    .show(ui, |ui| {
+                        for (i, version) in self.history_versions.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let selected = self.history_selected == Some(i);
+                                let label = RichText::new(format!("v{} · {}", self.history_versions.len() - i, format_age(version.saved_at)))
+                                    .monospace()
+                                    .color(if selected { theme().highlight } else { theme().fg });
+                                if ui.selectable_label(selected, label).clicked() {
+                                    select = Some(i);
+                                }
+                                if ui.button(RichText::new("Restore").color(theme().warning).monospace().size(10.0)).clicked() {
+                                    restore = Some(i);
+                                }
+                            });
+                        }
+                    });
+
+                if let Some(preview) = &self.history_preview {
+                    ui.separator();
+                    let current = self.current_matrix().map(|m| matrix_to_text(&m.rows())).unwrap_or_default();
+                    let diffs = diff_matrix_rows(preview, &current);
+                    ui.label(RichText::new(format!("{} row(s) differ from the current matrix", diffs.len()))
+                        .color(theme().dim)
+                        .monospace()
+                        .size(10.0));
+                    egui::ScrollArea::both()
+                        .max_height(320.0)
+                        .id_source("history_diff")
+                        .show(ui, |ui| {
+                            for diff in &diffs {
+                                ui.label(RichText::new(format!("{:3} - {}", diff.row, diff.old)).color(theme().error).monospace().size(10.0));
+                                ui.label(RichText::new(format!("{:3} + {}", diff.row, diff.new)).color(theme().success).monospace().size(10.0));
+                            }
+                        });
                 }
-            }
+            });
+
+        if let Some(i) = select {
+            self.history_selected = Some(i);
+            self.history_preview = self
+                .history_versions
+                .get(i)
+                .and_then(|v| std::fs::read_to_string(&v.path).ok());
+        }
+        if let Some(i) = restore {
+            self.restore_matrix_version(i);
         }
+        self.show_history_panel = open;
+    }
 
-        Ok(CharacterMatrix {
-            width: max_width,
-            height,
-            matrix,
-            text_regions: Vec::new(),
-            original_text: lines.iter().map(|s| s.to_string()).collect(),
-            char_width: 8.0,
-            char_height: 12.0,
-        })
+    fn show_region_list_panel(&mut self, ctx: &egui::Context) {
+        let rows: Vec<RegionRow> = self
+            .current_matrix()
+            .map(|m| {
+                region_list_order(&m.text_regions, self.region_sort, self.region_min_confidence)
+                    .into_iter()
+                    .map(|idx| {
+                        let region = &m.text_regions[idx];
+                        let preview: String = region.text_content.chars().take(40).collect();
+                        let warnings: Vec<String> = m.region_warnings(region.region_id).map(|w| w.to_string()).collect();
+                        let text = format!(
+                            "{}{:<5} {:>3},{:<3} {:>3}x{:<3} {:>3.0}%  {}",
+                            if warnings.is_empty() { "" } else { "⚠ " },
+                            region.display_name(),
+                            region.bbox.x,
+                            region.bbox.y,
+                            region.bbox.width,
+                            region.bbox.height,
+                            region.confidence * 100.0,
+                            preview.replace('\n', " ")
+                        );
+                        (idx, text, region.confidence, region.tag, warnings)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let total = self.current_matrix().map_or(0, |m| m.text_regions.len());
+        let flashed = self.region_flash.map(|(idx, _)| idx);
+        let mut jump = None;
+        let mut retag = None;
+        let mut edit: Option<RegionEdit> = None;
+        let mut reflow = None;
+        let mut segmentation = self.segmentation;
+
+        egui::SidePanel::right("region_panel")
+            .resizable(true)
+            .default_width(320.0)
+            .frame(egui::Frame::none().fill(theme().bg))
+            .show(ctx, |ui| {
+                draw_terminal_box(ui, "REGIONS", false, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("Sort:").color(theme().dim).monospace());
+                        egui::ComboBox::from_id_source("region_sort")
+                            .selected_text(self.region_sort.label())
+                            .show_ui(ui, |ui| {
+                                for sort in RegionSort::ALL {
+                                    ui.selectable_value(&mut self.region_sort, sort, sort.label());
+                                }
+                            });
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("Segment:").color(theme().dim).monospace());
+                        egui::ComboBox::from_id_source("region_segmentation")
+                            .selected_text(segmentation.label())
+                            .show_ui(ui, |ui| {
+                                for choice in Segmentation::ALL {
+                                    ui.selectable_value(&mut segmentation, choice, choice.label());
+                                }
+                            })
+                            .response
+                            .on_hover_text("How this document's pages are split into regions; XY-cut keeps columns apart");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("Min conf:").color(theme().dim).monospace());
+                        ui.add(egui::Slider::new(&mut self.region_min_confidence, 0.0..=1.0).fixed_decimals(2))
+                            .on_hover_text("Regions below this are hidden here, on the page and in the regions CSV (min_confidence in chonker.toml)");
+                    });
+                    ui.label(
+                        RichText::new(format!("{} of {} region(s)", rows.len(), total))
+                            .color(theme().dim)
+                            .monospace()
+                            .size(10.0),
+                    );
+                    ui.horizontal(|ui| {
+                        let picks = self.region_picks.len();
+                        if ui.add_enabled(picks >= 2, egui::Button::new(RichText::new(format!("Merge ({})", picks)).monospace().size(10.0)))
+                            .on_hover_text("Ctrl+click regions to pick them, then merge into one")
+                            .clicked() {
+                            edit = Some(RegionEdit::Merge);
+                        }
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        ui.label(RichText::new("Split at").color(theme().dim).monospace().size(10.0));
+                        ui.add(egui::DragValue::new(&mut self.region_split_offset).clamp_range(1..=999));
+                        if ui.add_enabled(picks == 1, egui::Button::new(RichText::new("Rows").monospace().size(10.0)))
+                            .on_hover_text("Cut the picked region above this many rows from its top")
+                            .clicked() {
+                            edit = Some(RegionEdit::Split(RegionSplit::Row(self.region_split_offset)));
+                        }
+                        if ui.add_enabled(picks == 1, egui::Button::new(RichText::new("Cols").monospace().size(10.0)))
+                            .on_hover_text("Cut the picked region this many columns from its left edge")
+                            .clicked() {
+                            edit = Some(RegionEdit::Split(RegionSplit::Column(self.region_split_offset)));
+                        }
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        if ui.add_enabled(picks == 1, egui::Button::new(RichText::new("Reflow").monospace().size(10.0)))
+                            .on_hover_text("Re-wrap the picked region's text to its width")
+                            .clicked() {
+                            reflow = self.region_picks.first().copied();
+                        }
+                    });
+                    ui.separator();
+
+                    egui::ScrollArea::vertical()
+                        .auto_shrink([false; 2])
+                        .id_source("region_list_scroll_area")
+                        .show(ui, |ui| {
+                            for (idx, text, confidence, tag, warnings) in &rows {
+                                let color = if let Some(tag) = tag {
+                                    tag_color(*tag)
+                                } else if *confidence > 0.8 {
+                                    theme().fg
+                                } else if *confidence > 0.5 {
+                                    theme().warning
+                                } else {
+                                    theme().dim
+                                };
+                                let label = RichText::new(text).color(color).monospace().size(10.0);
+                                let picked = self.region_picks.contains(idx);
+                                let mut response = ui.selectable_label(picked || flashed == Some(*idx), label);
+                                if !warnings.is_empty() {
+                                    response = response.on_hover_text(warnings.join("\n"));
+                                }
+                                if response.clicked() {
+                                    if ui.input(|i| i.modifiers.command) {
+                                        if picked {
+                                            self.region_picks.retain(|p| p != idx);
+                                        } else {
+                                            self.region_picks.push(*idx);
+                                        }
+                                    } else {
+                                        self.region_picks = vec![*idx];
+                                        jump = Some(*idx);
+                                    }
+                                }
+                                response.context_menu(|ui| {
+                                    if let Some(choice) = region_tag_menu(ui, *tag) {
+                                        retag = Some((*idx, choice));
+                                    }
+                                });
+                            }
+                        });
+                });
+            });
+
+        if let Some(idx) = jump {
+            self.focus_region(idx);
+        }
+        if let Some((idx, tag)) = retag {
+            self.set_region_tag(idx, tag);
+        }
+        if let Some(edit) = edit {
+            self.edit_regions(edit);
+        }
+        if let Some(idx) = reflow {
+            self.focus_region(idx);
+            self.apply_selection_op(SelectionOp::Reflow);
+        }
+        if segmentation != self.segmentation {
+            self.set_segmentation(segmentation, ctx);
+        }
     }
 
-    fn save_edited_matrix(&mut self) {
-        if let Some(editable_matrix) = &self.matrix_result.editable_matrix {
-            if let Some(pdf_path) = &self.pdf_path {
-                let output_path = pdf_path.with_extension("matrix.txt");
-
-                let mut content = String::new();
-                for row in editable_matrix {
-                    for ch in row {
-                        content.push(*ch);
-                    }
-                    content.push('\n');
-                }
+    fn edit_regions(&mut self, edit: RegionEdit) {
+        let picks = &self.region_picks;
+        let mut result = Err(anyhow::anyhow!("Extract the page before editing its regions"));
+        // Reaches the page cache with the document event
+        self.document.edit(self.current_page, |char_matrix| {
+            result = match edit {
+                RegionEdit::Merge => merge_regions(char_matrix, picks),
+                RegionEdit::Split(split) => match picks.as_slice() {
+                    &[idx] => split_region(char_matrix, idx, split),
+                    _ => Err(anyhow::anyhow!("Pick exactly one region to split")),
+                },
+            };
+        });
 
-                match std::fs::write(&output_path, content) {
-                    Ok(_) => {
-                        self.log(&format!(
-                            "✅ Saved edited matrix to: {}",
-                            output_path.display()
-                        ));
-                        self.matrix_result.matrix_dirty = false;
-                    }
-                    Err(e) => {
-                        self.log(&format!("❌ Failed to save matrix: {}", e));
-                    }
-                }
+        match result {
+            Ok(idx) => {
+                let name = self.current_matrix().map(|m| m.text_regions[idx].display_name()).unwrap_or_default();
+                // Indices shifted; drop anything that pointed at the old list
+                self.region_picks = vec![idx];
+                self.context_region = None;
+                self.review_cursor = None;
+                self.focus_region(idx);
+                self.log(&match edit {
+                    RegionEdit::Merge => format!("🔗 Merged regions into {}", name),
+                    RegionEdit::Split(_) => format!("✂️ Split off {}", name),
+                });
             }
+            Err(e) => self.log(&format!("⚠️ {}", e)),
         }
     }
 
     fn draw_character_matrix_overlay(&self, ui: &mut egui::Ui, image_response: &egui::Response) {
-        if let Some(char_matrix) = &self.matrix_result.character_matrix {
+        if let Some(char_matrix) = self.current_matrix() {
             let painter = ui.painter();
             let image_rect = image_response.rect;
+            let mapping = MatrixImageMapping::new(char_matrix, image_rect);
 
-            let pdf_width_pts = char_matrix.width as f32 * char_matrix.char_width;
-            let pdf_height_pts = char_matrix.height as f32 * char_matrix.char_height;
-
-            let scale_x = image_rect.width() / pdf_width_pts;
-            let scale_y = image_rect.height() / pdf_height_pts;
-
-            let grid_color = TERM_DIM.gamma_multiply(0.2);
+            let grid_color = theme().dim.gamma_multiply(0.2);
 
             for x in (0..char_matrix.width).step_by(10) {
-                let screen_x = image_rect.left() + (x as f32 * char_matrix.char_width * scale_x);
+                let screen_x = image_rect.left() + x as f32 * mapping.cell_size.x;
                 painter.line_segment(
                     [
                         egui::pos2(screen_x, image_rect.top()),
@@ -1778,7 +4839,7 @@ impl Chonker5App {
             }
 
             for y in (0..char_matrix.height).step_by(10) {
-                let screen_y = image_rect.top() + (y as f32 * char_matrix.char_height * scale_y);
+                let screen_y = image_rect.top() + y as f32 * mapping.cell_size.y;
                 painter.line_segment(
                     [
                         egui::pos2(image_rect.left(), screen_y),
@@ -1788,49 +4849,46 @@ impl Chonker5App {
                 );
             }
 
-            if let Some((sel_x, sel_y)) = self.selected_cell {
-                if sel_y < char_matrix.height && sel_x < char_matrix.width {
-                    let x1 = image_rect.left() + (sel_x as f32 * char_matrix.char_width * scale_x);
-                    let y1 = image_rect.top() + (sel_y as f32 * char_matrix.char_height * scale_y);
-                    let cell_rect = egui::Rect::from_min_size(
-                        egui::pos2(x1, y1),
-                        egui::vec2(
-                            char_matrix.char_width * scale_x,
-                            char_matrix.char_height * scale_y,
-                        ),
-                    );
-                    painter.rect_filled(cell_rect, 0.0, TERM_HIGHLIGHT.gamma_multiply(0.2));
-                    painter.rect_stroke(cell_rect, 0.0, egui::Stroke::new(2.0, TERM_HIGHLIGHT));
-                }
-            }
-
-            for region in char_matrix.text_regions.iter() {
-                let x1 =
-                    image_rect.left() + (region.bbox.x as f32 * char_matrix.char_width * scale_x);
-                let y1 =
-                    image_rect.top() + (region.bbox.y as f32 * char_matrix.char_height * scale_y);
-                let x2 = x1 + (region.bbox.width as f32 * char_matrix.char_width * scale_x);
-                let y2 = y1 + (region.bbox.height as f32 * char_matrix.char_height * scale_y);
-
-                let rect = egui::Rect::from_min_max(egui::pos2(x1, y1), egui::pos2(x2, y2));
+            for region in char_matrix.text_regions.iter().filter(|region| region.confidence >= self.region_min_confidence) {
+                let rect = mapping.bbox_rect(&region.bbox);
 
                 if rect.intersects(image_rect) {
-                    let color = if region.confidence > 0.8 {
-                        TERM_HIGHLIGHT
-                    } else if region.confidence > 0.5 {
-                        TERM_YELLOW
-                    } else {
-                        TERM_DIM
-                    };
+                    let color = region_color(region);
 
+                    if region.tag.is_some() {
+                        painter.rect_filled(rect, 0.0, color.gamma_multiply(0.12));
+                    }
                     painter.rect_stroke(rect, 0.0, egui::Stroke::new(2.0, color));
 
+                    if region.is_italic {
+                        painter.line_segment(
+                            [rect.left_bottom(), rect.right_top()],
+                            egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+                        );
+                    }
+
+                    // Misplaced glyphs (see the region list for why)
+                    if char_matrix.region_warnings(region.region_id).next().is_some() {
+                        painter.text(
+                            rect.right_top() + egui::vec2(-2.0, 2.0),
+                            egui::Align2::RIGHT_TOP,
+                            "⚠",
+                            FontId::monospace(10.0),
+                            theme().warning,
+                        );
+                    }
+
                     if rect.width() > 20.0 && rect.height() > 15.0 {
                         let label_pos = rect.min + egui::vec2(2.0, 2.0);
                         painter.text(
                             label_pos,
                             egui::Align2::LEFT_TOP,
-                            format!("R{}", region.region_id + 1),
+                            format!(
+                                "{}{}{}",
+                                region.display_name(),
+                                if region.is_italic { " /i" } else { "" },
+                                region.tag.map(|t| format!(" ·{}", t.as_str())).unwrap_or_default()
+                            ),
                             FontId::monospace(10.0),
                             color,
                         );
@@ -1839,6 +4897,77 @@ impl Chonker5App {
             }
         }
     }
+
+    /// Highlights the cell linked to the matrix cursor on the page image.
+    /// Drawn regardless of the bounding-box toggle so the link stays visible.
+    fn draw_linked_cell(&self, ui: &mut egui::Ui, image_response: &egui::Response) {
+        let (Some(char_matrix), Some((sel_x, sel_y))) =
+            (self.current_matrix(), self.selected_cell)
+        else {
+            return;
+        };
+        if sel_y < char_matrix.height && sel_x < char_matrix.width {
+            let mapping = MatrixImageMapping::new(char_matrix, image_response.rect);
+            let cell_rect = mapping.cell_rect(sel_x, sel_y);
+            let painter = ui.painter();
+            painter.rect_filled(cell_rect, 0.0, theme().highlight.gamma_multiply(0.2));
+            painter.rect_stroke(cell_rect, 0.0, egui::Stroke::new(2.0, theme().highlight));
+        }
+    }
+
+    /// Screen rectangle of a region of the current page on the page image.
+    fn region_screen_rect(&self, region_idx: usize, image_rect: egui::Rect) -> Option<egui::Rect> {
+        let char_matrix = self.current_matrix()?;
+        let region = char_matrix.text_regions.get(region_idx)?;
+        Some(MatrixImageMapping::new(char_matrix, image_rect).bbox_rect(&region.bbox))
+    }
+
+    /// Fades a highlight over the region last jumped to from the region list.
+    fn draw_region_flash(&mut self, ui: &mut egui::Ui, image_response: &egui::Response) {
+        const FLASH_DURATION: f32 = 1.2;
+
+        let Some((region_idx, started)) = self.region_flash else {
+            return;
+        };
+        let Some(rect) = self.region_screen_rect(region_idx, image_response.rect) else {
+            self.region_flash = None;
+            return;
+        };
+
+        if std::mem::take(&mut self.pdf_scroll_to_flash) {
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+        }
+
+        let elapsed = started.elapsed().as_secs_f32();
+        if elapsed >= FLASH_DURATION {
+            self.region_flash = None;
+            return;
+        }
+        let strength = 1.0 - elapsed / FLASH_DURATION;
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, theme().warning.gamma_multiply(0.35 * strength));
+        painter.rect_stroke(rect, 0.0, egui::Stroke::new(3.0, theme().warning.gamma_multiply(strength)));
+        ui.ctx().request_repaint();
+    }
+
+    /// Moves the raw-text cursor to the matrix cell under a click on the page image.
+    fn link_pdf_click_to_matrix(&mut self, image_response: &egui::Response) {
+        let Some(pos) = image_response.interact_pointer_pos() else {
+            return;
+        };
+        let Some(char_matrix) = self.current_matrix() else {
+            return;
+        };
+        let Some((x, y)) = MatrixImageMapping::new(char_matrix, image_response.rect).cell_at(pos)
+        else {
+            return;
+        };
+        self.selected_cell = Some((x, y));
+        self.active_tab = ExtractionTab::RawText;
+        if let Some(grid) = &mut self.raw_text_matrix_grid {
+            grid.set_cursor(y, x);
+        }
+    }
 }
 
 fn draw_terminal_frame(
@@ -1846,11 +4975,11 @@ fn draw_terminal_frame(
     is_focused: bool,
     add_contents: impl FnOnce(&mut egui::Ui),
 ) {
-    let stroke_color = if is_focused { TERM_HIGHLIGHT } else { CHROME };
+    let stroke_color = if is_focused { theme().highlight } else { theme().chrome };
     let stroke_width = if is_focused { 2.0 } else { 1.0 };
 
     let frame = egui::Frame::none()
-        .fill(TERM_BG)
+        .fill(theme().bg)
         .stroke(Stroke::new(stroke_width, stroke_color))
         .inner_margin(egui::Margin::same(5.0))
         .outer_margin(egui::Margin::same(1.0))
@@ -1867,11 +4996,11 @@ fn draw_terminal_box(
     is_focused: bool,
     add_contents: impl FnOnce(&mut egui::Ui),
 ) {
-    let stroke_color = if is_focused { TERM_HIGHLIGHT } else { CHROME };
+    let stroke_color = if is_focused { theme().highlight } else { theme().chrome };
     let stroke_width = if is_focused { 2.0 } else { 1.0 };
 
     let frame = egui::Frame::none()
-        .fill(TERM_BG)
+        .fill(theme().bg)
         .stroke(Stroke::new(stroke_width, stroke_color))
         .inner_margin(egui::Margin::same(5.0))
         .outer_margin(egui::Margin::same(1.0))
@@ -1879,17 +5008,17 @@ fn draw_terminal_box(
 
     frame.show(ui, |ui| {
         ui.horizontal(|ui| {
-            ui.label(RichText::new("▸").color(TERM_HIGHLIGHT).monospace());
+            ui.label(RichText::new("▸").color(theme().highlight).monospace());
             ui.label(
                 RichText::new(title)
-                    .color(if is_focused { TERM_HIGHLIGHT } else { CHROME })
+                    .color(if is_focused { theme().highlight } else { theme().chrome })
                     .monospace()
                     .strong(),
             );
             if is_focused {
                 ui.label(
                     RichText::new(" [ACTIVE]")
-                        .color(TERM_HIGHLIGHT)
+                        .color(theme().highlight)
                         .monospace()
                         .size(10.0),
                 );
@@ -1905,9 +5034,23 @@ impl eframe::App for Chonker5App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if self.first_frame {
             self.first_frame = false;
+            if let Some(path) = self.initial_file.take() {
+                match path.to_str().filter(|arg| is_pdf_url(arg)) {
+                    Some(url) => self.open_url(url.to_string(), ctx),
+                    None => {
+                        self.log(&format!("📂 Opening {}", path.display()));
+                        self.load_pdf(path, 0, ctx);
+                    }
+                }
+            }
         }
 
         self.process_file_dialog_result(ctx);
+        self.process_download_result(ctx);
+        self.process_project_dialog_result(ctx);
+        self.process_selection_save_result();
+        self.process_regions_csv_result();
+        self.process_edit_log_result();
 
         // Handle global keyboard shortcuts
         if self.focused_pane != FocusedPane::MatrixView {
@@ -1920,10 +5063,13 @@ impl eframe::App for Chonker5App {
                         ..
                     } = event
                     {
+                        let Some((key, modifiers)) = remap_key(&self.keymap, *key, *modifiers) else {
+                            continue;
+                        };
                         if modifiers.command || modifiers.ctrl {
                             match key {
                                 egui::Key::O => self.open_file(ctx),
-                                egui::Key::S if self.matrix_result.matrix_dirty => {
+                                egui::Key::S if self.document.is_modified(self.current_page) => {
                                     self.save_edited_matrix()
                                 }
                                 egui::Key::D => {
@@ -1933,6 +5079,14 @@ impl eframe::App for Chonker5App {
                                 egui::Key::B => {
                                     self.show_bounding_boxes = !self.show_bounding_boxes
                                 }
+                                egui::Key::F => self.pdf_search_open = true,
+                                egui::Key::L => self.show_log_panel = !self.show_log_panel,
+                                egui::Key::R => self.show_region_panel = !self.show_region_panel,
+                                egui::Key::H => self.toggle_history_panel(),
+                                egui::Key::J => self.open_bookmarks(),
+                                egui::Key::W => self.close_document(self.active_document),
+                                egui::Key::PageDown => self.cycle_document(1),
+                                egui::Key::PageUp => self.cycle_document(-1),
                                 _ => {}
                             }
                         }
@@ -1949,12 +5103,29 @@ impl eframe::App for Chonker5App {
                         ..
                     } = event
                     {
+                        let Some((key, modifiers)) = remap_key(&self.keymap, *key, *modifiers) else {
+                            continue;
+                        };
                         if modifiers.command || modifiers.ctrl {
                             match key {
                                 egui::Key::O => self.open_file(ctx),
-                                egui::Key::S if self.matrix_result.matrix_dirty => {
+                                egui::Key::S if modifiers.shift => self.save_selection_as(ctx),
+                                egui::Key::F if modifiers.shift => self.open_fill(),
+                                egui::Key::F => self.open_matrix_search(),
+                                egui::Key::G => self.open_goto(),
+                                egui::Key::PlusEquals => self.zoom_matrix(Some(1.1)),
+                                egui::Key::Minus => self.zoom_matrix(Some(1.0 / 1.1)),
+                                egui::Key::Num0 => self.zoom_matrix(None),
+                                egui::Key::S if self.document.is_modified(self.current_page) => {
                                     self.save_edited_matrix()
                                 }
+                                egui::Key::L => self.show_log_panel = !self.show_log_panel,
+                                egui::Key::R => self.show_region_panel = !self.show_region_panel,
+                                egui::Key::H => self.toggle_history_panel(),
+                                egui::Key::J => self.open_bookmarks(),
+                                egui::Key::W => self.close_document(self.active_document),
+                                egui::Key::PageDown => self.cycle_document(1),
+                                egui::Key::PageUp => self.cycle_document(-1),
                                 _ => {}
                             }
                         }
@@ -1967,24 +5138,14 @@ impl eframe::App for Chonker5App {
             self.needs_render = false;
             self.render_current_page(ctx);
         }
+        self.process_render_result(ctx);
+        self.process_area_result();
+        self.process_ferrules_result();
+        self.process_pdf_search_result();
 
         // Set up terminal style
         let mut style = (*ctx.style()).clone();
-        style.visuals.dark_mode = true;
-        style.visuals.override_text_color = Some(TERM_FG);
-        style.visuals.window_fill = TERM_BG;
-        style.visuals.panel_fill = TERM_BG;
-        style.visuals.extreme_bg_color = TERM_BG;
-        style.visuals.widgets.noninteractive.bg_fill = TERM_BG;
-        style.visuals.widgets.noninteractive.fg_stroke = Stroke::new(1.0, TERM_FG);
-        style.visuals.widgets.inactive.bg_fill = Color32::from_rgb(20, 25, 30);
-        style.visuals.widgets.inactive.bg_stroke = Stroke::new(1.0, CHROME);
-        style.visuals.widgets.hovered.bg_fill = Color32::from_rgb(30, 40, 45);
-        style.visuals.widgets.hovered.bg_stroke = Stroke::new(1.0, TERM_HIGHLIGHT);
-        style.visuals.widgets.active.bg_fill = Color32::from_rgb(40, 50, 55);
-        style.visuals.widgets.active.bg_stroke = Stroke::new(1.0, TERM_HIGHLIGHT);
-        style.visuals.selection.bg_fill = Color32::from_rgb(0, 150, 140);
-        style.visuals.selection.stroke = Stroke::new(1.0, TERM_HIGHLIGHT);
+        theme().apply_to_style(&mut style);
         ctx.set_style(style);
 
         // Handle focus switching
@@ -2013,30 +5174,120 @@ impl eframe::App for Chonker5App {
         });
 
         // Check for async results
-        if let Some(mut receiver) = self.vision_receiver.take() {
-            if let Ok(result) = receiver.try_recv() {
-                match result {
-                    Ok(character_matrix) => {
-                        self.matrix_result.character_matrix = Some(character_matrix.clone());
-                        self.matrix_result.editable_matrix = Some(character_matrix.matrix.clone());
-                        self.matrix_result.original_matrix = Some(character_matrix.matrix.clone());
-                        self.matrix_result.is_loading = false;
-                        self.matrix_result.matrix_dirty = false;
-                        self.log("✅ Character matrix extraction completed");
-                    }
-                    Err(e) => {
-                        self.matrix_result.error = Some(e);
-                        self.matrix_result.is_loading = false;
-                    }
-                }
-            } else {
-                self.vision_receiver = Some(receiver);
+        self.process_pdf_changes(ctx);
+        self.process_extraction_jobs();
+        self.process_document_events();
+
+        // Low-confidence review navigation (F8 / Shift+F8)
+        let review_step = ctx.input(|i| {
+            i.key_pressed(egui::Key::F8)
+                .then_some(if i.modifiers.shift { -1 } else { 1 })
+        });
+        if let Some(step) = review_step {
+            self.jump_low_confidence(step, ctx);
+        }
+
+        self.show_status_bar(ctx);
+
+        // Log panel
+        if self.show_log_panel {
+            egui::TopBottomPanel::bottom("log_panel")
+                .resizable(true)
+                .default_height(140.0)
+                .frame(egui::Frame::none().fill(theme().bg))
+                .show(ctx, |ui| {
+                    draw_terminal_box(ui, "LOG", false, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("Filter:").color(theme().dim).monospace());
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.log_filter)
+                                    .font(egui::TextStyle::Monospace)
+                                    .desired_width(200.0),
+                            );
+                        });
+                        egui::ScrollArea::vertical()
+                            .auto_shrink([false; 2])
+                            .stick_to_bottom(true)
+                            .id_source("log_scroll_area")
+                            .show(ui, |ui| {
+                                for entry in self.log_messages.filtered(&self.log_filter) {
+                                    let color = if entry.starts_with('❌') {
+                                        theme().error
+                                    } else if entry.starts_with('⚠') {
+                                        theme().warning
+                                    } else {
+                                        theme().fg
+                                    };
+                                    ui.label(RichText::new(entry).color(color).monospace().size(10.0));
+                                }
+                            });
+                    });
+                });
+        }
+
+        if self.show_region_panel {
+            self.show_region_list_panel(ctx);
+        }
+
+        if self.show_history_panel {
+            self.show_history_window(ctx);
+        }
+
+        if self.reading_order_mode {
+            self.show_reading_order_window(ctx);
+        }
+
+        if self.show_bookmarks {
+            self.show_bookmarks_window(ctx);
+        }
+
+        if self.show_edits {
+            self.show_edits_window(ctx);
+        }
+
+        if !self.pending_reextract.is_empty() {
+            self.show_reextract_window(ctx);
+        }
+
+        if self.pending_merge.is_some() {
+            self.show_merge_window(ctx);
+        }
+
+        if self.open_url_dialog {
+            self.show_open_url_window(ctx);
+        }
+
+        if self.pdf_search_open {
+            self.show_pdf_search_window(ctx);
+        }
+
+        // "?" toggles the shortcut overlay unless it would be typed somewhere
+        let typing = ctx.memory(|m| m.focus().is_some())
+            || (self.focused_pane == FocusedPane::MatrixView
+                && self.raw_text_matrix_grid.as_ref().is_some_and(|g| g.cursor_pos.is_some()));
+        if !typing {
+            let question = ctx.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Text(t) if t == "?")));
+            if question {
+                self.show_shortcuts = !self.show_shortcuts;
             }
         }
+        if self.show_shortcuts && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.show_shortcuts = false;
+        }
+        if self.show_shortcuts {
+            self.show_shortcuts_overlay(ctx);
+        }
+
+        if ctx.input(|i| i.key_pressed(egui::Key::F1)) {
+            self.show_help = !self.show_help;
+        }
+        if self.show_help {
+            self.show_help_window(ctx);
+        }
 
         // Main UI
         egui::CentralPanel::default()
-            .frame(egui::Frame::none().fill(TERM_BG))
+            .frame(egui::Frame::none().fill(theme().bg))
             .show(ctx, |ui| {
                 // Header controls
                 ui.horizontal(|ui| {
@@ -2048,97 +5299,271 @@ impl eframe::App for Chonker5App {
 
                     ui.label(
                         RichText::new("CHONKER 5")
-                            .color(TERM_HIGHLIGHT)
+                            .color(theme().highlight)
                             .monospace()
                             .size(16.0)
                             .strong()
                     );
 
-                    ui.label(RichText::new("│").color(CHROME).monospace());
+                    ui.label(RichText::new("│").color(theme().chrome).monospace());
 
-                    if ui.button(RichText::new("[O] Open").color(TERM_FG).monospace().size(12.0)).clicked() {
+                    if ui.button(RichText::new("[O] Open").color(theme().fg).monospace().size(12.0)).clicked() {
                         self.open_file(ctx);
                     }
+                    if ui.add_enabled(self.download_receiver.is_none(), egui::Button::new(RichText::new("Open URL…").color(theme().fg).monospace().size(12.0))).clicked() {
+                        self.open_url_dialog = true;
+                    }
+
+                    ui.menu_button(RichText::new("[P] Project").color(theme().fg).monospace().size(12.0), |ui| {
+                        if ui.button(RichText::new("Open project…").monospace()).clicked() {
+                            self.open_project_dialog(ctx, false);
+                            ui.close_menu();
+                        }
+                        if ui.add_enabled(self.pdf_path.is_some(), egui::Button::new(RichText::new("Save project as…").monospace())).clicked() {
+                            self.open_project_dialog(ctx, true);
+                            ui.close_menu();
+                        }
+                        ui.checkbox(&mut self.project_portable, RichText::new("Portable (relative paths)").monospace())
+                            .on_hover_text("Store document paths relative to the project file");
+                        ui.separator();
+                        if ui.add_enabled(!self.page_cache.is_empty(), egui::Button::new(RichText::new("Export regions CSV…").monospace()))
+                            .on_hover_text("Regions of all extracted pages, for bulk labelling")
+                            .clicked() {
+                            self.open_regions_csv_dialog(ctx, true);
+                            ui.close_menu();
+                        }
+                        if ui.add_enabled(self.pdf_path.is_some(), egui::Button::new(RichText::new("Import region labels CSV…").monospace())).clicked() {
+                            self.open_regions_csv_dialog(ctx, false);
+                            ui.close_menu();
+                        }
+                        if ui.add_enabled(self.pdf_path.is_some(), egui::Button::new(RichText::new("Export edit changelog…").monospace()))
+                            .on_hover_text("Every saved hand edit as JSON: page, row, col, before, after, timestamp")
+                            .clicked() {
+                            self.open_edit_log_dialog(ctx);
+                            ui.close_menu();
+                        }
+                    });
 
-                    ui.label(RichText::new("│").color(CHROME).monospace());
+                    ui.label(RichText::new("│").color(theme().chrome).monospace());
 
                     // Navigation
                     ui.add_enabled_ui(self.pdf_path.is_some() && self.current_page > 0, |ui| {
-                        if ui.button(RichText::new("←").color(TERM_FG).monospace().size(12.0)).clicked() {
-                            self.current_page = self.current_page.saturating_sub(1);
-                            self.matrix_result.character_matrix = None;
-                            self.ferrules_output_cache = None;
-                            self.ferrules_matrix_grid = None;
-                            self.render_current_page(ctx);
-                            self.extract_character_matrix(ctx);
+                        if ui.button(RichText::new("←").color(theme().fg).monospace().size(12.0)).clicked() {
+                            self.go_to_page(self.current_page.saturating_sub(1), ctx);
                         }
                     });
 
                     if self.pdf_path.is_some() {
-                        ui.label(RichText::new(format!("{}/{}", self.current_page + 1, self.total_pages))
-                            .color(TERM_FG)
+                        let current_failed = self.failed_pages.get(&self.current_page);
+                        let label = ui.label(RichText::new(format!("{}/{}", self.current_page + 1, self.total_pages))
+                            .color(if current_failed.is_some() { theme().error } else { theme().fg })
                             .monospace()
                             .size(12.0));
+                        if let Some(e) = current_failed {
+                            label.on_hover_text(e.to_string());
+                        }
                     }
 
                     ui.add_enabled_ui(self.pdf_path.is_some() && self.current_page < self.total_pages - 1, |ui| {
-                        if ui.button(RichText::new("→").color(TERM_FG).monospace().size(12.0)).clicked() {
-                            self.current_page += 1;
-                            self.matrix_result.character_matrix = None;
-                            self.ferrules_output_cache = None;
-                            self.ferrules_matrix_grid = None;
-                            self.render_current_page(ctx);
-                            self.extract_character_matrix(ctx);
+                        if ui.button(RichText::new("→").color(theme().fg).monospace().size(12.0)).clicked() {
+                            self.go_to_page(self.current_page + 1, ctx);
+                        }
+                    });
+
+                    if !self.failed_pages.is_empty() {
+                        let mut jump = None;
+                        let mut retry = false;
+                        ui.menu_button(RichText::new(format!("✗ {}", self.failed_pages.len())).color(theme().error).monospace().size(12.0), |ui| {
+                            for (&page, error) in &self.failed_pages {
+                                if ui.button(RichText::new(format!("Page {}", page + 1)).color(error_color(error)).monospace())
+                                    .on_hover_text(error.to_string())
+                                    .clicked() {
+                                    jump = Some(page);
+                                    ui.close_menu();
+                                }
+                            }
+                            ui.separator();
+                            if ui.button(RichText::new("Retry failed").monospace()).clicked() {
+                                retry = true;
+                                ui.close_menu();
+                            }
+                        })
+                        .response
+                        .on_hover_text(failed_pages_summary(&self.failed_pages));
+                        if let Some(page) = jump {
+                            self.go_to_page(page, ctx);
+                        }
+                        if retry {
+                            self.retry_failed_pages(ctx);
+                        }
+                    }
+
+                    ui.label(RichText::new("│").color(theme().chrome).monospace());
+
+                    // Low-confidence review
+                    ui.add_enabled_ui(self.pdf_path.is_some(), |ui| {
+                        if ui.button(RichText::new("◀").color(theme().warning).monospace().size(12.0))
+                            .on_hover_text("Previous low-confidence region (Shift+F8)")
+                            .clicked() {
+                            self.jump_low_confidence(-1, ctx);
+                        }
+                        ui.add(egui::DragValue::new(&mut self.review_threshold)
+                            .speed(0.01)
+                            .clamp_range(0.0..=1.0)
+                            .custom_formatter(|v, _| format!("<{:.0}%", v * 100.0)))
+                            .on_hover_text("Confidence threshold for review");
+                        if ui.button(RichText::new("▶").color(theme().warning).monospace().size(12.0))
+                            .on_hover_text("Next low-confidence region (F8)")
+                            .clicked() {
+                            self.jump_low_confidence(1, ctx);
+                        }
+                        let scan_text = if self.review_scanning { "Scanning…" } else { "Scan" };
+                        if ui.button(RichText::new(scan_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Extract all pages so review covers the whole document")
+                            .clicked() {
+                            self.scan_document_for_review(ctx);
                         }
                     });
 
-                    ui.label(RichText::new("│").color(CHROME).monospace());
+                    ui.label(RichText::new("│").color(theme().chrome).monospace());
 
                     // Zoom controls
                     ui.add_enabled_ui(self.pdf_path.is_some(), |ui| {
-                        if ui.button(RichText::new("-").color(TERM_FG).monospace().size(12.0)).clicked() {
+                        if ui.button(RichText::new("-").color(theme().fg).monospace().size(12.0)).clicked() {
                             self.zoom_level = (self.zoom_level - 0.25).max(0.5);
                             self.render_current_page(ctx);
                         }
 
                         ui.label(RichText::new(format!("{}%", (self.zoom_level * 100.0) as i32))
-                            .color(TERM_FG)
+                            .color(theme().fg)
                             .monospace()
                             .size(12.0));
 
-                        if ui.button(RichText::new("+").color(TERM_FG).monospace().size(12.0)).clicked() {
+                        if ui.button(RichText::new("+").color(theme().fg).monospace().size(12.0)).clicked() {
                             self.zoom_level = (self.zoom_level + 0.25).min(3.0);
                             self.render_current_page(ctx);
                         }
                     });
 
-                    ui.label(RichText::new("│").color(CHROME).monospace());
+                    ui.label(RichText::new("│").color(theme().chrome).monospace());
 
                     ui.add_enabled_ui(self.pdf_path.is_some(), |ui| {
-                        if ui.button(RichText::new("[M]").color(TERM_FG).monospace().size(12.0)).clicked() {
+                        if ui.button(RichText::new("[M]").color(theme().fg).monospace().size(12.0)).clicked() {
                             self.extract_character_matrix(ctx);
                             self.active_tab = ExtractionTab::RawText;
                         }
 
-                        ui.label(RichText::new("│").color(CHROME).monospace());
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
 
                         let bbox_text = if self.show_bounding_boxes { "[B]✓" } else { "[B]" };
-                        if ui.button(RichText::new(bbox_text).color(TERM_FG).monospace().size(12.0)).clicked() {
+                        if ui.button(RichText::new(bbox_text).color(theme().fg).monospace().size(12.0)).clicked() {
                             self.show_bounding_boxes = !self.show_bounding_boxes;
                         }
 
-                        ui.label(RichText::new("│").color(CHROME).monospace());
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        let watch_text = if self.watch_pdf { "[W]✓" } else { "[W]" };
+                        if ui.button(RichText::new(watch_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Watch the PDF and re-extract when another tool regenerates it")
+                            .clicked() {
+                            self.watch_pdf = !self.watch_pdf;
+                        }
+
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
                         let dark_text = if self.pdf_dark_mode { "[D]✓" } else { "[D]" };
-                        if ui.button(RichText::new(dark_text).color(TERM_FG).monospace().size(12.0))
+                        if ui.button(RichText::new(dark_text).color(theme().fg).monospace().size(12.0))
                             .on_hover_text("Toggle light/dark mode for PDF")
                             .clicked() {
                             self.pdf_dark_mode = !self.pdf_dark_mode;
                             self.render_current_page(ctx);
                         }
 
-                        if self.matrix_result.matrix_dirty {
-                            ui.label(RichText::new("│").color(CHROME).monospace());
-                            if ui.button(RichText::new("[S] Save").color(TERM_YELLOW).monospace().size(12.0)).clicked() {
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        let log_text = if self.show_log_panel { "[L]✓" } else { "[L]" };
+                        if ui.button(RichText::new(log_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Toggle log panel")
+                            .clicked() {
+                            self.show_log_panel = !self.show_log_panel;
+                        }
+
+                        ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        if ui.button(RichText::new("[?]").color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("How extraction works (F1)")
+                            .clicked() {
+                            self.show_help = !self.show_help;
+                        }
+
+                        let area_text = if self.area_tool { "[A]✓" } else { "[A]" };
+                        if ui.button(RichText::new(area_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Draw a rectangle on the page to re-extract just that area")
+                            .clicked() {
+                            self.area_tool = !self.area_tool;
+                            self.area_drag = None;
+                        }
+
+                        let find_text = if self.pdf_search_open { "[F]✓" } else { "[F]" };
+                        if ui.button(RichText::new(find_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Find text in the PDF (Ctrl+F on the PDF pane)")
+                            .clicked() {
+                            self.pdf_search_open = !self.pdf_search_open;
+                        }
+
+                        let order_text = if self.reading_order_mode { "[#]✓" } else { "[#]" };
+                        if ui.button(RichText::new(order_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Edit the reading order of this page's regions")
+                            .clicked() {
+                            self.reading_order_mode = !self.reading_order_mode;
+                        }
+
+                        let regions_text = if self.show_region_panel { "[R]✓" } else { "[R]" };
+                        if ui.button(RichText::new(regions_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Toggle region list (Ctrl+R)")
+                            .clicked() {
+                            self.show_region_panel = !self.show_region_panel;
+                        }
+
+                        let bookmarks_text = if self.show_bookmarks { "[J]✓" } else { "[J]" };
+                        if ui.button(RichText::new(bookmarks_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Named bookmarks (Ctrl+J)")
+                            .clicked() {
+                            self.show_bookmarks = !self.show_bookmarks;
+                        }
+
+                        let edits_text = if self.show_edits { "[E]✓" } else { "[E]" };
+                        if ui.button(RichText::new(edits_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Diff of this page's edits against the extraction, with revert")
+                            .clicked() {
+                            self.show_edits = !self.show_edits;
+                        }
+
+                        let keys_text = if self.show_shortcuts { "[K]✓" } else { "[K]" };
+                        if ui.button(RichText::new(keys_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Keyboard shortcuts for the focused pane (?)")
+                            .clicked() {
+                            self.show_shortcuts = !self.show_shortcuts;
+                        }
+
+                        ui.menu_button(RichText::new("[T]").color(theme().fg).monospace().size(12.0), |ui| {
+                            for (name, _) in Theme::PRESETS {
+                                let current = self.theme_config.preset.as_deref().unwrap_or("teal") == name;
+                                if ui.selectable_label(current, name).clicked() {
+                                    self.set_theme_preset(name);
+                                    ui.close_menu();
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text("Color theme (overrides in chonker5_theme.json still apply)");
+
+                        let history_text = if self.show_history_panel { "[H]✓" } else { "[H]" };
+                        if ui.button(RichText::new(history_text).color(theme().fg).monospace().size(12.0))
+                            .on_hover_text("Browse saved versions of this page's matrix")
+                            .clicked() {
+                            self.toggle_history_panel();
+                        }
+
+                        if self.document.is_modified(self.current_page) {
+                            ui.label(RichText::new("│").color(theme().chrome).monospace());
+                            if ui.button(RichText::new("[S] Save").color(theme().warning).monospace().size(12.0)).clicked() {
                                 self.save_edited_matrix();
                             }
                         }
@@ -2147,6 +5572,41 @@ impl eframe::App for Chonker5App {
 
                 ui.add_space(2.0);
 
+                // Document tabs
+                if self.document_tabs.len() > 1 {
+                    let mut switch_to = None;
+                    let mut close = None;
+                    ui.horizontal(|ui| {
+                        for (i, tab) in self.document_tabs.iter().enumerate() {
+                            let active = i == self.active_document;
+                            let (title, dirty) = match tab {
+                                Some(tab) => (tab.title(), tab.document.has_unsaved_edits()),
+                                None => (document_title(self.pdf_path.as_deref()), self.document.has_unsaved_edits()),
+                            };
+                            let text = format!("{}{}", title, if dirty { " ●" } else { "" });
+                            let label = if active {
+                                RichText::new(format!("[{}]", text)).color(theme().highlight).monospace()
+                            } else {
+                                RichText::new(format!(" {} ", text)).color(theme().dim).monospace()
+                            };
+                            if ui.button(label).on_hover_text("Ctrl+PageUp/PageDown to switch").clicked() {
+                                switch_to = Some(i);
+                            }
+                            if ui.small_button(RichText::new("×").color(theme().dim).monospace())
+                                .on_hover_text("Close tab (Ctrl+W)")
+                                .clicked() {
+                                close = Some(i);
+                            }
+                            ui.label(RichText::new("│").color(theme().chrome).monospace());
+                        }
+                    });
+                    if let Some(i) = switch_to {
+                        self.switch_document(i);
+                    } else if let Some(i) = close {
+                        self.close_document(i);
+                    }
+                }
+
                 // Main content area
                 if self.pdf_path.is_some() {
                     let available_size = ui.available_size();
@@ -2184,11 +5644,52 @@ impl eframe::App for Chonker5App {
                                                 let total_pages = self.total_pages;
 
                                                 ui.vertical_centered(|ui| {
-                                                    let response = ui.image(egui::load::SizedTexture::new(texture_id, display_size));
+                                                    let response = ui.add(
+                                                        egui::Image::new(egui::load::SizedTexture::new(texture_id, display_size))
+                                                            .sense(if self.area_tool {
+                                                                egui::Sense::click_and_drag()
+                                                            } else {
+                                                                egui::Sense::click()
+                                                            }),
+                                                    );
+
+                                                    if self.area_tool {
+                                                        self.handle_area_drag(ui, &response);
+                                                    }
+
+                                                    if response.clicked() {
+                                                        self.link_pdf_click_to_matrix(&response);
+                                                    }
+
+                                                    if response.secondary_clicked() {
+                                                        self.context_region = response
+                                                            .interact_pointer_pos()
+                                                            .and_then(|pos| self.region_at(response.rect, pos));
+                                                    }
+                                                    if let Some(region_idx) = self.context_region {
+                                                        let current = self.current_matrix()
+                                                            .and_then(|m| m.text_regions.get(region_idx))
+                                                            .and_then(|r| r.tag);
+                                                        let mut chosen = None;
+                                                        response.clone().context_menu(|ui| {
+                                                            chosen = region_tag_menu(ui, current);
+                                                        });
+                                                        if let Some(tag) = chosen {
+                                                            self.set_region_tag(region_idx, tag);
+                                                        }
+                                                    }
 
                                                     if self.show_bounding_boxes {
                                                         self.draw_character_matrix_overlay(ui, &response);
                                                     }
+                                                    if self.reading_order_mode {
+                                                        self.draw_reading_order(ui, &response);
+                                                    }
+                                                    self.draw_linked_cell(ui, &response);
+                                                    if !self.pdf_search_hits.is_empty() {
+                                                        self.draw_pdf_search_hits(ui, &response);
+                                                    }
+                                                    self.draw_region_flash(ui, &response);
 
                                                     if response.hovered() {
                                                         let zoom_delta = ui.input(|i| i.zoom_delta());
@@ -2200,19 +5701,9 @@ impl eframe::App for Chonker5App {
                                                         let scroll_delta = ui.input(|i| i.scroll_delta);
                                                         if scroll_delta.y.abs() > 10.0 {
                                                             if scroll_delta.y > 0.0 && current_page > 0 {
-                                                                self.current_page = current_page - 1;
-                                                                self.matrix_result.character_matrix = None;
-                                                                self.ferrules_output_cache = None;
-                                                                self.ferrules_matrix_grid = None;
-                                                                self.needs_render = true;
-                                                                self.extract_character_matrix(ctx);
+                                                                self.go_to_page(current_page - 1, ctx);
                                                             } else if scroll_delta.y < 0.0 && current_page < total_pages - 1 {
-                                                                self.current_page = current_page + 1;
-                                                                self.matrix_result.character_matrix = None;
-                                                                self.ferrules_output_cache = None;
-                                                                self.ferrules_matrix_grid = None;
-                                                                self.needs_render = true;
-                                                                self.extract_character_matrix(ctx);
+                                                                self.go_to_page(current_page + 1, ctx);
                                                             }
                                                         }
                                                     }
@@ -2220,7 +5711,7 @@ impl eframe::App for Chonker5App {
                                             } else {
                                                 ui.centered_and_justified(|ui| {
                                                     ui.label(RichText::new("Loading page...")
-                                                        .color(TERM_DIM)
+                                                        .color(theme().dim)
                                                         .monospace());
                                                 });
                                             }
@@ -2238,9 +5729,9 @@ impl eframe::App for Chonker5App {
                         let separator_response = ui.allocate_rect(separator_rect, egui::Sense::drag());
 
                         let separator_color = if separator_response.hovered() {
-                            TERM_HIGHLIGHT
+                            theme().highlight
                         } else {
-                            CHROME
+                            theme().chrome
                         };
                         ui.painter().rect_filled(separator_response.rect, 0.0, separator_color);
 
@@ -2249,7 +5740,7 @@ impl eframe::App for Chonker5App {
                             ui.painter().circle_filled(
                                 egui::pos2(center.x, center.y + i as f32 * 10.0),
                                 1.5,
-                                TERM_DIM
+                                theme().dim
                             );
                         }
 
@@ -2286,22 +5777,33 @@ impl eframe::App for Chonker5App {
                                             if self.focused_pane == FocusedPane::MatrixView && self.selected_cell.is_some() {
                                                 label.push_str(" ⌨️");
                                             }
-                                            RichText::new(label).color(TERM_HIGHLIGHT).monospace()
+                                            RichText::new(label).color(theme().highlight).monospace()
                                         } else {
-                                            RichText::new(" Raw Text ").color(TERM_DIM).monospace()
+                                            RichText::new(" Raw Text ").color(theme().dim).monospace()
                                         };
                                         if ui.button(matrix_label).clicked() {
                                             self.active_tab = ExtractionTab::RawText;
                                         }
 
                                         let ferrules_label = if self.active_tab == ExtractionTab::SmartLayout {
-                                            RichText::new("[SMART LAYOUT]").color(TERM_HIGHLIGHT).monospace()
+                                            RichText::new("[SMART LAYOUT]").color(theme().highlight).monospace()
                                         } else {
-                                            RichText::new(" Smart Layout ").color(TERM_DIM).monospace()
+                                            RichText::new(" Smart Layout ").color(theme().dim).monospace()
                                         };
                                         if ui.button(ferrules_label).clicked() {
                                             self.active_tab = ExtractionTab::SmartLayout;
                                         }
+
+                                        let compare_label = if self.active_tab == ExtractionTab::Compare {
+                                            RichText::new("[COMPARE]").color(theme().highlight).monospace()
+                                        } else {
+                                            RichText::new(" Compare ").color(theme().dim).monospace()
+                                        };
+                                        if ui.button(compare_label)
+                                            .on_hover_text("Raw Text and Smart Layout side by side with differences highlighted")
+                                            .clicked() {
+                                            self.active_tab = ExtractionTab::Compare;
+                                        }
                                     });
 
                                     ui.separator();
@@ -2318,118 +5820,396 @@ impl eframe::App for Chonker5App {
                                                         ui.centered_and_justified(|ui| {
                                                             ui.spinner();
                                                             ui.label(RichText::new("\nExtracting raw text...")
-                                                                .color(TERM_FG)
+                                                                .color(theme().fg)
                                                                 .monospace());
                                                         });
                                                     } else if let Some(error) = &self.matrix_result.error {
-                                                        ui.label(RichText::new(error).color(TERM_ERROR).monospace());
-                                                    } else if let Some(character_matrix) = &self.matrix_result.character_matrix {
-                                                        // Create or update the matrix grid for Raw Text
-                                                        if self.matrix_result.editable_matrix.is_none() {
-                                                            // Initialize the editable matrix from character matrix
-                                                            self.matrix_result.editable_matrix = Some(character_matrix.matrix.clone());
+                                                        ui.label(RichText::new(error.to_string()).color(error_color(error)).monospace());
+                                                        if let Some(hint) = error.hint() {
+                                                            ui.label(RichText::new(hint).color(theme().dim).monospace().size(10.0));
+                                                        }
+                                                    } else if let Some(document_page) = self.document.page(self.current_page) {
+                                                        // The grid views the document's page: `commit_grid_edits`
+                                                        // hands its edits back and its undo keys step the document
+                                                        if self.raw_text_matrix_grid.is_none() {
+                                                            let mut grid = MatrixGrid::new("");
+                                                            grid.set_matrix(document_page.matrix.rows().into_owned());
+                                                            grid.shared_history = true;
+                                                            if let Some(bbox) = self.pending_grid_selection.take() {
+                                                                grid.select_block(&bbox);
+                                                            }
+                                                            if let Some((row, col)) = self.pending_grid_cursor.take() {
+                                                                grid.set_cursor(row, col);
+                                                            }
+                                                            self.raw_text_matrix_grid = Some(grid);
                                                         }
                                                         
-                                                        // Format the matrix with line numbers for MatrixGrid
-                                                        let mut matrix_text = String::new();
-                                                        if let Some(editable_matrix) = &self.matrix_result.editable_matrix {
-                                                            for (row_idx, row) in editable_matrix.iter().enumerate() {
-                                                                matrix_text.push_str(&format!("{:3} ", row_idx));
-                                                                for &ch in row {
-                                                                    matrix_text.push(ch);
+                                                        let mut selection_op = None;
+                                                        ui.horizontal(|ui| {
+                                                            ui.label(RichText::new("Click to place cursor. Click and drag to select. Drag selection to move. Type to edit. Ctrl+C/X/V for copy/cut/paste.")
+                                                                .color(theme().dim)
+                                                                .size(10.0));
+                                                            let has_selection = self.raw_text_matrix_grid.as_ref().is_some_and(|g| g.selection.bbox().is_some());
+                                                            if ui.add_enabled(has_selection, egui::Button::new(RichText::new("Save selection as…").color(theme().fg).monospace().size(10.0)))
+                                                                .on_hover_text("Write the selected rectangle to a text file (Ctrl+Shift+S)")
+                                                                .clicked() {
+                                                                self.save_selection_as(ui.ctx());
+                                                            }
+                                                            ui.add_enabled_ui(has_selection, |ui| {
+                                                                ui.menu_button(RichText::new("Selection ▾").color(theme().fg).monospace().size(10.0), |ui| {
+                                                                    if ui.button("Align columns")
+                                                                        .on_hover_text("Pad tokens separated by 2+ spaces so the columns line up")
+                                                                        .clicked() {
+                                                                        selection_op = Some(SelectionOp::AlignColumns);
+                                                                        ui.close_menu();
+                                                                    }
+                                                                    if ui.button("Fill with…")
+                                                                        .on_hover_text("Set every selected cell to one character (Ctrl+Shift+F)")
+                                                                        .clicked() {
+                                                                        self.open_fill();
+                                                                        ui.close_menu();
+                                                                    }
+                                                                    if ui.button("Clear")
+                                                                        .on_hover_text("Blank the selected cells (Delete)")
+                                                                        .clicked() {
+                                                                        selection_op = Some(SelectionOp::Fill(' '));
+                                                                        ui.close_menu();
+                                                                    }
+                                                                    if ui.button("Reflow paragraph")
+                                                                        .on_hover_text("Re-wrap the selected text to the selection's width")
+                                                                        .clicked() {
+                                                                        selection_op = Some(SelectionOp::Reflow);
+                                                                        ui.close_menu();
+                                                                    }
+                                                                    if ui.button("Transpose")
+                                                                        .on_hover_text("Swap rows and columns of the selected rectangle")
+                                                                        .clicked() {
+                                                                        selection_op = Some(SelectionOp::Transpose);
+                                                                        ui.close_menu();
+                                                                    }
+                                                                    ui.menu_button("Transform", |ui| {
+                                                                        for transform in CharTransform::ALL {
+                                                                            if ui.button(transform.label()).clicked() {
+                                                                                selection_op = Some(SelectionOp::Transform(transform));
+                                                                                ui.close_menu();
+                                                                            }
+                                                                        }
+                                                                    });
+                                                                    ui.separator();
+                                                                    ui.label(RichText::new("Sort rows").color(theme().dim).monospace().size(10.0));
+                                                                    let sort = &mut self.row_sort;
+                                                                    let mut by_cols = sort.key_cols.is_some();
+                                                                    ui.checkbox(&mut by_cols, "By columns (relative to selection)");
+                                                                    if by_cols {
+                                                                        let (mut from, mut to) = sort.key_cols.unwrap_or((0, 0));
+                                                                        ui.horizontal(|ui| {
+                                                                            ui.add(egui::DragValue::new(&mut from).prefix("from "));
+                                                                            ui.add(egui::DragValue::new(&mut to).prefix("to "));
+                                                                        });
+                                                                        sort.key_cols = Some((from, to.max(from)));
+                                                                    } else {
+                                                                        sort.key_cols = None;
+                                                                    }
+                                                                    ui.checkbox(&mut sort.numeric, "Numeric");
+                                                                    ui.checkbox(&mut sort.reverse, "Reverse");
+                                                                    if ui.button("Sort").clicked() {
+                                                                        selection_op = Some(SelectionOp::SortRows(*sort));
+                                                                        ui.close_menu();
+                                                                    }
+                                                                });
+                                                            });
+                                                            ui.menu_button(RichText::new("Cleanup ▾").color(theme().fg).monospace().size(10.0), |ui| {
+                                                                ui.label(RichText::new(if has_selection { "Scope: selection" } else { "Scope: whole matrix" })
+                                                                    .color(theme().dim)
+                                                                    .monospace()
+                                                                    .size(10.0));
+                                                                for cleanup in WhitespaceOp::ALL {
+                                                                    if ui.button(cleanup.label()).clicked() {
+                                                                        selection_op = Some(SelectionOp::Whitespace(cleanup));
+                                                                        ui.close_menu();
+                                                                    }
+                                                                }
+                                                            });
+                                                            if ui.button(RichText::new(self.matrix_split.label()).color(theme().fg).monospace().size(10.0))
+                                                                .on_hover_text("Split the matrix into two viewports of the same buffer")
+                                                                .clicked() {
+                                                                self.matrix_split = self.matrix_split.next();
+                                                                self.active_viewport = 0;
+                                                            }
+                                                            let draw_label = if self.box_draw { "[Box ✏]✓" } else { "[Box ✏]" };
+                                                            if ui.button(RichText::new(draw_label).color(if self.box_draw { theme().highlight } else { theme().fg }).monospace().size(10.0))
+                                                                .on_hover_text("Drag to draw ─ │ ┌ ┐ └ ┘ lines; crossings join into ┼")
+                                                                .clicked() {
+                                                                self.box_draw = !self.box_draw;
+                                                            }
+                                                            let snap_label = if self.snap_drops { "[Snap]✓" } else { "[Snap]" };
+                                                            if ui.button(RichText::new(snap_label).color(if self.snap_drops { theme().highlight } else { theme().fg }).monospace().size(10.0))
+                                                                .on_hover_text("Snap dropped blocks to nearby text region columns and rows")
+                                                                .clicked() {
+                                                                self.snap_drops = !self.snap_drops;
+                                                            }
+                                                            let edits_label = if self.highlight_edits { "[Edits]✓" } else { "[Edits]" };
+                                                            if ui.button(RichText::new(edits_label).color(if self.highlight_edits { theme().highlight } else { theme().fg }).monospace().size(10.0))
+                                                                .on_hover_text("Tint cells that differ from the original extraction")
+                                                                .clicked() {
+                                                                self.highlight_edits = !self.highlight_edits;
+                                                            }
+                                                            let vim_label = if self.vim_mode { "[Vim]✓" } else { "[Vim]" };
+                                                            if ui.button(RichText::new(vim_label).color(if self.vim_mode { theme().highlight } else { theme().fg }).monospace().size(10.0))
+                                                                .on_hover_text("Modal editing: hjkl, counts, i/Esc, v for visual block, x dd yy p u")
+                                                                .clicked() {
+                                                                self.vim_mode = !self.vim_mode;
+                                                            }
+                                                            let mut drop_policy = None;
+                                                            ui.menu_button(RichText::new("Drop ▾").color(theme().fg).monospace().size(10.0), |ui| {
+                                                                for policy in DropPolicy::ALL {
+                                                                    if ui.selectable_label(self.matrix_view.drop_policy == policy, policy.label()).clicked() {
+                                                                        drop_policy = Some(policy);
+                                                                        ui.close_menu();
+                                                                    }
                                                                 }
-                                                                matrix_text.push('\n');
+                                                            })
+                                                            .response
+                                                            .on_hover_text("What a dragged block does to text already under it");
+                                                            if let Some(policy) = drop_policy {
+                                                                self.set_drop_policy(policy);
                                                             }
+                                                        });
+                                                        if let Some(op) = selection_op {
+                                                            self.apply_selection_op(op);
                                                         }
                                                         
-                                                        // Create or update MatrixGrid
-                                                        if self.raw_text_matrix_grid.is_none() {
-                                                            self.raw_text_matrix_grid = Some(MatrixGrid::new(&matrix_text));
+                                                        if self.matrix_search_open {
+                                                            self.show_matrix_search_bar(ui);
                                                         }
-                                                        
-                                                        ui.label(RichText::new("Click to place cursor. Click and drag to select. Drag selection to move. Type to edit. Ctrl+C/X/V for copy/cut/paste.")
-                                                            .color(TERM_DIM)
-                                                            .size(10.0));
-                                                        
+                                                        if self.fill_open {
+                                                            self.show_fill_bar(ui);
+                                                        }
+                                                        if self.goto_open {
+                                                            self.show_goto_bar(ui);
+                                                        }
+
+                                                        let mut matrix_zoom = 1.0;
+                                                        let mut clicked_viewport = None;
+                                                        let mut drop_rejected = false;
+                                                        let mut copy_spill = None;
+                                                        let split = self.matrix_split;
+                                                        let active_viewport = self.active_viewport;
                                                         egui::Frame::none()
-                                                            .fill(Color32::from_rgb(10, 15, 20))
+                                                            .fill(theme().bg)
                                                             .show(ui, |ui| {
-                                                                egui::ScrollArea::both()
-                                                                    .auto_shrink([false; 2])
-                                                                    .show(ui, |ui| {
-                                                                        // Use the stored matrix grid
-                                                                        if let Some(grid) = &mut self.raw_text_matrix_grid {
-                                                                            let response = grid.show(ui);
-                                                                            
-                                                                            // Sync any changes made by MatrixGrid back to the editable matrix
-                                                                            if grid.modified {
-                                                                                if let Some(editable) = &mut self.matrix_result.editable_matrix {
-                                                                                    *editable = grid.matrix.clone();
-                                                                                    self.matrix_result.matrix_dirty = true;
+                                                                // Use the stored matrix grid
+                                                                if let Some(grid) = &mut self.raw_text_matrix_grid {
+                                                                    grid.set_font_size(self.matrix_view.font_size);
+                                                                    grid.draw_mode = self.box_draw;
+                                                                    grid.drop_policy = self.matrix_view.drop_policy;
+                                                                    if !self.highlight_edits {
+                                                                        grid.original = None;
+                                                                    } else if grid.original.is_none() {
+                                                                        grid.original = self.document.page(self.current_page).map(|page| page.extracted.clone());
+                                                                    }
+                                                                    if self.vim_mode != grid.vim.is_some() {
+                                                                        grid.vim = self.vim_mode.then(VimState::default);
+                                                                    }
+                                                                    grid.snap = if self.snap_drops {
+                                                                        self.document.page(self.current_page).map(|page| SnapGuides::from_regions(&page.matrix.text_regions))
+                                                                    } else {
+                                                                        None
+                                                                    };
+
+                                                                    // Every viewport paints the same grid; only the active one takes keys
+                                                                    let mut viewport = |ui: &mut egui::Ui, index: usize| {
+                                                                        egui::ScrollArea::both()
+                                                                            .id_source(("raw_matrix_viewport", index))
+                                                                            .auto_shrink([false; 2])
+                                                                            .show(ui, |ui| {
+                                                                                let response = grid.show_viewport(ui, index == active_viewport);
+                                                                                if response.hovered() {
+                                                                                    matrix_zoom = ui.input(|i| i.zoom_delta());
                                                                                 }
-                                                                                grid.modified = false; // Reset the flag
-                                                                            }
+                                                                                if response.clicked() || response.drag_started() {
+                                                                                    clicked_viewport = Some(index);
+                                                                                }
+                                                                            });
+                                                                    };
+                                                                    match split {
+                                                                        MatrixSplit::Off => viewport(ui, 0),
+                                                                        MatrixSplit::Rows => {
+                                                                            let size = Vec2::new(ui.available_width(), (ui.available_height() - 8.0) / 2.0);
+                                                                            ui.allocate_ui(size, |ui| viewport(ui, 0));
+                                                                            ui.separator();
+                                                                            viewport(ui, 1);
                                                                         }
-                                                                    });
+                                                                        MatrixSplit::Columns => {
+                                                                            ui.columns(2, |columns| {
+                                                                                viewport(&mut columns[0], 0);
+                                                                                viewport(&mut columns[1], 1);
+                                                                            });
+                                                                        }
+                                                                    }
+
+                                                                    // Mirror the cursor onto the PDF pane
+                                                                    if let Some((row, col)) = grid.cursor_pos {
+                                                                        self.selected_cell = Some((col, row));
+                                                                    }
+
+                                                                    drop_rejected = std::mem::take(&mut grid.drop_rejected);
+                                                                    copy_spill = grid.copy_spill.take();
+                                                                }
                                                             });
+                                                        if drop_rejected {
+                                                            self.log("⚠️ Drop refused: it would overwrite text (drop policy is Reject)");
+                                                        }
+                                                        match copy_spill {
+                                                            Some(Ok(path)) => self.log(&format!("📋 Selection too large for the clipboard - wrote it to {}", path.display())),
+                                                            Some(Err(e)) => self.log(&format!("❌ Could not write selection: {}", e)),
+                                                            None => {}
+                                                        }
+                                                        if matrix_zoom != 1.0 {
+                                                            self.zoom_matrix(Some(matrix_zoom));
+                                                        }
+                                                        if let Some(index) = clicked_viewport {
+                                                            self.active_viewport = index;
+                                                        }
                                                         
-                                                        // Show statistics
                                                         ui.separator();
-                                                        ui.label(RichText::new(format!("Character Matrix ({}x{}) - Page {} | Text Regions: {} | Objects: {}", 
-                                                            character_matrix.width, 
-                                                            character_matrix.height,
-                                                            self.current_page + 1,
-                                                            character_matrix.text_regions.len(),
-                                                            character_matrix.original_text.len()))
-                                                            .color(TERM_DIM)
-                                                            .monospace()
-                                                            .size(10.0));
+
+                                                        let (warnings, monospace, char_size) = self
+                                                            .current_matrix()
+                                                            .map(|m| (m.warnings.clone(), m.monospace.clone(), (m.char_width, m.char_height)))
+                                                            .unwrap_or_default();
+                                                        for warning in &warnings {
+                                                            ui.label(RichText::new(format!("⚠️ {}", warning))
+                                                                .color(theme().warning)
+                                                                .monospace()
+                                                                .size(10.0));
+                                                        }
+
+                                                        if let Some(detection) = &monospace {
+                                                            let mut label = None;
+                                                            let row = ui.horizontal(|ui| {
+                                                                ui.label(RichText::new(format!("Fixed-width: {:.0}% · pitch {:.1}pt",
+                                                                    detection.probability * 100.0,
+                                                                    detection.features.pitch))
+                                                                    .color(if detection.is_monospace() { theme().success } else { theme().dim })
+                                                                    .monospace()
+                                                                    .size(10.0));
+                                                                if ui.small_button(RichText::new("Monospace").monospace().size(10.0))
+                                                                    .on_hover_text("Teach the detector that this page is fixed-width")
+                                                                    .clicked() {
+                                                                    label = Some(true);
+                                                                }
+                                                                if ui.small_button(RichText::new("Proportional").monospace().size(10.0))
+                                                                    .on_hover_text("Teach the detector that this page is not fixed-width")
+                                                                    .clicked() {
+                                                                    label = Some(false);
+                                                                }
+                                                            });
+                                                            self.scroll_to_help_setting(ui, HelpSetting::MonospaceLabel, row.response.rect);
+                                                            if let Some(is_monospace) = label {
+                                                                self.label_monospace(is_monospace, ui.ctx());
+                                                            }
+                                                        }
+
+                                                        // Per-page character size override
+                                                        let has_override = self.char_overrides.contains_key(&self.current_page);
+                                                        let mut apply_override = None;
+                                                        let mut auto_override = false;
+                                                        let row = ui.horizontal(|ui| {
+                                                            ui.label(RichText::new(format!("Char: {:.1}x{:.1}pt{}",
+                                                                char_size.0,
+                                                                char_size.1,
+                                                                if has_override { " (override)" } else { "" }))
+                                                                .color(theme().dim)
+                                                                .monospace()
+                                                                .size(10.0));
+                                                            ui.add(egui::DragValue::new(&mut self.char_override_edit.char_width).speed(0.1).clamp_range(2.0..=40.0).prefix("w "));
+                                                            ui.add(egui::DragValue::new(&mut self.char_override_edit.char_height).speed(0.1).clamp_range(4.0..=60.0).prefix("h "));
+                                                            if ui.button(RichText::new("Apply").monospace().size(10.0)).clicked() {
+                                                                apply_override = Some(Some(self.char_override_edit));
+                                                            }
+                                                            if ui.button(RichText::new("Auto").monospace().size(10.0))
+                                                                .on_hover_text("Compute from this page's font sizes")
+                                                                .clicked() {
+                                                                auto_override = true;
+                                                            }
+                                                            if has_override && ui.button(RichText::new("Clear").monospace().size(10.0)).clicked() {
+                                                                apply_override = Some(None);
+                                                            }
+                                                        });
+                                                        self.scroll_to_help_setting(ui, HelpSetting::CharSize, row.response.rect);
+                                                        if let Some(dims) = apply_override {
+                                                            self.set_char_override(dims, ui.ctx());
+                                                        } else if auto_override {
+                                                            self.auto_char_override(ui.ctx());
+                                                        }
+
+                                                        // Extraction limits (apply to the next extraction)
+                                                        let mut save_limits = false;
+                                                        let row = ui.horizontal(|ui| {
+                                                            let limits = &mut self.extraction_limits;
+                                                            ui.label(RichText::new("Limits:").color(theme().dim).monospace().size(10.0));
+                                                            ui.add(egui::DragValue::new(&mut limits.timeout_secs).clamp_range(1..=3600).suffix("s"))
+                                                                .on_hover_text("Extraction timeout");
+                                                            ui.add(egui::DragValue::new(&mut limits.max_width).speed(10).clamp_range(10..=20_000).prefix("cols "));
+                                                            ui.add(egui::DragValue::new(&mut limits.max_height).speed(10).clamp_range(10..=20_000).prefix("rows "));
+                                                            ui.add(egui::DragValue::new(&mut limits.max_text_objects).speed(1000).clamp_range(1000..=10_000_000).prefix("objs "))
+                                                                .on_hover_text("Most text objects read from one page");
+                                                            if ui.button(RichText::new("Save").monospace().size(10.0)).clicked() {
+                                                                save_limits = true;
+                                                            }
+                                                        });
+                                                        self.scroll_to_help_setting(ui, HelpSetting::ExtractionLimits, row.response.rect);
+                                                        if save_limits {
+                                                            match self.extraction_limits.save() {
+                                                                Ok(()) => self.log("⚙️ Extraction limits saved; they apply to the next extraction"),
+                                                                Err(e) => self.log(&format!("⚠️ Could not save extraction limits: {}", e)),
+                                                            }
+                                                        }
                                                     } else {
                                                         ui.centered_and_justified(|ui| {
                                                             ui.label(RichText::new("No character matrix yet\n\nPress [M] to extract")
-                                                                .color(TERM_DIM)
+                                                                .color(theme().dim)
                                                                 .monospace());
                                                         });
                                                     }
                                                 }
+                                                ExtractionTab::Compare => self.show_compare_view(ui),
                                                 ExtractionTab::SmartLayout => {
                                                     // Ferrules smart layout view
-                                                    if let Some(pdf_path) = self.pdf_path.clone() {
-                                                        if self.ferrules_output_cache.is_none() {
-                                                            self.log(&format!("🔄 Running Ferrules for page {}...", self.current_page + 1));
-                                                            match self.matrix_engine.run_ferrules_integration_test(&pdf_path) {
-                                                                Ok(console_output) => {
-                                                                    let page_output = format!(
-                                                                        "📄 Page {}/{}\n{}",
-                                                                        self.current_page + 1,
-                                                                        self.total_pages,
-                                                                        console_output
-                                                                    );
-                                                                    self.ferrules_output_cache = Some(page_output.clone());
-                                                                    self.ferrules_matrix_grid = Some(MatrixGrid::new(&console_output));
-                                                                    self.log("✅ Ferrules analysis complete");
-                                                                }
-                                                                Err(e) => {
-                                                                    self.ferrules_output_cache = Some(format!("❌ Terminal command failed: {}", e));
-                                                                    self.log(&format!("❌ Ferrules failed: {}", e));
-                                                                }
-                                                            }
+                                                    if self.pdf_path.is_some() {
+                                                        self.ensure_ferrules_output(ui.ctx());
+
+                                                        let running = self.ferrules_receiver.is_some();
+                                                        let rerun = ui
+                                                            .add_enabled(!running && self.ferrules_binary.is_some(), egui::Button::new(RichText::new("Re-run Ferrules").monospace().size(10.0)))
+                                                            .on_hover_text("Ignore the cached result for this page, e.g. after updating ferrules or its model")
+                                                            .clicked();
+                                                        if rerun {
+                                                            self.rerun_ferrules(ui.ctx());
                                                         }
 
                                                         if let Some(matrix_grid) = &mut self.ferrules_matrix_grid {
                                                             ui.label(RichText::new("Click to place cursor. Click and drag to select. Drag selection to move. Type to edit. Ctrl+C/X/V for copy/cut/paste.")
-                                                                .color(TERM_DIM)
+                                                                .color(theme().dim)
                                                                 .size(10.0));
 
+                                                            let mut matrix_zoom = 1.0;
                                                             egui::Frame::none()
-                                                                .fill(Color32::from_rgb(10, 15, 20))
+                                                                .fill(theme().bg)
                                                                 .show(ui, |ui| {
                                                                     egui::ScrollArea::both()
                                                                         .auto_shrink([false; 2])
                                                                         .show(ui, |ui| {
-                                                                            matrix_grid.show(ui);
+                                                                            matrix_grid.set_font_size(self.matrix_view.font_size);
+                                                                            if matrix_grid.show(ui).hovered() {
+                                                                                matrix_zoom = ui.input(|i| i.zoom_delta());
+                                                                            }
                                                                         });
                                                                 });
+                                                            if matrix_zoom != 1.0 {
+                                                                self.zoom_matrix(Some(matrix_zoom));
+                                                            }
                                                         } else if let Some(output) = &self.ferrules_output_cache {
                                                             egui::ScrollArea::both()
                                                                 .auto_shrink([false; 2])
@@ -2444,15 +6224,15 @@ impl eframe::App for Chonker5App {
                                                         } else {
                                                             ui.centered_and_justified(|ui| {
                                                                 ui.spinner();
-                                                                ui.label(RichText::new("\nPreparing Ferrules analysis...")
-                                                                    .color(TERM_FG)
+                                                                ui.label(RichText::new(format!("\nRunning Ferrules on page {}...", self.current_page + 1))
+                                                                    .color(theme().fg)
                                                                     .monospace());
                                                             });
                                                         }
                                                     } else {
                                                         ui.centered_and_justified(|ui| {
                                                             ui.label(RichText::new("No PDF loaded")
-                                                                .color(TERM_DIM)
+                                                                .color(theme().dim)
                                                                 .monospace());
                                                         });
                                                     }
@@ -2468,17 +6248,43 @@ impl eframe::App for Chonker5App {
                     draw_terminal_box(ui, "WELCOME", false, |ui| {
                         ui.centered_and_justified(|ui| {
                             ui.label(RichText::new("🐹 CHONKER 5\n\nCharacter Matrix PDF Representation\n\nPress [O] to open a PDF file\n\nThen [M] to create character matrix")
-                                .color(TERM_FG)
+                                .color(theme().fg)
                                 .monospace()
                                 .size(16.0));
                         });
                     });
                 }
             });
+
+        self.commit_grid_edits();
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("export") {
+        eprintln!("❌ Headless export moved to the CLI: chonker {}", args.join(" "));
+        std::process::exit(64);
+    }
+
+    if args.first().map(String::as_str) == Some("--register-handler") {
+        match register_pdf_handler() {
+            Ok(message) => println!("✅ {}", message),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // `chonker5 path/to/file.pdf` (also how file managers launch a handler)
+    let initial_file = args
+        .iter()
+        .find(|arg| !arg.starts_with('-'))
+        .map(PathBuf::from);
+    let watch_pdf = args.iter().any(|arg| arg == "--watch");
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1520.0, 950.0]),
         ..Default::default()
@@ -2487,7 +6293,7 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "🐹 CHONKER 5 - PDF Viewer",
         options,
-        Box::new(|cc| Box::new(Chonker5App::new(cc))),
+        Box::new(move |cc| Box::new(Chonker5App::new(cc, initial_file, watch_pdf))),
     )
 }
 
@@ -2496,65 +6302,337 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_char_bbox_contains() {
-        let bbox = CharBBox {
-            x: 10,
-            y: 5,
-            width: 20,
-            height: 15,
+    fn test_relative_path() {
+        let base = Path::new("/work/projects/alpha");
+        assert_eq!(
+            relative_path(Path::new("/work/projects/alpha/docs/a.pdf"), base),
+            PathBuf::from("docs/a.pdf")
+        );
+        assert_eq!(
+            relative_path(Path::new("/work/shared/b.pdf"), base),
+            PathBuf::from("../../shared/b.pdf")
+        );
+    }
+
+    #[test]
+    fn test_low_confidence_navigation_wraps_across_pages() {
+        let region = |x: usize, y: usize, confidence: f32| TextRegion {
+            bbox: CharBBox {
+                x,
+                y,
+                width: 2,
+                height: 1,
+            },
+            confidence,
+            text_content: "xx".to_string(),
+            region_id: 0,
+            is_italic: false,
+            label: None,
+            tag: None,
+        };
+
+        let mut cache = PageCache::new();
+        let mut page0 = CharacterMatrix::new(10, 10);
+        page0.text_regions = vec![region(0, 5, 0.3), region(0, 1, 0.9), region(4, 1, 0.2)];
+        let mut page2 = CharacterMatrix::new(10, 10);
+        page2.text_regions = vec![region(0, 0, 0.1)];
+        cache.insert(0, page0);
+        cache.insert(2, page2);
+
+        let targets = low_confidence_targets(&cache, 0.5);
+        assert_eq!(targets, vec![(0, 2), (0, 0), (2, 0)]);
+        assert_eq!(step_target(&targets, None, 1), Some((0, 2)));
+        assert_eq!(step_target(&targets, Some((2, 0)), 1), Some((0, 2)));
+        assert_eq!(step_target(&targets, Some((0, 2)), -1), Some((2, 0)));
+        assert_eq!(step_target(&[], None, 1), None);
+    }
+
+    #[test]
+    fn test_search_help() {
+        assert_eq!(search_help("").len(), HELP_TOPICS.len());
+        let italic = search_help("oblique");
+        assert_eq!(italic.len(), 1);
+        assert_eq!(HELP_TOPICS[italic[0]].title, "Italic detection");
+        assert!(search_help("no such heuristic xyz").is_empty());
+    }
+
+    #[test]
+    fn test_page_diagnostics_link_topics_and_settings() {
+        let mut matrix = CharacterMatrix::new(10, 4);
+        matrix.backend = Some(ExtractionBackend::Pdfium);
+        matrix.text_regions = [(0, 1.0, false), (1, 0.4, true)]
+            .into_iter()
+            .map(|(id, confidence, is_italic)| TextRegion {
+                bbox: CharBBox { x: 0, y: id, width: 3, height: 1 },
+                confidence,
+                text_content: String::new(),
+                region_id: id,
+                is_italic,
+                label: None,
+                tag: None,
+            })
+            .collect();
+        matrix.warnings = vec![ExtractionWarning::Truncated { page: 0, reason: "too wide".to_string() }];
+        matrix.layout = LayoutNotes { cell_font_size: Some(10.0), columns: vec![0, 12], collisions: 3 };
+
+        let diagnostics = page_diagnostics(&matrix, true, Segmentation::Backend, 0.5);
+        let found = |title: &str| {
+            diagnostics
+                .iter()
+                .find(|d| HELP_TOPICS[d.topic].title == title)
+                .unwrap_or_else(|| panic!("no diagnostic for {}", title))
         };
+        assert!(found("Extraction backends").finding.contains("PDFium"));
+        assert!(found("Character cell size").finding.contains("override"));
+        assert_eq!(found("Region merging").setting, HelpSetting::Segmentation);
+        assert!(found("Region merging").finding.contains("merged from glyphs"));
+        let layout: Vec<&str> = diagnostics
+            .iter()
+            .filter(|d| HELP_TOPICS[d.topic].title == "Columns and collisions")
+            .map(|d| d.finding.as_str())
+            .collect();
+        assert_eq!(layout, ["2 text column(s) starting at cell 0, 12", "3 glyph(s) landed on an occupied cell and were overwritten"]);
+
+        assert_eq!(found("Italic detection").setting, HelpSetting::RegionPanel);
+        assert!(found("Region confidence").finding.starts_with("1 region(s) hidden"));
+        assert_eq!(found("Extraction limits").setting, HelpSetting::ExtractionLimits);
+        assert!(diagnostics.iter().all(|d| HELP_TOPICS[d.topic].title != "Fixed-width detection"));
+        // Without the override the modal font size explains the cell
+        let diagnostics = page_diagnostics(&matrix, false, Segmentation::XyCut, 0.5);
+        let cell = diagnostics.iter().find(|d| HELP_TOPICS[d.topic].title == "Character cell size").unwrap();
+        assert!(cell.finding.ends_with("the modal font size, 10pt"));
+        assert!(diagnostics.iter().any(|d| d.finding == "2 region(s) from XY-cut blocks"));
+    }
+
+    #[test]
+    fn test_char_override_changes_overlay_cells() {
+        let xml = r#"<document><page width="60" height="40"><block><line><font name="Helvetica" size="10">
+<char quad="10 10 15 10 10 18 15 18" x="10" y="18" c="H"/><char quad="15 10 20 10 15 18 20 18" x="15" y="18" c="i"/>
+</font></line></block></page></document>"#;
+        let (limits, model) = (ExtractionLimits::default(), MonospaceModel::default());
+        let detected = parse_stext_to_matrix(xml, 0, None, &limits, &model).unwrap();
+        let dims = CharDimensions { char_width: 2.5, char_height: 10.0 };
+        let overridden = parse_stext_to_matrix(xml, 0, Some(dims), &limits, &model).unwrap();
+        assert_eq!((detected.width, overridden.width), (12, 24));
+
+        // 60 x 40 pt page drawn at 2x: the glyph stays put, its cell narrows
+        let image_rect = egui::Rect::from_min_size(egui::pos2(100.0, 50.0), Vec2::new(120.0, 80.0));
+        let before = MatrixImageMapping::new(&detected, image_rect).cell_rect(2, 1);
+        let after = MatrixImageMapping::new(&overridden, image_rect).cell_rect(4, 1);
+        assert_eq!((before.min, after.min), (egui::pos2(120.0, 70.0), egui::pos2(120.0, 70.0)));
+        assert_eq!((before.width(), after.width()), (10.0, 5.0));
+    }
 
-        assert!(bbox.contains(10, 5));
-        assert!(bbox.contains(15, 10));
-        assert!(bbox.contains(29, 19));
-        assert!(!bbox.contains(9, 5));
-        assert!(!bbox.contains(10, 4));
-        assert!(!bbox.contains(30, 10));
-        assert!(!bbox.contains(15, 20));
+    #[test]
+    fn test_matrix_image_mapping_round_trip() {
+        let mut matrix = CharacterMatrix::new(40, 20);
+        matrix.char_width = 6.0;
+        matrix.char_height = 12.0;
+        // 240 x 240 pt page drawn at 2x
+        let image_rect = egui::Rect::from_min_size(egui::pos2(100.0, 50.0), Vec2::new(480.0, 480.0));
+        let mapping = MatrixImageMapping::new(&matrix, image_rect);
+
+        let cell = mapping.cell_rect(3, 5);
+        assert_eq!(cell.min, egui::pos2(136.0, 170.0));
+        assert_eq!(cell.size(), Vec2::new(12.0, 24.0));
+        assert_eq!(mapping.cell_at(cell.center()), Some((3, 5)));
+        assert_eq!(mapping.cell_at(egui::pos2(90.0, 60.0)), None);
+        assert_eq!(mapping.cell_at(egui::pos2(590.0, 60.0)), None);
     }
 
     #[test]
-    fn test_char_bbox_area() {
-        let bbox = CharBBox {
-            x: 0,
-            y: 0,
-            width: 10,
-            height: 5,
+    fn test_region_list_order_sorts_and_filters() {
+        let region = |id: usize, x: usize, y: usize, confidence: f32| TextRegion {
+            bbox: CharBBox { x, y, width: 3, height: 1 },
+            confidence,
+            text_content: String::new(),
+            region_id: id,
+            is_italic: false,
+            label: None,
+            tag: None,
         };
-        assert_eq!(bbox.area(), 50);
+        let regions = vec![
+            region(0, 10, 5, 0.9),
+            region(1, 0, 2, 0.3),
+            region(2, 4, 2, 0.6),
+        ];
+
+        assert_eq!(region_list_order(&regions, RegionSort::Id, 0.0), vec![0, 1, 2]);
+        assert_eq!(region_list_order(&regions, RegionSort::Confidence, 0.0), vec![1, 2, 0]);
+        assert_eq!(region_list_order(&regions, RegionSort::Position, 0.0), vec![1, 2, 0]);
+        assert_eq!(region_list_order(&regions, RegionSort::Id, 0.5), vec![0, 2]);
+    }
 
-        let zero_bbox = CharBBox {
-            x: 0,
-            y: 0,
-            width: 0,
-            height: 10,
+    #[test]
+    fn test_merge_and_split_regions() {
+        let mut matrix = CharacterMatrix::new(10, 3);
+        for (i, ch) in "ab cd".chars().enumerate() {
+            matrix.rows_mut()[0][i] = ch;
+        }
+        for (i, ch) in "ef".chars().enumerate() {
+            matrix.rows_mut()[1][i] = ch;
+        }
+        let region = |id: usize, x: usize, y: usize, width: usize, confidence: f32| TextRegion {
+            bbox: CharBBox { x, y, width, height: 1 },
+            confidence,
+            text_content: String::new(),
+            region_id: id,
+            is_italic: false,
+            label: None,
+            tag: None,
         };
-        assert_eq!(zero_bbox.area(), 0);
+        matrix.text_regions = vec![region(0, 0, 0, 2, 0.9), region(1, 3, 0, 2, 0.6), region(2, 0, 1, 2, 0.8)];
+
+        let merged = merge_regions(&mut matrix, &[1, 0]).unwrap();
+        assert_eq!(merged, 0);
+        assert_eq!(matrix.text_regions.len(), 2);
+        let r = &matrix.text_regions[0];
+        assert_eq!((r.region_id, r.bbox.width, r.text_content.as_str()), (0, 5, "ab cd"));
+        assert!((r.confidence - 0.6).abs() < 1e-6);
+        assert!(merge_regions(&mut matrix, &[0]).is_err());
+
+        let second = split_region(&mut matrix, 0, RegionSplit::Column(3)).unwrap();
+        assert_eq!(second, 1);
+        assert_eq!(matrix.text_regions[0].text_content, "ab");
+        assert_eq!(matrix.text_regions[1].text_content, "cd");
+        assert_eq!(matrix.text_regions[1].region_id, 3);
+        assert_eq!(matrix.text_regions[1].bbox.x, 3);
+        assert!(split_region(&mut matrix, 0, RegionSplit::Row(1)).is_err());
+    }
+
+    #[test]
+    fn test_page_fraction_rect_flips_y() {
+        let r = page_fraction_rect(61.2, 692.0, 122.4, 712.0, 612.0, 792.0);
+        let expected = [0.1, 80.0 / 792.0, 0.2, 100.0 / 792.0];
+        for (a, b) in r.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_shortcuts_follow_focused_pane() {
+        let matrix: Vec<&str> = shortcuts_for(FocusedPane::MatrixView).iter().map(|s| s.action).collect();
+        assert!(matrix.contains(&"Open PDF"));
+        assert!(matrix.contains(&"Copy / cut / paste block"));
+        assert!(!matrix.contains(&"Toggle region overlays"));
+
+        let pdf: Vec<&str> = shortcuts_for(FocusedPane::PdfView).iter().map(|s| s.action).collect();
+        assert!(pdf.contains(&"Toggle region overlays"));
+        assert!(!pdf.contains(&"Find in matrix"));
+    }
+
+    #[test]
+    fn test_project_bookmarks_round_trip() {
+        let old: ProjectFile = serde_json::from_str(r#"{"pdf_path": "a.pdf"}"#).unwrap();
+        assert!(old.bookmarks.is_empty());
+
+        let dir = std::env::temp_dir().join(format!("chonker5_bookmarks_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let project_path = dir.join("doc.chonker.json");
+        let bookmark = Bookmark { name: "totals".to_string(), page: 2, row: 40, col: 12 };
+        ProjectFile {
+            portable: false,
+            pdf_path: dir.join("doc.pdf"),
+            current_page: 2,
+            zoom_level: 1.0,
+            page_range: String::new(),
+            bookmarks: vec![bookmark.clone()],
+        }
+        .save(&project_path)
+        .unwrap();
+        assert_eq!(ProjectFile::load(&project_path).unwrap().bookmarks, vec![bookmark]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_target_names() {
+        assert!(is_pdf_url("https://example.com/report.pdf"));
+        assert!(!is_pdf_url("reports/https.pdf"));
+
+        let name = |url: &str| download_target(url).file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(name("https://example.com/files/Q3%20report.PDF?token=abc#page=2"), "Q3_20report.PDF");
+        assert_eq!(name("https://example.com/download/1234/"), "1234.pdf");
+        assert_eq!(name("https://example.com"), "example.com.pdf");
+        assert!(download_target("http://x/a.pdf").starts_with(std::env::temp_dir()));
+    }
+
+    #[test]
+    fn test_job_queue_tracks_page_status() {
+        let mut jobs = JobQueue::new();
+        jobs.status.insert(0, JobStatus::Queued);
+        jobs.status.insert(1, JobStatus::Queued);
+        assert_eq!(jobs.progress(), (0, 2));
+
+        jobs.sender.send(JobEvent::Progress(ProgressEvent::Started { page: 0 })).unwrap();
+        jobs.sender.send(JobEvent::Progress(ProgressEvent::Started { page: 1 })).unwrap();
+        jobs.sender.send(JobEvent::Finished(1, Box::new(Err(ChonkerError::Other("boom".to_string()))))).unwrap();
+        let finished = jobs.poll();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0, 1);
+        assert_eq!(jobs.status(0), Some(JobStatus::Running));
+        assert_eq!(jobs.status(1), Some(JobStatus::Failed));
+        assert!(jobs.is_pending(0));
+        assert!(!jobs.is_pending(1));
+        assert_eq!(jobs.progress(), (1, 2));
+        assert_eq!(jobs.percent(), 50.0);
+
+        jobs.sender
+            .send(JobEvent::Finished(0, Box::new(Ok(CharacterMatrix::new(1, 1)))))
+            .unwrap();
+        assert_eq!(jobs.poll().len(), 1);
+        assert_eq!(jobs.status(0), Some(JobStatus::Done));
+        assert_eq!(jobs.pending(), 0);
     }
 
     #[test]
-    fn test_character_matrix_engine_new() {
-        let engine = CharacterMatrixEngine::new();
-        assert_eq!(engine.char_width, 6.0);
-        assert_eq!(engine.char_height, 12.0);
+    fn test_stale_pages_follow_affecting_settings() {
+        let model = MonospaceModel::default();
+        let dims = CharDimensions { char_width: 5.0, char_height: 10.0 };
+        let mut cache = PageCache::new();
+        for page in 0..3 {
+            let mut matrix = CharacterMatrix::new(1, 1);
+            matrix.settings_fingerprint = extraction_fingerprint(None, None, Segmentation::Backend, &model);
+            cache.insert(page, matrix);
+        }
+        // Ferrules pages carry no fingerprint
+        cache.insert(3, CharacterMatrix::new(1, 1));
+        assert!(stale_pages(&cache, |_| extraction_fingerprint(None, None, Segmentation::Backend, &model)).is_empty());
+
+        // A retrained model, another backend or segmentation affects every page
+        let retrained = MonospaceModel { bias: 0.5, ..model.clone() };
+        let everything = vec![0, 1, 2];
+        assert_eq!(stale_pages(&cache, |_| extraction_fingerprint(None, None, Segmentation::Backend, &retrained)), everything);
+        let stext = Some(ExtractionBackend::MutoolStext);
+        assert_eq!(stale_pages(&cache, |_| extraction_fingerprint(None, stext, Segmentation::Backend, &model)), everything);
+        assert_eq!(stale_pages(&cache, |_| extraction_fingerprint(None, None, Segmentation::XyCut, &model)), everything);
+
+        // A char size override only affects its own page
+        let overrides: HashMap<usize, CharDimensions> = [(2, dims)].into_iter().collect();
+        assert_eq!(
+            stale_pages(&cache, |page| extraction_fingerprint(overrides.get(&page).copied(), None, Segmentation::Backend, &model)),
+            vec![2]
+        );
+
+        // A page segmented after extraction matches the segmented settings
+        let page = cache.get_mut(&1).unwrap();
+        apply_segmentation(page, Segmentation::XyCut);
+        assert_eq!(page.settings_fingerprint, extraction_fingerprint(None, None, Segmentation::XyCut, &model));
     }
 
     #[test]
-    fn test_character_matrix_creation() {
-        let matrix = CharacterMatrix {
-            width: 80,
-            height: 25,
-            matrix: vec![vec![' '; 80]; 25],
-            text_regions: vec![],
-            original_text: vec!["Test text".to_string()],
-            char_width: 6.0,
-            char_height: 12.0,
-        };
-
-        assert_eq!(matrix.width, 80);
-        assert_eq!(matrix.height, 25);
-        assert_eq!(matrix.matrix.len(), 25);
-        assert_eq!(matrix.matrix[0].len(), 80);
-        assert_eq!(matrix.original_text.len(), 1);
+    fn test_log_buffer_collapses_repeats() {
+        let mut log = LogBuffer::new();
+        log.push("🔄 Processing");
+        log.push("🔄 Processing");
+        assert_eq!(log.entries, ["🔄 Processing", "   ↳ repeated 1 more times"]);
+        // Repeats show up as they arrive, not only once another message does
+        log.push("🔄 Processing");
+        assert_eq!(log.entries, ["🔄 Processing", "   ↳ repeated 2 more times"]);
+
+        log.push("✅ Done");
+        log.push("🔄 Processing");
+        assert_eq!(log.entries.len(), 4);
+        assert_eq!(log.filtered("done").count(), 1);
+        assert_eq!(log.filtered("repeated").count(), 1);
     }
 }
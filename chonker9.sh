@@ -8,4 +8,4 @@ SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
 export DYLD_LIBRARY_PATH="$SCRIPT_DIR/lib:$DYLD_LIBRARY_PATH"
 
 # Run chonker9 with Lance enabled by default
-exec "$SCRIPT_DIR/target/release/chonker9" --lance "$@"
\ No newline at end of file
+exec "$SCRIPT_DIR/chonker-viewer/target/release/chonker9" --lance "$@"
\ No newline at end of file